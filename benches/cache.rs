@@ -0,0 +1,144 @@
+// Image cache under concurrent poll workers
+//
+// Compares `ShardedCache` with the single `RwLock<HashMap>` the poller used
+// before. Each worker task looks an image up and writes it back, the way
+// `RegistryPoller` records the digest of every image it checks, and the report
+// shows the throughput of all workers together.
+//
+// Usage:
+//   cargo bench --bench cache
+//   cargo bench --bench cache -- sharded        # only matching cases
+//   cargo bench --bench cache -- --ops 50000    # operations per worker
+
+use headwind::polling::ShardedCache;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// Lookups and writes per worker unless `--ops` is given
+const DEFAULT_OPS: usize = 20_000;
+/// Distinct images the workers share
+const IMAGES: usize = 500;
+
+#[derive(Clone, Copy)]
+enum Design {
+    SingleLock,
+    Sharded(usize),
+}
+
+impl Design {
+    fn name(&self) -> String {
+        match self {
+            Design::SingleLock => "single-lock".to_string(),
+            Design::Sharded(shards) => format!("sharded-{}", shards),
+        }
+    }
+}
+
+/// Image keys, built up front so the timings only cover the cache
+fn images() -> Arc<Vec<String>> {
+    Arc::new(
+        (0..IMAGES)
+            .map(|i| format!("registry.example.com/team/app-{}:1.{}.0", i, i % 7))
+            .collect(),
+    )
+}
+
+fn digest(i: usize) -> String {
+    format!("sha256:{:064}", i)
+}
+
+async fn single_lock(workers: usize, ops: usize) -> Duration {
+    let cache: Arc<RwLock<HashMap<String, String>>> = Arc::new(RwLock::new(HashMap::new()));
+    let images = images();
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..workers)
+        .map(|worker| {
+            let (cache, images) = (cache.clone(), images.clone());
+            tokio::spawn(async move {
+                for i in 0..ops {
+                    let key = &images[(worker * ops + i) % IMAGES];
+                    let cached = cache.read().await.get(key).cloned();
+                    let digest = cached.unwrap_or_else(|| digest(i));
+                    cache.write().await.insert(key.clone(), digest);
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.expect("worker panicked");
+    }
+    start.elapsed()
+}
+
+async fn sharded(shards: usize, workers: usize, ops: usize) -> Duration {
+    let cache: Arc<ShardedCache<String>> = Arc::new(ShardedCache::new(shards));
+    let images = images();
+    let start = Instant::now();
+    let tasks: Vec<_> = (0..workers)
+        .map(|worker| {
+            let (cache, images) = (cache.clone(), images.clone());
+            tokio::spawn(async move {
+                for i in 0..ops {
+                    let key = &images[(worker * ops + i) % IMAGES];
+                    let digest = cache.get(key).unwrap_or_else(|| digest(i));
+                    cache.insert(key, digest);
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.expect("worker panicked");
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let mut filter = None;
+    let mut ops = DEFAULT_OPS;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Passed by `cargo bench`
+            "--bench" => {},
+            "--ops" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(n) => ops = n,
+                None => {
+                    eprintln!("--ops requires a number of operations");
+                    std::process::exit(2);
+                },
+            },
+            _ if arg.starts_with("--") => {},
+            _ => filter = Some(arg),
+        }
+    }
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build tokio runtime");
+
+    println!("{:<28} {:>12} {:>16}", "benchmark", "time", "ops/sec");
+    for design in [Design::SingleLock, Design::Sharded(16), Design::Sharded(64)] {
+        for workers in [1, 8, 50] {
+            let name = format!("{}/workers-{}", design.name(), workers);
+            if filter.as_ref().is_some_and(|f| !name.contains(f.as_str())) {
+                continue;
+            }
+            let elapsed = runtime.block_on(async {
+                match design {
+                    Design::SingleLock => single_lock(workers, ops).await,
+                    Design::Sharded(shards) => sharded(shards, workers, ops).await,
+                }
+            });
+            let total = (workers * ops * 2) as f64;
+            println!(
+                "{:<28} {:>9.1} ms {:>16.0}",
+                name,
+                elapsed.as_secs_f64() * 1000.0,
+                total / elapsed.as_secs_f64()
+            );
+        }
+    }
+}
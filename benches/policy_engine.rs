@@ -0,0 +1,219 @@
+// Benchmarks for `PolicyEngine::should_update`
+//
+// The policy engine runs once per container image per reconcile event, so it
+// sits on the hot path for large clusters. Each case reports the mean time
+// per call and the number of heap allocations per call.
+//
+// Usage:
+//   cargo bench --bench policy_engine
+//   cargo bench --bench policy_engine -- patch          # only matching cases
+//   cargo bench --bench policy_engine -- --max-patch-ns 200
+
+use headwind::policy::PolicyEngine;
+use headwind::{ResourcePolicy, UpdatePolicy};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Calls per measured sample
+const ITERATIONS: u32 = 10_000;
+/// Samples per case; the median is reported
+const SAMPLES: usize = 15;
+
+/// Global allocator that counts allocations so each case can report them
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// A benchmark case: policy plus a (current, new) version pair
+struct Case {
+    group: &'static str,
+    id: &'static str,
+    policy: ResourcePolicy,
+    current: &'static str,
+    new: &'static str,
+}
+
+struct Measurement {
+    ns_per_call: f64,
+    allocs_per_call: f64,
+}
+
+fn policy(policy: UpdatePolicy, pattern: Option<&str>) -> ResourcePolicy {
+    ResourcePolicy {
+        policy,
+        pattern: pattern.map(String::from),
+        ..Default::default()
+    }
+}
+
+fn cases() -> Vec<Case> {
+    let mut cases = Vec::new();
+
+    let semver_pairs = [
+        ("patch-bump", "1.2.3", "1.2.4"),
+        ("minor-bump", "1.2.3", "1.3.0"),
+        ("major-bump", "1.2.3", "2.0.0"),
+        ("downgrade", "1.2.4", "1.2.3"),
+        ("v-prefix", "v1.25.0", "v1.25.1"),
+        ("pre-release", "1.2.3-alpha.1", "1.2.3-beta.2"),
+        ("pre-release-to-release", "1.2.3-rc.1", "1.2.3"),
+        ("build-metadata", "1.2.3+build.1", "1.2.4+build.2"),
+    ];
+    for (group, update_policy) in [
+        ("patch", UpdatePolicy::Patch),
+        ("minor", UpdatePolicy::Minor),
+        ("major", UpdatePolicy::Major),
+    ] {
+        for (id, current, new) in semver_pairs {
+            cases.push(Case {
+                group,
+                id,
+                policy: policy(update_policy, None),
+                current,
+                new,
+            });
+        }
+    }
+
+    let glob_cases = [
+        ("wildcard", "*", "1.2.4"),
+        ("exact", "1.2.4-alpine", "1.2.4-alpine"),
+        ("prefix", "v1.*", "v1.26.3"),
+        ("suffix", "*-alpine", "1.26.3-alpine"),
+        ("prefix-suffix", "v1.*-alpine3.19", "v1.26.3-alpine3.19"),
+        ("no-match", "v2.*-alpine", "v1.26.3-bookworm"),
+    ];
+    for (id, pattern, new) in glob_cases {
+        cases.push(Case {
+            group: "glob",
+            id,
+            policy: policy(UpdatePolicy::Glob, Some(pattern)),
+            current: "1.2.3",
+            new,
+        });
+    }
+
+    cases.push(Case {
+        group: "all",
+        id: "changed",
+        policy: policy(UpdatePolicy::All, None),
+        current: "1.2.3",
+        new: "1.2.4",
+    });
+
+    cases
+}
+
+fn measure(engine: &PolicyEngine, case: &Case) -> Measurement {
+    let run = || {
+        for _ in 0..ITERATIONS {
+            let _ = black_box(engine.should_update(
+                black_box(&case.policy),
+                black_box(case.current),
+                black_box(case.new),
+            ));
+        }
+    };
+
+    // Warm up caches and the branch predictor
+    run();
+
+    let mut samples: Vec<Duration> = Vec::with_capacity(SAMPLES);
+    let mut allocations = 0;
+    for _ in 0..SAMPLES {
+        let allocs_before = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+        run();
+        samples.push(start.elapsed());
+        allocations += ALLOCATIONS.load(Ordering::Relaxed) - allocs_before;
+    }
+    samples.sort();
+
+    let calls = f64::from(ITERATIONS);
+    Measurement {
+        ns_per_call: samples[SAMPLES / 2].as_nanos() as f64 / calls,
+        allocs_per_call: allocations as f64 / (calls * SAMPLES as f64),
+    }
+}
+
+fn main() {
+    let mut filter = None;
+    let mut max_patch_ns: Option<f64> = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Passed by `cargo bench`
+            "--bench" => {},
+            "--max-patch-ns" => {
+                max_patch_ns = args.next().and_then(|v| v.parse().ok());
+                if max_patch_ns.is_none() {
+                    eprintln!("--max-patch-ns requires a number of nanoseconds");
+                    std::process::exit(2);
+                }
+            },
+            _ if arg.starts_with("--") => {},
+            _ => filter = Some(arg),
+        }
+    }
+
+    let engine = PolicyEngine;
+    let mut regressions = Vec::new();
+
+    println!(
+        "{:<40} {:>12} {:>14}",
+        "benchmark", "time/call", "allocs/call"
+    );
+    for case in cases() {
+        let name = format!("{}/{}", case.group, case.id);
+        if filter.as_ref().is_some_and(|f| !name.contains(f.as_str())) {
+            continue;
+        }
+
+        let m = measure(&engine, &case);
+        println!(
+            "{:<40} {:>9.1} ns {:>14.1}",
+            name, m.ns_per_call, m.allocs_per_call
+        );
+
+        if let Some(max) = max_patch_ns
+            && case.group == "patch"
+            && m.ns_per_call > max
+        {
+            regressions.push(format!("{} took {:.1} ns/call", name, m.ns_per_call));
+        }
+    }
+
+    if !regressions.is_empty() {
+        eprintln!();
+        eprintln!(
+            "Patch policy regressed beyond {} ns/call:",
+            max_patch_ns.unwrap_or_default()
+        );
+        for regression in regressions {
+            eprintln!("  {}", regression);
+        }
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,148 @@
+// Load test for `WebhookEventProcessor`
+//
+// Sends a burst of image push events from concurrent tasks, the way a webhook
+// storm reaches the axum handlers, and reports how long queueing took, how
+// many events were rejected with 429 and how long the workers needed to
+// drain the queue. Update detection is simulated with a fixed delay.
+//
+// Usage:
+//   cargo bench --bench webhook_processor
+//   cargo bench --bench webhook_processor -- workers-4      # only matching cases
+//   cargo bench --bench webhook_processor -- --events 5000
+
+use headwind::models::webhook::ImagePushEvent;
+use headwind::webhook::processor::{EnqueueError, QUEUE_CAPACITY, WebhookEventProcessor};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Events per burst unless `--events` is given
+const DEFAULT_EVENTS: usize = 1000;
+/// Simulated time to list workloads and compare versions for one event
+const DETECTION_TIME: Duration = Duration::from_millis(2);
+
+/// A benchmark case: worker count and queue capacity
+struct Case {
+    workers: usize,
+    capacity: usize,
+}
+
+struct Measurement {
+    enqueue: Duration,
+    drain: Duration,
+    accepted: usize,
+    rejected: usize,
+}
+
+fn cases() -> Vec<Case> {
+    let mut cases: Vec<Case> = [1, 4, 8, 16]
+        .into_iter()
+        .map(|workers| Case {
+            workers,
+            capacity: QUEUE_CAPACITY,
+        })
+        .collect();
+    // A queue smaller than the burst, so some senders are asked to retry
+    cases.push(Case {
+        workers: 4,
+        capacity: 100,
+    });
+    cases
+}
+
+fn event(i: usize) -> ImagePushEvent {
+    ImagePushEvent {
+        registry: "registry.example.com".to_string(),
+        repository: format!("team/app-{}", i % 50),
+        tag: format!("1.{}.0", i),
+        digest: None,
+    }
+}
+
+async fn measure(case: &Case, events: usize) -> Measurement {
+    let processed = Arc::new(AtomicUsize::new(0));
+    let counter = processed.clone();
+    let (processor, _) = WebhookEventProcessor::start(case.workers, case.capacity, move |_| {
+        let counter = counter.clone();
+        async move {
+            tokio::time::sleep(DETECTION_TIME).await;
+            counter.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+    });
+
+    let start = Instant::now();
+    let senders: Vec<_> = (0..events)
+        .map(|i| {
+            let processor = processor.clone();
+            tokio::spawn(async move { processor.try_enqueue(vec![event(i)]) })
+        })
+        .collect();
+    let mut accepted = 0;
+    let mut rejected = 0;
+    for sender in senders {
+        match sender.await.expect("sender task panicked") {
+            Ok(()) => accepted += 1,
+            Err(EnqueueError::Full) => rejected += 1,
+            Err(e) => panic!("unexpected enqueue error: {}", e),
+        }
+    }
+    let enqueue = start.elapsed();
+
+    while processed.load(Ordering::Relaxed) < accepted {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    Measurement {
+        enqueue,
+        drain: start.elapsed(),
+        accepted,
+        rejected,
+    }
+}
+
+fn main() {
+    let mut filter = None;
+    let mut events = DEFAULT_EVENTS;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Passed by `cargo bench`
+            "--bench" => {},
+            "--events" => match args.next().and_then(|v| v.parse().ok()) {
+                Some(n) => events = n,
+                None => {
+                    eprintln!("--events requires a number of events");
+                    std::process::exit(2);
+                },
+            },
+            _ if arg.starts_with("--") => {},
+            _ => filter = Some(arg),
+        }
+    }
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to build tokio runtime");
+
+    println!(
+        "{:<28} {:>12} {:>12} {:>10} {:>10}",
+        "benchmark", "enqueue", "drain", "accepted", "rejected"
+    );
+    for case in cases() {
+        let name = format!("workers-{}/queue-{}", case.workers, case.capacity);
+        if filter.as_ref().is_some_and(|f| !name.contains(f.as_str())) {
+            continue;
+        }
+        let m = runtime.block_on(measure(&case, events));
+        println!(
+            "{:<28} {:>9.2} ms {:>9.1} ms {:>10} {:>10}",
+            name,
+            m.enqueue.as_secs_f64() * 1000.0,
+            m.drain.as_secs_f64() * 1000.0,
+            m.accepted,
+            m.rejected
+        );
+    }
+}
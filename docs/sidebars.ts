@@ -28,6 +28,7 @@ const sidebars: SidebarsConfig = {
         'configuration/deployments',
         'configuration/statefulsets',
         'configuration/daemonsets',
+        'configuration/replicasets',
         'configuration/helmreleases',
         'configuration/event-sources',
         'configuration/approval-workflow',
@@ -46,6 +47,7 @@ const sidebars: SidebarsConfig = {
         'guides/helm-installation',
         'guides/update-requests',
         'guides/kubectl-plugin',
+        'guides/headwindctl',
         'guides/web-ui',
         'guides/web-ui-authentication',
         'guides/observability-dashboard',
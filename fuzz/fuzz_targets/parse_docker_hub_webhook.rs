@@ -0,0 +1,20 @@
+#![no_main]
+
+// Docker Hub webhook payloads come from the internet; parsing them must
+// either succeed or return an error, never panic.
+
+use headwind::models::webhook::{DockerHubWebhook, ImagePushEvent};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(payload) = serde_json::from_slice::<DockerHubWebhook>(data) {
+        // Mirror the conversion done by the /webhook/dockerhub handler
+        let event = ImagePushEvent {
+            registry: "docker.io".to_string(),
+            repository: payload.repository.repo_name,
+            tag: payload.push_data.tag,
+            digest: None,
+        };
+        let _ = event.full_image();
+    }
+});
@@ -0,0 +1,35 @@
+#![no_main]
+
+// Distribution-style registry notifications (Harbor, GitLab, self-hosted
+// registries) are parsed as `RegistryWebhook`; parsing must either succeed
+// or return an error, never panic.
+
+use headwind::models::webhook::{ChartPushEvent, ImagePushEvent, RegistryWebhook};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(payload) = serde_json::from_slice::<RegistryWebhook>(data) {
+        for event in payload.events {
+            let Some(tag) = event.target.tag else {
+                continue;
+            };
+
+            let image = ImagePushEvent {
+                registry: String::new(),
+                repository: event.target.repository.clone(),
+                tag: tag.clone(),
+                digest: Some(event.target.digest.clone()),
+            };
+            let _ = image.full_image();
+
+            let chart = ChartPushEvent {
+                registry: String::new(),
+                repository: event.target.repository,
+                version: tag,
+                digest: Some(event.target.digest),
+            };
+            let _ = chart.full_oci_url();
+            let _ = chart.base_oci_url();
+        }
+    }
+});
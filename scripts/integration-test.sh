@@ -0,0 +1,61 @@
+#!/bin/bash
+# Run the end-to-end tests in tests/integration against a throwaway kind cluster.
+#
+# Set KEEP_CLUSTER=1 to leave the cluster running afterwards for debugging.
+set -euo pipefail
+
+CLUSTER_NAME="${CLUSTER_NAME:-headwind-e2e}"
+IMAGE="headwind:e2e"
+NAMESPACE="headwind-system"
+WEBHOOK_PORT=18080
+APPROVAL_PORT=18081
+
+PORT_FORWARD_PIDS=()
+
+cleanup() {
+    for pid in "${PORT_FORWARD_PIDS[@]}"; do
+        kill "$pid" 2>/dev/null || true
+    done
+    if [ "${KEEP_CLUSTER:-0}" != "1" ]; then
+        echo "Deleting kind cluster ${CLUSTER_NAME}..."
+        kind delete cluster --name "${CLUSTER_NAME}"
+    fi
+}
+trap cleanup EXIT
+
+for tool in kind kubectl docker; do
+    if ! command -v "$tool" &> /dev/null; then
+        echo "$tool is required to run integration tests"
+        exit 1
+    fi
+done
+
+echo "Creating kind cluster ${CLUSTER_NAME}..."
+kind create cluster --name "${CLUSTER_NAME}" --wait 120s
+kubectl config use-context "kind-${CLUSTER_NAME}"
+
+echo "Building and loading ${IMAGE}..."
+docker build -t "${IMAGE}" .
+kind load docker-image "${IMAGE}" --name "${CLUSTER_NAME}"
+
+echo "Deploying headwind..."
+kubectl apply -f deploy/k8s/namespace.yaml
+kubectl apply -f deploy/k8s/crds/
+kubectl apply -f deploy/k8s/rbac.yaml
+kubectl apply -f deploy/k8s/configmap.yaml
+kubectl apply -f deploy/k8s/deployment.yaml
+kubectl apply -f deploy/k8s/service.yaml
+kubectl set image -n "${NAMESPACE}" deployment/headwind headwind="${IMAGE}"
+kubectl rollout status -n "${NAMESPACE}" deployment/headwind --timeout=180s
+
+echo "Port-forwarding webhook and approval API..."
+kubectl port-forward -n "${NAMESPACE}" svc/headwind-webhook "${WEBHOOK_PORT}:80" > /dev/null &
+PORT_FORWARD_PIDS+=($!)
+kubectl port-forward -n "${NAMESPACE}" svc/headwind-api "${APPROVAL_PORT}:80" > /dev/null &
+PORT_FORWARD_PIDS+=($!)
+sleep 3
+
+echo "Running integration tests..."
+HEADWIND_E2E_WEBHOOK_URL="http://localhost:${WEBHOOK_PORT}" \
+HEADWIND_E2E_APPROVAL_URL="http://localhost:${APPROVAL_PORT}" \
+    cargo test --test integration -- --ignored --test-threads=4
@@ -1,30 +1,115 @@
+pub mod rbac;
+
+use crate::cache::list_all;
+use crate::controller::expiry;
+use crate::controller::lock::UpdateRequestLock;
+use crate::controller::progress;
+use crate::controller::self_update::{self, self_update_for};
 use crate::controller::{
-    update_daemonset_image_with_tracking, update_deployment_image_with_tracking,
-    update_statefulset_image_with_tracking,
+    apply_batch, apply_container_updates, approved_status, deliver_update_request, rejected_status,
+    update_composition_image_with_tracking, update_daemonset_image_with_tracking,
+    update_deployment_image_with_tracking, update_knative_service_image_with_tracking,
+    update_replicaset_image_with_tracking, update_statefulset_image_with_tracking,
+};
+use crate::models::crd::{
+    BatchUpdateRequest, ProgressStep, RollbackRecord, UpdatePhase, UpdateRequest,
+    UpdateRequestStatus,
 };
-use crate::models::crd::{UpdatePhase, UpdateRequest, UpdateRequestStatus};
-use crate::notifications::{self, DeploymentInfo};
+use crate::notifications::{self, DeploymentInfo, NotificationEvent, NotificationPayload};
+use crate::policy::ignore::{IgnoredImages, ignore_patterns, ignored_images};
+use crate::polling::digest::split_tag;
 use crate::rollback::{
-    AutoRollbackConfig, HealthChecker, HealthStatus, RollbackManager, UpdateHistory,
+    AutoRollbackConfig, HealthChecker, HealthStatus, RollbackManager, RollbackReason, UpdateHistory,
 };
+use crate::schedule::PendingUpdate;
 use anyhow::Result;
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
-    routing::{get, post},
+    routing::{get, post, put},
 };
-use chrono::Utc;
-use k8s_openapi::api::apps::v1::Deployment;
-use kube::api::{Patch, PatchParams};
-use kube::{Api, Client};
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use kube::api::{ListParams, Patch, PatchParams};
+use kube::{Api, Client, ResourceExt};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::BTreeMap;
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tokio::task::JoinHandle;
 use tower_http::trace::TraceLayer;
 use tracing::{debug, error, info, warn};
 
+/// Recorded as the approver of updates made by automatic rollback
+const AUTO_ROLLBACK_USER: &str = "headwind-auto-rollback";
+
+/// Limits how many approved updates are applied at the same time
+static UPDATE_PERMITS: once_cell::sync::Lazy<Semaphore> =
+    once_cell::sync::Lazy::new(|| Semaphore::new(max_concurrent_approvals()));
+
+/// Maximum number of updates applied concurrently (`HEADWIND_MAX_CONCURRENT_APPROVALS`, default 5)
+fn max_concurrent_approvals() -> usize {
+    std::env::var("HEADWIND_MAX_CONCURRENT_APPROVALS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5usize)
+        .max(1)
+}
+
+/// Maximum length of a rejection reason in characters
+/// (`HEADWIND_MAX_REJECTION_REASON_LENGTH`, default 2048)
+pub fn max_rejection_reason_length() -> usize {
+    std::env::var("HEADWIND_MAX_REJECTION_REASON_LENGTH")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2048usize)
+}
+
+/// The trimmed rejection reason, or why it can't be used: rejections need a
+/// non-empty reason of at most `max_length` characters for the audit trail
+pub fn validate_rejection_reason(reason: Option<&str>, max_length: usize) -> Result<&str, String> {
+    let reason = reason.map(str::trim).unwrap_or_default();
+    if reason.is_empty() {
+        return Err("A rejection reason is required".to_string());
+    }
+
+    let length = reason.chars().count();
+    if length > max_length {
+        return Err(format!(
+            "Rejection reason is {} characters long, the maximum is {}",
+            length, max_length
+        ));
+    }
+    Ok(reason)
+}
+
+/// Decrements the approval queue depth when a queued update leaves the queue,
+/// including when the request is cancelled while waiting
+struct QueuedApproval;
+
+impl Drop for QueuedApproval {
+    fn drop(&mut self) {
+        crate::metrics::APPROVAL_QUEUE_DEPTH.dec();
+    }
+}
+
+/// Wait for a free update slot. Tokio semaphores are fair, so queued updates
+/// are applied in the order they were approved.
+async fn acquire_update_permit(permits: &Semaphore) -> Result<SemaphorePermit<'_>> {
+    if let Ok(permit) = permits.try_acquire() {
+        return Ok(permit);
+    }
+
+    crate::metrics::APPROVALS_THROTTLED_TOTAL.inc();
+    crate::metrics::APPROVAL_QUEUE_DEPTH.inc();
+    let _queued = QueuedApproval;
+
+    debug!("Update throttled, waiting for a free slot");
+    Ok(permits.acquire().await?)
+}
+
 #[derive(Clone)]
 pub struct ApprovalState {
     pub client: Client,
@@ -32,9 +117,27 @@ pub struct ApprovalState {
 
 pub async fn start_approval_server() -> Result<JoinHandle<()>> {
     let client = Client::try_default().await?;
-    let state = ApprovalState { client };
+    let app = router(ApprovalState { client });
+
+    let addr = "0.0.0.0:8081";
+    info!("Starting approval API server on {}", addr);
+
+    let handle = tokio::spawn(async move {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .expect("Failed to bind approval server");
+
+        axum::serve(listener, app)
+            .await
+            .expect("Approval server failed");
+    });
 
-    let app = Router::new()
+    Ok(handle)
+}
+
+/// Build the approval API router
+pub fn router(state: ApprovalState) -> Router {
+    Router::new()
         .route("/api/v1/updates", get(list_updates))
         .route("/api/v1/updates/{namespace}/{name}", get(get_update))
         .route(
@@ -53,24 +156,93 @@ pub async fn start_approval_server() -> Result<JoinHandle<()>> {
             "/api/v1/rollback/{namespace}/{deployment}",
             post(rollback_deployment),
         )
+        .route(
+            "/api/v1/rollback/{namespace}/{resource_kind}/{name}",
+            post(crate::ui::routes::trigger_rollback),
+        )
+        .route("/api/v1/batch-updates", get(list_batch_updates))
+        .route(
+            "/api/v1/batch-updates/{namespace}/{name}/approve",
+            post(approve_batch_update),
+        )
+        .route(
+            "/api/v1/batch-updates/{namespace}/{name}/reject",
+            post(reject_batch_update),
+        )
+        .route("/api/v1/scheduled-updates", get(list_scheduled_updates))
+        .route("/api/v1/update-requests", get(list_update_requests))
+        .route("/api/v1/update-requests/label", post(label_update_request))
+        .route(
+            "/api/v1/update-requests/{namespace}/{name}/schedule",
+            put(schedule_update_request),
+        )
+        .route(
+            "/api/v1/update-requests/{namespace}/{name}/extend-ttl",
+            post(extend_update_request_ttl),
+        )
+        .route(
+            "/api/v1/update-requests/{namespace}/{name}/rollbacks",
+            get(get_update_request_rollbacks),
+        )
+        .route(
+            "/api/v1/settings",
+            get(crate::ui::routes::get_settings).put(crate::ui::routes::update_settings),
+        )
+        .route("/api/v1/simulate", get(crate::ui::routes::simulate))
+        .route("/api/v1/explain", get(crate::ui::routes::explain))
+        .route("/api/v1/features", get(crate::ui::routes::get_features))
+        .route("/api/v1/ignored-images", get(list_ignored_images))
         .route("/health", get(health_check))
         .layer(TraceLayer::new_for_http())
-        .with_state(state);
-
-    let addr = "0.0.0.0:8081";
-    info!("Starting approval API server on {}", addr);
+        .with_state(state)
+}
 
-    let handle = tokio::spawn(async move {
-        let listener = tokio::net::TcpListener::bind(addr)
-            .await
-            .expect("Failed to bind approval server");
+/// `HEADWIND_IGNORE_IMAGES` patterns and the workload images each one matches
+async fn list_ignored_images(
+    State(state): State<ApprovalState>,
+) -> Result<Json<Vec<IgnoredImages>>, StatusCode> {
+    let images = workload_images(&state.client).await.map_err(|e| {
+        error!("Failed to list workload images: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(ignored_images(
+        ignore_patterns(),
+        images.iter().map(String::as_str),
+    )))
+}
 
-        axum::serve(listener, app)
-            .await
-            .expect("Approval server failed");
-    });
+/// Images of all containers and init containers of Deployments,
+/// StatefulSets, DaemonSets and ReplicaSets
+async fn workload_images(client: &Client) -> Result<Vec<String>> {
+    let mut pod_specs = Vec::new();
+    for deployment in list_all::<Deployment>(client).await? {
+        pod_specs.extend(deployment.spec.clone().and_then(|s| s.template.spec));
+    }
+    for statefulset in list_all::<StatefulSet>(client).await? {
+        pod_specs.extend(statefulset.spec.clone().and_then(|s| s.template.spec));
+    }
+    for daemonset in list_all::<DaemonSet>(client).await? {
+        pod_specs.extend(daemonset.spec.clone().and_then(|s| s.template.spec));
+    }
+    for replicaset in list_all::<ReplicaSet>(client).await? {
+        pod_specs.extend(
+            replicaset
+                .spec
+                .clone()
+                .and_then(|s| s.template)
+                .and_then(|t| t.spec),
+        );
+    }
 
-    Ok(handle)
+    Ok(pod_specs
+        .into_iter()
+        .flat_map(|spec| {
+            spec.containers
+                .into_iter()
+                .chain(spec.init_containers.unwrap_or_default())
+        })
+        .filter_map(|container| container.image)
+        .collect())
 }
 
 async fn list_updates(
@@ -88,6 +260,346 @@ async fn list_updates(
     }
 }
 
+/// Query parameters for `GET /api/v1/update-requests`
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRequestListQuery {
+    /// Kubernetes label selector, e.g. `app=nginx,env=prod`
+    pub label_selector: Option<String>,
+    /// Maximum number of items to return
+    pub limit: Option<u32>,
+    /// Continue token from a previous page
+    #[serde(rename = "continue")]
+    pub continue_token: Option<String>,
+}
+
+/// A page of UpdateRequests
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRequestPage {
+    pub items: Vec<UpdateRequest>,
+    /// Token for the next page, absent on the last page
+    #[serde(rename = "continue", skip_serializing_if = "Option::is_none")]
+    pub continue_token: Option<String>,
+}
+
+/// List UpdateRequests across all namespaces, filtered by label selector and paginated
+pub async fn list_update_requests(
+    State(state): State<ApprovalState>,
+    Query(query): Query<UpdateRequestListQuery>,
+) -> impl IntoResponse {
+    let update_requests: Api<UpdateRequest> = Api::all(state.client);
+
+    let mut params = ListParams::default();
+    if let Some(selector) = query.label_selector.as_deref().filter(|s| !s.is_empty()) {
+        params = params.labels(selector);
+    }
+    if let Some(limit) = query.limit {
+        params = params.limit(limit);
+    }
+    if let Some(token) = query.continue_token.as_deref().filter(|s| !s.is_empty()) {
+        params = params.continue_token(token);
+    }
+
+    match update_requests.list(&params).await {
+        Ok(list) => {
+            let page = UpdateRequestPage {
+                items: list.items,
+                continue_token: list.metadata.continue_.filter(|t| !t.is_empty()),
+            };
+            (StatusCode::OK, Json(json!(page)))
+        },
+        Err(kube::Error::Api(err)) if err.code == 400 || err.code == 410 => {
+            // Invalid selector or expired continue token
+            warn!("Rejected UpdateRequest list query: {}", err.message);
+            (
+                StatusCode::from_u16(err.code).unwrap_or(StatusCode::BAD_REQUEST),
+                Json(json!({"error": err.message})),
+            )
+        },
+        Err(e) => {
+            error!("Failed to list UpdateRequests: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Failed to list UpdateRequests: {}", e)})),
+            )
+        },
+    }
+}
+
+/// Body for `POST /api/v1/update-requests/label`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelUpdateRequest {
+    pub namespace: String,
+    pub name: String,
+    /// Labels to set; existing labels with other keys are kept
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Set labels on an existing UpdateRequest
+pub async fn label_update_request(
+    State(state): State<ApprovalState>,
+    Json(request): Json<LabelUpdateRequest>,
+) -> impl IntoResponse {
+    for (key, value) in &request.labels {
+        if let Err(e) = validate_label(key, value) {
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": e})));
+        }
+    }
+
+    let update_requests: Api<UpdateRequest> = Api::namespaced(state.client, &request.namespace);
+    let patch = json!({ "metadata": { "labels": request.labels } });
+
+    match update_requests
+        .patch(&request.name, &PatchParams::default(), &Patch::Merge(patch))
+        .await
+    {
+        Ok(updated) => {
+            info!(
+                "Updated labels on UpdateRequest {}/{}",
+                request.namespace, request.name
+            );
+            (StatusCode::OK, Json(json!(updated)))
+        },
+        Err(kube::Error::Api(err)) if err.code == 404 => (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": format!("UpdateRequest not found: {}", err.message)})),
+        ),
+        Err(e) => {
+            error!(
+                "Failed to label UpdateRequest {}/{}: {}",
+                request.namespace, request.name, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Failed to update labels: {}", e)})),
+            )
+        },
+    }
+}
+
+/// Body for `PUT /api/v1/update-requests/{namespace}/{name}/schedule`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleApprovalRequest {
+    /// When the UpdateRequest is approved; `null` cancels the schedule
+    pub scheduled_at: Option<DateTime<Utc>>,
+}
+
+/// Check that a scheduled approval time lies in the future
+pub fn validate_scheduled_at(
+    scheduled_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    if scheduled_at <= now {
+        return Err(format!(
+            "scheduled_at must be in the future, got {}",
+            scheduled_at.to_rfc3339()
+        ));
+    }
+    Ok(())
+}
+
+/// Schedule a pending UpdateRequest to be approved automatically at
+/// `scheduled_at`, or cancel its schedule
+pub async fn schedule_update_request(
+    State(state): State<ApprovalState>,
+    Path((namespace, name)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(request): Json<ScheduleApprovalRequest>,
+) -> impl IntoResponse {
+    if let Some(scheduled_at) = request.scheduled_at
+        && let Err(e) = validate_scheduled_at(scheduled_at, Utc::now())
+    {
+        return (StatusCode::BAD_REQUEST, Json(json!({"error": e})));
+    }
+
+    let update_requests: Api<UpdateRequest> = Api::namespaced(state.client.clone(), &namespace);
+    let update_request = match update_requests.get(&name).await {
+        Ok(ur) => ur,
+        Err(e) => {
+            warn!("UpdateRequest {}/{} not found: {}", namespace, name, e);
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({"error": format!("UpdateRequest not found: {}", e)})),
+            );
+        },
+    };
+
+    if let Some(status) = &update_request.status
+        && status.phase != UpdatePhase::Pending
+    {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": format!("UpdateRequest is in {:?} state, cannot schedule", status.phase),
+                "current_phase": format!("{:?}", status.phase)
+            })),
+        );
+    }
+
+    // A scheduled approval is an approval: the same role is required
+    let identity = rbac::CallerIdentity::from_headers(&headers);
+    if let Err(e) =
+        rbac::check_approval_access(&state.client, identity.as_ref(), &update_request).await
+    {
+        return (e.status_code(), Json(json!({"error": e.to_string()})));
+    }
+
+    let patch = json!({ "spec": { "scheduledAt": request.scheduled_at } });
+    match update_requests
+        .patch(&name, &PatchParams::default(), &Patch::Merge(patch))
+        .await
+    {
+        Ok(updated) => {
+            match request.scheduled_at {
+                Some(at) => info!(
+                    "Scheduled approval of UpdateRequest {}/{} at {}",
+                    namespace,
+                    name,
+                    at.to_rfc3339()
+                ),
+                None => info!(
+                    "Cancelled scheduled approval of UpdateRequest {}/{}",
+                    namespace, name
+                ),
+            }
+            (StatusCode::OK, Json(json!(updated)))
+        },
+        Err(e) => {
+            error!(
+                "Failed to schedule UpdateRequest {}/{}: {}",
+                namespace, name, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Failed to schedule approval: {}", e)})),
+            )
+        },
+    }
+}
+
+/// Body for `POST /api/v1/update-requests/{namespace}/{name}/extend-ttl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtendTtlRequest {
+    /// Hours from now the UpdateRequest expires at
+    pub extend_by_hours: i64,
+}
+
+/// Move the expiry of a Pending UpdateRequest to `extend_by_hours` from now,
+/// within `HEADWIND_MAX_UPDATE_REQUEST_LIFETIME_HOURS` of its creation
+pub async fn extend_update_request_ttl(
+    State(state): State<ApprovalState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(request): Json<ExtendTtlRequest>,
+) -> impl IntoResponse {
+    let update_requests: Api<UpdateRequest> = Api::namespaced(state.client.clone(), &namespace);
+    let update_request = match update_requests.get(&name).await {
+        Ok(ur) => ur,
+        Err(e) => {
+            warn!("UpdateRequest {}/{} not found: {}", namespace, name, e);
+            return (
+                StatusCode::NOT_FOUND,
+                Json(json!({"error": format!("UpdateRequest not found: {}", e)})),
+            );
+        },
+    };
+
+    if let Some(status) = &update_request.status
+        && status.phase != UpdatePhase::Pending
+    {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": format!("UpdateRequest is in {:?} state, cannot extend", status.phase),
+                "current_phase": format!("{:?}", status.phase)
+            })),
+        );
+    }
+
+    let now = Utc::now();
+    let expires_at = match expiry::extended_expiry(
+        &update_request,
+        request.extend_by_hours,
+        now,
+        expiry::max_lifetime(),
+    ) {
+        Ok(expires_at) => expires_at,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(json!({"error": e}))),
+    };
+
+    match expiry::extend(
+        &state.client,
+        &update_request,
+        request.extend_by_hours,
+        expires_at,
+        false,
+        now,
+    )
+    .await
+    {
+        Ok(updated) => (StatusCode::OK, Json(json!(updated))),
+        Err(e) => {
+            error!(
+                "Failed to extend UpdateRequest {}/{}: {}",
+                namespace, name, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Failed to extend expiry: {}", e)})),
+            )
+        },
+    }
+}
+
+/// Check a label against the Kubernetes syntax rules: an optional DNS subdomain
+/// prefix and a name of at most 63 alphanumeric characters, `-`, `_` or `.`
+/// that starts and ends with an alphanumeric character. Values follow the name
+/// rules but may be empty.
+pub fn validate_label(key: &str, value: &str) -> Result<(), String> {
+    fn is_label_name(s: &str) -> bool {
+        s.len() <= 63
+            && s.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && s.ends_with(|c: char| c.is_ascii_alphanumeric())
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    }
+
+    let (prefix, name) = match key.split_once('/') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, key),
+    };
+
+    if let Some(prefix) = prefix {
+        let valid_prefix = prefix.len() <= 253
+            && prefix.split('.').all(|part| {
+                !part.is_empty()
+                    && part.len() <= 63
+                    && part.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                    && part.ends_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            });
+        if !valid_prefix {
+            return Err(format!("Invalid label key prefix: {}", prefix));
+        }
+    }
+
+    if !is_label_name(name) {
+        return Err(format!("Invalid label key: {}", key));
+    }
+
+    if !value.is_empty() && !is_label_name(value) {
+        return Err(format!("Invalid value for label {}: {}", key, value));
+    }
+
+    Ok(())
+}
+
+/// Updates waiting for their `headwind.sh/update-schedule` to fire
+async fn list_scheduled_updates() -> Json<Vec<PendingUpdate>> {
+    Json(crate::schedule::list_pending_updates().await)
+}
+
 async fn get_update(
     State(state): State<ApprovalState>,
     Path((namespace, name)): Path<(String, String)>,
@@ -103,23 +615,47 @@ async fn get_update(
     }
 }
 
+/// Rollbacks of the update applied by an UpdateRequest, oldest first
+async fn get_update_request_rollbacks(
+    State(state): State<ApprovalState>,
+    Path((namespace, name)): Path<(String, String)>,
+) -> Result<Json<Vec<RollbackRecord>>, StatusCode> {
+    let update_requests: Api<UpdateRequest> = Api::namespaced(state.client, &namespace);
+
+    match update_requests.get(&name).await {
+        Ok(ur) => Ok(Json(
+            ur.status
+                .map(|status| status.rollback_history)
+                .unwrap_or_default(),
+        )),
+        Err(e) => {
+            warn!("UpdateRequest {}/{} not found: {}", namespace, name, e);
+            Err(StatusCode::NOT_FOUND)
+        },
+    }
+}
+
 /// Simple approval request for UI (doesn't require update_id since it's in the path)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimpleApprovalRequest {
     pub approver: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
 }
 
 /// Simple rejection request for UI (doesn't require update_id since it's in the path)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimpleRejectionRequest {
     pub approver: Option<String>,
+    /// Required by `reject_update`; optional for batches
     pub reason: Option<String>,
 }
 
 pub async fn approve_update(
     State(state): State<ApprovalState>,
     Path((namespace, name)): Path<(String, String)>,
-    Json(approval): Json<SimpleApprovalRequest>,
+    headers: HeaderMap,
+    Json(mut approval): Json<SimpleApprovalRequest>,
 ) -> impl IntoResponse {
     let update_requests: Api<UpdateRequest> = Api::namespaced(state.client.clone(), &namespace);
 
@@ -152,25 +688,110 @@ pub async fn approve_update(
         );
     }
 
+    // Only holders of the headwind.sh/approved-by-role role may approve
+    let identity = rbac::CallerIdentity::from_headers(&headers);
+    if let Err(e) =
+        rbac::check_approval_access(&state.client, identity.as_ref(), &update_request).await
+    {
+        return (e.status_code(), Json(json!({"error": e.to_string()})));
+    }
+    if let Some(identity) = identity {
+        approval.approver = Some(identity.user);
+    }
+
     info!(
-        "Approving UpdateRequest {}/{} by {:?}",
+        "Approving UpdateRequest {}/{} by {:?}{}",
         namespace,
         name,
-        approval.approver.as_deref().unwrap_or("unknown")
+        approval.approver.as_deref().unwrap_or("unknown"),
+        approval
+            .reason
+            .as_deref()
+            .map(|r| format!(": {}", r))
+            .unwrap_or_default()
     );
 
+    match apply_approved_update(&state.client, &update_request, approval.approver.clone()).await {
+        Ok(Some(updated_ur)) => (StatusCode::OK, Json(json!(updated_ur))),
+        Ok(None) => (
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": "UpdateRequest is being or has been applied by another replica"
+            })),
+        ),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": format!("Failed to update status: {}", e)})),
+        ),
+    }
+}
+
+/// Apply an approved UpdateRequest: execute the update, send notifications
+/// and record the outcome in its status. Shared by the approval API and the
+/// scheduled approval controller.
+///
+/// The update is applied under the UpdateRequest's Lease, so only one replica
+/// applies it. `None` means another replica holds the Lease or has already
+/// applied the request.
+pub(crate) async fn apply_approved_update(
+    client: &Client,
+    update_request: &UpdateRequest,
+    approver: Option<String>,
+) -> Result<Option<UpdateRequest>, kube::Error> {
+    let name = update_request.name_any();
+    let namespace = update_request.namespace().unwrap_or_default();
+    let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
+
+    let Some(lock) = UpdateRequestLock::try_acquire(client, update_request).await? else {
+        info!(
+            "UpdateRequest {}/{} is being applied by another replica",
+            namespace, name
+        );
+        return Ok(None);
+    };
+
+    // Another replica may have applied it since it was read
+    let latest = update_requests.get(&name).await?;
+    if latest
+        .status
+        .as_ref()
+        .is_some_and(|s| s.phase != UpdatePhase::Pending)
+    {
+        info!(
+            "UpdateRequest {}/{} was already applied by another replica",
+            namespace, name
+        );
+        release(lock, &namespace, &name).await;
+        return Ok(None);
+    }
+
     // Increment approved counter
     crate::metrics::UPDATES_APPROVED.inc();
+    crate::metrics::queue::record_approval(update_request, Utc::now());
 
     // Execute the update
+    let mut progress_steps = update_request
+        .status
+        .as_ref()
+        .map(|status| status.progress_steps.clone())
+        .unwrap_or_default();
+    let patch_step = ProgressStep::start(progress::KUBERNETES_PATCH);
     let update_result = execute_update(
-        &state.client,
-        &update_request,
+        client,
+        update_request,
         Some(name.clone()),
-        approval.approver.clone(),
+        approver.clone(),
         true, // Enable automatic rollback monitoring
     )
     .await;
+    progress::upsert_step(
+        &mut progress_steps,
+        match &update_result {
+            Ok(()) => patch_step.succeed(None),
+            Err(e) => patch_step.fail(e.to_string()),
+        },
+    );
+    let notification_step = ProgressStep::start(progress::NOTIFICATION_SENT);
 
     // Build deployment info for notifications
     let deployment_info = DeploymentInfo {
@@ -185,10 +806,7 @@ pub async fn approve_update(
     // Send approval notification
     notifications::notify_update_approved(
         deployment_info.clone(),
-        approval
-            .approver
-            .clone()
-            .unwrap_or_else(|| "unknown".to_string()),
+        approver.clone().unwrap_or_else(|| "unknown".to_string()),
         name.clone(),
     );
 
@@ -198,14 +816,23 @@ pub async fn approve_update(
             info!("Successfully applied update {}/{}", namespace, name);
 
             // Send completion notification
-            notifications::notify_update_completed(deployment_info.clone());
+            notifications::notify(
+                NotificationPayload::new(
+                    NotificationEvent::UpdateCompleted,
+                    deployment_info.clone(),
+                )
+                .with_update_request(name.clone()),
+            );
+            crate::integrations::jira::complete_issue_for_update_request(update_request).await;
+            progress::upsert_step(&mut progress_steps, notification_step.succeed(None));
 
             UpdateRequestStatus {
                 phase: UpdatePhase::Completed,
-                approved_by: approval.approver.clone(),
+                approved_by: approver.clone(),
                 approved_at: Some(Utc::now()),
                 message: Some("Update applied successfully".to_string()),
                 last_updated: Some(Utc::now()),
+                progress_steps,
                 ..Default::default()
             }
         },
@@ -213,14 +840,20 @@ pub async fn approve_update(
             error!("Failed to apply update {}/{}: {}", namespace, name, e);
 
             // Send failure notification
-            notifications::notify_update_failed(deployment_info.clone(), e.to_string());
+            notifications::notify(
+                NotificationPayload::new(NotificationEvent::UpdateFailed, deployment_info.clone())
+                    .with_error(e.to_string())
+                    .with_update_request(name.clone()),
+            );
+            progress::upsert_step(&mut progress_steps, notification_step.succeed(None));
 
             UpdateRequestStatus {
                 phase: UpdatePhase::Failed,
-                approved_by: approval.approver.clone(),
+                approved_by: approver.clone(),
                 approved_at: Some(Utc::now()),
                 message: Some(format!("Update failed: {}", e)),
                 last_updated: Some(Utc::now()),
+                progress_steps,
                 ..Default::default()
             }
         },
@@ -233,32 +866,72 @@ pub async fn approve_update(
         "status": new_status
     });
 
-    match update_requests
+    let patched = update_requests
         .patch_status(&name, &PatchParams::default(), &Patch::Merge(status_patch))
-        .await
-    {
+        .await;
+    release(lock, &namespace, &name).await;
+
+    match patched {
         Ok(updated_ur) => {
             info!("Updated status for UpdateRequest {}/{}", namespace, name);
-            (StatusCode::OK, Json(json!(updated_ur)))
+            Ok(Some(updated_ur))
         },
         Err(e) => {
             error!(
                 "Failed to update status for UpdateRequest {}/{}: {}",
                 namespace, name, e
             );
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({"error": format!("Failed to update status: {}", e)})),
-            )
+            Err(e)
         },
     }
 }
 
+/// Release the Lease of an UpdateRequest. Failures are only logged: the
+/// Lease expires on its own.
+async fn release(lock: UpdateRequestLock, namespace: &str, name: &str) {
+    if let Err(e) = lock.release().await {
+        warn!(
+            "Failed to release the Lease of UpdateRequest {}/{}: {}",
+            namespace, name, e
+        );
+    }
+}
+
+/// Record steps on the UpdateRequest an update was applied for, if any.
+/// Failures are only logged.
+async fn record_progress(
+    client: &Client,
+    namespace: &str,
+    update_request_name: Option<&str>,
+    steps: Vec<ProgressStep>,
+) {
+    let Some(update_request_name) = update_request_name else {
+        return;
+    };
+    if let Err(e) = progress::record_steps(client, namespace, update_request_name, &steps).await {
+        warn!("{:#}", e);
+    }
+}
+
 pub async fn reject_update(
     State(state): State<ApprovalState>,
     Path((namespace, name)): Path<(String, String)>,
     Json(approval): Json<SimpleRejectionRequest>,
 ) -> impl IntoResponse {
+    let reason = match validate_rejection_reason(
+        approval.reason.as_deref(),
+        max_rejection_reason_length(),
+    ) {
+        Ok(reason) => reason.to_string(),
+        Err(e) => {
+            warn!(
+                "Refusing to reject UpdateRequest {}/{}: {}",
+                namespace, name, e
+            );
+            return (StatusCode::BAD_REQUEST, Json(json!({"error": e})));
+        },
+    };
+
     let update_requests: Api<UpdateRequest> = Api::namespaced(state.client.clone(), &namespace);
 
     // Get the UpdateRequest
@@ -291,11 +964,11 @@ pub async fn reject_update(
     }
 
     info!(
-        "Rejecting UpdateRequest {}/{} by {:?}: {:?}",
+        "Rejecting UpdateRequest {}/{} by {:?}: {}",
         namespace,
         name,
         approval.approver.as_deref().unwrap_or("unknown"),
-        approval.reason
+        reason
     );
 
     // Build deployment info for notifications
@@ -315,44 +988,124 @@ pub async fn reject_update(
             .approver
             .clone()
             .unwrap_or_else(|| "unknown".to_string()),
-        approval
-            .reason
-            .clone()
-            .unwrap_or_else(|| "No reason provided".to_string()),
+        reason.clone(),
         name.clone(),
     );
 
-    // Update the CRD status
-    let new_status = UpdateRequestStatus {
-        phase: UpdatePhase::Rejected,
-        rejected_by: approval.approver.clone(),
-        rejected_at: Some(Utc::now()),
-        message: approval
-            .reason
-            .clone()
-            .or(Some("Rejected by user".to_string())),
-        last_updated: Some(Utc::now()),
-        ..Default::default()
-    };
+    // Update the CRD status
+    let new_status = UpdateRequestStatus {
+        phase: UpdatePhase::Rejected,
+        rejected_by: approval.approver.clone(),
+        rejected_at: Some(Utc::now()),
+        message: Some(reason),
+        last_updated: Some(Utc::now()),
+        ..Default::default()
+    };
+
+    // Patch the status
+    let status_patch = json!({
+        "apiVersion": "headwind.sh/v1alpha1",
+        "kind": "UpdateRequest",
+        "status": new_status
+    });
+
+    match update_requests
+        .patch_status(&name, &PatchParams::default(), &Patch::Merge(status_patch))
+        .await
+    {
+        Ok(updated_ur) => {
+            info!("Updated status for UpdateRequest {}/{}", namespace, name);
+            (StatusCode::OK, Json(json!(updated_ur)))
+        },
+        Err(e) => {
+            error!(
+                "Failed to update status for UpdateRequest {}/{}: {}",
+                namespace, name, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": format!("Failed to update status: {}", e)})),
+            )
+        },
+    }
+}
+
+/// List BatchUpdateRequests across all namespaces
+async fn list_batch_updates(
+    State(state): State<ApprovalState>,
+) -> Result<Json<Vec<BatchUpdateRequest>>, StatusCode> {
+    let batches: Api<BatchUpdateRequest> = Api::all(state.client);
+
+    match batches.list(&Default::default()).await {
+        Ok(list) => Ok(Json(list.items)),
+        Err(e) => {
+            error!("Failed to list BatchUpdateRequests: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        },
+    }
+}
+
+/// Fetch a BatchUpdateRequest that is still pending, or the error response
+async fn get_pending_batch(
+    batches: &Api<BatchUpdateRequest>,
+    namespace: &str,
+    name: &str,
+    action: &str,
+) -> Result<BatchUpdateRequest, (StatusCode, Json<serde_json::Value>)> {
+    let batch = batches.get(name).await.map_err(|e| {
+        warn!("BatchUpdateRequest {}/{} not found: {}", namespace, name, e);
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": format!("BatchUpdateRequest not found: {}", e)})),
+        )
+    })?;
+
+    if let Some(status) = &batch.status
+        && status.phase != UpdatePhase::Pending
+    {
+        warn!(
+            "BatchUpdateRequest {}/{} is not in pending state: {:?}",
+            namespace, name, status.phase
+        );
+        return Err((
+            StatusCode::CONFLICT,
+            Json(json!({
+                "error": format!("BatchUpdateRequest is in {:?} state, cannot {}", status.phase, action),
+                "current_phase": format!("{:?}", status.phase)
+            })),
+        ));
+    }
+
+    Ok(batch)
+}
 
-    // Patch the status
+/// Write the status of a BatchUpdateRequest and return the updated resource
+async fn patch_batch_status(
+    batches: &Api<BatchUpdateRequest>,
+    namespace: &str,
+    name: &str,
+    status: UpdateRequestStatus,
+) -> (StatusCode, Json<serde_json::Value>) {
     let status_patch = json!({
         "apiVersion": "headwind.sh/v1alpha1",
-        "kind": "UpdateRequest",
-        "status": new_status
+        "kind": "BatchUpdateRequest",
+        "status": status
     });
 
-    match update_requests
-        .patch_status(&name, &PatchParams::default(), &Patch::Merge(status_patch))
+    match batches
+        .patch_status(name, &PatchParams::default(), &Patch::Merge(status_patch))
         .await
     {
-        Ok(updated_ur) => {
-            info!("Updated status for UpdateRequest {}/{}", namespace, name);
-            (StatusCode::OK, Json(json!(updated_ur)))
+        Ok(updated) => {
+            info!(
+                "Updated status for BatchUpdateRequest {}/{}",
+                namespace, name
+            );
+            (StatusCode::OK, Json(json!(updated)))
         },
         Err(e) => {
             error!(
-                "Failed to update status for UpdateRequest {}/{}: {}",
+                "Failed to update status for BatchUpdateRequest {}/{}: {}",
                 namespace, name, e
             );
             (
@@ -363,6 +1116,75 @@ pub async fn reject_update(
     }
 }
 
+/// Approve a BatchUpdateRequest, applying all of its updates in one patch
+pub async fn approve_batch_update(
+    State(state): State<ApprovalState>,
+    Path((namespace, name)): Path<(String, String)>,
+    headers: HeaderMap,
+    Json(mut approval): Json<SimpleApprovalRequest>,
+) -> impl IntoResponse {
+    let batches: Api<BatchUpdateRequest> = Api::namespaced(state.client.clone(), &namespace);
+    let batch = match get_pending_batch(&batches, &namespace, &name, "approve").await {
+        Ok(batch) => batch,
+        Err(response) => return response,
+    };
+
+    // Only holders of the headwind.sh/approved-by-role role may approve
+    let identity = rbac::CallerIdentity::from_headers(&headers);
+    if let Err(e) =
+        rbac::check_batch_approval_access(&state.client, identity.as_ref(), &batch).await
+    {
+        return (e.status_code(), Json(json!({"error": e.to_string()})));
+    }
+    if let Some(identity) = identity {
+        approval.approver = Some(identity.user);
+    }
+
+    info!(
+        "Approving BatchUpdateRequest {}/{} ({} updates) by {:?}",
+        namespace,
+        name,
+        batch.spec.updates.len(),
+        approval.approver.as_deref().unwrap_or("unknown")
+    );
+    crate::metrics::UPDATES_APPROVED.inc();
+
+    let result = match acquire_update_permit(&UPDATE_PERMITS).await {
+        Ok(_permit) => apply_batch(&state.client, &batch, &name, approval.approver.clone()).await,
+        Err(e) => Err(e),
+    };
+    match &result {
+        Ok(()) => info!("Successfully applied batch update {}/{}", namespace, name),
+        Err(e) => error!("Failed to apply batch update {}/{}: {}", namespace, name, e),
+    }
+
+    let status = approved_status(approval.approver, &result);
+    patch_batch_status(&batches, &namespace, &name, status).await
+}
+
+/// Reject a BatchUpdateRequest and every update in it
+pub async fn reject_batch_update(
+    State(state): State<ApprovalState>,
+    Path((namespace, name)): Path<(String, String)>,
+    Json(rejection): Json<SimpleRejectionRequest>,
+) -> impl IntoResponse {
+    let batches: Api<BatchUpdateRequest> = Api::namespaced(state.client.clone(), &namespace);
+    if let Err(response) = get_pending_batch(&batches, &namespace, &name, "reject").await {
+        return response;
+    }
+
+    info!(
+        "Rejecting BatchUpdateRequest {}/{} by {:?}: {:?}",
+        namespace,
+        name,
+        rejection.approver.as_deref().unwrap_or("unknown"),
+        rejection.reason
+    );
+
+    let status = rejected_status(rejection.approver, rejection.reason);
+    patch_batch_status(&batches, &namespace, &name, status).await
+}
+
 async fn execute_update(
     client: &Client,
     update_request: &UpdateRequest,
@@ -373,11 +1195,30 @@ async fn execute_update(
     let spec = &update_request.spec;
     let target = &spec.target_ref;
 
+    let _permit = acquire_update_permit(&UPDATE_PERMITS).await?;
+
     debug!(
         "Executing update for {}/{} in namespace {}",
         target.kind, target.name, target.namespace
     );
 
+    // Deployments delivered through git are updated there, not patched
+    let tracking_name = update_request_name
+        .clone()
+        .unwrap_or_else(|| update_request.name_any());
+    if deliver_update_request(client, update_request, &tracking_name).await? {
+        crate::metrics::UPDATES_APPLIED.inc();
+        return Ok(());
+    }
+
+    // Multi-container updates are applied together in a single patch
+    if !spec.updates.is_empty() {
+        let tracking_name = update_request_name.unwrap_or_else(|| update_request.name_any());
+        apply_container_updates(client, target, &spec.updates, &tracking_name, approved_by).await?;
+        crate::metrics::UPDATES_APPLIED.inc();
+        return Ok(());
+    }
+
     // Route to appropriate update handler based on resource kind
     match target.kind.as_str() {
         "Deployment" => {
@@ -397,12 +1238,23 @@ async fn execute_update(
         "DaemonSet" => {
             execute_daemonset_update(client, update_request, update_request_name, approved_by).await
         },
+        "ReplicaSet" => execute_replicaset_update(client, update_request, approved_by).await,
         "HelmRelease" => {
             execute_helmrelease_update(client, update_request, update_request_name, approved_by)
                 .await
         },
+        "Service" if target.api_version.starts_with("serving.knative.dev/") => {
+            execute_knative_service_update(client, update_request, approved_by).await
+        },
+        "Composition"
+            if target
+                .api_version
+                .starts_with("apiextensions.crossplane.io/") =>
+        {
+            execute_composition_update(client, update_request, approved_by).await
+        },
         _ => Err(anyhow::anyhow!(
-            "Unsupported resource kind: {}. Only Deployment, StatefulSet, DaemonSet, and HelmRelease are supported.",
+            "Unsupported resource kind: {}. Only Deployment, StatefulSet, DaemonSet, ReplicaSet, HelmRelease, Knative Service, and Crossplane Composition are supported.",
             target.kind
         )),
     }
@@ -464,6 +1316,29 @@ async fn execute_deployment_update(
         .and_then(|c| c.image.as_ref())
         .cloned();
 
+    // Headwind updating itself: refuse while other updates are in flight and
+    // warn before restarting. The update keeps the running image in
+    // `headwind.sh/previous-image` for manual rollback.
+    if self_update_for(&deployment).is_some() {
+        self_update::ensure_no_pending_updates(
+            client,
+            &update_request.namespace().unwrap_or_default(),
+            &update_request.name_any(),
+        )
+        .await?;
+        self_update::notify_self_update(
+            DeploymentInfo {
+                name: target.name.clone(),
+                namespace: target.namespace.clone(),
+                current_image: spec.current_image.clone(),
+                new_image: spec.new_image.clone(),
+                container: Some(container_name.clone()),
+                resource_kind: None,
+            },
+            approved_by.clone(),
+        );
+    }
+
     // Call the update function with tracking metadata
     update_deployment_image_with_tracking(
         client.clone(),
@@ -484,6 +1359,7 @@ async fn execute_deployment_update(
         let container_name_clone = container_name.clone();
         let new_image = spec.new_image.clone();
         let current_image_clone = current_image.clone();
+        let update_request_name = update_request_name.clone();
 
         tokio::spawn(async move {
             info!(
@@ -491,144 +1367,147 @@ async fn execute_deployment_update(
                 namespace, deployment_name
             );
 
+            let rollout_step = ProgressStep::start(progress::ROLLOUT_HEALTH);
             let health_checker = HealthChecker::new(client_clone.clone());
-            match health_checker
+            let status = match health_checker
                 .monitor_deployment_health(&deployment_name, &namespace, &auto_rollback_config)
                 .await
             {
-                Ok(HealthStatus::Healthy) => {
-                    info!(
-                        "Deployment {}/{} is healthy after update to {}",
-                        namespace, deployment_name, new_image
-                    );
-                },
-                Ok(HealthStatus::Failed(reason)) => {
+                Ok(status) => status,
+                Err(e) => {
                     error!(
-                        "Automatic rollback triggered for {}/{}: {}",
-                        namespace, deployment_name, reason
+                        "Error monitoring deployment {}/{}: {}",
+                        namespace, deployment_name, e
                     );
+                    record_progress(
+                        &client_clone,
+                        &namespace,
+                        update_request_name.as_deref(),
+                        vec![rollout_step.fail(format!("Failed to monitor rollout: {}", e))],
+                    )
+                    .await;
+                    return;
+                },
+            };
 
-                    // Send rollback trigger notification
-                    let deployment_info = DeploymentInfo {
-                        name: deployment_name.clone(),
-                        namespace: namespace.clone(),
-                        current_image: new_image.clone(),
-                        new_image: current_image_clone.clone().unwrap_or_default(),
-                        container: Some(container_name_clone.clone()),
-                        resource_kind: None,
-                    };
-                    notifications::notify_rollback_triggered(
-                        deployment_info.clone(),
-                        reason.clone(),
-                    );
+            let rollout_step = match &status {
+                HealthStatus::Healthy => {
+                    rollout_step.succeed(Some("Rollout is healthy".to_string()))
+                },
+                HealthStatus::Progressing => rollout_step.succeed(Some(
+                    "Rollout still progressing when monitoring ended".to_string(),
+                )),
+                HealthStatus::Failed(reason) => rollout_step.fail(reason.clone()),
+                HealthStatus::Timeout => rollout_step.fail("Health check timeout"),
+            };
+            record_progress(
+                &client_clone,
+                &namespace,
+                update_request_name.as_deref(),
+                vec![rollout_step],
+            )
+            .await;
 
-                    // Attempt rollback
-                    if let Some(rollback_image) = current_image_clone.as_ref() {
-                        match update_deployment_image_with_tracking(
-                            client_clone,
-                            &namespace,
-                            &deployment_name,
-                            &container_name_clone,
-                            rollback_image,
-                            None,
-                            Some("headwind-auto-rollback".to_string()),
-                        )
-                        .await
-                        {
-                            Ok(()) => {
-                                info!(
-                                    "Successfully rolled back {}/{} from {} to {}",
-                                    namespace, deployment_name, new_image, rollback_image
-                                );
-                                notifications::notify_rollback_completed(deployment_info.clone());
-                            },
-                            Err(e) => {
-                                error!(
-                                    "Failed to rollback {}/{}: {}",
-                                    namespace, deployment_name, e
-                                );
-                                notifications::notify_rollback_failed(
-                                    deployment_info.clone(),
-                                    e.to_string(),
-                                );
-                            },
-                        }
-                    } else {
-                        warn!(
-                            "Cannot rollback {}/{}: no previous image found",
-                            namespace, deployment_name
-                        );
-                    }
+            let reason = match &status {
+                HealthStatus::Healthy => {
+                    info!(
+                        "Deployment {}/{} is healthy after update to {}",
+                        namespace, deployment_name, new_image
+                    );
+                    return;
                 },
-                Ok(HealthStatus::Timeout) => {
-                    error!(
-                        "Automatic rollback triggered for {}/{}: Health check timeout",
+                HealthStatus::Progressing => {
+                    warn!(
+                        "Deployment {}/{} still progressing after timeout",
                         namespace, deployment_name
                     );
+                    return;
+                },
+                HealthStatus::Failed(reason) => reason.clone(),
+                HealthStatus::Timeout => "Health check timeout".to_string(),
+            };
+            let Some(rollback_reason) = RollbackReason::from_health_status(&status) else {
+                return;
+            };
 
-                    // Send rollback trigger notification
-                    let deployment_info = DeploymentInfo {
-                        name: deployment_name.clone(),
-                        namespace: namespace.clone(),
-                        current_image: new_image.clone(),
-                        new_image: current_image_clone.clone().unwrap_or_default(),
-                        container: Some(container_name_clone.clone()),
-                        resource_kind: None,
-                    };
-                    notifications::notify_rollback_triggered(
-                        deployment_info.clone(),
-                        "Health check timeout".to_string(),
-                    );
+            error!(
+                "Automatic rollback triggered for {}/{}: {}",
+                namespace, deployment_name, reason
+            );
 
-                    // Attempt rollback
-                    if let Some(rollback_image) = current_image_clone.as_ref() {
-                        match update_deployment_image_with_tracking(
+            // Send rollback trigger notification
+            let deployment_info = DeploymentInfo {
+                name: deployment_name.clone(),
+                namespace: namespace.clone(),
+                current_image: new_image.clone(),
+                new_image: current_image_clone.clone().unwrap_or_default(),
+                container: Some(container_name_clone.clone()),
+                resource_kind: None,
+            };
+            notifications::notify_rollback_triggered(deployment_info.clone(), reason.clone());
+
+            // Attempt rollback
+            let Some(rollback_image) = current_image_clone.as_ref() else {
+                warn!(
+                    "Cannot rollback {}/{}: no previous image found",
+                    namespace, deployment_name
+                );
+                return;
+            };
+
+            crate::metrics::ROLLBACKS_TOTAL
+                .with_label_values(&[rollback_reason.as_str()])
+                .inc();
+            crate::metrics::ROLLBACKS_AUTOMATIC.inc();
+
+            match update_deployment_image_with_tracking(
+                client_clone.clone(),
+                &namespace,
+                &deployment_name,
+                &container_name_clone,
+                rollback_image,
+                None,
+                Some(AUTO_ROLLBACK_USER.to_string()),
+            )
+            .await
+            {
+                Ok(()) => {
+                    info!(
+                        "Successfully rolled back {}/{} from {} to {} ({})",
+                        namespace,
+                        deployment_name,
+                        new_image,
+                        rollback_image,
+                        rollback_reason.as_str()
+                    );
+                    notifications::notify_rollback_completed(deployment_info);
+
+                    if let Some(update_request_name) = update_request_name {
+                        let record = RollbackRecord {
+                            triggered_at: Utc::now(),
+                            reason,
+                            rolled_back_to: rollback_image.clone(),
+                            triggered_by: AUTO_ROLLBACK_USER.to_string(),
+                        };
+                        if let Err(e) = crate::rollback::record_rollback(
                             client_clone,
                             &namespace,
-                            &deployment_name,
-                            &container_name_clone,
-                            rollback_image,
-                            None,
-                            Some("headwind-auto-rollback".to_string()),
+                            &update_request_name,
+                            record,
                         )
                         .await
                         {
-                            Ok(()) => {
-                                info!(
-                                    "Successfully rolled back {}/{} from {} to {} due to timeout",
-                                    namespace, deployment_name, new_image, rollback_image
-                                );
-                                notifications::notify_rollback_completed(deployment_info.clone());
-                            },
-                            Err(e) => {
-                                error!(
-                                    "Failed to rollback {}/{}: {}",
-                                    namespace, deployment_name, e
-                                );
-                                notifications::notify_rollback_failed(
-                                    deployment_info.clone(),
-                                    e.to_string(),
-                                );
-                            },
+                            warn!("{:#}", e);
                         }
-                    } else {
-                        warn!(
-                            "Cannot rollback {}/{}: no previous image found",
-                            namespace, deployment_name
-                        );
                     }
                 },
-                Ok(HealthStatus::Progressing) => {
-                    warn!(
-                        "Deployment {}/{} still progressing after timeout",
-                        namespace, deployment_name
-                    );
-                },
                 Err(e) => {
+                    crate::metrics::ROLLBACKS_FAILED.inc();
                     error!(
-                        "Error monitoring deployment {}/{}: {}",
+                        "Failed to rollback {}/{}: {}",
                         namespace, deployment_name, e
                     );
+                    notifications::notify_rollback_failed(deployment_info, e.to_string());
                 },
             }
         });
@@ -769,14 +1648,8 @@ async fn execute_statefulset_update(
         client,
         &target.namespace,
         &target.name,
-        spec.current_image
-            .rsplit_once(':')
-            .map(|(image, _)| image)
-            .unwrap_or(&spec.current_image),
-        spec.new_image
-            .rsplit_once(':')
-            .map(|(_, version)| version)
-            .unwrap_or(&spec.new_image),
+        split_tag(&spec.current_image).map_or(spec.current_image.as_str(), |(image, _)| image),
+        split_tag(&spec.new_image).map_or(spec.new_image.as_str(), |(_, version)| version),
         approved_by.as_deref(),
     )
     .await?;
@@ -804,6 +1677,42 @@ async fn execute_statefulset_update(
     Ok(())
 }
 
+async fn execute_replicaset_update(
+    client: &Client,
+    update_request: &UpdateRequest,
+    approved_by: Option<String>,
+) -> Result<()> {
+    let spec = &update_request.spec;
+    let target = &spec.target_ref;
+
+    info!(
+        "Executing ReplicaSet update for {} in namespace {}",
+        target.name, target.namespace
+    );
+
+    update_replicaset_image_with_tracking(
+        client,
+        &target.namespace,
+        &target.name,
+        split_tag(&spec.current_image).map_or(spec.current_image.as_str(), |(image, _)| image),
+        split_tag(&spec.new_image).map_or(spec.new_image.as_str(), |(_, version)| version),
+        approved_by.as_deref(),
+    )
+    .await?;
+
+    crate::notifications::notify_update_completed(crate::notifications::DeploymentInfo {
+        name: target.name.clone(),
+        namespace: target.namespace.clone(),
+        current_image: spec.current_image.clone(),
+        new_image: spec.new_image.clone(),
+        container: spec.container_name.clone(),
+        resource_kind: Some("ReplicaSet".to_string()),
+    });
+    crate::metrics::UPDATES_APPLIED.inc();
+
+    Ok(())
+}
+
 async fn execute_daemonset_update(
     client: &Client,
     update_request: &UpdateRequest,
@@ -823,14 +1732,8 @@ async fn execute_daemonset_update(
         client,
         &target.namespace,
         &target.name,
-        spec.current_image
-            .rsplit_once(':')
-            .map(|(image, _)| image)
-            .unwrap_or(&spec.current_image),
-        spec.new_image
-            .rsplit_once(':')
-            .map(|(_, version)| version)
-            .unwrap_or(&spec.new_image),
+        split_tag(&spec.current_image).map_or(spec.current_image.as_str(), |(image, _)| image),
+        split_tag(&spec.new_image).map_or(spec.new_image.as_str(), |(_, version)| version),
         approved_by.as_deref(),
     )
     .await?;
@@ -858,6 +1761,93 @@ async fn execute_daemonset_update(
     Ok(())
 }
 
+async fn execute_knative_service_update(
+    client: &Client,
+    update_request: &UpdateRequest,
+    approved_by: Option<String>,
+) -> Result<()> {
+    let spec = &update_request.spec;
+    let target = &spec.target_ref;
+
+    info!(
+        "Executing Knative Service update for {} in namespace {}",
+        target.name, target.namespace
+    );
+
+    update_knative_service_image_with_tracking(
+        client,
+        &target.namespace,
+        &target.name,
+        split_tag(&spec.current_image).map_or(spec.current_image.as_str(), |(image, _)| image),
+        split_tag(&spec.new_image).map_or(spec.new_image.as_str(), |(_, version)| version),
+        approved_by.as_deref(),
+    )
+    .await?;
+
+    info!(
+        "Successfully updated Knative Service {}/{} to {}",
+        target.namespace, target.name, spec.new_image
+    );
+
+    let deployment_info = crate::notifications::DeploymentInfo {
+        name: target.name.clone(),
+        namespace: target.namespace.clone(),
+        current_image: spec.current_image.clone(),
+        new_image: spec.new_image.clone(),
+        container: spec.container_name.clone(),
+        resource_kind: Some("Service".to_string()),
+    };
+
+    crate::notifications::notify_update_completed(deployment_info);
+
+    crate::metrics::UPDATES_APPLIED.inc();
+
+    Ok(())
+}
+
+async fn execute_composition_update(
+    client: &Client,
+    update_request: &UpdateRequest,
+    approved_by: Option<String>,
+) -> Result<()> {
+    let spec = &update_request.spec;
+    let target = &spec.target_ref;
+
+    info!(
+        "Executing Crossplane Composition update for {}",
+        target.name
+    );
+
+    update_composition_image_with_tracking(
+        client,
+        &target.name,
+        split_tag(&spec.current_image).map_or(spec.current_image.as_str(), |(image, _)| image),
+        split_tag(&spec.new_image).map_or(spec.new_image.as_str(), |(_, version)| version),
+        approved_by.as_deref(),
+    )
+    .await?;
+
+    info!(
+        "Successfully updated Composition {} to {}",
+        target.name, spec.new_image
+    );
+
+    let deployment_info = crate::notifications::DeploymentInfo {
+        name: target.name.clone(),
+        namespace: target.namespace.clone(),
+        current_image: spec.current_image.clone(),
+        new_image: spec.new_image.clone(),
+        container: spec.container_name.clone(),
+        resource_kind: Some("Composition".to_string()),
+    };
+
+    crate::notifications::notify_update_completed(deployment_info);
+
+    crate::metrics::UPDATES_APPLIED.inc();
+
+    Ok(())
+}
+
 /// Query parameters for rollback
 #[derive(Debug, Deserialize)]
 struct RollbackQuery {
@@ -936,26 +1926,8 @@ async fn rollback_deployment(
     );
 
     // Get the target image from history
-    let target_image = match rollback_manager
-        .get_image_by_index(&deployment, &namespace, &request.container, index)
-        .await
-    {
-        Ok(Some(image)) => image,
-        Ok(None) => {
-            warn!(
-                "No history entry found at index {} for {}/{} container {}",
-                index, namespace, deployment, request.container
-            );
-            return (
-                StatusCode::NOT_FOUND,
-                Json(json!({
-                    "error": format!("No history entry found at index {}", index),
-                    "deployment": deployment,
-                    "container": request.container,
-                    "index": index
-                })),
-            );
-        },
+    let history = match rollback_manager.get_history(&deployment, &namespace).await {
+        Ok(history) => history,
         Err(e) => {
             error!(
                 "Failed to get rollback history for {}/{}: {}",
@@ -967,12 +1939,39 @@ async fn rollback_deployment(
             );
         },
     };
+    let Some(target_image) = history
+        .get_entry_by_index(&request.container, index)
+        .map(|entry| entry.image.clone())
+    else {
+        warn!(
+            "No history entry found at index {} for {}/{} container {}",
+            index, namespace, deployment, request.container
+        );
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "error": format!("No history entry found at index {}", index),
+                "deployment": deployment,
+                "container": request.container,
+                "index": index
+            })),
+        );
+    };
+    // The UpdateRequest that deployed the image being rolled back, if any
+    let rolled_back_update_request = history
+        .get_entry_by_index(&request.container, 0)
+        .and_then(|entry| entry.update_request_name.clone());
 
     info!(
         "Rolling back {}/{} container {} to image {}",
         namespace, deployment, request.container, target_image
     );
 
+    crate::metrics::ROLLBACKS_TOTAL
+        .with_label_values(&[RollbackReason::Manual.as_str()])
+        .inc();
+    crate::metrics::ROLLBACKS_MANUAL.inc();
+
     // Perform the rollback
     let rollback_result = update_deployment_image_with_tracking(
         state.client.clone(),
@@ -991,6 +1990,32 @@ async fn rollback_deployment(
                 "Successfully rolled back {}/{} container {} to {}",
                 namespace, deployment, request.container, target_image
             );
+
+            if let Some(update_request_name) = rolled_back_update_request {
+                let record = RollbackRecord {
+                    triggered_at: Utc::now(),
+                    reason: request
+                        .reason
+                        .clone()
+                        .unwrap_or_else(|| "Manual rollback".to_string()),
+                    rolled_back_to: target_image.clone(),
+                    triggered_by: request
+                        .user
+                        .clone()
+                        .unwrap_or_else(|| "unknown".to_string()),
+                };
+                if let Err(e) = crate::rollback::record_rollback(
+                    state.client.clone(),
+                    &namespace,
+                    &update_request_name,
+                    record,
+                )
+                .await
+                {
+                    warn!("{:#}", e);
+                }
+            }
+
             (
                 StatusCode::OK,
                 Json(json!({
@@ -1005,6 +2030,7 @@ async fn rollback_deployment(
             )
         },
         Err(e) => {
+            crate::metrics::ROLLBACKS_FAILED.inc();
             error!(
                 "Failed to rollback {}/{} container {}: {}",
                 namespace, deployment, request.container, e
@@ -1024,3 +2050,83 @@ async fn rollback_deployment(
 async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_validate_label() {
+        assert!(validate_label("app", "nginx").is_ok());
+        assert!(validate_label("headwind.sh/team", "platform").is_ok());
+        assert!(validate_label("env", "").is_ok());
+
+        assert!(validate_label("", "nginx").is_err());
+        assert!(validate_label("-app", "nginx").is_err());
+        assert!(validate_label("Headwind.sh/team", "platform").is_err());
+        assert!(validate_label("app", "has space").is_err());
+        assert!(validate_label("app", &"a".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn test_validate_scheduled_at() {
+        let now = Utc::now();
+        assert!(validate_scheduled_at(now + chrono::Duration::minutes(1), now).is_ok());
+
+        let err = validate_scheduled_at(now, now).unwrap_err();
+        assert!(err.contains("must be in the future"), "{}", err);
+        assert!(validate_scheduled_at(now - chrono::Duration::hours(1), now).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejection_reason() {
+        assert_eq!(
+            validate_rejection_reason(Some("  not yet \n"), 10),
+            Ok("not yet")
+        );
+        assert_eq!(
+            validate_rejection_reason(Some(&"é".repeat(10)), 10).map(str::len),
+            Ok(20)
+        );
+
+        for reason in [None, Some(""), Some(" \t\n")] {
+            assert_eq!(
+                validate_rejection_reason(reason, 10),
+                Err("A rejection reason is required".to_string())
+            );
+        }
+        assert_eq!(
+            validate_rejection_reason(Some("eleven char"), 10),
+            Err("Rejection reason is 11 characters long, the maximum is 10".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_permits_are_granted_in_order() {
+        let permits = Arc::new(Semaphore::new(1));
+        let held = acquire_update_permit(&permits).await.unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut waiters = Vec::new();
+        for i in 0..3 {
+            let permits = permits.clone();
+            let tx = tx.clone();
+            waiters.push(tokio::spawn(async move {
+                let _permit = acquire_update_permit(&permits).await.unwrap();
+                tx.send(i).unwrap();
+            }));
+            // Let the waiter enqueue before spawning the next one
+            tokio::task::yield_now().await;
+        }
+
+        assert!(rx.try_recv().is_err());
+        drop(held);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+
+        let order: Vec<i32> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(order, vec![0, 1, 2]);
+    }
+}
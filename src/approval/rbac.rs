@@ -0,0 +1,422 @@
+//! Approval restricted to holders of a Kubernetes role.
+//!
+//! When the target of an UpdateRequest carries `headwind.sh/approved-by-role`,
+//! the caller named in `X-Remote-User` (with `X-Remote-Groups`) must be allowed
+//! to `update` that Role in the workload's namespace. This is checked with a
+//! SubjectAccessReview, so it follows whatever RoleBindings and
+//! ClusterRoleBindings grant that permission.
+//!
+//! Required setup, for a role named `senior-engineer`:
+//!
+//! ```yaml
+//! apiVersion: rbac.authorization.k8s.io/v1
+//! kind: Role
+//! metadata:
+//!   name: senior-engineer
+//!   namespace: production
+//! rules:
+//!   - apiGroups: ["rbac.authorization.k8s.io"]
+//!     resources: ["roles"]
+//!     resourceNames: ["senior-engineer"]
+//!     verbs: ["update"]
+//! ```
+//!
+//! bound to the approvers with a RoleBinding, and `create` on
+//! `subjectaccessreviews.authorization.k8s.io` for Headwind's service account.
+//! The headers are trusted as sent, so the approval API must only be reachable
+//! through a proxy that authenticates callers and sets them.
+
+use crate::metrics::APPROVAL_RBAC_DENIALS_TOTAL;
+use crate::models::crd::{BatchUpdateRequest, TargetRef, UpdateRequest};
+use crate::models::policy::annotations;
+use axum::http::{HeaderMap, StatusCode};
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SubjectAccessReview, SubjectAccessReviewSpec,
+};
+use kube::api::{ApiResource, DynamicObject, PostParams};
+use kube::core::GroupVersion;
+use kube::{Api, Client, ResourceExt};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Header carrying the authenticated user, as set by an authenticating proxy
+pub const REMOTE_USER_HEADER: &str = "X-Remote-User";
+
+/// Header carrying the user's groups, comma-separated or repeated
+pub const REMOTE_GROUPS_HEADER: &str = "X-Remote-Groups";
+
+/// Caller of the approval API
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallerIdentity {
+    pub user: String,
+    pub groups: Vec<String>,
+}
+
+impl CallerIdentity {
+    /// Identity from `X-Remote-User` and `X-Remote-Groups`, None without a user
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let user = headers
+            .get(REMOTE_USER_HEADER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .to_string();
+        if user.is_empty() {
+            return None;
+        }
+
+        let groups = headers
+            .get_all(REMOTE_GROUPS_HEADER)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        Some(Self { user, groups })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ApprovalAccessError {
+    #[error("Approval requires role {0}: missing X-Remote-User header")]
+    MissingIdentity(String),
+    #[error("{user} may not approve: requires role {role}{}", reason.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default())]
+    Denied {
+        user: String,
+        role: String,
+        reason: Option<String>,
+    },
+    #[error("Kubernetes API error: {0}")]
+    Kube(#[from] kube::Error),
+    #[error("Unsupported target apiVersion {0}")]
+    InvalidTarget(String),
+}
+
+impl ApprovalAccessError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApprovalAccessError::MissingIdentity(_) | ApprovalAccessError::Denied { .. } => {
+                StatusCode::FORBIDDEN
+            },
+            ApprovalAccessError::Kube(_) | ApprovalAccessError::InvalidTarget(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            },
+        }
+    }
+}
+
+/// Role named by `headwind.sh/approved-by-role`, if any
+pub fn required_role(annotations: &BTreeMap<String, String>) -> Option<String> {
+    annotations
+        .get(annotations::APPROVED_BY_ROLE)
+        .map(|role| role.trim().to_string())
+        .filter(|role| !role.is_empty())
+}
+
+/// Review of whether `identity` may `update` the Role `role` in `namespace`
+pub fn access_review(
+    identity: &CallerIdentity,
+    namespace: &str,
+    role: &str,
+) -> SubjectAccessReview {
+    SubjectAccessReview {
+        spec: SubjectAccessReviewSpec {
+            user: Some(identity.user.clone()),
+            groups: (!identity.groups.is_empty()).then(|| identity.groups.clone()),
+            resource_attributes: Some(ResourceAttributes {
+                group: Some("rbac.authorization.k8s.io".to_string()),
+                resource: Some("roles".to_string()),
+                verb: Some("update".to_string()),
+                name: Some(role.to_string()),
+                namespace: Some(namespace.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Annotations of the workload an UpdateRequest targets
+async fn target_annotations(
+    client: &Client,
+    target: &TargetRef,
+) -> Result<BTreeMap<String, String>, ApprovalAccessError> {
+    let gvk = target
+        .api_version
+        .parse::<GroupVersion>()
+        .map_err(|_| ApprovalAccessError::InvalidTarget(target.api_version.clone()))?
+        .with_kind(&target.kind);
+    let api: Api<DynamicObject> = Api::namespaced_with(
+        client.clone(),
+        &target.namespace,
+        &ApiResource::from_gvk(&gvk),
+    );
+    Ok(api.get_metadata(&target.name).await?.annotations().clone())
+}
+
+/// Check that the caller may approve the UpdateRequest. Always allowed when
+/// the target has no `headwind.sh/approved-by-role` annotation.
+pub async fn check_approval_access(
+    client: &Client,
+    identity: Option<&CallerIdentity>,
+    update_request: &UpdateRequest,
+) -> Result<(), ApprovalAccessError> {
+    let request = format!(
+        "{}/{}",
+        update_request.namespace().unwrap_or_default(),
+        update_request.name_any()
+    );
+    check_access(client, identity, &update_request.spec.target_ref, &request).await
+}
+
+/// Check that the caller may approve the BatchUpdateRequest, under the same
+/// rule as its workload's UpdateRequests
+pub async fn check_batch_approval_access(
+    client: &Client,
+    identity: Option<&CallerIdentity>,
+    batch: &BatchUpdateRequest,
+) -> Result<(), ApprovalAccessError> {
+    let request = format!(
+        "{}/{}",
+        batch.namespace().unwrap_or_default(),
+        batch.name_any()
+    );
+    check_access(client, identity, &batch.spec.target_ref, &request).await
+}
+
+async fn check_access(
+    client: &Client,
+    identity: Option<&CallerIdentity>,
+    target: &TargetRef,
+    request: &str,
+) -> Result<(), ApprovalAccessError> {
+    let Some(role) = required_role(&target_annotations(client, target).await?) else {
+        return Ok(());
+    };
+
+    let Some(identity) = identity else {
+        APPROVAL_RBAC_DENIALS_TOTAL.inc();
+        warn!(
+            "Denied approval of {}: role {} required and no {} header",
+            request, role, REMOTE_USER_HEADER
+        );
+        return Err(ApprovalAccessError::MissingIdentity(role));
+    };
+
+    let reviews: Api<SubjectAccessReview> = Api::all(client.clone());
+    let review = reviews
+        .create(
+            &PostParams::default(),
+            &access_review(identity, &target.namespace, &role),
+        )
+        .await?;
+    let status = review.status.unwrap_or_default();
+
+    if status.allowed {
+        info!(
+            "{} holds role {} for {}/{}",
+            identity.user, role, target.namespace, target.name
+        );
+        return Ok(());
+    }
+
+    APPROVAL_RBAC_DENIALS_TOTAL.inc();
+    warn!(
+        "Denied approval of {} by {}: role {} required",
+        request, identity.user, role
+    );
+    Err(ApprovalAccessError::Denied {
+        user: identity.user.clone(),
+        role,
+        reason: status.reason.filter(|r| !r.is_empty()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FakeApi};
+    use axum::http::HeaderValue;
+    use http::Method;
+    use serde_json::{Value, json};
+
+    fn update_request() -> UpdateRequest {
+        let mut update_request = testing::update_request("production", "web-1-26-0");
+        update_request.spec.container_name = None;
+        update_request
+    }
+
+    /// Kubernetes API serving the web Deployment with `annotations` and
+    /// answering SubjectAccessReviews with `allowed`
+    fn fake_api(annotations: Value, allowed: bool) -> FakeApi {
+        FakeApi::ok(move |request| {
+            if request.method == Method::POST && request.path == SUBJECT_ACCESS_REVIEWS {
+                let mut review = request.body.clone();
+                review["status"] = if allowed {
+                    json!({"allowed": true})
+                } else {
+                    json!({"allowed": false, "reason": "no RBAC policy matched"})
+                };
+                return review;
+            }
+            assert_eq!(
+                request.path,
+                "/apis/apps/v1/namespaces/production/deployments/web"
+            );
+            json!({
+                "apiVersion": "meta.k8s.io/v1",
+                "kind": "PartialObjectMetadata",
+                "metadata": {
+                    "name": "web",
+                    "namespace": "production",
+                    "annotations": annotations
+                }
+            })
+        })
+    }
+
+    const SUBJECT_ACCESS_REVIEWS: &str = "/apis/authorization.k8s.io/v1/subjectaccessreviews";
+
+    /// SubjectAccessReviews sent to the fake API server
+    fn reviews(api: &FakeApi) -> Vec<Value> {
+        api.bodies(Method::POST, SUBJECT_ACCESS_REVIEWS)
+    }
+
+    fn identity() -> CallerIdentity {
+        CallerIdentity {
+            user: "alice".to_string(),
+            groups: vec!["sre".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_identity_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(CallerIdentity::from_headers(&headers), None);
+
+        headers.insert(REMOTE_USER_HEADER, HeaderValue::from_static("alice"));
+        headers.append(REMOTE_GROUPS_HEADER, HeaderValue::from_static("sre, dev"));
+        headers.append(REMOTE_GROUPS_HEADER, HeaderValue::from_static("oncall"));
+        assert_eq!(
+            CallerIdentity::from_headers(&headers),
+            Some(CallerIdentity {
+                user: "alice".to_string(),
+                groups: vec!["sre".to_string(), "dev".to_string(), "oncall".to_string()],
+            })
+        );
+
+        headers.insert(REMOTE_USER_HEADER, HeaderValue::from_static(" "));
+        assert_eq!(CallerIdentity::from_headers(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_allowed_when_sar_allows() {
+        let api = fake_api(
+            json!({(annotations::APPROVED_BY_ROLE): "senior-engineer"}),
+            true,
+        );
+
+        check_approval_access(&api.client(), Some(&identity()), &update_request())
+            .await
+            .unwrap();
+
+        let reviews = reviews(&api);
+        assert_eq!(reviews.len(), 1);
+        let spec = &reviews[0]["spec"];
+        assert_eq!(spec["user"], "alice");
+        assert_eq!(spec["groups"], json!(["sre"]));
+        assert_eq!(
+            spec["resourceAttributes"],
+            json!({
+                "group": "rbac.authorization.k8s.io",
+                "resource": "roles",
+                "verb": "update",
+                "name": "senior-engineer",
+                "namespace": "production"
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_denied_when_sar_denies() {
+        let api = fake_api(
+            json!({(annotations::APPROVED_BY_ROLE): "senior-engineer"}),
+            false,
+        );
+        let before = APPROVAL_RBAC_DENIALS_TOTAL.get();
+
+        let err = check_approval_access(&api.client(), Some(&identity()), &update_request())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            err.to_string(),
+            "alice may not approve: requires role senior-engineer (no RBAC policy matched)"
+        );
+        assert!(APPROVAL_RBAC_DENIALS_TOTAL.get() > before);
+    }
+
+    #[tokio::test]
+    async fn test_identity_required_only_with_role() {
+        let api = fake_api(
+            json!({(annotations::APPROVED_BY_ROLE): "senior-engineer"}),
+            true,
+        );
+        let err = check_approval_access(&api.client(), None, &update_request())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ApprovalAccessError::MissingIdentity(_)));
+        assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+        assert!(reviews(&api).is_empty());
+
+        let api = fake_api(json!({}), false);
+        check_approval_access(&api.client(), None, &update_request())
+            .await
+            .unwrap();
+        assert!(reviews(&api).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batch_approval_requires_role() {
+        let batch: BatchUpdateRequest = serde_json::from_value(json!({
+            "apiVersion": "headwind.sh/v1alpha1",
+            "kind": "BatchUpdateRequest",
+            "metadata": { "name": "deployment-web-batch", "namespace": "production" },
+            "spec": {
+                "targetRef": {
+                    "apiVersion": "apps/v1",
+                    "kind": "Deployment",
+                    "name": "web",
+                    "namespace": "production"
+                },
+                "updates": [],
+                "policy": "minor"
+            }
+        }))
+        .unwrap();
+
+        let api = fake_api(
+            json!({(annotations::APPROVED_BY_ROLE): "senior-engineer"}),
+            false,
+        );
+        let err = check_batch_approval_access(&api.client(), Some(&identity()), &batch)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ApprovalAccessError::Denied { .. }));
+
+        let api = fake_api(
+            json!({(annotations::APPROVED_BY_ROLE): "senior-engineer"}),
+            true,
+        );
+        check_batch_approval_access(&api.client(), Some(&identity()), &batch)
+            .await
+            .unwrap();
+        assert_eq!(reviews(&api).len(), 1);
+    }
+}
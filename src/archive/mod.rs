@@ -0,0 +1,58 @@
+//! Long-term archives of UpdateRequests.
+//!
+//! Finished UpdateRequests are deleted by the cleanup controller after the
+//! retention period. An [`Archiver`] stores each one first, so the update
+//! history outlives the cluster's copy; see [`s3::S3Archiver`].
+
+pub mod s3;
+
+use crate::models::crd::UpdateRequest;
+use anyhow::Result;
+use async_trait::async_trait;
+use flate2::{Compression, write::GzEncoder};
+use std::io::Write;
+
+pub use s3::S3Archiver;
+
+/// Store for UpdateRequests about to be deleted
+#[async_trait]
+pub trait Archiver: Send + Sync {
+    /// Store `ur` and return where it was stored
+    async fn archive(&self, ur: &UpdateRequest) -> Result<String>;
+}
+
+/// `data` as a gzip stream
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .expect("writing to a Vec cannot fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        GzDecoder::new(data).read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_gzip_roundtrip() {
+        let line = "{\"kind\":\"UpdateRequest\",\"spec\":{\"newImage\":\"nginx:1.26.0\"}}\n";
+        let json = line.repeat(1000);
+        let binary: Vec<u8> = (0..70_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        for data in [&b""[..], b"a", b"aaaaaaaaaa", json.as_bytes(), &binary] {
+            let gz = gzip(data);
+            assert_eq!(&gz[..3], &[0x1f, 0x8b, 8]);
+            assert_eq!(gunzip(&gz), data);
+        }
+        // Repetitive JSON compresses well
+        assert!(gzip(json.as_bytes()).len() * 20 < json.len());
+    }
+}
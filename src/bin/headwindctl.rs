@@ -0,0 +1,23 @@
+use headwind::cli::{self, Cli};
+use std::process::ExitCode;
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = match Cli::parse(&args) {
+        Ok(cli) => cli::run(cli).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        },
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            ExitCode::FAILURE
+        },
+    }
+}
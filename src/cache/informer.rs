@@ -0,0 +1,157 @@
+//! Shared in-memory cache of workloads.
+//!
+//! Webhook handlers, the registry poller and the metrics gauges all need the
+//! full list of Deployments, StatefulSets, DaemonSets and ReplicaSets. Rather than listing
+//! them from the API server on every event, a reflector per kind keeps a
+//! `Store` up to date from a single watch.
+
+use crate::controller::watched_api;
+use crate::metrics::STORE_SIZE;
+use anyhow::Result;
+use futures::StreamExt;
+use k8s_openapi::NamespaceResourceScope;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use kube::{
+    Api, Client, Resource,
+    runtime::{
+        WatchStreamExt, reflector,
+        reflector::Store,
+        watcher::{Config, watcher},
+    },
+};
+use once_cell::sync::OnceCell;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+static SHARED_CACHE: OnceCell<SharedInformerCache> = OnceCell::new();
+
+/// Reflector stores for the workload kinds headwind manages
+pub struct SharedInformerCache {
+    deployments: Store<Deployment>,
+    statefulsets: Store<StatefulSet>,
+    daemonsets: Store<DaemonSet>,
+    replicasets: Store<ReplicaSet>,
+}
+
+impl SharedInformerCache {
+    /// Start the watchers and install the cache globally. Calling this more
+    /// than once returns the cache created by the first call.
+    pub fn init(client: Client) -> &'static SharedInformerCache {
+        SHARED_CACHE.get_or_init(|| {
+            info!("Starting shared informer cache");
+            Self {
+                deployments: spawn_reflector(watched_api(client.clone()), "Deployment"),
+                statefulsets: spawn_reflector(watched_api(client.clone()), "StatefulSet"),
+                daemonsets: spawn_reflector(watched_api(client.clone()), "DaemonSet"),
+                replicasets: spawn_reflector(watched_api(client), "ReplicaSet"),
+            }
+        })
+    }
+
+    pub fn get_all_deployments(&self) -> Vec<Arc<Deployment>> {
+        self.deployments.state()
+    }
+
+    pub fn get_all_statefulsets(&self) -> Vec<Arc<StatefulSet>> {
+        self.statefulsets.state()
+    }
+
+    pub fn get_all_daemonsets(&self) -> Vec<Arc<DaemonSet>> {
+        self.daemonsets.state()
+    }
+
+    pub fn get_all_replicasets(&self) -> Vec<Arc<ReplicaSet>> {
+        self.replicasets.state()
+    }
+}
+
+/// The cache started by `start_controllers`, if any
+pub fn shared_cache() -> Option<&'static SharedInformerCache> {
+    SHARED_CACHE.get()
+}
+
+/// Run a reflector for `api` in the background and return its store
+fn spawn_reflector<K>(api: Api<K>, kind: &'static str) -> Store<K>
+where
+    K: Resource + Clone + DeserializeOwned + Debug + Send + Sync + 'static,
+    K::DynamicType: Eq + std::hash::Hash + Clone + Default,
+{
+    let (reader, writer) = reflector::store();
+    let store = reader.clone();
+
+    tokio::spawn(async move {
+        reflector(writer, watcher(api, Config::default()))
+            .default_backoff()
+            .for_each(|event| {
+                if let Err(e) = event {
+                    warn!("{} informer watch error: {}", kind, e);
+                }
+                STORE_SIZE
+                    .with_label_values(&[kind])
+                    .set(store.len() as i64);
+                std::future::ready(())
+            })
+            .await;
+        warn!("{} informer stream ended", kind);
+    });
+
+    reader
+}
+
+/// Workload kinds held by the shared cache
+pub trait CachedResource:
+    Resource<Scope = NamespaceResourceScope, DynamicType = ()>
+    + Clone
+    + DeserializeOwned
+    + Debug
+    + 'static
+{
+    fn store(cache: &SharedInformerCache) -> &Store<Self>;
+}
+
+impl CachedResource for Deployment {
+    fn store(cache: &SharedInformerCache) -> &Store<Self> {
+        &cache.deployments
+    }
+}
+
+impl CachedResource for StatefulSet {
+    fn store(cache: &SharedInformerCache) -> &Store<Self> {
+        &cache.statefulsets
+    }
+}
+
+impl CachedResource for DaemonSet {
+    fn store(cache: &SharedInformerCache) -> &Store<Self> {
+        &cache.daemonsets
+    }
+}
+
+impl CachedResource for ReplicaSet {
+    fn store(cache: &SharedInformerCache) -> &Store<Self> {
+        &cache.replicasets
+    }
+}
+
+/// How long `list_all` waits for the initial sync before falling back to the API
+const SYNC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// List every watched resource of kind `K`, from the shared cache once it has
+/// synced, or from the API server when the cache is not running or not synced
+/// yet
+pub async fn list_all<K: CachedResource>(client: &Client) -> Result<Vec<Arc<K>>> {
+    if let Some(cache) = shared_cache() {
+        let store = K::store(cache);
+        match tokio::time::timeout(SYNC_TIMEOUT, store.wait_until_ready()).await {
+            Ok(Ok(())) => return Ok(store.state()),
+            _ => debug!("Informer cache not ready, listing from the API server"),
+        }
+    }
+
+    let api: Api<K> = watched_api(client.clone());
+    let list = api.list(&Default::default()).await?;
+    Ok(list.items.into_iter().map(Arc::new).collect())
+}
@@ -0,0 +1,3 @@
+pub mod informer;
+
+pub use informer::*;
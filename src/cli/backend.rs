@@ -0,0 +1,494 @@
+//! Where `headwindctl` sends its requests: Headwind's REST API, or the
+//! Kubernetes API directly when no API URL is configured.
+
+use crate::config::HeadwindConfig;
+use crate::controller::update_deployment_image_with_tracking;
+use crate::metrics::status::SystemStatus;
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use crate::models::policy::UpdatePolicy;
+use crate::polling::{
+    PollingConfig, RegistryPoller, RegistryTlsConfig, SimulationResult,
+    insecure_registries_from_env,
+};
+use crate::rollback::manual::{rollback_to_image, rollback_to_previous_image};
+use crate::rollback::{RollbackManager, UpdateHistory};
+use crate::ui::export::backup_line;
+use crate::ui::import::{ImportSummary, import_update_requests};
+use crate::ui::routes::REMOTE_USER_HEADER;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
+use kube::api::{Patch, PatchParams};
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::{Api, Client};
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+/// A rollback to perform; `container` None means the only container with history
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollbackTarget {
+    pub namespace: String,
+    pub deployment: String,
+    pub container: Option<String>,
+    pub index: usize,
+}
+
+/// A workload to roll back to its `headwind.sh/previous-image`, or with
+/// `--force` to `to_image`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceRollback {
+    /// Kubernetes kind, e.g. `Deployment`
+    pub kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub to_image: Option<String>,
+}
+
+pub enum Backend {
+    Api(ApiClient),
+    Kubernetes(Client),
+}
+
+impl Backend {
+    /// The REST API at `url` if given, otherwise Kubernetes using `kubeconfig`
+    /// or the default configuration
+    pub async fn connect(url: Option<String>, kubeconfig: Option<String>) -> Result<Self> {
+        if let Some(url) = url {
+            return Ok(Self::Api(ApiClient::new(&url)));
+        }
+
+        let client = match kubeconfig {
+            Some(path) => {
+                let kubeconfig = Kubeconfig::read_from(&path)
+                    .with_context(|| format!("Failed to read kubeconfig {}", path))?;
+                let config =
+                    kube::Config::from_custom_kubeconfig(kubeconfig, &KubeConfigOptions::default())
+                        .await?;
+                Client::try_from(config)?
+            },
+            None => Client::try_default().await.context(
+                "No API URL set (--url or HEADWIND_API_URL) and no Kubernetes configuration found",
+            )?,
+        };
+        Ok(Self::Kubernetes(client))
+    }
+
+    pub async fn list_updates(&self) -> Result<Vec<UpdateRequest>> {
+        match self {
+            Self::Api(api) => api.get("/api/v1/updates").await,
+            Self::Kubernetes(client) => {
+                let api: Api<UpdateRequest> = Api::all(client.clone());
+                Ok(api.list(&Default::default()).await?.items)
+            },
+        }
+    }
+
+    pub async fn status(&self) -> Result<SystemStatus> {
+        match self {
+            Self::Api(api) => api.get("/api/v1/status").await,
+            // Only the running operator knows its controllers and notifiers
+            Self::Kubernetes(_) => {
+                bail!("status requires the Headwind API: set --url or HEADWIND_API_URL")
+            },
+        }
+    }
+
+    pub async fn approve(
+        &self,
+        namespace: &str,
+        name: &str,
+        approver: &str,
+        reason: Option<&str>,
+    ) -> Result<Value> {
+        match self {
+            Self::Api(api) => {
+                api.post(
+                    &format!("/api/v1/updates/{}/{}/approve", namespace, name),
+                    &json!({"approver": approver, "reason": reason}),
+                )
+                .await
+            },
+            // Approved updates are applied by the operator, which only the API reaches
+            Self::Kubernetes(_) => {
+                bail!("Approving requires the Headwind API: set --url or HEADWIND_API_URL")
+            },
+        }
+    }
+
+    pub async fn reject(
+        &self,
+        namespace: &str,
+        name: &str,
+        approver: &str,
+        reason: &str,
+    ) -> Result<Value> {
+        match self {
+            Self::Api(api) => {
+                api.post(
+                    &format!("/api/v1/updates/{}/{}/reject", namespace, name),
+                    &json!({"approver": approver, "reason": reason}),
+                )
+                .await
+            },
+            Self::Kubernetes(client) => {
+                let api: Api<UpdateRequest> = Api::namespaced(client.clone(), namespace);
+                let update_request = api.get(name).await?;
+                if let Some(status) = &update_request.status
+                    && status.phase != UpdatePhase::Pending
+                {
+                    bail!(
+                        "UpdateRequest is in {:?} state, cannot reject",
+                        status.phase
+                    );
+                }
+
+                let now = Utc::now();
+                let patch = json!({
+                    "apiVersion": "headwind.sh/v1alpha1",
+                    "kind": "UpdateRequest",
+                    "status": {
+                        "phase": UpdatePhase::Rejected,
+                        "rejectedBy": approver,
+                        "rejectedAt": now,
+                        "message": reason,
+                        "lastUpdated": now
+                    }
+                });
+                api.patch_status(name, &PatchParams::default(), &Patch::Merge(&patch))
+                    .await?;
+                Ok(json!({"message": "Update rejected", "namespace": namespace, "name": name}))
+            },
+        }
+    }
+
+    pub async fn simulate(
+        &self,
+        namespace: Option<&str>,
+        policy: Option<&str>,
+    ) -> Result<Vec<SimulationResult>> {
+        match self {
+            Self::Api(api) => {
+                let mut query = Vec::new();
+                if let Some(namespace) = namespace {
+                    query.push(("namespace", namespace));
+                }
+                if let Some(policy) = policy {
+                    query.push(("policy", policy));
+                }
+                api.send(api.http.get(api.url("/api/v1/simulate")).query(&query))
+                    .await
+            },
+            Self::Kubernetes(client) => {
+                let policy = policy
+                    .map(|p| p.parse::<UpdatePolicy>())
+                    .transpose()
+                    .map_err(|e| anyhow!("Invalid policy: {}", e))?;
+                // Events are never sent during a simulation
+                let (event_sender, _) = tokio::sync::mpsc::unbounded_channel();
+                let (chart_event_sender, _) = tokio::sync::mpsc::unbounded_channel();
+                let poller = RegistryPoller::with_client(
+                    PollingConfig {
+                        insecure_registries: insecure_registries_from_env(),
+                        tls: RegistryTlsConfig::from_env()?,
+                        ..Default::default()
+                    },
+                    event_sender,
+                    chart_event_sender,
+                    client.clone(),
+                );
+                poller.simulate(namespace, policy).await
+            },
+        }
+    }
+
+    pub async fn rollback(&self, target: &RollbackTarget, user: &str) -> Result<Value> {
+        let container = match &target.container {
+            Some(container) => container.clone(),
+            None => only_container(&self.rollback_history(target).await?)?,
+        };
+
+        match self {
+            Self::Api(api) => {
+                api.post(
+                    &format!(
+                        "/api/v1/rollback/{}/{}",
+                        target.namespace, target.deployment
+                    ),
+                    &json!({"container": container, "index": target.index, "user": user}),
+                )
+                .await
+            },
+            Self::Kubernetes(client) => {
+                let image = RollbackManager::new(client.clone())
+                    .get_image_by_index(
+                        &target.deployment,
+                        &target.namespace,
+                        &container,
+                        target.index,
+                    )
+                    .await?
+                    .ok_or_else(|| anyhow!("No history entry found at index {}", target.index))?;
+                update_deployment_image_with_tracking(
+                    client.clone(),
+                    &target.namespace,
+                    &target.deployment,
+                    &container,
+                    &image,
+                    None,
+                    Some(user.to_string()),
+                )
+                .await?;
+                Ok(json!({
+                    "message": "Rollback successful",
+                    "deployment": target.deployment,
+                    "namespace": target.namespace,
+                    "container": container,
+                    "image": image
+                }))
+            },
+        }
+    }
+
+    pub async fn rollback_resource(&self, target: &ResourceRollback, user: &str) -> Result<Value> {
+        match self {
+            Self::Api(api) => {
+                let body = match &target.to_image {
+                    Some(image) => json!({"force": true, "toImage": image}),
+                    None => json!({}),
+                };
+                api.send(
+                    api.http
+                        .post(api.url(&format!(
+                            "/api/v1/rollback/{}/{}/{}",
+                            target.namespace, target.kind, target.name
+                        )))
+                        .header(REMOTE_USER_HEADER, user)
+                        .json(&body),
+                )
+                .await
+            },
+            Self::Kubernetes(client) => {
+                let rollback = match &target.to_image {
+                    Some(image) => {
+                        rollback_to_image(
+                            client,
+                            &target.kind,
+                            &target.namespace,
+                            &target.name,
+                            image,
+                            user,
+                        )
+                        .await?
+                    },
+                    None => {
+                        rollback_to_previous_image(
+                            client,
+                            &target.kind,
+                            &target.namespace,
+                            &target.name,
+                            user,
+                        )
+                        .await?
+                    },
+                };
+                Ok(serde_json::to_value(rollback)?)
+            },
+        }
+    }
+
+    async fn rollback_history(&self, target: &RollbackTarget) -> Result<UpdateHistory> {
+        match self {
+            Self::Api(api) => {
+                api.get(&format!(
+                    "/api/v1/rollback/{}/{}",
+                    target.namespace, target.deployment
+                ))
+                .await
+            },
+            Self::Kubernetes(client) => {
+                RollbackManager::new(client.clone())
+                    .get_history(&target.deployment, &target.namespace)
+                    .await
+            },
+        }
+    }
+
+    /// Current settings as the JSON the settings API serves
+    pub async fn get_config(&self) -> Result<Value> {
+        match self {
+            Self::Api(api) => api.get("/api/v1/settings").await,
+            Self::Kubernetes(client) => {
+                let config = HeadwindConfig::load(client.clone())
+                    .await
+                    .map_err(|e| anyhow!("Failed to load configuration: {}", e))?;
+                Ok(serde_json::to_value(config)?)
+            },
+        }
+    }
+
+    /// All UpdateRequests, or those in `namespace`, as `json-lines` export
+    pub async fn export(&self, namespace: Option<&str>) -> Result<String> {
+        match self {
+            Self::Api(api) => {
+                let mut query = vec![("format", "json-lines")];
+                if let Some(namespace) = namespace {
+                    query.push(("namespace", namespace));
+                }
+                api.send_text(
+                    api.http
+                        .get(api.url("/api/v1/update-requests/export"))
+                        .query(&query),
+                )
+                .await
+            },
+            Self::Kubernetes(client) => {
+                let api: Api<UpdateRequest> = match namespace {
+                    Some(namespace) => Api::namespaced(client.clone(), namespace),
+                    None => Api::all(client.clone()),
+                };
+                let mut update_requests = api.list(&Default::default()).await?.items;
+                update_requests.sort_by_key(|ur| ur.metadata.creation_timestamp.clone());
+                Ok(update_requests.iter().filter_map(backup_line).collect())
+            },
+        }
+    }
+
+    /// Recreate the UpdateRequests of a `json-lines` export
+    pub async fn import(&self, body: String) -> Result<ImportSummary> {
+        match self {
+            Self::Api(api) => {
+                api.send(
+                    api.http
+                        .post(api.url("/api/v1/update-requests/import"))
+                        .header("Content-Type", "application/x-ndjson")
+                        .body(body),
+                )
+                .await
+            },
+            Self::Kubernetes(client) => import_update_requests(client, &body).await,
+        }
+    }
+
+    pub async fn set_config(&self, config: Value) -> Result<Value> {
+        let config: HeadwindConfig =
+            serde_json::from_value(config).context("Invalid configuration")?;
+        match self {
+            Self::Api(api) => {
+                api.send(api.http.put(api.url("/api/v1/settings")).json(&config))
+                    .await
+            },
+            Self::Kubernetes(client) => {
+                config
+                    .save(client.clone())
+                    .await
+                    .map_err(|e| anyhow!("Failed to save configuration: {}", e))?;
+                Ok(json!({"message": "Configuration updated successfully"}))
+            },
+        }
+    }
+}
+
+/// The container of a history that covers exactly one container
+fn only_container(history: &UpdateHistory) -> Result<String> {
+    let mut containers: Vec<&str> = history
+        .entries()
+        .iter()
+        .map(|e| e.container.as_str())
+        .collect();
+    containers.sort_unstable();
+    containers.dedup();
+
+    match containers.as_slice() {
+        [container] => Ok(container.to_string()),
+        [] => bail!("No rollback history recorded for this deployment"),
+        many => bail!(
+            "Deployment has history for several containers ({}), pass --container",
+            many.join(", ")
+        ),
+    }
+}
+
+/// Client for Headwind's REST API
+pub struct ApiClient {
+    base_url: String,
+    http: reqwest::Client,
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        self.send(self.http.get(self.url(path))).await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str, body: &Value) -> Result<T> {
+        self.send(self.http.post(self.url(path)).json(body)).await
+    }
+
+    async fn send<T: DeserializeOwned>(&self, builder: reqwest::RequestBuilder) -> Result<T> {
+        let body: Value =
+            serde_json::from_str(&self.send_text(builder).await?).unwrap_or(Value::Null);
+        serde_json::from_value(body).context("Unexpected response from Headwind API")
+    }
+
+    /// Body of a successful response as text
+    async fn send_text(&self, builder: reqwest::RequestBuilder) -> Result<String> {
+        let response = builder
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach Headwind API at {}", self.base_url))?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+
+        if !status.is_success() {
+            let body: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+            bail!(
+                "Headwind API returned {}: {}",
+                status,
+                body.get("error")
+                    .and_then(|e| e.as_str())
+                    .unwrap_or("unknown error")
+            );
+        }
+
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rollback::UpdateHistoryEntry;
+
+    fn history(containers: &[&str]) -> UpdateHistory {
+        let mut history = UpdateHistory::new();
+        for container in containers {
+            history.add_entry(UpdateHistoryEntry {
+                container: container.to_string(),
+                image: format!("{}:1.0.0", container),
+                timestamp: Utc::now(),
+                update_request_name: None,
+                approved_by: None,
+            });
+        }
+        history
+    }
+
+    #[test]
+    fn test_only_container() {
+        assert_eq!(only_container(&history(&["web", "web"])).unwrap(), "web");
+
+        let err = only_container(&history(&["web", "sidecar"])).unwrap_err();
+        assert!(err.to_string().contains("sidecar, web"));
+
+        assert!(only_container(&history(&[])).is_err());
+    }
+}
@@ -0,0 +1,803 @@
+//! `headwindctl`: command-line client for inspecting and managing Headwind.
+//!
+//! Commands go to Headwind's REST API at `--url` or `HEADWIND_API_URL`. Without
+//! an API URL they talk to the Kubernetes API directly, using `--kubeconfig` or
+//! the default client configuration.
+
+pub mod backend;
+pub mod output;
+pub mod status;
+pub mod watch;
+
+use self::backend::{Backend, ResourceRollback, RollbackTarget};
+use self::output::{OutputFormat, Table, cell, render};
+use self::watch::WatchFilter;
+use crate::config::HeadwindConfig;
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use crate::polling::SimulationResult;
+use crate::ui::import::ImportSummary;
+use anyhow::{Context, Result, anyhow, bail};
+use kube::ResourceExt;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const USAGE: &str = "\
+Usage: headwindctl [--url URL] [--kubeconfig PATH] [--output table|json|yaml] <command>
+
+Commands:
+  list-updates [--namespace NS] [--status PHASE]   List UpdateRequests
+  watch [--namespace NS] [--filter status=PHASE]   Live table of UpdateRequests (Kubernetes only)
+  approve <namespace> <name> [--reason TEXT]       Approve and apply an update
+  reject <namespace> <name> --reason TEXT          Reject an update
+  simulate [--namespace NS] [--policy POLICY]      Show which updates the policies would apply
+  status [--watch]                                 Show controller, polling and notification health
+  rollback <namespace> <deployment> [--container NAME] [--index N]
+                                                   Roll a Deployment back (default: previous image)
+  rollback <kind>/<name> [--namespace NS] [--force --to-image IMAGE]
+                                                   Roll a workload back to its previous-image annotation
+  config get [KEY]                                 Show settings, or one dotted key
+  config set KEY VALUE                             Change one setting, e.g. polling.interval 600
+  config set KEY=VALUE... [--set KEY=VALUE] [--dry-run]
+                                                   Change several settings, or print the result only
+  export [--namespace NS]                          Write UpdateRequests as JSON Lines for a backup
+  import <FILE|->                                  Recreate UpdateRequests from an export
+
+Options:
+  --url URL          Headwind API URL (default: $HEADWIND_API_URL)
+  --kubeconfig PATH  Talk to Kubernetes directly when no API URL is set
+  -o, --output FMT   Output format: table (default), json or yaml; watch-json for watch";
+
+/// A parsed `headwindctl` subcommand
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    ListUpdates {
+        namespace: Option<String>,
+        status: Option<String>,
+    },
+    Watch {
+        namespace: Option<String>,
+        filter: WatchFilter,
+    },
+    Approve {
+        namespace: String,
+        name: String,
+        reason: Option<String>,
+    },
+    Reject {
+        namespace: String,
+        name: String,
+        reason: String,
+    },
+    Simulate {
+        namespace: Option<String>,
+        policy: Option<String>,
+    },
+    /// `watch` refreshes every 5 seconds until interrupted
+    Status {
+        watch: bool,
+    },
+    Rollback(RollbackTarget),
+    RollbackResource(ResourceRollback),
+    ConfigGet {
+        key: Option<String>,
+    },
+    /// `(dotted.key, value)` pairs applied in order
+    ConfigSet {
+        assignments: Vec<(String, String)>,
+        dry_run: bool,
+    },
+    Export {
+        namespace: Option<String>,
+    },
+    /// Path of the export to import, `-` for stdin
+    Import {
+        file: String,
+    },
+    Help,
+}
+
+/// Command line of `headwindctl`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub url: Option<String>,
+    pub kubeconfig: Option<String>,
+    pub output: OutputFormat,
+    pub command: Command,
+}
+
+/// Flags that take a value, with their short aliases
+const VALUE_FLAGS: &[(&str, Option<&str>)] = &[
+    ("url", None),
+    ("kubeconfig", None),
+    ("output", Some("o")),
+    ("namespace", Some("n")),
+    ("status", None),
+    ("filter", None),
+    ("reason", None),
+    ("policy", None),
+    ("container", None),
+    ("index", None),
+    ("set", None),
+    ("to-image", None),
+];
+
+/// Flags without a value
+const BOOL_FLAGS: &[&str] = &["dry-run", "force", "watch"];
+
+impl Cli {
+    /// Parse the arguments following the program name
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let mut flags: HashMap<&'static str, String> = HashMap::new();
+        let mut sets = Vec::new();
+        let mut positional = Vec::new();
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                positional.insert(0, "help".to_string());
+                continue;
+            }
+            let Some(flag) = arg.strip_prefix('-') else {
+                positional.push(arg.clone());
+                continue;
+            };
+
+            let (name, inline_value) = match flag.strip_prefix('-') {
+                Some(long) => match long.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (long, None),
+                },
+                None => (flag, None),
+            };
+            if let Some(bool_flag) = BOOL_FLAGS
+                .iter()
+                .find(|f| **f == name && inline_value.is_none())
+            {
+                flags.insert(bool_flag, "true".to_string());
+                continue;
+            }
+            let (key, _) = VALUE_FLAGS
+                .iter()
+                .find(|(long, short)| {
+                    (flag.starts_with('-') && *long == name) || *short == Some(name)
+                })
+                .ok_or_else(|| anyhow!("Unknown option {}", arg))?;
+            let value = match inline_value {
+                Some(value) => value,
+                None => args
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Option {} requires a value", arg))?,
+            };
+            // --set may be repeated
+            if *key == "set" {
+                sets.push(value);
+            } else {
+                flags.insert(key, value);
+            }
+        }
+
+        let url = flags.remove("url");
+        let kubeconfig = flags.remove("kubeconfig");
+        let output = flags
+            .remove("output")
+            .map(|o| o.parse())
+            .transpose()?
+            .unwrap_or_default();
+        let command = parse_command(&positional, &mut flags, &mut sets)?;
+
+        if let Some(flag) = flags.keys().next() {
+            bail!("Option --{} is not supported by this command", flag);
+        }
+        if !sets.is_empty() {
+            bail!("Option --set is not supported by this command");
+        }
+
+        Ok(Self {
+            url,
+            kubeconfig,
+            output,
+            command,
+        })
+    }
+}
+
+fn parse_command(
+    positional: &[String],
+    flags: &mut HashMap<&'static str, String>,
+    sets: &mut Vec<String>,
+) -> Result<Command> {
+    let args: Vec<&str> = positional.iter().map(String::as_str).collect();
+    let command = match args.as_slice() {
+        [] | ["help", ..] => Command::Help,
+        ["list-updates"] => Command::ListUpdates {
+            namespace: flags.remove("namespace"),
+            status: flags.remove("status"),
+        },
+        ["watch"] => Command::Watch {
+            namespace: flags.remove("namespace"),
+            filter: flags
+                .remove("filter")
+                .map(|f| WatchFilter::parse(&f))
+                .transpose()?
+                .unwrap_or_default(),
+        },
+        ["approve", namespace, name] => Command::Approve {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            reason: flags.remove("reason"),
+        },
+        ["reject", namespace, name] => Command::Reject {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+            reason: flags
+                .remove("reason")
+                .ok_or_else(|| anyhow!("reject requires --reason"))?,
+        },
+        ["simulate"] => Command::Simulate {
+            namespace: flags.remove("namespace"),
+            policy: flags.remove("policy"),
+        },
+        ["status"] => Command::Status {
+            watch: flags.remove("watch").is_some(),
+        },
+        ["rollback", namespace, deployment] => Command::Rollback(RollbackTarget {
+            namespace: namespace.to_string(),
+            deployment: deployment.to_string(),
+            container: flags.remove("container"),
+            index: flags
+                .remove("index")
+                .map(|i| i.parse().context("--index must be a number"))
+                .transpose()?
+                .unwrap_or(1),
+        }),
+        ["rollback", resource] => {
+            let (kind, name) = parse_resource(resource)?;
+            let force = flags.remove("force").is_some();
+            let to_image = flags.remove("to-image");
+            match (force, &to_image) {
+                (true, None) => bail!("--force requires --to-image"),
+                (false, Some(_)) => bail!("--to-image requires --force"),
+                _ => {},
+            }
+            Command::RollbackResource(ResourceRollback {
+                kind,
+                namespace: flags
+                    .remove("namespace")
+                    .unwrap_or_else(|| "default".to_string()),
+                name,
+                to_image,
+            })
+        },
+        ["config", "get"] => Command::ConfigGet { key: None },
+        ["config", "get", key] => Command::ConfigGet {
+            key: Some(key.to_string()),
+        },
+        ["config", "set", assignments @ ..] => {
+            let mut assignments = match assignments {
+                [key, value] if !key.contains('=') => vec![(key.to_string(), value.to_string())],
+                assignments => assignments
+                    .iter()
+                    .map(|a| parse_assignment(a))
+                    .collect::<Result<_>>()?,
+            };
+            for assignment in sets.drain(..) {
+                assignments.push(parse_assignment(&assignment)?);
+            }
+            if assignments.is_empty() {
+                bail!("config set requires KEY VALUE or KEY=VALUE");
+            }
+            Command::ConfigSet {
+                assignments,
+                dry_run: flags.remove("dry-run").is_some(),
+            }
+        },
+        ["export"] => Command::Export {
+            namespace: flags.remove("namespace"),
+        },
+        ["import", file] => Command::Import {
+            file: file.to_string(),
+        },
+        [command, ..] => bail!("Invalid arguments for {}\n\n{}", command, USAGE),
+    };
+    Ok(command)
+}
+
+/// `kind/name` as the Kubernetes kind and name, accepting kubectl's short names
+fn parse_resource(resource: &str) -> Result<(String, String)> {
+    let (kind, name) = resource
+        .split_once('/')
+        .filter(|(_, name)| !name.is_empty())
+        .ok_or_else(|| anyhow!("Invalid resource {}, expected KIND/NAME", resource))?;
+    let kind = match kind.to_lowercase().as_str() {
+        "deployment" | "deployments" | "deploy" => "Deployment",
+        "statefulset" | "statefulsets" | "sts" => "StatefulSet",
+        "daemonset" | "daemonsets" | "ds" => "DaemonSet",
+        "replicaset" | "replicasets" | "rs" => "ReplicaSet",
+        _ => bail!(
+            "Cannot roll back {}, expected deployment, statefulset, daemonset or replicaset",
+            kind
+        ),
+    };
+    Ok((kind.to_string(), name.to_string()))
+}
+
+/// `key=value` as `(key, value)`
+fn parse_assignment(assignment: &str) -> Result<(String, String)> {
+    match assignment.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => bail!("Invalid setting {}, expected KEY=VALUE", assignment),
+    }
+}
+
+/// Run the command and return what to print
+pub async fn run(cli: Cli) -> Result<String> {
+    if cli.command == Command::Help {
+        return Ok(USAGE.to_string());
+    }
+
+    let url = cli
+        .url
+        .or_else(|| std::env::var("HEADWIND_API_URL").ok())
+        .filter(|u| !u.is_empty());
+    let backend = Backend::connect(url, cli.kubeconfig).await?;
+    if cli.output == OutputFormat::WatchJson && !matches!(cli.command, Command::Watch { .. }) {
+        bail!("--output watch-json is only supported by watch");
+    }
+    let user = std::env::var("USER").unwrap_or_else(|_| "headwindctl".to_string());
+    let format = cli.output;
+
+    match cli.command {
+        Command::ListUpdates { namespace, status } => {
+            let updates: Vec<UpdateRequest> = backend
+                .list_updates()
+                .await?
+                .into_iter()
+                .filter(|ur| namespace.is_none() || ur.namespace() == namespace)
+                .filter(|ur| {
+                    status
+                        .as_deref()
+                        .is_none_or(|s| phase_name(ur).eq_ignore_ascii_case(s))
+                })
+                .collect();
+            render(&updates, format, |updates| updates_table(updates))
+        },
+        Command::Watch { namespace, filter } => {
+            let Backend::Kubernetes(client) = backend else {
+                bail!("watch talks to Kubernetes directly; unset --url and HEADWIND_API_URL");
+            };
+            if !matches!(format, OutputFormat::Table | OutputFormat::WatchJson) {
+                bail!("watch supports --output table or watch-json");
+            }
+            watch::watch(client, namespace.as_deref(), filter, format).await?;
+            Ok(String::new())
+        },
+        Command::Approve {
+            namespace,
+            name,
+            reason,
+        } => message(
+            &backend
+                .approve(&namespace, &name, &user, reason.as_deref())
+                .await?,
+            format,
+        ),
+        Command::Reject {
+            namespace,
+            name,
+            reason,
+        } => message(
+            &backend.reject(&namespace, &name, &user, &reason).await?,
+            format,
+        ),
+        Command::Simulate { namespace, policy } => {
+            let results = backend
+                .simulate(namespace.as_deref(), policy.as_deref())
+                .await?;
+            render(&results, format, |results| simulation_table(results))
+        },
+        Command::Status { watch: false } => status::render_status(&backend.status().await?, format),
+        Command::Status { watch: true } => {
+            status::watch_status(&backend, format).await?;
+            Ok(String::new())
+        },
+        Command::Rollback(target) => message(&backend.rollback(&target, &user).await?, format),
+        Command::RollbackResource(target) => {
+            rollback_message(&backend.rollback_resource(&target, &user).await?, format)
+        },
+        Command::ConfigGet { key } => {
+            let config = backend.get_config().await?;
+            let value = match &key {
+                Some(key) => get_path(&config, key)?.clone(),
+                None => config,
+            };
+            render(&value, format, |value| config_table(key.as_deref(), value))
+        },
+        Command::ConfigSet {
+            assignments,
+            dry_run,
+        } => {
+            let mut config = backend.get_config().await?;
+            for (key, value) in &assignments {
+                set_path(&mut config, key, value)?;
+            }
+            let config = validate_config(config)?;
+            if dry_run {
+                return render(&config, format, |config| config_table(None, config));
+            }
+            message(&backend.set_config(config).await?, format)
+        },
+        // Always JSON Lines, the format import reads
+        Command::Export { namespace } => Ok(backend
+            .export(namespace.as_deref())
+            .await?
+            .trim_end()
+            .to_string()),
+        Command::Import { file } => {
+            let body = if file == "-" {
+                std::io::read_to_string(std::io::stdin()).context("Failed to read stdin")?
+            } else {
+                std::fs::read_to_string(&file)
+                    .with_context(|| format!("Failed to read {}", file))?
+            };
+            let summary = backend.import(body).await?;
+            render(&summary, format, import_table)
+        },
+        Command::Help => unreachable!("handled above"),
+    }
+}
+
+/// Phase of an UpdateRequest, `Pending` when it has no status yet
+fn phase_name(update_request: &UpdateRequest) -> String {
+    let phase = update_request
+        .status
+        .as_ref()
+        .map(|s| s.phase.clone())
+        .unwrap_or(UpdatePhase::Pending);
+    format!("{:?}", phase)
+}
+
+fn updates_table(updates: &[UpdateRequest]) -> Table {
+    let mut table = Table::new(&["NAMESPACE", "NAME", "TARGET", "CURRENT", "NEW", "PHASE"]);
+    for ur in updates {
+        table.add_row(vec![
+            ur.namespace().unwrap_or_default(),
+            ur.name_any(),
+            format!("{}/{}", ur.spec.target_ref.kind, ur.spec.target_ref.name),
+            ur.spec.current_image.clone(),
+            ur.spec.new_image.clone(),
+            phase_name(ur),
+        ]);
+    }
+    table
+}
+
+fn import_table(summary: &ImportSummary) -> Table {
+    let mut table = Table::new(&["RESULT", "DETAIL"]);
+    table.add_row(vec!["imported".to_string(), summary.imported.to_string()]);
+    table.add_row(vec!["skipped".to_string(), summary.skipped.to_string()]);
+    for error in &summary.errors {
+        table.add_row(vec!["error".to_string(), error.clone()]);
+    }
+    table
+}
+
+fn simulation_table(results: &[SimulationResult]) -> Table {
+    let mut table = Table::new(&["NAMESPACE", "NAME", "CURRENT", "LATEST", "UPDATE", "REASON"]);
+    for result in results {
+        table.add_row(vec![
+            result.namespace.clone(),
+            result.name.clone(),
+            result.current_image.clone(),
+            cell(result.latest_available.as_deref()),
+            if result.would_update { "yes" } else { "no" }.to_string(),
+            cell(result.policy_rejection_reason.as_deref()),
+        ]);
+    }
+    table
+}
+
+fn config_table(key: Option<&str>, value: &Value) -> Table {
+    let mut rows = Vec::new();
+    flatten(key.unwrap_or_default(), value, &mut rows);
+
+    let mut table = Table::new(&["KEY", "VALUE"]);
+    for (key, value) in rows {
+        table.add_row(vec![key, value]);
+    }
+    table
+}
+
+/// `(dotted.key, value)` for every scalar under `value`
+fn flatten(prefix: &str, value: &Value, rows: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, rows);
+            }
+        },
+        Value::String(s) => rows.push((prefix.to_string(), s.clone())),
+        Value::Null => rows.push((prefix.to_string(), "-".to_string())),
+        other => rows.push((prefix.to_string(), other.to_string())),
+    }
+}
+
+fn get_path<'a>(config: &'a Value, key: &str) -> Result<&'a Value> {
+    key.split('.')
+        .try_fold(config, |value, part| value.get(part))
+        .ok_or_else(|| anyhow!("Unknown setting {}", key))
+}
+
+/// Replace the value at `key`, parsing `raw` according to the current value's type
+fn set_path(config: &mut Value, key: &str, raw: &str) -> Result<()> {
+    let target = key
+        .split('.')
+        .try_fold(config, |value, part| value.get_mut(part))
+        .ok_or_else(|| anyhow!("Unknown setting {}", key))?;
+
+    *target = match target {
+        Value::String(_) | Value::Null => Value::String(raw.to_string()),
+        Value::Bool(_) => Value::Bool(
+            raw.parse()
+                .with_context(|| format!("{} must be true or false", key))?,
+        ),
+        Value::Number(_) => Value::Number(
+            raw.parse()
+                .with_context(|| format!("{} must be a number", key))?,
+        ),
+        Value::Array(_) | Value::Object(_) => {
+            serde_json::from_str(raw).with_context(|| format!("{} must be a JSON value", key))?
+        },
+    };
+    Ok(())
+}
+
+/// `config` if it is a valid `HeadwindConfig`, normalized as the API serves it
+fn validate_config(config: Value) -> Result<Value> {
+    let config: HeadwindConfig = serde_json::from_value(config).context("Invalid configuration")?;
+    if config.polling.interval == 0 {
+        bail!("polling.interval must be at least 1 second");
+    }
+    Ok(serde_json::to_value(config)?)
+}
+
+/// Summary of a manual rollback in table output, the whole response otherwise
+fn rollback_message(response: &Value, format: OutputFormat) -> Result<String> {
+    render(response, format, |response| {
+        let field = |name| response.get(name).and_then(Value::as_str).unwrap_or("?");
+        let mut text = format!(
+            "Rolled back {} {}/{} container {} from {} to {}",
+            field("resourceKind"),
+            field("namespace"),
+            field("name"),
+            field("container"),
+            field("rolledBackFrom"),
+            field("rolledBackTo")
+        );
+        if let Some(update_request) = response.get("updateRequest").and_then(Value::as_str) {
+            text.push_str(&format!(" (UpdateRequest {})", update_request));
+        }
+        Table::new(&[text.as_str()])
+    })
+}
+
+/// The `message` of an API response in table output, the whole response otherwise
+fn message(response: &Value, format: OutputFormat) -> Result<String> {
+    render(response, format, |response| {
+        let text = response
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("OK");
+        Table::new(&[text])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(args: &[&str]) -> Result<Cli> {
+        Cli::parse(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        let cli = parse(&[
+            "--url",
+            "http://headwind:8081",
+            "list-updates",
+            "-n",
+            "prod",
+        ])
+        .unwrap();
+        assert_eq!(cli.url.as_deref(), Some("http://headwind:8081"));
+        assert_eq!(
+            cli.command,
+            Command::ListUpdates {
+                namespace: Some("prod".to_string()),
+                status: None
+            }
+        );
+
+        let cli = parse(&[
+            "watch",
+            "--namespace",
+            "production",
+            "--filter",
+            "status=Pending",
+            "-o",
+            "watch-json",
+        ])
+        .unwrap();
+        assert_eq!(cli.output, OutputFormat::WatchJson);
+        assert_eq!(
+            cli.command,
+            Command::Watch {
+                namespace: Some("production".to_string()),
+                filter: WatchFilter::parse("status=Pending").unwrap(),
+            }
+        );
+
+        let cli = parse(&[
+            "reject",
+            "default",
+            "web-1-2-0",
+            "--reason=too risky",
+            "-o",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(cli.output, OutputFormat::Json);
+        assert_eq!(
+            cli.command,
+            Command::Reject {
+                namespace: "default".to_string(),
+                name: "web-1-2-0".to_string(),
+                reason: "too risky".to_string()
+            }
+        );
+
+        let cli = parse(&["status", "--watch", "-o", "json"]).unwrap();
+        assert_eq!(cli.output, OutputFormat::Json);
+        assert_eq!(cli.command, Command::Status { watch: true });
+        assert_eq!(
+            parse(&["status"]).unwrap().command,
+            Command::Status { watch: false }
+        );
+
+        let cli = parse(&["rollback", "default", "web", "--container", "app"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Rollback(RollbackTarget {
+                namespace: "default".to_string(),
+                deployment: "web".to_string(),
+                container: Some("app".to_string()),
+                index: 1
+            })
+        );
+
+        let cli = parse(&[
+            "rollback",
+            "sts/db",
+            "-n",
+            "prod",
+            "--force",
+            "--to-image",
+            "postgres:15",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Command::RollbackResource(ResourceRollback {
+                kind: "StatefulSet".to_string(),
+                namespace: "prod".to_string(),
+                name: "db".to_string(),
+                to_image: Some("postgres:15".to_string())
+            })
+        );
+
+        assert_eq!(
+            parse(&["config", "set", "polling.interval", "600"])
+                .unwrap()
+                .command,
+            Command::ConfigSet {
+                assignments: vec![("polling.interval".to_string(), "600".to_string())],
+                dry_run: false
+            }
+        );
+        assert_eq!(
+            parse(&[
+                "config",
+                "set",
+                "notifications.slack.enabled=true",
+                "--set",
+                "polling.interval=600",
+                "--set=polling.enabled=true",
+                "--dry-run"
+            ])
+            .unwrap()
+            .command,
+            Command::ConfigSet {
+                assignments: vec![
+                    (
+                        "notifications.slack.enabled".to_string(),
+                        "true".to_string()
+                    ),
+                    ("polling.interval".to_string(), "600".to_string()),
+                    ("polling.enabled".to_string(), "true".to_string())
+                ],
+                dry_run: true
+            }
+        );
+        assert_eq!(
+            parse(&["export", "-n", "prod"]).unwrap().command,
+            Command::Export {
+                namespace: Some("prod".to_string())
+            }
+        );
+        assert_eq!(
+            parse(&["import", "backup.jsonl"]).unwrap().command,
+            Command::Import {
+                file: "backup.jsonl".to_string()
+            }
+        );
+        assert_eq!(parse(&[]).unwrap().command, Command::Help);
+        assert_eq!(
+            parse(&["simulate", "--help"]).unwrap().command,
+            Command::Help
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(&["reject", "default", "web"]).is_err());
+        assert!(parse(&["approve", "default"]).is_err());
+        assert!(parse(&["import"]).is_err());
+        assert!(parse(&["list-updates", "--bogus", "x"]).is_err());
+        assert!(parse(&["list-updates", "--reason", "x"]).is_err());
+        assert!(parse(&["list-updates", "--namespace"]).is_err());
+        assert!(parse(&["rollback", "default", "web", "--index", "x"]).is_err());
+        assert!(parse(&["rollback", "cronjob/web"]).is_err());
+        assert!(parse(&["rollback", "deployment/"]).is_err());
+        assert!(parse(&["rollback", "deployment/web", "--force"]).is_err());
+        assert!(parse(&["rollback", "deployment/web", "--to-image", "nginx:1"]).is_err());
+        assert!(parse(&["-o", "xml", "list-updates"]).is_err());
+        assert!(parse(&["config", "set"]).is_err());
+        assert!(parse(&["config", "set", "polling.interval"]).is_err());
+        assert!(parse(&["config", "set", "=600"]).is_err());
+        assert!(parse(&["list-updates", "--set", "polling.interval=600"]).is_err());
+        assert!(parse(&["list-updates", "--dry-run"]).is_err());
+        assert!(parse(&["watch", "--filter", "kind=Deployment"]).is_err());
+        assert!(parse(&["list-updates", "--filter", "status=Pending"]).is_err());
+    }
+
+    #[test]
+    fn test_config_paths() {
+        let mut config = json!({
+            "polling": {"enabled": false, "interval": 300},
+            "notifications": {"slack": {"channel": null}}
+        });
+
+        set_path(&mut config, "polling.interval", "600").unwrap();
+        set_path(&mut config, "polling.enabled", "true").unwrap();
+        set_path(&mut config, "notifications.slack.channel", "#ops").unwrap();
+        assert_eq!(config["polling"], json!({"enabled": true, "interval": 600}));
+        assert_eq!(
+            get_path(&config, "notifications.slack.channel").unwrap(),
+            "#ops"
+        );
+
+        assert!(set_path(&mut config, "polling.interval", "soon").is_err());
+        assert!(set_path(&mut config, "polling.unknown", "1").is_err());
+        assert!(get_path(&config, "missing").is_err());
+
+        let table = config_table(Some("polling"), &config["polling"]).render();
+        assert!(table.contains("polling.interval   600"));
+    }
+}
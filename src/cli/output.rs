@@ -0,0 +1,142 @@
+//! Rendering of command results as aligned tables, JSON or YAML.
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Output format selected with `--output`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    /// One JSON line per change, for `watch`
+    WatchJson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "yaml" => Ok(Self::Yaml),
+            "watch-json" => Ok(Self::WatchJson),
+            other => bail!(
+                "Unknown output format {} (expected table, json, yaml or watch-json)",
+                other
+            ),
+        }
+    }
+}
+
+/// Rows with a header, printed with columns padded to the widest cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new(header: &[&str]) -> Self {
+        Self {
+            header: header.iter().map(|h| h.to_string()).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn add_row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+
+    pub fn render(&self) -> String {
+        let mut widths: Vec<usize> = self.header.iter().map(String::len).collect();
+        for row in &self.rows {
+            for (i, cell) in row.iter().enumerate() {
+                if let Some(width) = widths.get_mut(i) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+        }
+
+        let line = |cells: &[String]| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("   ")
+                .trim_end()
+                .to_string()
+        };
+
+        let mut out = line(&self.header);
+        for row in &self.rows {
+            out.push('\n');
+            out.push_str(&line(row));
+        }
+        out
+    }
+}
+
+/// Render `value` in `format`, calling `table` only for table output
+pub fn render<T: Serialize>(
+    value: &T,
+    format: OutputFormat,
+    table: impl FnOnce(&T) -> Table,
+) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Table => table(value).render(),
+        OutputFormat::Json => serde_json::to_string_pretty(value)?,
+        OutputFormat::WatchJson => serde_json::to_string(value)?,
+        OutputFormat::Yaml => serde_yaml::to_string(value)?.trim_end().to_string(),
+    })
+}
+
+/// Cell text for an optional value
+pub fn cell(value: Option<&str>) -> String {
+    value.filter(|v| !v.is_empty()).unwrap_or("-").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_table_alignment() {
+        let mut table = Table::new(&["NAMESPACE", "NAME"]);
+        table.add_row(vec!["default".to_string(), "web".to_string()]);
+        table.add_row(vec!["kube-system".to_string(), "dns".to_string()]);
+
+        assert_eq!(
+            table.render(),
+            "NAMESPACE     NAME\ndefault       web\nkube-system   dns"
+        );
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let value = json!({"name": "web"});
+        let table = |_: &serde_json::Value| Table::new(&["NAME"]);
+
+        assert_eq!(
+            render(&value, OutputFormat::Json, table).unwrap(),
+            "{\n  \"name\": \"web\"\n}"
+        );
+        assert_eq!(
+            render(&value, OutputFormat::Yaml, table).unwrap(),
+            "name: web"
+        );
+        assert_eq!(render(&value, OutputFormat::Table, table).unwrap(), "NAME");
+    }
+
+    #[test]
+    fn test_output_format_from_str() {
+        assert_eq!("JSON".parse::<OutputFormat>().unwrap(), OutputFormat::Json);
+        assert_eq!("yaml".parse::<OutputFormat>().unwrap(), OutputFormat::Yaml);
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+}
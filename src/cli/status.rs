@@ -0,0 +1,196 @@
+//! `headwindctl status`: health of the Headwind process behind the API, as
+//! reported by `GET /api/v1/status`.
+//!
+//! With `--watch` the status is fetched again every 5 seconds, redrawing the
+//! table or writing one JSON line per refresh.
+
+use super::backend::Backend;
+use super::output::{OutputFormat, Table, render};
+use super::watch::{CLEAR_SCREEN, RESET};
+use crate::metrics::status::{ControllerState, SystemStatus};
+use anyhow::Result;
+use std::io::Write;
+use std::time::Duration;
+
+/// How often `--watch` refreshes
+pub const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+
+/// The status as a table of components, running controllers and healthy
+/// channels in green and the rest in red
+pub fn status_table(status: &SystemStatus) -> String {
+    let mut table = Table::new(&["COMPONENT", "STATUS"]);
+    let mut colors = Vec::new();
+    let mut add = |component: String, state: String, color: &'static str| {
+        table.add_row(vec![component, state]);
+        colors.push(color);
+    };
+
+    add("version".to_string(), status.version.clone(), "");
+    add("uptime".to_string(), uptime(status.uptime_seconds), "");
+    add("replica".to_string(), status.leader.clone(), "");
+    add(
+        "pending updates".to_string(),
+        status
+            .pending_updates
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| "unknown".to_string()),
+        "",
+    );
+    add(
+        "last poll cycle".to_string(),
+        match (status.last_poll_cycle, status.poll_cycle_duration_ms) {
+            (Some(at), Some(ms)) => format!("{} ({}ms)", at.format("%Y-%m-%d %H:%M:%S UTC"), ms),
+            _ => "never".to_string(),
+        },
+        "",
+    );
+    for (name, state) in &status.controllers {
+        let (text, color) = match state {
+            ControllerState::Running => ("running", GREEN),
+            ControllerState::Stopped => ("stopped", RED),
+        };
+        add(format!("controller/{}", name), text.to_string(), color);
+    }
+    for (channel, health) in &status.notifications {
+        let color = if health == "ok" { GREEN } else { RED };
+        add(format!("notification/{}", channel), health.clone(), color);
+    }
+
+    // Color whole lines after aligning, so escapes don't count as width
+    let mut lines = table
+        .render()
+        .lines()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    for (line, color) in lines.iter_mut().skip(1).zip(colors) {
+        if !color.is_empty() {
+            *line = format!("{}{}{}", color, line, RESET);
+        }
+    }
+    lines.join("\n")
+}
+
+/// Uptime like `2d 3h 12m`
+fn uptime(seconds: u64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m {}s", minutes, seconds % 60)
+    }
+}
+
+/// `status` in `format`
+pub fn render_status(status: &SystemStatus, format: OutputFormat) -> Result<String> {
+    match format {
+        OutputFormat::Table => Ok(status_table(status)),
+        format => render(status, format, |_| {
+            unreachable!("table output is handled above")
+        }),
+    }
+}
+
+/// Print the status every [`REFRESH_INTERVAL`] until interrupted
+pub async fn watch_status(backend: &Backend, format: OutputFormat) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    let mut interval = tokio::time::interval(REFRESH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let status = backend.status().await?;
+        match format {
+            OutputFormat::Table => writeln!(stdout, "{}{}", CLEAR_SCREEN, status_table(&status))?,
+            // One line per refresh, so the output can be streamed into jq
+            OutputFormat::Json | OutputFormat::WatchJson => {
+                writeln!(stdout, "{}", serde_json::to_string(&status)?)?
+            },
+            OutputFormat::Yaml => writeln!(stdout, "---\n{}", render_status(&status, format)?)?,
+        }
+        stdout.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::BTreeMap;
+
+    fn status() -> SystemStatus {
+        SystemStatus {
+            version: "0.2.0".to_string(),
+            uptime_seconds: 3 * 3600 + 12 * 60,
+            leader: "headwind-0".to_string(),
+            controllers: BTreeMap::from([
+                ("cleanup".to_string(), ControllerState::Stopped),
+                ("deployment".to_string(), ControllerState::Running),
+            ]),
+            last_poll_cycle: Some(chrono::Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()),
+            poll_cycle_duration_ms: Some(1250),
+            pending_updates: Some(3),
+            notifications: BTreeMap::from([
+                ("slack".to_string(), "ok".to_string()),
+                ("teams".to_string(), "error: HTTP 502".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_status_table() {
+        let table = status_table(&status());
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "COMPONENT               STATUS");
+        assert_eq!(lines[1], "version                 0.2.0");
+        assert_eq!(lines[2], "uptime                  3h 12m");
+        assert_eq!(lines[4], "pending updates         3");
+        assert_eq!(
+            lines[5],
+            "last poll cycle         2026-03-01 12:00:00 UTC (1250ms)"
+        );
+        // Colored after alignment
+        assert_eq!(lines[6], "\x1b[31mcontroller/cleanup      stopped\x1b[0m");
+        assert_eq!(lines[7], "\x1b[32mcontroller/deployment   running\x1b[0m");
+        assert_eq!(lines[8], "\x1b[32mnotification/slack      ok\x1b[0m");
+        assert_eq!(
+            lines[9],
+            "\x1b[31mnotification/teams      error: HTTP 502\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_status_before_first_poll() {
+        let status = SystemStatus {
+            last_poll_cycle: None,
+            poll_cycle_duration_ms: None,
+            pending_updates: None,
+            ..status()
+        };
+        let table = status_table(&status);
+        assert!(table.contains("last poll cycle         never"), "{}", table);
+        assert!(
+            table.contains("pending updates         unknown"),
+            "{}",
+            table
+        );
+    }
+
+    #[test]
+    fn test_uptime() {
+        assert_eq!(uptime(42), "0m 42s");
+        assert_eq!(uptime(3 * 3600 + 60), "3h 1m");
+        assert_eq!(uptime(2 * 86400 + 3 * 3600 + 12 * 60), "2d 3h 12m");
+    }
+
+    #[test]
+    fn test_render_status_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&render_status(&status(), OutputFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["controllers"]["deployment"], "running");
+        assert_eq!(json["notifications"]["teams"], "error: HTTP 502");
+        assert_eq!(json["pending_updates"], 3);
+    }
+}
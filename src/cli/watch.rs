@@ -0,0 +1,657 @@
+//! `headwindctl watch`: a live table of UpdateRequests fed by a Kubernetes
+//! watch, redrawn whenever one changes.
+//!
+//! Up/Down (or k/j) select a row, Enter opens its details, Esc or Backspace
+//! goes back to the table and q quits. With `--output watch-json` every change
+//! is written as one JSON line instead.
+
+use super::output::{OutputFormat, Table, cell};
+use super::phase_name;
+use crate::models::crd::UpdateRequest;
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use kube::runtime::watcher::{self, Event};
+use kube::{Api, Client, ResourceExt};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+pub(super) const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+pub(super) const RESET: &str = "\x1b[0m";
+const REVERSE: &str = "\x1b[7m";
+
+/// Rows shown by `watch`, from `--filter status=PHASE[,PHASE...]`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WatchFilter {
+    /// Phases to show, lowercase; empty shows every phase
+    statuses: Vec<String>,
+}
+
+impl WatchFilter {
+    pub fn parse(filter: &str) -> Result<Self> {
+        match filter.split_once('=') {
+            Some(("status", phases)) if !phases.is_empty() => Ok(Self {
+                statuses: phases
+                    .split(',')
+                    .map(|p| p.trim().to_lowercase())
+                    .filter(|p| !p.is_empty())
+                    .collect(),
+            }),
+            _ => bail!("Invalid filter {}, expected status=PHASE", filter),
+        }
+    }
+
+    pub fn matches(&self, update_request: &UpdateRequest) -> bool {
+        self.statuses.is_empty()
+            || self
+                .statuses
+                .contains(&phase_name(update_request).to_lowercase())
+    }
+}
+
+/// A key pressed while watching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Enter,
+    Back,
+    Quit,
+}
+
+/// UpdateRequests known from the watch, and what the user is looking at
+#[derive(Debug, Default)]
+pub struct WatchView {
+    filter: WatchFilter,
+    updates: BTreeMap<(String, String), UpdateRequest>,
+    /// UpdateRequests listed while the watch (re)starts, swapped in when done
+    relisting: Option<BTreeMap<(String, String), UpdateRequest>>,
+    selected: usize,
+    /// Namespace and name of the UpdateRequest whose details are shown
+    detail: Option<(String, String)>,
+}
+
+fn row_key(update_request: &UpdateRequest) -> (String, String) {
+    (
+        update_request.namespace().unwrap_or_default(),
+        update_request.name_any(),
+    )
+}
+
+impl WatchView {
+    pub fn new(filter: WatchFilter) -> Self {
+        Self {
+            filter,
+            ..Default::default()
+        }
+    }
+
+    /// Apply a watch event
+    pub fn apply(&mut self, event: Event<UpdateRequest>) {
+        match event {
+            Event::Apply(ur) => {
+                self.updates.insert(row_key(&ur), ur);
+            },
+            Event::Delete(ur) => {
+                self.updates.remove(&row_key(&ur));
+            },
+            Event::Init => self.relisting = Some(BTreeMap::new()),
+            Event::InitApply(ur) => {
+                self.relisting
+                    .get_or_insert_with(BTreeMap::new)
+                    .insert(row_key(&ur), ur);
+            },
+            Event::InitDone => {
+                if let Some(updates) = self.relisting.take() {
+                    self.updates = updates;
+                }
+            },
+        }
+        self.selected = self.selected.min(self.visible().len().saturating_sub(1));
+    }
+
+    /// Handle a key; false when the user quit
+    pub fn key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Quit => return false,
+            Key::Up => self.selected = self.selected.saturating_sub(1),
+            Key::Down => {
+                self.selected = (self.selected + 1).min(self.visible().len().saturating_sub(1))
+            },
+            Key::Enter => self.detail = self.visible().get(self.selected).map(|ur| row_key(ur)),
+            Key::Back => self.detail = None,
+        }
+        true
+    }
+
+    /// UpdateRequests passing the filter, by namespace and name
+    pub fn visible(&self) -> Vec<&UpdateRequest> {
+        self.updates
+            .values()
+            .filter(|ur| self.filter.matches(ur))
+            .collect()
+    }
+
+    /// The table, or the details of the selected UpdateRequest
+    pub fn render(&self, now: DateTime<Utc>) -> String {
+        if let Some(update_request) = self.detail.as_ref().and_then(|k| self.updates.get(k)) {
+            return detail(update_request);
+        }
+
+        let visible = self.visible();
+        let mut table = Table::new(&[
+            "NAME",
+            "NAMESPACE",
+            "RESOURCE",
+            "CURRENT→NEW",
+            "POLICY",
+            "STATUS",
+            "AGE",
+            "APPROVED BY",
+        ]);
+        for ur in &visible {
+            table.add_row(row(ur, now));
+        }
+
+        // Color whole lines after aligning, so escapes don't count as width
+        let mut lines = table
+            .render()
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        for (i, ur) in visible.iter().enumerate() {
+            let line = &mut lines[i + 1];
+            let color = phase_color(&phase_name(ur));
+            let highlight = if i == self.selected { REVERSE } else { "" };
+            *line = format!("{}{}{}{}", color, highlight, line, RESET);
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "{} UpdateRequests · ↑/↓ select · Enter details · q quit",
+            visible.len()
+        ));
+        lines.join("\n")
+    }
+}
+
+fn row(ur: &UpdateRequest, now: DateTime<Utc>) -> Vec<String> {
+    vec![
+        ur.name_any(),
+        ur.namespace().unwrap_or_default(),
+        format!("{}/{}", ur.spec.target_ref.kind, ur.spec.target_ref.name),
+        format!("{} → {}", ur.spec.current_image, ur.spec.new_image),
+        format!("{:?}", ur.spec.policy).to_lowercase(),
+        phase_name(ur),
+        ur.creation_timestamp()
+            .map(|created| age(now - created.0))
+            .unwrap_or_else(|| "-".to_string()),
+        cell(ur.status.as_ref().and_then(|s| s.approved_by.as_deref())),
+    ]
+}
+
+/// ANSI color of rows in `phase`
+fn phase_color(phase: &str) -> &'static str {
+    match phase {
+        "Pending" => "\x1b[33m",
+        "Completed" => "\x1b[32m",
+        "Failed" | "Rejected" => "\x1b[31m",
+        _ => "",
+    }
+}
+
+/// Age like kubectl: `45s`, `12m`, `5h`, `3d`
+fn age(elapsed: chrono::Duration) -> String {
+    let seconds = elapsed.num_seconds().max(0);
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86400),
+    }
+}
+
+fn detail(ur: &UpdateRequest) -> String {
+    let status = ur.status.as_ref();
+    let mut table = Table::new(&["FIELD", "VALUE"]);
+    let fields = [
+        ("Name", ur.name_any()),
+        ("Namespace", ur.namespace().unwrap_or_default()),
+        (
+            "Resource",
+            format!("{}/{}", ur.spec.target_ref.kind, ur.spec.target_ref.name),
+        ),
+        ("Container", cell(ur.spec.container_name.as_deref())),
+        ("Current image", ur.spec.current_image.clone()),
+        ("New image", ur.spec.new_image.clone()),
+        ("Policy", format!("{:?}", ur.spec.policy).to_lowercase()),
+        ("Status", phase_name(ur)),
+        ("Reason", cell(ur.spec.reason.as_deref())),
+        ("Message", cell(status.and_then(|s| s.message.as_deref()))),
+        (
+            "Approved by",
+            cell(status.and_then(|s| s.approved_by.as_deref())),
+        ),
+        (
+            "Rejected by",
+            cell(status.and_then(|s| s.rejected_by.as_deref())),
+        ),
+        (
+            "Scheduled at",
+            ur.spec
+                .scheduled_at
+                .map(|at| at.to_rfc3339())
+                .unwrap_or_else(|| "-".to_string()),
+        ),
+    ];
+    for (field, value) in fields {
+        table.add_row(vec![field.to_string(), value]);
+    }
+    format!("{}\n\nEsc back · q quit", table.render())
+}
+
+/// One `--output watch-json` line for `event`, if it concerns a shown row
+pub fn event_line(event: &Event<UpdateRequest>, filter: &WatchFilter) -> Option<String> {
+    let (kind, ur) = match event {
+        Event::Apply(ur) | Event::InitApply(ur) => ("applied", ur),
+        Event::Delete(ur) => ("deleted", ur),
+        Event::InitDone => return Some(json!({ "type": "synced" }).to_string()),
+        Event::Init => return None,
+    };
+    filter
+        .matches(ur)
+        .then(|| json!({ "type": kind, "updateRequest": ur }).to_string())
+}
+
+/// Follow `events` until they end or the user quits, writing to `out`
+pub async fn run_watch<E, K>(
+    events: E,
+    keys: K,
+    filter: WatchFilter,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> Result<()>
+where
+    E: Stream<Item = Result<Event<UpdateRequest>, watcher::Error>>,
+    K: Stream<Item = Key>,
+{
+    let mut events = std::pin::pin!(events);
+    let mut keys = std::pin::pin!(keys.fuse());
+    let mut view = WatchView::new(filter.clone());
+
+    loop {
+        tokio::select! {
+            event = events.next() => match event {
+                Some(Ok(event)) => {
+                    if format == OutputFormat::WatchJson {
+                        if let Some(line) = event_line(&event, &filter) {
+                            writeln!(out, "{}", line)?;
+                        }
+                        continue;
+                    }
+                    let synced = matches!(event, Event::Apply(_) | Event::Delete(_) | Event::InitDone);
+                    view.apply(event);
+                    if synced {
+                        writeln!(out, "{}{}", CLEAR_SCREEN, view.render(Utc::now()))?;
+                    }
+                },
+                // The watcher retries by itself; report and carry on
+                Some(Err(e)) => {
+                    if format == OutputFormat::WatchJson {
+                        writeln!(out, "{}", json!({ "type": "error", "message": e.to_string() }))?;
+                    }
+                },
+                None => return Ok(()),
+            },
+            Some(key) = keys.next() => {
+                if !view.key(key) {
+                    return Ok(());
+                }
+                if format != OutputFormat::WatchJson {
+                    writeln!(out, "{}{}", CLEAR_SCREEN, view.render(Utc::now()))?;
+                }
+            },
+        }
+        out.flush()?;
+    }
+}
+
+/// Watch UpdateRequests in `namespace` (all namespaces if `None`)
+pub async fn watch(
+    client: Client,
+    namespace: Option<&str>,
+    filter: WatchFilter,
+    format: OutputFormat,
+) -> Result<()> {
+    let api: Api<UpdateRequest> = match namespace {
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::all(client),
+    };
+    let events = watcher::watcher(api, watcher::Config::default());
+
+    let mut stdout = std::io::stdout();
+    if format == OutputFormat::WatchJson {
+        return run_watch(
+            events,
+            futures::stream::empty(),
+            filter,
+            format,
+            &mut stdout,
+        )
+        .await;
+    }
+    let _raw = terminal::RawMode::enable();
+    let keys = terminal::keys();
+    write!(stdout, "\x1b[?1049h")?;
+    let result = run_watch(events, keys, filter, format, &mut stdout).await;
+    write!(stdout, "\x1b[?1049l")?;
+    stdout.flush()?;
+    result
+}
+
+/// Key presses from a terminal on stdin
+mod terminal {
+    use super::Key;
+    use futures::Stream;
+    use std::io::Read;
+
+    /// Keys read from stdin on a blocking thread
+    pub fn keys() -> impl Stream<Item = Key> {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        std::thread::spawn(move || {
+            let mut stdin = std::io::stdin();
+            let mut buffer = [0u8; 8];
+            while let Ok(read) = stdin.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                for key in parse_keys(&buffer[..read]) {
+                    if sender.send(key).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        tokio_stream::wrappers::UnboundedReceiverStream::new(receiver)
+    }
+
+    /// Keys in bytes read from a raw-mode terminal
+    pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+        let mut keys = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let key = match &bytes[i..] {
+                [0x1b, b'[', b'A', ..] => Some((Key::Up, 3)),
+                [0x1b, b'[', b'B', ..] => Some((Key::Down, 3)),
+                [0x1b, b'[', _, ..] => None,
+                [0x1b, ..] => Some((Key::Back, 1)),
+                [b'k', ..] => Some((Key::Up, 1)),
+                [b'j', ..] => Some((Key::Down, 1)),
+                [b'\r' | b'\n', ..] => Some((Key::Enter, 1)),
+                [0x7f | 0x08, ..] => Some((Key::Back, 1)),
+                [b'q' | 0x03, ..] => Some((Key::Quit, 1)),
+                _ => None,
+            };
+            match key {
+                Some((key, len)) => {
+                    keys.push(key);
+                    i += len;
+                },
+                None if bytes[i] == 0x1b => i += 3,
+                None => i += 1,
+            }
+        }
+        keys
+    }
+
+    /// Puts the terminal in raw mode, restoring it when dropped
+    pub struct RawMode {
+        #[cfg(unix)]
+        original: Option<libc::termios>,
+    }
+
+    impl RawMode {
+        #[cfg(unix)]
+        pub fn enable() -> Self {
+            // SAFETY: termios is plain data, filled by tcgetattr before use
+            unsafe {
+                if libc::isatty(libc::STDIN_FILENO) != 1 {
+                    return Self { original: None };
+                }
+                let mut original: libc::termios = std::mem::zeroed();
+                if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                    return Self { original: None };
+                }
+                let mut raw = original;
+                raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+                raw.c_cc[libc::VMIN] = 1;
+                raw.c_cc[libc::VTIME] = 0;
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw);
+                Self {
+                    original: Some(original),
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        pub fn enable() -> Self {
+            Self {}
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            #[cfg(unix)]
+            if let Some(original) = &self.original {
+                // SAFETY: restores the settings read in enable()
+                unsafe {
+                    libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::crd::UpdatePhase;
+    use crate::testing;
+
+    fn update_request(name: &str, phase: UpdatePhase, approved_by: Option<&str>) -> UpdateRequest {
+        let mut ur = testing::update_request_in_phase("production", name, phase);
+        if let Some(status) = ur.status.as_mut() {
+            status.approved_by = approved_by.map(str::to_string);
+        }
+        ur
+    }
+
+    fn events(
+        events: Vec<Event<UpdateRequest>>,
+    ) -> impl Stream<Item = Result<Event<UpdateRequest>, watcher::Error>> {
+        tokio_stream::iter(events.into_iter().map(Ok))
+    }
+
+    /// Text of the last frame written, without colors
+    fn last_frame(out: &[u8]) -> String {
+        let out = String::from_utf8(out.to_vec()).unwrap();
+        let frame = out.rsplit(CLEAR_SCREEN).next().unwrap();
+        regex::Regex::new("\x1b\\[[0-9;]*m")
+            .unwrap()
+            .replace_all(frame, "")
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_table_follows_watch_events() {
+        let mut out = Vec::new();
+        run_watch(
+            events(vec![
+                Event::Init,
+                Event::InitApply(update_request("web-1", UpdatePhase::Pending, None)),
+                Event::InitApply(update_request("web-2", UpdatePhase::Pending, None)),
+                Event::InitDone,
+                Event::Apply(update_request(
+                    "web-1",
+                    UpdatePhase::Completed,
+                    Some("alice"),
+                )),
+                Event::Delete(update_request("web-2", UpdatePhase::Pending, None)),
+            ]),
+            futures::stream::empty(),
+            WatchFilter::default(),
+            OutputFormat::Table,
+            &mut out,
+        )
+        .await
+        .unwrap();
+
+        let frame = last_frame(&out);
+        let lines: Vec<&str> = frame.lines().collect();
+        assert!(lines[0].starts_with("NAME"));
+        assert!(lines[0].contains("CURRENT→NEW"));
+        assert!(lines[1].starts_with("web-1"));
+        assert!(lines[1].contains("Deployment/web"));
+        assert!(lines[1].contains("nginx:1.25.0 → nginx:1.26.0"));
+        assert!(lines[1].contains("Completed"));
+        assert!(lines[1].ends_with("alice"));
+        assert!(!frame.contains("web-2"));
+        // 1 frame after the initial list, then 1 per change
+        assert_eq!(
+            String::from_utf8(out)
+                .unwrap()
+                .matches(CLEAR_SCREEN)
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_rows_are_colored_by_status() {
+        let mut view = WatchView::new(WatchFilter::default());
+        view.apply(Event::Apply(update_request(
+            "a",
+            UpdatePhase::Pending,
+            None,
+        )));
+        view.apply(Event::Apply(update_request(
+            "b",
+            UpdatePhase::Completed,
+            None,
+        )));
+        view.apply(Event::Apply(update_request("c", UpdatePhase::Failed, None)));
+        view.apply(Event::Apply(update_request(
+            "d",
+            UpdatePhase::Rejected,
+            None,
+        )));
+
+        let frame = view.render(Utc::now());
+        let lines: Vec<&str> = frame.lines().collect();
+        assert!(lines[1].starts_with("\x1b[33m\x1b[7ma"));
+        assert!(lines[2].starts_with("\x1b[32mb"));
+        assert!(lines[3].starts_with("\x1b[31mc"));
+        assert!(lines[4].starts_with("\x1b[31md"));
+    }
+
+    #[test]
+    fn test_filter_by_status() {
+        let filter = WatchFilter::parse("status=Pending,approved").unwrap();
+        assert!(filter.matches(&update_request("a", UpdatePhase::Pending, None)));
+        assert!(filter.matches(&update_request("a", UpdatePhase::Approved, None)));
+        assert!(!filter.matches(&update_request("a", UpdatePhase::Completed, None)));
+
+        assert!(WatchFilter::parse("phase=Pending").is_err());
+        assert!(WatchFilter::parse("status=").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_enter_opens_details_of_selected_row() {
+        let mut out = Vec::new();
+        let (keys, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut view_events = vec![
+            Event::Apply(update_request("web-1", UpdatePhase::Pending, None)),
+            Event::Apply(update_request("web-2", UpdatePhase::Completed, Some("bob"))),
+        ];
+        view_events.reverse();
+        let events = futures::stream::unfold(view_events, |mut events| async move {
+            match events.pop() {
+                Some(event) => Some((Ok(event), events)),
+                // Keep the watch open until the user quits
+                None => futures::future::pending().await,
+            }
+        });
+        let watch = run_watch(
+            events,
+            tokio_stream::wrappers::UnboundedReceiverStream::new(receiver),
+            WatchFilter::default(),
+            OutputFormat::Table,
+            &mut out,
+        );
+        let press = async {
+            // After the events have been shown
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            for key in [Key::Down, Key::Enter, Key::Quit] {
+                keys.send(key).unwrap();
+            }
+        };
+        let (result, _) = tokio::join!(watch, press);
+        result.unwrap();
+
+        let frame = last_frame(&out);
+        assert!(frame.starts_with("FIELD"));
+        assert!(frame.contains("web-2"));
+        assert!(frame.contains("bob"));
+    }
+
+    #[tokio::test]
+    async fn test_watch_json_streams_filtered_events() {
+        let mut out = Vec::new();
+        run_watch(
+            events(vec![
+                Event::Init,
+                Event::InitApply(update_request("web-1", UpdatePhase::Pending, None)),
+                Event::InitApply(update_request("web-2", UpdatePhase::Completed, None)),
+                Event::InitDone,
+                Event::Delete(update_request("web-1", UpdatePhase::Pending, None)),
+            ]),
+            futures::stream::empty(),
+            WatchFilter::parse("status=Pending").unwrap(),
+            OutputFormat::WatchJson,
+            &mut out,
+        )
+        .await
+        .unwrap();
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let kinds: Vec<&str> = lines.iter().map(|l| l["type"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["applied", "synced", "deleted"]);
+        assert_eq!(lines[0]["updateRequest"]["metadata"]["name"], "web-1");
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            terminal::parse_keys(b"\x1b[B\x1b[Aj\r\x1bq"),
+            [
+                Key::Down,
+                Key::Up,
+                Key::Down,
+                Key::Enter,
+                Key::Back,
+                Key::Quit
+            ]
+        );
+    }
+
+    #[test]
+    fn test_age() {
+        assert_eq!(age(chrono::Duration::seconds(42)), "42s");
+        assert_eq!(age(chrono::Duration::minutes(90)), "1h");
+        assert_eq!(age(chrono::Duration::days(3)), "3d");
+    }
+}
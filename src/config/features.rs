@@ -0,0 +1,214 @@
+//! Feature flags for capabilities operators opt into.
+//!
+//! Each flag is enabled with `HEADWIND_FEATURE_{NAME}=true`, e.g.
+//! `HEADWIND_FEATURE_DIGEST_PINNING=true`, and is off otherwise. Flags are
+//! read once at startup; changing them needs a restart.
+
+use crate::gitops::GitOpsConfig;
+use crate::models::policy::annotations;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Prefix of the environment variables enabling a feature
+pub const ENV_PREFIX: &str = "HEADWIND_FEATURE_";
+
+/// Which optional features are enabled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureFlags {
+    /// Deploy `name:tag@digest` for resources annotated `headwind.sh/digest-pinning`
+    pub digest_pinning: bool,
+    /// Verify cosign signatures of new images
+    pub cosign_verification: bool,
+    /// Scan new images with Trivy before updating
+    pub trivy_scanning: bool,
+    /// Commit updates to git for resources annotated `headwind.sh/gitops-mode: git-push`
+    pub git_push_mode: bool,
+    /// Approve and reject UpdateRequests from signed email links
+    pub email_approval: bool,
+    /// Poll OCI Helm repositories for new chart versions
+    pub helm_oci_polling: bool,
+    /// Mark Pending UpdateRequests past their `spec.expiresAt` as `Expired`
+    pub update_request_expiry: bool,
+}
+
+/// A resource asks for features that cannot be combined
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FeatureConflict {
+    #[error(
+        "{} is git-push and {} is set; updates are either committed to git or patched into the cluster, remove one",
+        annotations::GITOPS_MODE,
+        .0
+    )]
+    GitPushWithDirectPatching(&'static str),
+}
+
+impl FeatureFlags {
+    /// Flags from `HEADWIND_FEATURE_*` environment variables
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Flags from variables returned by `lookup`
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let flag = |name: &str| {
+            let var = format!("{}{}", ENV_PREFIX, name.to_uppercase());
+            match lookup(&var) {
+                Some(value) => value.trim().to_lowercase().parse().unwrap_or_else(|_| {
+                    warn!("Ignoring {}={}, expected true or false", var, value);
+                    false
+                }),
+                None => false,
+            }
+        };
+        Self {
+            digest_pinning: flag("digest_pinning"),
+            cosign_verification: flag("cosign_verification"),
+            trivy_scanning: flag("trivy_scanning"),
+            git_push_mode: flag("git_push_mode"),
+            email_approval: flag("email_approval"),
+            helm_oci_polling: flag("helm_oci_polling"),
+            update_request_expiry: flag("update_request_expiry"),
+        }
+    }
+
+    /// Every flag with its name, in declaration order
+    pub fn all(&self) -> [(&'static str, bool); 7] {
+        [
+            ("digest_pinning", self.digest_pinning),
+            ("cosign_verification", self.cosign_verification),
+            ("trivy_scanning", self.trivy_scanning),
+            ("git_push_mode", self.git_push_mode),
+            ("email_approval", self.email_approval),
+            ("helm_oci_polling", self.helm_oci_polling),
+            ("update_request_expiry", self.update_request_expiry),
+        ]
+    }
+
+    /// Names of the enabled flags
+    pub fn enabled(&self) -> Vec<&'static str> {
+        self.all()
+            .into_iter()
+            .filter_map(|(name, enabled)| enabled.then_some(name))
+            .collect()
+    }
+
+    /// Reject resource annotations that combine features which cannot apply together
+    pub fn check_resource(
+        &self,
+        resource_annotations: &BTreeMap<String, String>,
+    ) -> Result<(), FeatureConflict> {
+        if !self.git_push_mode || GitOpsConfig::from_annotations(resource_annotations).is_none() {
+            return Ok(());
+        }
+        for annotation in [annotations::FLUX_KUSTOMIZATION, annotations::ARGOCD_APP] {
+            if resource_annotations.contains_key(annotation) {
+                return Err(FeatureConflict::GitPushWithDirectPatching(annotation));
+            }
+        }
+        Ok(())
+    }
+}
+
+static FEATURES: once_cell::sync::Lazy<FeatureFlags> =
+    once_cell::sync::Lazy::new(FeatureFlags::from_env);
+
+/// Feature flags of this process
+pub fn features() -> &'static FeatureFlags {
+    &FEATURES
+}
+
+/// Log which features are enabled
+pub fn log_enabled_features() {
+    let enabled = features().enabled();
+    if enabled.is_empty() {
+        info!("No optional features enabled");
+    } else {
+        info!("Enabled features: {}", enabled.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn flags(vars: &[(&str, &str)]) -> FeatureFlags {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        FeatureFlags::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    fn resource_annotations(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flags_default_to_disabled() {
+        assert_eq!(flags(&[]), FeatureFlags::default());
+        assert!(flags(&[]).enabled().is_empty());
+    }
+
+    #[test]
+    fn test_flag_parsing() {
+        let features = flags(&[
+            ("HEADWIND_FEATURE_DIGEST_PINNING", "true"),
+            ("HEADWIND_FEATURE_EMAIL_APPROVAL", " TRUE "),
+            ("HEADWIND_FEATURE_GIT_PUSH_MODE", "false"),
+            ("HEADWIND_FEATURE_HELM_OCI_POLLING", "yes"),
+            ("HEADWIND_FEATURE_TRIVY", "true"),
+        ]);
+        assert!(features.digest_pinning);
+        assert!(features.email_approval);
+        assert!(!features.git_push_mode);
+        // Only true and false are understood
+        assert!(!features.helm_oci_polling);
+        // Unknown names are ignored
+        assert!(!features.trivy_scanning);
+        assert_eq!(features.enabled(), vec!["digest_pinning", "email_approval"]);
+    }
+
+    #[test]
+    fn test_serializes_every_flag() {
+        let json = serde_json::to_value(flags(&[("HEADWIND_FEATURE_COSIGN_VERIFICATION", "true")]))
+            .unwrap();
+        assert_eq!(json.as_object().unwrap().len(), 7);
+        assert_eq!(json["cosign_verification"], true);
+        assert_eq!(json["git_push_mode"], false);
+    }
+
+    #[test]
+    fn test_git_push_conflicts_with_direct_patching() {
+        let git_push = [
+            (annotations::GITOPS_MODE, "git-push"),
+            (annotations::GIT_REPO_URL, "https://github.com/org/config"),
+            (annotations::GIT_FILE_PATH, "apps/web/deployment.yaml"),
+        ];
+        let enabled = flags(&[("HEADWIND_FEATURE_GIT_PUSH_MODE", "true")]);
+
+        assert_eq!(
+            enabled.check_resource(&resource_annotations(&git_push)),
+            Ok(())
+        );
+        for integration in [annotations::FLUX_KUSTOMIZATION, annotations::ARGOCD_APP] {
+            let mut resource = resource_annotations(&git_push);
+            resource.insert(integration.to_string(), "web".to_string());
+            assert_eq!(
+                enabled.check_resource(&resource),
+                Err(FeatureConflict::GitPushWithDirectPatching(integration))
+            );
+            // git-push is ignored while the feature is off, so nothing conflicts
+            assert_eq!(FeatureFlags::default().check_resource(&resource), Ok(()));
+        }
+        assert_eq!(
+            enabled.check_resource(&resource_annotations(&[(annotations::ARGOCD_APP, "web")])),
+            Ok(())
+        );
+    }
+}
@@ -1,16 +1,73 @@
+pub mod features;
+pub mod validation;
+
 use futures::StreamExt;
 use k8s_openapi::api::core::v1::{ConfigMap, Secret};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
 use kube::runtime::{WatchStreamExt, watcher};
 use kube::{Api, Client};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
 use tokio::time::Duration;
 use tracing::{debug, error, info, warn};
 
 const CONFIGMAP_NAME: &str = "headwind-config";
 const SECRET_NAME: &str = "headwind-secrets";
-const NAMESPACE: &str = "headwind-system";
+/// Namespace used when neither `HEADWIND_NAMESPACE` nor `MY_POD_NAMESPACE` is set
+const DEFAULT_NAMESPACE: &str = "headwind-system";
+
+/// Namespace where headwind keeps its own ConfigMaps and Secrets.
+///
+/// `HEADWIND_NAMESPACE` takes precedence, then the pod's namespace from the
+/// downward API (`MY_POD_NAMESPACE`), then `headwind-system`.
+pub fn headwind_namespace() -> String {
+    std::env::var("HEADWIND_NAMESPACE")
+        .ok()
+        .filter(|ns| !ns.is_empty())
+        .or_else(|| {
+            std::env::var("MY_POD_NAMESPACE")
+                .ok()
+                .filter(|ns| !ns.is_empty())
+        })
+        .unwrap_or_else(|| DEFAULT_NAMESPACE.to_string())
+}
+
+/// Certificate files for serving the webhook server over mutual TLS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookTlsConfig {
+    /// PEM server certificate chain (`HEADWIND_WEBHOOK_TLS_CERT_FILE`)
+    pub cert_file: PathBuf,
+    /// PEM private key (`HEADWIND_WEBHOOK_TLS_KEY_FILE`)
+    pub key_file: PathBuf,
+    /// PEM CA certificates client certificates must chain to
+    /// (`HEADWIND_WEBHOOK_CLIENT_CA_FILE`)
+    pub client_ca_file: PathBuf,
+}
+
+impl WebhookTlsConfig {
+    /// mTLS settings from the environment: None when none of the three
+    /// variables is set, an error when only some are
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+        match (
+            var("HEADWIND_WEBHOOK_TLS_CERT_FILE"),
+            var("HEADWIND_WEBHOOK_TLS_KEY_FILE"),
+            var("HEADWIND_WEBHOOK_CLIENT_CA_FILE"),
+        ) {
+            (None, None, None) => Ok(None),
+            (Some(cert_file), Some(key_file), Some(client_ca_file)) => Ok(Some(Self {
+                cert_file: cert_file.into(),
+                key_file: key_file.into(),
+                client_ca_file: client_ca_file.into(),
+            })),
+            _ => anyhow::bail!(
+                "HEADWIND_WEBHOOK_TLS_CERT_FILE, HEADWIND_WEBHOOK_TLS_KEY_FILE and HEADWIND_WEBHOOK_CLIENT_CA_FILE must be set together"
+            ),
+        }
+    }
+}
 
 /// Headwind configuration loaded from ConfigMap and Secret
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,7 +100,27 @@ pub struct ControllersConfig {
 pub struct NotificationsConfig {
     pub slack: SlackConfig,
     pub teams: TeamsConfig,
+    #[serde(default)]
+    pub telegram: TelegramConfig,
+    #[serde(default)]
+    pub victorops: VictorOpsConfig,
     pub webhook: WebhookConfig,
+    /// Per-namespace overrides, from the `notifications.routes` YAML list
+    #[serde(default)]
+    pub routes: Vec<NotificationRoute>,
+}
+
+/// Notification settings for namespaces matching `namespace_selector`.
+/// Only the fields that are set override the global configuration.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationRoute {
+    #[serde(default)]
+    pub namespace_selector: LabelSelector,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack_channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagerduty_service: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,6 +132,13 @@ pub struct SlackConfig {
     pub username: Option<String>,
     #[serde(rename = "iconEmoji")]
     pub icon_emoji: Option<String>,
+    /// ConfigMap holding custom message templates, see [`crate::notifications::template`]
+    #[serde(
+        rename = "messageTemplateConfigMap",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub message_template_configmap: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +148,39 @@ pub struct TeamsConfig {
     pub webhook_url: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelegramConfig {
+    pub enabled: bool,
+    #[serde(rename = "botToken")]
+    pub bot_token: Option<String>,
+    #[serde(rename = "chatId")]
+    pub chat_id: Option<String>,
+    /// Markdown or HTML
+    #[serde(rename = "parseMode")]
+    pub parse_mode: String,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bot_token: None,
+            chat_id: None,
+            parse_mode: "Markdown".to_string(),
+        }
+    }
+}
+
+/// VictorOps (Splunk On-Call) REST integration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VictorOpsConfig {
+    pub enabled: bool,
+    #[serde(rename = "restEndpointUrl")]
+    pub rest_endpoint_url: Option<String>,
+    #[serde(rename = "routingKey")]
+    pub routing_key: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebhookConfig {
     pub enabled: bool,
@@ -118,15 +235,19 @@ impl Default for HeadwindConfig {
                     channel: None,
                     username: Some("Headwind".to_string()),
                     icon_emoji: Some(":sailboat:".to_string()),
+                    message_template_configmap: None,
                 },
                 teams: TeamsConfig {
                     enabled: false,
                     webhook_url: None,
                 },
+                telegram: TelegramConfig::default(),
+                victorops: VictorOpsConfig::default(),
                 webhook: WebhookConfig {
                     enabled: false,
                     url: None,
                 },
+                routes: Vec::new(),
             },
             observability: ObservabilityConfig {
                 metrics_backend: "auto".to_string(),
@@ -159,8 +280,9 @@ impl HeadwindConfig {
     pub async fn load(client: Client) -> Result<Self, Box<dyn std::error::Error>> {
         info!("Loading Headwind configuration from ConfigMap and Secret");
 
-        let configmap_api: Api<ConfigMap> = Api::namespaced(client.clone(), NAMESPACE);
-        let secret_api: Api<Secret> = Api::namespaced(client.clone(), NAMESPACE);
+        let namespace = headwind_namespace();
+        let configmap_api: Api<ConfigMap> = Api::namespaced(client.clone(), &namespace);
+        let secret_api: Api<Secret> = Api::namespaced(client.clone(), &namespace);
 
         // Load ConfigMap (create with defaults if doesn't exist)
         let config_data = match configmap_api.get(CONFIGMAP_NAME).await {
@@ -220,15 +342,35 @@ impl HeadwindConfig {
                         .or_else(|| Some("Headwind".to_string())),
                     icon_emoji: parse_optional_string(&config_data, "slack.iconEmoji")
                         .or_else(|| Some(":sailboat:".to_string())),
+                    message_template_configmap: parse_optional_string(
+                        &config_data,
+                        "slack.messageTemplateConfigMap",
+                    ),
                 },
                 teams: TeamsConfig {
                     enabled: parse_bool(&config_data, "teams.enabled", false),
                     webhook_url: get_secret_value(&secret_data, "teams-webhook-url"),
                 },
+                telegram: TelegramConfig {
+                    enabled: parse_bool(&config_data, "telegram.enabled", false),
+                    bot_token: get_secret_value(&secret_data, "telegram-bot-token"),
+                    chat_id: parse_optional_string(&config_data, "telegram.chatId"),
+                    parse_mode: parse_optional_string(&config_data, "telegram.parseMode")
+                        .unwrap_or_else(|| "Markdown".to_string()),
+                },
+                victorops: VictorOpsConfig {
+                    enabled: parse_bool(&config_data, "victorops.enabled", false),
+                    rest_endpoint_url: get_secret_value(
+                        &secret_data,
+                        "victorops-rest-endpoint-url",
+                    ),
+                    routing_key: parse_optional_string(&config_data, "victorops.routingKey"),
+                },
                 webhook: WebhookConfig {
                     enabled: parse_bool(&config_data, "webhook.enabled", false),
                     url: get_secret_value(&secret_data, "webhook-url"),
                 },
+                routes: parse_notification_routes(&config_data),
             },
             observability: ObservabilityConfig {
                 metrics_backend: parse_optional_string(
@@ -283,8 +425,9 @@ impl HeadwindConfig {
     pub async fn save(&self, client: Client) -> Result<(), Box<dyn std::error::Error>> {
         info!("Saving Headwind configuration to ConfigMap and Secret");
 
-        let configmap_api: Api<ConfigMap> = Api::namespaced(client.clone(), NAMESPACE);
-        let secret_api: Api<Secret> = Api::namespaced(client, NAMESPACE);
+        let namespace = headwind_namespace();
+        let configmap_api: Api<ConfigMap> = Api::namespaced(client.clone(), &namespace);
+        let secret_api: Api<Secret> = Api::namespaced(client, &namespace);
 
         // Build ConfigMap data
         let mut config_data = BTreeMap::new();
@@ -328,14 +471,56 @@ impl HeadwindConfig {
                 .clone()
                 .unwrap_or_else(|| ":sailboat:".to_string()),
         );
+        config_data.insert(
+            "slack.messageTemplateConfigMap".to_string(),
+            self.notifications
+                .slack
+                .message_template_configmap
+                .clone()
+                .unwrap_or_default(),
+        );
         config_data.insert(
             "teams.enabled".to_string(),
             self.notifications.teams.enabled.to_string(),
         );
+        config_data.insert(
+            "telegram.enabled".to_string(),
+            self.notifications.telegram.enabled.to_string(),
+        );
+        config_data.insert(
+            "telegram.chatId".to_string(),
+            self.notifications
+                .telegram
+                .chat_id
+                .clone()
+                .unwrap_or_default(),
+        );
+        config_data.insert(
+            "telegram.parseMode".to_string(),
+            self.notifications.telegram.parse_mode.clone(),
+        );
+        config_data.insert(
+            "victorops.enabled".to_string(),
+            self.notifications.victorops.enabled.to_string(),
+        );
+        config_data.insert(
+            "victorops.routingKey".to_string(),
+            self.notifications
+                .victorops
+                .routing_key
+                .clone()
+                .unwrap_or_default(),
+        );
         config_data.insert(
             "webhook.enabled".to_string(),
             self.notifications.webhook.enabled.to_string(),
         );
+        if !self.notifications.routes.is_empty() {
+            config_data.insert(
+                "notifications.routes".to_string(),
+                serde_yaml::to_string(&self.notifications.routes)?,
+            );
+        }
         config_data.insert(
             "observability.metricsBackend".to_string(),
             self.observability.metrics_backend.clone(),
@@ -393,11 +578,22 @@ impl HeadwindConfig {
                 .unwrap_or_default(),
         );
 
+        // Notification routes are edited in the ConfigMap directly, not through
+        // the settings page, so keep the existing ones when none are given
+        if !config_data.contains_key("notifications.routes")
+            && let Ok(existing) = configmap_api.get(CONFIGMAP_NAME).await
+            && let Some(routes) = existing
+                .data
+                .and_then(|mut data| data.remove("notifications.routes"))
+        {
+            config_data.insert("notifications.routes".to_string(), routes);
+        }
+
         // Update or create ConfigMap
         let configmap = ConfigMap {
             metadata: kube::api::ObjectMeta {
                 name: Some(CONFIGMAP_NAME.to_string()),
-                namespace: Some(NAMESPACE.to_string()),
+                namespace: Some(namespace.clone()),
                 labels: Some(
                     [("app".to_string(), "headwind".to_string())]
                         .into_iter()
@@ -432,6 +628,12 @@ impl HeadwindConfig {
         if let Some(url) = &self.notifications.teams.webhook_url {
             secret_data.insert("teams-webhook-url".to_string(), url.clone());
         }
+        if let Some(token) = &self.notifications.telegram.bot_token {
+            secret_data.insert("telegram-bot-token".to_string(), token.clone());
+        }
+        if let Some(url) = &self.notifications.victorops.rest_endpoint_url {
+            secret_data.insert("victorops-rest-endpoint-url".to_string(), url.clone());
+        }
         if let Some(url) = &self.notifications.webhook.url {
             secret_data.insert("webhook-url".to_string(), url.clone());
         }
@@ -440,7 +642,7 @@ impl HeadwindConfig {
         let secret = Secret {
             metadata: kube::api::ObjectMeta {
                 name: Some(SECRET_NAME.to_string()),
-                namespace: Some(NAMESPACE.to_string()),
+                namespace: Some(namespace.clone()),
                 labels: Some(
                     [("app".to_string(), "headwind".to_string())]
                         .into_iter()
@@ -488,6 +690,24 @@ fn parse_optional_string(data: &BTreeMap<String, String>, key: &str) -> Option<S
         .map(|v| v.to_string())
 }
 
+/// Parse the `notifications.routes` YAML list, ignoring it entirely if invalid
+fn parse_notification_routes(data: &BTreeMap<String, String>) -> Vec<NotificationRoute> {
+    let Some(yaml) = data
+        .get("notifications.routes")
+        .filter(|v| !v.trim().is_empty())
+    else {
+        return Vec::new();
+    };
+
+    serde_yaml::from_str(yaml).unwrap_or_else(|e| {
+        error!(
+            "Invalid notifications.routes, ignoring notification routes: {}",
+            e
+        );
+        Vec::new()
+    })
+}
+
 fn get_secret_value(data: &BTreeMap<String, String>, key: &str) -> Option<String> {
     data.get(key)
         .filter(|v| !v.is_empty())
@@ -516,8 +736,9 @@ fn update_cached_config(config: HeadwindConfig) {
 pub async fn start_config_watcher(client: Client) {
     info!("Starting configuration watcher for hot-reload");
 
-    let configmap_api: Api<ConfigMap> = Api::namespaced(client.clone(), NAMESPACE);
-    let secret_api: Api<Secret> = Api::namespaced(client.clone(), NAMESPACE);
+    let namespace = headwind_namespace();
+    let configmap_api: Api<ConfigMap> = Api::namespaced(client.clone(), &namespace);
+    let secret_api: Api<Secret> = Api::namespaced(client.clone(), &namespace);
 
     // Load initial configuration
     match HeadwindConfig::load(client.clone()).await {
@@ -532,6 +753,7 @@ pub async fn start_config_watcher(client: Client) {
 
     // Spawn ConfigMap watcher
     let cm_client = client.clone();
+    let cm_namespace = namespace.clone();
     tokio::spawn(async move {
         loop {
             let watcher_config = watcher::Config::default().timeout(60).any_semantic();
@@ -542,7 +764,7 @@ pub async fn start_config_watcher(client: Client) {
 
             info!(
                 "ConfigMap watcher started for {}/{}",
-                NAMESPACE, CONFIGMAP_NAME
+                cm_namespace, CONFIGMAP_NAME
             );
 
             while let Some(event) = stream.next().await {
@@ -595,7 +817,7 @@ pub async fn start_config_watcher(client: Client) {
                 .default_backoff()
                 .boxed();
 
-            info!("Secret watcher started for {}/{}", NAMESPACE, SECRET_NAME);
+            info!("Secret watcher started for {}/{}", namespace, SECRET_NAME);
 
             while let Some(event) = stream.next().await {
                 match event {
@@ -663,6 +885,42 @@ mod tests {
         assert!(parse_bool(&data, "missing.key", true));
     }
 
+    #[test]
+    fn test_parse_notification_routes() {
+        let mut data = BTreeMap::new();
+        assert!(parse_notification_routes(&data).is_empty());
+
+        data.insert(
+            "notifications.routes".to_string(),
+            r##"
+- namespaceSelector:
+    matchLabels:
+      team: payments
+  slackChannel: "#payments-deploys"
+- namespaceSelector: {}
+  pagerdutyService: PABC123
+"##
+            .to_string(),
+        );
+        let routes = parse_notification_routes(&data);
+        assert_eq!(routes.len(), 2);
+        assert_eq!(
+            routes[0].slack_channel.as_deref(),
+            Some("#payments-deploys")
+        );
+        assert_eq!(
+            routes[0].namespace_selector.match_labels.as_ref().unwrap()["team"],
+            "payments"
+        );
+        assert_eq!(routes[1].pagerduty_service.as_deref(), Some("PABC123"));
+
+        data.insert(
+            "notifications.routes".to_string(),
+            "not: [a list".to_string(),
+        );
+        assert!(parse_notification_routes(&data).is_empty());
+    }
+
     #[test]
     fn test_parse_u64() {
         let mut data = BTreeMap::new();
@@ -0,0 +1,587 @@
+//! Startup checks of the Headwind configuration.
+//!
+//! Most settings fall back to a default when they can't be parsed and most
+//! integrations fail only when first used, so a broken configuration often
+//! goes unnoticed until an update is missed. `validate_config` reports these
+//! problems at startup, each with a hint on how to fix it. Set
+//! `HEADWIND_STRICT_CONFIG_VALIDATION=true` to refuse to start instead.
+
+use super::{HeadwindConfig, SECRET_NAME, headwind_namespace};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{Api, Client};
+use std::fmt;
+use std::time::Duration;
+
+const STRICT_VALIDATION_ENV: &str = "HEADWIND_STRICT_CONFIG_VALIDATION";
+
+/// How long a notification webhook may take to accept a connection
+const REACHABILITY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest accepted polling interval, one day
+const MAX_POLLING_INTERVAL: u64 = 86400;
+
+/// Metrics backends understood by `observability.metricsBackend`
+const METRICS_BACKENDS: &[&str] = &["auto", "prometheus", "victoriametrics", "influxdb", "live"];
+
+/// A problem found in the configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigValidationError {
+    /// Setting the problem is about, e.g. `notifications.slack.webhookUrl`
+    pub field: String,
+    pub message: String,
+    /// What to change to fix it
+    pub hint: String,
+}
+
+impl ConfigValidationError {
+    fn new(field: impl Into<String>, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+            hint: hint.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}. {}", self.field, self.message, self.hint)
+    }
+}
+
+/// Whether startup should fail on validation errors
+pub fn strict_validation_enabled() -> bool {
+    std::env::var(STRICT_VALIDATION_ENV)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false)
+}
+
+/// Check `config` and the environment, returning every problem found
+pub async fn validate_config(
+    config: &HeadwindConfig,
+    client: &Client,
+) -> Vec<ConfigValidationError> {
+    let env = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+
+    let mut errors = validate_intervals(config, env);
+    errors.extend(validate_consistency(config, env));
+    errors.extend(validate_webhook_urls(config));
+    errors.extend(check_webhook_reachability(config).await);
+
+    let namespace = headwind_namespace();
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &namespace);
+    match secrets.get_opt(SECRET_NAME).await {
+        Ok(secret) => errors.extend(validate_secret_references(
+            config,
+            &namespace,
+            secret.as_ref(),
+        )),
+        Err(e) => errors.push(ConfigValidationError::new(
+            SECRET_NAME,
+            format!("Failed to read Secret {}/{}: {}", namespace, SECRET_NAME, e),
+            "Grant the Headwind ServiceAccount get on secrets in its namespace",
+        )),
+    }
+
+    errors
+}
+
+/// Polling intervals and concurrency from the ConfigMap and environment must
+/// be numbers in range
+fn validate_intervals(
+    config: &HeadwindConfig,
+    env: impl Fn(&str) -> Option<String>,
+) -> Vec<ConfigValidationError> {
+    let mut errors = Vec::new();
+    let interval_hint = format!(
+        "Use a number of seconds between 1 and {}",
+        MAX_POLLING_INTERVAL
+    );
+
+    if !(1..=MAX_POLLING_INTERVAL).contains(&config.polling.interval) {
+        errors.push(ConfigValidationError::new(
+            "polling.interval",
+            format!("{} is out of range", config.polling.interval),
+            &interval_hint,
+        ));
+    }
+
+    if let Some(value) = env("HEADWIND_POLLING_INTERVAL") {
+        match value.parse::<u64>() {
+            Ok(interval) if (1..=MAX_POLLING_INTERVAL).contains(&interval) => {},
+            Ok(_) => errors.push(ConfigValidationError::new(
+                "HEADWIND_POLLING_INTERVAL",
+                format!("{} is out of range", value),
+                &interval_hint,
+            )),
+            Err(_) => errors.push(ConfigValidationError::new(
+                "HEADWIND_POLLING_INTERVAL",
+                format!("{:?} is not a number, the default of 300 is used", value),
+                &interval_hint,
+            )),
+        }
+    }
+
+    if let Some(value) = env("HEADWIND_POLLING_MAX_CONCURRENT") {
+        match value.parse::<usize>() {
+            Ok(concurrent) if concurrent > 0 => {},
+            _ => errors.push(ConfigValidationError::new(
+                "HEADWIND_POLLING_MAX_CONCURRENT",
+                format!("{:?} is not a positive number", value),
+                "Use the number of registries to poll at the same time, e.g. 10",
+            )),
+        }
+    }
+
+    errors
+}
+
+/// Settings that are valid on their own but don't work together
+fn validate_consistency(
+    config: &HeadwindConfig,
+    env: impl Fn(&str) -> Option<String>,
+) -> Vec<ConfigValidationError> {
+    let mut errors = Vec::new();
+
+    let polling_enabled = match env("HEADWIND_POLLING_ENABLED") {
+        None => false,
+        Some(value) => match value.parse::<bool>() {
+            Ok(enabled) => enabled,
+            Err(_) => {
+                errors.push(ConfigValidationError::new(
+                    "HEADWIND_POLLING_ENABLED",
+                    format!("{:?} is not a boolean, polling stays disabled", value),
+                    "Set it to true or false",
+                ));
+                false
+            },
+        },
+    };
+
+    if config.polling.enabled && !polling_enabled {
+        errors.push(ConfigValidationError::new(
+            "polling.enabled",
+            "Polling is enabled in the ConfigMap but the registry poller isn't running",
+            "Set HEADWIND_POLLING_ENABLED=true on the Headwind Deployment",
+        ));
+    }
+
+    // Vault only provides registry credentials when all three are set
+    let vault_vars = [
+        "HEADWIND_VAULT_ADDR",
+        "HEADWIND_VAULT_ROLE",
+        "HEADWIND_VAULT_CREDENTIAL_PATH",
+    ];
+    let missing_vault: Vec<&str> = vault_vars
+        .iter()
+        .copied()
+        .filter(|name| env(name).is_none())
+        .collect();
+    if polling_enabled && !missing_vault.is_empty() && missing_vault.len() < vault_vars.len() {
+        errors.push(ConfigValidationError::new(
+            "HEADWIND_VAULT_ADDR",
+            format!(
+                "Polling is enabled but Vault registry credentials are disabled because {} is not set",
+                missing_vault.join(", ")
+            ),
+            "Set HEADWIND_VAULT_ADDR, HEADWIND_VAULT_ROLE and HEADWIND_VAULT_CREDENTIAL_PATH together",
+        ));
+    }
+
+    let notifications = &config.notifications;
+    if notifications.telegram.enabled && notifications.telegram.chat_id.is_none() {
+        errors.push(ConfigValidationError::new(
+            "telegram.chatId",
+            "Telegram notifications are enabled without a chat",
+            "Set telegram.chatId in the headwind-config ConfigMap",
+        ));
+    }
+    if notifications.victorops.enabled && notifications.victorops.routing_key.is_none() {
+        errors.push(ConfigValidationError::new(
+            "victorops.routingKey",
+            "VictorOps notifications are enabled without a routing key",
+            "Set victorops.routingKey in the headwind-config ConfigMap",
+        ));
+    }
+
+    let backend = config.observability.metrics_backend.as_str();
+    if !METRICS_BACKENDS.contains(&backend) {
+        errors.push(ConfigValidationError::new(
+            "observability.metricsBackend",
+            format!("Unknown metrics backend {:?}", backend),
+            format!("Use one of {}", METRICS_BACKENDS.join(", ")),
+        ));
+    }
+
+    errors
+}
+
+/// URLs of the enabled notification webhooks, by setting
+fn enabled_webhook_urls(config: &HeadwindConfig) -> Vec<(&'static str, &str)> {
+    let notifications = &config.notifications;
+    [
+        (
+            "notifications.slack.webhookUrl",
+            notifications.slack.enabled,
+            notifications.slack.webhook_url.as_deref(),
+        ),
+        (
+            "notifications.teams.webhookUrl",
+            notifications.teams.enabled,
+            notifications.teams.webhook_url.as_deref(),
+        ),
+        (
+            "notifications.victorops.restEndpointUrl",
+            notifications.victorops.enabled,
+            notifications.victorops.rest_endpoint_url.as_deref(),
+        ),
+        (
+            "notifications.webhook.url",
+            notifications.webhook.enabled,
+            notifications.webhook.url.as_deref(),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(field, enabled, url)| Some((field, url.filter(|_| enabled)?)))
+    .collect()
+}
+
+/// Enabled notification webhooks must have an http(s) URL. Missing URLs are
+/// reported by `validate_secret_references`, since they come from the Secret.
+fn validate_webhook_urls(config: &HeadwindConfig) -> Vec<ConfigValidationError> {
+    enabled_webhook_urls(config)
+        .into_iter()
+        .filter(|(_, url)| !is_http_url(url))
+        .map(|(field, _)| {
+            ConfigValidationError::new(
+                field,
+                "Not a valid http(s) URL",
+                format!(
+                    "Fix the URL in the {} Secret, e.g. https://hooks.example.com/...",
+                    SECRET_NAME
+                ),
+            )
+        })
+        .collect()
+}
+
+fn is_http_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some())
+}
+
+/// Every enabled notification webhook with a valid URL must accept connections
+async fn check_webhook_reachability(config: &HeadwindConfig) -> Vec<ConfigValidationError> {
+    let mut errors = Vec::new();
+    for (field, url) in enabled_webhook_urls(config) {
+        if !is_http_url(url) {
+            continue;
+        }
+        if let Err(e) = check_url_reachable(url, REACHABILITY_TIMEOUT).await {
+            errors.push(ConfigValidationError::new(
+                field,
+                format!("Webhook is unreachable: {}", e),
+                "Check the URL and that egress to its host is allowed",
+            ));
+        }
+    }
+    errors
+}
+
+/// Whether `url` answers an HTTP request within `timeout`. Any status counts,
+/// as webhooks commonly reject requests without a payload.
+async fn check_url_reachable(url: &str, timeout: Duration) -> Result<(), String> {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| e.to_string())?;
+    client.head(url).send().await.map(|_| ()).map_err(|e| {
+        if e.is_timeout() {
+            format!("no response within {}s", timeout.as_secs())
+        } else {
+            e.to_string()
+        }
+    })
+}
+
+/// Keys of the Headwind Secret that enabled integrations read, by setting
+fn secret_references(config: &HeadwindConfig) -> Vec<(&'static str, &'static str)> {
+    let notifications = &config.notifications;
+    [
+        (
+            notifications.slack.enabled,
+            "notifications.slack.webhookUrl",
+            "slack-webhook-url",
+        ),
+        (
+            notifications.teams.enabled,
+            "notifications.teams.webhookUrl",
+            "teams-webhook-url",
+        ),
+        (
+            notifications.telegram.enabled,
+            "notifications.telegram.botToken",
+            "telegram-bot-token",
+        ),
+        (
+            notifications.victorops.enabled,
+            "notifications.victorops.restEndpointUrl",
+            "victorops-rest-endpoint-url",
+        ),
+        (
+            notifications.webhook.enabled,
+            "notifications.webhook.url",
+            "webhook-url",
+        ),
+    ]
+    .into_iter()
+    .filter(|(enabled, _, _)| *enabled)
+    .map(|(_, field, key)| (field, key))
+    .collect()
+}
+
+/// The Headwind Secret must exist and hold every key an enabled integration
+/// reads from it
+fn validate_secret_references(
+    config: &HeadwindConfig,
+    namespace: &str,
+    secret: Option<&Secret>,
+) -> Vec<ConfigValidationError> {
+    let references = secret_references(config);
+    let Some(secret) = secret else {
+        return references
+            .into_iter()
+            .map(|(field, key)| {
+                ConfigValidationError::new(
+                    field,
+                    format!("Secret {}/{} doesn't exist", namespace, SECRET_NAME),
+                    format!(
+                        "Create it with the {} key: kubectl create secret generic {} -n {} --from-literal={}=...",
+                        key, SECRET_NAME, namespace, key
+                    ),
+                )
+            })
+            .collect();
+    };
+
+    let has_key = |key: &str| {
+        secret
+            .data
+            .as_ref()
+            .and_then(|data| data.get(key))
+            .is_some_and(|value| !String::from_utf8_lossy(&value.0).trim().is_empty())
+            || secret
+                .string_data
+                .as_ref()
+                .and_then(|data| data.get(key))
+                .is_some_and(|value| !value.trim().is_empty())
+    };
+
+    references
+        .into_iter()
+        .filter(|(_, key)| !has_key(key))
+        .map(|(field, key)| {
+            ConfigValidationError::new(
+                field,
+                format!("Secret {}/{} has no {} key", namespace, SECRET_NAME, key),
+                format!(
+                    "Add the {} key to the Secret or disable the integration",
+                    key
+                ),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::ByteString;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn fields(errors: &[ConfigValidationError]) -> Vec<&str> {
+        errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn test_display() {
+        let error = ConfigValidationError::new("polling.interval", "0 is out of range", "Use 300");
+        assert_eq!(
+            error.to_string(),
+            "polling.interval: 0 is out of range. Use 300"
+        );
+    }
+
+    #[test]
+    fn test_validate_intervals() {
+        let mut config = HeadwindConfig::default();
+        assert!(validate_intervals(&config, env(&[])).is_empty());
+
+        config.polling.interval = 0;
+        let errors = validate_intervals(
+            &config,
+            env(&[
+                ("HEADWIND_POLLING_INTERVAL", "5m"),
+                ("HEADWIND_POLLING_MAX_CONCURRENT", "0"),
+            ]),
+        );
+        assert_eq!(
+            fields(&errors),
+            [
+                "polling.interval",
+                "HEADWIND_POLLING_INTERVAL",
+                "HEADWIND_POLLING_MAX_CONCURRENT"
+            ]
+        );
+        assert!(errors[1].message.contains("not a number"));
+
+        config.polling.interval = 60;
+        let errors = validate_intervals(&config, env(&[("HEADWIND_POLLING_INTERVAL", "604800")]));
+        assert_eq!(fields(&errors), ["HEADWIND_POLLING_INTERVAL"]);
+        assert!(errors[0].message.contains("out of range"));
+    }
+
+    #[test]
+    fn test_validate_consistency() {
+        let mut config = HeadwindConfig::default();
+        assert!(validate_consistency(&config, env(&[])).is_empty());
+
+        // Enabled in the ConfigMap, but the poller reads the environment
+        config.polling.enabled = true;
+        assert_eq!(
+            fields(&validate_consistency(&config, env(&[]))),
+            ["polling.enabled"]
+        );
+        assert!(
+            validate_consistency(&config, env(&[("HEADWIND_POLLING_ENABLED", "true")])).is_empty()
+        );
+        assert_eq!(
+            fields(&validate_consistency(
+                &config,
+                env(&[("HEADWIND_POLLING_ENABLED", "yes")])
+            )),
+            ["HEADWIND_POLLING_ENABLED", "polling.enabled"]
+        );
+
+        // Polling without the Vault credentials it was meant to use
+        let errors = validate_consistency(
+            &config,
+            env(&[
+                ("HEADWIND_POLLING_ENABLED", "true"),
+                ("HEADWIND_VAULT_ADDR", "https://vault:8200"),
+            ]),
+        );
+        assert_eq!(fields(&errors), ["HEADWIND_VAULT_ADDR"]);
+        assert!(
+            errors[0]
+                .message
+                .contains("HEADWIND_VAULT_ROLE, HEADWIND_VAULT_CREDENTIAL_PATH")
+        );
+
+        config.polling.enabled = false;
+        config.notifications.telegram.enabled = true;
+        config.notifications.victorops.enabled = true;
+        config.observability.metrics_backend = "graphite".to_string();
+        assert_eq!(
+            fields(&validate_consistency(&config, env(&[]))),
+            [
+                "telegram.chatId",
+                "victorops.routingKey",
+                "observability.metricsBackend"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_webhook_urls() {
+        let mut config = HeadwindConfig::default();
+        config.notifications.slack.webhook_url = Some("not a url".to_string());
+        // Disabled integrations aren't checked
+        assert!(validate_webhook_urls(&config).is_empty());
+
+        config.notifications.slack.enabled = true;
+        config.notifications.teams.enabled = true;
+        config.notifications.teams.webhook_url =
+            Some("https://example.webhook.office.com/webhookb2/abc".to_string());
+        config.notifications.webhook.enabled = true;
+        config.notifications.webhook.url = Some("ftp://hooks.example.com".to_string());
+        assert_eq!(
+            fields(&validate_webhook_urls(&config)),
+            [
+                "notifications.slack.webhookUrl",
+                "notifications.webhook.url"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_url_reachable() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().route("/hook", axum::routing::post(|| async { "ok" }));
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        // Rejecting a HEAD request still proves the webhook is reachable
+        let url = format!("http://{}/hook", addr);
+        assert!(
+            check_url_reachable(&url, REACHABILITY_TIMEOUT)
+                .await
+                .is_ok()
+        );
+
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+        let url = format!("http://{}/hook", closed_addr);
+        assert!(
+            check_url_reachable(&url, REACHABILITY_TIMEOUT)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_validate_secret_references() {
+        let mut config = HeadwindConfig::default();
+        assert!(validate_secret_references(&config, "headwind-system", None).is_empty());
+
+        config.notifications.slack.enabled = true;
+        config.notifications.telegram.enabled = true;
+
+        let errors = validate_secret_references(&config, "headwind-system", None);
+        assert_eq!(
+            fields(&errors),
+            [
+                "notifications.slack.webhookUrl",
+                "notifications.telegram.botToken"
+            ]
+        );
+        assert!(errors[0].message.contains("doesn't exist"));
+        assert!(errors[0].hint.contains("--from-literal=slack-webhook-url="));
+
+        let secret = Secret {
+            data: Some(BTreeMap::from([
+                (
+                    "slack-webhook-url".to_string(),
+                    ByteString(b"https://hooks.slack.com/services/T/B/X".to_vec()),
+                ),
+                ("telegram-bot-token".to_string(), ByteString(b" ".to_vec())),
+            ])),
+            ..Default::default()
+        };
+        let errors = validate_secret_references(&config, "headwind-system", Some(&secret));
+        assert_eq!(fields(&errors), ["notifications.telegram.botToken"]);
+        assert!(errors[0].message.contains("has no telegram-bot-token key"));
+    }
+}
@@ -0,0 +1,484 @@
+//! Batched approval of container image updates.
+//!
+//! Workloads annotated with `headwind.sh/batch-updates: "true"` collect the
+//! pending update of every container into a single `BatchUpdateRequest`
+//! instead of one `UpdateRequest` per container. Approving the batch applies
+//! all updates in one strategic merge patch; rejecting it rejects them all.
+
+use crate::models::crd::{
+    BatchUpdateRequest, BatchUpdateRequestSpec, SingleUpdate, TargetRef, UpdatePhase,
+    UpdatePolicyType, UpdateRequestStatus,
+};
+use crate::models::policy::annotations;
+use crate::notifications::{self, DeploymentInfo};
+use crate::rollback::RollbackManager;
+use anyhow::Result;
+use chrono::Utc;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::PodSpec;
+use kube::api::{Api, Patch, PatchParams, PostParams};
+use kube::client::Client;
+use serde_json::{Value, json};
+use tracing::{debug, info, warn};
+
+/// Attempts to merge into an existing batch before giving up on write conflicts
+const MAX_CONFLICT_RETRIES: usize = 3;
+
+/// Name of the batch collecting updates for a workload, e.g. `deployment-web-batch`
+pub fn batch_request_name(kind: &str, name: &str) -> String {
+    format!("{}-{}-batch", kind.to_lowercase(), name)
+}
+
+/// Add or replace the update for `update.container_name`.
+/// Returns false if the batch already contained exactly this update.
+pub fn merge_update(updates: &mut Vec<SingleUpdate>, update: SingleUpdate) -> bool {
+    match updates
+        .iter_mut()
+        .find(|u| u.container_name == update.container_name)
+    {
+        Some(existing) if *existing == update => false,
+        Some(existing) => {
+            *existing = update;
+            true
+        },
+        None => {
+            updates.push(update);
+            true
+        },
+    }
+}
+
+/// Strategic merge patch setting every container image in the batch at once
+pub fn build_batch_patch(updates: &[SingleUpdate], last_update: &str) -> Value {
+    let containers: Vec<Value> = updates
+        .iter()
+        .map(|u| json!({ "name": u.container_name, "image": u.new_image }))
+        .collect();
+
+    json!({
+        "metadata": {
+            "annotations": {
+                (annotations::LAST_UPDATE): last_update
+            }
+        },
+        "spec": {
+            "template": {
+                "spec": {
+                    "containers": containers
+                }
+            }
+        }
+    })
+}
+
+/// Add a container update to the pending batch for `target`, creating the
+/// batch if there is none or the previous one has finished
+pub async fn add_to_batch(
+    client: &Client,
+    target: TargetRef,
+    update: SingleUpdate,
+    policy: UpdatePolicyType,
+) -> Result<(), kube::Error> {
+    let batches: Api<BatchUpdateRequest> = Api::namespaced(client.clone(), &target.namespace);
+    let name = batch_request_name(&target.kind, &target.name);
+
+    for _ in 0..MAX_CONFLICT_RETRIES {
+        match batches.get_opt(&name).await? {
+            Some(mut existing) if is_pending(&existing) => {
+                if !merge_update(&mut existing.spec.updates, update.clone()) {
+                    debug!(
+                        "BatchUpdateRequest {}/{} already contains update for container {}",
+                        target.namespace, name, update.container_name
+                    );
+                    return Ok(());
+                }
+                existing.spec.reason = Some(batch_reason(&existing.spec.updates));
+
+                match batches
+                    .replace(&name, &PostParams::default(), &existing)
+                    .await
+                {
+                    Ok(_) => {
+                        info!(
+                            "Added container {} to BatchUpdateRequest {}/{}",
+                            update.container_name, target.namespace, name
+                        );
+                        return Ok(());
+                    },
+                    // Another container's update changed the batch first; re-read and merge again
+                    Err(kube::Error::Api(err)) if err.code == 409 => continue,
+                    Err(e) => return Err(e),
+                }
+            },
+            Some(existing) => {
+                info!(
+                    "Existing BatchUpdateRequest is in terminal state ({:?}), creating new one",
+                    existing.status.map(|s| s.phase).unwrap_or_default()
+                );
+                batches.delete(&name, &Default::default()).await?;
+                return create_batch(&batches, &name, target, update, policy.clone()).await;
+            },
+            None => match create_batch(
+                &batches,
+                &name,
+                target.clone(),
+                update.clone(),
+                policy.clone(),
+            )
+            .await
+            {
+                Err(kube::Error::Api(err)) if err.code == 409 => continue,
+                result => return result,
+            },
+        }
+    }
+
+    Err(kube::Error::Api(kube::core::ErrorResponse {
+        status: "Failure".to_string(),
+        message: format!(
+            "Gave up updating BatchUpdateRequest {}/{} after {} conflicts",
+            target.namespace, name, MAX_CONFLICT_RETRIES
+        ),
+        reason: "Conflict".to_string(),
+        code: 409,
+    }))
+}
+
+async fn create_batch(
+    batches: &Api<BatchUpdateRequest>,
+    name: &str,
+    target: TargetRef,
+    update: SingleUpdate,
+    policy: UpdatePolicyType,
+) -> Result<(), kube::Error> {
+    let deployment_info = DeploymentInfo {
+        name: target.name.clone(),
+        namespace: target.namespace.clone(),
+        current_image: update.current_image.clone(),
+        new_image: update.new_image.clone(),
+        container: Some(update.container_name.clone()),
+        resource_kind: Some(target.kind.clone()),
+    };
+
+    let policy_name = format!("{:?}", policy);
+    let updates = vec![update];
+    let batch = BatchUpdateRequest::new(
+        name,
+        BatchUpdateRequestSpec {
+            reason: Some(batch_reason(&updates)),
+            target_ref: target,
+            updates,
+            policy,
+            expires_at: Some(Utc::now() + chrono::Duration::hours(24)),
+        },
+    );
+
+    batches.create(&PostParams::default(), &batch).await?;
+    info!(
+        "Created BatchUpdateRequest {}/{} for {} {}",
+        deployment_info.namespace, name, batch.spec.target_ref.kind, deployment_info.name
+    );
+
+    notifications::notify_update_request_created(
+        deployment_info,
+        policy_name,
+        true,
+        name.to_string(),
+        None,
+        None,
+    );
+
+    Ok(())
+}
+
+fn is_pending(batch: &BatchUpdateRequest) -> bool {
+    batch
+        .status
+        .as_ref()
+        .is_none_or(|s| s.phase == UpdatePhase::Pending)
+}
+
+fn batch_reason(updates: &[SingleUpdate]) -> String {
+    format!("New versions available for {} container(s)", updates.len())
+}
+
+/// Apply every update in the batch to the target workload in a single patch
+pub async fn apply_batch(
+    client: &Client,
+    batch: &BatchUpdateRequest,
+    batch_name: &str,
+    approved_by: Option<String>,
+) -> Result<()> {
+    let spec = &batch.spec;
+    if spec.updates.is_empty() {
+        return Err(anyhow::anyhow!("BatchUpdateRequest has no updates"));
+    }
+    apply_container_updates(
+        client,
+        &spec.target_ref,
+        &spec.updates,
+        batch_name,
+        approved_by,
+    )
+    .await
+}
+
+/// Names of the containers in `updates` that `pod_spec` does not have
+pub fn missing_containers<'a>(
+    pod_spec: Option<&PodSpec>,
+    updates: &'a [SingleUpdate],
+) -> Vec<&'a str> {
+    updates
+        .iter()
+        .filter(|u| {
+            !pod_spec.is_some_and(|pod| pod.containers.iter().any(|c| c.name == u.container_name))
+        })
+        .map(|u| u.container_name.as_str())
+        .collect()
+}
+
+/// Refuse updates of containers the workload doesn't have, which the
+/// strategic merge patch would otherwise add as new containers
+fn ensure_containers_exist(
+    target: &TargetRef,
+    pod_spec: Option<&PodSpec>,
+    updates: &[SingleUpdate],
+) -> Result<()> {
+    let missing = missing_containers(pod_spec, updates);
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "Container(s) '{}' not found in {} {}/{}",
+        missing.join("', '"),
+        target.kind,
+        target.namespace,
+        target.name
+    ))
+}
+
+/// Apply `updates` to the containers of `target` in a single strategic merge
+/// patch, so they roll out together. Deployment updates are recorded in
+/// rollback history under `tracking_name`, the batch or UpdateRequest.
+pub async fn apply_container_updates(
+    client: &Client,
+    target: &TargetRef,
+    updates: &[SingleUpdate],
+    tracking_name: &str,
+    approved_by: Option<String>,
+) -> Result<()> {
+    let patch = Patch::Strategic(build_batch_patch(updates, &Utc::now().to_rfc3339()));
+    let params = PatchParams::default();
+
+    info!(
+        "Applying {} update(s) to {} {}/{}",
+        updates.len(),
+        target.kind,
+        target.namespace,
+        target.name
+    );
+
+    match target.kind.as_str() {
+        "Deployment" => {
+            let api: Api<Deployment> = Api::namespaced(client.clone(), &target.namespace);
+            let deployment = api.get(&target.name).await?;
+            ensure_containers_exist(
+                target,
+                deployment
+                    .spec
+                    .as_ref()
+                    .and_then(|s| s.template.spec.as_ref()),
+                updates,
+            )?;
+            api.patch(&target.name, &params, &patch).await?;
+
+            // Track each container in rollback history
+            let rollback_manager = RollbackManager::new(client.clone());
+            for update in updates {
+                if let Err(e) = rollback_manager
+                    .track_update(
+                        &target.name,
+                        &target.namespace,
+                        &update.container_name,
+                        &update.new_image,
+                        Some(tracking_name.to_string()),
+                        approved_by.clone(),
+                    )
+                    .await
+                {
+                    warn!(
+                        "Failed to track update in rollback history for {}/{}: {}",
+                        target.namespace, target.name, e
+                    );
+                }
+            }
+        },
+        "StatefulSet" => {
+            let api: Api<StatefulSet> = Api::namespaced(client.clone(), &target.namespace);
+            let statefulset = api.get(&target.name).await?;
+            ensure_containers_exist(
+                target,
+                statefulset
+                    .spec
+                    .as_ref()
+                    .and_then(|s| s.template.spec.as_ref()),
+                updates,
+            )?;
+            api.patch(&target.name, &params, &patch).await?;
+        },
+        "DaemonSet" => {
+            let api: Api<DaemonSet> = Api::namespaced(client.clone(), &target.namespace);
+            let daemonset = api.get(&target.name).await?;
+            ensure_containers_exist(
+                target,
+                daemonset
+                    .spec
+                    .as_ref()
+                    .and_then(|s| s.template.spec.as_ref()),
+                updates,
+            )?;
+            api.patch(&target.name, &params, &patch).await?;
+        },
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unsupported resource kind for multi-container updates: {}. Only Deployment, StatefulSet, and DaemonSet are supported.",
+                target.kind
+            ));
+        },
+    }
+
+    Ok(())
+}
+
+/// Status for a batch that was approved, successfully applied or not
+pub fn approved_status(approved_by: Option<String>, result: &Result<()>) -> UpdateRequestStatus {
+    let (phase, message) = match result {
+        Ok(()) => (
+            UpdatePhase::Completed,
+            "Batch update applied successfully".to_string(),
+        ),
+        Err(e) => (UpdatePhase::Failed, format!("Batch update failed: {}", e)),
+    };
+
+    UpdateRequestStatus {
+        phase,
+        approved_by,
+        approved_at: Some(Utc::now()),
+        message: Some(message),
+        last_updated: Some(Utc::now()),
+        ..Default::default()
+    }
+}
+
+/// Status for a rejected batch
+pub fn rejected_status(rejected_by: Option<String>, reason: Option<String>) -> UpdateRequestStatus {
+    UpdateRequestStatus {
+        phase: UpdatePhase::Rejected,
+        rejected_by,
+        rejected_at: Some(Utc::now()),
+        message: reason
+            .filter(|reason| !reason.trim().is_empty())
+            .or(Some("Rejected by user".to_string())),
+        last_updated: Some(Utc::now()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(container: &str, new_image: &str) -> SingleUpdate {
+        SingleUpdate {
+            container_name: container.to_string(),
+            current_image: format!("{}:1.0.0", container),
+            new_image: new_image.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_batch_request_name() {
+        assert_eq!(
+            batch_request_name("Deployment", "web"),
+            "deployment-web-batch"
+        );
+        assert_eq!(
+            batch_request_name("DaemonSet", "agent"),
+            "daemonset-agent-batch"
+        );
+    }
+
+    #[test]
+    fn test_merge_update() {
+        let mut updates = Vec::new();
+        assert!(merge_update(&mut updates, update("app", "app:1.0.1")));
+        assert!(merge_update(
+            &mut updates,
+            update("sidecar", "sidecar:2.0.0")
+        ));
+        assert_eq!(updates.len(), 2);
+
+        // Same update again is a no-op
+        assert!(!merge_update(&mut updates, update("app", "app:1.0.1")));
+
+        // A newer version replaces the container's previous entry
+        assert!(merge_update(&mut updates, update("app", "app:1.0.2")));
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].new_image, "app:1.0.2");
+        assert_eq!(updates[1].container_name, "sidecar");
+    }
+
+    #[test]
+    fn test_build_batch_patch() {
+        let updates = vec![
+            update("app", "app:1.0.1"),
+            update("sidecar", "sidecar:2.0.0"),
+        ];
+        let patch = build_batch_patch(&updates, "2026-01-01T00:00:00+00:00");
+
+        assert_eq!(
+            patch["spec"]["template"]["spec"]["containers"],
+            json!([
+                { "name": "app", "image": "app:1.0.1" },
+                { "name": "sidecar", "image": "sidecar:2.0.0" }
+            ])
+        );
+        assert_eq!(
+            patch["metadata"]["annotations"][annotations::LAST_UPDATE],
+            "2026-01-01T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_missing_containers() {
+        let pod_spec = PodSpec {
+            containers: ["app", "sidecar"]
+                .into_iter()
+                .map(|name| k8s_openapi::api::core::v1::Container {
+                    name: name.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let updates = vec![
+            update("app", "app:1.0.1"),
+            update("sidecar", "sidecar:2.0.0"),
+        ];
+        assert!(missing_containers(Some(&pod_spec), &updates).is_empty());
+
+        let updates = vec![update("app", "app:1.0.1"), update("worker", "worker:1.1.0")];
+        assert_eq!(
+            missing_containers(Some(&pod_spec), &updates),
+            vec!["worker"]
+        );
+        assert_eq!(missing_containers(None, &updates), vec!["app", "worker"]);
+    }
+
+    #[test]
+    fn test_rejected_status_defaults_message() {
+        let status = rejected_status(Some("alice".to_string()), Some("  ".to_string()));
+        assert_eq!(status.phase, UpdatePhase::Rejected);
+        assert_eq!(status.message.as_deref(), Some("Rejected by user"));
+    }
+}
@@ -0,0 +1,539 @@
+//! Cleanup of finished UpdateRequests.
+//!
+//! Every `HEADWIND_CLEANUP_INTERVAL_HOURS` (default 24) UpdateRequests that
+//! are Completed, Rejected, Failed or Expired, and have not changed for
+//! `HEADWIND_CLEANUP_RETENTION_DAYS` (default 30), are deleted. Requests
+//! annotated `headwind.sh/preserve: "true"` are kept.
+//!
+//! With `HEADWIND_CLEANUP_ARCHIVE_ENABLED=true` each cycle first writes the
+//! requests it deletes, as gzipped JSON Lines, to new ConfigMaps
+//! `headwind-archive-<time>-<part>` in Headwind's namespace, each holding at
+//! most [`MAX_ARCHIVE_PART_BYTES`] of JSON so it stays under the 1 MiB limit
+//! of a ConfigMap. Only the newest `HEADWIND_CLEANUP_ARCHIVE_MAX_CONFIGMAPS`
+//! (default 30) archive ConfigMaps are kept. Nothing is deleted if the archive
+//! can't be written.
+//!
+//! With `HEADWIND_ARCHIVE_S3_BUCKET` set each request is also uploaded to S3
+//! (see [`crate::archive::s3`]) before it is deleted. Requests that fail to
+//! upload are kept and retried in the next cycle.
+
+use crate::archive::{Archiver, S3Archiver, gzip};
+use crate::config::headwind_namespace;
+use crate::metrics::{CLEANUP_ARCHIVED_TOTAL, CLEANUP_DELETED_TOTAL};
+use crate::models::annotations;
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::api::{DeleteParams, ListParams, PostParams};
+use kube::{Api, Client, ResourceExt};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Name prefix of the ConfigMaps receiving the archived UpdateRequests
+pub const ARCHIVE_CONFIGMAP: &str = "headwind-archive";
+
+/// Label marking archive ConfigMaps, so old ones can be pruned
+pub const ARCHIVE_LABEL: &str = "headwind.sh/archive";
+
+/// Most uncompressed JSON archived in one ConfigMap. Even data that doesn't
+/// compress at all stays well under the 1 MiB limit once gzipped.
+pub const MAX_ARCHIVE_PART_BYTES: usize = 512 * 1024;
+
+/// Phases after which an UpdateRequest never changes again
+const TERMINAL_PHASES: &[UpdatePhase] = &[
+    UpdatePhase::Completed,
+    UpdatePhase::Rejected,
+    UpdatePhase::Failed,
+    UpdatePhase::Expired,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CleanupConfig {
+    pub interval: Duration,
+    pub retention: chrono::Duration,
+    pub archive: bool,
+    /// Archive ConfigMaps kept, oldest deleted first
+    pub archive_max_configmaps: usize,
+}
+
+impl Default for CleanupConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(24 * 3600),
+            retention: chrono::Duration::days(30),
+            archive: false,
+            archive_max_configmaps: 30,
+        }
+    }
+}
+
+impl CleanupConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let env = |name| std::env::var(name).ok();
+        Self {
+            interval: env("HEADWIND_CLEANUP_INTERVAL_HOURS")
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|hours| *hours > 0)
+                .map(|hours| Duration::from_secs(hours * 3600))
+                .unwrap_or(default.interval),
+            retention: env("HEADWIND_CLEANUP_RETENTION_DAYS")
+                .and_then(|v| v.parse::<i64>().ok())
+                .filter(|days| *days > 0)
+                .map(chrono::Duration::days)
+                .unwrap_or(default.retention),
+            archive: env("HEADWIND_CLEANUP_ARCHIVE_ENABLED")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.archive),
+            archive_max_configmaps: env("HEADWIND_CLEANUP_ARCHIVE_MAX_CONFIGMAPS")
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|max| *max > 0)
+                .unwrap_or(default.archive_max_configmaps),
+        }
+    }
+}
+
+/// When `update_request` last changed: its status update time, or its creation
+fn last_change(update_request: &UpdateRequest) -> Option<DateTime<Utc>> {
+    update_request
+        .status
+        .as_ref()
+        .and_then(|status| status.last_updated)
+        .or_else(|| {
+            update_request
+                .metadata
+                .creation_timestamp
+                .as_ref()
+                .map(|t| t.0)
+        })
+}
+
+/// Whether `update_request` is finished, older than `retention` at `now`
+/// and not annotated `headwind.sh/preserve: "true"`
+pub fn is_expired(
+    update_request: &UpdateRequest,
+    now: DateTime<Utc>,
+    retention: chrono::Duration,
+) -> bool {
+    let terminal = update_request
+        .status
+        .as_ref()
+        .is_some_and(|status| TERMINAL_PHASES.contains(&status.phase));
+    let preserved = update_request
+        .annotations()
+        .get(annotations::PRESERVE)
+        .is_some_and(|value| value == "true");
+    let old = last_change(update_request).is_some_and(|changed| now - changed > retention);
+    terminal && old && !preserved
+}
+
+/// The UpdateRequests of `update_requests` to delete at `now`
+pub fn cleanup_candidates(
+    update_requests: &[UpdateRequest],
+    now: DateTime<Utc>,
+    retention: chrono::Duration,
+) -> Vec<&UpdateRequest> {
+    update_requests
+        .iter()
+        .filter(|update_request| is_expired(update_request, now, retention))
+        .collect()
+}
+
+/// The UpdateRequests of `update_requests` that `archiver` stored. The others
+/// are logged and left for a later cleanup.
+pub async fn archive_each<'a>(
+    archiver: &dyn Archiver,
+    update_requests: Vec<&'a UpdateRequest>,
+) -> Vec<&'a UpdateRequest> {
+    let mut archived = Vec::with_capacity(update_requests.len());
+    for update_request in update_requests {
+        match archiver.archive(update_request).await {
+            Ok(location) => {
+                info!(
+                    "Archived UpdateRequest {}/{} to {}",
+                    update_request.namespace().unwrap_or_default(),
+                    update_request.name_any(),
+                    location
+                );
+                archived.push(update_request);
+            },
+            Err(e) => warn!(
+                "Failed to archive UpdateRequest {}/{}, keeping it: {:#}",
+                update_request.namespace().unwrap_or_default(),
+                update_request.name_any(),
+                e
+            ),
+        }
+    }
+    archived
+}
+
+pub struct CleanupController {
+    client: Client,
+    config: CleanupConfig,
+    archiver: Option<Box<dyn Archiver>>,
+}
+
+impl CleanupController {
+    pub async fn new() -> Result<Self> {
+        let client = Client::try_default().await?;
+        let archiver = S3Archiver::from_env()
+            .context("Invalid S3 archive configuration")?
+            .map(|archiver| Box::new(archiver) as Box<dyn Archiver>);
+        Ok(Self {
+            client,
+            config: CleanupConfig::from_env(),
+            archiver,
+        })
+    }
+
+    pub async fn run(self) {
+        info!(
+            "UpdateRequest cleanup starting: every {}h, retention {} days, archive {}, S3 archive {}",
+            self.config.interval.as_secs() / 3600,
+            self.config.retention.num_days(),
+            if self.config.archive {
+                "enabled"
+            } else {
+                "disabled"
+            },
+            if self.archiver.is_some() {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+
+        loop {
+            if let Err(e) = self.cleanup(Utc::now()).await {
+                error!("UpdateRequest cleanup failed: {:#}", e);
+            }
+            tokio::time::sleep(self.config.interval).await;
+        }
+    }
+
+    /// Delete, after archiving if enabled, the UpdateRequests expired at `now`
+    async fn cleanup(&self, now: DateTime<Utc>) -> Result<()> {
+        let api: Api<UpdateRequest> = super::watched_api(self.client.clone());
+        let update_requests = api
+            .list(&ListParams::default())
+            .await
+            .context("Failed to list UpdateRequests")?
+            .items;
+        let mut expired = cleanup_candidates(&update_requests, now, self.config.retention);
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        if self.config.archive {
+            self.archive(&expired, now)
+                .await
+                .context("Failed to archive UpdateRequests, none deleted")?;
+            CLEANUP_ARCHIVED_TOTAL.inc_by(expired.len() as u64);
+        }
+        if let Some(archiver) = &self.archiver {
+            expired = archive_each(archiver.as_ref(), expired).await;
+        }
+
+        for update_request in expired {
+            let namespace = update_request.namespace().unwrap_or_default();
+            let name = update_request.name_any();
+            let api: Api<UpdateRequest> = Api::namespaced(self.client.clone(), &namespace);
+            match api.delete(&name, &DeleteParams::default()).await {
+                Ok(_) => {
+                    info!("Deleted expired UpdateRequest {}/{}", namespace, name);
+                    CLEANUP_DELETED_TOTAL.inc();
+                },
+                Err(kube::Error::Api(e)) if e.code == 404 => {},
+                Err(e) => warn!(
+                    "Failed to delete expired UpdateRequest {}/{}: {}",
+                    namespace, name, e
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the archive of this cycle to new ConfigMaps, then prune the
+    /// oldest archive ConfigMaps
+    async fn archive(&self, update_requests: &[&UpdateRequest], now: DateTime<Utc>) -> Result<()> {
+        let api: Api<ConfigMap> = Api::namespaced(self.client.clone(), &headwind_namespace());
+        let key = archive_key(now);
+
+        let parts = archive_parts(update_requests)?;
+        for (part, lines) in parts.iter().enumerate() {
+            let name = archive_configmap_name(now, part);
+            let data = base64::engine::general_purpose::STANDARD.encode(gzip(lines.as_bytes()));
+            let configmap: ConfigMap = serde_json::from_value(serde_json::json!({
+                "metadata": {
+                    "name": &name,
+                    "labels": { ARCHIVE_LABEL: "true" }
+                },
+                "binaryData": { &key: data }
+            }))?;
+            match api.create(&PostParams::default(), &configmap).await {
+                Ok(_) => {},
+                // Written by an earlier attempt of this cycle
+                Err(kube::Error::Api(e)) if e.code == 409 => {
+                    api.replace(&name, &PostParams::default(), &configmap)
+                        .await?;
+                },
+                Err(e) => return Err(e.into()),
+            }
+        }
+        info!(
+            "Archived {} UpdateRequests to {} ConfigMap(s) {}-{}-*",
+            update_requests.len(),
+            parts.len(),
+            ARCHIVE_CONFIGMAP,
+            archive_stamp(now)
+        );
+
+        if let Err(e) = self.prune_archives(&api).await {
+            warn!("Failed to prune archive ConfigMaps: {:#}", e);
+        }
+        Ok(())
+    }
+
+    /// Delete all but the newest `archive_max_configmaps` archive ConfigMaps
+    async fn prune_archives(&self, api: &Api<ConfigMap>) -> Result<()> {
+        let names: Vec<String> = api
+            .list_metadata(&ListParams::default().labels(&format!("{}=true", ARCHIVE_LABEL)))
+            .await?
+            .items
+            .iter()
+            .map(|configmap| configmap.name_any())
+            .collect();
+        for name in archives_to_prune(names, self.config.archive_max_configmaps) {
+            match api.delete(&name, &DeleteParams::default()).await {
+                Ok(_) => info!("Deleted archive ConfigMap {}", name),
+                Err(kube::Error::Api(e)) if e.code == 404 => {},
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// JSON Lines of `update_requests`, split into parts of at most
+/// [`MAX_ARCHIVE_PART_BYTES`]. A single larger UpdateRequest gets a part of
+/// its own.
+pub fn archive_parts(update_requests: &[&UpdateRequest]) -> Result<Vec<String>> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for update_request in update_requests {
+        let line = serde_json::to_string(update_request)? + "\n";
+        if !current.is_empty() && current.len() + line.len() > MAX_ARCHIVE_PART_BYTES {
+            parts.push(std::mem::take(&mut current));
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    Ok(parts)
+}
+
+/// Names of the archive ConfigMaps to delete to keep the newest `keep`.
+/// Names sort by the time of their cleanup.
+pub fn archives_to_prune(mut names: Vec<String>, keep: usize) -> Vec<String> {
+    names.sort();
+    let excess = names.len().saturating_sub(keep);
+    names.truncate(excess);
+    names
+}
+
+/// Time of a cleanup as used in archive names, sorting chronologically
+fn archive_stamp(now: DateTime<Utc>) -> String {
+    now.format("%Y%m%dt%H%M%Sz").to_string()
+}
+
+/// Name of part `part` of the archive ConfigMaps written at `now`
+pub fn archive_configmap_name(now: DateTime<Utc>, part: usize) -> String {
+    format!("{}-{}-{}", ARCHIVE_CONFIGMAP, archive_stamp(now), part)
+}
+
+/// `binaryData` key of the archive written at `now`
+pub fn archive_key(now: DateTime<Utc>) -> String {
+    format!("update-requests-{}.jsonl.gz", now.format("%Y%m%dT%H%M%SZ"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+    fn update_request(phase: UpdatePhase, last_updated: DateTime<Utc>) -> UpdateRequest {
+        let mut update_request = testing::update_request_in_phase("default", "web-update", phase);
+        if let Some(status) = update_request.status.as_mut() {
+            status.last_updated = Some(last_updated);
+        }
+        update_request
+    }
+
+    #[test]
+    fn test_age_based_filtering() {
+        let now = Utc::now();
+        let retention = chrono::Duration::days(30);
+        let old = now - chrono::Duration::days(31);
+        let recent = now - chrono::Duration::days(29);
+
+        assert!(is_expired(
+            &update_request(UpdatePhase::Completed, old),
+            now,
+            retention
+        ));
+        assert!(is_expired(
+            &update_request(UpdatePhase::Rejected, old),
+            now,
+            retention
+        ));
+        assert!(is_expired(
+            &update_request(UpdatePhase::Failed, old),
+            now,
+            retention
+        ));
+        assert!(is_expired(
+            &update_request(UpdatePhase::Expired, old),
+            now,
+            retention
+        ));
+        assert!(!is_expired(
+            &update_request(UpdatePhase::Completed, recent),
+            now,
+            retention
+        ));
+        // Requests that can still change are never deleted
+        assert!(!is_expired(
+            &update_request(UpdatePhase::Pending, old),
+            now,
+            retention
+        ));
+        assert!(!is_expired(
+            &update_request(UpdatePhase::Approved, old),
+            now,
+            retention
+        ));
+
+        // Without a status update time, the creation time counts
+        let mut created = update_request(UpdatePhase::Completed, now);
+        created.status.as_mut().unwrap().last_updated = None;
+        created.metadata.creation_timestamp = Some(Time(old));
+        assert!(is_expired(&created, now, retention));
+        created.metadata.creation_timestamp = None;
+        assert!(!is_expired(&created, now, retention));
+    }
+
+    #[test]
+    fn test_preserve_annotation_is_skipped() {
+        let now = Utc::now();
+        let retention = chrono::Duration::days(30);
+        let old = now - chrono::Duration::days(90);
+
+        let mut preserved = update_request(UpdatePhase::Completed, old);
+        preserved
+            .annotations_mut()
+            .insert(annotations::PRESERVE.to_string(), "true".to_string());
+        let mut not_preserved = update_request(UpdatePhase::Failed, old);
+        not_preserved
+            .annotations_mut()
+            .insert(annotations::PRESERVE.to_string(), "false".to_string());
+
+        let update_requests = vec![preserved, not_preserved];
+        let candidates = cleanup_candidates(&update_requests, now, retention);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(
+            candidates[0].status.as_ref().unwrap().phase,
+            UpdatePhase::Failed
+        );
+    }
+
+    struct FailingFor(&'static str);
+
+    #[async_trait::async_trait]
+    impl Archiver for FailingFor {
+        async fn archive(&self, ur: &UpdateRequest) -> Result<String> {
+            if ur.name_any() == self.0 {
+                anyhow::bail!("connection refused");
+            }
+            Ok(format!("archive/{}.json.gz", ur.name_any()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_archived_requests_are_deleted() {
+        let old = Utc::now() - chrono::Duration::days(90);
+        let mut first = update_request(UpdatePhase::Completed, old);
+        first.metadata.name = Some("first".to_string());
+        let mut second = update_request(UpdatePhase::Failed, old);
+        second.metadata.name = Some("second".to_string());
+
+        let archived = archive_each(&FailingFor("first"), vec![&first, &second]).await;
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].name_any(), "second");
+    }
+
+    #[test]
+    fn test_archive_key() {
+        assert_eq!(
+            archive_key("2026-10-15T03:00:00Z".parse().unwrap()),
+            "update-requests-20261015T030000Z.jsonl.gz"
+        );
+    }
+
+    #[test]
+    fn test_archive_configmap_name() {
+        let now = "2026-10-15T03:00:00Z".parse().unwrap();
+        assert_eq!(
+            archive_configmap_name(now, 0),
+            "headwind-archive-20261015t030000z-0"
+        );
+        assert!(archive_configmap_name(now, 1) < archive_configmap_name(now, 2));
+    }
+
+    #[test]
+    fn test_archive_parts_stay_under_configmap_limit() {
+        let old = Utc::now() - chrono::Duration::days(90);
+        let mut large = update_request(UpdatePhase::Completed, old);
+        large.spec.reason = Some("x".repeat(100 * 1024));
+        let update_requests: Vec<UpdateRequest> = (0..12).map(|_| large.clone()).collect();
+        let refs: Vec<&UpdateRequest> = update_requests.iter().collect();
+
+        let parts = archive_parts(&refs).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts.iter().map(|p| p.lines().count()).sum::<usize>(), 12);
+        for part in &parts {
+            assert!(part.len() <= MAX_ARCHIVE_PART_BYTES);
+            // binaryData is limited to 1 MiB in total
+            assert!(gzip(part.as_bytes()).len() < 1024 * 1024);
+        }
+
+        assert!(archive_parts(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_oldest_archives_are_pruned() {
+        let names: Vec<String> = [
+            "headwind-archive-20261015t030000z-0",
+            "headwind-archive-20261013t030000z-0",
+            "headwind-archive-20261014t030000z-1",
+            "headwind-archive-20261014t030000z-0",
+        ]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+
+        assert_eq!(
+            archives_to_prune(names.clone(), 2),
+            vec![
+                "headwind-archive-20261013t030000z-0".to_string(),
+                "headwind-archive-20261014t030000z-0".to_string(),
+            ]
+        );
+        assert!(archives_to_prune(names, 4).is_empty());
+    }
+}
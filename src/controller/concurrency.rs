@@ -0,0 +1,146 @@
+//! Per-controller reconcile concurrency.
+//!
+//! `HEADWIND_<CONTROLLER>_CONCURRENCY` caps how many objects a controller
+//! reconciles at the same time, e.g. `HEADWIND_DEPLOYMENT_CONCURRENCY=4` or
+//! `HEADWIND_SCHEDULED_APPROVAL_CONCURRENCY=2`. Unset keeps the kube-runtime
+//! default of no limit. Reconciles of the same object never overlap either way.
+
+use crate::metrics::RECONCILE_CONCURRENT_IN_FLIGHT;
+use kube::runtime::controller::Config;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+/// Names of the controllers started by `start_controllers`
+pub const CONTROLLERS: &[&str] = &[
+    "deployment",
+    "statefulset",
+    "daemonset",
+    "helm",
+    "replicaset",
+    "scheduled-approval",
+    "knative",
+    "crossplane",
+];
+
+/// Above this, concurrent reconciles are likely to run into API server rate limits
+const HIGH_CONCURRENCY: u16 = 10;
+
+/// Reconciles currently running, per controller
+static IN_FLIGHT: Lazy<Mutex<HashMap<&'static str, Arc<AtomicUsize>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn env_var(controller: &str) -> String {
+    format!(
+        "HEADWIND_{}_CONCURRENCY",
+        controller.to_uppercase().replace('-', "_")
+    )
+}
+
+/// Configured concurrency of `controller`, `None` when unlimited
+pub fn concurrency(controller: &str) -> Option<u16> {
+    let var = env_var(controller);
+    let value = std::env::var(&var).ok().filter(|v| !v.is_empty())?;
+    match value.parse::<u16>() {
+        Ok(0) => None,
+        Ok(n) => Some(n),
+        Err(_) => {
+            warn!("Invalid {}={}, reconciles are not limited", var, value);
+            None
+        },
+    }
+}
+
+/// Controller config applying the configured concurrency of `controller`
+pub fn config(controller: &str) -> Config {
+    match concurrency(controller) {
+        Some(n) => Config::default().concurrency(n),
+        None => Config::default(),
+    }
+}
+
+/// Log the configured concurrency of every controller at startup
+pub fn log_settings() {
+    for controller in CONTROLLERS {
+        let Some(n) = concurrency(controller) else {
+            continue;
+        };
+        info!(
+            "{} controller reconciles up to {} objects at once",
+            controller, n
+        );
+        if n > HIGH_CONCURRENCY {
+            warn!(
+                "{}={} is above {}; concurrent reconciles may hit Kubernetes API rate limits",
+                env_var(controller),
+                n,
+                HIGH_CONCURRENCY
+            );
+        }
+    }
+}
+
+/// Guard counting a running reconcile; decrements the in-flight gauge on drop
+pub struct InFlight {
+    controller: &'static str,
+    count: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let n = self.count.fetch_sub(1, Ordering::SeqCst) - 1;
+        RECONCILE_CONCURRENT_IN_FLIGHT
+            .with_label_values(&[self.controller])
+            .set(n as i64);
+    }
+}
+
+/// Call at the start of a reconcile; the reconcile counts as in flight until
+/// the returned guard is dropped
+pub fn track(controller: &'static str) -> InFlight {
+    let count = IN_FLIGHT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .entry(controller)
+        .or_default()
+        .clone();
+    let n = count.fetch_add(1, Ordering::SeqCst) + 1;
+    RECONCILE_CONCURRENT_IN_FLIGHT
+        .with_label_values(&[controller])
+        .set(n as i64);
+    InFlight { controller, count }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var_names() {
+        assert_eq!(env_var("deployment"), "HEADWIND_DEPLOYMENT_CONCURRENCY");
+        assert_eq!(
+            env_var("scheduled-approval"),
+            "HEADWIND_SCHEDULED_APPROVAL_CONCURRENCY"
+        );
+    }
+
+    #[test]
+    fn test_in_flight_gauge() {
+        let gauge = || {
+            RECONCILE_CONCURRENT_IN_FLIGHT
+                .with_label_values(&["in-flight-test"])
+                .get()
+        };
+
+        let first = track("in-flight-test");
+        let second = track("in-flight-test");
+        assert_eq!(gauge(), 2);
+
+        drop(first);
+        assert_eq!(gauge(), 1);
+        drop(second);
+        assert_eq!(gauge(), 0);
+    }
+}
@@ -0,0 +1,594 @@
+use crate::metrics::{RECONCILE_DURATION, RECONCILE_ERRORS};
+use crate::models::{
+    Composition, CompositionImageRef, EventSource, ResourcePolicy, TargetRef, UpdatePolicy,
+    UpdatePolicyType, UpdateRequest, UpdateRequestSpec, UpdateType, annotations,
+    global_tag_normalization, parse_tag_normalization,
+};
+use crate::notifications::{self, DeploymentInfo};
+use crate::policy::PolicyEngine;
+use anyhow::Result;
+use chrono::Utc;
+use futures::StreamExt;
+use kube::{
+    ResourceExt,
+    api::{Api, Patch, PatchParams, PostParams},
+    client::Client,
+    runtime::controller::Action,
+};
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument};
+
+pub struct CompositionController {
+    client: Client,
+}
+
+impl CompositionController {
+    pub async fn new() -> Result<Self> {
+        let client = Client::try_default().await?;
+
+        Ok(Self { client })
+    }
+
+    pub async fn run(self) {
+        info!("Crossplane Composition controller starting...");
+
+        // Run the controller in a loop with exponential backoff
+        // This handles transient errors during startup or runtime
+        let mut backoff_seconds = 1;
+        const MAX_BACKOFF: u64 = 60;
+
+        loop {
+            // Compositions are cluster-scoped
+            let compositions: Api<Composition> = Api::all(self.client.clone());
+
+            info!("Creating controller for crossplane compositions");
+
+            let result = super::queue_metrics::controller(compositions, "crossplane")
+                .run(reconcile, error_policy, Arc::new(()))
+                .for_each(|res| async move {
+                    match res {
+                        Ok((obj_ref, _action)) => {
+                            info!("Reconciled composition: {}", obj_ref.name);
+                        },
+                        Err(e) => {
+                            // Log reconciliation errors but continue processing
+                            error!("Reconciliation error: {}", e);
+                            RECONCILE_ERRORS.inc();
+                        },
+                    }
+                })
+                .await;
+
+            // If the controller stream ends, log it and restart after backoff
+            error!(
+                "Crossplane Composition controller stream ended, restarting in {}s...",
+                backoff_seconds
+            );
+            tokio::time::sleep(Duration::from_secs(backoff_seconds)).await;
+
+            // Exponential backoff up to MAX_BACKOFF seconds
+            backoff_seconds = (backoff_seconds * 2).min(MAX_BACKOFF);
+
+            debug!("Controller loop result: {:?}", result);
+        }
+    }
+}
+
+/// Validate the Composition's policy; updates come from image push events
+/// (see [`handle_image_update`]) and `headwind_crossplane_compositions_watched`
+/// is counted by the resource gauge updater
+#[instrument(skip(_ctx, composition), fields(composition = %composition.name_any()))]
+async fn reconcile(composition: Arc<Composition>, _ctx: Arc<()>) -> Result<Action, kube::Error> {
+    let _timer = RECONCILE_DURATION.start_timer();
+    let _queue_timer = super::queue_metrics::start_reconcile("crossplane", composition.as_ref());
+    let _in_flight = super::concurrency::track("crossplane");
+
+    let name = composition.name_any();
+
+    debug!("Reconciling composition {} - starting", name);
+
+    // Check if this composition has headwind annotations
+    let Some(annotations) = composition.metadata.annotations.as_ref() else {
+        debug!("Composition {} has no annotations, skipping", name);
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    };
+
+    if !annotations.contains_key(annotations::POLICY) {
+        debug!(
+            "Composition {} has no headwind policy annotation, skipping",
+            name
+        );
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    }
+
+    let policy = match parse_policy_from_annotations(annotations) {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Failed to parse policy for composition {}: {}", name, e);
+            return Err(create_error(&format!("Failed to parse policy: {}", e)));
+        },
+    };
+
+    debug!(
+        "Composition {} has policy {:?} and {} image reference(s)",
+        name,
+        policy.policy,
+        composition.image_refs().len()
+    );
+
+    debug!("Composition {} reconciliation complete", name);
+
+    Ok(Action::requeue(Duration::from_secs(300)))
+}
+
+fn error_policy(_object: Arc<Composition>, _error: &kube::Error, _ctx: Arc<()>) -> Action {
+    // Requeue after 60 seconds on errors
+    Action::requeue(Duration::from_secs(60))
+}
+
+/// Helper to create a kube::Error from a string message
+fn create_error(msg: &str) -> kube::Error {
+    kube::Error::Api(kube::error::ErrorResponse {
+        status: "Failure".to_string(),
+        message: msg.to_string(),
+        reason: "InvalidConfiguration".to_string(),
+        code: 400,
+    })
+}
+
+/// Parse an image string into (image_name, tag)
+/// Example: "myregistry.com/myimage:v1.2.3" -> ("myregistry.com/myimage", "v1.2.3")
+fn parse_image(image: &str) -> Result<(String, String), String> {
+    let parts: Vec<&str> = image.rsplitn(2, ':').collect();
+    if parts.len() != 2 {
+        return Err(format!("Invalid image format: {}", image));
+    }
+    Ok((parts[1].to_string(), parts[0].to_string()))
+}
+
+/// Image references in the composition that belong to `image`
+fn matching_refs(composition: &Composition, image: &str) -> Vec<CompositionImageRef> {
+    composition
+        .image_refs()
+        .into_iter()
+        .filter(|r| match parse_image(&r.image) {
+            Ok((name, _)) => name == image,
+            Err(_) => false,
+        })
+        .collect()
+}
+
+/// Handle an available image update for a Crossplane Composition
+/// This is called for image push events from webhooks and registry polling
+#[instrument(skip(client, policy_engine, composition))]
+pub async fn handle_image_update(
+    client: &Client,
+    policy_engine: &Arc<PolicyEngine>,
+    composition: &Composition,
+    image: &str,
+    new_version: &str,
+) -> Result<()> {
+    let name = composition.name_any();
+
+    info!(
+        "Handling image update for composition {}: {} -> {}",
+        name, image, new_version
+    );
+
+    let annotations = composition
+        .metadata
+        .annotations
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Composition has no annotations"))?;
+
+    let policy = parse_policy_from_annotations(annotations)?;
+
+    let refs = matching_refs(composition, image);
+    let first = refs
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("Image {} not referenced by composition", image))?;
+    let (_, current_version) = parse_image(&first.image).map_err(|e| anyhow::anyhow!(e))?;
+
+    let should_update = policy_engine
+        .should_update(&policy, &current_version, new_version)
+        .map_err(|e| anyhow::anyhow!("Policy evaluation failed: {}", e))?;
+
+    if !should_update {
+        info!(
+            "Update from {} to {} rejected by policy {:?}",
+            current_version, new_version, policy.policy
+        );
+        return Ok(());
+    }
+
+    // Check minimum update interval
+    if let (Some(min_interval), Some(last_update_str)) = (
+        policy.min_update_interval,
+        annotations.get(annotations::LAST_UPDATE),
+    ) && let Ok(last_update) = chrono::DateTime::parse_from_rfc3339(last_update_str)
+    {
+        let elapsed = Utc::now().signed_duration_since(last_update.with_timezone(&Utc));
+        let min_duration = chrono::Duration::seconds(min_interval as i64);
+
+        if elapsed < min_duration {
+            info!(
+                "Skipping update for composition {}: minimum interval not met ({} < {} seconds)",
+                name,
+                elapsed.num_seconds(),
+                min_interval
+            );
+            return Ok(());
+        }
+    }
+
+    if policy.require_approval {
+        if !super::rate_limit::allow_update_request("Composition", "", &name, Some(annotations)) {
+            return Ok(());
+        }
+
+        info!(
+            "Creating UpdateRequest for composition {}: {} -> {}",
+            name, current_version, new_version
+        );
+
+        create_update_request(
+            client,
+            &name,
+            first.resource_name.clone(),
+            image,
+            &current_version,
+            new_version,
+            &policy,
+            annotations,
+        )
+        .await?;
+    } else {
+        info!(
+            "Auto-updating composition {} (no approval required): {} -> {}",
+            name, current_version, new_version
+        );
+
+        update_composition_image(client, &name, image, new_version).await?;
+
+        notifications::notify_update_completed(DeploymentInfo {
+            name: name.clone(),
+            namespace: String::new(),
+            current_image: format!("{}:{}", image, current_version),
+            new_image: format!("{}:{}", image, new_version),
+            container: first.resource_name.clone(),
+            resource_kind: Some("Composition".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Create an UpdateRequest CRD for a pending update.
+/// Compositions are cluster-scoped, so the request lives in headwind's own namespace.
+#[allow(clippy::too_many_arguments)]
+async fn create_update_request(
+    client: &Client,
+    name: &str,
+    resource_name: Option<String>,
+    image: &str,
+    current_version: &str,
+    new_version: &str,
+    policy: &ResourcePolicy,
+    resource_annotations: &std::collections::BTreeMap<String, String>,
+) -> Result<()> {
+    let namespace = crate::config::headwind_namespace();
+    let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
+
+    let request_name = format!(
+        "composition-{}-{}",
+        name,
+        new_version.replace([':', '.', '/'], "-").to_lowercase()
+    );
+
+    let update_request = UpdateRequest {
+        metadata: kube::api::ObjectMeta {
+            name: Some(request_name.clone()),
+            namespace: Some(namespace.clone()),
+            ..Default::default()
+        },
+        spec: UpdateRequestSpec {
+            target_ref: TargetRef {
+                api_version: "apiextensions.crossplane.io/v1".to_string(),
+                kind: "Composition".to_string(),
+                name: name.to_string(),
+                namespace: String::new(),
+            },
+            update_type: UpdateType::Image,
+            container_name: resource_name,
+            container_kind: Default::default(),
+            current_image: format!("{}:{}", image, current_version),
+            new_image: format!("{}:{}", image, new_version),
+            policy: map_policy_to_crd(&policy.policy),
+            reason: Some(format!(
+                "Update from {} to {}",
+                current_version, new_version
+            )),
+            require_approval: true,
+            expires_at: Some(Utc::now() + chrono::Duration::hours(24)),
+            scheduled_at: None,
+            priority: None,
+            updates: Vec::new(),
+            discussion_thread: Vec::new(),
+            sla_deadline: super::sla::sla_deadline(Some(resource_annotations), chrono::Utc::now()),
+            impact: None,
+        },
+        status: None,
+    };
+
+    match update_requests.get(&request_name).await {
+        Ok(existing) => {
+            // Recreate if the previous request is in a terminal state
+            if let Some(status) = &existing.status {
+                use crate::models::crd::UpdatePhase;
+                if status.phase == UpdatePhase::Completed
+                    || status.phase == UpdatePhase::Rejected
+                    || status.phase == UpdatePhase::Failed
+                {
+                    update_requests
+                        .delete(&request_name, &Default::default())
+                        .await?;
+                    update_requests
+                        .create(&PostParams::default(), &update_request)
+                        .await?;
+                }
+            }
+        },
+        Err(kube::Error::Api(err)) if err.code == 404 => {
+            update_requests
+                .create(&PostParams::default(), &update_request)
+                .await?;
+            info!(
+                "Created UpdateRequest {}/{} for composition {}",
+                namespace, request_name, name
+            );
+        },
+        Err(e) => {
+            error!("Failed to check for existing UpdateRequest: {}", e);
+            return Err(anyhow::anyhow!("Failed to check UpdateRequest: {}", e));
+        },
+    }
+
+    Ok(())
+}
+
+/// Map internal UpdatePolicy to CRD UpdatePolicyType
+fn map_policy_to_crd(policy: &UpdatePolicy) -> UpdatePolicyType {
+    match policy {
+        UpdatePolicy::Patch => UpdatePolicyType::Patch,
+        UpdatePolicy::Minor => UpdatePolicyType::Minor,
+        UpdatePolicy::Major => UpdatePolicyType::Major,
+        UpdatePolicy::Glob => UpdatePolicyType::Glob,
+        UpdatePolicy::None => UpdatePolicyType::None,
+        // Map All and Force to Major since they don't exist in CRD
+        UpdatePolicy::All | UpdatePolicy::Force => UpdatePolicyType::Major,
+    }
+}
+
+/// Parse ResourcePolicy from Composition annotations
+fn parse_policy_from_annotations(
+    annotations: &std::collections::BTreeMap<String, String>,
+) -> Result<ResourcePolicy> {
+    let policy_str = annotations
+        .get(annotations::POLICY)
+        .ok_or_else(|| anyhow::anyhow!("No policy annotation found"))?;
+
+    let policy = policy_str
+        .parse::<UpdatePolicy>()
+        .map_err(|e| anyhow::anyhow!("Invalid update policy: {}", e))?;
+
+    let pattern = annotations.get(annotations::PATTERN).cloned();
+
+    let require_approval = annotations
+        .get(annotations::REQUIRE_APPROVAL)
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(true);
+
+    let min_update_interval = annotations
+        .get(annotations::MIN_UPDATE_INTERVAL)
+        .and_then(|v| v.parse::<u64>().ok());
+
+    let images = annotations
+        .get(annotations::IMAGES)
+        .map(|s| s.split(',').map(|i| i.trim().to_string()).collect())
+        .unwrap_or_default();
+
+    let event_source = annotations
+        .get(annotations::EVENT_SOURCE)
+        .and_then(|v| v.parse::<EventSource>().ok())
+        .unwrap_or_default();
+
+    let polling_interval = annotations
+        .get(annotations::POLLING_INTERVAL)
+        .and_then(|v| v.parse::<u64>().ok());
+
+    let tag_normalization = parse_tag_normalization(annotations, &global_tag_normalization())
+        .unwrap_or_else(|_| global_tag_normalization());
+
+    Ok(ResourcePolicy {
+        policy,
+        pattern,
+        require_approval,
+        min_update_interval,
+        images,
+        container_names: Vec::new(),
+        event_source,
+        polling_interval,
+        batch_mode: false,
+        tag_normalization,
+        observers: annotations
+            .get(annotations::NOTIFY_OBSERVERS)
+            .map(|v| crate::models::parse_observers(v))
+            .unwrap_or_default(),
+        helm_version_constraint: None,
+        include_init_containers: false,
+        require_slsa_level: None,
+        slsa_builder: None,
+        opa_policy: None,
+    })
+}
+
+/// Build the JSON Patch replacing every reference to an image.
+///
+/// Each replace is preceded by a `test` of the current value so the patch fails
+/// instead of overwriting a path whose contents changed since it was read.
+fn build_image_patch(
+    refs: &[CompositionImageRef],
+    new_image: &str,
+    last_update_value: &str,
+) -> Result<json_patch::Patch> {
+    let mut ops = Vec::new();
+
+    for image_ref in refs {
+        let path = image_ref.json_pointer();
+        ops.push(json!({ "op": "test", "path": path, "value": image_ref.image }));
+        ops.push(json!({ "op": "replace", "path": path, "value": new_image }));
+    }
+
+    // "/" in the annotation key is escaped as "~1" in JSON Pointer
+    ops.push(json!({
+        "op": "add",
+        "path": format!("/metadata/annotations/{}", annotations::LAST_UPDATE.replace('/', "~1")),
+        "value": last_update_value
+    }));
+
+    Ok(serde_json::from_value(serde_json::Value::Array(ops))?)
+}
+
+/// Update a Composition's image references - public wrapper
+pub async fn update_composition_image(
+    client: &Client,
+    name: &str,
+    image: &str,
+    new_version: &str,
+) -> Result<()> {
+    update_composition_image_with_tracking(client, name, image, new_version, None).await
+}
+
+/// Update a Composition's image references with tracking
+/// If approver is provided, it will be recorded in the last-update annotation
+pub async fn update_composition_image_with_tracking(
+    client: &Client,
+    name: &str,
+    image: &str,
+    new_version: &str,
+    approver: Option<&str>,
+) -> Result<()> {
+    let new_image = format!("{}:{}", image, new_version);
+
+    info!("Updating composition {} image to {}", name, new_image);
+
+    let compositions: Api<Composition> = Api::all(client.clone());
+    let composition = compositions.get(name).await?;
+
+    let refs = matching_refs(&composition, image);
+    if refs.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Image {} not referenced by composition {}",
+            image,
+            name
+        ));
+    }
+
+    let now = Utc::now();
+    let last_update_value = if let Some(approver) = approver {
+        format!("{} (approved by {})", now.to_rfc3339(), approver)
+    } else {
+        now.to_rfc3339()
+    };
+
+    let patch = build_image_patch(&refs, &new_image, &last_update_value)?;
+    compositions
+        .patch(name, &PatchParams::default(), &Patch::Json::<()>(patch))
+        .await?;
+
+    info!(
+        "Successfully updated {} image reference(s) in composition {} to version {}",
+        refs.len(),
+        name,
+        new_version
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn composition() -> Composition {
+        serde_json::from_value(json!({
+            "apiVersion": "apiextensions.crossplane.io/v1",
+            "kind": "Composition",
+            "metadata": {
+                "name": "platform-app",
+                "annotations": { "headwind.sh/policy": "minor" }
+            },
+            "spec": {
+                "resources": [{
+                    "name": "worker",
+                    "base": {
+                        "spec": {
+                            "containers": [
+                                { "name": "app", "image": "ghcr.io/org/worker:1.2.0" },
+                                { "name": "proxy", "image": "ghcr.io/org/worker-proxy:1.0.0" }
+                            ]
+                        }
+                    }
+                }]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_matching_refs_uses_full_image_name() {
+        let refs = matching_refs(&composition(), "ghcr.io/org/worker");
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].container_name.as_deref(), Some("app"));
+    }
+
+    #[test]
+    fn test_build_image_patch() {
+        let refs = matching_refs(&composition(), "ghcr.io/org/worker");
+        let patch =
+            build_image_patch(&refs, "ghcr.io/org/worker:1.3.0", "2025-01-01T00:00:00Z").unwrap();
+
+        let ops = serde_json::to_value(&patch).unwrap();
+        assert_eq!(
+            ops,
+            json!([
+                {
+                    "op": "test",
+                    "path": "/spec/resources/0/base/spec/containers/0/image",
+                    "value": "ghcr.io/org/worker:1.2.0"
+                },
+                {
+                    "op": "replace",
+                    "path": "/spec/resources/0/base/spec/containers/0/image",
+                    "value": "ghcr.io/org/worker:1.3.0"
+                },
+                {
+                    "op": "add",
+                    "path": "/metadata/annotations/headwind.sh~1last-update",
+                    "value": "2025-01-01T00:00:00Z"
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_policy_from_annotations() {
+        let policy =
+            parse_policy_from_annotations(composition().metadata.annotations.as_ref().unwrap())
+                .unwrap();
+        assert_eq!(policy.policy, UpdatePolicy::Minor);
+        assert!(policy.require_approval);
+    }
+}
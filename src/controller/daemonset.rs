@@ -1,27 +1,42 @@
 use crate::metrics::{DAEMONSETS_WATCHED, RECONCILE_DURATION, RECONCILE_ERRORS};
 use crate::models::{
-    EventSource, ResourcePolicy, TargetRef, UpdatePolicy, UpdatePolicyType, UpdateRequest,
-    UpdateRequestSpec, UpdateType, annotations,
+    EventSource, ResourcePolicy, SingleUpdate, TargetRef, UpdatePolicy, UpdatePolicyType,
+    UpdateRequest, UpdateRequestSpec, UpdateType, annotations, global_tag_normalization,
+    parse_container_names, parse_slsa_level, parse_tag_normalization,
 };
 use crate::notifications::{self, DeploymentInfo};
 use crate::policy::PolicyEngine;
+use crate::policy::ignore::is_resource_ignored;
+use crate::polling::digest::{split_tag, strip_digest, target_image};
+use crate::schedule::PendingUpdate;
 use anyhow::Result;
 use chrono::Utc;
 use futures::StreamExt;
 use k8s_openapi::api::apps::v1::DaemonSet;
+use k8s_openapi::api::core::v1::{Container, Pod};
 use kube::{
-    ResourceExt,
-    api::{Api, Patch, PatchParams, PostParams},
+    Resource, ResourceExt,
+    api::{Api, ListParams, Patch, PatchParams, PostParams},
     client::Client,
-    runtime::{
-        controller::{Action, Controller},
-        watcher::Config,
-    },
+    runtime::controller::Action,
+    runtime::events::{Event, EventType, Recorder, Reporter},
 };
 use serde_json::json;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tracing::{debug, error, info, instrument};
+use tracing::{debug, error, info, instrument, warn};
+
+/// How long a single node may take to run the updated pod during a paused rollout
+const ROLLOUT_STEP_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Reason of the event published when a paused rollout fails
+pub const PAUSED_ROLLOUT_FAILED_REASON: &str = "PausedRolloutFailed";
+
+lazy_static::lazy_static! {
+    /// DaemonSets (namespace, name) with a paused rollout running in this process
+    static ref PAUSED_ROLLOUTS: Mutex<HashSet<(String, String)>> = Mutex::new(HashSet::new());
+}
 
 pub struct DaemonSetController {
     client: Client,
@@ -48,11 +63,11 @@ impl DaemonSetController {
         const MAX_BACKOFF: u64 = 60;
 
         loop {
-            let daemonsets: Api<DaemonSet> = Api::all(self.client.clone());
+            let daemonsets: Api<DaemonSet> = super::watched_api(self.client.clone());
 
             info!("Creating controller for daemonsets");
 
-            let result = Controller::new(daemonsets, Config::default())
+            let result = super::queue_metrics::controller(daemonsets, "daemonset")
                 .run(
                     reconcile,
                     error_policy,
@@ -101,12 +116,14 @@ struct ControllerContext {
     policy_engine: Arc<PolicyEngine>,
 }
 
-#[instrument(skip(_ctx), fields(daemonset = %daemonset.name_any()))]
+#[instrument(skip(ctx), fields(daemonset = %daemonset.name_any()))]
 async fn reconcile(
     daemonset: Arc<DaemonSet>,
-    _ctx: Arc<ControllerContext>,
+    ctx: Arc<ControllerContext>,
 ) -> Result<Action, kube::Error> {
     let _timer = RECONCILE_DURATION.start_timer();
+    let _queue_timer = super::queue_metrics::start_reconcile("daemonset", daemonset.as_ref());
+    let _in_flight = super::concurrency::track("daemonset");
 
     let namespace = daemonset.namespace().unwrap_or_default();
     let name = daemonset.name_any();
@@ -125,6 +142,25 @@ async fn reconcile(
 
     let annotations = annotations.unwrap();
 
+    // Restore the strategy of a paused rollout no longer running, e.g. after a
+    // restart, unless it failed and is left on OnDelete
+    if annotations.contains_key(annotations::PAUSED_ROLLOUT_STRATEGY)
+        && !annotations.contains_key(annotations::PAUSED_ROLLOUT_FAILED)
+        && !PAUSED_ROLLOUTS
+            .lock()
+            .unwrap()
+            .contains(&(namespace.clone(), name.clone()))
+    {
+        restore_update_strategy(&ctx.client, &namespace, &name)
+            .await
+            .map_err(|e| create_error(&format!("Failed to restore update strategy: {}", e)))?;
+    }
+
+    if is_resource_ignored(annotations) {
+        debug!("DaemonSet {}/{} is ignored, skipping", namespace, name);
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    }
+
     // Check if this daemonset has headwind annotations
     if !annotations.contains_key(annotations::POLICY) {
         debug!(
@@ -181,9 +217,10 @@ fn create_error(msg: &str) -> kube::Error {
     })
 }
 
-/// Parse an image string into (image_name, tag)
+/// Parse an image string into (image_name, tag), ignoring any pinned digest
 /// Example: "myregistry.com/myimage:v1.2.3" -> ("myregistry.com/myimage", "v1.2.3")
 fn parse_image(image: &str) -> Result<(String, String), String> {
+    let image = strip_digest(image);
     let parts: Vec<&str> = image.rsplitn(2, ':').collect();
     if parts.len() != 2 {
         return Err(format!("Invalid image format: {}", image));
@@ -233,28 +270,45 @@ pub async fn handle_image_update(
 
     let containers = &template_spec.containers;
 
-    // Find container with matching image
-    let mut current_version = None;
+    // Find the targeted container with matching image
+    let mut current = None;
     for container in containers {
+        if !policy.targets_container(&container.name) {
+            continue;
+        }
+
         let empty_image = String::new();
         let container_image = container.image.as_ref().unwrap_or(&empty_image);
         let (img_name, img_tag) = parse_image(container_image)
             .map_err(|e| anyhow::anyhow!("Failed to parse container image: {}", e))?;
 
         if img_name == image || container_image.starts_with(image) {
-            current_version = Some(img_tag);
+            current = Some((container.name.clone(), img_tag));
             break;
         }
     }
 
-    let current_version = current_version
-        .ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
+    let (container_name, current_version) =
+        current.ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
 
     debug!(
         "Current version: {}, new version: {}",
         current_version, new_version
     );
 
+    // OPA decides before the update policy is evaluated
+    if !crate::policy::opa::check_update(
+        client,
+        &daemonset.object_ref(&()),
+        &policy,
+        &format!("{}:{}", image, current_version),
+        &format!("{}:{}", image, new_version),
+    )
+    .await
+    {
+        return Ok(());
+    }
+
     // Check if we should update based on policy
     let should_update = policy_engine
         .should_update(&policy, &current_version, new_version)
@@ -294,8 +348,61 @@ pub async fn handle_image_update(
         }
     }
 
+    // SLSA provenance: only images built by a trusted builder
+    if !crate::security::slsa::check_provenance(
+        client,
+        &daemonset.object_ref(&()),
+        &policy,
+        &format!("{}:{}", image, new_version),
+    )
+    .await
+    {
+        return Ok(());
+    }
+
+    // Digest pinning: deploy the digest the new tag currently resolves to
+    let target = target_image(
+        client,
+        &namespace,
+        Some(annotations),
+        &format!("{}:{}", image, new_version),
+    )
+    .await?;
+    let new_version = split_tag(target.as_str()).map_or(new_version, |(_, version)| version);
+
     // Check if approval is required
-    if policy.require_approval {
+    if policy.require_approval && policy.batch_mode {
+        info!(
+            "Adding container {} to BatchUpdateRequest for daemonset {}/{}: {} -> {}",
+            container_name, namespace, name, current_version, new_version
+        );
+
+        super::add_to_batch(
+            client,
+            TargetRef {
+                api_version: "apps/v1".to_string(),
+                kind: "DaemonSet".to_string(),
+                name: name.clone(),
+                namespace: namespace.clone(),
+            },
+            SingleUpdate {
+                container_name: container_name.clone(),
+                current_image: format!("{}:{}", image, current_version),
+                new_image: format!("{}:{}", image, new_version),
+            },
+            map_policy_to_crd(&policy.policy),
+        )
+        .await?;
+    } else if policy.require_approval {
+        if !super::rate_limit::allow_update_request(
+            "DaemonSet",
+            &namespace,
+            &name,
+            Some(annotations),
+        ) {
+            return Ok(());
+        }
+
         info!(
             "Creating UpdateRequest for daemonset {}/{}: {} -> {}",
             namespace, name, current_version, new_version
@@ -308,9 +415,46 @@ pub async fn handle_image_update(
             image,
             &current_version,
             new_version,
+            &container_name,
             &policy,
+            annotations,
         )
         .await?;
+    } else if let Some((schedule, scheduled_for)) =
+        crate::schedule::deferred_until(annotations, Utc::now())
+    {
+        let client = client.clone();
+        let current_image = format!("{}:{}", image, current_version);
+        let new_image = format!("{}:{}", image, new_version);
+        let (image, new_version) = (image.to_string(), new_version.to_string());
+
+        crate::schedule::defer_update(
+            PendingUpdate {
+                kind: "DaemonSet".to_string(),
+                namespace: namespace.clone(),
+                name: name.clone(),
+                container: Some(container_name.clone()),
+                current_image: current_image.clone(),
+                new_image: new_image.clone(),
+                policy: policy.policy,
+                schedule,
+                scheduled_for,
+                queued_at: Utc::now(),
+            },
+            move || async move {
+                update_daemonset_image(&client, &namespace, &name, &image, &new_version).await?;
+                notifications::notify_update_completed(DeploymentInfo {
+                    name,
+                    namespace,
+                    current_image,
+                    new_image,
+                    container: Some(container_name),
+                    resource_kind: Some("DaemonSet".to_string()),
+                });
+                Ok(())
+            },
+        )
+        .await;
     } else {
         info!(
             "Auto-updating daemonset {}/{} (no approval required): {} -> {}",
@@ -326,7 +470,7 @@ pub async fn handle_image_update(
             namespace: namespace.clone(),
             current_image: format!("{}:{}", image, current_version),
             new_image: format!("{}:{}", image, new_version),
-            container: None,
+            container: Some(container_name),
             resource_kind: Some("DaemonSet".to_string()),
         });
     }
@@ -336,6 +480,7 @@ pub async fn handle_image_update(
 
 /// Create an UpdateRequest CRD for a pending update
 #[allow(dead_code)]
+#[allow(clippy::too_many_arguments)]
 async fn create_update_request(
     client: &Client,
     namespace: &str,
@@ -343,15 +488,18 @@ async fn create_update_request(
     image: &str,
     current_version: &str,
     new_version: &str,
+    container_name: &str,
     policy: &ResourcePolicy,
+    resource_annotations: &std::collections::BTreeMap<String, String>,
 ) -> Result<()> {
     let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), namespace);
 
-    // Create a unique name for the update request
+    // Create a unique name for the update request, without any pinned digest
+    let new_tag = strip_digest(new_version);
     let request_name = format!(
         "{}-{}",
         name,
-        new_version.replace([':', '.', '/'], "-").to_lowercase()
+        new_tag.replace([':', '.', '/'], "-").to_lowercase()
     );
 
     debug!(
@@ -373,16 +521,20 @@ async fn create_update_request(
                 namespace: namespace.to_string(),
             },
             update_type: UpdateType::Image,
-            container_name: None,
+            container_name: Some(container_name.to_string()),
+            container_kind: Default::default(),
             current_image: format!("{}:{}", image, current_version),
             new_image: format!("{}:{}", image, new_version),
             policy: map_policy_to_crd(&policy.policy),
-            reason: Some(format!(
-                "Update from {} to {}",
-                current_version, new_version
-            )),
+            reason: Some(format!("Update from {} to {}", current_version, new_tag)),
             require_approval: true,
             expires_at: Some(Utc::now() + chrono::Duration::hours(24)),
+            scheduled_at: None,
+            priority: None,
+            updates: Vec::new(),
+            discussion_thread: Vec::new(),
+            sla_deadline: super::sla::sla_deadline(Some(resource_annotations), chrono::Utc::now()),
+            impact: None,
         },
         status: None,
     };
@@ -412,6 +564,12 @@ async fn create_update_request(
                     update_requests
                         .create(&PostParams::default(), &update_request)
                         .await?;
+                    crate::integrations::jira::open_issue_for_update_request(
+                        client,
+                        Some(resource_annotations),
+                        &update_request,
+                    )
+                    .await;
                 }
             }
         },
@@ -420,6 +578,12 @@ async fn create_update_request(
             update_requests
                 .create(&PostParams::default(), &update_request)
                 .await?;
+            crate::integrations::jira::open_issue_for_update_request(
+                client,
+                Some(resource_annotations),
+                &update_request,
+            )
+            .await;
             info!(
                 "Created UpdateRequest {}/{} for daemonset {}",
                 namespace, request_name, name
@@ -448,35 +612,6 @@ fn map_policy_to_crd(policy: &UpdatePolicy) -> UpdatePolicyType {
     }
 }
 
-/// Simple glob pattern matching (supports * and ?)
-#[allow(dead_code)]
-fn glob_match(pattern: &str, text: &str) -> bool {
-    let pattern_chars: Vec<char> = pattern.chars().collect();
-    let text_chars: Vec<char> = text.chars().collect();
-
-    glob_match_impl(&pattern_chars, &text_chars, 0, 0)
-}
-
-#[allow(dead_code)]
-fn glob_match_impl(pattern: &[char], text: &[char], pi: usize, ti: usize) -> bool {
-    if pi >= pattern.len() && ti >= text.len() {
-        return true;
-    }
-    if pi >= pattern.len() {
-        return false;
-    }
-
-    if pattern[pi] == '*' {
-        // Try matching zero or more characters
-        glob_match_impl(pattern, text, pi + 1, ti)
-            || (ti < text.len() && glob_match_impl(pattern, text, pi, ti + 1))
-    } else if ti < text.len() && (pattern[pi] == '?' || pattern[pi] == text[ti]) {
-        glob_match_impl(pattern, text, pi + 1, ti + 1)
-    } else {
-        false
-    }
-}
-
 /// Parse ResourcePolicy from Deployment annotations
 fn parse_policy_from_annotations(
     annotations: &std::collections::BTreeMap<String, String>,
@@ -523,14 +658,48 @@ fn parse_policy_from_annotations(
         .get(annotations::POLLING_INTERVAL)
         .and_then(|v| v.parse::<u64>().ok());
 
+    let batch_mode = annotations
+        .get(annotations::BATCH_UPDATES)
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    let container_names = annotations
+        .get(annotations::CONTAINER_NAMES)
+        .map(|v| parse_container_names(v))
+        .unwrap_or_default();
+
+    let tag_normalization = parse_tag_normalization(annotations, &global_tag_normalization())
+        .unwrap_or_else(|_| global_tag_normalization());
+
+    let require_slsa_level = annotations
+        .get(annotations::REQUIRE_SLSA_LEVEL)
+        .map(|v| parse_slsa_level(v))
+        .transpose()?;
+
+    let slsa_builder = annotations.get(annotations::SLSA_BUILDER).cloned();
+
+    let opa_policy = annotations.get(annotations::OPA_POLICY).cloned();
+
     Ok(ResourcePolicy {
         policy,
         pattern,
         require_approval,
         min_update_interval,
         images,
+        container_names,
         event_source,
         polling_interval,
+        batch_mode,
+        tag_normalization,
+        observers: annotations
+            .get(annotations::NOTIFY_OBSERVERS)
+            .map(|v| crate::models::parse_observers(v))
+            .unwrap_or_default(),
+        helm_version_constraint: None,
+        include_init_containers: false,
+        require_slsa_level,
+        slsa_builder,
+        opa_policy,
     })
 }
 
@@ -565,48 +734,277 @@ pub async fn update_daemonset_image_with_tracking(
         namespace, name, new_image
     );
 
-    // Create strategic merge patch to update the container image
-    // We need to find which container to update
     let daemonset = daemonsets.get(name).await?;
-    let spec = daemonset
+
+    // Update last-update annotation with timestamp
+    let now = Utc::now();
+    let last_update_value = if let Some(approver) = approver {
+        format!("{} (approved by {})", now.to_rfc3339(), approver)
+    } else {
+        now.to_rfc3339()
+    };
+
+    let container = container_for_image(&daemonset, image)?;
+    let container_name = container.name.clone();
+    let pause = daemonset
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(rollout_pause_seconds);
+    let saved_strategy = pause.map(|_| strategy_to_restore(&daemonset)).transpose()?;
+
+    let mut patch = build_image_patch(
+        &container_name,
+        &new_image,
+        &last_update_value,
+        saved_strategy.as_deref(),
+    );
+    super::set_previous_image(&mut patch, container.image.as_deref(), &new_image);
+
+    daemonsets
+        .patch(
+            name,
+            &PatchParams::apply("headwind"),
+            &Patch::Strategic(patch),
+        )
+        .await?;
+
+    info!(
+        "Successfully updated daemonset {}/{} to version {}",
+        namespace, name, new_version
+    );
+
+    if let Some(pause) = pause {
+        // Roll pods one node at a time in the background. The original strategy
+        // is saved in an annotation, so reconcile restores it if this is cut short.
+        PAUSED_ROLLOUTS
+            .lock()
+            .unwrap()
+            .insert((namespace.to_string(), name.to_string()));
+        let client = client.clone();
+        let namespace = namespace.to_string();
+        let name = name.to_string();
+
+        tokio::spawn(async move {
+            paused_rollout(
+                &client,
+                &namespace,
+                &name,
+                &container_name,
+                &new_image,
+                Duration::from_secs(pause),
+            )
+            .await;
+        });
+    }
+
+    Ok(())
+}
+
+/// Run a paused rollout, then restore the saved update strategy. A failed
+/// rollout is left on OnDelete, so the DaemonSet doesn't go on to replace the
+/// remaining pods with an image that didn't become ready; the failure is
+/// recorded in an annotation and a warning event instead.
+async fn paused_rollout(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    container_name: &str,
+    new_image: &str,
+    pause: Duration,
+) {
+    let key = (namespace.to_string(), name.to_string());
+    match run_paused_rollout(client, namespace, name, container_name, new_image, pause).await {
+        Ok(()) => {
+            PAUSED_ROLLOUTS.lock().unwrap().remove(&key);
+            if let Err(e) = restore_update_strategy(client, namespace, name).await {
+                error!(
+                    "Failed to restore update strategy of daemonset {}/{}: {}",
+                    namespace, name, e
+                );
+            }
+        },
+        Err(e) => {
+            error!(
+                "Paused rollout of daemonset {}/{} failed, leaving it on OnDelete: {}",
+                namespace, name, e
+            );
+            // Marked before reconcile may see the rollout as no longer running
+            if let Err(e) = record_paused_rollout_failure(client, namespace, name, &e).await {
+                error!(
+                    "Failed to record paused rollout failure of daemonset {}/{}: {}",
+                    namespace, name, e
+                );
+            }
+            PAUSED_ROLLOUTS.lock().unwrap().remove(&key);
+        },
+    }
+}
+
+/// Annotate the DaemonSet with why its paused rollout failed and publish a
+/// `PausedRolloutFailed` warning event
+async fn record_paused_rollout_failure(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    failure: &anyhow::Error,
+) -> Result<()> {
+    let daemonsets: Api<DaemonSet> = Api::namespaced(client.clone(), namespace);
+    let patch = json!({
+        "metadata": {
+            "annotations": { annotations::PAUSED_ROLLOUT_FAILED: failure.to_string() }
+        }
+    });
+    let daemonset = daemonsets
+        .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await?;
+
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    let event = Event {
+        type_: EventType::Warning,
+        reason: PAUSED_ROLLOUT_FAILED_REASON.to_string(),
+        note: Some(format!(
+            "Paused rollout failed, update strategy left on OnDelete: {}",
+            failure
+        )),
+        action: "UpdateImage".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(&event, &daemonset.object_ref(&())).await {
+        warn!(
+            "Failed to publish {} event for daemonset {}/{}: {}",
+            PAUSED_ROLLOUT_FAILED_REASON, namespace, name, e
+        );
+    }
+    Ok(())
+}
+
+/// The update strategy to save before a paused rollout switches to OnDelete:
+/// the one already saved by an unfinished rollout, or the current one
+fn strategy_to_restore(daemonset: &DaemonSet) -> Result<String> {
+    if let Some(saved) = daemonset
+        .annotations()
+        .get(annotations::PAUSED_ROLLOUT_STRATEGY)
+    {
+        return Ok(saved.clone());
+    }
+    let strategy = daemonset
         .spec
         .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("DaemonSet has no spec"))?;
+        .and_then(|spec| spec.update_strategy.clone())
+        .unwrap_or_default();
+    Ok(serde_json::to_string(&strategy)?)
+}
 
-    let template_spec = spec
+/// Merge patch restoring the update strategy saved by a paused rollout and
+/// removing the annotation it was saved in, or None if nothing is saved
+fn strategy_restore_patch(
+    annotations: &BTreeMap<String, String>,
+) -> Result<Option<serde_json::Value>> {
+    let Some(saved) = annotations.get(annotations::PAUSED_ROLLOUT_STRATEGY) else {
+        return Ok(None);
+    };
+    let strategy: serde_json::Value = serde_json::from_str(saved).map_err(|e| {
+        anyhow::anyhow!(
+            "Invalid {} annotation: {}",
+            annotations::PAUSED_ROLLOUT_STRATEGY,
+            e
+        )
+    })?;
+
+    Ok(Some(json!({
+        "metadata": { "annotations": { annotations::PAUSED_ROLLOUT_STRATEGY: null } },
+        "spec": { "updateStrategy": strategy }
+    })))
+}
+
+/// Restore the update strategy saved by a paused rollout, if any
+async fn restore_update_strategy(client: &Client, namespace: &str, name: &str) -> Result<()> {
+    let daemonsets: Api<DaemonSet> = Api::namespaced(client.clone(), namespace);
+    let daemonset = daemonsets.get(name).await?;
+    let Some(patch) = strategy_restore_patch(daemonset.annotations())? else {
+        return Ok(());
+    };
+
+    daemonsets
+        .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await?;
+    info!(
+        "Restored update strategy of daemonset {}/{}",
+        namespace, name
+    );
+    Ok(())
+}
+
+/// Read `headwind.sh/rollout-pause-between-steps` (seconds, must be > 0)
+fn rollout_pause_seconds(annotations: &BTreeMap<String, String>) -> Option<u64> {
+    annotations
+        .get(annotations::ROLLOUT_PAUSE_BETWEEN_STEPS)
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|pause| *pause > 0)
+}
+
+/// Name of the first container whose image starts with `image`
+fn container_for_image<'a>(daemonset: &'a DaemonSet, image: &str) -> Result<&'a Container> {
+    let template_spec = daemonset
+        .spec
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("DaemonSet has no spec"))?
         .template
         .spec
         .as_ref()
         .ok_or_else(|| anyhow::anyhow!("DaemonSet template has no spec"))?;
 
-    // Find the container index
-    let mut container_index = None;
-    for (idx, container) in template_spec.containers.iter().enumerate() {
-        if let Some(container_image) = &container.image
-            && container_image.starts_with(image)
-        {
-            container_index = Some(idx);
-            break;
-        }
-    }
+    template_spec
+        .containers
+        .iter()
+        .find(|c| c.image.as_ref().is_some_and(|i| i.starts_with(image)))
+        .ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))
+}
 
-    let container_index = container_index
-        .ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
+/// The strategic merge patch `update_daemonset_image_with_tracking` applies
+/// to move the container running `image` to `new_version`
+pub fn build_daemonset_image_patch(
+    daemonset: &DaemonSet,
+    image: &str,
+    new_version: &str,
+    last_update_value: &str,
+) -> Result<serde_json::Value> {
+    let container = container_for_image(daemonset, image)?;
+    let saved_strategy = daemonset
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(rollout_pause_seconds)
+        .map(|_| strategy_to_restore(daemonset))
+        .transpose()?;
 
-    // Update last-update annotation with timestamp
-    let now = Utc::now();
-    let last_update_value = if let Some(approver) = approver {
-        format!("{} (approved by {})", now.to_rfc3339(), approver)
-    } else {
-        now.to_rfc3339()
-    };
+    let new_image = format!("{}:{}", image, new_version);
+    let mut patch = build_image_patch(
+        &container.name,
+        &new_image,
+        last_update_value,
+        saved_strategy.as_deref(),
+    );
+    super::set_previous_image(&mut patch, container.image.as_deref(), &new_image);
+    Ok(patch)
+}
 
-    let patch = json!({
+/// Strategic merge patch setting the container image. With a `saved_strategy`,
+/// the update strategy is switched to OnDelete so headwind can replace pods
+/// itself, and the saved one is kept in an annotation to be restored.
+fn build_image_patch(
+    container_name: &str,
+    new_image: &str,
+    last_update_value: &str,
+    saved_strategy: Option<&str>,
+) -> serde_json::Value {
+    let mut patch = json!({
         "spec": {
             "template": {
                 "spec": {
                     "containers": [{
-                        "name": template_spec.containers[container_index].name,
+                        "name": container_name,
                         "image": new_image
                     }]
                 }
@@ -619,25 +1017,141 @@ pub async fn update_daemonset_image_with_tracking(
         }
     });
 
-    daemonsets
-        .patch(
+    if let Some(saved_strategy) = saved_strategy {
+        // rollingUpdate must be cleared, it's rejected alongside OnDelete
+        patch["spec"]["updateStrategy"] = json!({ "type": "OnDelete", "rollingUpdate": null });
+        patch["metadata"]["annotations"][annotations::PAUSED_ROLLOUT_STRATEGY] =
+            json!(saved_strategy);
+        // A new rollout replaces a failed one
+        patch["metadata"]["annotations"][annotations::PAUSED_ROLLOUT_FAILED] =
+            serde_json::Value::Null;
+    }
+
+    patch
+}
+
+/// Names of running pods whose container doesn't run `new_image` yet, ordered by node
+fn pods_to_update(pods: &[Pod], container_name: &str, new_image: &str) -> Vec<String> {
+    let mut outdated: Vec<(String, String)> = pods
+        .iter()
+        .filter(|pod| pod.metadata.deletion_timestamp.is_none())
+        .filter(|pod| {
+            pod.spec
+                .as_ref()
+                .and_then(|spec| spec.containers.iter().find(|c| c.name == container_name))
+                .and_then(|c| c.image.as_deref())
+                != Some(new_image)
+        })
+        .map(|pod| {
+            let node = pod
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.node_name.clone())
+                .unwrap_or_default();
+            (node, pod.name_any())
+        })
+        .collect();
+
+    outdated.sort();
+    outdated.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Replace outdated pods one node at a time. After each replacement pod is
+/// ready, wait `pause` before moving on to the next node.
+async fn run_paused_rollout(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    container_name: &str,
+    new_image: &str,
+    pause: Duration,
+) -> Result<()> {
+    let daemonsets: Api<DaemonSet> = Api::namespaced(client.clone(), namespace);
+    let pods: Api<Pod> = Api::namespaced(client.clone(), namespace);
+
+    let daemonset = daemonsets.get(name).await?;
+    let selector = daemonset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.selector.match_labels.as_ref())
+        .map(|labels| {
+            labels
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .ok_or_else(|| anyhow::anyhow!("DaemonSet has no matchLabels selector"))?;
+
+    loop {
+        let pod_list = pods.list(&ListParams::default().labels(&selector)).await?;
+        let outdated = pods_to_update(&pod_list.items, container_name, new_image);
+        let Some(pod_name) = outdated.first() else {
+            break;
+        };
+
+        info!(
+            "Updating daemonset {}/{} pod {} ({} remaining)",
+            namespace,
             name,
-            &PatchParams::apply("headwind"),
-            &Patch::Strategic(patch),
-        )
-        .await?;
+            pod_name,
+            outdated.len()
+        );
+        let updated_before = daemonsets
+            .get_status(name)
+            .await?
+            .status
+            .map(|s| s.updated_number_scheduled.unwrap_or(0))
+            .unwrap_or(0);
+        pods.delete(pod_name, &Default::default()).await?;
+
+        wait_for_step(&daemonsets, name, updated_before).await?;
+
+        if outdated.len() > 1 {
+            debug!(
+                "Pausing {}s before the next daemonset {}/{} pod",
+                pause.as_secs(),
+                namespace,
+                name
+            );
+            tokio::time::sleep(pause).await;
+        }
+    }
 
     info!(
-        "Successfully updated daemonset {}/{} to version {}",
-        namespace, name, new_version
+        "Paused rollout of daemonset {}/{} complete",
+        namespace, name
     );
-
     Ok(())
 }
 
+/// Wait until one more pod is updated and every scheduled pod is ready again
+async fn wait_for_step(daemonsets: &Api<DaemonSet>, name: &str, updated_before: i32) -> Result<()> {
+    let deadline = tokio::time::Instant::now() + ROLLOUT_STEP_TIMEOUT;
+
+    loop {
+        tokio::time::sleep(Duration::from_secs(5)).await;
+
+        if let Some(status) = daemonsets.get_status(name).await?.status
+            && status.updated_number_scheduled.unwrap_or(0) > updated_before
+            && status.number_ready == status.desired_number_scheduled
+        {
+            return Ok(());
+        }
+
+        if tokio::time::Instant::now() >= deadline {
+            return Err(anyhow::anyhow!(
+                "Timed out after {}s waiting for the updated pod to become ready",
+                ROLLOUT_STEP_TIMEOUT.as_secs()
+            ));
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::policy::glob_match;
 
     #[test]
     fn test_parse_image() {
@@ -648,6 +1162,11 @@ mod tests {
         let (name, tag) = parse_image("nginx:latest").unwrap();
         assert_eq!(name, "nginx");
         assert_eq!(tag, "latest");
+
+        // A pinned digest isn't part of the tag
+        let (name, tag) = parse_image("nginx:1.26.0@sha256:abc").unwrap();
+        assert_eq!(name, "nginx");
+        assert_eq!(tag, "1.26.0");
     }
 
     #[test]
@@ -685,4 +1204,218 @@ mod tests {
         assert!(!policy.require_approval);
         assert_eq!(policy.min_update_interval, Some(600));
     }
+
+    #[test]
+    fn test_rollout_pause_seconds() {
+        let mut annotations = BTreeMap::new();
+        assert_eq!(rollout_pause_seconds(&annotations), None);
+
+        annotations.insert(
+            annotations::ROLLOUT_PAUSE_BETWEEN_STEPS.to_string(),
+            "60".to_string(),
+        );
+        assert_eq!(rollout_pause_seconds(&annotations), Some(60));
+
+        annotations.insert(
+            annotations::ROLLOUT_PAUSE_BETWEEN_STEPS.to_string(),
+            "0".to_string(),
+        );
+        assert_eq!(rollout_pause_seconds(&annotations), None);
+    }
+
+    #[test]
+    fn test_build_image_patch_paused() {
+        let patch = build_image_patch("agent", "agent:2.0.0", "2025-01-01T00:00:00Z", None);
+        assert!(patch["spec"].get("updateStrategy").is_none());
+        assert!(
+            patch["metadata"]["annotations"]
+                .get(annotations::PAUSED_ROLLOUT_STRATEGY)
+                .is_none()
+        );
+
+        let saved = r#"{"type":"RollingUpdate","rollingUpdate":{"maxUnavailable":2}}"#;
+        let patch = build_image_patch("agent", "agent:2.0.0", "2025-01-01T00:00:00Z", Some(saved));
+        assert_eq!(patch["spec"]["updateStrategy"]["type"], "OnDelete");
+        assert!(patch["spec"]["updateStrategy"]["rollingUpdate"].is_null());
+        assert_eq!(
+            patch["metadata"]["annotations"][annotations::PAUSED_ROLLOUT_STRATEGY],
+            saved
+        );
+        assert!(
+            patch["metadata"]["annotations"]
+                .get(annotations::PAUSED_ROLLOUT_FAILED)
+                .is_some_and(|v| v.is_null())
+        );
+        assert_eq!(
+            patch["spec"]["template"]["spec"]["containers"][0]["image"],
+            "agent:2.0.0"
+        );
+    }
+
+    #[test]
+    fn test_strategy_to_restore() {
+        let daemonset = |annotations: serde_json::Value| -> DaemonSet {
+            serde_json::from_value(json!({
+                "metadata": { "name": "agent", "annotations": annotations },
+                "spec": {
+                    "selector": {},
+                    "template": {},
+                    "updateStrategy": { "type": "OnDelete" }
+                }
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(
+            strategy_to_restore(&daemonset(json!({}))).unwrap(),
+            r#"{"type":"OnDelete"}"#
+        );
+
+        // A rollout that hasn't been restored yet keeps the original strategy
+        let saved = r#"{"rollingUpdate":{"maxUnavailable":2},"type":"RollingUpdate"}"#;
+        assert_eq!(
+            strategy_to_restore(&daemonset(
+                json!({ annotations::PAUSED_ROLLOUT_STRATEGY: saved })
+            ))
+            .unwrap(),
+            saved
+        );
+    }
+
+    #[test]
+    fn test_strategy_restore_patch() {
+        assert_eq!(strategy_restore_patch(&BTreeMap::new()).unwrap(), None);
+
+        let annotations = BTreeMap::from([(
+            annotations::PAUSED_ROLLOUT_STRATEGY.to_string(),
+            r#"{"type":"RollingUpdate","rollingUpdate":{"maxUnavailable":2}}"#.to_string(),
+        )]);
+        let patch = strategy_restore_patch(&annotations).unwrap().unwrap();
+        assert_eq!(
+            patch["spec"]["updateStrategy"],
+            json!({ "type": "RollingUpdate", "rollingUpdate": { "maxUnavailable": 2 } })
+        );
+        assert!(patch["metadata"]["annotations"][annotations::PAUSED_ROLLOUT_STRATEGY].is_null());
+
+        let invalid = BTreeMap::from([(
+            annotations::PAUSED_ROLLOUT_STRATEGY.to_string(),
+            "OnDelete".to_string(),
+        )]);
+        assert!(strategy_restore_patch(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_pods_to_update() {
+        let pod = |name: &str, node: &str, image: &str| -> Pod {
+            serde_json::from_value(json!({
+                "metadata": { "name": name },
+                "spec": {
+                    "nodeName": node,
+                    "containers": [{ "name": "agent", "image": image }]
+                }
+            }))
+            .unwrap()
+        };
+
+        let pods = vec![
+            pod("agent-c", "node-3", "agent:1.0.0"),
+            pod("agent-a", "node-1", "agent:2.0.0"),
+            pod("agent-b", "node-2", "agent:1.0.0"),
+        ];
+
+        assert_eq!(
+            pods_to_update(&pods, "agent", "agent:2.0.0"),
+            vec!["agent-b".to_string(), "agent-c".to_string()]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_paused_rollout_timeout_leaves_on_delete() {
+        use crate::testing::{FakeApi, api_error};
+        use axum::http::{Method, StatusCode};
+
+        let daemonset = json!({
+            "apiVersion": "apps/v1",
+            "kind": "DaemonSet",
+            "metadata": {
+                "name": "agent",
+                "namespace": "default",
+                "annotations": {
+                    annotations::PAUSED_ROLLOUT_STRATEGY: r#"{"type":"RollingUpdate"}"#
+                }
+            },
+            "spec": {
+                "selector": { "matchLabels": { "app": "agent" } },
+                "updateStrategy": { "type": "OnDelete" },
+                "template": {
+                    "metadata": { "labels": { "app": "agent" } },
+                    "spec": { "containers": [{ "name": "agent", "image": "agent:2.0.0" }] }
+                }
+            },
+            // The replacement pod never becomes ready
+            "status": {
+                "currentNumberScheduled": 1,
+                "desiredNumberScheduled": 1,
+                "numberMisscheduled": 0,
+                "numberReady": 0,
+                "updatedNumberScheduled": 0
+            }
+        });
+        let pod = json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": { "name": "agent-a", "namespace": "default" },
+            "spec": { "nodeName": "node-a", "containers": [{ "name": "agent", "image": "agent:1.0.0" }] }
+        });
+
+        let api = FakeApi::new(
+            move |request| match (&request.method, request.path.as_str()) {
+                (&Method::GET, "/apis/apps/v1/namespaces/default/daemonsets/agent")
+                | (&Method::GET, "/apis/apps/v1/namespaces/default/daemonsets/agent/status")
+                | (&Method::PATCH, "/apis/apps/v1/namespaces/default/daemonsets/agent") => {
+                    (StatusCode::OK, daemonset.clone())
+                },
+                (&Method::GET, "/api/v1/namespaces/default/pods") => (
+                    StatusCode::OK,
+                    json!({ "apiVersion": "v1", "kind": "PodList", "metadata": {}, "items": [pod.clone()] }),
+                ),
+                (&Method::DELETE, "/api/v1/namespaces/default/pods/agent-a") => {
+                    (StatusCode::OK, pod.clone())
+                },
+                (&Method::POST, "/apis/events.k8s.io/v1/namespaces/default/events") => {
+                    (StatusCode::CREATED, request.body.clone())
+                },
+                _ => api_error(StatusCode::NOT_FOUND, "NotFound", "not found"),
+            },
+        );
+
+        let key = ("default".to_string(), "agent".to_string());
+        PAUSED_ROLLOUTS.lock().unwrap().insert(key.clone());
+        paused_rollout(
+            &api.client(),
+            "default",
+            "agent",
+            "agent",
+            "agent:2.0.0",
+            Duration::from_secs(60),
+        )
+        .await;
+
+        // The strategy is not restored: the only patch records the failure
+        let patches = api.bodies(Method::PATCH, "/daemonsets/agent");
+        assert_eq!(patches.len(), 1);
+        assert!(patches[0]["spec"].get("updateStrategy").is_none());
+        let failure = patches[0]["metadata"]["annotations"][annotations::PAUSED_ROLLOUT_FAILED]
+            .as_str()
+            .unwrap();
+        assert!(failure.starts_with("Timed out after 600s"), "{}", failure);
+
+        let events = api.bodies(Method::POST, "/events");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["type"], "Warning");
+        assert_eq!(events[0]["reason"], PAUSED_ROLLOUT_FAILED_REASON);
+        assert_eq!(events[0]["regarding"]["name"], "agent");
+
+        assert!(!PAUSED_ROLLOUTS.lock().unwrap().contains(&key));
+    }
 }
@@ -0,0 +1,352 @@
+//! Deployment updates delivered outside the Deployment.
+//!
+//! A Deployment annotated `headwind.sh/gitops-mode: git-push` is updated by a
+//! commit to its manifests instead of a patch, one annotated
+//! `headwind.sh/flux-kustomization` through the Kustomization's substitution
+//! variables and one annotated `headwind.sh/argocd-app` through the Argo CD
+//! Application's image override. Delivery follows the usual approval flow: when approval is
+//! required the UpdateRequest stays `Pending` and nothing is delivered until
+//! it is approved.
+
+use super::deployment::parse_image;
+use crate::config::features::features;
+use crate::gitops::{GitOpsConfig, record_completed_update};
+use crate::integrations::argocd::{ArgoCdAppConfig, ArgoCdClient, apply_argocd_update};
+use crate::integrations::flux::kustomization::{
+    FluxKustomizationConfig, update_kustomization_image_substitute,
+};
+use crate::models::crd::{
+    TargetRef, UpdatePhase, UpdatePolicyType, UpdateRequest, UpdateRequestSpec,
+    UpdateRequestStatus, UpdateType,
+};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::api::{Patch, PatchParams};
+use kube::{Api, Client, ResourceExt};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// Where a Deployment's updates are delivered instead of patching it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delivery {
+    /// A commit to the manifests in git
+    Git(GitOpsConfig),
+    /// A substitution variable of a Flux Kustomization
+    Flux(FluxKustomizationConfig),
+    /// The image override of an Argo CD Application, followed by a sync
+    ArgoCd(ArgoCdAppConfig),
+}
+
+impl Delivery {
+    /// The delivery configured by a Deployment's annotations, if any
+    pub fn from_annotations(annotations: &BTreeMap<String, String>) -> Option<Self> {
+        GitOpsConfig::from_annotations(annotations)
+            .map(Delivery::Git)
+            .or_else(|| FluxKustomizationConfig::from_annotations(annotations).map(Delivery::Flux))
+            .or_else(|| ArgoCdAppConfig::from_annotations(annotations).map(Delivery::ArgoCd))
+    }
+
+    /// Where updates go, for UpdateRequest reasons and messages
+    pub fn describe(&self) -> String {
+        match self {
+            Delivery::Git(config) => {
+                format!("committed to {} ({})", config.repo_url, config.file_path)
+            },
+            Delivery::Flux(flux) => match &flux.namespace {
+                Some(namespace) => format!(
+                    "substituted in Flux Kustomization {}/{}",
+                    namespace, flux.name
+                ),
+                None => format!("substituted in Flux Kustomization {}", flux.name),
+            },
+            Delivery::ArgoCd(app) => format!(
+                "image override of Argo CD Application {}/{}",
+                app.namespace, app.name
+            ),
+        }
+    }
+
+    /// Deliver `new_image` for `container` of the Deployment `namespace/name`.
+    /// Returns the status fields to record on the UpdateRequest.
+    pub async fn deliver(
+        &self,
+        client: &Client,
+        namespace: &str,
+        name: &str,
+        container: &str,
+        new_image: &str,
+    ) -> Result<serde_json::Value> {
+        let (image_name, new_tag) = parse_image(new_image)?;
+        match self {
+            Delivery::Git(config) => {
+                // Patching the cluster instead would make it drift from git
+                if !features().git_push_mode {
+                    return Err(anyhow::anyhow!(
+                        "{}/{} uses git-push mode but HEADWIND_FEATURE_GIT_PUSH_MODE is not enabled",
+                        namespace,
+                        name
+                    ));
+                }
+                info!(
+                    "Delivering {} for {}/{} container {} via git push to {} ({})",
+                    new_image, namespace, name, container, config.repo_url, config.file_path
+                );
+                let sha = config.updater().apply_update(&image_name, &new_tag).await?;
+                Ok(json!({ "commitSha": sha }))
+            },
+            Delivery::Flux(flux) => {
+                // The Kustomization may only be in another namespace if it allows it
+                let kustomization_namespace = flux.namespace.as_deref().unwrap_or(namespace);
+                update_kustomization_image_substitute(
+                    client,
+                    namespace,
+                    kustomization_namespace,
+                    &flux.name,
+                    &flux.var_name_for(container),
+                    &new_tag,
+                )
+                .await?;
+                info!(
+                    "Updated Flux Kustomization {}/{} for {}/{} container {} to {}",
+                    kustomization_namespace, flux.name, namespace, name, container, new_tag
+                );
+                Ok(json!({}))
+            },
+            Delivery::ArgoCd(app) => {
+                let argocd = ArgoCdClient::from_env(client.clone());
+                let operation =
+                    apply_argocd_update(&argocd, app, namespace, &image_name, new_image).await?;
+                info!(
+                    "Updated Argo CD Application {}/{} for {}/{} container {} to {} (sync operation: {})",
+                    app.namespace,
+                    app.name,
+                    namespace,
+                    name,
+                    container,
+                    new_tag,
+                    operation.as_deref().unwrap_or("none")
+                );
+                Ok(match operation {
+                    Some(operation) => json!({ "argocdSyncOperation": operation }),
+                    None => json!({}),
+                })
+            },
+        }
+    }
+}
+
+/// Deliver an update that needs no approval and record it as a `Completed`
+/// UpdateRequest
+pub async fn deliver_unapproved(
+    client: &Client,
+    delivery: &Delivery,
+    target_ref: TargetRef,
+    container: &str,
+    current_image: &str,
+    new_image: &str,
+    policy: UpdatePolicyType,
+) -> Result<()> {
+    let fields = delivery
+        .deliver(
+            client,
+            &target_ref.namespace,
+            &target_ref.name,
+            container,
+            new_image,
+        )
+        .await?;
+
+    let (_, new_tag) = parse_image(new_image)?;
+    let spec = UpdateRequestSpec {
+        target_ref,
+        update_type: UpdateType::Image,
+        container_name: Some(container.to_string()),
+        container_kind: Default::default(),
+        current_image: current_image.to_string(),
+        new_image: new_image.to_string(),
+        policy,
+        reason: Some(delivery.describe()),
+        require_approval: false,
+        expires_at: None,
+        scheduled_at: None,
+        priority: None,
+        updates: Vec::new(),
+        discussion_thread: Vec::new(),
+        sla_deadline: None,
+        impact: None,
+    };
+    let mut status = serde_json::to_value(UpdateRequestStatus {
+        phase: UpdatePhase::Completed,
+        message: Some(format!("Delivered: {}", delivery.describe())),
+        last_updated: Some(Utc::now()),
+        ..Default::default()
+    })?;
+    if let (Some(status), serde_json::Value::Object(fields)) = (status.as_object_mut(), fields) {
+        status.extend(fields);
+    }
+    record_completed_update(client, spec, &new_tag, serde_json::from_value(status)?).await?;
+    Ok(())
+}
+
+/// Deliver an approved UpdateRequest for a Deployment with a [`Delivery`],
+/// recording the outcome in its status. Returns false, without doing
+/// anything, when the Deployment is patched as usual.
+pub async fn deliver_update_request(
+    client: &Client,
+    update_request: &UpdateRequest,
+    update_request_name: &str,
+) -> Result<bool> {
+    let spec = &update_request.spec;
+    let target = &spec.target_ref;
+    if target.kind != "Deployment" {
+        return Ok(false);
+    }
+
+    let deployments: Api<Deployment> = Api::namespaced(client.clone(), &target.namespace);
+    let deployment = deployments.get(&target.name).await?;
+    let Some(delivery) = Delivery::from_annotations(deployment.annotations()) else {
+        return Ok(false);
+    };
+
+    let updates: Vec<(&str, &str)> = if spec.updates.is_empty() {
+        let container = spec
+            .container_name
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("Container name not specified in UpdateRequest"))?;
+        vec![(container, spec.new_image.as_str())]
+    } else {
+        spec.updates
+            .iter()
+            .map(|u| (u.container_name.as_str(), u.new_image.as_str()))
+            .collect()
+    };
+
+    let mut status = serde_json::Map::new();
+    for (container, new_image) in updates {
+        let fields = delivery
+            .deliver(
+                client,
+                &target.namespace,
+                &target.name,
+                container,
+                new_image,
+            )
+            .await?;
+        if let serde_json::Value::Object(fields) = fields {
+            status.extend(fields);
+        }
+    }
+
+    let namespace = update_request.namespace().unwrap_or_default();
+    let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
+    update_requests
+        .patch_status(
+            update_request_name,
+            &PatchParams::default(),
+            &Patch::Merge(json!({ "status": status })),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to record delivery on UpdateRequest {}/{}",
+                namespace, update_request_name
+            )
+        })?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::policy::annotations;
+    use crate::testing::{self, FakeApi};
+
+    fn update_request(kind: &str) -> UpdateRequest {
+        let mut update_request = testing::update_request("production", "web-nginx-1-26-0");
+        update_request.spec.target_ref.kind = kind.to_string();
+        update_request
+    }
+
+    /// A fake API server serving the Deployment `production/web` with the
+    /// given annotations and panicking on anything else
+    fn api_with_deployment(annotations: serde_json::Value) -> FakeApi {
+        FakeApi::ok(move |request| {
+            assert_eq!(
+                request.path,
+                "/apis/apps/v1/namespaces/production/deployments/web"
+            );
+            json!({
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "metadata": {
+                    "name": "web",
+                    "namespace": "production",
+                    "annotations": annotations
+                }
+            })
+        })
+    }
+
+    #[test]
+    fn test_delivery_from_annotations() {
+        let mut pairs = BTreeMap::new();
+        assert_eq!(Delivery::from_annotations(&pairs), None);
+
+        pairs.insert(annotations::GITOPS_MODE.to_string(), "git-push".to_string());
+        pairs.insert(
+            annotations::GIT_REPO_URL.to_string(),
+            "https://github.com/org/deploy.git".to_string(),
+        );
+        pairs.insert(
+            annotations::GIT_FILE_PATH.to_string(),
+            "apps/web/deployment.yaml".to_string(),
+        );
+        assert!(matches!(
+            Delivery::from_annotations(&pairs),
+            Some(Delivery::Git(_))
+        ));
+
+        let pairs = BTreeMap::from([(
+            annotations::FLUX_KUSTOMIZATION.to_string(),
+            "apps".to_string(),
+        )]);
+        assert!(matches!(
+            Delivery::from_annotations(&pairs),
+            Some(Delivery::Flux(_))
+        ));
+
+        let pairs = BTreeMap::from([(annotations::ARGOCD_APP.to_string(), "my-app".to_string())]);
+        assert!(matches!(
+            Delivery::from_annotations(&pairs),
+            Some(Delivery::ArgoCd(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_other_kinds_are_not_delivered() {
+        let api = api_with_deployment(json!({}));
+        let delivered = deliver_update_request(
+            &api.client(),
+            &update_request("StatefulSet"),
+            "web-nginx-1-26-0",
+        )
+        .await
+        .unwrap();
+        assert!(!delivered);
+    }
+
+    #[tokio::test]
+    async fn test_deployments_without_delivery_are_patched() {
+        let api = api_with_deployment(json!({ "headwind.sh/policy": "minor" }));
+        let delivered = deliver_update_request(
+            &api.client(),
+            &update_request("Deployment"),
+            "web-nginx-1-26-0",
+        )
+        .await
+        .unwrap();
+        assert!(!delivered);
+    }
+}
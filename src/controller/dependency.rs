@@ -0,0 +1,392 @@
+//! Ordering of updates between Deployments.
+//!
+//! A Deployment annotated `headwind.sh/update-after: "shared-lib"` is only
+//! updated once the `shared-lib` Deployment of the same namespace has no
+//! pending or approved UpdateRequest left. Several names can be listed,
+//! separated by commas. The graph of these annotations is rebuilt at startup
+//! and every five minutes; a cycle is reported as a warning event and the
+//! previous graph is kept until it is removed.
+
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use crate::models::policy::annotations;
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use kube::{Api, Client, Resource, ResourceExt};
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::RwLock;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+/// How often the graph is rebuilt from the cluster's Deployments
+const REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Reason of the warning event emitted for a dependency cycle
+pub const DEPENDENCY_CYCLE_REASON: &str = "UpdateDependencyCycle";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GraphError {
+    #[error("Update dependency cycle: {}", path.join(" -> "))]
+    CycleDetected { path: Vec<String> },
+}
+
+/// Deployments, as `namespace/name`, and the Deployments each updates after
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateDependencyGraph {
+    predecessors: BTreeMap<String, BTreeSet<String>>,
+}
+
+fn node(namespace: &str, name: &str) -> String {
+    format!("{}/{}", namespace, name)
+}
+
+impl UpdateDependencyGraph {
+    /// Graph of the `headwind.sh/update-after` annotations of `deployments`,
+    /// failing if they form a cycle
+    pub fn from_deployments(deployments: &[Deployment]) -> Result<Self, GraphError> {
+        let mut graph = Self::default();
+        for deployment in deployments {
+            let namespace = deployment.namespace().unwrap_or_default();
+            let after = deployment
+                .annotations()
+                .get(annotations::UPDATE_AFTER)
+                .map(String::as_str)
+                .unwrap_or_default();
+            graph.add(&namespace, &deployment.name_any(), after);
+        }
+        graph.update_order()?;
+        Ok(graph)
+    }
+
+    /// Add `name` with the comma-separated Deployments it updates after
+    fn add(&mut self, namespace: &str, name: &str, after: &str) {
+        let predecessors: BTreeSet<String> = after
+            .split(',')
+            .map(str::trim)
+            .filter(|p| !p.is_empty() && *p != name)
+            .map(|p| node(namespace, p))
+            .collect();
+        for predecessor in &predecessors {
+            self.predecessors.entry(predecessor.clone()).or_default();
+        }
+        self.predecessors
+            .entry(node(namespace, name))
+            .or_default()
+            .extend(predecessors);
+    }
+
+    /// Deployments of `namespace` that `name` updates after
+    pub fn predecessors(&self, namespace: &str, name: &str) -> Vec<String> {
+        let prefix = format!("{}/", namespace);
+        self.predecessors
+            .get(&node(namespace, name))
+            .into_iter()
+            .flatten()
+            .filter_map(|p| p.strip_prefix(&prefix).map(String::from))
+            .collect()
+    }
+
+    /// Every Deployment, each after the ones it updates after
+    pub fn update_order(&self) -> Result<Vec<String>, GraphError> {
+        let mut remaining: BTreeMap<&str, usize> = self
+            .predecessors
+            .iter()
+            .map(|(node, predecessors)| (node.as_str(), predecessors.len()))
+            .collect();
+        let mut ready: Vec<&str> = remaining
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(node, _)| *node)
+            .collect();
+        let mut order = Vec::with_capacity(remaining.len());
+
+        while let Some(next) = ready.pop() {
+            remaining.remove(next);
+            order.push(next.to_string());
+            for (node, predecessors) in &self.predecessors {
+                if predecessors.contains(next)
+                    && let Some(count) = remaining.get_mut(node.as_str())
+                {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(node);
+                    }
+                }
+            }
+        }
+
+        if remaining.is_empty() {
+            Ok(order)
+        } else {
+            Err(GraphError::CycleDetected {
+                path: self.find_cycle(remaining.keys().copied()),
+            })
+        }
+    }
+
+    /// A cycle among `nodes`, which all have a predecessor left after
+    /// topological sorting, starting and ending with the same Deployment
+    fn find_cycle<'a>(&'a self, nodes: impl Iterator<Item = &'a str>) -> Vec<String> {
+        let nodes: BTreeSet<&str> = nodes.collect();
+        let Some(start) = nodes.first() else {
+            return Vec::new();
+        };
+
+        // Follow predecessors until one repeats
+        let mut path: Vec<&str> = vec![start];
+        loop {
+            let last = path[path.len() - 1];
+            let Some(next) = self.predecessors[last]
+                .iter()
+                .map(String::as_str)
+                .find(|p| nodes.contains(p))
+            else {
+                return path.iter().map(|n| n.to_string()).collect();
+            };
+            if let Some(i) = path.iter().position(|n| *n == next) {
+                // Reverse into update order: each Deployment before the one waiting for it
+                let mut cycle: Vec<String> =
+                    path[i..].iter().rev().map(|n| n.to_string()).collect();
+                cycle.insert(0, next.to_string());
+                return cycle;
+            }
+            path.push(next);
+        }
+    }
+}
+
+static GRAPH: Lazy<RwLock<UpdateDependencyGraph>> =
+    Lazy::new(|| RwLock::new(UpdateDependencyGraph::default()));
+
+/// Deployments that `namespace/name` updates after, from the current graph
+pub fn predecessors(namespace: &str, name: &str) -> Vec<String> {
+    GRAPH
+        .read()
+        .map(|graph| graph.predecessors(namespace, name))
+        .unwrap_or_default()
+}
+
+/// Rebuild the graph from the watched Deployments
+pub async fn refresh(client: &Client) {
+    let api: Api<Deployment> = super::watched_api(client.clone());
+    let deployments = match api.list(&Default::default()).await {
+        Ok(list) => list.items,
+        Err(e) => {
+            warn!("Failed to list Deployments for update dependencies: {}", e);
+            return;
+        },
+    };
+
+    match UpdateDependencyGraph::from_deployments(&deployments) {
+        Ok(graph) => {
+            debug!("Update dependency graph: {:?}", graph);
+            if let Ok(mut current) = GRAPH.write() {
+                *current = graph;
+            }
+        },
+        Err(e) => {
+            error!("{}, keeping the previous update order", e);
+            let GraphError::CycleDetected { path } = &e;
+            if let Some(deployment) = path.first().and_then(|first| {
+                deployments
+                    .iter()
+                    .find(|d| node(&d.namespace().unwrap_or_default(), &d.name_any()) == *first)
+            }) {
+                publish_cycle(client, deployment, &e.to_string()).await;
+            }
+        },
+    }
+}
+
+async fn publish_cycle(client: &Client, deployment: &Deployment, note: &str) {
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    let event = Event {
+        type_: EventType::Warning,
+        reason: DEPENDENCY_CYCLE_REASON.to_string(),
+        note: Some(note.to_string()),
+        action: "UpdateImage".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(&event, &deployment.object_ref(&())).await {
+        warn!(
+            "Failed to publish {} event for {}/{}: {}",
+            DEPENDENCY_CYCLE_REASON,
+            deployment.namespace().unwrap_or_default(),
+            deployment.name_any(),
+            e
+        );
+    }
+}
+
+/// Rebuild the graph now and every five minutes
+pub fn start_graph_refresher(client: Client) -> JoinHandle<()> {
+    info!(
+        "Refreshing update dependencies every {}s",
+        REFRESH_INTERVAL.as_secs()
+    );
+    tokio::spawn(async move {
+        loop {
+            refresh(&client).await;
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    })
+}
+
+/// Predecessors of `namespace/name` that still have a pending or approved
+/// UpdateRequest, which the update has to wait for
+pub async fn pending_predecessors(client: &Client, namespace: &str, name: &str) -> Vec<String> {
+    let predecessors = predecessors(namespace, name);
+    if predecessors.is_empty() {
+        return Vec::new();
+    }
+
+    let api: Api<UpdateRequest> = Api::namespaced(client.clone(), namespace);
+    match api.list(&Default::default()).await {
+        Ok(list) => blocking_predecessors(&predecessors, &list.items),
+        Err(e) => {
+            warn!(
+                "Failed to list UpdateRequests for dependencies of {}/{}, not waiting: {}",
+                namespace, name, e
+            );
+            Vec::new()
+        },
+    }
+}
+
+/// `predecessors` with an UpdateRequest that hasn't been applied or closed
+fn blocking_predecessors(
+    predecessors: &[String],
+    update_requests: &[UpdateRequest],
+) -> Vec<String> {
+    predecessors
+        .iter()
+        .filter(|predecessor| {
+            update_requests.iter().any(|ur| {
+                ur.spec.target_ref.kind == "Deployment"
+                    && ur.spec.target_ref.name == **predecessor
+                    && ur.status.as_ref().is_none_or(|s| {
+                        matches!(s.phase, UpdatePhase::Pending | UpdatePhase::Approved)
+                    })
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(edges: &[(&str, &str)]) -> UpdateDependencyGraph {
+        let mut graph = UpdateDependencyGraph::default();
+        for (name, after) in edges {
+            graph.add("prod", name, after);
+        }
+        graph
+    }
+
+    fn position(order: &[String], name: &str) -> usize {
+        order.iter().position(|n| n == &node("prod", name)).unwrap()
+    }
+
+    #[test]
+    fn test_linear_order() {
+        // api after lib, web after api
+        let graph = graph(&[("web", "api"), ("api", "lib"), ("lib", "")]);
+        assert_eq!(
+            graph.update_order().unwrap(),
+            ["prod/lib", "prod/api", "prod/web"]
+        );
+        assert_eq!(graph.predecessors("prod", "web"), ["api"]);
+        assert!(graph.predecessors("prod", "lib").is_empty());
+        assert!(graph.predecessors("staging", "web").is_empty());
+    }
+
+    #[test]
+    fn test_diamond_order() {
+        // left and right after base, top after both
+        let graph = graph(&[("top", "left, right"), ("left", "base"), ("right", "base")]);
+        let order = graph.update_order().unwrap();
+        assert_eq!(order.len(), 4);
+        assert_eq!(position(&order, "base"), 0);
+        assert_eq!(position(&order, "top"), 3);
+        assert_eq!(graph.predecessors("prod", "top"), ["left", "right"]);
+    }
+
+    #[test]
+    fn test_cycle_detected() {
+        let graph = graph(&[("a", "c"), ("b", "a"), ("c", "b"), ("d", "a")]);
+        let Err(GraphError::CycleDetected { path }) = graph.update_order() else {
+            panic!("expected a cycle");
+        };
+        assert_eq!(path, ["prod/a", "prod/b", "prod/c", "prod/a"]);
+        assert_eq!(
+            GraphError::CycleDetected { path }.to_string(),
+            "Update dependency cycle: prod/a -> prod/b -> prod/c -> prod/a"
+        );
+    }
+
+    #[test]
+    fn test_from_deployments() {
+        let deployments: Vec<Deployment> = serde_json::from_value(serde_json::json!([
+            {
+                "metadata": {
+                    "name": "web",
+                    "namespace": "prod",
+                    "annotations": { "headwind.sh/update-after": "lib" }
+                }
+            },
+            { "metadata": { "name": "lib", "namespace": "prod" } },
+            {
+                "metadata": {
+                    "name": "lib",
+                    "namespace": "staging",
+                    "annotations": { "headwind.sh/update-after": "lib" }
+                }
+            }
+        ]))
+        .unwrap();
+        let graph = UpdateDependencyGraph::from_deployments(&deployments).unwrap();
+        assert_eq!(graph.predecessors("prod", "web"), ["lib"]);
+        // A Deployment updating after itself is ignored
+        assert!(graph.predecessors("staging", "lib").is_empty());
+    }
+
+    #[test]
+    fn test_blocking_predecessors() {
+        let update_request = |name: &str, phase: Option<UpdatePhase>| -> UpdateRequest {
+            let mut ur: UpdateRequest = serde_json::from_value(serde_json::json!({
+                "apiVersion": "headwind.sh/v1alpha1",
+                "kind": "UpdateRequest",
+                "metadata": { "name": format!("{}-update", name), "namespace": "prod" },
+                "spec": {
+                    "targetRef": { "apiVersion": "apps/v1", "kind": "Deployment", "name": name, "namespace": "prod" },
+                    "updateType": "image",
+                    "currentImage": "lib:1.0.0",
+                    "newImage": "lib:1.1.0",
+                    "policy": "minor"
+                }
+            }))
+            .unwrap();
+            ur.status = phase.map(|phase| crate::models::crd::UpdateRequestStatus {
+                phase,
+                ..Default::default()
+            });
+            ur
+        };
+
+        let predecessors = vec!["lib".to_string(), "api".to_string(), "db".to_string()];
+        let update_requests = vec![
+            update_request("lib", None),
+            update_request("api", Some(UpdatePhase::Completed)),
+            update_request("db", Some(UpdatePhase::Approved)),
+            update_request("cache", Some(UpdatePhase::Pending)),
+        ];
+        assert_eq!(
+            blocking_predecessors(&predecessors, &update_requests),
+            ["lib", "db"]
+        );
+    }
+}
@@ -1,29 +1,41 @@
-use crate::metrics::{RECONCILE_DURATION, RECONCILE_ERRORS};
+use super::delivery::{self, Delivery};
+use super::dependency;
+use super::ephemeral;
+use super::impact;
+use super::progress;
+use super::quota::{self, QuotaCheckResult};
+use crate::config::features::features;
+use crate::metrics::{DIGEST_PIN_CORRECTIONS_TOTAL, RECONCILE_DURATION, RECONCILE_ERRORS};
 use crate::models::{
-    ResourcePolicy, TargetRef, UpdatePolicy, UpdatePolicyType, UpdateRequest, UpdateRequestSpec,
-    UpdateType, annotations,
+    ProgressStep, ResourcePolicy, SingleUpdate, TargetRef, UpdateImpact, UpdatePolicy,
+    UpdatePolicyType, UpdateRequest, UpdateRequestSpec, UpdateType, annotations,
+    global_tag_normalization, parse_container_names, parse_slsa_level, parse_tag_normalization,
 };
 use crate::notifications::{self, DeploymentInfo};
 use crate::policy::PolicyEngine;
+use crate::policy::ignore::{ignore_patterns, is_image_ignored, is_resource_ignored};
+use crate::polling::digest::{ImageReference, strip_digest, target_image};
 use crate::rollback::RollbackManager;
+use crate::schedule::PendingUpdate;
 use anyhow::Result;
 use chrono::Utc;
 use futures::StreamExt;
 use k8s_openapi::api::apps::v1::Deployment;
 use kube::{
-    ResourceExt,
+    Resource, ResourceExt,
     api::{Api, Patch, PatchParams, PostParams},
     client::Client,
-    runtime::{
-        controller::{Action, Controller},
-        watcher::Config,
-    },
+    runtime::controller::Action,
 };
 use serde_json::json;
+use std::collections::HashSet;
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, error, info, instrument, warn};
 
+/// How long an update waits before the ResourceQuota is checked again
+const QUOTA_RETRY_DELAY: Duration = Duration::from_secs(60);
+
 pub struct DeploymentController {
     client: Client,
     policy_engine: Arc<PolicyEngine>,
@@ -49,11 +61,11 @@ impl DeploymentController {
         const MAX_BACKOFF: u64 = 60;
 
         loop {
-            let deployments: Api<Deployment> = Api::all(self.client.clone());
+            let deployments: Api<Deployment> = super::watched_api(self.client.clone());
 
             info!("Creating controller for deployments");
 
-            let result = Controller::new(deployments, Config::default())
+            let result = super::queue_metrics::controller(deployments, "deployment")
                 .run(
                     reconcile,
                     error_policy,
@@ -102,12 +114,14 @@ struct ControllerContext {
     policy_engine: Arc<PolicyEngine>,
 }
 
-#[instrument(skip(_ctx, deployment), fields(deployment = %deployment.name_any()))]
+#[instrument(skip(ctx, deployment), fields(deployment = %deployment.name_any()))]
 async fn reconcile(
     deployment: Arc<Deployment>,
-    _ctx: Arc<ControllerContext>,
+    ctx: Arc<ControllerContext>,
 ) -> Result<Action, kube::Error> {
     let _timer = RECONCILE_DURATION.start_timer();
+    let _queue_timer = super::queue_metrics::start_reconcile("deployment", deployment.as_ref());
+    let _in_flight = super::concurrency::track("deployment");
 
     let name = deployment.name_any();
     let namespace = deployment.namespace().ok_or_else(|| {
@@ -130,9 +144,38 @@ async fn reconcile(
         },
     };
 
+    if is_resource_ignored(annotations) {
+        debug!("Deployment is ignored, skipping");
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    }
+
     // Parse the policy from annotations
     let policy = parse_policy_from_annotations(annotations)?;
 
+    // Restore the pinned digest wherever a targeted container drifted from it
+    if let Some(pinned_digest) = annotations.get(annotations::PIN_DIGEST) {
+        let containers = deployment
+            .spec
+            .as_ref()
+            .and_then(|s| s.template.spec.as_ref())
+            .map(|pod| pod.containers.as_slice())
+            .unwrap_or_default();
+        for container in containers {
+            if !policy.targets_container(&container.name) {
+                continue;
+            }
+            if let Err(e) =
+                verify_and_pin_digest(&ctx.client, &deployment, &container.name, pinned_digest)
+                    .await
+            {
+                warn!(
+                    "Failed to pin container {} in {}/{} to {}: {:#}",
+                    container.name, namespace, name, pinned_digest, e
+                );
+            }
+        }
+    }
+
     if policy.policy == UpdatePolicy::None {
         debug!("Deployment has policy 'none', skipping");
         return Ok(Action::requeue(Duration::from_secs(300)));
@@ -156,6 +199,7 @@ async fn reconcile(
         .ok_or_else(|| create_error("Deployment template has no spec"))?;
 
     let containers = &template_spec.containers;
+    let mut tracked_images = HashSet::new();
 
     for container in containers {
         // Skip containers not in the tracked images list (if specified)
@@ -167,11 +211,24 @@ async fn reconcile(
             continue;
         }
 
+        if !policy.targets_container(&container.name) {
+            debug!(
+                "Skipping container {} (not in targeted container names)",
+                container.name
+            );
+            continue;
+        }
+
         let current_image = container
             .image
             .as_ref()
             .ok_or_else(|| create_error(&format!("Container {} has no image", container.name)))?;
 
+        if is_image_ignored(ignore_patterns(), current_image) {
+            debug!("Skipping container {} (image ignored)", container.name);
+            continue;
+        }
+
         // Extract image name and tag
         let (image_name, current_tag) = parse_image(current_image)?;
 
@@ -179,11 +236,85 @@ async fn reconcile(
             "Processing container {} with image {}:{}",
             container.name, image_name, current_tag
         );
+        tracked_images.insert(current_image.clone());
+    }
+
+    // Ephemeral debug containers are only reported, never updated
+    if !tracked_images.is_empty() {
+        ephemeral::report_ephemeral_containers(&ctx.client, &deployment, &tracked_images).await;
     }
 
     Ok(Action::requeue(Duration::from_secs(60)))
 }
 
+/// Run `handle_image_update` again after `QUOTA_RETRY_DELAY`, unless the
+/// container no longer runs `current_image` by then
+fn retry_after_quota_wait(
+    ctx: Arc<ControllerContext>,
+    policy: ResourcePolicy,
+    deployment: &Deployment,
+    container_name: &str,
+    current_image: &str,
+    new_image: &str,
+) {
+    let namespace = deployment.namespace().unwrap_or_default();
+    let name = deployment.name_any();
+    let (container_name, current_image, new_image) = (
+        container_name.to_string(),
+        current_image.to_string(),
+        new_image.to_string(),
+    );
+
+    tokio::spawn(async move {
+        tokio::time::sleep(QUOTA_RETRY_DELAY).await;
+
+        let deployments: Api<Deployment> = Api::namespaced(ctx.client.clone(), &namespace);
+        let deployment = match deployments.get(&name).await {
+            Ok(deployment) => deployment,
+            Err(e) => {
+                warn!(
+                    "Failed to get {}/{} for quota retry: {}",
+                    namespace, name, e
+                );
+                return;
+            },
+        };
+        let unchanged = deployment
+            .spec
+            .as_ref()
+            .and_then(|spec| spec.template.spec.as_ref())
+            .is_some_and(|spec| {
+                spec.containers.iter().any(|c| {
+                    c.name == container_name && c.image.as_deref() == Some(current_image.as_str())
+                })
+            });
+        if !unchanged {
+            debug!(
+                "Container {} of {}/{} changed while waiting for quota, dropping retry",
+                container_name, namespace, name
+            );
+            return;
+        }
+
+        if let Err(e) = handle_image_update(
+            ctx.client.clone(),
+            ctx.policy_engine.clone(),
+            &deployment,
+            &policy,
+            &container_name,
+            &current_image,
+            &new_image,
+        )
+        .await
+        {
+            error!(
+                "Retrying update of {}/{} after quota wait failed: {}",
+                namespace, name, e
+            );
+        }
+    });
+}
+
 fn error_policy(
     _deployment: Arc<Deployment>,
     error: &kube::Error,
@@ -209,7 +340,9 @@ fn create_error(msg: &str) -> kube::Error {
 ///   "nginx:1.25.0" -> ("nginx", "1.25.0")
 ///   "gcr.io/project/image:v1.0" -> ("gcr.io/project/image", "v1.0")
 ///   "nginx" -> ("nginx", "latest")
-fn parse_image(image: &str) -> Result<(String, String), kube::Error> {
+///   "nginx:1.25.0@sha256:abc" -> ("nginx", "1.25.0")
+pub(super) fn parse_image(image: &str) -> Result<(String, String), kube::Error> {
+    let image = strip_digest(image);
     match image.rsplit_once(':') {
         Some((name, tag)) => {
             // Check if the part after ':' looks like a port (e.g., "localhost:5000/image")
@@ -243,11 +376,59 @@ pub async fn handle_image_update(
     let namespace = deployment.namespace().unwrap();
     let name = deployment.name_any();
 
+    // Headwind's own Deployment is never updated without approval
+    let mut policy = policy.clone();
+    if super::self_update::self_update_for(deployment).is_some() {
+        policy.require_approval = true;
+    }
+    let policy = &policy;
+
+    if !policy.targets_container(container_name) {
+        debug!(
+            "Skipping update of container {} in {}/{}: not in {}",
+            container_name,
+            namespace,
+            name,
+            annotations::CONTAINER_NAMES
+        );
+        return Ok(());
+    }
+
+    // A pinned digest freezes the container until the pin is removed
+    if deployment
+        .annotations()
+        .contains_key(annotations::PIN_DIGEST)
+    {
+        info!(
+            "Skipping update of container {} in {}/{} to {}: pinned by {}",
+            container_name,
+            namespace,
+            name,
+            new_image,
+            annotations::PIN_DIGEST
+        );
+        return Ok(());
+    }
+
     // Parse images to get tags
     let (_, current_tag) = parse_image(current_image)?;
     let (image_name, new_tag) = parse_image(new_image)?;
 
+    // OPA decides before the update policy is evaluated
+    if !crate::policy::opa::check_update(
+        &ctx.client,
+        &deployment.object_ref(&()),
+        policy,
+        current_image,
+        new_image,
+    )
+    .await
+    {
+        return Ok(());
+    }
+
     // Evaluate policy to see if we should update
+    let policy_step = ProgressStep::start(progress::POLICY_EVALUATION);
     let should_update = ctx
         .policy_engine
         .should_update(policy, &current_tag, &new_tag)
@@ -260,6 +441,10 @@ pub async fn handle_image_update(
         );
         return Ok(());
     }
+    let policy_step = policy_step.succeed(Some(format!(
+        "{:?} policy allows {} -> {}",
+        policy.policy, current_tag, new_tag
+    )));
 
     // Check minimum update interval
     let min_interval_seconds = policy.min_update_interval.unwrap_or(300);
@@ -288,6 +473,70 @@ pub async fn handle_image_update(
         }
     }
 
+    // SLSA provenance: only images built by a trusted builder
+    if !crate::security::slsa::check_provenance(
+        &ctx.client,
+        &deployment.object_ref(&()),
+        policy,
+        new_image,
+    )
+    .await
+    {
+        return Ok(());
+    }
+
+    // Quota check: wait until the rolling update's surge pods fit
+    if deployment
+        .annotations()
+        .get(annotations::CHECK_QUOTA)
+        .is_some_and(|v| v == "true")
+    {
+        match quota::check_resource_quota(&ctx.client, &namespace, deployment).await {
+            Ok(QuotaCheckResult::Sufficient) => {},
+            Ok(QuotaCheckResult::Insufficient { reason }) => {
+                warn!(
+                    "Delaying update of {}/{} container {} to {}: {}",
+                    namespace, name, container_name, new_image, reason
+                );
+                quota::publish_quota_insufficient(&ctx.client, deployment, &reason).await;
+                retry_after_quota_wait(
+                    ctx.clone(),
+                    policy.clone(),
+                    deployment,
+                    container_name,
+                    current_image,
+                    new_image,
+                );
+                return Ok(());
+            },
+            Err(e) => warn!(
+                "ResourceQuota check for {}/{} failed, updating anyway: {}",
+                namespace, name, e
+            ),
+        }
+    }
+
+    // Dependency ordering: wait for the Deployments this one updates after
+    let waiting_for = dependency::pending_predecessors(&ctx.client, &namespace, &name).await;
+    if !waiting_for.is_empty() {
+        info!(
+            "Delaying update of {}/{} container {} to {}: waiting for pending updates of {}",
+            namespace,
+            name,
+            container_name,
+            new_image,
+            waiting_for.join(", ")
+        );
+        return Ok(());
+    }
+
+    // Digest pinning: deploy the digest the new tag currently resolves to
+    let annotations = deployment.metadata.annotations.as_ref();
+    let target = target_image(&ctx.client, &namespace, annotations, new_image)
+        .await
+        .map_err(|e| create_error(&format!("{:#}", e)))?;
+    let new_image = target.as_str();
+
     info!(
         "Update available for {}/{} container {}: {} -> {}",
         namespace, name, container_name, current_tag, new_tag
@@ -304,8 +553,171 @@ pub async fn handle_image_update(
     };
     notifications::notify_update_detected(deployment_info);
 
-    // Check if approval is required
-    if policy.require_approval {
+    if let Some(annotations) = annotations {
+        features()
+            .check_resource(annotations)
+            .map_err(|e| create_error(&e.to_string()))?;
+    }
+
+    // Delivery outside the Deployment: without approval it happens now,
+    // otherwise once the UpdateRequest created below is approved
+    let delivery = deployment
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(Delivery::from_annotations);
+    if let Some(delivery) = &delivery
+        && !policy.require_approval
+    {
+        delivery::deliver_unapproved(
+            &ctx.client,
+            delivery,
+            TargetRef {
+                api_version: "apps/v1".to_string(),
+                kind: "Deployment".to_string(),
+                name: name.clone(),
+                namespace: namespace.clone(),
+            },
+            container_name,
+            current_image,
+            new_image,
+            map_policy_to_crd(&policy.policy),
+        )
+        .await
+        .map_err(|e| create_error(&format!("Delivery of {} failed: {}", new_image, e)))?;
+
+        info!(
+            "Delivered {} for {}/{}: {}",
+            new_image,
+            namespace,
+            name,
+            delivery.describe()
+        );
+        return Ok(());
+    }
+
+    // Approval via GitHub pull request instead of an UpdateRequest
+    if policy.require_approval
+        && let Some(github) = deployment
+            .metadata
+            .annotations
+            .as_ref()
+            .and_then(crate::integrations::github::GitHubPrConfig::from_annotations)
+    {
+        let target = crate::integrations::github::PrTarget {
+            kind: "Deployment".to_string(),
+            namespace: namespace.clone(),
+            name: name.clone(),
+            container: container_name.to_string(),
+            new_image: new_image.to_string(),
+        };
+        let pr_result = match github.client(&ctx.client, &namespace).await {
+            Ok(gh) => {
+                gh.open_image_update_pr(&github, &target, &image_name, &current_tag, &new_tag)
+                    .await
+            },
+            Err(e) => Err(e),
+        };
+        let number =
+            pr_result.map_err(|e| create_error(&format!("Failed to open GitHub PR: {}", e)))?;
+        crate::integrations::github::record_proposed_image(
+            &ctx.client,
+            deployment,
+            container_name,
+            new_image,
+        )
+        .await
+        .map_err(|e| create_error(&e.to_string()))?;
+
+        info!(
+            "Opened GitHub PR #{} for {}/{} container {}",
+            number, namespace, name, container_name
+        );
+        return Ok(());
+    }
+
+    // Approval via GitLab merge request instead of an UpdateRequest
+    if policy.require_approval
+        && let Some(gitlab) = deployment
+            .metadata
+            .annotations
+            .as_ref()
+            .and_then(crate::integrations::gitlab::GitLabMrConfig::from_annotations)
+    {
+        let target = crate::integrations::github::PrTarget {
+            kind: "Deployment".to_string(),
+            namespace: namespace.clone(),
+            name: name.clone(),
+            container: container_name.to_string(),
+            new_image: new_image.to_string(),
+        };
+        let mr_result = match gitlab.client(&ctx.client, &namespace).await {
+            Ok(gl) => {
+                gl.open_image_update_mr(&gitlab, &target, &image_name, &current_tag, &new_tag)
+                    .await
+            },
+            Err(e) => Err(e),
+        };
+        let iid = mr_result
+            .map_err(|e| create_error(&format!("Failed to open GitLab merge request: {}", e)))?;
+        crate::integrations::github::record_proposed_image(
+            &ctx.client,
+            deployment,
+            container_name,
+            new_image,
+        )
+        .await
+        .map_err(|e| create_error(&e.to_string()))?;
+
+        info!(
+            "Opened GitLab merge request !{} for {}/{} container {}",
+            iid, namespace, name, container_name
+        );
+        return Ok(());
+    }
+
+    // Check if approval is required. Delivered updates are approved one by
+    // one, as a batch is applied in a single patch of the Deployment.
+    if policy.require_approval && policy.batch_mode && delivery.is_none() {
+        // Collect this container's update into the deployment's BatchUpdateRequest
+        super::add_to_batch(
+            &ctx.client,
+            TargetRef {
+                api_version: "apps/v1".to_string(),
+                kind: "Deployment".to_string(),
+                name: name.clone(),
+                namespace: namespace.clone(),
+            },
+            SingleUpdate {
+                container_name: container_name.to_string(),
+                current_image: current_image.to_string(),
+                new_image: new_image.to_string(),
+            },
+            map_policy_to_crd(&policy.policy),
+        )
+        .await?;
+    } else if policy.require_approval {
+        if !super::rate_limit::allow_update_request(
+            "Deployment",
+            &namespace,
+            &name,
+            deployment.metadata.annotations.as_ref(),
+        ) {
+            return Ok(());
+        }
+
+        // Estimate the blast radius for the reviewers
+        let impact = match impact::assess_update_impact(&ctx.client, deployment).await {
+            Ok(impact) => Some(impact),
+            Err(e) => {
+                warn!(
+                    "Failed to assess the impact of updating {}/{}: {:#}",
+                    namespace, name, e
+                );
+                None
+            },
+        };
+
         // Create UpdateRequest CRD
         create_update_request(
             ctx.client.clone(),
@@ -315,9 +727,57 @@ pub async fn handle_image_update(
             &image_name,
             current_image,
             new_image,
+            shared_image_updates(deployment, policy, current_image, new_image),
             &policy.policy,
+            deployment
+                .metadata
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(annotations::STATUS_PAGE))
+                .cloned(),
+            deployment.metadata.annotations.as_ref(),
+            progress::detection_steps(policy_step, features()),
+            impact,
         )
         .await?;
+    } else if let Some((schedule, scheduled_for)) = deployment
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|a| crate::schedule::deferred_until(a, Utc::now()))
+    {
+        // Outside the update schedule, apply at the next fire time
+        let client = ctx.client.clone();
+        let container = container_name.to_string();
+        let (current_image, image) = (current_image.to_string(), new_image.to_string());
+
+        crate::schedule::defer_update(
+            PendingUpdate {
+                kind: "Deployment".to_string(),
+                namespace: namespace.clone(),
+                name: name.clone(),
+                container: Some(container.clone()),
+                current_image: current_image.clone(),
+                new_image: image.clone(),
+                policy: policy.policy,
+                schedule,
+                scheduled_for,
+                queued_at: Utc::now(),
+            },
+            move || async move {
+                update_deployment_image(client, &namespace, &name, &container, &image).await?;
+                notifications::notify_update_completed(DeploymentInfo {
+                    name,
+                    namespace,
+                    current_image,
+                    new_image: image,
+                    container: Some(container),
+                    resource_kind: Some("Deployment".to_string()),
+                });
+                Ok(())
+            },
+        )
+        .await;
     } else {
         // Auto-update without approval
         info!(
@@ -338,6 +798,51 @@ pub async fn handle_image_update(
     Ok(())
 }
 
+/// Updates of every targeted container running `current_image`, in pod
+/// template order, when more than one does. Containers sharing an image are
+/// updated together so they never run different versions; a single container
+/// gets no `updates` and keeps the single-container UpdateRequest fields.
+pub(crate) fn shared_image_updates(
+    deployment: &Deployment,
+    policy: &ResourcePolicy,
+    current_image: &str,
+    new_image: &str,
+) -> Vec<SingleUpdate> {
+    let updates: Vec<SingleUpdate> = deployment
+        .spec
+        .as_ref()
+        .and_then(|s| s.template.spec.as_ref())
+        .map(|pod| pod.containers.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter(|c| c.image.as_deref() == Some(current_image))
+        .filter(|c| policy.targets_container(&c.name))
+        .map(|c| SingleUpdate {
+            container_name: c.name.clone(),
+            current_image: current_image.to_string(),
+            new_image: new_image.to_string(),
+        })
+        .collect();
+    if updates.len() > 1 {
+        updates
+    } else {
+        Vec::new()
+    }
+}
+
+/// Record the steps that led to a new UpdateRequest. Failures are only
+/// logged, the UpdateRequest is usable without them.
+async fn record_progress(
+    client: &Client,
+    namespace: &str,
+    request_name: &str,
+    steps: &[ProgressStep],
+) {
+    if let Err(e) = progress::record_steps(client, namespace, request_name, steps).await {
+        warn!("{:#}", e);
+    }
+}
+
 /// Create an UpdateRequest custom resource
 #[allow(clippy::too_many_arguments)]
 async fn create_update_request(
@@ -348,9 +853,22 @@ async fn create_update_request(
     image_name: &str,
     current_image: &str,
     new_image: &str,
+    updates: Vec<SingleUpdate>,
     policy: &UpdatePolicy,
+    status_page_url: Option<String>,
+    resource_annotations: Option<&std::collections::BTreeMap<String, String>>,
+    progress_steps: Vec<ProgressStep>,
+    impact: Option<UpdateImpact>,
 ) -> Result<(), kube::Error> {
-    let update_requests: Api<UpdateRequest> = Api::namespaced(client, namespace);
+    let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), namespace);
+
+    // Containers sharing the image are updated by one request, named after
+    // the first of them whichever container's update is handled
+    let container_name = updates
+        .first()
+        .map_or(container_name, |u| u.container_name.as_str())
+        .to_string();
+    let container_name = container_name.as_str();
 
     // Generate a deterministic name for the update request (without timestamp for deduplication)
     let (_, current_tag) = parse_image(current_image)?;
@@ -362,7 +880,7 @@ async fn create_update_request(
         new_tag.replace(['.', ':'], "-")
     );
 
-    let update_request = UpdateRequest::new(
+    let mut update_request = UpdateRequest::new(
         &request_name,
         UpdateRequestSpec {
             target_ref: TargetRef {
@@ -373,18 +891,47 @@ async fn create_update_request(
             },
             update_type: UpdateType::Image,
             container_name: Some(container_name.to_string()),
+            container_kind: Default::default(),
             current_image: current_image.to_string(),
             new_image: new_image.to_string(),
             policy: map_policy_to_crd(policy),
-            reason: Some(format!(
-                "New version available for {}: {} -> {}",
-                image_name, current_tag, new_tag
-            )),
+            reason: Some(if updates.is_empty() {
+                format!(
+                    "New version available for {}: {} -> {}",
+                    image_name, current_tag, new_tag
+                )
+            } else {
+                format!(
+                    "New version available for {} in {} containers: {} -> {}",
+                    image_name,
+                    updates.len(),
+                    current_tag,
+                    new_tag
+                )
+            }),
             require_approval: true,
             expires_at: Some(Utc::now() + chrono::Duration::hours(24)),
+            scheduled_at: None,
+            priority: None,
+            updates,
+            discussion_thread: Vec::new(),
+            sla_deadline: super::sla::sla_deadline(resource_annotations, chrono::Utc::now()),
+            impact: impact.clone(),
         },
     );
 
+    // Flag rollouts that take down more pods than a PodDisruptionBudget allows
+    let pdb_warning = impact
+        .as_ref()
+        .is_some_and(UpdateImpact::violates_disruption_budget);
+    if pdb_warning {
+        update_request.metadata.labels = Some(
+            [(annotations::PDB_WARNING.to_string(), "true".to_string())]
+                .into_iter()
+                .collect(),
+        );
+    }
+
     // Check if UpdateRequest already exists
     match update_requests.get(&request_name).await {
         Ok(existing) => {
@@ -410,6 +957,13 @@ async fn create_update_request(
                     update_requests
                         .create(&PostParams::default(), &update_request)
                         .await?;
+                    crate::integrations::jira::open_issue_for_update_request(
+                        &client,
+                        resource_annotations,
+                        &update_request,
+                    )
+                    .await;
+                    record_progress(&client, namespace, &request_name, &progress_steps).await;
 
                     info!(
                         "Created UpdateRequest {} for deployment {}/{}",
@@ -423,6 +977,13 @@ async fn create_update_request(
             update_requests
                 .create(&PostParams::default(), &update_request)
                 .await?;
+            crate::integrations::jira::open_issue_for_update_request(
+                &client,
+                resource_annotations,
+                &update_request,
+            )
+            .await;
+            record_progress(&client, namespace, &request_name, &progress_steps).await;
             info!(
                 "Created UpdateRequest {} for deployment {}/{}",
                 request_name, namespace, deployment_name
@@ -443,16 +1004,66 @@ async fn create_update_request(
         container: Some(container_name.to_string()),
         resource_kind: None,
     };
-    notifications::notify_update_request_created(
-        deployment_info,
+    let image_labels =
+        fetch_labels_if_enabled(&client, namespace, new_image, resource_annotations).await;
+    let mut payload = notifications::update_request_created_payload(
+        deployment_info.clone(),
         format!("{:?}", policy),
         true, // require_approval is true in this flow
         request_name.clone(),
+        status_page_url,
+        image_labels,
     );
+    if let Some(impact) = &impact {
+        payload = payload.with_impact(impact.clone());
+    }
+    notifications::notify(payload);
+
+    if let Some(impact) = impact
+        && pdb_warning
+    {
+        warn!(
+            "Updating {}/{} would exceed its PodDisruptionBudget: {}",
+            namespace,
+            deployment_name,
+            impact.summary()
+        );
+        notifications::notify_pdb_warning(deployment_info, request_name, impact);
+    }
 
     Ok(())
 }
 
+/// OCI labels of `image` when the workload opts in with `headwind.sh/fetch-labels`.
+/// Failing to fetch them only costs the notification its changelog link.
+async fn fetch_labels_if_enabled(
+    client: &Client,
+    namespace: &str,
+    image: &str,
+    resource_annotations: Option<&std::collections::BTreeMap<String, String>>,
+) -> Option<std::collections::HashMap<String, String>> {
+    let annotations = resource_annotations?;
+    if annotations
+        .get(annotations::FETCH_LABELS)
+        .map(String::as_str)
+        != Some("true")
+    {
+        return None;
+    }
+
+    let source = annotations
+        .get(annotations::CREDENTIAL_SOURCE)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_default();
+    match crate::polling::labels::labels_for_image(client, namespace, image, source).await {
+        Ok(labels) => Some(labels),
+        Err(e) => {
+            warn!("Failed to fetch labels of {}: {}", image, e);
+            None
+        },
+    }
+}
+
 /// Map UpdatePolicy to UpdatePolicyType for CRD
 fn map_policy_to_crd(policy: &UpdatePolicy) -> UpdatePolicyType {
     match policy {
@@ -464,22 +1075,6 @@ fn map_policy_to_crd(policy: &UpdatePolicy) -> UpdatePolicyType {
     }
 }
 
-/// Simple glob matching (supports * wildcard)
-#[allow(dead_code)]
-fn glob_match(pattern: &str, text: &str) -> bool {
-    // Simple implementation - for production use a proper glob library
-    if pattern == "*" {
-        return true;
-    }
-    if let Some(prefix) = pattern.strip_suffix('*') {
-        return text.starts_with(prefix);
-    }
-    if let Some(suffix) = pattern.strip_prefix('*') {
-        return text.ends_with(suffix);
-    }
-    pattern == text
-}
-
 fn parse_policy_from_annotations(
     annotations: &std::collections::BTreeMap<String, String>,
 ) -> Result<ResourcePolicy, kube::Error> {
@@ -531,6 +1126,47 @@ fn parse_policy_from_annotations(
         policy.polling_interval = polling_interval.parse().ok();
     }
 
+    if let Some(container_names) = annotations.get(annotations::CONTAINER_NAMES) {
+        policy.container_names = parse_container_names(container_names);
+    }
+
+    if let Some(batch_mode) = annotations.get(annotations::BATCH_UPDATES) {
+        policy.batch_mode = batch_mode.parse().unwrap_or(false);
+    }
+
+    if let Some(observers) = annotations.get(annotations::NOTIFY_OBSERVERS) {
+        policy.observers = crate::models::parse_observers(observers);
+    }
+
+    policy.tag_normalization = parse_tag_normalization(annotations, &global_tag_normalization())
+        .map_err(|e| {
+            kube::Error::Api(kube::core::ErrorResponse {
+                status: "Error".to_string(),
+                message: format!("Failed to parse tag normalization: {}", e),
+                reason: "InvalidTagNormalization".to_string(),
+                code: 400,
+            })
+        })?;
+
+    if let Some(level) = annotations.get(annotations::REQUIRE_SLSA_LEVEL) {
+        policy.require_slsa_level = Some(parse_slsa_level(level).map_err(|e| {
+            kube::Error::Api(kube::core::ErrorResponse {
+                status: "Error".to_string(),
+                message: format!("Failed to parse SLSA level: {}", e),
+                reason: "InvalidSlsaLevel".to_string(),
+                code: 400,
+            })
+        })?);
+    }
+
+    if let Some(builder) = annotations.get(annotations::SLSA_BUILDER) {
+        policy.slsa_builder = Some(builder.clone());
+    }
+
+    if let Some(package) = annotations.get(annotations::OPA_POLICY) {
+        policy.opa_policy = Some(package.clone());
+    }
+
     Ok(policy)
 }
 
@@ -554,6 +1190,104 @@ pub async fn update_deployment_image(
     .await
 }
 
+/// The strategic merge patch `update_deployment_image_with_tracking` applies
+pub fn build_deployment_image_patch(container_name: &str, new_image: &str) -> serde_json::Value {
+    json!({
+        "spec": {
+            "template": {
+                "spec": {
+                    "containers": [{
+                        "name": container_name,
+                        "image": new_image
+                    }]
+                }
+            }
+        }
+    })
+}
+
+/// Image of the container named `container_name`, if the deployment has one
+pub(crate) fn container_image<'a>(
+    deployment: &'a Deployment,
+    container_name: &str,
+) -> Option<&'a str> {
+    deployment
+        .spec
+        .as_ref()
+        .and_then(|s| s.template.spec.as_ref())
+        .and_then(|pod| pod.containers.iter().find(|c| c.name == container_name))
+        .and_then(|c| c.image.as_deref())
+}
+
+/// Whether `digest` is a digest such as `sha256:<hex>`
+fn is_valid_digest(digest: &str) -> bool {
+    digest.split_once(':').is_some_and(|(algorithm, hex)| {
+        !algorithm.is_empty()
+            && algorithm.chars().all(|c| c.is_ascii_alphanumeric())
+            && !hex.is_empty()
+            && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Image to restore when `current_image` doesn't run `pinned_digest`, or
+/// `None` if it does
+pub(crate) fn digest_drift(current_image: &str, pinned_digest: &str) -> Option<String> {
+    match current_image.split_once('@') {
+        Some((_, digest)) if digest == pinned_digest => None,
+        _ => Some(
+            ImageReference::pinned(current_image, pinned_digest)
+                .as_str()
+                .to_string(),
+        ),
+    }
+}
+
+/// Patch the container named `container_name` back to `pinned_digest` if its
+/// image has drifted from it. Corrections don't create an UpdateRequest.
+pub async fn verify_and_pin_digest(
+    client: &Client,
+    deployment: &Deployment,
+    container_name: &str,
+    pinned_digest: &str,
+) -> Result<()> {
+    if !is_valid_digest(pinned_digest) {
+        return Err(anyhow::anyhow!(
+            "Invalid {} {}, expected a digest such as sha256:<hex>",
+            annotations::PIN_DIGEST,
+            pinned_digest
+        ));
+    }
+    let namespace = deployment.namespace().unwrap_or_default();
+    let name = deployment.name_any();
+    let current_image = container_image(deployment, container_name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Container {} not found in deployment {}/{}",
+            container_name,
+            namespace,
+            name
+        )
+    })?;
+    let Some(pinned_image) = digest_drift(current_image, pinned_digest) else {
+        return Ok(());
+    };
+
+    warn!(
+        "Deployment {}/{} container {} runs {} instead of pinned digest {}, restoring {}",
+        namespace, name, container_name, current_image, pinned_digest, pinned_image
+    );
+    let deployments: Api<Deployment> = Api::namespaced(client.clone(), &namespace);
+    deployments
+        .patch(
+            &name,
+            &PatchParams::default(),
+            &Patch::Strategic(build_deployment_image_patch(container_name, &pinned_image)),
+        )
+        .await?;
+    DIGEST_PIN_CORRECTIONS_TOTAL.inc();
+
+    Ok(())
+}
+
 /// Update a deployment image with optional rollback tracking metadata
 pub async fn update_deployment_image_with_tracking(
     client: Client,
@@ -566,18 +1300,13 @@ pub async fn update_deployment_image_with_tracking(
 ) -> Result<()> {
     let deployments: Api<Deployment> = Api::namespaced(client.clone(), namespace);
 
-    let patch = json!({
-        "spec": {
-            "template": {
-                "spec": {
-                    "containers": [{
-                        "name": container_name,
-                        "image": new_image
-                    }]
-                }
-            }
-        }
-    });
+    let deployment = deployments.get(name).await?;
+    let mut patch = build_deployment_image_patch(container_name, new_image);
+    super::set_previous_image(
+        &mut patch,
+        container_image(&deployment, container_name),
+        new_image,
+    );
 
     info!(
         "Updating deployment {}/{} container {} to image {}",
@@ -616,6 +1345,9 @@ pub async fn update_deployment_image_with_tracking(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::TagNormalization;
+    use crate::policy::glob_match;
+    use crate::testing::FakeApi;
     use std::collections::BTreeMap;
 
     #[test]
@@ -635,6 +1367,326 @@ mod tests {
         assert_eq!(policy.images, vec!["nginx", "redis"]);
     }
 
+    #[test]
+    fn test_parse_tag_normalization() {
+        let mut annotations = BTreeMap::new();
+        annotations.insert(
+            annotations::NORMALIZE_TAGS.to_string(),
+            "strip-v".to_string(),
+        );
+        let policy = parse_policy_from_annotations(&annotations).unwrap();
+        assert_eq!(policy.tag_normalization, TagNormalization::StripVPrefix);
+
+        annotations.insert(annotations::NORMALIZE_TAGS.to_string(), "bogus".to_string());
+        assert!(parse_policy_from_annotations(&annotations).is_err());
+
+        let policy = parse_policy_from_annotations(&BTreeMap::new()).unwrap();
+        assert_eq!(policy.tag_normalization, TagNormalization::None);
+    }
+
+    #[test]
+    fn test_parse_slsa_annotations() {
+        let mut annotations = BTreeMap::new();
+        annotations.insert(annotations::REQUIRE_SLSA_LEVEL.to_string(), "2".to_string());
+        annotations.insert(
+            annotations::SLSA_BUILDER.to_string(),
+            "https://github.com/actions/runner".to_string(),
+        );
+        let policy = parse_policy_from_annotations(&annotations).unwrap();
+        assert_eq!(policy.require_slsa_level, Some(2));
+        assert_eq!(
+            policy.slsa_builder.as_deref(),
+            Some("https://github.com/actions/runner")
+        );
+
+        // An invalid level must not silently disable the check
+        annotations.insert(annotations::REQUIRE_SLSA_LEVEL.to_string(), "4".to_string());
+        assert!(parse_policy_from_annotations(&annotations).is_err());
+
+        let policy = parse_policy_from_annotations(&BTreeMap::new()).unwrap();
+        assert_eq!(policy.require_slsa_level, None);
+    }
+
+    #[test]
+    fn test_parse_observers() {
+        let mut annotations = BTreeMap::new();
+        annotations.insert(
+            annotations::NOTIFY_OBSERVERS.to_string(),
+            "dba-team@example.com, https://hooks.example.com/dba, http://plain.example.com, dba"
+                .to_string(),
+        );
+        let policy = parse_policy_from_annotations(&annotations).unwrap();
+        assert_eq!(
+            policy.observers,
+            vec!["dba-team@example.com", "https://hooks.example.com/dba"]
+        );
+
+        let policy = parse_policy_from_annotations(&BTreeMap::new()).unwrap();
+        assert!(policy.observers.is_empty());
+    }
+
+    /// Deployment running `app`, `sidecar` and `metrics` containers
+    fn three_container_deployment(annotations: BTreeMap<String, String>) -> Deployment {
+        serde_json::from_value(serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "web", "namespace": "default", "annotations": annotations },
+            "spec": {
+                "selector": { "matchLabels": { "app": "web" } },
+                "template": {
+                    "metadata": { "labels": { "app": "web" } },
+                    "spec": {
+                        "containers": [
+                            { "name": "app", "image": "example/app:1.0.0" },
+                            { "name": "sidecar", "image": "example/proxy:1.0.0" },
+                            { "name": "metrics", "image": "example/exporter:1.0.0" }
+                        ]
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    /// Fake API server answering everything with `deployment`
+    fn deployment_api(deployment: &Deployment) -> FakeApi {
+        let deployment = serde_json::to_value(deployment).unwrap();
+        FakeApi::ok(move |_| deployment.clone())
+    }
+
+    /// Bodies of the PATCHes sent to `api`
+    fn patches(api: &FakeApi) -> Vec<serde_json::Value> {
+        api.bodies(http::Method::PATCH, "")
+    }
+
+    #[test]
+    fn test_parse_container_names() {
+        let mut annotations = BTreeMap::new();
+        annotations.insert(
+            annotations::CONTAINER_NAMES.to_string(),
+            "app, sidecar,".to_string(),
+        );
+        let policy = parse_policy_from_annotations(&annotations).unwrap();
+        assert_eq!(policy.container_names, vec!["app", "sidecar"]);
+
+        let deployment = three_container_deployment(annotations);
+        let containers = &deployment.spec.unwrap().template.spec.unwrap().containers;
+        let targeted: Vec<&str> = containers
+            .iter()
+            .filter(|c| policy.targets_container(&c.name))
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(targeted, vec!["app", "sidecar"]);
+
+        // Without the annotation every container is targeted
+        let policy = parse_policy_from_annotations(&BTreeMap::new()).unwrap();
+        assert!(containers.iter().all(|c| policy.targets_container(&c.name)));
+    }
+
+    #[test]
+    fn test_shared_image_updates() {
+        let mut deployment = three_container_deployment(BTreeMap::new());
+        let policy = parse_policy_from_annotations(&BTreeMap::new()).unwrap();
+
+        // Every container runs a different image
+        assert!(
+            shared_image_updates(
+                &deployment,
+                &policy,
+                "example/app:1.0.0",
+                "example/app:1.1.0"
+            )
+            .is_empty()
+        );
+
+        // The metrics container runs the app image too
+        let pod = deployment
+            .spec
+            .as_mut()
+            .and_then(|s| s.template.spec.as_mut())
+            .unwrap();
+        pod.containers[2].image = Some("example/app:1.0.0".to_string());
+        let updates = shared_image_updates(
+            &deployment,
+            &policy,
+            "example/app:1.0.0",
+            "example/app:1.1.0",
+        );
+        let containers: Vec<&str> = updates.iter().map(|u| u.container_name.as_str()).collect();
+        assert_eq!(containers, vec!["app", "metrics"]);
+        assert!(updates.iter().all(|u| u.new_image == "example/app:1.1.0"));
+
+        // Untargeted containers are left out
+        let mut annotations = BTreeMap::new();
+        annotations.insert(
+            annotations::CONTAINER_NAMES.to_string(),
+            "app,sidecar".to_string(),
+        );
+        let policy = parse_policy_from_annotations(&annotations).unwrap();
+        assert!(
+            shared_image_updates(
+                &deployment,
+                &policy,
+                "example/app:1.0.0",
+                "example/app:1.1.0"
+            )
+            .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handle_image_update_only_updates_targeted_containers() {
+        let annotations = BTreeMap::from([
+            (annotations::POLICY.to_string(), "minor".to_string()),
+            (
+                annotations::REQUIRE_APPROVAL.to_string(),
+                "false".to_string(),
+            ),
+            (
+                annotations::CONTAINER_NAMES.to_string(),
+                "app,sidecar".to_string(),
+            ),
+        ]);
+        let policy = parse_policy_from_annotations(&annotations).unwrap();
+        let deployment = three_container_deployment(annotations);
+        let api = deployment_api(&deployment);
+        let client = api.client();
+
+        for (container, current, new) in [
+            ("app", "example/app:1.0.0", "example/app:1.1.0"),
+            ("sidecar", "example/proxy:1.0.0", "example/proxy:1.1.0"),
+            (
+                "metrics",
+                "example/exporter:1.0.0",
+                "example/exporter:1.1.0",
+            ),
+        ] {
+            handle_image_update(
+                client.clone(),
+                Arc::new(PolicyEngine),
+                &deployment,
+                &policy,
+                container,
+                current,
+                new,
+            )
+            .await
+            .unwrap();
+        }
+
+        let patches = serde_json::to_string(&patches(&api)).unwrap();
+        assert!(patches.contains("example/app:1.1.0"), "{}", patches);
+        assert!(patches.contains("example/proxy:1.1.0"), "{}", patches);
+        assert!(!patches.contains("example/exporter:1.1.0"), "{}", patches);
+    }
+
+    #[test]
+    fn test_digest_drift() {
+        let pinned = format!("sha256:{}", "a".repeat(64));
+        let other = format!("sha256:{}", "b".repeat(64));
+
+        assert_eq!(
+            digest_drift(&format!("nginx:1.26.0@{}", pinned), &pinned),
+            None
+        );
+        // A different digest is replaced, keeping the tag
+        assert_eq!(
+            digest_drift(&format!("nginx:1.26.0@{}", other), &pinned),
+            Some(format!("nginx:1.26.0@{}", pinned))
+        );
+        // So is a tag without a digest, e.g. after a manual `kubectl set image`
+        assert_eq!(
+            digest_drift("nginx:1.27.0", &pinned),
+            Some(format!("nginx:1.27.0@{}", pinned))
+        );
+
+        assert!(is_valid_digest(&pinned));
+        assert!(is_valid_digest("sha256:abc123"));
+        assert!(!is_valid_digest("abc123"));
+        assert!(!is_valid_digest("sha256:"));
+        assert!(!is_valid_digest("sha256:xyz"));
+        assert!(!is_valid_digest(":abc123"));
+    }
+
+    #[tokio::test]
+    async fn test_verify_and_pin_digest_corrects_drift() {
+        let pinned = format!("sha256:{}", "a".repeat(64));
+        let mut deployment = three_container_deployment(BTreeMap::from([(
+            annotations::PIN_DIGEST.to_string(),
+            pinned.clone(),
+        )]));
+        let api = deployment_api(&deployment);
+        let client = api.client();
+
+        // Drifted: restored to the pinned digest
+        verify_and_pin_digest(&client, &deployment, "app", &pinned)
+            .await
+            .unwrap();
+        assert_eq!(patches(&api).len(), 1);
+        assert_eq!(
+            patches(&api)[0]["spec"]["template"]["spec"]["containers"],
+            json!([{ "name": "app", "image": format!("example/app:1.0.0@{}", pinned) }])
+        );
+
+        // Running the pinned digest: nothing to do
+        deployment
+            .spec
+            .as_mut()
+            .and_then(|s| s.template.spec.as_mut())
+            .unwrap()
+            .containers[0]
+            .image = Some(format!("example/app:1.0.0@{}", pinned));
+        verify_and_pin_digest(&client, &deployment, "app", &pinned)
+            .await
+            .unwrap();
+        assert_eq!(patches(&api).len(), 1);
+
+        // Invalid pins and unknown containers are never patched
+        assert!(
+            verify_and_pin_digest(&client, &deployment, "app", "latest")
+                .await
+                .is_err()
+        );
+        assert!(
+            verify_and_pin_digest(&client, &deployment, "worker", &pinned)
+                .await
+                .is_err()
+        );
+        assert_eq!(patches(&api).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_handle_image_update_skips_pinned_containers() {
+        let annotations = BTreeMap::from([
+            (annotations::POLICY.to_string(), "minor".to_string()),
+            (
+                annotations::REQUIRE_APPROVAL.to_string(),
+                "false".to_string(),
+            ),
+            (
+                annotations::PIN_DIGEST.to_string(),
+                "sha256:abc123".to_string(),
+            ),
+        ]);
+        let policy = parse_policy_from_annotations(&annotations).unwrap();
+        let deployment = three_container_deployment(annotations);
+        let api = deployment_api(&deployment);
+        let client = api.client();
+
+        handle_image_update(
+            client,
+            Arc::new(PolicyEngine),
+            &deployment,
+            &policy,
+            "app",
+            "example/app:1.0.0",
+            "example/app:1.1.0",
+        )
+        .await
+        .unwrap();
+        assert!(patches(&api).is_empty());
+    }
+
     #[test]
     fn test_parse_policy_defaults() {
         let annotations = BTreeMap::new();
@@ -671,6 +1723,33 @@ mod tests {
         let (name, tag) = parse_image("registry.example.com:5000/image:v2.0").unwrap();
         assert_eq!(name, "registry.example.com:5000/image");
         assert_eq!(tag, "v2.0");
+
+        // Image pinned to a digest keeps its tag
+        let (name, tag) = parse_image("registry.example.com:5000/image:v2.0@sha256:abc").unwrap();
+        assert_eq!(name, "registry.example.com:5000/image");
+        assert_eq!(tag, "v2.0");
+    }
+
+    #[test]
+    fn test_image_patch_with_pinned_digest() {
+        let digest = format!("sha256:{}", "a".repeat(64));
+        let target = ImageReference::pinned("nginx:1.26.0", &digest);
+
+        let patch = build_deployment_image_patch("web", target.as_str());
+        let image = patch["spec"]["template"]["spec"]["containers"][0]["image"]
+            .as_str()
+            .unwrap();
+        assert_eq!(image, format!("nginx:1.26.0@{}", digest));
+        assert!(image.contains("@sha256:"));
+
+        let patch = build_deployment_image_patch(
+            "web",
+            ImageReference::Tag("nginx:1.26.0".to_string()).as_str(),
+        );
+        assert_eq!(
+            patch["spec"]["template"]["spec"]["containers"][0]["image"],
+            "nginx:1.26.0"
+        );
     }
 
     #[test]
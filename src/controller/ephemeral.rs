@@ -0,0 +1,238 @@
+//! Reporting of ephemeral containers running images Headwind manages.
+//!
+//! `kubectl debug` adds ephemeral containers to a running pod, often with
+//! the same image as the container being debugged. They are never updated:
+//! each one found is logged and recorded as a `Normal` event on its pod,
+//! comparing the image it runs with the one the Deployment tracks.
+
+use crate::metrics::EPHEMERAL_CONTAINER_REPORTS_TOTAL;
+use crate::polling::digest::strip_digest;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, ListParams};
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use kube::{Client, Resource, ResourceExt};
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+/// Reason of the event recorded for each ephemeral container found
+pub const EPHEMERAL_CONTAINER_REASON: &str = "EphemeralContainerImage";
+
+lazy_static! {
+    /// Ephemeral containers already reported, as (deployment, pod uid,
+    /// container name), so the minutely reconcile doesn't repeat the event
+    static ref REPORTED: Mutex<HashSet<(String, String, String)>> = Mutex::new(HashSet::new());
+}
+
+/// An ephemeral container running an image that Headwind tracks
+#[derive(Debug, Clone, PartialEq)]
+pub struct EphemeralContainerReport {
+    pub pod: String,
+    pub container: String,
+    /// Image the ephemeral container runs
+    pub image: String,
+    /// Image of the same repository in the pod template
+    pub tracked_image: String,
+}
+
+/// Repository of `image`, without tag or digest
+fn repository(image: &str) -> &str {
+    let image = strip_digest(image);
+    match image.rsplit_once(':') {
+        // A ':' followed by a '/' is a registry port, not a tag
+        Some((name, tag)) if !tag.contains('/') => name,
+        _ => image,
+    }
+}
+
+/// Ephemeral containers of `pod` whose image is from the same repository as
+/// one of `tracked_images`
+pub fn scan_ephemeral_containers(
+    pod: &Pod,
+    tracked_images: &HashSet<String>,
+) -> Vec<EphemeralContainerReport> {
+    let Some(ephemeral) = pod
+        .spec
+        .as_ref()
+        .and_then(|s| s.ephemeral_containers.as_ref())
+    else {
+        return Vec::new();
+    };
+
+    ephemeral
+        .iter()
+        .filter_map(|container| {
+            let image = container.image.as_ref()?;
+            // Sorted so the report is the same whatever the set's order
+            let mut matching: Vec<&String> = tracked_images
+                .iter()
+                .filter(|tracked| repository(tracked) == repository(image))
+                .collect();
+            matching.sort();
+            let tracked_image = matching.first()?;
+            Some(EphemeralContainerReport {
+                pod: pod.name_any(),
+                container: container.name.clone(),
+                image: image.clone(),
+                tracked_image: tracked_image.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Log and record an event for every new ephemeral container in the pods of
+/// `deployment` that runs one of `tracked_images`. Nothing is patched.
+pub async fn report_ephemeral_containers(
+    client: &Client,
+    deployment: &Deployment,
+    tracked_images: &HashSet<String>,
+) {
+    let namespace = deployment.namespace().unwrap_or_default();
+    let name = deployment.name_any();
+    let Some(selector) = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.selector.match_labels.as_ref())
+        .map(|labels| {
+            labels
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    else {
+        return;
+    };
+
+    let pods: Api<Pod> = Api::namespaced(client.clone(), &namespace);
+    let pod_list = match pods.list(&ListParams::default().labels(&selector)).await {
+        Ok(list) => list,
+        Err(e) => {
+            warn!(
+                "Failed to list pods of deployment {}/{} for ephemeral containers: {}",
+                namespace, name, e
+            );
+            return;
+        },
+    };
+
+    // Forget pods of this Deployment that are gone
+    let key = format!("{}/{}", namespace, name);
+    let uids: HashSet<String> = pod_list.items.iter().filter_map(|p| p.uid()).collect();
+    REPORTED
+        .lock()
+        .unwrap()
+        .retain(|(deployment, uid, _)| *deployment != key || uids.contains(uid));
+
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    for pod in &pod_list.items {
+        let uid = pod.uid().unwrap_or_default();
+        for report in scan_ephemeral_containers(pod, tracked_images) {
+            if !REPORTED.lock().unwrap().insert((
+                key.clone(),
+                uid.clone(),
+                report.container.clone(),
+            )) {
+                continue;
+            }
+
+            debug!(
+                "Ephemeral container {} in pod {}/{} runs {}, deployment {} tracks {}",
+                report.container, namespace, report.pod, report.image, name, report.tracked_image
+            );
+            EPHEMERAL_CONTAINER_REPORTS_TOTAL.inc();
+
+            let event = Event {
+                type_: EventType::Normal,
+                reason: EPHEMERAL_CONTAINER_REASON.to_string(),
+                note: Some(format!(
+                    "Ephemeral container {} runs {}; Headwind tracks {} (not updated)",
+                    report.container, report.image, report.tracked_image
+                )),
+                action: "ReportEphemeralContainer".to_string(),
+                secondary: Some(deployment.object_ref(&())),
+            };
+            if let Err(e) = recorder.publish(&event, &pod.object_ref(&())).await {
+                warn!(
+                    "Failed to publish {} event for pod {}/{}: {}",
+                    EPHEMERAL_CONTAINER_REASON, namespace, report.pod, e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pod(ephemeral: serde_json::Value) -> Pod {
+        serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": { "name": "web-7d9f-abcde", "namespace": "default" },
+            "spec": {
+                "containers": [{ "name": "app", "image": "example/app:1.2.0" }],
+                "ephemeralContainers": ephemeral
+            }
+        }))
+        .unwrap()
+    }
+
+    fn tracked(images: &[&str]) -> HashSet<String> {
+        images.iter().map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn test_reports_ephemeral_container_of_tracked_repository() {
+        let pod = pod(json!([
+            { "name": "debugger-x1", "image": "example/app:1.0.0" },
+            { "name": "debugger-x2", "image": "busybox:1.36" }
+        ]));
+        let reports = scan_ephemeral_containers(&pod, &tracked(&["example/app:1.2.0"]));
+        assert_eq!(
+            reports,
+            vec![EphemeralContainerReport {
+                pod: "web-7d9f-abcde".to_string(),
+                container: "debugger-x1".to_string(),
+                image: "example/app:1.0.0".to_string(),
+                tracked_image: "example/app:1.2.0".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_matches_repository_ignoring_digest_and_registry_port() {
+        let pod = pod(json!([
+            { "name": "pinned", "image": "localhost:5000/app:1.0@sha256:abc" },
+            { "name": "untagged", "image": "localhost:5000/app" }
+        ]));
+        let reports = scan_ephemeral_containers(&pod, &tracked(&["localhost:5000/app:2.0"]));
+        assert_eq!(reports.len(), 2);
+        assert!(
+            reports
+                .iter()
+                .all(|r| r.tracked_image == "localhost:5000/app:2.0")
+        );
+    }
+
+    #[test]
+    fn test_no_ephemeral_containers() {
+        let pod: Pod = serde_json::from_value(json!({
+            "metadata": { "name": "web" },
+            "spec": { "containers": [{ "name": "app", "image": "example/app:1.2.0" }] }
+        }))
+        .unwrap();
+        assert!(scan_ephemeral_containers(&pod, &tracked(&["example/app:1.2.0"])).is_empty());
+        assert!(
+            scan_ephemeral_containers(
+                &self::pod(json!([{ "name": "d", "image": "example/app:1.0.0" }])),
+                &HashSet::new()
+            )
+            .is_empty()
+        );
+    }
+}
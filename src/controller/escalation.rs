@@ -0,0 +1,372 @@
+//! Priority escalation of UpdateRequests left waiting for approval.
+//!
+//! Every 15 minutes, each Pending UpdateRequest is given a priority of
+//! `HEADWIND_ESCALATION_PRIORITY_STEP` (default 100) for every
+//! `headwind.sh/escalation-threshold-hours` (default 48) it has been pending,
+//! up to `HEADWIND_ESCALATION_MAX_PRIORITY` (default 1000). The priority is
+//! computed from the age alone, so running twice changes nothing, and a
+//! priority already higher, e.g. set by hand, is never lowered. Each raise
+//! sends an `update_escalated` notification.
+
+use crate::metrics::ESCALATION_EVENTS_TOTAL;
+use crate::models::annotations;
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use crate::notifications::{self, DeploymentInfo, NotificationEvent, NotificationPayload};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use kube::api::{ListParams, Patch, PatchParams};
+use kube::{Api, Client, ResourceExt};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// How often Pending UpdateRequests are checked
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscalationConfig {
+    /// Pending time after which, and after every multiple of which, the
+    /// priority is raised, unless the UpdateRequest overrides it
+    pub threshold: chrono::Duration,
+    /// Priority added per threshold
+    pub step: u32,
+    /// Highest priority escalation raises to
+    pub max_priority: u32,
+}
+
+impl Default for EscalationConfig {
+    fn default() -> Self {
+        Self {
+            threshold: chrono::Duration::hours(48),
+            step: 100,
+            max_priority: 1000,
+        }
+    }
+}
+
+impl EscalationConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let env = |name| std::env::var(name).ok();
+        Self {
+            threshold: env("HEADWIND_ESCALATION_THRESHOLD_HOURS")
+                .and_then(|v| parse_threshold_hours(&v))
+                .unwrap_or(default.threshold),
+            step: env("HEADWIND_ESCALATION_PRIORITY_STEP")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.step),
+            max_priority: env("HEADWIND_ESCALATION_MAX_PRIORITY")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default.max_priority),
+        }
+    }
+
+    /// Threshold of `update_request`: its `headwind.sh/escalation-threshold-hours`
+    /// or the configured default
+    pub fn threshold_for(&self, update_request: &UpdateRequest) -> chrono::Duration {
+        update_request
+            .annotations()
+            .get(annotations::ESCALATION_THRESHOLD_HOURS)
+            .and_then(|v| parse_threshold_hours(v))
+            .unwrap_or(self.threshold)
+    }
+}
+
+/// A positive whole number of hours
+fn parse_threshold_hours(value: &str) -> Option<chrono::Duration> {
+    value
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|hours| *hours > 0)
+        .map(chrono::Duration::hours)
+}
+
+/// Priority of an UpdateRequest pending for `pending_for`: `step` for every
+/// full `threshold`, capped at `max_priority`
+pub fn escalated_priority(
+    pending_for: chrono::Duration,
+    threshold: chrono::Duration,
+    step: u32,
+    max_priority: u32,
+) -> u32 {
+    let levels = pending_for.num_seconds().max(0) / threshold.num_seconds().max(1);
+    u32::try_from(levels)
+        .unwrap_or(u32::MAX)
+        .saturating_mul(step)
+        .min(max_priority)
+}
+
+/// The priority `update_request` should be raised to at `now`, or `None` if
+/// it is not Pending or its priority is already at least that high
+pub fn escalation(
+    update_request: &UpdateRequest,
+    now: DateTime<Utc>,
+    config: &EscalationConfig,
+) -> Option<u32> {
+    let pending = update_request
+        .status
+        .as_ref()
+        .is_none_or(|status| status.phase == UpdatePhase::Pending);
+    if !pending {
+        return None;
+    }
+    let created = update_request.metadata.creation_timestamp.as_ref()?.0;
+
+    let priority = escalated_priority(
+        now - created,
+        config.threshold_for(update_request),
+        config.step,
+        config.max_priority,
+    );
+    (priority > update_request.spec.priority.unwrap_or(0)).then_some(priority)
+}
+
+pub struct EscalationController {
+    client: Client,
+    config: EscalationConfig,
+}
+
+impl EscalationController {
+    pub async fn new() -> Result<Self> {
+        let client = Client::try_default().await?;
+        Ok(Self {
+            client,
+            config: EscalationConfig::from_env(),
+        })
+    }
+
+    pub async fn run(self) {
+        info!(
+            "UpdateRequest escalation starting: +{} priority every {}h pending, up to {}",
+            self.config.step,
+            self.config.threshold.num_hours(),
+            self.config.max_priority
+        );
+
+        loop {
+            if let Err(e) = self.escalate(Utc::now()).await {
+                error!("UpdateRequest escalation failed: {:#}", e);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Raise the priority of the UpdateRequests due for escalation at `now`
+    async fn escalate(&self, now: DateTime<Utc>) -> Result<()> {
+        let api: Api<UpdateRequest> = super::watched_api(self.client.clone());
+        let update_requests = api
+            .list(&ListParams::default())
+            .await
+            .context("Failed to list UpdateRequests")?
+            .items;
+
+        for update_request in &update_requests {
+            let Some(priority) = escalation(update_request, now, &self.config) else {
+                continue;
+            };
+            let namespace = update_request.namespace().unwrap_or_default();
+            let name = update_request.name_any();
+            let api: Api<UpdateRequest> = Api::namespaced(self.client.clone(), &namespace);
+            match api
+                .patch(
+                    &name,
+                    &PatchParams::default(),
+                    &Patch::Merge(json!({ "spec": { "priority": priority } })),
+                )
+                .await
+            {
+                Ok(_) => {
+                    info!(
+                        "Escalated UpdateRequest {}/{} to priority {}",
+                        namespace, name, priority
+                    );
+                    ESCALATION_EVENTS_TOTAL.inc();
+                    notify_escalated(update_request, priority, now);
+                },
+                Err(kube::Error::Api(e)) if e.code == 404 => {},
+                Err(e) => warn!(
+                    "Failed to escalate UpdateRequest {}/{}: {}",
+                    namespace, name, e
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Notification that `update_request` was raised to `priority`
+pub fn escalated_payload(
+    update_request: &UpdateRequest,
+    priority: u32,
+    now: DateTime<Utc>,
+) -> NotificationPayload {
+    let spec = &update_request.spec;
+    let deployment = DeploymentInfo {
+        name: spec.target_ref.name.clone(),
+        namespace: spec.target_ref.namespace.clone(),
+        current_image: spec.current_image.clone(),
+        new_image: spec.new_image.clone(),
+        container: spec.container_name.clone(),
+        resource_kind: Some(spec.target_ref.kind.clone()),
+    };
+    let pending_hours = update_request
+        .metadata
+        .creation_timestamp
+        .as_ref()
+        .map(|created| (now - created.0).num_hours())
+        .unwrap_or_default();
+
+    NotificationPayload::new(NotificationEvent::UpdateEscalated, deployment)
+        .with_policy(format!("{:?}", spec.policy).to_lowercase())
+        .with_requires_approval(true)
+        .with_update_request(update_request.name_any())
+        .with_metadata(HashMap::from([
+            ("priority".to_string(), priority.to_string()),
+            ("pendingHours".to_string(), pending_hours.to_string()),
+        ]))
+}
+
+fn notify_escalated(update_request: &UpdateRequest, priority: u32, now: DateTime<Utc>) {
+    notifications::notify(escalated_payload(update_request, priority, now));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+    fn update_request(
+        phase: UpdatePhase,
+        created: DateTime<Utc>,
+        priority: Option<u32>,
+    ) -> UpdateRequest {
+        let mut update_request = testing::update_request_in_phase("default", "web-update", phase);
+        update_request.metadata.creation_timestamp = Some(Time(created));
+        update_request.spec.priority = priority;
+        update_request
+    }
+
+    fn hours(h: i64) -> chrono::Duration {
+        chrono::Duration::hours(h)
+    }
+
+    #[test]
+    fn test_escalated_priority_boundaries() {
+        let threshold = hours(48);
+        let priority = |pending_for| escalated_priority(pending_for, threshold, 100, 1000);
+
+        assert_eq!(priority(hours(0)), 0);
+        assert_eq!(priority(hours(48) - chrono::Duration::seconds(1)), 0);
+        assert_eq!(priority(hours(48)), 100);
+        assert_eq!(priority(hours(95)), 100);
+        assert_eq!(priority(hours(96)), 200);
+        // Capped at the maximum
+        assert_eq!(priority(hours(48 * 10)), 1000);
+        assert_eq!(priority(hours(48 * 100)), 1000);
+        // Clock skew
+        assert_eq!(priority(hours(-1)), 0);
+    }
+
+    #[test]
+    fn test_escalation_of_pending_requests() {
+        let now = Utc::now();
+        let config = EscalationConfig::default();
+
+        let fresh = update_request(UpdatePhase::Pending, now - hours(47), None);
+        assert_eq!(escalation(&fresh, now, &config), None);
+
+        let due = update_request(UpdatePhase::Pending, now - hours(48), None);
+        assert_eq!(escalation(&due, now, &config), Some(100));
+
+        // Escalated again after every further threshold
+        let mut escalated = update_request(UpdatePhase::Pending, now - hours(97), Some(100));
+        assert_eq!(escalation(&escalated, now, &config), Some(200));
+
+        // Idempotent: nothing to do once the priority is applied
+        escalated.spec.priority = Some(200);
+        assert_eq!(escalation(&escalated, now, &config), None);
+
+        // Without a status the request is still Pending
+        let mut new = update_request(UpdatePhase::Pending, now - hours(48), None);
+        new.status = None;
+        assert_eq!(escalation(&new, now, &config), Some(100));
+
+        for phase in [
+            UpdatePhase::Approved,
+            UpdatePhase::Completed,
+            UpdatePhase::Rejected,
+        ] {
+            let finished = update_request(phase, now - hours(500), None);
+            assert_eq!(escalation(&finished, now, &config), None);
+        }
+    }
+
+    #[test]
+    fn test_manual_priority_is_never_lowered() {
+        let now = Utc::now();
+        let config = EscalationConfig::default();
+
+        let manual = update_request(UpdatePhase::Pending, now - hours(100), Some(500));
+        assert_eq!(escalation(&manual, now, &config), None);
+
+        // Above the cap stays above the cap
+        let manual = update_request(UpdatePhase::Pending, now - hours(48 * 20), Some(5000));
+        assert_eq!(escalation(&manual, now, &config), None);
+
+        // Overtaken once escalation passes it
+        let manual = update_request(UpdatePhase::Pending, now - hours(48 * 6), Some(500));
+        assert_eq!(escalation(&manual, now, &config), Some(600));
+    }
+
+    #[test]
+    fn test_threshold_annotation() {
+        let now = Utc::now();
+        let config = EscalationConfig::default();
+        let mut update_request = update_request(UpdatePhase::Pending, now - hours(12), None);
+        assert_eq!(escalation(&update_request, now, &config), None);
+
+        update_request.metadata.annotations = Some(
+            [(
+                annotations::ESCALATION_THRESHOLD_HOURS.to_string(),
+                "4".to_string(),
+            )]
+            .into(),
+        );
+        assert_eq!(config.threshold_for(&update_request), hours(4));
+        assert_eq!(escalation(&update_request, now, &config), Some(300));
+
+        // Invalid values fall back to the default
+        for value in ["0", "-4", "soon"] {
+            update_request.metadata.annotations = Some(
+                [(
+                    annotations::ESCALATION_THRESHOLD_HOURS.to_string(),
+                    value.to_string(),
+                )]
+                .into(),
+            );
+            assert_eq!(config.threshold_for(&update_request), hours(48));
+        }
+    }
+
+    #[test]
+    fn test_escalated_payload() {
+        let now = Utc::now();
+        let update_request = update_request(UpdatePhase::Pending, now - hours(50), Some(0));
+        let payload = escalated_payload(&update_request, 100, now);
+
+        assert_eq!(
+            payload.title(),
+            "[ESCALATED] Update still pending approval: Deployment default/web"
+        );
+        assert!(
+            payload
+                .description()
+                .ends_with("Pending for 50h, priority raised to 100")
+        );
+        assert_eq!(payload.update_request_name.as_deref(), Some("web-update"));
+    }
+}
@@ -0,0 +1,446 @@
+//! Expiry of UpdateRequests left pending.
+//!
+//! With `HEADWIND_FEATURE_UPDATE_REQUEST_EXPIRY=true`, every 5 minutes the
+//! [`ExpiryController`] marks Pending UpdateRequests past their
+//! `spec.expiresAt` as `Expired`, keeping them for the audit trail until
+//! cleanup removes them. Requests scheduled for approval are left to their
+//! schedule, and none expires before its review SLA deadline. A request
+//! annotated `headwind.sh/auto-extend-ttl: "true"` is extended by 24 hours
+//! instead, once.
+//!
+//! Extensions, automatic or through
+//! `POST /api/v1/update-requests/{namespace}/{name}/extend-ttl`, never let a
+//! request live longer than `HEADWIND_MAX_UPDATE_REQUEST_LIFETIME_HOURS`
+//! (default 168) after its creation, and are recorded in
+//! `status.ttlExtensions`.
+
+use crate::metrics::{EXPIRED_UPDATE_REQUESTS_TOTAL, TTL_EXTENSIONS_TOTAL};
+use crate::models::annotations;
+use crate::models::crd::{TtlExtension, UpdatePhase, UpdateRequest};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use kube::api::{ListParams, Patch, PatchParams};
+use kube::{Api, Client, ResourceExt};
+use serde_json::json;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// How often Pending UpdateRequests are checked
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Hours an expired request annotated `headwind.sh/auto-extend-ttl` is
+/// extended by
+pub const AUTO_EXTEND_HOURS: i64 = 24;
+
+/// Longest an UpdateRequest may live after its creation
+/// (`HEADWIND_MAX_UPDATE_REQUEST_LIFETIME_HOURS`, default 168)
+pub fn max_lifetime() -> chrono::Duration {
+    let hours = std::env::var("HEADWIND_MAX_UPDATE_REQUEST_LIFETIME_HOURS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(168);
+    chrono::Duration::hours(hours)
+}
+
+/// When `update_request` expires: its `spec.expiresAt`, but never before its
+/// `spec.slaDeadline`. None if it has no expiry or is scheduled for approval,
+/// which the scheduled approval controller takes care of.
+pub fn expiry_of(update_request: &UpdateRequest) -> Option<DateTime<Utc>> {
+    let spec = &update_request.spec;
+    if spec.scheduled_at.is_some() {
+        return None;
+    }
+    let expires_at = spec.expires_at?;
+    Some(
+        spec.sla_deadline
+            .map_or(expires_at, |deadline| expires_at.max(deadline)),
+    )
+}
+
+/// Whether `update_request` is still Pending at or after its expiry at `now`
+pub fn is_expired(update_request: &UpdateRequest, now: DateTime<Utc>) -> bool {
+    let pending = update_request
+        .status
+        .as_ref()
+        .is_none_or(|status| status.phase == UpdatePhase::Pending);
+    pending && expiry_of(update_request).is_some_and(|expires_at| now >= expires_at)
+}
+
+/// Expiry of `update_request` extended to `hours` after `now`, or why it
+/// can't be extended that far
+pub fn extended_expiry(
+    update_request: &UpdateRequest,
+    hours: i64,
+    now: DateTime<Utc>,
+    max_lifetime: chrono::Duration,
+) -> Result<DateTime<Utc>, String> {
+    if hours <= 0 {
+        return Err(format!("extend_by_hours must be positive, got {}", hours));
+    }
+    let expires_at = now + chrono::Duration::hours(hours);
+    if let Some(created) = &update_request.metadata.creation_timestamp
+        && expires_at - created.0 > max_lifetime
+    {
+        return Err(format!(
+            "extending by {}h would keep the UpdateRequest for {}h after its creation, more than the maximum of {}h",
+            hours,
+            (expires_at - created.0).num_hours(),
+            max_lifetime.num_hours()
+        ));
+    }
+    Ok(expires_at)
+}
+
+/// Expiry of an automatic extension of `update_request` at `now`, None if it
+/// isn't annotated `headwind.sh/auto-extend-ttl: "true"`, was already
+/// extended automatically or would outlive the maximum lifetime
+pub fn auto_extension(
+    update_request: &UpdateRequest,
+    now: DateTime<Utc>,
+    max_lifetime: chrono::Duration,
+) -> Option<DateTime<Utc>> {
+    let enabled = update_request
+        .annotations()
+        .get(annotations::AUTO_EXTEND_TTL)
+        .is_some_and(|v| v == "true");
+    let extended = update_request
+        .status
+        .as_ref()
+        .is_some_and(|status| status.ttl_extensions.iter().any(|e| e.automatic));
+    if !enabled || extended {
+        return None;
+    }
+    extended_expiry(update_request, AUTO_EXTEND_HOURS, now, max_lifetime).ok()
+}
+
+/// Set the expiry of `update_request` to `expires_at`, `hours` after `now`,
+/// and record the extension in its status
+pub async fn extend(
+    client: &Client,
+    update_request: &UpdateRequest,
+    hours: i64,
+    expires_at: DateTime<Utc>,
+    automatic: bool,
+    now: DateTime<Utc>,
+) -> Result<UpdateRequest, kube::Error> {
+    let namespace = update_request.namespace().unwrap_or_default();
+    let name = update_request.name_any();
+    let api: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
+
+    let mut extensions = update_request
+        .status
+        .as_ref()
+        .map(|s| s.ttl_extensions.clone())
+        .unwrap_or_default();
+    extensions.push(TtlExtension {
+        extended_at: now,
+        extend_by_hours: hours,
+        previous_expires_at: update_request.spec.expires_at,
+        expires_at,
+        automatic,
+    });
+
+    api.patch(
+        &name,
+        &PatchParams::default(),
+        &Patch::Merge(json!({ "spec": { "expiresAt": expires_at } })),
+    )
+    .await?;
+    let updated = api
+        .patch_status(
+            &name,
+            &PatchParams::default(),
+            &Patch::Merge(json!({ "status": { "ttlExtensions": extensions } })),
+        )
+        .await?;
+    TTL_EXTENSIONS_TOTAL.inc();
+    info!(
+        "Extended UpdateRequest {}/{} by {}h to {}{}",
+        namespace,
+        name,
+        hours,
+        expires_at.to_rfc3339(),
+        if automatic { " (automatic)" } else { "" }
+    );
+    Ok(updated)
+}
+
+pub struct ExpiryController {
+    client: Client,
+}
+
+impl ExpiryController {
+    pub async fn new() -> Result<Self> {
+        Ok(Self {
+            client: Client::try_default().await?,
+        })
+    }
+
+    pub async fn run(self) {
+        info!(
+            "UpdateRequest expiry controller starting, checking every {}m",
+            CHECK_INTERVAL.as_secs() / 60
+        );
+
+        loop {
+            if let Err(e) = self.check(Utc::now()).await {
+                error!("UpdateRequest expiry check failed: {:#}", e);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Extend or mark `Expired` the UpdateRequests expired at `now`
+    async fn check(&self, now: DateTime<Utc>) -> Result<()> {
+        let api: Api<UpdateRequest> = super::watched_api(self.client.clone());
+        let update_requests = api
+            .list(&ListParams::default())
+            .await
+            .context("Failed to list UpdateRequests")?
+            .items;
+        let max_lifetime = max_lifetime();
+
+        for update_request in update_requests.iter().filter(|ur| is_expired(ur, now)) {
+            let namespace = update_request.namespace().unwrap_or_default();
+            let name = update_request.name_any();
+
+            let result = match auto_extension(update_request, now, max_lifetime) {
+                Some(expires_at) => extend(
+                    &self.client,
+                    update_request,
+                    AUTO_EXTEND_HOURS,
+                    expires_at,
+                    true,
+                    now,
+                )
+                .await
+                .map(|_| ()),
+                None => {
+                    let namespaced: Api<UpdateRequest> =
+                        Api::namespaced(self.client.clone(), &namespace);
+                    let status = json!({
+                        "status": {
+                            "phase": UpdatePhase::Expired,
+                            "message": format!(
+                                "Expired at {} without approval",
+                                update_request
+                                    .spec
+                                    .expires_at
+                                    .map(|t| t.to_rfc3339())
+                                    .unwrap_or_default()
+                            ),
+                            "lastUpdated": now,
+                        }
+                    });
+                    namespaced
+                        .patch_status(&name, &PatchParams::default(), &Patch::Merge(&status))
+                        .await
+                        .map(|_| {
+                            EXPIRED_UPDATE_REQUESTS_TOTAL.inc();
+                            info!("UpdateRequest {}/{} expired", namespace, name);
+                        })
+                },
+            };
+            match result {
+                Ok(()) => {},
+                // Deleted meanwhile
+                Err(kube::Error::Api(e)) if e.code == 404 => {},
+                Err(e) => warn!(
+                    "Failed to expire UpdateRequest {}/{}: {}",
+                    namespace, name, e
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing;
+    use chrono::TimeZone;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+    use std::collections::BTreeMap;
+
+    fn created() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 8, 0, 0).unwrap()
+    }
+
+    fn update_request(phase: UpdatePhase, auto_extend: bool) -> UpdateRequest {
+        let mut update_request = testing::update_request_in_phase("payments", "web-update", phase);
+        update_request.spec.current_image = "web:1.4.0".to_string();
+        update_request.spec.new_image = "web:1.5.0".to_string();
+        update_request.spec.expires_at = Some(created() + chrono::Duration::hours(24));
+        update_request.metadata.creation_timestamp = Some(Time(created()));
+        if auto_extend {
+            update_request.metadata.annotations = Some(BTreeMap::from([(
+                annotations::AUTO_EXTEND_TTL.to_string(),
+                "true".to_string(),
+            )]));
+        }
+        update_request
+    }
+
+    #[test]
+    fn test_expired_at_expiry_while_pending() {
+        let ur = update_request(UpdatePhase::Pending, false);
+        let expires_at = created() + chrono::Duration::hours(24);
+        assert!(!is_expired(&ur, expires_at - chrono::Duration::seconds(1)));
+        assert!(is_expired(&ur, expires_at));
+
+        for phase in [UpdatePhase::Approved, UpdatePhase::Completed] {
+            assert!(!is_expired(&update_request(phase, false), expires_at));
+        }
+
+        let mut no_expiry = update_request(UpdatePhase::Pending, false);
+        no_expiry.spec.expires_at = None;
+        assert!(!is_expired(&no_expiry, expires_at));
+    }
+
+    #[test]
+    fn test_extension_up_to_maximum_lifetime() {
+        let ur = update_request(UpdatePhase::Pending, false);
+        let max = chrono::Duration::hours(168);
+        let now = created() + chrono::Duration::hours(20);
+
+        assert_eq!(
+            extended_expiry(&ur, 24, now, max),
+            Ok(now + chrono::Duration::hours(24))
+        );
+        // Exactly the maximum lifetime is allowed
+        assert_eq!(
+            extended_expiry(&ur, 148, now, max),
+            Ok(created() + chrono::Duration::hours(168))
+        );
+        let err = extended_expiry(&ur, 149, now, max).unwrap_err();
+        assert!(err.contains("169h"), "{}", err);
+        assert!(err.contains("maximum of 168h"), "{}", err);
+
+        // The cap counts from creation, not from the current expiry
+        let late = created() + chrono::Duration::hours(160);
+        assert!(extended_expiry(&ur, 8, late, max).is_ok());
+        assert!(extended_expiry(&ur, 9, late, max).is_err());
+    }
+
+    #[test]
+    fn test_configured_maximum_lifetime() {
+        let ur = update_request(UpdatePhase::Pending, false);
+        let max = chrono::Duration::hours(48);
+        assert!(extended_expiry(&ur, 24, created() + chrono::Duration::hours(24), max).is_ok());
+        assert!(extended_expiry(&ur, 25, created() + chrono::Duration::hours(24), max).is_err());
+    }
+
+    #[test]
+    fn test_extension_must_be_positive() {
+        let ur = update_request(UpdatePhase::Pending, false);
+        for hours in [0, -24] {
+            let err =
+                extended_expiry(&ur, hours, created(), chrono::Duration::hours(168)).unwrap_err();
+            assert!(err.contains("must be positive"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_auto_extension_happens_once() {
+        let max = chrono::Duration::hours(168);
+        let now = created() + chrono::Duration::hours(24);
+
+        assert_eq!(
+            auto_extension(&update_request(UpdatePhase::Pending, false), now, max),
+            None
+        );
+
+        let mut ur = update_request(UpdatePhase::Pending, true);
+        assert_eq!(
+            auto_extension(&ur, now, max),
+            Some(now + chrono::Duration::hours(AUTO_EXTEND_HOURS))
+        );
+
+        // An extension through the API doesn't use up the automatic one
+        let manual = TtlExtension {
+            extended_at: now,
+            extend_by_hours: 24,
+            previous_expires_at: ur.spec.expires_at,
+            expires_at: now + chrono::Duration::hours(24),
+            automatic: false,
+        };
+        ur.status
+            .as_mut()
+            .unwrap()
+            .ttl_extensions
+            .push(manual.clone());
+        assert!(auto_extension(&ur, now, max).is_some());
+
+        ur.status
+            .as_mut()
+            .unwrap()
+            .ttl_extensions
+            .push(TtlExtension {
+                automatic: true,
+                ..manual
+            });
+        assert_eq!(auto_extension(&ur, now, max), None);
+    }
+
+    #[test]
+    fn test_auto_extension_respects_maximum_lifetime() {
+        let ur = update_request(UpdatePhase::Pending, true);
+        let now = created() + chrono::Duration::hours(150);
+        assert_eq!(auto_extension(&ur, now, chrono::Duration::hours(168)), None);
+    }
+
+    #[test]
+    fn test_scheduled_requests_do_not_expire() {
+        let mut ur = update_request(UpdatePhase::Pending, false);
+        // Scheduled three days out, well past the 24h expiry
+        ur.spec.scheduled_at = Some(created() + chrono::Duration::hours(72));
+        assert_eq!(expiry_of(&ur), None);
+        assert!(!is_expired(&ur, created() + chrono::Duration::hours(48)));
+        assert_eq!(
+            crate::controller::scheduled::scheduled_action(
+                &ur,
+                created() + chrono::Duration::hours(72)
+            ),
+            crate::controller::scheduled::ScheduledAction::Approve
+        );
+    }
+
+    #[test]
+    fn test_requests_do_not_expire_before_sla_deadline() {
+        let mut ur = update_request(UpdatePhase::Pending, false);
+        let deadline = created() + chrono::Duration::hours(72);
+        ur.spec.sla_deadline = Some(deadline);
+
+        assert_eq!(expiry_of(&ur), Some(deadline));
+        assert!(!is_expired(&ur, created() + chrono::Duration::hours(48)));
+        // Breached before it expires, so the breach is still reported
+        assert!(crate::controller::sla::is_breached(
+            &ur,
+            deadline + chrono::Duration::minutes(1)
+        ));
+        assert!(is_expired(&ur, deadline));
+
+        // A deadline before the expiry changes nothing
+        ur.spec.sla_deadline = Some(created() + chrono::Duration::hours(12));
+        assert_eq!(expiry_of(&ur), ur.spec.expires_at);
+    }
+
+    #[test]
+    fn test_expired_requests_are_not_escalated() {
+        let config = crate::controller::escalation::EscalationConfig::default();
+        let now = created() + chrono::Duration::hours(48);
+
+        let pending = update_request(UpdatePhase::Pending, false);
+        assert!(crate::controller::escalation::escalation(&pending, now, &config).is_some());
+
+        let expired = update_request(UpdatePhase::Expired, false);
+        assert!(!is_expired(&expired, now));
+        assert_eq!(
+            crate::controller::escalation::escalation(&expired, now, &config),
+            None
+        );
+    }
+}
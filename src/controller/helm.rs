@@ -1,4 +1,5 @@
-use crate::helm::{HelmRepositoryClient, OciHelmClient};
+use crate::helm::{HelmRepositoryClient, OciHelmClient, changelog};
+use crate::integrations::flux::helm_repository::discover_helm_repo;
 use crate::metrics::{
     HELM_CHART_VERSIONS_CHECKED, HELM_RELEASES_WATCHED, HELM_REPOSITORY_ERRORS,
     HELM_REPOSITORY_QUERIES, HELM_REPOSITORY_QUERY_DURATION, HELM_UPDATES_APPROVED,
@@ -9,16 +10,16 @@ use crate::models::crd::{
     UpdateRequestStatus, UpdateType,
 };
 use crate::models::policy::annotations;
-use crate::models::{HelmRelease, HelmRepository, ResourcePolicy, UpdatePolicy};
+use crate::models::{
+    HelmRelease, HelmRepository, HelmRepositorySpec, ResourcePolicy, UpdatePolicy,
+};
 use crate::policy::PolicyEngine;
+use crate::policy::ignore::is_resource_ignored;
 use anyhow::Result;
 use futures::StreamExt;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
-use kube::{
-    Api, Client, ResourceExt,
-    api::ListParams,
-    runtime::{Controller, controller::Action, watcher::Config},
-};
+use kube::{Api, Client, ResourceExt, api::ListParams, runtime::controller::Action};
+use semver::VersionReq;
 use std::{collections::BTreeMap, sync::Arc, time::Duration};
 use tracing::{debug, error, info, warn};
 
@@ -51,7 +52,7 @@ impl HelmController {
     }
 
     pub async fn run(self) {
-        let api: Api<HelmRelease> = Api::all(self.client.clone());
+        let api: Api<HelmRelease> = super::watched_api(self.client.clone());
 
         // Create Helm repository client for version discovery
         let helm_repo_client = HelmRepositoryClient::with_kube_client()
@@ -71,7 +72,7 @@ impl HelmController {
         });
 
         // Set up controller with exponential backoff
-        Controller::new(api, Config::default())
+        super::queue_metrics::controller(api, "helm")
             .shutdown_on_signal()
             .run(reconcile, error_policy, context)
             .filter_map(|x| async move { std::result::Result::ok(x) })
@@ -93,6 +94,8 @@ async fn reconcile(
     ctx: Arc<ControllerContext>,
 ) -> Result<Action, kube::Error> {
     let _timer = RECONCILE_DURATION.start_timer();
+    let _queue_timer = super::queue_metrics::start_reconcile("helm", helm_release.as_ref());
+    let _in_flight = super::concurrency::track("helm");
 
     let namespace = helm_release.namespace().ok_or_else(|| {
         kube::Error::Api(kube::error::ErrorResponse {
@@ -111,6 +114,11 @@ async fn reconcile(
         helm_release.metadata.generation.unwrap_or_default()
     );
 
+    if is_resource_ignored(helm_release.annotations()) {
+        debug!("HelmRelease {}/{} is ignored, skipping", namespace, name);
+        return Ok(Action::requeue(Duration::from_secs(3600)));
+    }
+
     // Parse policy from annotations
     let policy = parse_policy_from_annotations(helm_release.metadata.annotations.as_ref());
 
@@ -159,9 +167,19 @@ async fn reconcile(
         return Ok(Action::requeue(Duration::from_secs(3600)));
     }
 
+    // Build resource policy from annotations
+    let resource_policy = build_resource_policy(helm_release.metadata.annotations.as_ref(), policy);
+
     // Attempt to discover new versions from Helm repository
-    if let Some(new_version) =
-        discover_new_version(&ctx, &helm_release, chart_name, base_version, &policy).await
+    if let Some(new_version) = discover_new_version(
+        &ctx,
+        &helm_release,
+        chart_name,
+        base_version,
+        &policy,
+        resource_policy.helm_version_constraint.as_ref(),
+    )
+    .await
     {
         debug!(
             "HelmRelease {}/{} - New version {} discovered (current: {})",
@@ -174,10 +192,6 @@ async fn reconcile(
         // Potential update available - increment found metric
         HELM_UPDATES_FOUND.inc();
 
-        // Build resource policy from annotations
-        let resource_policy =
-            build_resource_policy(helm_release.metadata.annotations.as_ref(), policy);
-
         // Check if update should proceed based on policy
         match ctx
             .policy_engine
@@ -203,6 +217,7 @@ async fn reconcile(
                         base_version,
                         &new_version,
                         &resource_policy,
+                        helm_release.metadata.annotations.as_ref(),
                     )
                     .await
                     {
@@ -213,7 +228,7 @@ async fn reconcile(
                             );
 
                             // Send notification for UpdateRequest creation
-                            crate::notifications::notify_update_request_created(
+                            let mut payload = crate::notifications::update_request_created_payload(
                                 crate::notifications::DeploymentInfo {
                                     name: name.clone(),
                                     namespace: namespace.clone(),
@@ -225,7 +240,26 @@ async fn reconcile(
                                 format!("{:?}", resource_policy.policy),
                                 resource_policy.require_approval,
                                 update_request_name,
+                                helm_release
+                                    .metadata
+                                    .annotations
+                                    .as_ref()
+                                    .and_then(|a| a.get(annotations::STATUS_PAGE))
+                                    .cloned(),
+                                None,
                             );
+                            if let Some(changelog) = chart_changelog(
+                                &ctx,
+                                &helm_release,
+                                chart_name,
+                                base_version,
+                                &new_version,
+                            )
+                            .await
+                            {
+                                payload = payload.with_changelog(changelog);
+                            }
+                            crate::notifications::notify(payload);
                         },
                         Err(e) => {
                             warn!(
@@ -264,9 +298,7 @@ async fn reconcile(
                                 remaining.num_seconds()
                             );
                             crate::metrics::UPDATES_SKIPPED_INTERVAL.inc();
-                            return Ok(Action::requeue(Duration::from_secs(
-                                min_update_interval as u64,
-                            )));
+                            return Ok(Action::requeue(Duration::from_secs(min_update_interval)));
                         }
                     }
 
@@ -350,15 +382,70 @@ async fn reconcile(
 }
 
 /// Discover new chart versions by querying the Helm repository (HTTP or OCI)
-async fn discover_new_version(
+/// Changes between two chart versions for the UpdateRequest notification.
+/// Only public HTTP repositories are queried; OCI registries and repositories
+/// with credentials don't expose the chart metadata without pulling it.
+async fn chart_changelog(
     ctx: &Arc<ControllerContext>,
     helm_release: &HelmRelease,
     chart_name: &str,
-    current_version: &str,
-    policy: &UpdatePolicy,
+    from_version: &str,
+    to_version: &str,
 ) -> Option<String> {
-    // Get the HelmRepository reference from the HelmRelease
+    let (helm_repo, _) = helm_repository(ctx, helm_release).await?;
+    if helm_repo.spec.secret_ref.is_some() {
+        return None;
+    }
+
+    match changelog::fetch_chart_changelog(
+        &helm_repo.spec.url,
+        chart_name,
+        from_version,
+        to_version,
+    )
+    .await
+    {
+        Ok(changelog) => changelog,
+        Err(e) => {
+            warn!(
+                "Failed to fetch changelog of chart {} {}: {}",
+                chart_name, to_version, e
+            );
+            None
+        },
+    }
+}
+
+/// The chart repository of a HelmRelease and the namespace of its credentials.
+/// `headwind.sh/helm-repo-url` takes precedence over the HelmRepository in
+/// `spec.chart.spec.sourceRef`, which is looked up through the per-namespace
+/// HelmRepository cache.
+async fn helm_repository(
+    ctx: &Arc<ControllerContext>,
+    helm_release: &HelmRelease,
+) -> Option<(HelmRepository, String)> {
     let source_ref = &helm_release.spec.chart.spec.source_ref;
+    let namespace = helm_release.namespace().unwrap_or_default();
+
+    if let Some(url) = helm_release.annotations().get(annotations::HELM_REPO_URL) {
+        debug!(
+            "HelmRelease {}/{} uses chart repository {} from annotation",
+            namespace,
+            helm_release.name_any(),
+            url
+        );
+        let spec = HelmRepositorySpec {
+            url: url.clone(),
+            interval: None,
+            timeout: None,
+            secret_ref: None,
+            cert_secret_ref: None,
+            pass_credentials: None,
+            repository_type: None,
+            provider: None,
+        };
+        return Some((HelmRepository::new(&source_ref.name, spec), namespace));
+    }
 
     // Only handle HelmRepository sources (not GitRepository, Bucket, etc.)
     if source_ref.kind != "HelmRepository" {
@@ -369,22 +456,28 @@ async fn discover_new_version(
         return None;
     }
 
-    let namespace = helm_release.namespace().unwrap_or_default();
-    let repo_namespace = source_ref.namespace.as_deref().unwrap_or(&namespace);
-    let repo_name = &source_ref.name;
-
-    // Fetch the HelmRepository resource
-    let repo_api: Api<HelmRepository> = Api::namespaced(ctx.client.clone(), repo_namespace);
-    let helm_repo = match repo_api.get(repo_name).await {
-        Ok(repo) => repo,
+    let repo_namespace = source_ref.namespace.clone().unwrap_or(namespace);
+    match discover_helm_repo(&ctx.client, &repo_namespace, &source_ref.name).await {
+        Ok(helm_repo) => Some((helm_repo, repo_namespace)),
         Err(e) => {
             warn!(
                 "Failed to fetch HelmRepository {}/{}: {}",
-                repo_namespace, repo_name, e
+                repo_namespace, source_ref.name, e
             );
-            return None;
+            None
         },
-    };
+    }
+}
+
+async fn discover_new_version(
+    ctx: &Arc<ControllerContext>,
+    helm_release: &HelmRelease,
+    chart_name: &str,
+    current_version: &str,
+    policy: &UpdatePolicy,
+    constraint: Option<&VersionReq>,
+) -> Option<String> {
+    let (helm_repo, repo_namespace) = helm_repository(ctx, helm_release).await?;
 
     let repo_url = &helm_repo.spec.url;
 
@@ -398,10 +491,11 @@ async fn discover_new_version(
         discover_oci_version(
             ctx,
             &helm_repo,
-            repo_namespace,
+            &repo_namespace,
             chart_name,
             current_version,
             policy,
+            constraint,
         )
         .await
     } else {
@@ -410,10 +504,11 @@ async fn discover_new_version(
         discover_http_version(
             ctx,
             &helm_repo,
-            repo_namespace,
+            &repo_namespace,
             chart_name,
             current_version,
             policy,
+            constraint,
         )
         .await
     }
@@ -427,6 +522,7 @@ async fn discover_oci_version(
     chart_name: &str,
     current_version: &str,
     policy: &UpdatePolicy,
+    constraint: Option<&VersionReq>,
 ) -> Option<String> {
     let repo_url = &helm_repo.spec.url;
 
@@ -460,7 +556,7 @@ async fn discover_oci_version(
     HELM_REPOSITORY_QUERIES.inc();
 
     // List available versions (tags) from OCI registry
-    let versions = match ctx
+    let mut versions = match ctx
         .oci_helm_client
         .get_chart_versions(&full_oci_url, username.as_deref(), password.as_deref())
         .await
@@ -479,6 +575,17 @@ async fn discover_oci_version(
         versions
     );
 
+    if let Some(constraint) = constraint {
+        versions.retain(|v| satisfies_constraint(constraint, v));
+        if versions.is_empty() {
+            warn!(
+                "No version of chart {} satisfies constraint {}, skipping",
+                chart_name, constraint
+            );
+            return None;
+        }
+    }
+
     // Find best version using policy
     ctx.oci_helm_client
         .find_best_version(&versions, current_version, policy)
@@ -492,11 +599,12 @@ async fn discover_http_version(
     chart_name: &str,
     current_version: &str,
     policy: &UpdatePolicy,
+    constraint: Option<&VersionReq>,
 ) -> Option<String> {
     let repo_url = &helm_repo.spec.url;
 
     // Check if authentication is required
-    let index = if let Some(secret_ref) = &helm_repo.spec.secret_ref {
+    let mut index = if let Some(secret_ref) = &helm_repo.spec.secret_ref {
         // Fetch credentials from Secret
         match ctx
             .helm_repo_client
@@ -551,11 +659,48 @@ async fn discover_http_version(
         }
     };
 
+    if let Some(constraint) = constraint
+        && let Some(entries) = index.entries.get_mut(chart_name)
+    {
+        entries.retain(|entry| satisfies_constraint(constraint, &entry.version));
+        if entries.is_empty() {
+            warn!(
+                "No version of chart {} satisfies constraint {}, skipping",
+                chart_name, constraint
+            );
+            return None;
+        }
+    }
+
     // Find the best version matching the policy
     ctx.helm_repo_client
         .find_best_version(&index, chart_name, current_version, policy)
 }
 
+/// Whether a chart version lies within the `helm-version-constraint` range.
+/// Versions that are not valid semver never satisfy a constraint.
+fn satisfies_constraint(constraint: &VersionReq, version: &str) -> bool {
+    semver::Version::parse(version.trim_start_matches('v'))
+        .is_ok_and(|version| constraint.matches(&version))
+}
+
+/// Parse the `headwind.sh/helm-version-constraint` annotation
+fn parse_version_constraint(annotations: Option<&BTreeMap<String, String>>) -> Option<VersionReq> {
+    let value = annotations?.get(annotations::HELM_VERSION_CONSTRAINT)?;
+    match VersionReq::parse(value) {
+        Ok(constraint) => Some(constraint),
+        Err(e) => {
+            warn!(
+                "Invalid {} annotation '{}': {}, ignoring",
+                annotations::HELM_VERSION_CONSTRAINT,
+                value,
+                e
+            );
+            None
+        },
+    }
+}
+
 fn error_policy(
     _helm_release: Arc<HelmRelease>,
     error: &kube::Error,
@@ -586,7 +731,7 @@ fn parse_policy_from_annotations(annotations: Option<&BTreeMap<String, String>>)
 }
 
 async fn update_helm_releases_count(client: &Client) {
-    let api: Api<HelmRelease> = Api::all(client.clone());
+    let api: Api<HelmRelease> = super::watched_api(client.clone());
     match api.list(&ListParams::default()).await {
         Ok(list) => {
             HELM_RELEASES_WATCHED.set(list.items.len() as i64);
@@ -629,11 +774,21 @@ fn build_resource_policy(
         require_approval,
         min_update_interval,
         images: Vec::new(),
+        container_names: Vec::new(),
         event_source,
         polling_interval,
+        batch_mode: false,
+        tag_normalization: Default::default(),
+        observers: Vec::new(),
+        helm_version_constraint: parse_version_constraint(annotations),
+        include_init_containers: false,
+        require_slsa_level: None,
+        slsa_builder: None,
+        opa_policy: None,
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn create_update_request(
     client: kube::Client,
     namespace: &str,
@@ -642,6 +797,7 @@ async fn create_update_request(
     current_version: &str,
     new_version: &str,
     policy: &ResourcePolicy,
+    resource_annotations: Option<&BTreeMap<String, String>>,
 ) -> Result<String, kube::Error> {
     use kube::{Api, api::PostParams};
 
@@ -667,12 +823,19 @@ async fn create_update_request(
         },
         update_type: UpdateType::HelmChart,
         container_name: None,
+        container_kind: Default::default(),
         current_image: format!("{}:{}", chart_name, current_version),
         new_image: format!("{}:{}", chart_name, new_version),
         policy: policy_type,
         reason: Some(format!("New chart version {} available", new_version)),
         require_approval: policy.require_approval,
         expires_at: Some(chrono::Utc::now() + chrono::Duration::hours(24)),
+        scheduled_at: None,
+        priority: None,
+        updates: Vec::new(),
+        discussion_thread: Vec::new(),
+        sla_deadline: super::sla::sla_deadline(resource_annotations, chrono::Utc::now()),
+        impact: None,
     };
 
     let status = UpdateRequestStatus {
@@ -785,10 +948,29 @@ pub async fn handle_chart_update(
         require_approval: true,
         min_update_interval: None,
         images: Vec::new(),
+        container_names: Vec::new(),
         event_source: Default::default(),
         polling_interval: None,
+        batch_mode: false,
+        tag_normalization: Default::default(),
+        observers: Vec::new(),
+        helm_version_constraint: None,
+        include_init_containers: false,
+        require_slsa_level: None,
+        slsa_builder: None,
+        opa_policy: None,
     };
 
+    if let Some(constraint) = parse_version_constraint(helm_release.metadata.annotations.as_ref())
+        && !satisfies_constraint(&constraint, new_version)
+    {
+        warn!(
+            "Version {} of chart {} does not satisfy constraint {} for HelmRelease {}/{}, skipping",
+            new_version, chart_name, constraint, namespace, name
+        );
+        return Ok(());
+    }
+
     // Check if update is allowed by policy
     let should_update = policy_engine
         .should_update(&temp_policy, current_version, new_version)
@@ -829,12 +1011,30 @@ pub async fn handle_chart_update(
         require_approval,
         min_update_interval: Some(min_update_interval),
         images: Vec::new(),
+        container_names: Vec::new(),
         event_source: Default::default(),
         polling_interval: None,
+        batch_mode: false,
+        tag_normalization: Default::default(),
+        observers: Vec::new(),
+        helm_version_constraint: None,
+        include_init_containers: false,
+        require_slsa_level: None,
+        slsa_builder: None,
+        opa_policy: None,
     };
 
     // Check if approval is required
     if resource_policy.require_approval {
+        if !super::rate_limit::allow_update_request(
+            "HelmRelease",
+            &namespace,
+            &name,
+            helm_release.metadata.annotations.as_ref(),
+        ) {
+            return Ok(());
+        }
+
         info!(
             "Creating UpdateRequest for HelmRelease {}/{}",
             namespace, name
@@ -847,6 +1047,7 @@ pub async fn handle_chart_update(
             current_version,
             new_version,
             &resource_policy,
+            helm_release.metadata.annotations.as_ref(),
         )
         .await?;
     } else {
@@ -917,3 +1118,80 @@ pub async fn handle_chart_update(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotated(constraint: &str) -> BTreeMap<String, String> {
+        BTreeMap::from([(
+            annotations::HELM_VERSION_CONSTRAINT.to_string(),
+            constraint.to_string(),
+        )])
+    }
+
+    /// Best version after applying the constraint, as the reconciler does
+    fn best_version(constraint: &str, current: &str, policy: UpdatePolicy) -> Option<String> {
+        let resource_policy = build_resource_policy(Some(&annotated(constraint)), policy);
+        let constraint = resource_policy.helm_version_constraint.unwrap();
+        let versions: Vec<String> = ["1.9.0", "2.0.0", "2.0.1", "2.1.0", "2.3.4", "3.0.0"]
+            .iter()
+            .map(|v| v.to_string())
+            .filter(|v| satisfies_constraint(&constraint, v))
+            .collect();
+        OciHelmClient::new().find_best_version(&versions, current, &policy)
+    }
+
+    #[test]
+    fn test_parse_version_constraint() {
+        let policy =
+            build_resource_policy(Some(&annotated(">= 2.0.0, < 3.0.0")), UpdatePolicy::Major);
+        let constraint = policy.helm_version_constraint.unwrap();
+        assert!(satisfies_constraint(&constraint, "2.5.0"));
+        assert!(satisfies_constraint(&constraint, "v2.0.0"));
+        assert!(!satisfies_constraint(&constraint, "3.0.0"));
+        assert!(!satisfies_constraint(&constraint, "latest"));
+
+        let policy = build_resource_policy(Some(&annotated("not a range")), UpdatePolicy::Major);
+        assert!(policy.helm_version_constraint.is_none());
+        assert!(
+            build_resource_policy(None, UpdatePolicy::Major)
+                .helm_version_constraint
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_constraint_with_patch_policy() {
+        assert_eq!(
+            best_version(">= 2.0.0, < 2.0.1", "2.0.0", UpdatePolicy::Patch),
+            None
+        );
+        assert_eq!(
+            best_version(">= 2.0.0, < 3.0.0", "2.0.0", UpdatePolicy::Patch),
+            Some("2.0.1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_constraint_with_minor_policy() {
+        assert_eq!(
+            best_version(">= 2.0.0, < 2.2.0", "2.0.0", UpdatePolicy::Minor),
+            Some("2.1.0".to_string())
+        );
+        assert_eq!(
+            best_version(">= 2.0.0, < 3.0.0", "2.0.0", UpdatePolicy::Minor),
+            Some("2.3.4".to_string())
+        );
+    }
+
+    #[test]
+    fn test_constraint_with_major_policy() {
+        // The policy alone would move to 3.0.0
+        assert_eq!(
+            best_version(">= 2.0.0, < 3.0.0", "1.9.0", UpdatePolicy::Major),
+            Some("2.3.4".to_string())
+        );
+        assert_eq!(best_version(">= 4.0.0", "1.9.0", UpdatePolicy::Major), None);
+    }
+}
@@ -0,0 +1,408 @@
+//! Blast radius of a Deployment update, assessed before its UpdateRequest is
+//! created.
+//!
+//! The rollout restarts every replica, taking down `maxUnavailable` pods at a
+//! time (all of them with `Recreate`). That is compared with the most
+//! restrictive PodDisruptionBudget selecting the pods; an update that takes
+//! down more pods than the budget allows gets a `headwind.sh/pdb-warning`
+//! label on its UpdateRequest and a warning notification.
+
+use crate::models::{PdbSummary, UpdateImpact};
+use crate::notifications::router::selector_matches;
+use anyhow::Result;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::policy::v1::PodDisruptionBudget;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{Api, ListParams};
+use kube::{Client, ResourceExt};
+
+/// Period of a readiness probe that doesn't set `periodSeconds`
+const DEFAULT_PROBE_PERIOD_SECONDS: i32 = 10;
+
+/// Assess the impact of rolling out a new image to `deployment`
+pub async fn assess_update_impact(
+    client: &Client,
+    deployment: &Deployment,
+) -> Result<UpdateImpact> {
+    let namespace = deployment.namespace().unwrap_or_default();
+    let pdbs: Api<PodDisruptionBudget> = Api::namespaced(client.clone(), &namespace);
+    let budgets = pdbs.list(&ListParams::default()).await?.items;
+    Ok(update_impact(deployment, &budgets))
+}
+
+/// Impact of rolling out `deployment` with the PodDisruptionBudgets of its
+/// namespace
+pub fn update_impact(deployment: &Deployment, budgets: &[PodDisruptionBudget]) -> UpdateImpact {
+    let replica_count = deployment
+        .spec
+        .as_ref()
+        .and_then(|s| s.replicas)
+        .unwrap_or(1)
+        .max(0);
+    let max_unavailable = max_unavailable(deployment);
+
+    let pod_labels = deployment
+        .spec
+        .as_ref()
+        .and_then(|s| s.template.metadata.as_ref())
+        .and_then(|m| m.labels.clone())
+        .unwrap_or_default();
+    let disruption_budget = budgets
+        .iter()
+        .filter(|pdb| {
+            pdb.spec
+                .as_ref()
+                .and_then(|s| s.selector.as_ref())
+                .is_some_and(|selector| selector_matches(selector, &pod_labels))
+        })
+        .map(|pdb| pdb_summary(pdb, replica_count))
+        .min_by_key(|summary| summary.disruptions_allowed);
+
+    UpdateImpact {
+        replica_count,
+        disruption_budget,
+        max_unavailable,
+        estimated_downtime_seconds: estimated_downtime_seconds(deployment, max_unavailable),
+    }
+}
+
+/// Pods a rollout takes down at once: every replica with `Recreate`,
+/// otherwise `maxUnavailable` (25% rounded down by default, at least one if
+/// `maxSurge` is zero as well)
+pub fn max_unavailable(deployment: &Deployment) -> Option<i32> {
+    let spec = deployment.spec.as_ref()?;
+    let replicas = spec.replicas.unwrap_or(1).max(0);
+    let strategy = spec.strategy.as_ref();
+    if strategy.and_then(|s| s.type_.as_deref()) == Some("Recreate") {
+        return Some(replicas);
+    }
+
+    let max_unavailable = strategy
+        .and_then(|s| s.rolling_update.as_ref())
+        .and_then(|r| r.max_unavailable.clone())
+        .unwrap_or_else(|| IntOrString::String("25%".to_string()));
+    let max_unavailable = match max_unavailable {
+        IntOrString::Int(n) => n.max(0),
+        IntOrString::String(s) => s
+            .strip_suffix('%')
+            .and_then(|p| p.parse::<i32>().ok())
+            .map(|percent| replicas * percent / 100)
+            .unwrap_or(0),
+    };
+    if max_unavailable == 0 && super::quota::surge_pods(deployment) == 0 {
+        return Some(1.min(replicas));
+    }
+    Some(max_unavailable.min(replicas))
+}
+
+/// `value` of `replicas`, percentages rounded up like the disruption
+/// controller does
+fn scaled(value: &IntOrString, replicas: i32) -> i32 {
+    match value {
+        IntOrString::Int(n) => *n,
+        IntOrString::String(s) => s
+            .strip_suffix('%')
+            .and_then(|p| p.parse::<i32>().ok())
+            .map(|percent| (replicas * percent + 99) / 100)
+            .unwrap_or(0),
+    }
+}
+
+fn pdb_summary(pdb: &PodDisruptionBudget, replicas: i32) -> PdbSummary {
+    let spec = pdb.spec.as_ref();
+    let min_available = spec.and_then(|s| s.min_available.as_ref());
+    let max_unavailable = spec.and_then(|s| s.max_unavailable.as_ref());
+    let disruptions_allowed = match (min_available, max_unavailable) {
+        (Some(min), _) => replicas - scaled(min, replicas),
+        (None, Some(max)) => scaled(max, replicas),
+        (None, None) => replicas,
+    };
+
+    let text = |value: &IntOrString| match value {
+        IntOrString::Int(n) => n.to_string(),
+        IntOrString::String(s) => s.clone(),
+    };
+    PdbSummary {
+        name: pdb.name_any(),
+        min_available: min_available.map(text),
+        max_unavailable: max_unavailable.map(text),
+        disruptions_allowed: disruptions_allowed.max(0),
+    }
+}
+
+/// Seconds with fewer ready replicas than desired: each batch of
+/// `max_unavailable` pods is down until its replacements pass their
+/// readiness probe and `minReadySeconds`
+fn estimated_downtime_seconds(
+    deployment: &Deployment,
+    max_unavailable: Option<i32>,
+) -> Option<u64> {
+    let spec = deployment.spec.as_ref()?;
+    let max_unavailable = max_unavailable?;
+    if max_unavailable == 0 {
+        return Some(0);
+    }
+
+    let ready_seconds = spec
+        .template
+        .spec
+        .as_ref()?
+        .containers
+        .iter()
+        .filter_map(|c| c.readiness_probe.as_ref())
+        .map(|probe| {
+            probe.initial_delay_seconds.unwrap_or(0)
+                + probe.period_seconds.unwrap_or(DEFAULT_PROBE_PERIOD_SECONDS)
+        })
+        .max()?;
+    let ready_seconds = (ready_seconds + spec.min_ready_seconds.unwrap_or(0)).max(0) as u64;
+
+    let replicas = spec.replicas.unwrap_or(1).max(0) as u64;
+    let batches = replicas.div_ceil(max_unavailable as u64);
+    Some(batches * ready_seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn deployment(replicas: i32, strategy: serde_json::Value) -> Deployment {
+        serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "web", "namespace": "default" },
+            "spec": {
+                "replicas": replicas,
+                "strategy": strategy,
+                "minReadySeconds": 5,
+                "selector": { "matchLabels": { "app": "web" } },
+                "template": {
+                    "metadata": { "labels": { "app": "web", "tier": "frontend" } },
+                    "spec": {
+                        "containers": [
+                            {
+                                "name": "app",
+                                "image": "example/app:1.0.0",
+                                "readinessProbe": {
+                                    "httpGet": { "path": "/ready", "port": 8080 },
+                                    "initialDelaySeconds": 15
+                                }
+                            }
+                        ]
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn pdb(
+        name: &str,
+        selector: serde_json::Value,
+        budget: serde_json::Value,
+    ) -> PodDisruptionBudget {
+        let mut spec = budget;
+        spec["selector"] = selector;
+        serde_json::from_value(json!({
+            "apiVersion": "policy/v1",
+            "kind": "PodDisruptionBudget",
+            "metadata": { "name": name, "namespace": "default" },
+            "spec": spec
+        }))
+        .unwrap()
+    }
+
+    fn rolling(max_unavailable: serde_json::Value) -> serde_json::Value {
+        json!({ "type": "RollingUpdate", "rollingUpdate": { "maxUnavailable": max_unavailable } })
+    }
+
+    #[test]
+    fn test_no_pdb() {
+        let impact = update_impact(&deployment(4, json!({})), &[]);
+        assert_eq!(
+            impact,
+            UpdateImpact {
+                replica_count: 4,
+                disruption_budget: None,
+                // 25% of 4
+                max_unavailable: Some(1),
+                // 4 batches of 15s delay + 10s period + 5s minReadySeconds
+                estimated_downtime_seconds: Some(120),
+            }
+        );
+        assert!(!impact.violates_disruption_budget());
+    }
+
+    #[test]
+    fn test_min_available_within_budget() {
+        let budgets = [pdb(
+            "web",
+            json!({ "matchLabels": { "app": "web" } }),
+            json!({ "minAvailable": 2 }),
+        )];
+        let impact = update_impact(&deployment(3, rolling(json!(1))), &budgets);
+        assert_eq!(
+            impact.disruption_budget,
+            Some(PdbSummary {
+                name: "web".to_string(),
+                min_available: Some("2".to_string()),
+                max_unavailable: None,
+                disruptions_allowed: 1,
+            })
+        );
+        assert!(!impact.violates_disruption_budget());
+    }
+
+    #[test]
+    fn test_max_unavailable_above_budget_violates() {
+        let budgets = [pdb(
+            "web",
+            json!({ "matchLabels": { "app": "web" } }),
+            json!({ "maxUnavailable": 1 }),
+        )];
+        let impact = update_impact(&deployment(10, rolling(json!("50%"))), &budgets);
+        assert_eq!(impact.max_unavailable, Some(5));
+        assert_eq!(
+            impact
+                .disruption_budget
+                .as_ref()
+                .unwrap()
+                .disruptions_allowed,
+            1
+        );
+        assert!(impact.violates_disruption_budget());
+    }
+
+    #[test]
+    fn test_percentage_budgets_round_up() {
+        // minAvailable 50% of 3 is 2 pods, so 1 may be disrupted
+        let min = [pdb(
+            "web",
+            json!({ "matchLabels": { "app": "web" } }),
+            json!({ "minAvailable": "50%" }),
+        )];
+        let impact = update_impact(&deployment(3, rolling(json!(1))), &min);
+        assert_eq!(impact.disruption_budget.unwrap().disruptions_allowed, 1);
+
+        // maxUnavailable 10% of 3 is 1 pod
+        let max = [pdb(
+            "web",
+            json!({ "matchLabels": { "app": "web" } }),
+            json!({ "maxUnavailable": "10%" }),
+        )];
+        let impact = update_impact(&deployment(3, rolling(json!(1))), &max);
+        assert_eq!(impact.disruption_budget.unwrap().disruptions_allowed, 1);
+    }
+
+    #[test]
+    fn test_recreate_takes_down_every_replica() {
+        let budgets = [pdb(
+            "web",
+            json!({ "matchLabels": { "app": "web" } }),
+            json!({ "minAvailable": 1 }),
+        )];
+        let impact = update_impact(&deployment(3, json!({ "type": "Recreate" })), &budgets);
+        assert_eq!(impact.max_unavailable, Some(3));
+        // One batch: the whole Deployment is down until the new pods are ready
+        assert_eq!(impact.estimated_downtime_seconds, Some(30));
+        assert!(impact.violates_disruption_budget());
+    }
+
+    #[test]
+    fn test_zero_max_unavailable_has_no_downtime() {
+        let budgets = [pdb(
+            "web",
+            json!({ "matchLabels": { "app": "web" } }),
+            json!({ "maxUnavailable": 0 }),
+        )];
+        let impact = update_impact(&deployment(3, rolling(json!(0))), &budgets);
+        assert_eq!(impact.max_unavailable, Some(0));
+        assert_eq!(impact.estimated_downtime_seconds, Some(0));
+        assert!(!impact.violates_disruption_budget());
+
+        // With no surge either, Kubernetes still takes one pod down at a time
+        let strategy = json!({
+            "type": "RollingUpdate",
+            "rollingUpdate": { "maxUnavailable": 0, "maxSurge": 0 }
+        });
+        let impact = update_impact(&deployment(3, strategy), &budgets);
+        assert_eq!(impact.max_unavailable, Some(1));
+        assert!(impact.violates_disruption_budget());
+    }
+
+    #[test]
+    fn test_most_restrictive_matching_pdb_is_used() {
+        let budgets = [
+            pdb(
+                "loose",
+                json!({ "matchLabels": { "app": "web" } }),
+                json!({ "maxUnavailable": 2 }),
+            ),
+            pdb(
+                "strict",
+                json!({ "matchExpressions": [
+                    { "key": "tier", "operator": "In", "values": ["frontend"] }
+                ] }),
+                json!({ "minAvailable": 4 }),
+            ),
+            pdb(
+                "other",
+                json!({ "matchLabels": { "app": "api" } }),
+                json!({ "minAvailable": 4 }),
+            ),
+        ];
+        let impact = update_impact(&deployment(4, rolling(json!(1))), &budgets);
+        let pdb = impact.disruption_budget.clone().unwrap();
+        assert_eq!(pdb.name, "strict");
+        assert_eq!(pdb.disruptions_allowed, 0);
+        assert!(impact.violates_disruption_budget());
+    }
+
+    #[test]
+    fn test_pdb_without_selector_matches_nothing() {
+        let budget: PodDisruptionBudget = serde_json::from_value(json!({
+            "metadata": { "name": "none" },
+            "spec": { "minAvailable": 3 }
+        }))
+        .unwrap();
+        let impact = update_impact(&deployment(3, rolling(json!(1))), &[budget]);
+        assert_eq!(impact.disruption_budget, None);
+    }
+
+    #[test]
+    fn test_no_readiness_probe_has_no_estimate() {
+        let mut deployment = deployment(3, rolling(json!(1)));
+        let pod = deployment
+            .spec
+            .as_mut()
+            .unwrap()
+            .template
+            .spec
+            .as_mut()
+            .unwrap();
+        pod.containers[0].readiness_probe = None;
+        assert_eq!(
+            update_impact(&deployment, &[]).estimated_downtime_seconds,
+            None
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let budgets = [pdb(
+            "web",
+            json!({ "matchLabels": { "app": "web" } }),
+            json!({ "minAvailable": 2 }),
+        )];
+        let impact = update_impact(&deployment(3, rolling(json!(1))), &budgets);
+        assert_eq!(
+            impact.summary(),
+            "3 replicas, 1 unavailable at a time, ~90s degraded, PDB web (1 disruption allowed)"
+        );
+        assert_eq!(
+            update_impact(&deployment(1, json!({ "type": "Recreate" })), &[]).summary(),
+            "1 replica, 1 unavailable at a time, ~30s degraded, no PDB"
+        );
+    }
+}
@@ -0,0 +1,686 @@
+use crate::metrics::{RECONCILE_DURATION, RECONCILE_ERRORS};
+use crate::models::{
+    EventSource, KNATIVE_UPDATE_TIMESTAMP_ANNOTATION, KnativeService, ResourcePolicy, TargetRef,
+    UpdatePolicy, UpdatePolicyType, UpdateRequest, UpdateRequestSpec, UpdateType, annotations,
+    global_tag_normalization, parse_container_names, parse_tag_normalization,
+};
+use crate::notifications::{self, DeploymentInfo};
+use crate::policy::PolicyEngine;
+use crate::schedule::PendingUpdate;
+use anyhow::Result;
+use chrono::Utc;
+use futures::StreamExt;
+use kube::{
+    ResourceExt,
+    api::{Api, ApiResource, DynamicObject, Patch, PatchParams, PostParams},
+    client::Client,
+    runtime::controller::Action,
+};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument};
+
+pub struct KnativeServiceController {
+    client: Client,
+}
+
+impl KnativeServiceController {
+    pub async fn new() -> Result<Self> {
+        let client = Client::try_default().await?;
+
+        Ok(Self { client })
+    }
+
+    pub async fn run(self) {
+        info!("Knative Service controller starting...");
+
+        // Run the controller in a loop with exponential backoff
+        // This handles transient errors during startup or runtime
+        let mut backoff_seconds = 1;
+        const MAX_BACKOFF: u64 = 60;
+
+        loop {
+            let services: Api<KnativeService> = super::watched_api(self.client.clone());
+
+            info!("Creating controller for knative services");
+
+            let result = super::queue_metrics::controller(services, "knative")
+                .run(reconcile, error_policy, Arc::new(()))
+                .for_each(|res| async move {
+                    match res {
+                        Ok((obj_ref, _action)) => {
+                            info!(
+                                "Reconciled knative service: {}/{}",
+                                obj_ref.namespace.as_deref().unwrap_or("default"),
+                                obj_ref.name
+                            );
+                        },
+                        Err(e) => {
+                            // Log reconciliation errors but continue processing
+                            error!("Reconciliation error: {}", e);
+                            RECONCILE_ERRORS.inc();
+                        },
+                    }
+                })
+                .await;
+
+            // If the controller stream ends, log it and restart after backoff
+            error!(
+                "Knative Service controller stream ended, restarting in {}s...",
+                backoff_seconds
+            );
+            tokio::time::sleep(Duration::from_secs(backoff_seconds)).await;
+
+            // Exponential backoff up to MAX_BACKOFF seconds
+            backoff_seconds = (backoff_seconds * 2).min(MAX_BACKOFF);
+
+            debug!("Controller loop result: {:?}", result);
+        }
+    }
+}
+
+/// Validate the Service's policy; updates come from image push events (see
+/// [`handle_image_update`]) and `headwind_knative_services_watched` is
+/// counted by the resource gauge updater
+#[instrument(skip(_ctx, service), fields(service = %service.name_any()))]
+async fn reconcile(service: Arc<KnativeService>, _ctx: Arc<()>) -> Result<Action, kube::Error> {
+    let _timer = RECONCILE_DURATION.start_timer();
+    let _queue_timer = super::queue_metrics::start_reconcile("knative", service.as_ref());
+    let _in_flight = super::concurrency::track("knative");
+
+    let namespace = service.namespace().unwrap_or_default();
+    let name = service.name_any();
+
+    debug!(
+        "Reconciling knative service {}/{} - starting",
+        namespace, name
+    );
+
+    // Check if this service has headwind annotations
+    let Some(annotations) = service.metadata.annotations.as_ref() else {
+        debug!(
+            "Knative Service {}/{} has no annotations, skipping",
+            namespace, name
+        );
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    };
+
+    if !annotations.contains_key(annotations::POLICY) {
+        debug!(
+            "Knative Service {}/{} has no headwind policy annotation, skipping",
+            namespace, name
+        );
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    }
+
+    let policy = match parse_policy_from_annotations(annotations) {
+        Ok(p) => p,
+        Err(e) => {
+            error!(
+                "Failed to parse policy for knative service {}/{}: {}",
+                namespace, name, e
+            );
+            return Err(create_error(&format!("Failed to parse policy: {}", e)));
+        },
+    };
+
+    debug!(
+        "Knative Service {}/{} has policy: {:?}",
+        namespace, name, policy.policy
+    );
+
+    debug!(
+        "Knative Service {}/{} reconciliation complete",
+        namespace, name
+    );
+
+    Ok(Action::requeue(Duration::from_secs(300)))
+}
+
+fn error_policy(_object: Arc<KnativeService>, _error: &kube::Error, _ctx: Arc<()>) -> Action {
+    // Requeue after 60 seconds on errors
+    Action::requeue(Duration::from_secs(60))
+}
+
+/// Helper to create a kube::Error from a string message
+fn create_error(msg: &str) -> kube::Error {
+    kube::Error::Api(kube::error::ErrorResponse {
+        status: "Failure".to_string(),
+        message: msg.to_string(),
+        reason: "InvalidConfiguration".to_string(),
+        code: 400,
+    })
+}
+
+/// Parse an image string into (image_name, tag)
+/// Example: "myregistry.com/myimage:v1.2.3" -> ("myregistry.com/myimage", "v1.2.3")
+fn parse_image(image: &str) -> Result<(String, String), String> {
+    let parts: Vec<&str> = image.rsplitn(2, ':').collect();
+    if parts.len() != 2 {
+        return Err(format!("Invalid image format: {}", image));
+    }
+    Ok((parts[1].to_string(), parts[0].to_string()))
+}
+
+/// Handle an available image update for a Knative Service
+/// This is called for image push events from webhooks and registry polling
+#[instrument(skip(client, policy_engine, service))]
+pub async fn handle_image_update(
+    client: &Client,
+    policy_engine: &Arc<PolicyEngine>,
+    service: &KnativeService,
+    image: &str,
+    new_version: &str,
+) -> Result<()> {
+    let namespace = service.namespace().unwrap_or_default();
+    let name = service.name_any();
+
+    info!(
+        "Handling image update for knative service {}/{}: {} -> {}",
+        namespace, name, image, new_version
+    );
+
+    let annotations = service
+        .metadata
+        .annotations
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Knative Service has no annotations"))?;
+
+    let policy = parse_policy_from_annotations(annotations)?;
+
+    // Find container with matching image
+    let mut current = None;
+    for container in service.containers() {
+        if !policy.targets_container(container.name.as_deref().unwrap_or_default()) {
+            continue;
+        }
+        let Some(container_image) = container.image.as_deref() else {
+            continue;
+        };
+        let (img_name, img_tag) = parse_image(container_image)
+            .map_err(|e| anyhow::anyhow!("Failed to parse container image: {}", e))?;
+
+        if img_name == image || container_image.starts_with(image) {
+            current = Some((container.name.clone(), img_tag));
+            break;
+        }
+    }
+
+    let (container_name, current_version) =
+        current.ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
+
+    let should_update = policy_engine
+        .should_update(&policy, &current_version, new_version)
+        .map_err(|e| anyhow::anyhow!("Policy evaluation failed: {}", e))?;
+
+    if !should_update {
+        info!(
+            "Update from {} to {} rejected by policy {:?}",
+            current_version, new_version, policy.policy
+        );
+        return Ok(());
+    }
+
+    // Check minimum update interval
+    if let (Some(min_interval), Some(last_update_str)) = (
+        policy.min_update_interval,
+        annotations.get(annotations::LAST_UPDATE),
+    ) && let Ok(last_update) = chrono::DateTime::parse_from_rfc3339(last_update_str)
+    {
+        let elapsed = Utc::now().signed_duration_since(last_update.with_timezone(&Utc));
+        let min_duration = chrono::Duration::seconds(min_interval as i64);
+
+        if elapsed < min_duration {
+            info!(
+                "Skipping update for knative service {}/{}: minimum interval not met ({} < {} seconds)",
+                namespace,
+                name,
+                elapsed.num_seconds(),
+                min_interval
+            );
+            return Ok(());
+        }
+    }
+
+    if policy.require_approval {
+        if !super::rate_limit::allow_update_request("Service", &namespace, &name, Some(annotations))
+        {
+            return Ok(());
+        }
+
+        info!(
+            "Creating UpdateRequest for knative service {}/{}: {} -> {}",
+            namespace, name, current_version, new_version
+        );
+
+        create_update_request(
+            client,
+            &namespace,
+            &name,
+            container_name,
+            image,
+            &current_version,
+            new_version,
+            &policy,
+            annotations,
+        )
+        .await?;
+    } else if let Some((schedule, scheduled_for)) =
+        crate::schedule::deferred_until(annotations, Utc::now())
+    {
+        let client = client.clone();
+        let current_image = format!("{}:{}", image, current_version);
+        let new_image = format!("{}:{}", image, new_version);
+        let (image, new_version) = (image.to_string(), new_version.to_string());
+
+        crate::schedule::defer_update(
+            PendingUpdate {
+                kind: "Service".to_string(),
+                namespace: namespace.clone(),
+                name: name.clone(),
+                container: container_name.clone(),
+                current_image: current_image.clone(),
+                new_image: new_image.clone(),
+                policy: policy.policy,
+                schedule,
+                scheduled_for,
+                queued_at: Utc::now(),
+            },
+            move || async move {
+                update_knative_service_image(&client, &namespace, &name, &image, &new_version)
+                    .await?;
+                notifications::notify_update_completed(DeploymentInfo {
+                    name,
+                    namespace,
+                    current_image,
+                    new_image,
+                    container: container_name,
+                    resource_kind: Some("Service".to_string()),
+                });
+                Ok(())
+            },
+        )
+        .await;
+    } else {
+        info!(
+            "Auto-updating knative service {}/{} (no approval required): {} -> {}",
+            namespace, name, current_version, new_version
+        );
+
+        update_knative_service_image(client, &namespace, &name, image, new_version).await?;
+
+        notifications::notify_update_completed(DeploymentInfo {
+            name: name.clone(),
+            namespace: namespace.clone(),
+            current_image: format!("{}:{}", image, current_version),
+            new_image: format!("{}:{}", image, new_version),
+            container: container_name,
+            resource_kind: Some("Service".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Create an UpdateRequest CRD for a pending update
+#[allow(clippy::too_many_arguments)]
+async fn create_update_request(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    container_name: Option<String>,
+    image: &str,
+    current_version: &str,
+    new_version: &str,
+    policy: &ResourcePolicy,
+    resource_annotations: &std::collections::BTreeMap<String, String>,
+) -> Result<()> {
+    let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), namespace);
+
+    let request_name = format!(
+        "{}-{}",
+        name,
+        new_version.replace([':', '.', '/'], "-").to_lowercase()
+    );
+
+    let update_request = UpdateRequest {
+        metadata: kube::api::ObjectMeta {
+            name: Some(request_name.clone()),
+            namespace: Some(namespace.to_string()),
+            ..Default::default()
+        },
+        spec: UpdateRequestSpec {
+            target_ref: TargetRef {
+                api_version: "serving.knative.dev/v1".to_string(),
+                kind: "Service".to_string(),
+                name: name.to_string(),
+                namespace: namespace.to_string(),
+            },
+            update_type: UpdateType::Image,
+            container_name,
+            container_kind: Default::default(),
+            current_image: format!("{}:{}", image, current_version),
+            new_image: format!("{}:{}", image, new_version),
+            policy: map_policy_to_crd(&policy.policy),
+            reason: Some(format!(
+                "Update from {} to {}",
+                current_version, new_version
+            )),
+            require_approval: true,
+            expires_at: Some(Utc::now() + chrono::Duration::hours(24)),
+            scheduled_at: None,
+            priority: None,
+            updates: Vec::new(),
+            discussion_thread: Vec::new(),
+            sla_deadline: super::sla::sla_deadline(Some(resource_annotations), chrono::Utc::now()),
+            impact: None,
+        },
+        status: None,
+    };
+
+    match update_requests.get(&request_name).await {
+        Ok(existing) => {
+            // Recreate if the previous request is in a terminal state
+            if let Some(status) = &existing.status {
+                use crate::models::crd::UpdatePhase;
+                if status.phase == UpdatePhase::Completed
+                    || status.phase == UpdatePhase::Rejected
+                    || status.phase == UpdatePhase::Failed
+                {
+                    update_requests
+                        .delete(&request_name, &Default::default())
+                        .await?;
+                    update_requests
+                        .create(&PostParams::default(), &update_request)
+                        .await?;
+                }
+            }
+        },
+        Err(kube::Error::Api(err)) if err.code == 404 => {
+            update_requests
+                .create(&PostParams::default(), &update_request)
+                .await?;
+            info!(
+                "Created UpdateRequest {}/{} for knative service {}",
+                namespace, request_name, name
+            );
+        },
+        Err(e) => {
+            error!("Failed to check for existing UpdateRequest: {}", e);
+            return Err(anyhow::anyhow!("Failed to check UpdateRequest: {}", e));
+        },
+    }
+
+    Ok(())
+}
+
+/// Map internal UpdatePolicy to CRD UpdatePolicyType
+fn map_policy_to_crd(policy: &UpdatePolicy) -> UpdatePolicyType {
+    match policy {
+        UpdatePolicy::Patch => UpdatePolicyType::Patch,
+        UpdatePolicy::Minor => UpdatePolicyType::Minor,
+        UpdatePolicy::Major => UpdatePolicyType::Major,
+        UpdatePolicy::Glob => UpdatePolicyType::Glob,
+        UpdatePolicy::None => UpdatePolicyType::None,
+        // Map All and Force to Major since they don't exist in CRD
+        UpdatePolicy::All | UpdatePolicy::Force => UpdatePolicyType::Major,
+    }
+}
+
+/// Parse ResourcePolicy from Knative Service annotations
+fn parse_policy_from_annotations(
+    annotations: &std::collections::BTreeMap<String, String>,
+) -> Result<ResourcePolicy> {
+    let policy_str = annotations
+        .get(annotations::POLICY)
+        .ok_or_else(|| anyhow::anyhow!("No policy annotation found"))?;
+
+    let policy = policy_str
+        .parse::<UpdatePolicy>()
+        .map_err(|e| anyhow::anyhow!("Invalid update policy: {}", e))?;
+
+    let pattern = annotations.get(annotations::PATTERN).cloned();
+
+    let require_approval = annotations
+        .get(annotations::REQUIRE_APPROVAL)
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(true);
+
+    let min_update_interval = annotations
+        .get(annotations::MIN_UPDATE_INTERVAL)
+        .and_then(|v| v.parse::<u64>().ok());
+
+    let images = annotations
+        .get(annotations::IMAGES)
+        .map(|s| s.split(',').map(|i| i.trim().to_string()).collect())
+        .unwrap_or_default();
+
+    let event_source = annotations
+        .get(annotations::EVENT_SOURCE)
+        .and_then(|v| v.parse::<EventSource>().ok())
+        .unwrap_or_default();
+
+    let polling_interval = annotations
+        .get(annotations::POLLING_INTERVAL)
+        .and_then(|v| v.parse::<u64>().ok());
+
+    let container_names = annotations
+        .get(annotations::CONTAINER_NAMES)
+        .map(|v| parse_container_names(v))
+        .unwrap_or_default();
+
+    let tag_normalization = parse_tag_normalization(annotations, &global_tag_normalization())
+        .unwrap_or_else(|_| global_tag_normalization());
+
+    Ok(ResourcePolicy {
+        policy,
+        pattern,
+        require_approval,
+        min_update_interval,
+        images,
+        container_names,
+        event_source,
+        polling_interval,
+        batch_mode: false,
+        tag_normalization,
+        observers: annotations
+            .get(annotations::NOTIFY_OBSERVERS)
+            .map(|v| crate::models::parse_observers(v))
+            .unwrap_or_default(),
+        helm_version_constraint: None,
+        include_init_containers: false,
+        require_slsa_level: None,
+        slsa_builder: None,
+        opa_policy: None,
+    })
+}
+
+/// Build the merge patch that sets a container image and bumps the Knative
+/// update timestamp so a new Revision is created.
+///
+/// Merge patches replace lists wholesale, so `containers` must be the complete
+/// container list from the live object; only the image at `index` is changed.
+fn build_image_patch(
+    mut containers: Vec<Value>,
+    index: usize,
+    new_image: &str,
+    timestamp: &str,
+    last_update_value: &str,
+) -> Value {
+    if let Some(container) = containers.get_mut(index) {
+        container["image"] = json!(new_image);
+    }
+
+    json!({
+        "metadata": {
+            "annotations": {
+                annotations::LAST_UPDATE: last_update_value
+            }
+        },
+        "spec": {
+            "template": {
+                "metadata": {
+                    "annotations": {
+                        KNATIVE_UPDATE_TIMESTAMP_ANNOTATION: timestamp
+                    }
+                },
+                "spec": {
+                    "containers": containers
+                }
+            }
+        }
+    })
+}
+
+/// Find the index of the first container whose image starts with `image`
+fn find_container_index(containers: &[Value], image: &str) -> Option<usize> {
+    containers.iter().position(|c| {
+        c.get("image")
+            .and_then(Value::as_str)
+            .is_some_and(|i| i.starts_with(image))
+    })
+}
+
+/// Update a Knative Service's container image - public wrapper
+pub async fn update_knative_service_image(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    image: &str,
+    new_version: &str,
+) -> Result<()> {
+    update_knative_service_image_with_tracking(client, namespace, name, image, new_version, None)
+        .await
+}
+
+/// Update a Knative Service's container image with tracking
+/// If approver is provided, it will be recorded in the last-update annotation
+pub async fn update_knative_service_image_with_tracking(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    image: &str,
+    new_version: &str,
+    approver: Option<&str>,
+) -> Result<()> {
+    let new_image = format!("{}:{}", image, new_version);
+
+    info!(
+        "Updating knative service {}/{} image to {}",
+        namespace, name, new_image
+    );
+
+    // Read the raw object so the full container definitions survive the merge patch
+    let resource = ApiResource::erase::<KnativeService>(&());
+    let raw: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace, &resource);
+    let service = raw.get(name).await?;
+
+    let containers = service
+        .data
+        .pointer("/spec/template/spec/containers")
+        .and_then(Value::as_array)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Knative Service has no containers"))?;
+
+    let container_index = find_container_index(&containers, image)
+        .ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
+
+    let now = Utc::now();
+    let last_update_value = if let Some(approver) = approver {
+        format!("{} (approved by {})", now.to_rfc3339(), approver)
+    } else {
+        now.to_rfc3339()
+    };
+
+    let patch = build_image_patch(
+        containers,
+        container_index,
+        &new_image,
+        &now.to_rfc3339(),
+        &last_update_value,
+    );
+
+    let services: Api<KnativeService> = Api::namespaced(client.clone(), namespace);
+    services
+        .patch(name, &PatchParams::default(), &Patch::Merge(patch))
+        .await?;
+
+    info!(
+        "Successfully updated knative service {}/{} to version {}",
+        namespace, name, new_version
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_image() {
+        let (name, tag) = parse_image("ghcr.io/knative/helloworld-go:v1.2.3").unwrap();
+        assert_eq!(name, "ghcr.io/knative/helloworld-go");
+        assert_eq!(tag, "v1.2.3");
+        assert!(parse_image("invalid-no-tag").is_err());
+    }
+
+    #[test]
+    fn test_parse_policy_from_annotations() {
+        let mut annotations = std::collections::BTreeMap::new();
+        annotations.insert(annotations::POLICY.to_string(), "patch".to_string());
+        annotations.insert(
+            annotations::REQUIRE_APPROVAL.to_string(),
+            "false".to_string(),
+        );
+
+        let policy = parse_policy_from_annotations(&annotations).unwrap();
+        assert_eq!(policy.policy, UpdatePolicy::Patch);
+        assert!(!policy.require_approval);
+
+        annotations.insert(annotations::POLICY.to_string(), "bogus".to_string());
+        assert!(parse_policy_from_annotations(&annotations).is_err());
+    }
+
+    #[test]
+    fn test_find_container_index() {
+        let containers = vec![
+            json!({"name": "sidecar", "image": "envoy:1.0"}),
+            json!({"name": "app", "image": "myapp:1.0.0"}),
+        ];
+        assert_eq!(find_container_index(&containers, "myapp"), Some(1));
+        assert_eq!(find_container_index(&containers, "redis"), None);
+    }
+
+    #[test]
+    fn test_build_image_patch_preserves_container_fields() {
+        let containers = vec![json!({
+            "image": "myapp:1.0.0",
+            "env": [{"name": "TARGET", "value": "World"}],
+            "ports": [{"containerPort": 8080}]
+        })];
+
+        let patch = build_image_patch(
+            containers,
+            0,
+            "myapp:1.0.1",
+            "2025-01-01T00:00:00+00:00",
+            "2025-01-01T00:00:00+00:00 (approved by alice)",
+        );
+
+        let container = &patch["spec"]["template"]["spec"]["containers"][0];
+        assert_eq!(container["image"], "myapp:1.0.1");
+        assert_eq!(container["env"][0]["value"], "World");
+        assert_eq!(container["ports"][0]["containerPort"], 8080);
+        assert_eq!(
+            patch["spec"]["template"]["metadata"]["annotations"]
+                [KNATIVE_UPDATE_TIMESTAMP_ANNOTATION],
+            "2025-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            patch["metadata"]["annotations"][annotations::LAST_UPDATE],
+            "2025-01-01T00:00:00+00:00 (approved by alice)"
+        );
+    }
+}
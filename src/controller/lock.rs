@@ -0,0 +1,316 @@
+//! Per-UpdateRequest lock on a Kubernetes Lease.
+//!
+//! With several replicas running, an approval or a schedule can be processed
+//! by more than one of them. Before applying an UpdateRequest a replica takes
+//! the Lease `headwind-ur-{uid}` in the UpdateRequest's namespace, and a
+//! replica that finds it held by another skips the update and tries again
+//! later. The Lease is written with the resourceVersion that was read, so two
+//! replicas racing for a free Lease cannot both win. A held Lease expires 30
+//! seconds after it was taken, so a replica that dies while applying an update
+//! does not block the request forever. The Lease is owned by its
+//! UpdateRequest, so Kubernetes deletes it along with the request.
+
+use crate::metrics::{LOCK_ACQUISITIONS_TOTAL, LOCK_CONTENTIONS_TOTAL};
+use crate::models::crd::UpdateRequest;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::coordination::v1::{Lease, LeaseSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{MicroTime, OwnerReference};
+use kube::api::{ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, Resource, ResourceExt};
+use serde_json::json;
+use std::time::Duration;
+use tracing::{debug, info};
+
+/// How long a Lease is held unless released
+pub const LEASE_DURATION_SECONDS: i32 = 30;
+
+/// When to try again after another replica held the lock
+pub const CONTENTION_REQUEUE: Duration = Duration::from_secs(10);
+
+/// Name of the Lease locking the UpdateRequest with `uid`
+pub fn lease_name(uid: &str) -> String {
+    format!("headwind-ur-{}", uid)
+}
+
+/// Identity of this replica: the pod name, which Kubernetes sets as hostname
+pub fn holder_identity() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "headwind".to_string())
+}
+
+/// Whether `identity` may take `lease` at `now`: it has no holder, is held by
+/// `identity` already, or was last renewed more than its duration ago
+fn is_available(lease: &Lease, identity: &str, now: DateTime<Utc>) -> bool {
+    let Some(spec) = &lease.spec else {
+        return true;
+    };
+    match spec.holder_identity.as_deref() {
+        None | Some("") => return true,
+        Some(holder) if holder == identity => return true,
+        Some(_) => {},
+    }
+
+    let duration = spec
+        .lease_duration_seconds
+        .unwrap_or(LEASE_DURATION_SECONDS);
+    match spec.renew_time.as_ref().or(spec.acquire_time.as_ref()) {
+        Some(MicroTime(taken)) => *taken + chrono::Duration::seconds(duration.into()) <= now,
+        None => true,
+    }
+}
+
+/// A Lease held by `identity` from `now`, owned by `owner`
+fn held_lease(
+    name: &str,
+    resource_version: Option<String>,
+    owner: Option<&OwnerReference>,
+    identity: &str,
+    now: DateTime<Utc>,
+) -> Lease {
+    Lease {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            resource_version,
+            owner_references: owner.map(|owner| vec![owner.clone()]),
+            labels: Some(
+                [(
+                    "app.kubernetes.io/managed-by".to_string(),
+                    "headwind".to_string(),
+                )]
+                .into(),
+            ),
+            ..Default::default()
+        },
+        spec: Some(LeaseSpec {
+            holder_identity: Some(identity.to_string()),
+            lease_duration_seconds: Some(LEASE_DURATION_SECONDS),
+            acquire_time: Some(MicroTime(now)),
+            renew_time: Some(MicroTime(now)),
+            ..Default::default()
+        }),
+    }
+}
+
+/// The lock of one UpdateRequest, held by this replica until released
+pub struct UpdateRequestLock {
+    api: Api<Lease>,
+    name: String,
+}
+
+impl UpdateRequestLock {
+    /// Take the lock of `update_request`, or `None` when another replica
+    /// holds it
+    pub async fn try_acquire(
+        client: &Client,
+        update_request: &UpdateRequest,
+    ) -> Result<Option<Self>, kube::Error> {
+        let namespace = update_request.namespace().unwrap_or_default();
+        let uid = update_request
+            .uid()
+            .unwrap_or_else(|| update_request.name_any());
+        let owner = update_request.controller_owner_ref(&());
+        let api: Api<Lease> = Api::namespaced(client.clone(), &namespace);
+        let name = lease_name(&uid);
+        let identity = holder_identity();
+        let now = Utc::now();
+
+        let result = match api.get_opt(&name).await? {
+            None => {
+                api.create(
+                    &PostParams::default(),
+                    &held_lease(&name, None, owner.as_ref(), &identity, now),
+                )
+                .await
+            },
+            Some(lease) if is_available(&lease, &identity, now) => {
+                let resource_version = lease.metadata.resource_version;
+                api.replace(
+                    &name,
+                    &PostParams::default(),
+                    &held_lease(&name, resource_version, owner.as_ref(), &identity, now),
+                )
+                .await
+            },
+            Some(lease) => {
+                debug!(
+                    "Lease {}/{} is held by {}",
+                    namespace,
+                    name,
+                    lease
+                        .spec
+                        .and_then(|s| s.holder_identity)
+                        .unwrap_or_default()
+                );
+                LOCK_CONTENTIONS_TOTAL.inc();
+                return Ok(None);
+            },
+        };
+
+        match result {
+            Ok(_) => {
+                LOCK_ACQUISITIONS_TOTAL.inc();
+                Ok(Some(Self { api, name }))
+            },
+            // Another replica created or took the Lease since it was read
+            Err(kube::Error::Api(e)) if e.code == 409 => {
+                info!("Lost the race for Lease {}/{}", namespace, name);
+                LOCK_CONTENTIONS_TOTAL.inc();
+                Ok(None)
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Release the lock by clearing the holder of the Lease
+    pub async fn release(self) -> Result<(), kube::Error> {
+        self.api
+            .patch(
+                &self.name,
+                &PatchParams::default(),
+                &Patch::Merge(json!({ "spec": { "holderIdentity": null } })),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FakeApi, api_error};
+    use http::{Method, StatusCode};
+
+    fn update_request(uid: &str) -> UpdateRequest {
+        let mut update_request = testing::update_request("production", "web-nginx-1-26-0");
+        update_request.metadata.uid = Some(uid.to_string());
+        update_request
+    }
+
+    fn lease(holder: Option<&str>, renewed: DateTime<Utc>) -> Lease {
+        let mut lease = held_lease("headwind-ur-1", Some("7".to_string()), None, "", renewed);
+        lease.spec.as_mut().unwrap().holder_identity = holder.map(str::to_string);
+        lease
+    }
+
+    #[test]
+    fn test_is_available() {
+        let now = Utc::now();
+        let recent = now - chrono::Duration::seconds(5);
+        let expired = now - chrono::Duration::seconds(LEASE_DURATION_SECONDS.into());
+
+        assert!(is_available(&Lease::default(), "replica-a", now));
+        assert!(is_available(&lease(None, recent), "replica-a", now));
+        assert!(is_available(&lease(Some(""), recent), "replica-a", now));
+        assert!(is_available(
+            &lease(Some("replica-a"), recent),
+            "replica-a",
+            now
+        ));
+        assert!(!is_available(
+            &lease(Some("replica-b"), recent),
+            "replica-a",
+            now
+        ));
+        assert!(is_available(
+            &lease(Some("replica-b"), expired),
+            "replica-a",
+            now
+        ));
+    }
+
+    /// Kubernetes API serving `existing` as the Lease (404 when `None`) and
+    /// answering writes with `write_status`
+    fn fake_api(existing: Option<Lease>, write_status: StatusCode) -> FakeApi {
+        FakeApi::new(move |request| match (&request.method, &existing) {
+            (&Method::GET, Some(lease)) => (StatusCode::OK, serde_json::to_value(lease).unwrap()),
+            (&Method::GET, None) => api_error(StatusCode::NOT_FOUND, "NotFound", "not found"),
+            _ if write_status == StatusCode::CONFLICT => {
+                api_error(StatusCode::CONFLICT, "Conflict", "conflict")
+            },
+            _ => {
+                let mut lease = request.body.clone();
+                lease["apiVersion"] = json!("coordination.k8s.io/v1");
+                lease["kind"] = json!("Lease");
+                (write_status, lease)
+            },
+        })
+    }
+
+    const LEASE_PATH: &str = "/apis/coordination.k8s.io/v1/namespaces/production/leases";
+
+    #[tokio::test]
+    async fn test_acquire_creates_missing_lease_and_release_clears_holder() {
+        let api = fake_api(None, StatusCode::CREATED);
+
+        let lock = UpdateRequestLock::try_acquire(&api.client(), &update_request("1234"))
+            .await
+            .unwrap()
+            .expect("lock acquired");
+        lock.release().await.unwrap();
+
+        let requests = api.requests();
+        assert_eq!(
+            api.calls(),
+            [
+                (Method::GET, format!("{}/headwind-ur-1234", LEASE_PATH)),
+                (Method::POST, LEASE_PATH.to_string()),
+                (Method::PATCH, format!("{}/headwind-ur-1234", LEASE_PATH)),
+            ]
+        );
+        let created = &requests[1].body;
+        assert_eq!(created["metadata"]["name"], "headwind-ur-1234");
+        assert_eq!(created["spec"]["holderIdentity"], holder_identity());
+        assert_eq!(created["spec"]["leaseDurationSeconds"], 30);
+        // Deleted by Kubernetes along with the UpdateRequest
+        let owner = &created["metadata"]["ownerReferences"][0];
+        assert_eq!(owner["kind"], "UpdateRequest");
+        assert_eq!(owner["name"], "web-nginx-1-26-0");
+        assert_eq!(owner["uid"], "1234");
+        assert_eq!(
+            requests[2].body,
+            json!({ "spec": { "holderIdentity": null } })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_lease_is_taken_with_its_resource_version() {
+        let expired = Utc::now() - chrono::Duration::minutes(5);
+        let api = fake_api(Some(lease(Some("replica-b"), expired)), StatusCode::OK);
+
+        let lock = UpdateRequestLock::try_acquire(&api.client(), &update_request("1"))
+            .await
+            .unwrap();
+        assert!(lock.is_some());
+
+        let requests = api.requests();
+        assert_eq!(requests[1].method, Method::PUT);
+        let replaced = &requests[1].body;
+        assert_eq!(replaced["metadata"]["resourceVersion"], "7");
+        assert_eq!(replaced["metadata"]["ownerReferences"][0]["uid"], "1");
+        assert_eq!(replaced["spec"]["holderIdentity"], holder_identity());
+    }
+
+    #[tokio::test]
+    async fn test_lease_held_by_another_replica_is_contention() {
+        let api = fake_api(Some(lease(Some("replica-b"), Utc::now())), StatusCode::OK);
+
+        let lock = UpdateRequestLock::try_acquire(&api.client(), &update_request("1"))
+            .await
+            .unwrap();
+        assert!(lock.is_none());
+        // Only read, never written
+        assert_eq!(api.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_losing_the_race_is_contention() {
+        let api = fake_api(None, StatusCode::CONFLICT);
+
+        let lock = UpdateRequestLock::try_acquire(&api.client(), &update_request("1"))
+            .await
+            .unwrap();
+        assert!(lock.is_none());
+    }
+}
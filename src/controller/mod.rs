@@ -1,26 +1,136 @@
+mod batch;
+mod cleanup;
+mod concurrency;
+mod crossplane;
 mod daemonset;
+mod delivery;
+mod dependency;
 mod deployment;
+mod ephemeral;
+mod escalation;
+pub mod expiry;
 mod helm;
+mod impact;
+mod knative;
+pub mod lock;
+mod preview;
+pub mod progress;
+mod queue_metrics;
+pub mod quota;
+mod rate_limit;
+mod replicaset;
+mod scheduled;
+pub mod self_update;
+mod sla;
 mod statefulset;
 
+use crate::config::headwind_namespace;
+use crate::metrics::status::track_controller;
+use crate::models::policy::annotations;
 use anyhow::Result;
+use k8s_openapi::NamespaceResourceScope;
+use kube::{Api, Client, Resource};
 use tokio::task::JoinHandle;
 use tracing::info;
 
+pub use batch::{
+    add_to_batch, apply_batch, apply_container_updates, approved_status, batch_request_name,
+    rejected_status,
+};
+pub use cleanup::CleanupController;
+pub use crossplane::{
+    CompositionController, handle_image_update as handle_composition_image_update,
+    update_composition_image, update_composition_image_with_tracking,
+};
 pub use daemonset::{
-    DaemonSetController, handle_image_update as handle_daemonset_image_update,
-    update_daemonset_image, update_daemonset_image_with_tracking,
+    DaemonSetController, build_daemonset_image_patch,
+    handle_image_update as handle_daemonset_image_update, update_daemonset_image,
+    update_daemonset_image_with_tracking,
 };
+pub use delivery::{Delivery, deliver_update_request};
 pub use deployment::{
-    DeploymentController, handle_image_update as handle_deployment_image_update,
-    update_deployment_image, update_deployment_image_with_tracking,
+    DeploymentController, build_deployment_image_patch,
+    handle_image_update as handle_deployment_image_update, update_deployment_image,
+    update_deployment_image_with_tracking,
 };
+pub use escalation::EscalationController;
+pub use expiry::ExpiryController;
 pub use helm::{HelmController, handle_chart_update as handle_helm_chart_update};
+pub use knative::{
+    KnativeServiceController, handle_image_update as handle_knative_service_image_update,
+    update_knative_service_image, update_knative_service_image_with_tracking,
+};
+pub use preview::{PatchPreview, PreviewTarget, build_preview_patch, preview_update_request};
+pub use replicaset::{
+    ReplicaSetController, build_replicaset_image_patch,
+    handle_image_update as handle_replicaset_image_update,
+    is_standalone as is_standalone_replicaset, update_replicaset_image,
+    update_replicaset_image_with_tracking,
+};
+pub use scheduled::{SCHEDULED_APPROVER, ScheduledApprovalController};
+pub use sla::SlaMonitor;
 pub use statefulset::{
-    StatefulSetController, handle_image_update as handle_statefulset_image_update,
-    update_statefulset_image, update_statefulset_image_with_tracking,
+    StatefulSetController, build_statefulset_image_patch,
+    handle_image_update as handle_statefulset_image_update, update_statefulset_image,
+    update_statefulset_image_with_tracking,
 };
 
+/// Whether controllers watch every namespace (`HEADWIND_WATCH_ALL_NAMESPACES`, default true).
+/// When false, controllers are restricted to the headwind namespace so that the
+/// ServiceAccount only needs namespace-scoped RBAC.
+pub fn watch_all_namespaces() -> bool {
+    std::env::var("HEADWIND_WATCH_ALL_NAMESPACES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(true)
+}
+
+/// Whether Knative Services are updated (`HEADWIND_ENABLE_KNATIVE`, default
+/// false). Requires the Knative Serving CRDs.
+pub fn knative_enabled() -> bool {
+    std::env::var("HEADWIND_ENABLE_KNATIVE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false)
+}
+
+/// Whether Crossplane Compositions are updated (`HEADWIND_ENABLE_CROSSPLANE`,
+/// default false). Requires the Crossplane CRDs.
+pub fn crossplane_enabled() -> bool {
+    std::env::var("HEADWIND_ENABLE_CROSSPLANE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false)
+}
+
+/// Build the API handle controllers use to watch and list resources,
+/// honouring `HEADWIND_WATCH_ALL_NAMESPACES`
+pub fn watched_api<K>(client: Client) -> Api<K>
+where
+    K: Resource<Scope = NamespaceResourceScope>,
+    <K as Resource>::DynamicType: Default,
+{
+    if watch_all_namespaces() {
+        Api::all(client)
+    } else {
+        Api::namespaced(client, &headwind_namespace())
+    }
+}
+
+/// Save the image being replaced in the `headwind.sh/previous-image`
+/// annotation of an image patch, so a manual rollback can restore it. Nothing
+/// is saved when the image does not change.
+pub(crate) fn set_previous_image(
+    patch: &mut serde_json::Value,
+    previous_image: Option<&str>,
+    new_image: &str,
+) {
+    if let Some(previous_image) = previous_image.filter(|image| *image != new_image) {
+        patch["metadata"]["annotations"][annotations::PREVIOUS_IMAGE] =
+            serde_json::Value::from(previous_image);
+    }
+}
+
 pub async fn start_controllers() -> Result<JoinHandle<()>> {
     info!("Starting Kubernetes controllers");
 
@@ -31,6 +141,28 @@ pub async fn start_controllers() -> Result<JoinHandle<()>> {
         .unwrap_or(true);
 
     let handle = if controllers_enabled {
+        // Start the shared informer cache used by webhooks, polling and metrics
+        crate::cache::SharedInformerCache::init(Client::try_default().await?);
+
+        concurrency::log_settings();
+
+        if watch_all_namespaces() {
+            info!("Controllers watching all namespaces");
+        } else {
+            info!(
+                "Controllers restricted to namespace {} (HEADWIND_WATCH_ALL_NAMESPACES=false)",
+                headwind_namespace()
+            );
+        }
+
+        // Order updates by headwind.sh/update-after
+        let dependency_handle = dependency::start_graph_refresher(Client::try_default().await?);
+
+        // Report UpdateRequest phase changes to headwind.sh/status-webhook-url
+        let status_webhook_handle = crate::notifications::status_webhook::start_status_webhooks(
+            Client::try_default().await?,
+        )?;
+
         // Start deployment controller
         let deployment_controller = DeploymentController::new().await?;
 
@@ -44,34 +176,150 @@ pub async fn start_controllers() -> Result<JoinHandle<()>> {
         let policy_engine = std::sync::Arc::new(crate::policy::PolicyEngine);
         let helm_controller = HelmController::new(policy_engine).await?;
 
+        // Start ReplicaSet controller (standalone ReplicaSets only)
+        let replicaset_controller = ReplicaSetController::new().await?;
+
+        // Start scheduled approval controller
+        let scheduled_controller = ScheduledApprovalController::new().await?;
+
+        // Start cleanup of finished UpdateRequests
+        let cleanup_controller = CleanupController::new().await?;
+
+        // Start priority escalation of UpdateRequests pending approval
+        let escalation_controller = EscalationController::new().await?;
+
+        // Start reporting of UpdateRequests past their review SLA
+        let sla_monitor = SlaMonitor::new().await?;
+
+        // Start expiry of pending UpdateRequests (opt-in, see ExpiryController)
+        let expiry_controller = if crate::config::features::features().update_request_expiry {
+            Some(ExpiryController::new().await?)
+        } else {
+            None
+        };
+
+        // Start Knative Service controller (opt-in, requires Knative Serving CRDs)
+        let knative_controller = if knative_enabled() {
+            Some(KnativeServiceController::new().await?)
+        } else {
+            None
+        };
+
+        // Start Crossplane Composition controller (opt-in, requires Crossplane CRDs)
+        let crossplane_controller = if crossplane_enabled() {
+            Some(CompositionController::new().await?)
+        } else {
+            None
+        };
+
         tokio::spawn(async move {
             // Run all controllers concurrently
             let deployment_handle = tokio::spawn(async move {
-                deployment_controller.run().await;
+                track_controller("deployment", deployment_controller.run()).await;
                 tracing::info!("Deployment controller stopped");
             });
 
             let statefulset_handle = tokio::spawn(async move {
-                statefulset_controller.run().await;
+                track_controller("statefulset", statefulset_controller.run()).await;
                 tracing::info!("StatefulSet controller stopped");
             });
 
             let daemonset_handle = tokio::spawn(async move {
-                daemonset_controller.run().await;
+                track_controller("daemonset", daemonset_controller.run()).await;
                 tracing::info!("DaemonSet controller stopped");
             });
 
             let helm_handle = tokio::spawn(async move {
-                helm_controller.run().await;
+                track_controller("helm", helm_controller.run()).await;
                 tracing::info!("Helm controller stopped");
             });
 
+            let replicaset_handle = tokio::spawn(async move {
+                track_controller("replicaset", replicaset_controller.run()).await;
+                tracing::info!("ReplicaSet controller stopped");
+            });
+
+            let scheduled_handle = tokio::spawn(async move {
+                track_controller("scheduled-approval", scheduled_controller.run()).await;
+                tracing::info!("Scheduled approval controller stopped");
+            });
+
+            let cleanup_handle = tokio::spawn(async move {
+                track_controller("cleanup", cleanup_controller.run()).await;
+                tracing::info!("UpdateRequest cleanup controller stopped");
+            });
+
+            let escalation_handle = tokio::spawn(async move {
+                track_controller("escalation", escalation_controller.run()).await;
+                tracing::info!("UpdateRequest escalation controller stopped");
+            });
+
+            let sla_handle = tokio::spawn(async move {
+                track_controller("sla", sla_monitor.run()).await;
+                tracing::info!("UpdateRequest SLA monitor stopped");
+            });
+
+            let expiry_handle = expiry_controller.map(|controller| {
+                tokio::spawn(async move {
+                    track_controller("expiry", controller.run()).await;
+                    tracing::info!("UpdateRequest expiry controller stopped");
+                })
+            });
+            let expiry_wait = async move {
+                match expiry_handle {
+                    Some(handle) => {
+                        let _ = handle.await;
+                    },
+                    None => std::future::pending::<()>().await,
+                }
+            };
+
+            let knative_handle = knative_controller.map(|controller| {
+                tokio::spawn(async move {
+                    track_controller("knative", controller.run()).await;
+                    tracing::info!("Knative Service controller stopped");
+                })
+            });
+            let knative_wait = async move {
+                match knative_handle {
+                    Some(handle) => {
+                        let _ = handle.await;
+                    },
+                    None => std::future::pending::<()>().await,
+                }
+            };
+
+            let crossplane_handle = crossplane_controller.map(|controller| {
+                tokio::spawn(async move {
+                    track_controller("crossplane", controller.run()).await;
+                    tracing::info!("Crossplane Composition controller stopped");
+                })
+            });
+            let crossplane_wait = async move {
+                match crossplane_handle {
+                    Some(handle) => {
+                        let _ = handle.await;
+                    },
+                    None => std::future::pending::<()>().await,
+                }
+            };
+
             // Wait for any controller to stop
             tokio::select! {
                 _ = deployment_handle => {},
                 _ = statefulset_handle => {},
                 _ = daemonset_handle => {},
                 _ = helm_handle => {},
+                _ = replicaset_handle => {},
+                _ = scheduled_handle => {},
+                _ = cleanup_handle => {},
+                _ = escalation_handle => {},
+                _ = sla_handle => {},
+                _ = expiry_wait => {},
+                _ = knative_wait => {},
+                _ = crossplane_wait => {},
+                _ = dependency_handle => {},
+                _ = status_webhook_handle => {},
             }
         })
     } else {
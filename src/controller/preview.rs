@@ -0,0 +1,389 @@
+//! Preview of the patch an UpdateRequest would apply.
+//!
+//! Builds the same strategic merge patch as the `update_*_image_with_tracking`
+//! functions, without applying it. Optionally the patch is sent to the API
+//! server as a dry run to validate it and report the resulting changes.
+
+use super::{
+    build_daemonset_image_patch, build_deployment_image_patch, build_statefulset_image_patch,
+};
+use crate::models::crd::UpdateRequest;
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use kube::api::{Api, Patch, PatchParams};
+use kube::client::Client;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Metadata fields the API server changes on every write, left out of dry-run diffs
+const VOLATILE_PATHS: &[&str] = &[
+    "/metadata/generation",
+    "/metadata/managedFields",
+    "/metadata/resourceVersion",
+];
+
+/// The patch an UpdateRequest would apply to its target
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchPreview {
+    pub kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub patch_type: String,
+    pub patch: Value,
+    /// RFC 6902 operations between the current resource and the server's dry-run result
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run_diff: Option<Value>,
+}
+
+/// Current state of the resource an UpdateRequest targets
+pub enum PreviewTarget {
+    Deployment(Deployment),
+    StatefulSet(StatefulSet),
+    DaemonSet(DaemonSet),
+}
+
+/// Build the patch `update_request` would apply to `target`
+pub fn build_preview_patch(
+    update_request: &UpdateRequest,
+    target: &PreviewTarget,
+    last_update_value: &str,
+) -> Result<Value> {
+    let spec = &update_request.spec;
+
+    match target {
+        PreviewTarget::Deployment(deployment) => {
+            let container_name = spec
+                .container_name
+                .as_ref()
+                .ok_or_else(|| anyhow!("Container name not specified in UpdateRequest"))?;
+            let container_exists = deployment
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.spec.as_ref())
+                .is_some_and(|pod| pod.containers.iter().any(|c| c.name == *container_name));
+            if !container_exists {
+                return Err(anyhow!(
+                    "Container '{}' not found in deployment {}",
+                    container_name,
+                    spec.target_ref.name
+                ));
+            }
+
+            let mut patch = build_deployment_image_patch(container_name, &spec.new_image);
+            super::set_previous_image(
+                &mut patch,
+                super::deployment::container_image(deployment, container_name),
+                &spec.new_image,
+            );
+            Ok(patch)
+        },
+        PreviewTarget::StatefulSet(statefulset) => {
+            let (image, version) = split_image(update_request);
+            build_statefulset_image_patch(statefulset, image, version, last_update_value)
+        },
+        PreviewTarget::DaemonSet(daemonset) => {
+            let (image, version) = split_image(update_request);
+            build_daemonset_image_patch(daemonset, image, version, last_update_value)
+        },
+    }
+}
+
+/// Image name from `currentImage` and version from `newImage`, as the approval API passes them
+fn split_image(update_request: &UpdateRequest) -> (&str, &str) {
+    let spec = &update_request.spec;
+    let image = spec
+        .current_image
+        .rsplit_once(':')
+        .map(|(image, _)| image)
+        .unwrap_or(&spec.current_image);
+    let version = spec
+        .new_image
+        .rsplit_once(':')
+        .map(|(_, version)| version)
+        .unwrap_or(&spec.new_image);
+    (image, version)
+}
+
+/// Fetch the target of `update_request` and build its patch. With
+/// `server_side_dry_run`, the API server validates the patch without persisting it.
+pub async fn preview_update_request(
+    client: &Client,
+    update_request: &UpdateRequest,
+    server_side_dry_run: bool,
+) -> Result<PatchPreview> {
+    let target_ref = &update_request.spec.target_ref;
+    let namespace = &target_ref.namespace;
+    let name = &target_ref.name;
+    let last_update_value = Utc::now().to_rfc3339();
+
+    let (patch, dry_run_diff) = match target_ref.kind.as_str() {
+        "Deployment" => {
+            let api: Api<Deployment> = Api::namespaced(client.clone(), namespace);
+            let current = api.get(name).await?;
+            let patch = build_preview_patch(
+                update_request,
+                &PreviewTarget::Deployment(current.clone()),
+                &last_update_value,
+            )?;
+            let diff = dry_run_diff(&api, name, &current, &patch, server_side_dry_run).await?;
+            (patch, diff)
+        },
+        "StatefulSet" => {
+            let api: Api<StatefulSet> = Api::namespaced(client.clone(), namespace);
+            let current = api.get(name).await?;
+            let patch = build_preview_patch(
+                update_request,
+                &PreviewTarget::StatefulSet(current.clone()),
+                &last_update_value,
+            )?;
+            let diff = dry_run_diff(&api, name, &current, &patch, server_side_dry_run).await?;
+            (patch, diff)
+        },
+        "DaemonSet" => {
+            let api: Api<DaemonSet> = Api::namespaced(client.clone(), namespace);
+            let current = api.get(name).await?;
+            let patch = build_preview_patch(
+                update_request,
+                &PreviewTarget::DaemonSet(current.clone()),
+                &last_update_value,
+            )?;
+            let diff = dry_run_diff(&api, name, &current, &patch, server_side_dry_run).await?;
+            (patch, diff)
+        },
+        kind => {
+            return Err(anyhow!(
+                "Patch preview is not supported for {}. Only Deployment, StatefulSet, and DaemonSet are supported.",
+                kind
+            ));
+        },
+    };
+
+    Ok(PatchPreview {
+        kind: target_ref.kind.clone(),
+        namespace: namespace.clone(),
+        name: name.clone(),
+        patch_type: "application/strategic-merge-patch+json".to_string(),
+        patch,
+        dry_run_diff,
+    })
+}
+
+/// Send `patch` as a server-side dry run and diff the result against `current`
+async fn dry_run_diff<K>(
+    api: &Api<K>,
+    name: &str,
+    current: &K,
+    patch: &Value,
+    enabled: bool,
+) -> Result<Option<Value>>
+where
+    K: kube::Resource + Clone + Serialize + serde::de::DeserializeOwned + std::fmt::Debug,
+{
+    if !enabled {
+        return Ok(None);
+    }
+
+    let result = api
+        .patch(
+            name,
+            &PatchParams::default().dry_run(),
+            &Patch::Strategic(patch),
+        )
+        .await?;
+
+    let before = serde_json::to_value(current)?;
+    let after = serde_json::to_value(&result)?;
+    Ok(Some(resource_diff(&before, &after)))
+}
+
+/// JSON patch operations turning `before` into `after`, ignoring volatile metadata
+fn resource_diff(before: &Value, after: &Value) -> Value {
+    let diff = json_patch::diff(before, after);
+    let operations: Vec<Value> = serde_json::to_value(&diff)
+        .ok()
+        .and_then(|v| v.as_array().cloned())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|op| {
+            let path = op["path"].as_str().unwrap_or_default();
+            !VOLATILE_PATHS.iter().any(|p| path.starts_with(p))
+        })
+        .collect();
+    Value::Array(operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing;
+    use serde_json::json;
+
+    fn update_request(kind: &str, container: Option<&str>) -> UpdateRequest {
+        let mut update_request = testing::update_request("default", "web-app-1-2-0");
+        update_request.spec.target_ref.kind = kind.to_string();
+        update_request.spec.container_name = container.map(String::from);
+        update_request.spec.current_image = "registry.io/app:1.1.0".to_string();
+        update_request.spec.new_image = "registry.io/app:1.2.0".to_string();
+        update_request
+    }
+
+    fn workload(kind: &str, annotations: Value) -> Value {
+        json!({
+            "apiVersion": "apps/v1",
+            "kind": kind,
+            "metadata": { "name": "web", "namespace": "default", "annotations": annotations },
+            "spec": {
+                "selector": { "matchLabels": { "app": "web" } },
+                "serviceName": "web",
+                "template": {
+                    "metadata": { "labels": { "app": "web" } },
+                    "spec": {
+                        "containers": [
+                            { "name": "app", "image": "registry.io/app:1.1.0" },
+                            { "name": "proxy", "image": "envoy:1.30.0" }
+                        ]
+                    }
+                }
+            }
+        })
+    }
+
+    /// Apply a strategic merge patch the way the API server does for the
+    /// fields headwind patches: objects merge, `containers` merge by name
+    fn strategic_merge(target: &mut Value, patch: &Value) {
+        match (target, patch) {
+            (Value::Object(target), Value::Object(patch)) => {
+                for (key, value) in patch {
+                    if value.is_null() {
+                        target.remove(key);
+                    } else if key == "containers" {
+                        let containers = target.entry(key).or_insert_with(|| json!([]));
+                        for patched in value.as_array().unwrap() {
+                            let existing = containers
+                                .as_array_mut()
+                                .unwrap()
+                                .iter_mut()
+                                .find(|c| c["name"] == patched["name"]);
+                            match existing {
+                                Some(existing) => strategic_merge(existing, patched),
+                                None => containers.as_array_mut().unwrap().push(patched.clone()),
+                            }
+                        }
+                    } else {
+                        strategic_merge(target.entry(key).or_insert(Value::Null), value);
+                    }
+                }
+            },
+            (target, patch) => *target = patch.clone(),
+        }
+    }
+
+    /// Apply `patch` and check the result still deserializes as `K` with the
+    /// new image on the `app` container only
+    fn assert_applies<K: serde::de::DeserializeOwned>(resource: &Value, patch: &Value) -> Value {
+        assert!(patch["spec"]["template"]["spec"]["containers"].is_array());
+
+        let mut patched = resource.clone();
+        strategic_merge(&mut patched, patch);
+        serde_json::from_value::<K>(patched.clone()).expect("patched resource is valid");
+
+        let containers = &patched["spec"]["template"]["spec"]["containers"];
+        assert_eq!(containers.as_array().unwrap().len(), 2);
+        assert_eq!(containers[0]["image"], "registry.io/app:1.2.0");
+        assert_eq!(containers[1]["image"], "envoy:1.30.0");
+
+        // The replaced image is kept for manual rollback
+        assert_eq!(
+            patched["metadata"]["annotations"]["headwind.sh/previous-image"],
+            "registry.io/app:1.1.0"
+        );
+        patched
+    }
+
+    #[test]
+    fn test_deployment_patch_is_valid() {
+        let resource = workload("Deployment", json!({}));
+        let deployment: Deployment = serde_json::from_value(resource.clone()).unwrap();
+        let patch = build_preview_patch(
+            &update_request("Deployment", Some("app")),
+            &PreviewTarget::Deployment(deployment),
+            "2026-01-01T00:00:00Z",
+        )
+        .unwrap();
+
+        assert_applies::<Deployment>(&resource, &patch);
+    }
+
+    #[test]
+    fn test_deployment_patch_requires_known_container() {
+        let deployment: Deployment =
+            serde_json::from_value(workload("Deployment", json!({}))).unwrap();
+
+        for container in [None, Some("missing")] {
+            assert!(
+                build_preview_patch(
+                    &update_request("Deployment", container),
+                    &PreviewTarget::Deployment(deployment.clone()),
+                    "2026-01-01T00:00:00Z",
+                )
+                .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_statefulset_patch_is_valid() {
+        let resource = workload("StatefulSet", json!({}));
+        let statefulset: StatefulSet = serde_json::from_value(resource.clone()).unwrap();
+        let patch = build_preview_patch(
+            &update_request("StatefulSet", None),
+            &PreviewTarget::StatefulSet(statefulset),
+            "2026-01-01T00:00:00Z",
+        )
+        .unwrap();
+
+        let patched = assert_applies::<StatefulSet>(&resource, &patch);
+        assert_eq!(
+            patched["metadata"]["annotations"]["headwind.sh/last-update"],
+            "2026-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_daemonset_patch_is_valid() {
+        let resource = workload(
+            "DaemonSet",
+            json!({ "headwind.sh/rollout-pause-between-steps": "30" }),
+        );
+        let daemonset: DaemonSet = serde_json::from_value(resource.clone()).unwrap();
+        let patch = build_preview_patch(
+            &update_request("DaemonSet", None),
+            &PreviewTarget::DaemonSet(daemonset),
+            "2026-01-01T00:00:00Z",
+        )
+        .unwrap();
+
+        let patched = assert_applies::<DaemonSet>(&resource, &patch);
+        assert_eq!(patched["spec"]["updateStrategy"]["type"], "OnDelete");
+    }
+
+    #[test]
+    fn test_resource_diff_ignores_volatile_metadata() {
+        let before = json!({
+            "metadata": { "resourceVersion": "1", "generation": 1 },
+            "spec": { "image": "app:1.1.0" }
+        });
+        let after = json!({
+            "metadata": { "resourceVersion": "2", "generation": 2 },
+            "spec": { "image": "app:1.2.0" }
+        });
+
+        assert_eq!(
+            resource_diff(&before, &after),
+            json!([{ "op": "replace", "path": "/spec/image", "value": "app:1.2.0" }])
+        );
+    }
+}
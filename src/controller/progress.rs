@@ -0,0 +1,271 @@
+//! Step-by-step progress of UpdateRequests, recorded in `status.progressSteps`.
+//!
+//! The controller records the detection steps when it creates an
+//! UpdateRequest, the approval flow adds the patch and notification steps, and
+//! the rollout health monitor adds its result once the rollout settles.
+
+use crate::config::features::FeatureFlags;
+use crate::metrics::STEP_DURATION_SECONDS;
+use crate::models::crd::{ProgressStep, StepStatus, UpdateRequest};
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use kube::Api;
+use kube::api::{Patch, PatchParams};
+use kube::client::Client;
+use tracing::debug;
+
+pub const POLICY_EVALUATION: &str = "PolicyEvaluation";
+pub const SIGNATURE_VERIFICATION: &str = "SignatureVerification";
+pub const VULNERABILITY_CHECK: &str = "VulnerabilityCheck";
+pub const KUBERNETES_PATCH: &str = "KubernetesPatch";
+pub const ROLLOUT_HEALTH: &str = "RolloutHealth";
+pub const NOTIFICATION_SENT: &str = "NotificationSent";
+
+/// Attempts at recording a step before giving up on conflicts
+const RECORD_STEP_ATTEMPTS: usize = 3;
+
+impl ProgressStep {
+    /// A step starting now
+    pub fn start(step: &str) -> Self {
+        Self {
+            step: step.to_string(),
+            status: StepStatus::Running,
+            started_at: Utc::now(),
+            completed_at: None,
+            message: None,
+        }
+    }
+
+    pub fn succeed(self, message: Option<String>) -> Self {
+        self.finish(StepStatus::Succeeded, message)
+    }
+
+    pub fn fail(self, message: impl Into<String>) -> Self {
+        self.finish(StepStatus::Failed, Some(message.into()))
+    }
+
+    /// A step that was not run, with the reason
+    pub fn skipped(step: &str, reason: impl Into<String>) -> Self {
+        Self::start(step).finish(StepStatus::Skipped, Some(reason.into()))
+    }
+
+    fn finish(mut self, status: StepStatus, message: Option<String>) -> Self {
+        let completed_at = Utc::now();
+        if status != StepStatus::Skipped {
+            let duration = (completed_at - self.started_at).num_milliseconds().max(0);
+            STEP_DURATION_SECONDS
+                .with_label_values(&[&self.step])
+                .observe(duration as f64 / 1000.0);
+        }
+        self.status = status;
+        self.completed_at = Some(completed_at);
+        self.message = message;
+        self
+    }
+}
+
+/// Steps recorded when an update is detected: the policy evaluation and the
+/// image checks enabled with feature flags.
+///
+/// Signature verification and vulnerability scanning are reserved flags with
+/// no checks behind them yet, so they are recorded as skipped.
+pub fn detection_steps(
+    policy_evaluation: ProgressStep,
+    features: &FeatureFlags,
+) -> Vec<ProgressStep> {
+    let mut steps = vec![policy_evaluation];
+    if features.cosign_verification {
+        steps.push(ProgressStep::skipped(
+            SIGNATURE_VERIFICATION,
+            "No signature verifier is available in this version",
+        ));
+    }
+    if features.trivy_scanning {
+        steps.push(ProgressStep::skipped(
+            VULNERABILITY_CHECK,
+            "No vulnerability scanner is available in this version",
+        ));
+    }
+    steps
+}
+
+/// Add `step` to `steps`, replacing an earlier entry of the same step
+pub fn upsert_step(steps: &mut Vec<ProgressStep>, step: ProgressStep) {
+    match steps.iter_mut().find(|s| s.step == step.step) {
+        Some(existing) => *existing = step,
+        None => steps.push(step),
+    }
+}
+
+/// Record `steps` in an UpdateRequest's status, replacing earlier entries of
+/// the same steps.
+///
+/// Like the rollback history, the whole list is written back with the
+/// resourceVersion it was read at and retried on conflicts.
+pub async fn record_steps(
+    client: &Client,
+    namespace: &str,
+    update_request_name: &str,
+    steps: &[ProgressStep],
+) -> Result<()> {
+    let api: Api<UpdateRequest> = Api::namespaced(client.clone(), namespace);
+
+    for attempt in 1..=RECORD_STEP_ATTEMPTS {
+        let update_request = api.get_status(update_request_name).await.with_context(|| {
+            format!(
+                "Failed to get UpdateRequest {}/{}",
+                namespace, update_request_name
+            )
+        })?;
+
+        let mut progress = update_request
+            .status
+            .map(|status| status.progress_steps)
+            .unwrap_or_default();
+        for step in steps {
+            upsert_step(&mut progress, step.clone());
+        }
+
+        let patch = serde_json::json!({
+            "metadata": {
+                "resourceVersion": update_request.metadata.resource_version,
+            },
+            "status": {
+                "progressSteps": progress,
+            },
+        });
+
+        match api
+            .patch_status(
+                update_request_name,
+                &PatchParams::default(),
+                &Patch::Merge(&patch),
+            )
+            .await
+        {
+            Ok(_) => return Ok(()),
+            Err(kube::Error::Api(e)) if e.code == 409 && attempt < RECORD_STEP_ATTEMPTS => {
+                debug!(
+                    "Conflict recording progress on UpdateRequest {}/{}, retrying",
+                    namespace, update_request_name
+                );
+            },
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Failed to record progress on UpdateRequest {}/{}",
+                        namespace, update_request_name
+                    )
+                });
+            },
+        }
+    }
+
+    Err(anyhow!(
+        "Failed to record progress on UpdateRequest {}/{}: too many conflicts",
+        namespace,
+        update_request_name
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_features() -> FeatureFlags {
+        FeatureFlags {
+            cosign_verification: true,
+            trivy_scanning: true,
+            ..Default::default()
+        }
+    }
+
+    fn names(steps: &[ProgressStep]) -> Vec<&str> {
+        steps.iter().map(|s| s.step.as_str()).collect()
+    }
+
+    #[test]
+    fn test_successful_update_records_every_step() {
+        let policy = ProgressStep::start(POLICY_EVALUATION).succeed(None);
+        let mut steps = detection_steps(policy, &all_features());
+
+        // Approval: the patch is applied and notifications go out
+        let rollout = ProgressStep::start(ROLLOUT_HEALTH);
+        upsert_step(
+            &mut steps,
+            ProgressStep::start(KUBERNETES_PATCH).succeed(None),
+        );
+        upsert_step(
+            &mut steps,
+            ProgressStep::start(NOTIFICATION_SENT).succeed(None),
+        );
+        // The health monitor reports back once the rollout is healthy
+        upsert_step(&mut steps, rollout.succeed(Some("Healthy".to_string())));
+
+        assert_eq!(
+            names(&steps),
+            vec![
+                POLICY_EVALUATION,
+                SIGNATURE_VERIFICATION,
+                VULNERABILITY_CHECK,
+                KUBERNETES_PATCH,
+                NOTIFICATION_SENT,
+                ROLLOUT_HEALTH,
+            ]
+        );
+        let statuses: Vec<StepStatus> = steps.iter().map(|s| s.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                StepStatus::Succeeded,
+                StepStatus::Skipped,
+                StepStatus::Skipped,
+                StepStatus::Succeeded,
+                StepStatus::Succeeded,
+                StepStatus::Succeeded,
+            ]
+        );
+        assert!(steps.iter().all(|s| s.completed_at.is_some()));
+        assert!(
+            steps
+                .iter()
+                .all(|s| s.completed_at.unwrap() >= s.started_at)
+        );
+    }
+
+    #[test]
+    fn test_disabled_checks_are_not_recorded() {
+        let policy = ProgressStep::start(POLICY_EVALUATION).succeed(None);
+        let steps = detection_steps(policy, &FeatureFlags::default());
+        assert_eq!(names(&steps), vec![POLICY_EVALUATION]);
+    }
+
+    #[test]
+    fn test_finished_steps_are_observed() {
+        let before = STEP_DURATION_SECONDS
+            .with_label_values(&[KUBERNETES_PATCH])
+            .get_sample_count();
+        let step = ProgressStep::start(KUBERNETES_PATCH).fail("deployments.apps \"web\" not found");
+        assert_eq!(step.status, StepStatus::Failed);
+        assert_eq!(
+            step.message.as_deref(),
+            Some("deployments.apps \"web\" not found")
+        );
+        assert_eq!(
+            STEP_DURATION_SECONDS
+                .with_label_values(&[KUBERNETES_PATCH])
+                .get_sample_count(),
+            before + 1
+        );
+    }
+
+    #[test]
+    fn test_steps_serialize_in_camel_case() {
+        let step = ProgressStep::skipped(SIGNATURE_VERIFICATION, "disabled");
+        let json = serde_json::to_value(&step).unwrap();
+        assert_eq!(json["step"], "SignatureVerification");
+        assert_eq!(json["status"], "Skipped");
+        assert!(json["startedAt"].is_string());
+        assert!(json["completedAt"].is_string());
+    }
+}
@@ -0,0 +1,373 @@
+//! ResourceQuota check before rolling out a new image.
+//!
+//! A rolling update starts up to `maxSurge` extra pods before old ones are
+//! removed. With `headwind.sh/check-quota: "true"`, the Deployment's pod
+//! template requests are multiplied by the surge and compared with the room
+//! left in every ResourceQuota of the namespace.
+
+use anyhow::Result;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::{PodSpec, ResourceQuota};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{Api, ListParams};
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use kube::{Client, Resource, ResourceExt};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+/// Reason of the warning event emitted when an update waits for quota
+pub const QUOTA_INSUFFICIENT_REASON: &str = "QuotaInsufficient";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuotaCheckResult {
+    Sufficient,
+    Insufficient { reason: String },
+}
+
+/// Compare the extra usage of rolling out `deployment` with the room left in
+/// the ResourceQuotas of `namespace`
+pub async fn check_resource_quota(
+    client: &Client,
+    namespace: &str,
+    deployment: &Deployment,
+) -> Result<QuotaCheckResult> {
+    let api: Api<ResourceQuota> = Api::namespaced(client.clone(), namespace);
+    let quotas = api.list(&ListParams::default()).await?.items;
+    Ok(evaluate_quotas(&quotas, deployment))
+}
+
+/// `check_resource_quota` on already fetched quotas
+pub fn evaluate_quotas(quotas: &[ResourceQuota], deployment: &Deployment) -> QuotaCheckResult {
+    let surge = surge_pods(deployment);
+    let Some(pod_spec) = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.spec.as_ref())
+    else {
+        return QuotaCheckResult::Sufficient;
+    };
+    if surge == 0 {
+        return QuotaCheckResult::Sufficient;
+    }
+
+    for quota in quotas {
+        // Scoped quotas only count some pods (e.g. BestEffort); not evaluated
+        if quota.spec.as_ref().is_some_and(|spec| {
+            spec.scopes.as_ref().is_some_and(|s| !s.is_empty()) || spec.scope_selector.is_some()
+        }) {
+            continue;
+        }
+
+        let Some(status) = &quota.status else {
+            continue;
+        };
+        let (Some(hard), used) = (&status.hard, status.used.clone().unwrap_or_default()) else {
+            continue;
+        };
+
+        for (resource, limit) in hard {
+            let Some(per_pod) = pod_usage(pod_spec, resource) else {
+                continue;
+            };
+            let (Some(limit), used) = (
+                parse_quantity(&limit.0),
+                used.get(resource)
+                    .and_then(|q| parse_quantity(&q.0))
+                    .unwrap_or(0.0),
+            ) else {
+                continue;
+            };
+
+            let needed = per_pod * f64::from(surge);
+            let available = (limit - used).max(0.0);
+            if needed > available {
+                return QuotaCheckResult::Insufficient {
+                    reason: format!(
+                        "ResourceQuota {} has {} {} left, the rolling update needs {} for {} surge pod(s)",
+                        quota.name_any(),
+                        format_amount(available),
+                        resource,
+                        format_amount(needed),
+                        surge
+                    ),
+                };
+            }
+        }
+    }
+
+    QuotaCheckResult::Sufficient
+}
+
+/// Pods started on top of the desired replicas during a rolling update
+/// (`maxSurge`, 25% rounded up by default, none for `Recreate`)
+pub fn surge_pods(deployment: &Deployment) -> u32 {
+    let Some(spec) = &deployment.spec else {
+        return 0;
+    };
+    let replicas = spec.replicas.unwrap_or(1).max(0) as u32;
+    let strategy = spec.strategy.as_ref();
+    if strategy.and_then(|s| s.type_.as_deref()) == Some("Recreate") {
+        return 0;
+    }
+
+    let max_surge = strategy
+        .and_then(|s| s.rolling_update.as_ref())
+        .and_then(|r| r.max_surge.clone())
+        .unwrap_or_else(|| IntOrString::String("25%".to_string()));
+    match max_surge {
+        IntOrString::Int(n) => n.max(0) as u32,
+        IntOrString::String(s) => s
+            .strip_suffix('%')
+            .and_then(|p| p.parse::<u32>().ok())
+            .map(|percent| (replicas * percent).div_ceil(100))
+            .unwrap_or(0),
+    }
+}
+
+/// Usage of one pod for the quota resource `resource` (`pods`, `cpu`,
+/// `requests.memory`, `limits.cpu`, ...), None for resources that don't
+/// depend on pods
+fn pod_usage(pod_spec: &PodSpec, resource: &str) -> Option<f64> {
+    if resource == "pods" || resource == "count/pods" {
+        return Some(1.0);
+    }
+
+    let (limits, name) = match resource.split_once('.') {
+        Some(("limits", name)) => (true, name),
+        Some(("requests", name)) => (false, name),
+        Some(_) => return None,
+        None => (false, resource),
+    };
+    if !matches!(name, "cpu" | "memory" | "ephemeral-storage") {
+        return None;
+    }
+
+    let total = pod_spec
+        .containers
+        .iter()
+        .filter_map(|c| c.resources.as_ref())
+        .filter_map(|r| {
+            let get = |map: &Option<BTreeMap<String, Quantity>>| {
+                map.as_ref()
+                    .and_then(|m| m.get(name))
+                    .and_then(|q| parse_quantity(&q.0))
+            };
+            // Requests default to limits when only limits are set
+            if limits {
+                get(&r.limits)
+            } else {
+                get(&r.requests).or_else(|| get(&r.limits))
+            }
+        })
+        .sum();
+    Some(total)
+}
+
+/// Value of a Kubernetes quantity such as `500m`, `2Gi` or `1e3` in base units
+pub fn parse_quantity(quantity: &str) -> Option<f64> {
+    let quantity = quantity.trim();
+    let split = quantity
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(quantity.len());
+    let (number, suffix) = quantity.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier = match suffix {
+        "" => 1.0,
+        "n" => 1e-9,
+        "u" => 1e-6,
+        "m" => 1e-3,
+        "k" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "E" => 1e18,
+        "Ki" => 1024.0,
+        "Mi" => 1024f64.powi(2),
+        "Gi" => 1024f64.powi(3),
+        "Ti" => 1024f64.powi(4),
+        "Pi" => 1024f64.powi(5),
+        "Ei" => 1024f64.powi(6),
+        exponent => 10f64.powi(
+            exponent
+                .strip_prefix(['e', 'E'])
+                .and_then(|e| e.parse().ok())?,
+        ),
+    };
+    Some(number * multiplier)
+}
+
+fn format_amount(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value)
+    } else {
+        format!("{:.3}", value)
+    }
+}
+
+/// Emit a `QuotaInsufficient` warning event on `deployment`
+pub async fn publish_quota_insufficient(client: &Client, deployment: &Deployment, reason: &str) {
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    let event = Event {
+        type_: EventType::Warning,
+        reason: QUOTA_INSUFFICIENT_REASON.to_string(),
+        note: Some(reason.to_string()),
+        action: "UpdateImage".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(&event, &deployment.object_ref(&())).await {
+        warn!(
+            "Failed to publish {} event for {}/{}: {}",
+            QUOTA_INSUFFICIENT_REASON,
+            deployment.namespace().unwrap_or_default(),
+            deployment.name_any(),
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn deployment(replicas: i32, strategy: serde_json::Value) -> Deployment {
+        serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "web", "namespace": "default" },
+            "spec": {
+                "replicas": replicas,
+                "strategy": strategy,
+                "selector": { "matchLabels": { "app": "web" } },
+                "template": {
+                    "metadata": { "labels": { "app": "web" } },
+                    "spec": {
+                        "containers": [
+                            {
+                                "name": "app",
+                                "image": "example/app:1.0.0",
+                                "resources": {
+                                    "requests": { "cpu": "250m", "memory": "256Mi" },
+                                    "limits": { "cpu": "500m", "memory": "512Mi" }
+                                }
+                            },
+                            {
+                                "name": "sidecar",
+                                "image": "example/proxy:1.0.0",
+                                "resources": { "limits": { "cpu": "100m", "memory": "64Mi" } }
+                            }
+                        ]
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn quota(hard: serde_json::Value, used: serde_json::Value) -> ResourceQuota {
+        serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "ResourceQuota",
+            "metadata": { "name": "compute", "namespace": "default" },
+            "spec": { "hard": hard },
+            "status": { "hard": hard, "used": used }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_quantity("2"), Some(2.0));
+        assert_eq!(parse_quantity("500m"), Some(0.5));
+        assert_eq!(parse_quantity("1.5"), Some(1.5));
+        assert_eq!(parse_quantity("64Mi"), Some(64.0 * 1024.0 * 1024.0));
+        assert_eq!(parse_quantity("1G"), Some(1e9));
+        assert_eq!(parse_quantity("1e3"), Some(1000.0));
+        assert_eq!(parse_quantity("lots"), None);
+        assert_eq!(parse_quantity("1Zi"), None);
+    }
+
+    #[test]
+    fn test_surge_pods() {
+        assert_eq!(surge_pods(&deployment(4, json!({}))), 1);
+        assert_eq!(surge_pods(&deployment(10, json!({}))), 3);
+        assert_eq!(
+            surge_pods(&deployment(
+                4,
+                json!({ "type": "RollingUpdate", "rollingUpdate": { "maxSurge": 2 } })
+            )),
+            2
+        );
+        assert_eq!(
+            surge_pods(&deployment(
+                4,
+                json!({ "type": "RollingUpdate", "rollingUpdate": { "maxSurge": "50%" } })
+            )),
+            2
+        );
+        assert_eq!(surge_pods(&deployment(4, json!({ "type": "Recreate" }))), 0);
+    }
+
+    #[test]
+    fn test_sufficient_quota() {
+        // One surge pod needs 350m CPU (250m + sidecar limit 100m) and 1 pod
+        let deployment = deployment(4, json!({}));
+        let quotas = [quota(
+            json!({ "requests.cpu": "2", "pods": "10", "services": "5" }),
+            json!({ "requests.cpu": "1400m", "pods": "4", "services": "5" }),
+        )];
+        assert_eq!(
+            evaluate_quotas(&quotas, &deployment),
+            QuotaCheckResult::Sufficient
+        );
+        assert_eq!(
+            evaluate_quotas(&[], &deployment),
+            QuotaCheckResult::Sufficient
+        );
+    }
+
+    #[test]
+    fn test_insufficient_quota() {
+        let web = deployment(4, json!({}));
+
+        let quotas = [quota(json!({ "pods": "4" }), json!({ "pods": "4" }))];
+        assert_eq!(
+            evaluate_quotas(&quotas, &web),
+            QuotaCheckResult::Insufficient {
+                reason: "ResourceQuota compute has 0 pods left, the rolling update needs 1 for 1 surge pod(s)"
+                    .to_string()
+            }
+        );
+
+        let quotas = [quota(
+            json!({ "limits.cpu": "3" }),
+            json!({ "limits.cpu": "2800m" }),
+        )];
+        let QuotaCheckResult::Insufficient { reason } = evaluate_quotas(&quotas, &web) else {
+            panic!("expected insufficient quota");
+        };
+        assert!(reason.contains("0.200 limits.cpu left"), "{}", reason);
+        assert!(reason.contains("needs 0.600"), "{}", reason);
+
+        // Recreate doesn't surge
+        let recreate = deployment(4, json!({ "type": "Recreate" }));
+        assert_eq!(
+            evaluate_quotas(&quotas, &recreate),
+            QuotaCheckResult::Sufficient
+        );
+    }
+
+    #[test]
+    fn test_scoped_quotas_are_skipped() {
+        let deployment = deployment(4, json!({}));
+        let mut scoped = quota(json!({ "pods": "4" }), json!({ "pods": "4" }));
+        scoped.spec.as_mut().unwrap().scopes = Some(vec!["BestEffort".to_string()]);
+        assert_eq!(
+            evaluate_quotas(&[scoped], &deployment),
+            QuotaCheckResult::Sufficient
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Rate limit on UpdateRequest creation per resource.
+//!
+//! A misconfigured registry webhook can push the same resource many times a
+//! second. Each resource may create at most `HEADWIND_UPDATE_REQUEST_RATE_LIMIT`
+//! UpdateRequests (default `5/3600`, five per hour), overridden per resource
+//! with `headwind.sh/update-rate-limit`. Updates over the limit are dropped;
+//! the next push or poll after the window has room proposes them again.
+
+use crate::metrics::UPDATE_REQUEST_RATE_LIMITED_TOTAL;
+use crate::models::annotations;
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const RATE_LIMIT_ENV: &str = "HEADWIND_UPDATE_REQUEST_RATE_LIMIT";
+
+/// At most `count` UpdateRequests per `period`, written `count/seconds`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub count: usize,
+    pub period: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            count: 5,
+            period: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid rate limit {:?}, expected COUNT/SECONDS", s);
+        let (count, seconds) = s.trim().split_once('/').ok_or_else(invalid)?;
+        let count: usize = count.trim().parse().map_err(|_| invalid())?;
+        let seconds: u64 = seconds.trim().parse().map_err(|_| invalid())?;
+        if count == 0 || seconds == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            count,
+            period: Duration::from_secs(seconds),
+        })
+    }
+}
+
+/// Resource an UpdateRequest is created for: kind, namespace and name
+type ResourceKey = (String, String, String);
+
+/// Creation times of recent UpdateRequests of one resource
+struct Window {
+    period: Duration,
+    created: VecDeque<Instant>,
+}
+
+/// Sliding window limiter counting UpdateRequests per resource
+pub struct UpdateRequestRateLimiter {
+    default_limit: RateLimit,
+    windows: Mutex<HashMap<ResourceKey, Window>>,
+}
+
+impl UpdateRequestRateLimiter {
+    pub fn new(default_limit: RateLimit) -> Self {
+        Self {
+            default_limit,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Default limit from `HEADWIND_UPDATE_REQUEST_RATE_LIMIT`
+    pub fn from_env() -> Self {
+        let limit = match std::env::var(RATE_LIMIT_ENV) {
+            Ok(value) if !value.is_empty() => value.parse().unwrap_or_else(|e| {
+                warn!("{}: {}, using the default", RATE_LIMIT_ENV, e);
+                RateLimit::default()
+            }),
+            _ => RateLimit::default(),
+        };
+        Self::new(limit)
+    }
+
+    /// Limit of a resource, `headwind.sh/update-rate-limit` if set and valid
+    pub fn limit_for(&self, resource_annotations: Option<&BTreeMap<String, String>>) -> RateLimit {
+        let Some(value) = resource_annotations.and_then(|a| a.get(annotations::UPDATE_RATE_LIMIT))
+        else {
+            return self.default_limit;
+        };
+        value.parse().unwrap_or_else(|e| {
+            warn!(
+                "{}: {}, using the default",
+                annotations::UPDATE_RATE_LIMIT,
+                e
+            );
+            self.default_limit
+        })
+    }
+
+    /// Record an UpdateRequest for the resource at `now` if `limit` allows one
+    pub fn try_acquire_at(
+        &self,
+        kind: &str,
+        namespace: &str,
+        name: &str,
+        limit: RateLimit,
+        now: Instant,
+    ) -> bool {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Forget resources with no UpdateRequests left in their window
+        windows.retain(|_, w| {
+            w.created
+                .back()
+                .is_some_and(|last| now.saturating_duration_since(*last) < w.period)
+        });
+
+        let window = windows
+            .entry((kind.to_string(), namespace.to_string(), name.to_string()))
+            .or_insert_with(|| Window {
+                period: limit.period,
+                created: VecDeque::new(),
+            });
+        window.period = limit.period;
+        while window
+            .created
+            .front()
+            .is_some_and(|first| now.saturating_duration_since(*first) >= limit.period)
+        {
+            window.created.pop_front();
+        }
+
+        if window.created.len() >= limit.count {
+            return false;
+        }
+        window.created.push_back(now);
+        true
+    }
+
+    /// Whether another UpdateRequest may be created for the resource. Dropped
+    /// requests are logged and counted in `UPDATE_REQUEST_RATE_LIMITED_TOTAL`.
+    pub fn check(
+        &self,
+        kind: &str,
+        namespace: &str,
+        name: &str,
+        resource_annotations: Option<&BTreeMap<String, String>>,
+    ) -> bool {
+        let limit = self.limit_for(resource_annotations);
+        if self.try_acquire_at(kind, namespace, name, limit, Instant::now()) {
+            return true;
+        }
+
+        warn!(
+            "Not creating UpdateRequest for {} {}/{}: rate limit of {} per {}s exceeded",
+            kind,
+            namespace,
+            name,
+            limit.count,
+            limit.period.as_secs()
+        );
+        UPDATE_REQUEST_RATE_LIMITED_TOTAL.inc();
+        false
+    }
+}
+
+static LIMITER: Lazy<UpdateRequestRateLimiter> = Lazy::new(UpdateRequestRateLimiter::from_env);
+
+/// Whether another UpdateRequest may be created for the resource, using the
+/// process-wide limiter
+pub fn allow_update_request(
+    kind: &str,
+    namespace: &str,
+    name: &str,
+    resource_annotations: Option<&BTreeMap<String, String>>,
+) -> bool {
+    LIMITER.check(kind, namespace, name, resource_annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limit() {
+        assert_eq!(
+            "2/3600".parse::<RateLimit>().unwrap(),
+            RateLimit {
+                count: 2,
+                period: Duration::from_secs(3600)
+            }
+        );
+        assert_eq!(" 10 / 60 ".parse::<RateLimit>().unwrap().count, 10);
+        for invalid in ["5", "0/3600", "5/0", "five/3600", "5/-1", ""] {
+            assert!(invalid.parse::<RateLimit>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_sixth_request_within_an_hour_is_dropped() {
+        let limiter = UpdateRequestRateLimiter::new(RateLimit::default());
+        let before = UPDATE_REQUEST_RATE_LIMITED_TOTAL.get();
+
+        for _ in 0..5 {
+            assert!(limiter.check("Deployment", "prod", "flooded", None));
+        }
+        assert!(!limiter.check("Deployment", "prod", "flooded", None));
+        assert!(UPDATE_REQUEST_RATE_LIMITED_TOTAL.get() > before);
+
+        // Other resources have their own budget
+        assert!(limiter.check("Deployment", "prod", "quiet", None));
+        assert!(limiter.check("StatefulSet", "prod", "flooded", None));
+    }
+
+    #[test]
+    fn test_window_slides() {
+        let limiter = UpdateRequestRateLimiter::new(RateLimit::default());
+        let limit = "2/3600".parse().unwrap();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        assert!(limiter.try_acquire_at("Deployment", "prod", "web", limit, at(0)));
+        assert!(limiter.try_acquire_at("Deployment", "prod", "web", limit, at(1000)));
+        assert!(!limiter.try_acquire_at("Deployment", "prod", "web", limit, at(3599)));
+        // The first UpdateRequest left the window
+        assert!(limiter.try_acquire_at("Deployment", "prod", "web", limit, at(3600)));
+        assert!(!limiter.try_acquire_at("Deployment", "prod", "web", limit, at(4000)));
+    }
+
+    #[test]
+    fn test_annotation_override() {
+        let limiter = UpdateRequestRateLimiter::new(RateLimit::default());
+        let annotations = BTreeMap::from([(
+            annotations::UPDATE_RATE_LIMIT.to_string(),
+            "1/60".to_string(),
+        )]);
+        assert_eq!(
+            limiter.limit_for(Some(&annotations)),
+            RateLimit {
+                count: 1,
+                period: Duration::from_secs(60)
+            }
+        );
+
+        assert!(limiter.check("Deployment", "prod", "web", Some(&annotations)));
+        assert!(!limiter.check("Deployment", "prod", "web", Some(&annotations)));
+
+        let invalid = BTreeMap::from([(
+            annotations::UPDATE_RATE_LIMIT.to_string(),
+            "often".to_string(),
+        )]);
+        assert_eq!(limiter.limit_for(Some(&invalid)), RateLimit::default());
+    }
+}
@@ -0,0 +1,648 @@
+//! Controller for standalone ReplicaSets.
+//!
+//! Some legacy workloads create ReplicaSets directly instead of through a
+//! Deployment. Those are handled here like any other workload. ReplicaSets
+//! owned by a Deployment are skipped: their image is updated through the
+//! Deployment, which then rolls out a new ReplicaSet.
+//!
+//! A ReplicaSet does not replace running pods when its template changes, so an
+//! update only reaches pods created after it is applied.
+
+use super::statefulset::{map_policy_to_crd, parse_policy_from_annotations};
+use crate::metrics::{RECONCILE_DURATION, RECONCILE_ERRORS};
+use crate::models::{
+    ResourcePolicy, TargetRef, UpdateRequest, UpdateRequestSpec, UpdateType, annotations,
+};
+use crate::notifications::{self, DeploymentInfo};
+use crate::policy::PolicyEngine;
+use crate::policy::ignore::is_resource_ignored;
+use crate::schedule::PendingUpdate;
+use anyhow::Result;
+use chrono::Utc;
+use futures::StreamExt;
+use k8s_openapi::api::apps::v1::ReplicaSet;
+use kube::{
+    ResourceExt,
+    api::{Api, Patch, PatchParams, PostParams},
+    client::Client,
+    runtime::controller::Action,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument};
+
+/// Whether `replicaset` is managed directly rather than by a Deployment
+pub fn is_standalone(replicaset: &ReplicaSet) -> bool {
+    !replicaset
+        .owner_references()
+        .iter()
+        .any(|owner| owner.kind == "Deployment")
+}
+
+pub struct ReplicaSetController {
+    client: Client,
+    policy_engine: Arc<PolicyEngine>,
+}
+
+impl ReplicaSetController {
+    pub async fn new() -> Result<Self> {
+        let client = Client::try_default().await?;
+        let policy_engine = Arc::new(PolicyEngine);
+
+        Ok(Self {
+            client,
+            policy_engine,
+        })
+    }
+
+    pub async fn run(self) {
+        info!("ReplicaSet controller starting...");
+
+        // Run the controller in a loop with exponential backoff
+        // This handles transient errors during startup or runtime
+        let mut backoff_seconds = 1;
+        const MAX_BACKOFF: u64 = 60;
+
+        loop {
+            let replicasets: Api<ReplicaSet> = super::watched_api(self.client.clone());
+
+            info!("Creating controller for replicasets");
+
+            let result = super::queue_metrics::controller(replicasets, "replicaset")
+                .run(
+                    reconcile,
+                    error_policy,
+                    Arc::new(ControllerContext {
+                        client: self.client.clone(),
+                        policy_engine: self.policy_engine.clone(),
+                    }),
+                )
+                .for_each(|res| async move {
+                    match res {
+                        Ok((obj_ref, _action)) => {
+                            debug!(
+                                "Reconciled replicaset: {}/{}",
+                                obj_ref.namespace.as_deref().unwrap_or("default"),
+                                obj_ref.name
+                            );
+                        },
+                        Err(e) => {
+                            // Log reconciliation errors but continue processing
+                            error!("Reconciliation error: {}", e);
+                            RECONCILE_ERRORS.inc();
+                        },
+                    }
+                })
+                .await;
+
+            // If the controller stream ends, log it and restart after backoff
+            error!(
+                "ReplicaSet controller stream ended, restarting in {}s...",
+                backoff_seconds
+            );
+            tokio::time::sleep(Duration::from_secs(backoff_seconds)).await;
+
+            // Exponential backoff up to MAX_BACKOFF seconds
+            backoff_seconds = (backoff_seconds * 2).min(MAX_BACKOFF);
+
+            debug!("Controller loop result: {:?}", result);
+        }
+    }
+}
+
+struct ControllerContext {
+    #[allow(dead_code)]
+    client: Client,
+    #[allow(dead_code)]
+    policy_engine: Arc<PolicyEngine>,
+}
+
+#[instrument(skip(_ctx), fields(replicaset = %replicaset.name_any()))]
+async fn reconcile(
+    replicaset: Arc<ReplicaSet>,
+    _ctx: Arc<ControllerContext>,
+) -> Result<Action, kube::Error> {
+    let _timer = RECONCILE_DURATION.start_timer();
+    let _queue_timer = super::queue_metrics::start_reconcile("replicaset", replicaset.as_ref());
+    let _in_flight = super::concurrency::track("replicaset");
+
+    let namespace = replicaset.namespace().unwrap_or_default();
+    let name = replicaset.name_any();
+
+    // Deployment-owned ReplicaSets change whenever the Deployment rolls out
+    if !is_standalone(&replicaset) {
+        return Ok(Action::await_change());
+    }
+
+    let Some(annotations) = replicaset.metadata.annotations.as_ref() else {
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    };
+    if is_resource_ignored(annotations) {
+        debug!("ReplicaSet {}/{} is ignored, skipping", namespace, name);
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    }
+    if !annotations.contains_key(annotations::POLICY) {
+        debug!(
+            "ReplicaSet {}/{} has no headwind policy annotation, skipping",
+            namespace, name
+        );
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    }
+
+    let policy = parse_policy_from_annotations(annotations).map_err(|e| {
+        error!(
+            "Failed to parse policy for replicaset {}/{}: {}",
+            namespace, name, e
+        );
+        create_error(&format!("Failed to parse policy: {}", e))
+    })?;
+
+    debug!(
+        "ReplicaSet {}/{} has policy: {:?}",
+        namespace, name, policy.policy
+    );
+
+    Ok(Action::requeue(Duration::from_secs(300)))
+}
+
+fn error_policy(
+    _object: Arc<ReplicaSet>,
+    _error: &kube::Error,
+    _ctx: Arc<ControllerContext>,
+) -> Action {
+    // Requeue after 60 seconds on errors
+    Action::requeue(Duration::from_secs(60))
+}
+
+/// Helper to create a kube::Error from a string message
+fn create_error(msg: &str) -> kube::Error {
+    kube::Error::Api(kube::error::ErrorResponse {
+        status: "Failure".to_string(),
+        message: msg.to_string(),
+        reason: "InvalidConfiguration".to_string(),
+        code: 400,
+    })
+}
+
+/// Parse an image string into (image_name, tag)
+fn parse_image(image: &str) -> Result<(String, String), String> {
+    let parts: Vec<&str> = image.rsplitn(2, ':').collect();
+    if parts.len() != 2 {
+        return Err(format!("Invalid image format: {}", image));
+    }
+    Ok((parts[1].to_string(), parts[0].to_string()))
+}
+
+/// Handle an available image update for a standalone replicaset
+#[instrument(skip(client, policy_engine, replicaset))]
+pub async fn handle_image_update(
+    client: &Client,
+    policy_engine: &Arc<PolicyEngine>,
+    replicaset: &ReplicaSet,
+    image: &str,
+    new_version: &str,
+) -> Result<()> {
+    let namespace = replicaset.namespace().unwrap_or_default();
+    let name = replicaset.name_any();
+
+    if !is_standalone(replicaset) {
+        debug!(
+            "ReplicaSet {}/{} is owned by a Deployment, skipping",
+            namespace, name
+        );
+        return Ok(());
+    }
+
+    info!(
+        "Handling image update for replicaset {}/{}: {} -> {}",
+        namespace, name, image, new_version
+    );
+
+    let annotations = replicaset
+        .metadata
+        .annotations
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("ReplicaSet has no annotations"))?;
+    let policy = parse_policy_from_annotations(annotations)?;
+
+    let template_spec = replicaset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.as_ref())
+        .and_then(|template| template.spec.as_ref())
+        .ok_or_else(|| anyhow::anyhow!("ReplicaSet template has no spec"))?;
+
+    // Find the targeted container with matching image
+    let mut current = None;
+    for container in &template_spec.containers {
+        if !policy.targets_container(&container.name) {
+            continue;
+        }
+
+        let container_image = container.image.as_deref().unwrap_or_default();
+        let (img_name, img_tag) = parse_image(container_image)
+            .map_err(|e| anyhow::anyhow!("Failed to parse container image: {}", e))?;
+
+        if img_name == image || container_image.starts_with(image) {
+            current = Some((container.name.clone(), img_tag));
+            break;
+        }
+    }
+
+    let (container_name, current_version) =
+        current.ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
+
+    let should_update = policy_engine
+        .should_update(&policy, &current_version, new_version)
+        .map_err(|e| anyhow::anyhow!("Policy evaluation failed: {}", e))?;
+    if !should_update {
+        info!(
+            "Update from {} to {} rejected by policy {:?}",
+            current_version, new_version, policy.policy
+        );
+        return Ok(());
+    }
+
+    // Check minimum update interval
+    if let (Some(min_interval), Some(last_update_str)) = (
+        policy.min_update_interval,
+        annotations.get(annotations::LAST_UPDATE),
+    ) && let Ok(last_update) = chrono::DateTime::parse_from_rfc3339(last_update_str)
+    {
+        let elapsed = Utc::now().signed_duration_since(last_update.with_timezone(&Utc));
+        if elapsed < chrono::Duration::seconds(min_interval as i64) {
+            info!(
+                "Skipping update for replicaset {}/{}: minimum interval not met ({} < {} seconds)",
+                namespace,
+                name,
+                elapsed.num_seconds(),
+                min_interval
+            );
+            return Ok(());
+        }
+    }
+
+    let current_image = format!("{}:{}", image, current_version);
+    let new_image = format!("{}:{}", image, new_version);
+
+    if policy.require_approval {
+        if !super::rate_limit::allow_update_request(
+            "ReplicaSet",
+            &namespace,
+            &name,
+            Some(annotations),
+        ) {
+            return Ok(());
+        }
+
+        info!(
+            "Creating UpdateRequest for replicaset {}/{}: {} -> {}",
+            namespace, name, current_version, new_version
+        );
+
+        create_update_request(
+            client,
+            &namespace,
+            &name,
+            image,
+            &current_version,
+            new_version,
+            &container_name,
+            &policy,
+            annotations,
+        )
+        .await?;
+    } else if let Some((schedule, scheduled_for)) =
+        crate::schedule::deferred_until(annotations, Utc::now())
+    {
+        let client = client.clone();
+        let (image, new_version) = (image.to_string(), new_version.to_string());
+
+        crate::schedule::defer_update(
+            PendingUpdate {
+                kind: "ReplicaSet".to_string(),
+                namespace: namespace.clone(),
+                name: name.clone(),
+                container: Some(container_name.clone()),
+                current_image: current_image.clone(),
+                new_image: new_image.clone(),
+                policy: policy.policy,
+                schedule,
+                scheduled_for,
+                queued_at: Utc::now(),
+            },
+            move || async move {
+                update_replicaset_image(&client, &namespace, &name, &image, &new_version).await?;
+                notifications::notify_update_completed(DeploymentInfo {
+                    name,
+                    namespace,
+                    current_image,
+                    new_image,
+                    container: Some(container_name),
+                    resource_kind: Some("ReplicaSet".to_string()),
+                });
+                Ok(())
+            },
+        )
+        .await;
+    } else {
+        info!(
+            "Auto-updating replicaset {}/{} (no approval required): {} -> {}",
+            namespace, name, current_version, new_version
+        );
+
+        update_replicaset_image(client, &namespace, &name, image, new_version).await?;
+
+        notifications::notify_update_completed(DeploymentInfo {
+            name: name.clone(),
+            namespace: namespace.clone(),
+            current_image,
+            new_image,
+            container: Some(container_name),
+            resource_kind: Some("ReplicaSet".to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Create an UpdateRequest CRD targeting a replicaset
+#[allow(clippy::too_many_arguments)]
+async fn create_update_request(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    image: &str,
+    current_version: &str,
+    new_version: &str,
+    container_name: &str,
+    policy: &ResourcePolicy,
+    resource_annotations: &BTreeMap<String, String>,
+) -> Result<()> {
+    let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), namespace);
+
+    let request_name = format!(
+        "{}-{}",
+        name,
+        new_version.replace([':', '.', '/'], "-").to_lowercase()
+    );
+
+    let update_request = UpdateRequest {
+        metadata: kube::api::ObjectMeta {
+            name: Some(request_name.clone()),
+            namespace: Some(namespace.to_string()),
+            ..Default::default()
+        },
+        spec: UpdateRequestSpec {
+            target_ref: TargetRef {
+                api_version: "apps/v1".to_string(),
+                kind: "ReplicaSet".to_string(),
+                name: name.to_string(),
+                namespace: namespace.to_string(),
+            },
+            update_type: UpdateType::Image,
+            container_name: Some(container_name.to_string()),
+            container_kind: Default::default(),
+            current_image: format!("{}:{}", image, current_version),
+            new_image: format!("{}:{}", image, new_version),
+            policy: map_policy_to_crd(&policy.policy),
+            reason: Some(format!(
+                "Update from {} to {}",
+                current_version, new_version
+            )),
+            require_approval: true,
+            expires_at: Some(Utc::now() + chrono::Duration::hours(24)),
+            scheduled_at: None,
+            priority: None,
+            updates: Vec::new(),
+            discussion_thread: Vec::new(),
+            sla_deadline: super::sla::sla_deadline(Some(resource_annotations), chrono::Utc::now()),
+            impact: None,
+        },
+        status: None,
+    };
+
+    match update_requests.get(&request_name).await {
+        Ok(existing) => {
+            use crate::models::crd::UpdatePhase;
+            // Replace an UpdateRequest left over from an earlier decision
+            if let Some(status) = &existing.status
+                && matches!(
+                    status.phase,
+                    UpdatePhase::Completed | UpdatePhase::Rejected | UpdatePhase::Failed
+                )
+            {
+                info!(
+                    "Existing UpdateRequest is in terminal state ({:?}), creating new one",
+                    status.phase
+                );
+                update_requests
+                    .delete(&request_name, &Default::default())
+                    .await?;
+                update_requests
+                    .create(&PostParams::default(), &update_request)
+                    .await?;
+                crate::integrations::jira::open_issue_for_update_request(
+                    client,
+                    Some(resource_annotations),
+                    &update_request,
+                )
+                .await;
+            } else {
+                debug!(
+                    "UpdateRequest {}/{} already exists, skipping creation",
+                    namespace, request_name
+                );
+            }
+        },
+        Err(kube::Error::Api(err)) if err.code == 404 => {
+            update_requests
+                .create(&PostParams::default(), &update_request)
+                .await?;
+            crate::integrations::jira::open_issue_for_update_request(
+                client,
+                Some(resource_annotations),
+                &update_request,
+            )
+            .await;
+            info!(
+                "Created UpdateRequest {}/{} for replicaset {}",
+                namespace, request_name, name
+            );
+        },
+        Err(e) => {
+            error!("Failed to check for existing UpdateRequest: {}", e);
+            return Err(anyhow::anyhow!("Failed to check UpdateRequest: {}", e));
+        },
+    }
+
+    Ok(())
+}
+
+/// Update a replicaset's container image - public wrapper
+pub async fn update_replicaset_image(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    image: &str,
+    new_version: &str,
+) -> Result<()> {
+    update_replicaset_image_with_tracking(client, namespace, name, image, new_version, None).await
+}
+
+/// Update a replicaset's container image with tracking
+/// If approver is provided, it will be recorded in the last-update annotation
+pub async fn update_replicaset_image_with_tracking(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    image: &str,
+    new_version: &str,
+    approver: Option<&str>,
+) -> Result<()> {
+    let replicasets: Api<ReplicaSet> = Api::namespaced(client.clone(), namespace);
+
+    info!(
+        "Updating replicaset {}/{} image to {}:{}",
+        namespace, name, image, new_version
+    );
+
+    let replicaset = replicasets.get(name).await?;
+
+    let now = Utc::now();
+    let last_update_value = if let Some(approver) = approver {
+        format!("{} (approved by {})", now.to_rfc3339(), approver)
+    } else {
+        now.to_rfc3339()
+    };
+
+    let patch = build_replicaset_image_patch(&replicaset, image, new_version, &last_update_value)?;
+
+    replicasets
+        .patch(
+            name,
+            &PatchParams::apply("headwind"),
+            &Patch::Strategic(patch),
+        )
+        .await?;
+
+    info!(
+        "Successfully updated replicaset {}/{} to version {}",
+        namespace, name, new_version
+    );
+
+    Ok(())
+}
+
+/// The strategic merge patch `update_replicaset_image_with_tracking` applies
+/// to move the container running `image` to `new_version`
+pub fn build_replicaset_image_patch(
+    replicaset: &ReplicaSet,
+    image: &str,
+    new_version: &str,
+    last_update_value: &str,
+) -> Result<serde_json::Value> {
+    let template_spec = replicaset
+        .spec
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("ReplicaSet has no spec"))?
+        .template
+        .as_ref()
+        .and_then(|template| template.spec.as_ref())
+        .ok_or_else(|| anyhow::anyhow!("ReplicaSet template has no spec"))?;
+
+    let container = template_spec
+        .containers
+        .iter()
+        .find(|c| c.image.as_ref().is_some_and(|i| i.starts_with(image)))
+        .ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
+
+    let new_image = format!("{}:{}", image, new_version);
+    let mut patch = json!({
+        "spec": {
+            "template": {
+                "spec": {
+                    "containers": [{
+                        "name": container.name,
+                        "image": new_image
+                    }]
+                }
+            }
+        },
+        "metadata": {
+            "annotations": {
+                annotations::LAST_UPDATE: last_update_value
+            }
+        }
+    });
+    super::set_previous_image(&mut patch, container.image.as_deref(), &new_image);
+    Ok(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+
+    fn replicaset(owner_kinds: &[&str]) -> ReplicaSet {
+        let mut replicaset = ReplicaSet::default();
+        replicaset.metadata.name = Some("web-7d9f8".to_string());
+        replicaset.metadata.owner_references = Some(
+            owner_kinds
+                .iter()
+                .map(|kind| OwnerReference {
+                    api_version: "apps/v1".to_string(),
+                    kind: kind.to_string(),
+                    name: "web".to_string(),
+                    uid: "uid".to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+        );
+        replicaset
+    }
+
+    #[test]
+    fn test_deployment_owned_replicaset_is_not_standalone() {
+        assert!(!is_standalone(&replicaset(&["Deployment"])));
+        assert!(!is_standalone(&replicaset(&["Argo", "Deployment"])));
+    }
+
+    #[test]
+    fn test_standalone_replicasets() {
+        assert!(is_standalone(&ReplicaSet::default()));
+        assert!(is_standalone(&replicaset(&[])));
+        // Owned by something that is not a Deployment, e.g. an Argo Rollout
+        assert!(is_standalone(&replicaset(&["Rollout"])));
+    }
+
+    #[test]
+    fn test_build_replicaset_image_patch() {
+        let replicaset: ReplicaSet = serde_json::from_value(json!({
+            "metadata": { "name": "legacy" },
+            "spec": {
+                "selector": { "matchLabels": { "app": "legacy" } },
+                "template": {
+                    "spec": {
+                        "containers": [
+                            { "name": "app", "image": "nginx:1.25.0" },
+                            { "name": "sidecar", "image": "envoy:1.28.0" }
+                        ]
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let patch =
+            build_replicaset_image_patch(&replicaset, "nginx", "1.26.0", "2025-01-01T00:00:00Z")
+                .unwrap();
+        assert_eq!(
+            patch["spec"]["template"]["spec"]["containers"][0],
+            json!({ "name": "app", "image": "nginx:1.26.0" })
+        );
+        assert!(build_replicaset_image_patch(&replicaset, "redis", "7", "now").is_err());
+    }
+}
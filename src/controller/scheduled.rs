@@ -0,0 +1,231 @@
+//! Scheduled approval of UpdateRequests.
+//!
+//! A pending UpdateRequest with `spec.scheduledAt` set is approved
+//! automatically once that time has passed, exactly as if it had been approved
+//! through the approval API, and recorded with `approvedBy: scheduled`.
+
+use crate::metrics::{RECONCILE_DURATION, RECONCILE_ERRORS};
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use kube::{ResourceExt, api::Api, client::Client, runtime::controller::Action};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, info, instrument};
+
+/// Approver recorded on UpdateRequests approved by their schedule
+pub const SCHEDULED_APPROVER: &str = "scheduled";
+
+/// What to do with an UpdateRequest at a given time
+#[derive(Debug, PartialEq, Eq)]
+pub enum ScheduledAction {
+    /// Not pending or not scheduled
+    Ignore,
+    /// Scheduled in the future; check again after the duration
+    Wait(Duration),
+    /// The scheduled time has passed
+    Approve,
+}
+
+/// Decide what to do with `update_request` at `now`
+pub fn scheduled_action(update_request: &UpdateRequest, now: DateTime<Utc>) -> ScheduledAction {
+    let pending = update_request
+        .status
+        .as_ref()
+        .is_none_or(|s| s.phase == UpdatePhase::Pending);
+    let Some(scheduled_at) = update_request.spec.scheduled_at else {
+        return ScheduledAction::Ignore;
+    };
+    if !pending {
+        return ScheduledAction::Ignore;
+    }
+
+    match (scheduled_at - now).to_std() {
+        Ok(remaining) if !remaining.is_zero() => ScheduledAction::Wait(remaining),
+        _ => ScheduledAction::Approve,
+    }
+}
+
+pub struct ScheduledApprovalController {
+    client: Client,
+}
+
+impl ScheduledApprovalController {
+    pub async fn new() -> Result<Self> {
+        let client = Client::try_default().await?;
+        Ok(Self { client })
+    }
+
+    pub async fn run(self) {
+        info!("Scheduled approval controller starting...");
+
+        let mut backoff_seconds = 1;
+        const MAX_BACKOFF: u64 = 60;
+
+        loop {
+            let update_requests: Api<UpdateRequest> = super::watched_api(self.client.clone());
+
+            super::queue_metrics::controller(update_requests, "scheduled-approval")
+                .run(
+                    reconcile,
+                    error_policy,
+                    Arc::new(ControllerContext {
+                        client: self.client.clone(),
+                    }),
+                )
+                .for_each(|res| async move {
+                    match res {
+                        Ok((obj_ref, _action)) => {
+                            debug!(
+                                "Reconciled UpdateRequest schedule: {}/{}",
+                                obj_ref.namespace.as_deref().unwrap_or("default"),
+                                obj_ref.name
+                            );
+                        },
+                        Err(e) => {
+                            error!("Reconciliation error: {}", e);
+                            RECONCILE_ERRORS.inc();
+                        },
+                    }
+                })
+                .await;
+
+            error!(
+                "Scheduled approval controller stream ended, restarting in {}s...",
+                backoff_seconds
+            );
+            tokio::time::sleep(Duration::from_secs(backoff_seconds)).await;
+            backoff_seconds = (backoff_seconds * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+struct ControllerContext {
+    client: Client,
+}
+
+#[instrument(skip(ctx, update_request), fields(update_request = %update_request.name_any()))]
+async fn reconcile(
+    update_request: Arc<UpdateRequest>,
+    ctx: Arc<ControllerContext>,
+) -> Result<Action, kube::Error> {
+    let _timer = RECONCILE_DURATION.start_timer();
+    let _queue_timer =
+        super::queue_metrics::start_reconcile("scheduled-approval", update_request.as_ref());
+    let _in_flight = super::concurrency::track("scheduled-approval");
+
+    match scheduled_action(&update_request, Utc::now()) {
+        ScheduledAction::Ignore => return Ok(Action::await_change()),
+        ScheduledAction::Wait(remaining) => return Ok(Action::requeue(remaining)),
+        ScheduledAction::Approve => {},
+    }
+
+    // Re-read the request so a manual approval or a changed schedule since the
+    // event was queued is not overridden
+    let name = update_request.name_any();
+    let namespace = update_request.namespace().unwrap_or_default();
+    let api: Api<UpdateRequest> = Api::namespaced(ctx.client.clone(), &namespace);
+    let latest = api.get(&name).await?;
+    match scheduled_action(&latest, Utc::now()) {
+        ScheduledAction::Ignore => return Ok(Action::await_change()),
+        ScheduledAction::Wait(remaining) => return Ok(Action::requeue(remaining)),
+        ScheduledAction::Approve => {},
+    }
+
+    info!(
+        "Approving UpdateRequest {}/{} scheduled at {}",
+        namespace,
+        name,
+        latest
+            .spec
+            .scheduled_at
+            .map(|at| at.to_rfc3339())
+            .unwrap_or_default()
+    );
+    let applied = crate::approval::apply_approved_update(
+        &ctx.client,
+        &latest,
+        Some(SCHEDULED_APPROVER.to_string()),
+    )
+    .await?;
+
+    match applied {
+        Some(_) => Ok(Action::await_change()),
+        // Another replica holds the Lease; check whether it finished later
+        None => Ok(Action::requeue(super::lock::CONTENTION_REQUEUE)),
+    }
+}
+
+fn error_policy(
+    _update_request: Arc<UpdateRequest>,
+    error: &kube::Error,
+    _ctx: Arc<ControllerContext>,
+) -> Action {
+    error!("Scheduled approval failed: {}", error);
+    RECONCILE_ERRORS.inc();
+    Action::requeue(Duration::from_secs(60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::crd::UpdateRequestStatus;
+    use crate::testing;
+
+    fn update_request(scheduled_at: Option<DateTime<Utc>>) -> UpdateRequest {
+        let mut update_request = testing::update_request("default", "web-update");
+        update_request.spec.scheduled_at = scheduled_at;
+        update_request
+    }
+
+    #[test]
+    fn test_scheduled_action() {
+        let now = Utc::now();
+
+        assert_eq!(
+            scheduled_action(&update_request(None), now),
+            ScheduledAction::Ignore
+        );
+        assert_eq!(
+            scheduled_action(
+                &update_request(Some(now + chrono::Duration::minutes(5))),
+                now
+            ),
+            ScheduledAction::Wait(Duration::from_secs(300))
+        );
+        assert_eq!(
+            scheduled_action(&update_request(Some(now)), now),
+            ScheduledAction::Approve
+        );
+        assert_eq!(
+            scheduled_action(
+                &update_request(Some(now - chrono::Duration::minutes(5))),
+                now
+            ),
+            ScheduledAction::Approve
+        );
+    }
+
+    #[test]
+    fn test_only_pending_requests_are_approved() {
+        let now = Utc::now();
+        let mut ur = update_request(Some(now - chrono::Duration::minutes(1)));
+
+        ur.status = Some(UpdateRequestStatus::default());
+        assert_eq!(scheduled_action(&ur, now), ScheduledAction::Approve);
+
+        for phase in [
+            UpdatePhase::Completed,
+            UpdatePhase::Rejected,
+            UpdatePhase::Failed,
+            UpdatePhase::Expired,
+        ] {
+            ur.status = Some(UpdateRequestStatus {
+                phase,
+                ..Default::default()
+            });
+            assert_eq!(scheduled_action(&ur, now), ScheduledAction::Ignore);
+        }
+    }
+}
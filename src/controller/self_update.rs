@@ -0,0 +1,221 @@
+//! Self-update: Headwind updating its own Deployment.
+//!
+//! With `HEADWIND_SELF_UPDATE_ENABLED=true`, Headwind's Deployment
+//! (`HEADWIND_SELF_DEPLOYMENT_NAME` in `HEADWIND_SELF_NAMESPACE`) is handled
+//! like any annotated Deployment, with three safeguards: updates always need
+//! approval, an update is refused while other UpdateRequests are pending, and
+//! a notification is sent before Headwind restarts. Like every Deployment
+//! update, the patch saves the running image in `headwind.sh/previous-image`.
+
+use crate::config::headwind_namespace;
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use crate::models::policy::annotations;
+use crate::models::{ResourcePolicy, UpdatePolicy};
+use crate::notifications::{self, DeploymentInfo, NotificationEvent, NotificationPayload};
+use anyhow::{Result, bail};
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::api::Api;
+use kube::{Client, ResourceExt};
+use std::collections::BTreeMap;
+
+/// Default name of Headwind's own Deployment
+const DEFAULT_DEPLOYMENT_NAME: &str = "headwind";
+
+/// Headwind's own Deployment, when self-update is enabled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfUpdateConfig {
+    /// Image repository to follow, e.g. `ghcr.io/headwind-sh/headwind`;
+    /// None follows every container of the Deployment
+    pub image: Option<String>,
+    pub deployment_name: String,
+    pub namespace: String,
+}
+
+impl SelfUpdateConfig {
+    /// Configuration from `HEADWIND_SELF_*` env vars, None unless
+    /// `HEADWIND_SELF_UPDATE_ENABLED` is true
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var("HEADWIND_SELF_UPDATE_ENABLED")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+
+        let non_empty = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Some(Self {
+            image: non_empty("HEADWIND_SELF_UPDATE_IMAGE")
+                .map(|image| image.trim_end_matches('/').to_string()),
+            deployment_name: non_empty("HEADWIND_SELF_DEPLOYMENT_NAME")
+                .unwrap_or_else(|| DEFAULT_DEPLOYMENT_NAME.to_string()),
+            namespace: non_empty("HEADWIND_SELF_NAMESPACE").unwrap_or_else(headwind_namespace),
+        })
+    }
+
+    /// Whether `namespace/name` is Headwind's own Deployment
+    pub fn is_self(&self, namespace: &str, name: &str) -> bool {
+        self.namespace == namespace && self.deployment_name == name
+    }
+
+    /// Whether a container running `image_name` (without tag) is followed
+    pub fn tracks_image(&self, image_name: &str) -> bool {
+        self.image
+            .as_deref()
+            .is_none_or(|image| image == image_name)
+    }
+
+    /// Apply the self-update rules to the policy parsed from the Deployment's
+    /// annotations: `minor` when it has no policy annotation, and approval
+    /// always required
+    pub fn apply(&self, policy: &mut ResourcePolicy, annotations: &BTreeMap<String, String>) {
+        if !annotations.contains_key(annotations::POLICY) {
+            policy.policy = UpdatePolicy::Minor;
+        }
+        policy.require_approval = true;
+    }
+}
+
+/// The self-update configuration if `deployment` is Headwind's own Deployment
+pub fn self_update_for(deployment: &Deployment) -> Option<SelfUpdateConfig> {
+    SelfUpdateConfig::from_env().filter(|config| {
+        config.is_self(
+            &deployment.namespace().unwrap_or_default(),
+            &deployment.name_any(),
+        )
+    })
+}
+
+/// Names of pending UpdateRequests other than `namespace/name`
+pub fn other_pending(
+    update_requests: &[UpdateRequest],
+    namespace: &str,
+    name: &str,
+) -> Vec<String> {
+    update_requests
+        .iter()
+        .filter(|ur| {
+            ur.status
+                .as_ref()
+                .is_none_or(|s| s.phase == UpdatePhase::Pending)
+        })
+        .map(|ur| (ur.namespace().unwrap_or_default(), ur.name_any()))
+        .filter(|(ns, n)| !(ns == namespace && n == name))
+        .map(|(ns, n)| format!("{}/{}", ns, n))
+        .collect()
+}
+
+/// Refuse a self-update while other UpdateRequests are pending: the restart
+/// would interrupt them
+pub async fn ensure_no_pending_updates(
+    client: &Client,
+    namespace: &str,
+    update_request_name: &str,
+) -> Result<()> {
+    let api: Api<UpdateRequest> = Api::all(client.clone());
+    let update_requests = api.list(&Default::default()).await?.items;
+    let pending = other_pending(&update_requests, namespace, update_request_name);
+
+    if !pending.is_empty() {
+        bail!(
+            "Refusing to update Headwind while other UpdateRequests are pending: {}",
+            pending.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// High-priority notification that Headwind is about to restart itself
+pub fn notify_self_update(deployment: DeploymentInfo, approved_by: Option<String>) {
+    let mut payload = NotificationPayload::new(NotificationEvent::SelfUpdateStarted, deployment);
+    if let Some(approver) = approved_by {
+        payload = payload.with_approved_by(approver);
+    }
+    notifications::notify(payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing;
+
+    fn config(image: Option<&str>) -> SelfUpdateConfig {
+        SelfUpdateConfig {
+            image: image.map(String::from),
+            deployment_name: "headwind".to_string(),
+            namespace: "headwind-system".to_string(),
+        }
+    }
+
+    fn update_request(namespace: &str, name: &str, phase: Option<UpdatePhase>) -> UpdateRequest {
+        match phase {
+            Some(phase) => testing::update_request_in_phase(namespace, name, phase),
+            None => testing::update_request(namespace, name),
+        }
+    }
+
+    #[test]
+    fn test_is_self_and_tracks_image() {
+        let config = config(Some("ghcr.io/headwind-sh/headwind"));
+        assert!(config.is_self("headwind-system", "headwind"));
+        assert!(!config.is_self("default", "headwind"));
+        assert!(config.tracks_image("ghcr.io/headwind-sh/headwind"));
+        assert!(!config.tracks_image("ghcr.io/headwind-sh/sidecar"));
+
+        assert!(self::config(None).tracks_image("anything"));
+    }
+
+    #[test]
+    fn test_apply_forces_approval() {
+        let config = config(None);
+
+        // No policy annotation: minor, with approval
+        let mut policy = ResourcePolicy {
+            require_approval: false,
+            ..Default::default()
+        };
+        config.apply(&mut policy, &BTreeMap::new());
+        assert_eq!(policy.policy, UpdatePolicy::Minor);
+        assert!(policy.require_approval);
+
+        // An explicit policy is kept, the approval override is not
+        let annotations = BTreeMap::from([
+            (annotations::POLICY.to_string(), "patch".to_string()),
+            (
+                annotations::REQUIRE_APPROVAL.to_string(),
+                "false".to_string(),
+            ),
+        ]);
+        let mut policy = ResourcePolicy {
+            policy: UpdatePolicy::Patch,
+            require_approval: false,
+            ..Default::default()
+        };
+        config.apply(&mut policy, &annotations);
+        assert_eq!(policy.policy, UpdatePolicy::Patch);
+        assert!(policy.require_approval);
+    }
+
+    #[test]
+    fn test_other_pending() {
+        let update_requests = vec![
+            update_request(
+                "headwind-system",
+                "headwind-1-2-0",
+                Some(UpdatePhase::Pending),
+            ),
+            update_request("default", "web-1-26-0", None),
+            update_request("default", "api-2-0-0", Some(UpdatePhase::Completed)),
+        ];
+
+        assert_eq!(
+            other_pending(&update_requests, "headwind-system", "headwind-1-2-0"),
+            vec!["default/web-1-26-0"]
+        );
+        assert_eq!(
+            other_pending(&update_requests[..1], "headwind-system", "headwind-1-2-0"),
+            Vec::<String>::new()
+        );
+    }
+}
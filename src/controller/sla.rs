@@ -0,0 +1,351 @@
+//! Review SLAs of UpdateRequests.
+//!
+//! A workload annotated with `headwind.sh/sla-hours` gets UpdateRequests whose
+//! `spec.slaDeadline` is that many hours after their creation. Every 10
+//! minutes the [`SlaMonitor`] looks for Pending UpdateRequests past their
+//! deadline. Each one is marked `headwind.sh/sla-breached: "true"`, gets an
+//! `SlaBreached` condition and is reported once through the highest-priority
+//! notifier (see [`ESCALATION_ORDER`](crate::notifications::ESCALATION_ORDER)).
+
+use crate::metrics::SLA_BREACHES_TOTAL;
+use crate::metrics::queue::labels;
+use crate::models::annotations;
+use crate::models::crd::{UpdatePhase, UpdateRequest, UpdateRequestCondition};
+use crate::notifications::{self, DeploymentInfo, NotificationEvent, NotificationPayload};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use kube::api::{ListParams, Patch, PatchParams};
+use kube::{Api, Client, ResourceExt};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// How often Pending UpdateRequests are checked
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Condition type recorded on breached UpdateRequests
+pub const SLA_BREACHED_CONDITION: &str = "SlaBreached";
+
+/// Review deadline of an UpdateRequest created at `created` for a workload
+/// with `annotations`, None without a positive `headwind.sh/sla-hours`
+pub fn sla_deadline(
+    annotations: Option<&BTreeMap<String, String>>,
+    created: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let hours = annotations?
+        .get(annotations::SLA_HOURS)?
+        .trim()
+        .parse::<i64>()
+        .ok()
+        .filter(|hours| *hours > 0)?;
+    Some(created + chrono::Duration::hours(hours))
+}
+
+/// Whether `update_request` is still Pending after its SLA deadline at `now`
+/// and has not been reported yet
+pub fn is_breached(update_request: &UpdateRequest, now: DateTime<Utc>) -> bool {
+    let pending = update_request
+        .status
+        .as_ref()
+        .is_none_or(|status| status.phase == UpdatePhase::Pending);
+    let reported = update_request
+        .annotations()
+        .get(annotations::SLA_BREACHED)
+        .is_some_and(|v| v == "true");
+    pending
+        && !reported
+        && update_request
+            .spec
+            .sla_deadline
+            .is_some_and(|deadline| now > deadline)
+}
+
+/// `conditions` with the `SlaBreached` condition set at `now`
+pub fn with_breach_condition(
+    conditions: &[UpdateRequestCondition],
+    deadline: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<UpdateRequestCondition> {
+    let mut conditions: Vec<_> = conditions
+        .iter()
+        .filter(|c| c.type_ != SLA_BREACHED_CONDITION)
+        .cloned()
+        .collect();
+    conditions.push(UpdateRequestCondition {
+        type_: SLA_BREACHED_CONDITION.to_string(),
+        status: "True".to_string(),
+        reason: "DeadlinePassed".to_string(),
+        message: Some(format!(
+            "Still pending after the review deadline {}",
+            deadline.to_rfc3339()
+        )),
+        last_transition_time: now,
+    });
+    conditions
+}
+
+pub struct SlaMonitor {
+    client: Client,
+}
+
+impl SlaMonitor {
+    pub async fn new() -> Result<Self> {
+        Ok(Self {
+            client: Client::try_default().await?,
+        })
+    }
+
+    pub async fn run(self) {
+        info!(
+            "UpdateRequest SLA monitor starting, checking every {}m",
+            CHECK_INTERVAL.as_secs() / 60
+        );
+
+        loop {
+            if let Err(e) = self.check(Utc::now()).await {
+                error!("UpdateRequest SLA check failed: {:#}", e);
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Report the UpdateRequests whose SLA is breached at `now`
+    async fn check(&self, now: DateTime<Utc>) -> Result<()> {
+        let api: Api<UpdateRequest> = super::watched_api(self.client.clone());
+        let update_requests = api
+            .list(&ListParams::default())
+            .await
+            .context("Failed to list UpdateRequests")?
+            .items;
+
+        for update_request in update_requests.iter().filter(|ur| is_breached(ur, now)) {
+            let namespace = update_request.namespace().unwrap_or_default();
+            let name = update_request.name_any();
+            match self.mark_breached(update_request, now).await {
+                Ok(()) => {
+                    warn!(
+                        "UpdateRequest {}/{} is past its SLA deadline",
+                        namespace, name
+                    );
+                    let [namespace, policy] = labels(update_request);
+                    SLA_BREACHES_TOTAL
+                        .with_label_values(&[namespace.as_str(), policy.as_str()])
+                        .inc();
+                    notifications::notify_escalation(breached_payload(update_request));
+                },
+                Err(kube::Error::Api(e)) if e.code == 404 => {},
+                Err(e) => warn!(
+                    "Failed to mark UpdateRequest {}/{} as past its SLA: {}",
+                    namespace, name, e
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Annotate `update_request` and add the `SlaBreached` condition
+    async fn mark_breached(
+        &self,
+        update_request: &UpdateRequest,
+        now: DateTime<Utc>,
+    ) -> Result<(), kube::Error> {
+        let namespace = update_request.namespace().unwrap_or_default();
+        let name = update_request.name_any();
+        let api: Api<UpdateRequest> = Api::namespaced(self.client.clone(), &namespace);
+        let deadline = update_request.spec.sla_deadline.unwrap_or(now);
+        let conditions = with_breach_condition(
+            update_request
+                .status
+                .as_ref()
+                .map(|s| s.conditions.as_slice())
+                .unwrap_or_default(),
+            deadline,
+            now,
+        );
+
+        api.patch_status(
+            &name,
+            &PatchParams::default(),
+            &Patch::Merge(json!({ "status": { "conditions": conditions } })),
+        )
+        .await?;
+        api.patch(
+            &name,
+            &PatchParams::default(),
+            &Patch::Merge(json!({
+                "metadata": { "annotations": { annotations::SLA_BREACHED: "true" } }
+            })),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Notification that `update_request` passed its SLA deadline
+pub fn breached_payload(update_request: &UpdateRequest) -> NotificationPayload {
+    let spec = &update_request.spec;
+    let deployment = DeploymentInfo {
+        name: spec.target_ref.name.clone(),
+        namespace: spec.target_ref.namespace.clone(),
+        current_image: spec.current_image.clone(),
+        new_image: spec.new_image.clone(),
+        container: spec.container_name.clone(),
+        resource_kind: Some(spec.target_ref.kind.clone()),
+    };
+    let mut metadata = HashMap::new();
+    if let (Some(created), Some(deadline)) = (
+        update_request.metadata.creation_timestamp.as_ref(),
+        spec.sla_deadline,
+    ) {
+        metadata.insert(
+            "slaHours".to_string(),
+            (deadline - created.0).num_hours().to_string(),
+        );
+        metadata.insert(
+            "slaDeadline".to_string(),
+            deadline.format("%Y-%m-%d %H:%M UTC").to_string(),
+        );
+    }
+
+    NotificationPayload::new(NotificationEvent::SlaBreached, deployment)
+        .with_policy(format!("{:?}", spec.policy).to_lowercase())
+        .with_requires_approval(true)
+        .with_update_request(update_request.name_any())
+        .with_metadata(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::crd::UpdatePolicyType;
+    use crate::testing;
+    use chrono::TimeZone;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+    fn created() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 8, 0, 0).unwrap()
+    }
+
+    fn sla_annotations(hours: &str) -> BTreeMap<String, String> {
+        BTreeMap::from([(annotations::SLA_HOURS.to_string(), hours.to_string())])
+    }
+
+    fn update_request(phase: UpdatePhase, sla_hours: Option<i64>) -> UpdateRequest {
+        let mut update_request = testing::update_request_in_phase("payments", "web-update", phase);
+        update_request.spec.current_image = "openssl-app:3.0.13".to_string();
+        update_request.spec.new_image = "openssl-app:3.0.14".to_string();
+        update_request.spec.policy = UpdatePolicyType::Patch;
+        update_request.spec.sla_deadline =
+            sla_hours.map(|h| created() + chrono::Duration::hours(h));
+        update_request.metadata.creation_timestamp = Some(Time(created()));
+        update_request
+    }
+
+    #[test]
+    fn test_sla_deadline_from_annotation() {
+        assert_eq!(
+            sla_deadline(Some(&sla_annotations("4")), created()),
+            Some(Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap())
+        );
+        assert_eq!(
+            sla_deadline(Some(&sla_annotations(" 24 ")), created()),
+            Some(created() + chrono::Duration::hours(24))
+        );
+        for invalid in ["0", "-4", "4h", ""] {
+            assert_eq!(
+                sla_deadline(Some(&sla_annotations(invalid)), created()),
+                None
+            );
+        }
+        assert_eq!(sla_deadline(Some(&BTreeMap::new()), created()), None);
+        assert_eq!(sla_deadline(None, created()), None);
+    }
+
+    #[test]
+    fn test_breach_at_boundary_times() {
+        let ur = update_request(UpdatePhase::Pending, Some(4));
+        let deadline = created() + chrono::Duration::hours(4);
+
+        assert!(!is_breached(&ur, created()));
+        assert!(!is_breached(&ur, deadline - chrono::Duration::seconds(1)));
+        // Reviewed exactly at the deadline is still within the SLA
+        assert!(!is_breached(&ur, deadline));
+        assert!(is_breached(&ur, deadline + chrono::Duration::seconds(1)));
+        assert!(is_breached(&ur, deadline + chrono::Duration::days(30)));
+    }
+
+    #[test]
+    fn test_only_unreported_pending_requests_breach() {
+        let late = created() + chrono::Duration::hours(5);
+
+        // No SLA
+        assert!(!is_breached(
+            &update_request(UpdatePhase::Pending, None),
+            late
+        ));
+
+        // Without a status the request is still Pending
+        let mut new = update_request(UpdatePhase::Pending, Some(4));
+        new.status = None;
+        assert!(is_breached(&new, late));
+
+        for phase in [
+            UpdatePhase::Approved,
+            UpdatePhase::Rejected,
+            UpdatePhase::Completed,
+            UpdatePhase::Failed,
+            UpdatePhase::Expired,
+        ] {
+            assert!(!is_breached(&update_request(phase, Some(4)), late));
+        }
+
+        // Reported once only
+        let mut reported = update_request(UpdatePhase::Pending, Some(4));
+        reported.metadata.annotations = Some(BTreeMap::from([(
+            annotations::SLA_BREACHED.to_string(),
+            "true".to_string(),
+        )]));
+        assert!(!is_breached(&reported, late));
+    }
+
+    #[test]
+    fn test_breach_condition_replaces_previous() {
+        let deadline = created() + chrono::Duration::hours(4);
+        let now = deadline + chrono::Duration::minutes(10);
+        let other = UpdateRequestCondition {
+            type_: "Ready".to_string(),
+            status: "False".to_string(),
+            reason: "AwaitingApproval".to_string(),
+            message: None,
+            last_transition_time: created(),
+        };
+
+        let conditions = with_breach_condition(std::slice::from_ref(&other), deadline, now);
+        assert_eq!(conditions.len(), 2);
+        assert_eq!(conditions[0], other);
+        assert_eq!(conditions[1].type_, SLA_BREACHED_CONDITION);
+        assert_eq!(conditions[1].status, "True");
+        assert_eq!(conditions[1].last_transition_time, now);
+
+        let again = with_breach_condition(&conditions, deadline, now);
+        assert_eq!(again.len(), 2);
+    }
+
+    #[test]
+    fn test_breached_payload() {
+        let payload = breached_payload(&update_request(UpdatePhase::Pending, Some(4)));
+        assert_eq!(
+            payload.title(),
+            "[SLA BREACHED] Update not reviewed in time: Deployment payments/web"
+        );
+        assert!(
+            payload
+                .description()
+                .ends_with("SLA of 4h passed at 2026-03-02 12:00 UTC without a review"),
+            "{}",
+            payload.description()
+        );
+        assert_eq!(payload.update_request_name.as_deref(), Some("web-update"));
+    }
+}
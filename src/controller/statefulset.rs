@@ -1,22 +1,24 @@
 use crate::metrics::{RECONCILE_DURATION, RECONCILE_ERRORS, STATEFULSETS_WATCHED};
 use crate::models::{
-    EventSource, ResourcePolicy, TargetRef, UpdatePolicy, UpdatePolicyType, UpdateRequest,
-    UpdateRequestSpec, UpdateType, annotations,
+    ContainerKind, EventSource, ResourcePolicy, SingleUpdate, TargetRef, UpdatePolicy,
+    UpdatePolicyType, UpdateRequest, UpdateRequestSpec, UpdateType, annotations,
+    global_tag_normalization, parse_container_names, parse_slsa_level, parse_tag_normalization,
 };
 use crate::notifications::{self, DeploymentInfo};
 use crate::policy::PolicyEngine;
+use crate::policy::ignore::{ignore_patterns, is_image_ignored, is_resource_ignored};
+use crate::polling::digest::{split_tag, strip_digest, target_image};
+use crate::schedule::PendingUpdate;
 use anyhow::Result;
 use chrono::Utc;
 use futures::StreamExt;
 use k8s_openapi::api::apps::v1::StatefulSet;
+use k8s_openapi::api::core::v1::{Container, PodSpec};
 use kube::{
-    ResourceExt,
+    Resource, ResourceExt,
     api::{Api, Patch, PatchParams, PostParams},
     client::Client,
-    runtime::{
-        controller::{Action, Controller},
-        watcher::Config,
-    },
+    runtime::controller::Action,
 };
 use serde_json::json;
 use std::sync::Arc;
@@ -48,11 +50,11 @@ impl StatefulSetController {
         const MAX_BACKOFF: u64 = 60;
 
         loop {
-            let statefulsets: Api<StatefulSet> = Api::all(self.client.clone());
+            let statefulsets: Api<StatefulSet> = super::watched_api(self.client.clone());
 
             info!("Creating controller for statefulsets");
 
-            let result = Controller::new(statefulsets, Config::default())
+            let result = super::queue_metrics::controller(statefulsets, "statefulset")
                 .run(
                     reconcile,
                     error_policy,
@@ -107,6 +109,8 @@ async fn reconcile(
     _ctx: Arc<ControllerContext>,
 ) -> Result<Action, kube::Error> {
     let _timer = RECONCILE_DURATION.start_timer();
+    let _queue_timer = super::queue_metrics::start_reconcile("statefulset", statefulset.as_ref());
+    let _in_flight = super::concurrency::track("statefulset");
 
     let namespace = statefulset.namespace().unwrap_or_default();
     let name = statefulset.name_any();
@@ -125,6 +129,11 @@ async fn reconcile(
 
     let annotations = annotations.unwrap();
 
+    if is_resource_ignored(annotations) {
+        debug!("StatefulSet {}/{} is ignored, skipping", namespace, name);
+        return Ok(Action::requeue(Duration::from_secs(300)));
+    }
+
     // Check if this statefulset has headwind annotations
     if !annotations.contains_key(annotations::POLICY) {
         debug!(
@@ -181,9 +190,10 @@ fn create_error(msg: &str) -> kube::Error {
     })
 }
 
-/// Parse an image string into (image_name, tag)
+/// Parse an image string into (image_name, tag), ignoring any pinned digest
 /// Example: "myregistry.com/myimage:v1.2.3" -> ("myregistry.com/myimage", "v1.2.3")
 fn parse_image(image: &str) -> Result<(String, String), String> {
+    let image = strip_digest(image);
     let parts: Vec<&str> = image.rsplitn(2, ':').collect();
     if parts.len() != 2 {
         return Err(format!("Invalid image format: {}", image));
@@ -191,6 +201,38 @@ fn parse_image(image: &str) -> Result<(String, String), String> {
     Ok((parts[1].to_string(), parts[0].to_string()))
 }
 
+/// Containers whose images are tracked under `policy`: the targeted regular
+/// containers, followed by the targeted init containers when
+/// `headwind.sh/include-init-containers` is set, without images matching
+/// `HEADWIND_IGNORE_IMAGES`
+pub fn get_tracked_images<'a>(
+    template_spec: &'a PodSpec,
+    policy: &ResourcePolicy,
+) -> Vec<(ContainerKind, &'a Container)> {
+    let init_containers = template_spec
+        .init_containers
+        .as_deref()
+        .filter(|_| policy.include_init_containers)
+        .unwrap_or_default();
+
+    template_spec
+        .containers
+        .iter()
+        .map(|c| (ContainerKind::Container, c))
+        .chain(
+            init_containers
+                .iter()
+                .map(|c| (ContainerKind::InitContainer, c)),
+        )
+        .filter(|(_, c)| policy.targets_container(&c.name))
+        .filter(|(_, c)| {
+            !c.image
+                .as_deref()
+                .is_some_and(|image| is_image_ignored(ignore_patterns(), image))
+        })
+        .collect()
+}
+
 /// Handle an available image update for a statefulset
 /// This is called when we detect a new version is available (via webhook or polling)
 #[allow(dead_code)]
@@ -231,30 +273,41 @@ pub async fn handle_image_update(
         .as_ref()
         .ok_or_else(|| anyhow::anyhow!("StatefulSet template has no spec"))?;
 
-    let containers = &template_spec.containers;
-
-    // Find container with matching image
-    let mut current_version = None;
-    for container in containers {
+    // Find the targeted container with matching image
+    let mut current = None;
+    for (container_kind, container) in get_tracked_images(template_spec, &policy) {
         let empty_image = String::new();
         let container_image = container.image.as_ref().unwrap_or(&empty_image);
         let (img_name, img_tag) = parse_image(container_image)
             .map_err(|e| anyhow::anyhow!("Failed to parse container image: {}", e))?;
 
         if img_name == image || container_image.starts_with(image) {
-            current_version = Some(img_tag);
+            current = Some((container.name.clone(), container_kind, img_tag));
             break;
         }
     }
 
-    let current_version = current_version
-        .ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
+    let (container_name, container_kind, current_version) =
+        current.ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
 
     debug!(
         "Current version: {}, new version: {}",
         current_version, new_version
     );
 
+    // OPA decides before the update policy is evaluated
+    if !crate::policy::opa::check_update(
+        client,
+        &statefulset.object_ref(&()),
+        &policy,
+        &format!("{}:{}", image, current_version),
+        &format!("{}:{}", image, new_version),
+    )
+    .await
+    {
+        return Ok(());
+    }
+
     // Check if we should update based on policy
     let should_update = policy_engine
         .should_update(&policy, &current_version, new_version)
@@ -294,8 +347,61 @@ pub async fn handle_image_update(
         }
     }
 
+    // SLSA provenance: only images built by a trusted builder
+    if !crate::security::slsa::check_provenance(
+        client,
+        &statefulset.object_ref(&()),
+        &policy,
+        &format!("{}:{}", image, new_version),
+    )
+    .await
+    {
+        return Ok(());
+    }
+
+    // Digest pinning: deploy the digest the new tag currently resolves to
+    let target = target_image(
+        client,
+        &namespace,
+        Some(annotations),
+        &format!("{}:{}", image, new_version),
+    )
+    .await?;
+    let new_version = split_tag(target.as_str()).map_or(new_version, |(_, version)| version);
+
     // Check if approval is required
-    if policy.require_approval {
+    if policy.require_approval && policy.batch_mode {
+        info!(
+            "Adding container {} to BatchUpdateRequest for statefulset {}/{}: {} -> {}",
+            container_name, namespace, name, current_version, new_version
+        );
+
+        super::add_to_batch(
+            client,
+            TargetRef {
+                api_version: "apps/v1".to_string(),
+                kind: "StatefulSet".to_string(),
+                name: name.clone(),
+                namespace: namespace.clone(),
+            },
+            SingleUpdate {
+                container_name: container_name.clone(),
+                current_image: format!("{}:{}", image, current_version),
+                new_image: format!("{}:{}", image, new_version),
+            },
+            map_policy_to_crd(&policy.policy),
+        )
+        .await?;
+    } else if policy.require_approval {
+        if !super::rate_limit::allow_update_request(
+            "StatefulSet",
+            &namespace,
+            &name,
+            Some(annotations),
+        ) {
+            return Ok(());
+        }
+
         info!(
             "Creating UpdateRequest for statefulset {}/{}: {} -> {}",
             namespace, name, current_version, new_version
@@ -308,9 +414,47 @@ pub async fn handle_image_update(
             image,
             &current_version,
             new_version,
+            &container_name,
+            container_kind,
             &policy,
+            annotations,
         )
         .await?;
+    } else if let Some((schedule, scheduled_for)) =
+        crate::schedule::deferred_until(annotations, Utc::now())
+    {
+        let client = client.clone();
+        let current_image = format!("{}:{}", image, current_version);
+        let new_image = format!("{}:{}", image, new_version);
+        let (image, new_version) = (image.to_string(), new_version.to_string());
+
+        crate::schedule::defer_update(
+            PendingUpdate {
+                kind: "StatefulSet".to_string(),
+                namespace: namespace.clone(),
+                name: name.clone(),
+                container: Some(container_name.clone()),
+                current_image: current_image.clone(),
+                new_image: new_image.clone(),
+                policy: policy.policy,
+                schedule,
+                scheduled_for,
+                queued_at: Utc::now(),
+            },
+            move || async move {
+                update_statefulset_image(&client, &namespace, &name, &image, &new_version).await?;
+                notifications::notify_update_completed(DeploymentInfo {
+                    name,
+                    namespace,
+                    current_image,
+                    new_image,
+                    container: Some(container_name),
+                    resource_kind: Some("StatefulSet".to_string()),
+                });
+                Ok(())
+            },
+        )
+        .await;
     } else {
         info!(
             "Auto-updating statefulset {}/{} (no approval required): {} -> {}",
@@ -326,7 +470,7 @@ pub async fn handle_image_update(
             namespace: namespace.clone(),
             current_image: format!("{}:{}", image, current_version),
             new_image: format!("{}:{}", image, new_version),
-            container: None,
+            container: Some(container_name),
             resource_kind: Some("StatefulSet".to_string()),
         });
     }
@@ -336,6 +480,7 @@ pub async fn handle_image_update(
 
 /// Create an UpdateRequest CRD for a pending update
 #[allow(dead_code)]
+#[allow(clippy::too_many_arguments)]
 async fn create_update_request(
     client: &Client,
     namespace: &str,
@@ -343,15 +488,19 @@ async fn create_update_request(
     image: &str,
     current_version: &str,
     new_version: &str,
+    container_name: &str,
+    container_kind: ContainerKind,
     policy: &ResourcePolicy,
+    resource_annotations: &std::collections::BTreeMap<String, String>,
 ) -> Result<()> {
     let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), namespace);
 
-    // Create a unique name for the update request
+    // Create a unique name for the update request, without any pinned digest
+    let new_tag = strip_digest(new_version);
     let request_name = format!(
         "{}-{}",
         name,
-        new_version.replace([':', '.', '/'], "-").to_lowercase()
+        new_tag.replace([':', '.', '/'], "-").to_lowercase()
     );
 
     debug!(
@@ -373,16 +522,20 @@ async fn create_update_request(
                 namespace: namespace.to_string(),
             },
             update_type: UpdateType::Image,
-            container_name: None,
+            container_name: Some(container_name.to_string()),
+            container_kind,
             current_image: format!("{}:{}", image, current_version),
             new_image: format!("{}:{}", image, new_version),
             policy: map_policy_to_crd(&policy.policy),
-            reason: Some(format!(
-                "Update from {} to {}",
-                current_version, new_version
-            )),
+            reason: Some(format!("Update from {} to {}", current_version, new_tag)),
             require_approval: true,
             expires_at: Some(Utc::now() + chrono::Duration::hours(24)),
+            scheduled_at: None,
+            priority: None,
+            updates: Vec::new(),
+            discussion_thread: Vec::new(),
+            sla_deadline: super::sla::sla_deadline(Some(resource_annotations), chrono::Utc::now()),
+            impact: None,
         },
         status: None,
     };
@@ -412,6 +565,12 @@ async fn create_update_request(
                     update_requests
                         .create(&PostParams::default(), &update_request)
                         .await?;
+                    crate::integrations::jira::open_issue_for_update_request(
+                        client,
+                        Some(resource_annotations),
+                        &update_request,
+                    )
+                    .await;
                 }
             }
         },
@@ -420,6 +579,12 @@ async fn create_update_request(
             update_requests
                 .create(&PostParams::default(), &update_request)
                 .await?;
+            crate::integrations::jira::open_issue_for_update_request(
+                client,
+                Some(resource_annotations),
+                &update_request,
+            )
+            .await;
             info!(
                 "Created UpdateRequest {}/{} for statefulset {}",
                 namespace, request_name, name
@@ -436,7 +601,7 @@ async fn create_update_request(
 
 /// Map internal UpdatePolicy to CRD UpdatePolicyType
 #[allow(dead_code)]
-fn map_policy_to_crd(policy: &UpdatePolicy) -> UpdatePolicyType {
+pub(super) fn map_policy_to_crd(policy: &UpdatePolicy) -> UpdatePolicyType {
     match policy {
         UpdatePolicy::Patch => UpdatePolicyType::Patch,
         UpdatePolicy::Minor => UpdatePolicyType::Minor,
@@ -448,37 +613,8 @@ fn map_policy_to_crd(policy: &UpdatePolicy) -> UpdatePolicyType {
     }
 }
 
-/// Simple glob pattern matching (supports * and ?)
-#[allow(dead_code)]
-fn glob_match(pattern: &str, text: &str) -> bool {
-    let pattern_chars: Vec<char> = pattern.chars().collect();
-    let text_chars: Vec<char> = text.chars().collect();
-
-    glob_match_impl(&pattern_chars, &text_chars, 0, 0)
-}
-
-#[allow(dead_code)]
-fn glob_match_impl(pattern: &[char], text: &[char], pi: usize, ti: usize) -> bool {
-    if pi >= pattern.len() && ti >= text.len() {
-        return true;
-    }
-    if pi >= pattern.len() {
-        return false;
-    }
-
-    if pattern[pi] == '*' {
-        // Try matching zero or more characters
-        glob_match_impl(pattern, text, pi + 1, ti)
-            || (ti < text.len() && glob_match_impl(pattern, text, pi, ti + 1))
-    } else if ti < text.len() && (pattern[pi] == '?' || pattern[pi] == text[ti]) {
-        glob_match_impl(pattern, text, pi + 1, ti + 1)
-    } else {
-        false
-    }
-}
-
 /// Parse ResourcePolicy from Deployment annotations
-fn parse_policy_from_annotations(
+pub(super) fn parse_policy_from_annotations(
     annotations: &std::collections::BTreeMap<String, String>,
 ) -> Result<ResourcePolicy> {
     let policy_str = annotations
@@ -523,14 +659,53 @@ fn parse_policy_from_annotations(
         .get(annotations::POLLING_INTERVAL)
         .and_then(|v| v.parse::<u64>().ok());
 
+    let batch_mode = annotations
+        .get(annotations::BATCH_UPDATES)
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    let container_names = annotations
+        .get(annotations::CONTAINER_NAMES)
+        .map(|v| parse_container_names(v))
+        .unwrap_or_default();
+
+    let tag_normalization = parse_tag_normalization(annotations, &global_tag_normalization())
+        .unwrap_or_else(|_| global_tag_normalization());
+
+    let include_init_containers = annotations
+        .get(annotations::INCLUDE_INIT_CONTAINERS)
+        .and_then(|v| v.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    let require_slsa_level = annotations
+        .get(annotations::REQUIRE_SLSA_LEVEL)
+        .map(|v| parse_slsa_level(v))
+        .transpose()?;
+
+    let slsa_builder = annotations.get(annotations::SLSA_BUILDER).cloned();
+
+    let opa_policy = annotations.get(annotations::OPA_POLICY).cloned();
+
     Ok(ResourcePolicy {
         policy,
         pattern,
         require_approval,
         min_update_interval,
         images,
+        container_names,
         event_source,
         polling_interval,
+        batch_mode,
+        tag_normalization,
+        observers: annotations
+            .get(annotations::NOTIFY_OBSERVERS)
+            .map(|v| crate::models::parse_observers(v))
+            .unwrap_or_default(),
+        helm_version_constraint: None,
+        include_init_containers,
+        require_slsa_level,
+        slsa_builder,
+        opa_policy,
     })
 }
 
@@ -565,33 +740,7 @@ pub async fn update_statefulset_image_with_tracking(
         namespace, name, new_image
     );
 
-    // Create strategic merge patch to update the container image
-    // We need to find which container to update
     let statefulset = statefulsets.get(name).await?;
-    let spec = statefulset
-        .spec
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("StatefulSet has no spec"))?;
-
-    let template_spec = spec
-        .template
-        .spec
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("StatefulSet template has no spec"))?;
-
-    // Find the container index
-    let mut container_index = None;
-    for (idx, container) in template_spec.containers.iter().enumerate() {
-        if let Some(container_image) = &container.image
-            && container_image.starts_with(image)
-        {
-            container_index = Some(idx);
-            break;
-        }
-    }
-
-    let container_index = container_index
-        .ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?;
 
     // Update last-update annotation with timestamp
     let now = Utc::now();
@@ -601,23 +750,8 @@ pub async fn update_statefulset_image_with_tracking(
         now.to_rfc3339()
     };
 
-    let patch = json!({
-        "spec": {
-            "template": {
-                "spec": {
-                    "containers": [{
-                        "name": template_spec.containers[container_index].name,
-                        "image": new_image
-                    }]
-                }
-            }
-        },
-        "metadata": {
-            "annotations": {
-                annotations::LAST_UPDATE: last_update_value
-            }
-        }
-    });
+    let patch =
+        build_statefulset_image_patch(&statefulset, image, new_version, &last_update_value)?;
 
     statefulsets
         .patch(
@@ -635,9 +769,93 @@ pub async fn update_statefulset_image_with_tracking(
     Ok(())
 }
 
+/// The strategic merge patch `update_statefulset_image_with_tracking` applies
+/// to move the container running `image` to `new_version`
+pub fn build_statefulset_image_patch(
+    statefulset: &StatefulSet,
+    image: &str,
+    new_version: &str,
+    last_update_value: &str,
+) -> Result<serde_json::Value> {
+    let template_spec = statefulset
+        .spec
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("StatefulSet has no spec"))?
+        .template
+        .spec
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("StatefulSet template has no spec"))?;
+
+    let include_init_containers = statefulset
+        .annotations()
+        .get(annotations::INCLUDE_INIT_CONTAINERS)
+        .is_some_and(|v| v == "true");
+    let uses_image = |c: &&Container| c.image.as_ref().is_some_and(|i| i.starts_with(image));
+
+    // Find the container, and init container when tracked, to update
+    let containers: Vec<&Container> = template_spec
+        .containers
+        .iter()
+        .find(uses_image)
+        .into_iter()
+        .collect();
+    let init_containers: Vec<&Container> = template_spec
+        .init_containers
+        .as_deref()
+        .filter(|_| include_init_containers)
+        .unwrap_or_default()
+        .iter()
+        .find(uses_image)
+        .into_iter()
+        .collect();
+
+    let previous_image = containers
+        .iter()
+        .chain(&init_containers)
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Container with image {} not found", image))?
+        .image
+        .clone();
+
+    let new_image = format!("{}:{}", image, new_version);
+    let entries = |containers: &[&Container]| -> Vec<serde_json::Value> {
+        containers
+            .iter()
+            .map(|c| json!({ "name": c.name, "image": new_image }))
+            .collect()
+    };
+
+    let mut pod_spec = serde_json::Map::new();
+    if !containers.is_empty() {
+        pod_spec.insert("containers".to_string(), entries(&containers).into());
+    }
+    if !init_containers.is_empty() {
+        pod_spec.insert(
+            "initContainers".to_string(),
+            entries(&init_containers).into(),
+        );
+    }
+
+    let mut patch = json!({
+        "spec": {
+            "template": {
+                "spec": pod_spec
+            }
+        },
+        "metadata": {
+            "annotations": {
+                annotations::LAST_UPDATE: last_update_value
+            }
+        }
+    });
+    super::set_previous_image(&mut patch, previous_image.as_deref(), &new_image);
+    Ok(patch)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::policy::glob_match;
 
     #[test]
     fn test_parse_image() {
@@ -648,6 +866,11 @@ mod tests {
         let (name, tag) = parse_image("nginx:latest").unwrap();
         assert_eq!(name, "nginx");
         assert_eq!(tag, "latest");
+
+        // A pinned digest isn't part of the tag
+        let (name, tag) = parse_image("nginx:1.26.0@sha256:abc").unwrap();
+        assert_eq!(name, "nginx");
+        assert_eq!(tag, "1.26.0");
     }
 
     #[test]
@@ -685,4 +908,143 @@ mod tests {
         assert!(!policy.require_approval);
         assert_eq!(policy.min_update_interval, Some(600));
     }
+
+    fn statefulset(include_init_containers: bool) -> StatefulSet {
+        let mut annotations = serde_json::Map::new();
+        if include_init_containers {
+            annotations.insert(
+                annotations::INCLUDE_INIT_CONTAINERS.to_string(),
+                "true".into(),
+            );
+        }
+        serde_json::from_value(json!({
+            "metadata": { "name": "postgres", "annotations": annotations },
+            "spec": {
+                "selector": {},
+                "serviceName": "postgres",
+                "template": {
+                    "spec": {
+                        "initContainers": [
+                            { "name": "migrate", "image": "myapp/migrations:1.4.0" },
+                            { "name": "init-db", "image": "postgres:16.1" }
+                        ],
+                        "containers": [
+                            { "name": "postgres", "image": "postgres:16.1" }
+                        ]
+                    }
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_get_tracked_images() {
+        let sts = statefulset(false);
+        let template_spec = sts.spec.as_ref().unwrap().template.spec.as_ref().unwrap();
+        let mut policy = ResourcePolicy::default();
+
+        let tracked = get_tracked_images(template_spec, &policy);
+        assert_eq!(tracked.len(), 1);
+        assert_eq!(tracked[0].0, ContainerKind::Container);
+
+        policy.include_init_containers = true;
+        let tracked: Vec<_> = get_tracked_images(template_spec, &policy)
+            .into_iter()
+            .map(|(kind, c)| (kind, c.name.as_str()))
+            .collect();
+        assert_eq!(
+            tracked,
+            vec![
+                (ContainerKind::Container, "postgres"),
+                (ContainerKind::InitContainer, "migrate"),
+                (ContainerKind::InitContainer, "init-db"),
+            ]
+        );
+
+        policy.container_names = vec!["migrate".to_string()];
+        let tracked = get_tracked_images(template_spec, &policy);
+        assert_eq!(tracked.len(), 1);
+        assert_eq!(tracked[0].1.name, "migrate");
+    }
+
+    #[test]
+    fn test_parse_include_init_containers() {
+        let mut annotations = std::collections::BTreeMap::new();
+        annotations.insert(annotations::POLICY.to_string(), "patch".to_string());
+        assert!(
+            !parse_policy_from_annotations(&annotations)
+                .unwrap()
+                .include_init_containers
+        );
+
+        annotations.insert(
+            annotations::INCLUDE_INIT_CONTAINERS.to_string(),
+            "true".to_string(),
+        );
+        assert!(
+            parse_policy_from_annotations(&annotations)
+                .unwrap()
+                .include_init_containers
+        );
+    }
+
+    #[test]
+    fn test_patch_init_container_only() {
+        let patch = build_statefulset_image_patch(
+            &statefulset(true),
+            "myapp/migrations",
+            "1.5.0",
+            "2025-01-01T00:00:00Z",
+        )
+        .unwrap();
+
+        let pod_spec = &patch["spec"]["template"]["spec"];
+        assert!(pod_spec.get("containers").is_none());
+        assert_eq!(
+            pod_spec["initContainers"],
+            json!([{ "name": "migrate", "image": "myapp/migrations:1.5.0" }])
+        );
+        assert_eq!(
+            patch["metadata"]["annotations"][annotations::PREVIOUS_IMAGE],
+            "myapp/migrations:1.4.0"
+        );
+    }
+
+    #[test]
+    fn test_patch_containers_and_init_containers() {
+        let patch = build_statefulset_image_patch(
+            &statefulset(true),
+            "postgres",
+            "16.2",
+            "2025-01-01T00:00:00Z",
+        )
+        .unwrap();
+
+        let pod_spec = &patch["spec"]["template"]["spec"];
+        assert_eq!(
+            pod_spec["containers"],
+            json!([{ "name": "postgres", "image": "postgres:16.2" }])
+        );
+        assert_eq!(
+            pod_spec["initContainers"],
+            json!([{ "name": "init-db", "image": "postgres:16.2" }])
+        );
+    }
+
+    #[test]
+    fn test_patch_ignores_init_containers_by_default() {
+        let patch =
+            build_statefulset_image_patch(&statefulset(false), "postgres", "16.2", "now").unwrap();
+        assert!(
+            patch["spec"]["template"]["spec"]
+                .get("initContainers")
+                .is_none()
+        );
+
+        assert!(
+            build_statefulset_image_patch(&statefulset(false), "myapp/migrations", "1.5.0", "now")
+                .is_err()
+        );
+    }
 }
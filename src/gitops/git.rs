@@ -0,0 +1,675 @@
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::process::Command;
+use tracing::{debug, info};
+
+lazy_static! {
+    /// One lock per checkout directory, so concurrent reconciles of resources
+    /// sharing a repository don't fetch, reset and push over each other
+    static ref WORKDIR_LOCKS: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Authentication used when cloning and pushing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum GitAuth {
+    /// No credentials (public repository or credentials configured externally)
+    #[default]
+    None,
+    /// HTTPS with a username and personal access token
+    Https { username: String, token: String },
+    /// SSH with a private key file
+    Ssh { private_key_path: String },
+}
+
+impl GitAuth {
+    /// Load git credentials from environment variables
+    ///
+    /// - `HEADWIND_GIT_SSH_KEY_PATH` selects SSH auth
+    /// - `HEADWIND_GIT_TOKEN` (and optional `HEADWIND_GIT_USERNAME`, default `git`) selects HTTPS auth
+    pub fn from_env() -> Self {
+        if let Ok(private_key_path) = std::env::var("HEADWIND_GIT_SSH_KEY_PATH") {
+            return GitAuth::Ssh { private_key_path };
+        }
+
+        if let Ok(token) = std::env::var("HEADWIND_GIT_TOKEN") {
+            let username =
+                std::env::var("HEADWIND_GIT_USERNAME").unwrap_or_else(|_| "git".to_string());
+            return GitAuth::Https { username, token };
+        }
+
+        GitAuth::None
+    }
+}
+
+/// Pushes image tag changes to a git repository instead of patching Kubernetes
+#[derive(Debug, Clone)]
+pub struct GitUpdater {
+    pub repo_url: String,
+    pub branch: String,
+    pub file_path: String,
+    pub auth: GitAuth,
+    /// Local checkout directory (reused between updates)
+    pub workdir: PathBuf,
+    /// Hosts repositories may be pushed to, from `HEADWIND_GIT_ALLOWED_HOSTS`.
+    /// Credentials are only sent to these hosts.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl GitUpdater {
+    pub fn new(repo_url: String, branch: String, file_path: String, auth: GitAuth) -> Self {
+        let base = std::env::var("HEADWIND_GIT_WORKDIR")
+            .unwrap_or_else(|_| "/tmp/headwind-git".to_string());
+        let workdir = Path::new(&base).join(sanitize_dir_name(&repo_url));
+        let allowed_hosts = std::env::var("HEADWIND_GIT_ALLOWED_HOSTS")
+            .map(|hosts| parse_allowed_hosts(&hosts))
+            .unwrap_or_default();
+
+        Self {
+            repo_url,
+            branch,
+            file_path,
+            auth,
+            workdir,
+            allowed_hosts,
+        }
+    }
+
+    /// Clone (or pull) the repository, replace the image tag in `file_path`,
+    /// commit and push. Returns the SHA of the new commit.
+    pub async fn apply_update(&self, image: &str, new_tag: &str) -> Result<String> {
+        self.check_settings()?;
+
+        let lock = workdir_lock(&self.workdir);
+        let _guard = lock.lock().await;
+
+        self.sync_repository().await?;
+
+        let target = self.resolve_file().await?;
+        let content = tokio::fs::read_to_string(&target)
+            .await
+            .with_context(|| format!("Failed to read {}", self.file_path))?;
+
+        let updated = replace_image_tag(&content, image, new_tag)
+            .ok_or_else(|| anyhow::anyhow!("Image {} not found in {}", image, self.file_path))?;
+
+        if updated == content {
+            info!(
+                "{} already references {}:{}, nothing to commit",
+                self.file_path, image, new_tag
+            );
+            return self.head_sha().await;
+        }
+
+        tokio::fs::write(&target, updated)
+            .await
+            .with_context(|| format!("Failed to write {}", self.file_path))?;
+
+        let message = commit_message(image, new_tag);
+        self.git(&["add", "--", &self.file_path]).await?;
+        self.git(&[
+            "-c",
+            "user.name=headwind",
+            "-c",
+            "user.email=headwind@headwind.sh",
+            "commit",
+            "-m",
+            &message,
+        ])
+        .await?;
+
+        let refspec = format!("HEAD:{}", self.branch);
+        self.git(&["push", "--", "origin", &refspec]).await?;
+
+        let sha = self.head_sha().await?;
+        info!(
+            "Pushed commit {} updating {} to {} in {}",
+            sha, image, new_tag, self.file_path
+        );
+
+        Ok(sha)
+    }
+
+    /// Refuse settings that would make git write outside the checkout, parse
+    /// an annotation value as an option or send credentials to an unlisted host
+    fn check_settings(&self) -> Result<()> {
+        if !is_safe_file_path(&self.file_path) {
+            return Err(anyhow::anyhow!(
+                "Refusing to update {}: the file must be a relative path inside the repository",
+                self.file_path
+            ));
+        }
+        if !is_valid_branch(&self.branch) {
+            return Err(anyhow::anyhow!(
+                "Refusing to push to invalid branch name {:?}",
+                self.branch
+            ));
+        }
+        if !is_allowed_repo_url(&self.repo_url) {
+            return Err(anyhow::anyhow!(
+                "Refusing to push to {}: only https://, ssh:// and user@host:path repositories are supported",
+                self.repo_url
+            ));
+        }
+
+        // The credentials are the operator's, annotations may point anywhere
+        let restricted = !self.allowed_hosts.is_empty() || self.auth != GitAuth::None;
+        let allowed =
+            repo_host(&self.repo_url).is_some_and(|host| self.allowed_hosts.contains(&host));
+        if restricted && !allowed {
+            return Err(anyhow::anyhow!(
+                "Refusing to push to {}: its host is not listed in HEADWIND_GIT_ALLOWED_HOSTS",
+                self.repo_url
+            ));
+        }
+        Ok(())
+    }
+
+    /// Clone the repository, or fetch and reset an existing checkout to the remote branch
+    async fn sync_repository(&self) -> Result<()> {
+        if self.workdir.join(".git").exists() {
+            debug!("Updating existing checkout in {}", self.workdir.display());
+            // Checkouts cloned with credentials in the URL kept them in .git/config
+            self.git(&["remote", "set-url", "--", "origin", &self.repo_url])
+                .await?;
+            self.git(&["fetch", "--", "origin", &self.branch]).await?;
+            let remote_ref = format!("origin/{}", self.branch);
+            self.git(&["reset", "--hard", &remote_ref, "--"]).await?;
+            return Ok(());
+        }
+
+        debug!("Cloning repository into {}", self.workdir.display());
+        if let Some(parent) = self.workdir.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let workdir = self.workdir.to_string_lossy().to_string();
+        run_git(
+            None,
+            &self.auth,
+            &[
+                "clone",
+                "--branch",
+                &self.branch,
+                "--",
+                &self.repo_url,
+                &workdir,
+            ],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// The checked out `file_path`, refusing symlinks that lead out of the
+    /// checkout or into `.git`
+    async fn resolve_file(&self) -> Result<PathBuf> {
+        let workdir = tokio::fs::canonicalize(&self.workdir)
+            .await
+            .with_context(|| format!("Failed to resolve {}", self.workdir.display()))?;
+        let target = tokio::fs::canonicalize(self.workdir.join(&self.file_path))
+            .await
+            .with_context(|| format!("Failed to read {}", self.file_path))?;
+        if !target.starts_with(&workdir) || target.starts_with(workdir.join(".git")) {
+            return Err(anyhow::anyhow!(
+                "Refusing to update {}: it resolves outside the repository",
+                self.file_path
+            ));
+        }
+        Ok(target)
+    }
+
+    async fn head_sha(&self) -> Result<String> {
+        Ok(self.git(&["rev-parse", "HEAD"]).await?.trim().to_string())
+    }
+
+    async fn git(&self, args: &[&str]) -> Result<String> {
+        run_git(Some(&self.workdir), &self.auth, args).await
+    }
+}
+
+async fn run_git(dir: Option<&Path>, auth: &GitAuth, args: &[&str]) -> Result<String> {
+    let mut cmd = Command::new("git");
+    cmd.args(args);
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    cmd.envs(auth_env(auth));
+    cmd.env("GIT_TERMINAL_PROMPT", "0");
+    // Never fall back to transports running local commands (ext::, file://)
+    cmd.env("GIT_ALLOW_PROTOCOL", "https:ssh");
+
+    let output = cmd.output().await.context("Failed to run git")?;
+    if !output.status.success() {
+        // Don't include the arguments, they may contain credentials
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Whether `file_path` names a file inside a checkout: relative, without
+/// `..` components and outside `.git`
+pub fn is_safe_file_path(file_path: &str) -> bool {
+    !file_path.is_empty()
+        && Path::new(file_path).components().all(|c| match c {
+            Component::Normal(part) => !part.eq_ignore_ascii_case(".git"),
+            Component::CurDir => true,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => false,
+        })
+}
+
+/// Whether `branch` is a branch name `git check-ref-format --branch` accepts,
+/// limited to characters that can't be read as an option or revision syntax
+pub fn is_valid_branch(branch: &str) -> bool {
+    branch.len() <= 255
+        && !branch.starts_with(['-', '/'])
+        && !branch.ends_with(['/', '.'])
+        && !branch.contains("..")
+        && branch
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '/' | '-'))
+        && branch
+            .split('/')
+            .all(|part| !part.is_empty() && !part.starts_with('.') && !part.ends_with(".lock"))
+}
+
+/// Whether `repo_url` uses a transport Headwind pushes over: `https://`,
+/// `ssh://` or scp-like `user@host:path`. Local paths and transports running
+/// commands (`file://`, `ext::`) are refused.
+pub fn is_allowed_repo_url(repo_url: &str) -> bool {
+    lazy_static! {
+        static ref SCP_LIKE: Regex = Regex::new(
+            r"^[A-Za-z0-9_][A-Za-z0-9._-]*@[A-Za-z0-9][A-Za-z0-9.-]*:[A-Za-z0-9._~][A-Za-z0-9._~/-]*$"
+        )
+        .unwrap();
+    }
+
+    if SCP_LIKE.is_match(repo_url) {
+        return true;
+    }
+    match reqwest::Url::parse(repo_url) {
+        Ok(url) => {
+            matches!(url.scheme(), "https" | "ssh")
+                && url
+                    .host_str()
+                    .is_some_and(|host| !host.is_empty() && !host.starts_with('-'))
+        },
+        Err(_) => false,
+    }
+}
+
+/// Lowercased hosts of a comma-separated `HEADWIND_GIT_ALLOWED_HOSTS`
+fn parse_allowed_hosts(hosts: &str) -> Vec<String> {
+    hosts
+        .split(',')
+        .map(|host| host.trim().to_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect()
+}
+
+/// Lowercased host of an allowed repository URL, without user or port
+fn repo_host(repo_url: &str) -> Option<String> {
+    let host = match reqwest::Url::parse(repo_url) {
+        Ok(url) if matches!(url.scheme(), "https" | "ssh") => url.host_str()?.to_string(),
+        // scp-like user@host:path
+        _ => repo_url.split_once('@')?.1.split_once(':')?.0.to_string(),
+    };
+    Some(host.to_lowercase())
+}
+
+/// Commit message used for image updates
+pub fn commit_message(image: &str, new_tag: &str) -> String {
+    format!("chore: update {} to {} [headwind]", image, new_tag)
+}
+
+/// Replace the tag of every reference to `image` in `content`.
+/// Returns None if the image is not referenced at all.
+pub fn replace_image_tag(content: &str, image: &str, new_tag: &str) -> Option<String> {
+    // Match the image name not preceded by another path/name character, followed by :tag
+    let pattern = format!(
+        r"(^|[^A-Za-z0-9._/-])({}):([A-Za-z0-9_][A-Za-z0-9._-]{{0,127}})",
+        regex::escape(image)
+    );
+    let re = Regex::new(&pattern).ok()?;
+
+    if !re.is_match(content) {
+        return None;
+    }
+
+    Some(
+        re.replace_all(content, |caps: &regex::Captures| {
+            format!("{}{}:{}", &caps[1], &caps[2], new_tag)
+        })
+        .into_owned(),
+    )
+}
+
+/// Environment passing credentials to a git command. HTTPS tokens go in an
+/// `Authorization` header set through `GIT_CONFIG_*`, so they are never
+/// written to the checkout's `.git/config` nor shown in its remote URL.
+fn auth_env(auth: &GitAuth) -> Vec<(String, String)> {
+    match auth {
+        GitAuth::None => Vec::new(),
+        GitAuth::Https { username, token } => {
+            let credentials = STANDARD.encode(format!("{}:{}", username, token));
+            vec![
+                ("GIT_CONFIG_COUNT".to_string(), "1".to_string()),
+                (
+                    "GIT_CONFIG_KEY_0".to_string(),
+                    "http.extraHeader".to_string(),
+                ),
+                (
+                    "GIT_CONFIG_VALUE_0".to_string(),
+                    format!("Authorization: Basic {}", credentials),
+                ),
+            ]
+        },
+        GitAuth::Ssh { private_key_path } => vec![(
+            "GIT_SSH_COMMAND".to_string(),
+            format!(
+                "ssh -i {} -o IdentitiesOnly=yes -o StrictHostKeyChecking=accept-new",
+                private_key_path
+            ),
+        )],
+    }
+}
+
+fn workdir_lock(workdir: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    WORKDIR_LOCKS
+        .lock()
+        .unwrap()
+        .entry(workdir.to_path_buf())
+        .or_default()
+        .clone()
+}
+
+fn sanitize_dir_name(repo_url: &str) -> String {
+    repo_url
+        .trim_end_matches(".git")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_image_tag() {
+        let content = "containers:\n  - name: app\n    image: ghcr.io/org/app:1.0.0\n";
+        let updated = replace_image_tag(content, "ghcr.io/org/app", "1.1.0").unwrap();
+        assert_eq!(
+            updated,
+            "containers:\n  - name: app\n    image: ghcr.io/org/app:1.1.0\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_image_tag_quoted() {
+        let content = "image: \"nginx:1.25.0\"\n";
+        let updated = replace_image_tag(content, "nginx", "1.26.0").unwrap();
+        assert_eq!(updated, "image: \"nginx:1.26.0\"\n");
+    }
+
+    #[test]
+    fn test_replace_image_tag_does_not_match_other_images() {
+        let content = "image: docker.io/library/nginx-exporter:0.1.0\n";
+        assert!(replace_image_tag(content, "nginx", "1.26.0").is_none());
+
+        let content = "image: my-nginx:1.0.0\n";
+        assert!(replace_image_tag(content, "nginx", "1.26.0").is_none());
+    }
+
+    #[test]
+    fn test_is_safe_file_path() {
+        assert!(is_safe_file_path("apps/web/deployment.yaml"));
+        assert!(is_safe_file_path("./web.yaml"));
+
+        assert!(!is_safe_file_path(""));
+        assert!(!is_safe_file_path("/etc/passwd"));
+        assert!(!is_safe_file_path("../other/web.yaml"));
+        assert!(!is_safe_file_path("apps/../../web.yaml"));
+        assert!(!is_safe_file_path(".git/hooks/post-commit"));
+        assert!(!is_safe_file_path("apps/.GIT/config"));
+    }
+
+    #[test]
+    fn test_is_valid_branch() {
+        for branch in ["main", "release/1.2", "feature_x-y.z"] {
+            assert!(is_valid_branch(branch), "{} was refused", branch);
+        }
+        for branch in [
+            "",
+            "--upload-pack=touch /tmp/pwned",
+            "-b",
+            "/main",
+            "main/",
+            "main.",
+            "a..b",
+            "a//b",
+            ".hidden",
+            "refs/.x",
+            "main.lock",
+            "main@{1}",
+            "a b",
+            "a:b",
+            "a~1",
+        ] {
+            assert!(!is_valid_branch(branch), "{} was accepted", branch);
+        }
+    }
+
+    #[test]
+    fn test_is_allowed_repo_url() {
+        for url in [
+            "https://github.com/org/deploy.git",
+            "ssh://git@github.com/org/deploy.git",
+            "ssh://git@gitlab.example.com:2222/org/deploy.git",
+            "git@github.com:org/deploy.git",
+        ] {
+            assert!(is_allowed_repo_url(url), "{} was refused", url);
+        }
+        for url in [
+            "http://github.com/org/deploy.git",
+            "file:///etc",
+            "/srv/git/deploy.git",
+            "ext::sh -c touch% /tmp/pwned",
+            "--upload-pack=touch /tmp/pwned",
+            "git@github.com::org/deploy.git",
+            "-oProxyCommand=x@host:repo",
+            "ssh://-oProxyCommand=x/repo",
+            "git://github.com/org/deploy.git",
+        ] {
+            assert!(!is_allowed_repo_url(url), "{} was accepted", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_apply_update_refuses_options_in_annotations() {
+        let updater = GitUpdater::new(
+            "https://github.com/org/deploy.git".to_string(),
+            "--upload-pack=touch /tmp/pwned".to_string(),
+            "web.yaml".to_string(),
+            GitAuth::None,
+        );
+        let err = updater.apply_update("nginx", "1.26.0").await.unwrap_err();
+        assert!(err.to_string().contains("invalid branch name"));
+
+        let updater = GitUpdater::new(
+            "ext::sh -c touch% /tmp/pwned".to_string(),
+            "main".to_string(),
+            "web.yaml".to_string(),
+            GitAuth::None,
+        );
+        let err = updater.apply_update("nginx", "1.26.0").await.unwrap_err();
+        assert!(err.to_string().contains("only https://"));
+    }
+
+    #[test]
+    fn test_repo_host() {
+        assert_eq!(
+            repo_host("https://GitHub.com/org/deploy.git").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            repo_host("ssh://git@gitlab.example.com:2222/org/deploy.git").as_deref(),
+            Some("gitlab.example.com")
+        );
+        assert_eq!(
+            repo_host("git@github.com:org/deploy.git").as_deref(),
+            Some("github.com")
+        );
+        assert_eq!(
+            parse_allowed_hosts(" GitHub.com, ,gitlab.example.com"),
+            vec!["github.com", "gitlab.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_credentials_are_only_sent_to_allowed_hosts() {
+        let updater = |repo_url: &str, auth: GitAuth, allowed_hosts: &[&str]| GitUpdater {
+            repo_url: repo_url.to_string(),
+            branch: "main".to_string(),
+            file_path: "web.yaml".to_string(),
+            auth,
+            workdir: PathBuf::from("/tmp/headwind-git/unused"),
+            allowed_hosts: allowed_hosts.iter().map(|h| h.to_string()).collect(),
+        };
+        let token = GitAuth::Https {
+            username: "bot".to_string(),
+            token: "secret".to_string(),
+        };
+
+        // Credentials without an allowlist are never sent
+        assert!(
+            updater("https://github.com/org/deploy.git", token.clone(), &[])
+                .check_settings()
+                .is_err()
+        );
+        assert!(
+            updater(
+                "https://github.com/org/deploy.git",
+                token.clone(),
+                &["github.com"]
+            )
+            .check_settings()
+            .is_ok()
+        );
+        let err = updater("https://attacker.example/x.git", token, &["github.com"])
+            .check_settings()
+            .unwrap_err();
+        assert!(err.to_string().contains("HEADWIND_GIT_ALLOWED_HOSTS"));
+
+        // The allowlist also applies to public repositories
+        assert!(
+            updater("https://github.com/org/deploy.git", GitAuth::None, &[])
+                .check_settings()
+                .is_ok()
+        );
+        assert!(
+            updater("git@attacker.example:x.git", GitAuth::None, &["github.com"])
+                .check_settings()
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_file_refuses_symlinks_out_of_the_checkout() {
+        let base = std::env::temp_dir().join(format!("headwind-git-test-{}", std::process::id()));
+        let workdir = base.join("checkout");
+        std::fs::create_dir_all(workdir.join(".git")).unwrap();
+        std::fs::write(workdir.join("web.yaml"), "image: nginx:1.25.0\n").unwrap();
+        std::fs::write(base.join("outside.yaml"), "image: nginx:1.25.0\n").unwrap();
+        std::os::unix::fs::symlink(base.join("outside.yaml"), workdir.join("escape.yaml")).unwrap();
+        std::os::unix::fs::symlink(workdir.join(".git"), workdir.join("meta")).unwrap();
+
+        let updater = |file_path: &str| GitUpdater {
+            repo_url: "https://github.com/org/deploy.git".to_string(),
+            branch: "main".to_string(),
+            file_path: file_path.to_string(),
+            auth: GitAuth::None,
+            workdir: workdir.clone(),
+            allowed_hosts: Vec::new(),
+        };
+        assert!(updater("web.yaml").resolve_file().await.is_ok());
+        assert!(updater("escape.yaml").resolve_file().await.is_err());
+        assert!(updater("meta/config").resolve_file().await.is_err());
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_apply_update_refuses_paths_outside_the_checkout() {
+        let updater = GitUpdater::new(
+            "https://github.com/org/deploy.git".to_string(),
+            "main".to_string(),
+            "../../etc/passwd".to_string(),
+            GitAuth::None,
+        );
+        let err = updater.apply_update("nginx", "1.26.0").await.unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("relative path inside the repository")
+        );
+    }
+
+    #[test]
+    fn test_commit_message() {
+        assert_eq!(
+            commit_message("nginx", "1.26.0"),
+            "chore: update nginx to 1.26.0 [headwind]"
+        );
+    }
+
+    #[test]
+    fn test_auth_env_keeps_token_out_of_url() {
+        let auth = GitAuth::Https {
+            username: "bot".to_string(),
+            token: "secret".to_string(),
+        };
+        let env: HashMap<String, String> = auth_env(&auth).into_iter().collect();
+        assert_eq!(env["GIT_CONFIG_COUNT"], "1");
+        assert_eq!(env["GIT_CONFIG_KEY_0"], "http.extraHeader");
+        // base64("bot:secret")
+        assert_eq!(
+            env["GIT_CONFIG_VALUE_0"],
+            "Authorization: Basic Ym90OnNlY3JldA=="
+        );
+
+        let auth = GitAuth::Ssh {
+            private_key_path: "/keys/id".to_string(),
+        };
+        let env: HashMap<String, String> = auth_env(&auth).into_iter().collect();
+        assert!(env["GIT_SSH_COMMAND"].starts_with("ssh -i /keys/id "));
+
+        assert!(auth_env(&GitAuth::None).is_empty());
+    }
+
+    #[test]
+    fn test_workdir_lock_is_shared_per_directory() {
+        let a = workdir_lock(Path::new("/tmp/headwind-git/repo-a"));
+        let b = workdir_lock(Path::new("/tmp/headwind-git/repo-a"));
+        let c = workdir_lock(Path::new("/tmp/headwind-git/repo-b"));
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+    }
+
+    #[test]
+    fn test_sanitize_dir_name() {
+        assert_eq!(
+            sanitize_dir_name("https://github.com/org/repo.git"),
+            "https---github-com-org-repo"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+pub mod git;
+
+use crate::models::crd::{UpdateRequest, UpdateRequestSpec, UpdateRequestStatus};
+use crate::models::policy::annotations;
+use anyhow::Result;
+use kube::{
+    Api, Client,
+    api::{Patch, PatchParams, PostParams},
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::{error, warn};
+
+pub use git::{GitAuth, GitUpdater};
+
+/// Value of `headwind.sh/gitops-mode` that enables git push mode
+pub const GIT_PUSH_MODE: &str = "git-push";
+
+/// Git push settings parsed from resource annotations
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitOpsConfig {
+    pub repo_url: String,
+    pub file_path: String,
+    pub branch: String,
+}
+
+impl GitOpsConfig {
+    /// Returns Some when `headwind.sh/gitops-mode: git-push` is set along with
+    /// `headwind.sh/git-repo-url` and `headwind.sh/git-file-path`, the file
+    /// path stays inside the repository and the URL and branch are safe to
+    /// pass to git
+    pub fn from_annotations(annotations: &BTreeMap<String, String>) -> Option<Self> {
+        if annotations
+            .get(annotations::GITOPS_MODE)
+            .map(String::as_str)
+            != Some(GIT_PUSH_MODE)
+        {
+            return None;
+        }
+
+        let repo_url = annotations
+            .get(annotations::GIT_REPO_URL)?
+            .trim()
+            .to_string();
+        let file_path = annotations
+            .get(annotations::GIT_FILE_PATH)?
+            .trim()
+            .to_string();
+        if repo_url.is_empty() || file_path.is_empty() {
+            return None;
+        }
+        if !git::is_safe_file_path(&file_path) {
+            warn!(
+                "Ignoring {} {}: it must be a relative path inside the repository",
+                annotations::GIT_FILE_PATH,
+                file_path
+            );
+            return None;
+        }
+
+        let branch = annotations
+            .get(annotations::GIT_BRANCH)
+            .map(|b| b.trim().to_string())
+            .filter(|b| !b.is_empty())
+            .unwrap_or_else(|| "main".to_string());
+        if !git::is_valid_branch(&branch) {
+            warn!("Ignoring invalid {} {:?}", annotations::GIT_BRANCH, branch);
+            return None;
+        }
+        if !git::is_allowed_repo_url(&repo_url) {
+            warn!(
+                "Ignoring {} {}: only https://, ssh:// and user@host:path repositories are supported",
+                annotations::GIT_REPO_URL,
+                repo_url
+            );
+            return None;
+        }
+
+        Some(Self {
+            repo_url,
+            file_path,
+            branch,
+        })
+    }
+
+    pub fn updater(&self) -> GitUpdater {
+        GitUpdater::new(
+            self.repo_url.clone(),
+            self.branch.clone(),
+            self.file_path.clone(),
+            GitAuth::from_env(),
+        )
+    }
+}
+
+/// Record an update applied outside the workload (git, Argo CD) as an
+/// UpdateRequest with the given status, refreshing the status when the same
+/// update was recorded before. Returns the UpdateRequest name.
+pub async fn record_completed_update(
+    client: &Client,
+    spec: UpdateRequestSpec,
+    new_tag: &str,
+    status: UpdateRequestStatus,
+) -> Result<String> {
+    let namespace = spec.target_ref.namespace.clone();
+    let request_name = format!(
+        "{}-{}",
+        spec.target_ref.name,
+        new_tag.replace(['.', ':', '/'], "-").to_lowercase()
+    );
+
+    let update_request = UpdateRequest::new(&request_name, spec);
+    let api: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
+    match api.create(&PostParams::default(), &update_request).await {
+        Ok(_) => {},
+        Err(kube::Error::Api(err)) if err.code == 409 => {
+            // Same update recorded before, refresh its status below
+        },
+        Err(e) => {
+            error!("Failed to record UpdateRequest {}: {}", request_name, e);
+            return Err(e.into());
+        },
+    }
+
+    let status_patch = json!({
+        "apiVersion": "headwind.sh/v1alpha1",
+        "kind": "UpdateRequest",
+        "status": status
+    });
+    api.patch_status(
+        &request_name,
+        &PatchParams::default(),
+        &Patch::Merge(&status_patch),
+    )
+    .await?;
+
+    Ok(request_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotations(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_gitops_config_from_annotations() {
+        let config = GitOpsConfig::from_annotations(&annotations(&[
+            (annotations::GITOPS_MODE, "git-push"),
+            (
+                annotations::GIT_REPO_URL,
+                "https://github.com/org/deploy.git",
+            ),
+            (annotations::GIT_FILE_PATH, "apps/web/deployment.yaml"),
+        ]))
+        .unwrap();
+
+        assert_eq!(config.repo_url, "https://github.com/org/deploy.git");
+        assert_eq!(config.file_path, "apps/web/deployment.yaml");
+        assert_eq!(config.branch, "main");
+    }
+
+    #[test]
+    fn test_gitops_config_custom_branch() {
+        let config = GitOpsConfig::from_annotations(&annotations(&[
+            (annotations::GITOPS_MODE, "git-push"),
+            (annotations::GIT_REPO_URL, "git@github.com:org/deploy.git"),
+            (annotations::GIT_FILE_PATH, "web.yaml"),
+            (annotations::GIT_BRANCH, "production"),
+        ]))
+        .unwrap();
+
+        assert_eq!(config.branch, "production");
+    }
+
+    #[test]
+    fn test_gitops_config_requires_mode_and_fields() {
+        assert!(
+            GitOpsConfig::from_annotations(&annotations(&[
+                (
+                    annotations::GIT_REPO_URL,
+                    "https://github.com/org/deploy.git"
+                ),
+                (annotations::GIT_FILE_PATH, "web.yaml"),
+            ]))
+            .is_none()
+        );
+
+        assert!(
+            GitOpsConfig::from_annotations(&annotations(&[
+                (annotations::GITOPS_MODE, "git-push"),
+                (
+                    annotations::GIT_REPO_URL,
+                    "https://github.com/org/deploy.git"
+                ),
+            ]))
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_gitops_config_rejects_paths_outside_the_repository() {
+        for file_path in [
+            "/etc/passwd",
+            "../web.yaml",
+            "apps/../../web.yaml",
+            ".git/config",
+        ] {
+            assert!(
+                GitOpsConfig::from_annotations(&annotations(&[
+                    (annotations::GITOPS_MODE, "git-push"),
+                    (
+                        annotations::GIT_REPO_URL,
+                        "https://github.com/org/deploy.git"
+                    ),
+                    (annotations::GIT_FILE_PATH, file_path),
+                ]))
+                .is_none(),
+                "{} was accepted",
+                file_path
+            );
+        }
+    }
+
+    #[test]
+    fn test_gitops_config_rejects_unsafe_branches_and_urls() {
+        let config = |repo_url: &str, branch: &str| {
+            GitOpsConfig::from_annotations(&annotations(&[
+                (annotations::GITOPS_MODE, "git-push"),
+                (annotations::GIT_REPO_URL, repo_url),
+                (annotations::GIT_FILE_PATH, "web.yaml"),
+                (annotations::GIT_BRANCH, branch),
+            ]))
+        };
+
+        assert!(config("https://github.com/org/deploy.git", "--upload-pack=id").is_none());
+        assert!(config("ext::sh -c id", "main").is_none());
+        assert!(config("file:///srv/git/deploy.git", "main").is_none());
+    }
+}
@@ -0,0 +1,362 @@
+//! What changed between two chart versions, for UpdateRequest notifications.
+//!
+//! Charts published to Artifact Hub list their changes in the
+//! `artifacthub.io/changes` annotation of `Chart.yaml`, which HTTP
+//! repositories copy into `index.yaml`. When a chart has no such annotation
+//! but its `home` or `sources` point at GitHub, the sections of `CHANGELOG.md`
+//! between the two versions are used instead.
+
+use super::repository::{ChartEntry, HelmRepositoryClient};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::debug;
+
+/// Chart annotation listing the changes of a release
+pub const ARTIFACTHUB_CHANGES: &str = "artifacthub.io/changes";
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// One entry of `artifacthub.io/changes`, either a plain string or a
+/// structured change with a kind
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Change {
+    Text(String),
+    Structured {
+        kind: Option<String>,
+        description: String,
+    },
+}
+
+/// Changes between `from_version` and `to_version` of a chart in the HTTP
+/// repository at `repo_url`, or `None` when the chart doesn't publish any.
+/// OCI repositories don't serve chart metadata in an index and are skipped.
+pub async fn fetch_chart_changelog(
+    repo_url: &str,
+    chart_name: &str,
+    from_version: &str,
+    to_version: &str,
+) -> Result<Option<String>> {
+    if repo_url.starts_with("oci://") {
+        return Ok(None);
+    }
+
+    let index = HelmRepositoryClient::new()?.fetch_index(repo_url).await?;
+    let Some(entry) = index
+        .entries
+        .get(chart_name)
+        .and_then(|entries| entries.iter().find(|e| e.version == to_version))
+    else {
+        return Ok(None);
+    };
+
+    if let Some(changes) = artifacthub_changes(entry) {
+        return Ok(Some(changes));
+    }
+
+    let Some((owner, repo)) = chart_github_repo(entry) else {
+        return Ok(None);
+    };
+    fetch_github_changelog(
+        GITHUB_API_URL,
+        &owner,
+        &repo,
+        chart_name,
+        from_version,
+        to_version,
+    )
+    .await
+}
+
+/// `artifacthub.io/changes` of a chart version as a bullet list
+fn artifacthub_changes(entry: &ChartEntry) -> Option<String> {
+    let changes = entry.annotations.as_ref()?.get(ARTIFACTHUB_CHANGES)?;
+    parse_artifacthub_changes(changes)
+}
+
+/// Render the YAML list of `artifacthub.io/changes` as one line per change,
+/// e.g. `- Added: Support for topology spread constraints`
+pub fn parse_artifacthub_changes(yaml: &str) -> Option<String> {
+    let changes: Vec<Change> = match serde_yaml::from_str(yaml) {
+        Ok(changes) => changes,
+        Err(e) => {
+            debug!("Invalid {} annotation: {}", ARTIFACTHUB_CHANGES, e);
+            return None;
+        },
+    };
+
+    let lines: Vec<String> = changes
+        .into_iter()
+        .map(|change| match change {
+            Change::Text(text) => format!("- {}", text.trim()),
+            Change::Structured {
+                kind: Some(kind),
+                description,
+            } => format!("- {}: {}", capitalize(&kind), description.trim()),
+            Change::Structured {
+                kind: None,
+                description,
+            } => format!("- {}", description.trim()),
+        })
+        .collect();
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// GitHub `(owner, repo)` of the chart's `home` or first GitHub `sources` URL
+fn chart_github_repo(entry: &ChartEntry) -> Option<(String, String)> {
+    entry
+        .home
+        .iter()
+        .chain(entry.sources.iter().flatten())
+        .find_map(|url| github_repo(url))
+}
+
+fn github_repo(url: &str) -> Option<(String, String)> {
+    let path = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .strip_prefix("github.com/")?;
+    let mut parts = path.split('/');
+    let owner = parts.next().filter(|s| !s.is_empty())?;
+    let repo = parts
+        .next()
+        .map(|r| r.trim_end_matches(".git"))
+        .filter(|s| !s.is_empty())?;
+    Some((owner.to_string(), repo.to_string()))
+}
+
+/// Fetch `CHANGELOG.md` at the release tag of `to_version` and extract the
+/// sections newer than `from_version`. Chart repositories tag releases as
+/// `<chart>-<version>` (chart-releaser), `v<version>` or `<version>`.
+async fn fetch_github_changelog(
+    api_url: &str,
+    owner: &str,
+    repo: &str,
+    chart_name: &str,
+    from_version: &str,
+    to_version: &str,
+) -> Result<Option<String>> {
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .context("Failed to create HTTP client")?;
+    let token = std::env::var("HEADWIND_GITHUB_TOKEN").ok();
+
+    for tag in [
+        format!("{}-{}", chart_name, to_version),
+        format!("v{}", to_version),
+        to_version.to_string(),
+    ] {
+        let url = format!(
+            "{}/repos/{}/{}/contents/CHANGELOG.md",
+            api_url.trim_end_matches('/'),
+            owner,
+            repo
+        );
+        let mut request = http
+            .get(&url)
+            .query(&[("ref", tag.as_str())])
+            .header("Accept", "application/vnd.github.raw")
+            .header("User-Agent", "headwind");
+        if let Some(token) = &token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await.context("GitHub API request failed")?;
+        if !response.status().is_success() {
+            debug!(
+                "No CHANGELOG.md in {}/{} at {}: {}",
+                owner,
+                repo,
+                tag,
+                response.status()
+            );
+            continue;
+        }
+
+        let markdown = response.text().await?;
+        return Ok(changelog_between(&markdown, from_version, to_version));
+    }
+
+    Ok(None)
+}
+
+/// Sections of a markdown changelog from the heading of `to_version` up to,
+/// not including, the heading of `from_version`
+pub fn changelog_between(markdown: &str, from_version: &str, to_version: &str) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut in_range = false;
+
+    for line in markdown.lines() {
+        if line.starts_with('#') {
+            if mentions_version(line, from_version) {
+                break;
+            }
+            if mentions_version(line, to_version) {
+                in_range = true;
+            }
+        }
+        if in_range {
+            lines.push(line);
+        }
+    }
+
+    let text = lines.join("\n").trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Whether `line` contains `version` on its own, so `1.2.0` doesn't match `11.2.0`
+fn mentions_version(line: &str, version: &str) -> bool {
+    let is_version_char = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '-';
+    line.match_indices(version).any(|(i, _)| {
+        // Allow a `v` prefix, as in `v1.2.0`
+        let prefix = line[..i].strip_suffix('v').unwrap_or(&line[..i]);
+        let after = line[i + version.len()..].chars().next();
+        prefix
+            .chars()
+            .next_back()
+            .is_none_or(|c| !is_version_char(c))
+            && after.is_none_or(|c| !is_version_char(c))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helm::IndexYaml;
+    use axum::{
+        Router,
+        extract::{Path, Query},
+        http::StatusCode,
+        routing::get,
+    };
+    use std::collections::HashMap;
+
+    const INDEX: &str = r#"
+apiVersion: v1
+entries:
+  ingress-nginx:
+    - name: ingress-nginx
+      version: 4.11.0
+      home: https://github.com/kubernetes/ingress-nginx
+      urls:
+        - https://example.com/ingress-nginx-4.11.0.tgz
+      annotations:
+        artifacthub.io/changes: |
+          - kind: added
+            description: Support for topology spread constraints
+          - kind: fixed
+            description: Admission webhook timeout
+            links:
+              - name: PR
+                url: https://github.com/kubernetes/ingress-nginx/pull/1
+          - "Update controller image to v1.11.0"
+  cert-manager:
+    - name: cert-manager
+      version: 1.15.0
+      home: https://cert-manager.io
+      sources:
+        - https://github.com/cert-manager/cert-manager.git
+      urls:
+        - https://example.com/cert-manager-1.15.0.tgz
+"#;
+
+    fn entry(chart: &str) -> ChartEntry {
+        let index: IndexYaml = serde_yaml::from_str(INDEX).unwrap();
+        index.entries[chart][0].clone()
+    }
+
+    #[test]
+    fn test_artifacthub_changes() {
+        assert_eq!(
+            artifacthub_changes(&entry("ingress-nginx")).as_deref(),
+            Some(
+                "- Added: Support for topology spread constraints\n\
+                 - Fixed: Admission webhook timeout\n\
+                 - Update controller image to v1.11.0"
+            )
+        );
+        assert_eq!(artifacthub_changes(&entry("cert-manager")), None);
+        assert_eq!(parse_artifacthub_changes("not: [a list"), None);
+        assert_eq!(parse_artifacthub_changes("[]"), None);
+    }
+
+    #[test]
+    fn test_chart_github_repo() {
+        assert_eq!(
+            chart_github_repo(&entry("ingress-nginx")),
+            Some(("kubernetes".to_string(), "ingress-nginx".to_string()))
+        );
+        // home isn't on GitHub, sources is
+        assert_eq!(
+            chart_github_repo(&entry("cert-manager")),
+            Some(("cert-manager".to_string(), "cert-manager".to_string()))
+        );
+        assert_eq!(github_repo("https://gitlab.com/acme/chart"), None);
+    }
+
+    #[test]
+    fn test_changelog_between() {
+        let markdown = "# Changelog\n\n\
+            ## [1.15.0] - 2024-06-01\n\n- New feature\n\n\
+            ## v1.14.1\n\n- Bug fix\n\n\
+            ## 1.14.0\n\n- Older change\n\n\
+            ## 11.14.0\n";
+
+        assert_eq!(
+            changelog_between(markdown, "1.14.0", "1.15.0").as_deref(),
+            Some("## [1.15.0] - 2024-06-01\n\n- New feature\n\n## v1.14.1\n\n- Bug fix")
+        );
+        assert_eq!(changelog_between(markdown, "1.14.0", "2.0.0"), None);
+        assert!(!mentions_version("## 11.14.0", "1.14.0"));
+        assert!(!mentions_version("## 1.14.0-rc.1", "1.14.0"));
+        assert!(mentions_version("## v1.14.0 (2024-05-01)", "1.14.0"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_github_changelog_tries_release_tags() {
+        let app = Router::new().route(
+            "/repos/{owner}/{repo}/contents/CHANGELOG.md",
+            get(
+                |Path((owner, repo)): Path<(String, String)>,
+                 Query(query): Query<HashMap<String, String>>| async move {
+                    // Only the chart-releaser tag exists
+                    if owner == "acme" && repo == "charts" && query["ref"] == "app-2.1.0" {
+                        (
+                            StatusCode::OK,
+                            "## 2.1.0\n- Faster startup\n## 2.0.0\n- Initial".to_string(),
+                        )
+                    } else {
+                        (StatusCode::NOT_FOUND, String::new())
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        let api_url = format!("http://{}", addr);
+
+        let changelog = fetch_github_changelog(&api_url, "acme", "charts", "app", "2.0.0", "2.1.0")
+            .await
+            .unwrap();
+        assert_eq!(changelog.as_deref(), Some("## 2.1.0\n- Faster startup"));
+
+        let missing = fetch_github_changelog(&api_url, "acme", "other", "app", "2.0.0", "2.1.0")
+            .await
+            .unwrap();
+        assert_eq!(missing, None);
+    }
+}
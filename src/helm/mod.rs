@@ -1,3 +1,4 @@
+pub mod changelog;
 pub mod oci;
 pub mod repository;
 
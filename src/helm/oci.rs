@@ -118,8 +118,17 @@ impl OciHelmClient {
             require_approval: true,
             min_update_interval: None,
             images: Vec::new(),
+            container_names: Vec::new(),
             event_source: Default::default(),
             polling_interval: None,
+            batch_mode: false,
+            tag_normalization: Default::default(),
+            observers: Vec::new(),
+            helm_version_constraint: None,
+            include_init_containers: false,
+            require_slsa_level: None,
+            slsa_builder: None,
+            opa_policy: None,
         };
 
         let mut valid_versions: Vec<String> = versions
@@ -35,6 +35,8 @@ pub struct ChartEntry {
     pub digest: Option<String>,
     #[serde(default)]
     pub app_version: Option<String>,
+    #[serde(default)]
+    pub annotations: Option<HashMap<String, String>>,
 }
 
 /// Credentials for Helm repository authentication
@@ -248,8 +250,17 @@ impl HelmRepositoryClient {
                     require_approval: true,
                     min_update_interval: None,
                     images: Vec::new(),
+                    container_names: Vec::new(),
                     event_source: Default::default(),
                     polling_interval: None,
+                    batch_mode: false,
+                    tag_normalization: Default::default(),
+                    observers: Vec::new(),
+                    helm_version_constraint: None,
+                    include_init_containers: false,
+                    require_slsa_level: None,
+                    slsa_builder: None,
+                    opa_policy: None,
                 };
 
                 match policy_engine.should_update(&resource_policy, current_version, v) {
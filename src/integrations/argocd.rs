@@ -0,0 +1,590 @@
+use crate::models::policy::annotations;
+use anyhow::{Context, Result};
+use kube::{
+    Api, Client,
+    api::{ApiResource, DynamicObject, GroupVersionKind, Patch, PatchParams},
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+/// Namespace of Applications referenced without one, where Argo CD is installed by default
+pub const DEFAULT_APP_NAMESPACE: &str = "argocd";
+
+/// Argo CD Application settings parsed from resource annotations
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgoCdAppConfig {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl ArgoCdAppConfig {
+    /// Returns Some when `headwind.sh/argocd-app` is set to `name` or `namespace/name`
+    pub fn from_annotations(annotations: &BTreeMap<String, String>) -> Option<Self> {
+        let value = annotations.get(annotations::ARGOCD_APP)?.trim();
+        let (namespace, name) = match value.split_once('/') {
+            Some((namespace, name)) => (namespace.trim(), name.trim()),
+            None => (DEFAULT_APP_NAMESPACE, value),
+        };
+        if namespace.is_empty() || name.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        })
+    }
+}
+
+fn application_resource() -> ApiResource {
+    ApiResource::from_gvk(&GroupVersionKind::gvk(
+        "argoproj.io",
+        "v1alpha1",
+        "Application",
+    ))
+}
+
+/// Repository an override entry points at, without tag or digest
+fn override_repository(image: &str) -> &str {
+    let image = image
+        .split_once('@')
+        .map_or(image, |(repository, _)| repository);
+    let name_start = image.rfind('/').map_or(0, |i| i + 1);
+    match image[name_start..].find(':') {
+        Some(colon) => &image[..name_start + colon],
+        None => image,
+    }
+}
+
+/// Point the Kustomize image override for `image_name` at `new_image`.
+///
+/// Entries are `name=newName:tag` or `name:tag`. An entry matches when its
+/// name or the image it overrides to is `image_name`; the name is kept so the
+/// override still applies to the manifests. Without a match `new_image` is appended.
+pub fn set_image_override(images: &[String], image_name: &str, new_image: &str) -> Vec<String> {
+    let mut found = false;
+    let mut updated: Vec<String> = images
+        .iter()
+        .map(|entry| {
+            let replacement = match entry.split_once('=') {
+                Some((name, target))
+                    if name == image_name || override_repository(target) == image_name =>
+                {
+                    Some(format!("{}={}", name, new_image))
+                },
+                None if override_repository(entry) == image_name => Some(new_image.to_string()),
+                _ => None,
+            };
+            match replacement {
+                Some(replacement) => {
+                    found = true;
+                    replacement
+                },
+                None => entry.clone(),
+            }
+        })
+        .collect();
+
+    if !found {
+        updated.push(new_image.to_string());
+    }
+    updated
+}
+
+/// Operation ID of the sync Argo CD started: the revision being synced,
+/// falling back to the time the operation started
+pub fn sync_operation_id(application: &serde_json::Value) -> Option<String> {
+    [
+        &application["status"]["operationState"]["operation"]["sync"]["revision"],
+        &application["operation"]["sync"]["revision"],
+        &application["status"]["operationState"]["startedAt"],
+    ]
+    .into_iter()
+    .find_map(|value| value.as_str().filter(|s| !s.is_empty()))
+    .map(str::to_string)
+}
+
+/// Whether workloads in `workload_namespace` may update the Application:
+/// always from its own namespace or the namespace it deploys to, otherwise
+/// only when listed in its `headwind.sh/argocd-allowed-namespaces` annotation
+pub fn allows_namespace(application: &DynamicObject, workload_namespace: &str) -> bool {
+    if application.metadata.namespace.as_deref() == Some(workload_namespace)
+        || application.data["spec"]["destination"]["namespace"].as_str() == Some(workload_namespace)
+    {
+        return true;
+    }
+    application
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|a| a.get(annotations::ARGOCD_ALLOWED_NAMESPACES))
+        .is_some_and(|allowed| {
+            allowed
+                .split(',')
+                .map(str::trim)
+                .any(|ns| ns == "*" || ns == workload_namespace)
+        })
+}
+
+/// Updates Argo CD Applications through the Kubernetes API and syncs them
+/// through the Argo CD API server
+#[derive(Clone)]
+pub struct ArgoCdClient {
+    client: Client,
+    /// Argo CD API server, None to leave syncing to the Application's sync policy
+    pub server_url: Option<String>,
+    pub token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl ArgoCdClient {
+    pub fn new(client: Client, server_url: Option<String>, token: Option<String>) -> Self {
+        Self {
+            client,
+            server_url: server_url.map(|url| url.trim_end_matches('/').to_string()),
+            token,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Client using `HEADWIND_ARGOCD_SERVER_URL` and `HEADWIND_ARGOCD_TOKEN`
+    pub fn from_env(client: Client) -> Self {
+        let server_url = std::env::var("HEADWIND_ARGOCD_SERVER_URL")
+            .ok()
+            .filter(|url| !url.is_empty());
+        let token = std::env::var("HEADWIND_ARGOCD_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty());
+        Self::new(client, server_url, token)
+    }
+
+    /// Set the `spec.source.kustomize.images` override for `image_name` and
+    /// return the resulting list. The Application must allow updates from
+    /// `workload_namespace` (see [`allows_namespace`]).
+    pub async fn update_image_override(
+        &self,
+        app: &ArgoCdAppConfig,
+        workload_namespace: &str,
+        image_name: &str,
+        new_image: &str,
+    ) -> Result<Vec<String>> {
+        let api: Api<DynamicObject> =
+            Api::namespaced_with(self.client.clone(), &app.namespace, &application_resource());
+        let application = api.get(&app.name).await?;
+        if !allows_namespace(&application, workload_namespace) {
+            return Err(anyhow::anyhow!(
+                "Application {}/{} does not allow updates from namespace {} ({})",
+                app.namespace,
+                app.name,
+                workload_namespace,
+                annotations::ARGOCD_ALLOWED_NAMESPACES
+            ));
+        }
+
+        let current: Vec<String> = application.data["spec"]["source"]["kustomize"]["images"]
+            .as_array()
+            .map(|images| {
+                images
+                    .iter()
+                    .filter_map(|image| image.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let images = set_image_override(&current, image_name, new_image);
+
+        // A merge patch replaces the whole list, so send every override
+        api.patch(
+            &app.name,
+            &PatchParams::default(),
+            &Patch::Merge(&json!({
+                "spec": {"source": {"kustomize": {"images": images}}}
+            })),
+        )
+        .await?;
+        debug!(
+            "Set image overrides of Application {}/{} to {:?}",
+            app.namespace, app.name, images
+        );
+
+        Ok(images)
+    }
+
+    /// Trigger a sync of the Application and return its operation ID. Returns
+    /// None without a request when no Argo CD API server is configured.
+    pub async fn sync(&self, app: &ArgoCdAppConfig) -> Result<Option<String>> {
+        let Some(server_url) = &self.server_url else {
+            debug!(
+                "HEADWIND_ARGOCD_SERVER_URL not set, leaving {}/{} to its sync policy",
+                app.namespace, app.name
+            );
+            return Ok(None);
+        };
+
+        let mut request = self
+            .http
+            .post(format!(
+                "{}/api/v1/applications/{}/sync",
+                server_url, app.name
+            ))
+            .json(&json!({"name": app.name, "appNamespace": app.namespace}));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await.context("Argo CD API request failed")?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or(serde_json::Value::Null);
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "Argo CD API returned {}: {}",
+                status,
+                body["message"].as_str().unwrap_or("no message")
+            ));
+        }
+
+        Ok(sync_operation_id(&body))
+    }
+}
+
+/// Update the Application's image override for a workload in
+/// `workload_namespace` and sync it. Returns the sync operation, if Argo CD
+/// started one.
+pub async fn apply_argocd_update(
+    argocd: &ArgoCdClient,
+    app: &ArgoCdAppConfig,
+    workload_namespace: &str,
+    image_name: &str,
+    new_image: &str,
+) -> Result<Option<String>> {
+    info!(
+        "Applying {} via Argo CD Application {}/{}",
+        new_image, app.namespace, app.name
+    );
+
+    argocd
+        .update_image_override(app, workload_namespace, image_name, new_image)
+        .await?;
+    argocd.sync(app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        body::Bytes,
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+    };
+    use std::sync::{Arc, Mutex};
+
+    fn annotations(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn images(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_config_from_annotations() {
+        let config =
+            ArgoCdAppConfig::from_annotations(&annotations(&[(annotations::ARGOCD_APP, "my-app")]))
+                .unwrap();
+        assert_eq!(config.namespace, DEFAULT_APP_NAMESPACE);
+        assert_eq!(config.name, "my-app");
+
+        let config = ArgoCdAppConfig::from_annotations(&annotations(&[(
+            annotations::ARGOCD_APP,
+            "team-a/my-app",
+        )]))
+        .unwrap();
+        assert_eq!(config.namespace, "team-a");
+
+        for invalid in ["", "/my-app", "team-a/"] {
+            assert!(
+                ArgoCdAppConfig::from_annotations(&annotations(&[(
+                    annotations::ARGOCD_APP,
+                    invalid
+                )]))
+                .is_none()
+            );
+        }
+        assert!(ArgoCdAppConfig::from_annotations(&BTreeMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_set_image_override() {
+        // Renamed override keeps its name
+        assert_eq!(
+            set_image_override(
+                &images(&["redis:7", "myapp=ghcr.io/org/myapp:v1.2.2"]),
+                "ghcr.io/org/myapp",
+                "ghcr.io/org/myapp:v1.2.3"
+            ),
+            images(&["redis:7", "myapp=ghcr.io/org/myapp:v1.2.3"])
+        );
+
+        // Tag-only override, including registries with ports
+        assert_eq!(
+            set_image_override(
+                &images(&["registry:5000/org/myapp:1.0"]),
+                "registry:5000/org/myapp",
+                "registry:5000/org/myapp:1.1"
+            ),
+            images(&["registry:5000/org/myapp:1.1"])
+        );
+
+        // Override by name
+        assert_eq!(
+            set_image_override(&images(&["nginx=nginx:1.25"]), "nginx", "nginx:1.26"),
+            images(&["nginx=nginx:1.26"])
+        );
+
+        // Appended when no override exists
+        assert_eq!(
+            set_image_override(&images(&["redis:7"]), "nginx", "nginx:1.26"),
+            images(&["redis:7", "nginx:1.26"])
+        );
+    }
+
+    #[test]
+    fn test_sync_operation_id() {
+        assert_eq!(
+            sync_operation_id(&json!({
+                "operation": {"sync": {"revision": "abc123"}},
+                "status": {"operationState": {"startedAt": "2026-01-01T00:00:00Z"}}
+            })),
+            Some("abc123".to_string())
+        );
+        assert_eq!(
+            sync_operation_id(&json!({
+                "status": {"operationState": {"startedAt": "2026-01-01T00:00:00Z"}}
+            })),
+            Some("2026-01-01T00:00:00Z".to_string())
+        );
+        assert_eq!(sync_operation_id(&json!({})), None);
+    }
+
+    // GET requests have no body
+    fn json_body(body: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(body).unwrap_or(serde_json::Value::Null)
+    }
+
+    /// Requests received by the fake servers as (method, path, body)
+    type Requests = Arc<Mutex<Vec<(String, String, serde_json::Value)>>>;
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    /// Kubernetes API server with one Application
+    async fn fake_api_server(requests: Requests) -> Client {
+        async fn application(
+            State(requests): State<Requests>,
+            method: axum::http::Method,
+            body: Bytes,
+        ) -> Response {
+            requests.lock().unwrap().push((
+                method.to_string(),
+                "application".to_string(),
+                json_body(&body),
+            ));
+            Json(json!({
+                "apiVersion": "argoproj.io/v1alpha1",
+                "kind": "Application",
+                "metadata": {
+                    "name": "my-app",
+                    "namespace": "argocd",
+                    "annotations": {"headwind.sh/argocd-allowed-namespaces": "staging, qa"}
+                },
+                "spec": {
+                    "destination": {"namespace": "production"},
+                    "source": {
+                        "repoURL": "https://github.com/org/deploy.git",
+                        "path": "apps/myapp",
+                        "kustomize": {"images": ["redis:7", "myapp=ghcr.io/org/myapp:v1.2.2"]}
+                    }
+                }
+            }))
+            .into_response()
+        }
+
+        let app = Router::new()
+            .route(
+                "/apis/argoproj.io/v1alpha1/namespaces/argocd/applications/my-app",
+                get(application).patch(application),
+            )
+            .with_state(requests);
+
+        let config = kube::Config::new(serve(app).await.parse().unwrap());
+        Client::try_from(config).unwrap()
+    }
+
+    /// Argo CD API server answering syncs of my-app like Argo CD does, with the Application
+    async fn fake_argocd_server(requests: Requests) -> String {
+        let app = Router::new()
+            .route(
+                "/api/v1/applications/my-app/sync",
+                post(
+                    |State(requests): State<Requests>, headers: HeaderMap, body: Bytes| async move {
+                        if headers.get("authorization").and_then(|v| v.to_str().ok())
+                            != Some("Bearer argocd-token")
+                        {
+                            return (
+                                StatusCode::UNAUTHORIZED,
+                                Json(json!({"message": "invalid session"})),
+                            )
+                                .into_response();
+                        }
+                        requests.lock().unwrap().push((
+                            "POST".to_string(),
+                            "sync".to_string(),
+                            json_body(&body),
+                        ));
+                        Json(json!({
+                            "metadata": {"name": "my-app", "namespace": "argocd"},
+                            "operation": {
+                                "initiatedBy": {"username": "headwind"},
+                                "sync": {"revision": "4f2c9e1"}
+                            }
+                        }))
+                        .into_response()
+                    },
+                ),
+            )
+            .with_state(requests);
+        serve(app).await
+    }
+
+    #[tokio::test]
+    async fn test_apply_argocd_update_patches_and_syncs() {
+        let requests: Requests = Arc::new(Mutex::new(Vec::new()));
+        let client = fake_api_server(requests.clone()).await;
+        let server_url = fake_argocd_server(requests.clone()).await;
+        let argocd = ArgoCdClient::new(
+            client,
+            Some(format!("{}/", server_url)),
+            Some("argocd-token".to_string()),
+        );
+        let app = ArgoCdAppConfig {
+            namespace: "argocd".to_string(),
+            name: "my-app".to_string(),
+        };
+
+        let operation = apply_argocd_update(
+            &argocd,
+            &app,
+            "production",
+            "ghcr.io/org/myapp",
+            "ghcr.io/org/myapp:v1.2.3",
+        )
+        .await
+        .unwrap();
+        assert_eq!(operation.as_deref(), Some("4f2c9e1"));
+
+        let requests = requests.lock().unwrap();
+        let patch = requests
+            .iter()
+            .find(|(method, path, _)| method == "PATCH" && path == "application")
+            .unwrap();
+        assert_eq!(
+            patch.2,
+            json!({"spec": {"source": {"kustomize": {"images": [
+                "redis:7",
+                "myapp=ghcr.io/org/myapp:v1.2.3"
+            ]}}}})
+        );
+
+        let sync = requests.iter().find(|(_, path, _)| path == "sync").unwrap();
+        assert_eq!(sync.2["appNamespace"], "argocd");
+    }
+
+    #[tokio::test]
+    async fn test_cross_namespace_update_is_refused() {
+        let requests: Requests = Arc::new(Mutex::new(Vec::new()));
+        let client = fake_api_server(requests.clone()).await;
+        let server_url = fake_argocd_server(requests.clone()).await;
+        let argocd = ArgoCdClient::new(client, Some(server_url), Some("argocd-token".to_string()));
+        let app = ArgoCdAppConfig {
+            namespace: "argocd".to_string(),
+            name: "my-app".to_string(),
+        };
+
+        let err = apply_argocd_update(
+            &argocd,
+            &app,
+            "team-b",
+            "ghcr.io/org/myapp",
+            "ghcr.io/org/myapp:v1.2.3",
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not allow updates from namespace team-b"),
+            "{}",
+            err
+        );
+
+        // Read, but neither patched nor synced
+        let requests = requests.lock().unwrap();
+        assert!(requests.iter().all(|(method, _, _)| method == "GET"));
+    }
+
+    #[test]
+    fn test_allows_namespace() {
+        let application = |annotations: serde_json::Value| -> DynamicObject {
+            serde_json::from_value(json!({
+                "apiVersion": "argoproj.io/v1alpha1",
+                "kind": "Application",
+                "metadata": {"name": "my-app", "namespace": "argocd", "annotations": annotations},
+                "spec": {"destination": {"namespace": "web"}}
+            }))
+            .unwrap()
+        };
+
+        let app = application(json!({}));
+        assert!(allows_namespace(&app, "argocd"));
+        assert!(allows_namespace(&app, "web"));
+        assert!(!allows_namespace(&app, "team-a"));
+
+        let app = application(json!({"headwind.sh/argocd-allowed-namespaces": "team-a, team-b"}));
+        assert!(allows_namespace(&app, "team-a"));
+        assert!(allows_namespace(&app, "team-b"));
+        assert!(!allows_namespace(&app, "team-c"));
+
+        let app = application(json!({"headwind.sh/argocd-allowed-namespaces": "*"}));
+        assert!(allows_namespace(&app, "team-c"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_errors_and_skips() {
+        let requests: Requests = Arc::new(Mutex::new(Vec::new()));
+        let client = fake_api_server(requests.clone()).await;
+        let server_url = fake_argocd_server(requests.clone()).await;
+        let app = ArgoCdAppConfig {
+            namespace: "argocd".to_string(),
+            name: "my-app".to_string(),
+        };
+
+        let unauthorized = ArgoCdClient::new(client.clone(), Some(server_url), None);
+        let err = unauthorized.sync(&app).await.unwrap_err();
+        assert!(err.to_string().contains("invalid session"), "{}", err);
+
+        let without_server = ArgoCdClient::new(client, None, None);
+        assert_eq!(without_server.sync(&app).await.unwrap(), None);
+        assert!(requests.lock().unwrap().is_empty());
+    }
+}
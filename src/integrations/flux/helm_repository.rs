@@ -0,0 +1,194 @@
+//! Chart repository discovery from Flux HelmRepository objects.
+//!
+//! A HelmRelease names its chart repository in
+//! `spec.chart.spec.sourceRef`. The HelmRepositories of a namespace are
+//! listed at most once per [`CACHE_TTL`] and shared by every HelmRelease
+//! there, so reconciling many releases doesn't query the API server for each
+//! one. A HelmRepository created or changed meanwhile is seen within the TTL.
+
+use crate::models::HelmRepository;
+use anyhow::{Result, anyhow};
+use kube::api::ListParams;
+use kube::{Api, Client, ResourceExt};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// How long the HelmRepositories listed in a namespace are reused
+pub const CACHE_TTL: Duration = Duration::from_secs(60);
+
+static CACHE: Lazy<HelmRepositoryCache> = Lazy::new(|| HelmRepositoryCache::new(CACHE_TTL));
+
+/// HelmRepositories of a namespace and the time they were listed
+type CacheEntry = (Instant, Arc<Vec<HelmRepository>>);
+
+/// HelmRepositories per namespace
+pub struct HelmRepositoryCache {
+    ttl: Duration,
+    namespaces: Mutex<HashMap<String, CacheEntry>>,
+}
+
+impl HelmRepositoryCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            namespaces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The HelmRepositories of `namespace` if listed less than the TTL before `now`
+    pub fn get(&self, namespace: &str, now: Instant) -> Option<Arc<Vec<HelmRepository>>> {
+        let namespaces = self.namespaces.lock().unwrap_or_else(|e| e.into_inner());
+        namespaces
+            .get(namespace)
+            .filter(|(listed_at, _)| now.saturating_duration_since(*listed_at) < self.ttl)
+            .map(|(_, repositories)| Arc::clone(repositories))
+    }
+
+    pub fn insert(
+        &self,
+        namespace: &str,
+        repositories: Vec<HelmRepository>,
+        now: Instant,
+    ) -> Arc<Vec<HelmRepository>> {
+        let repositories = Arc::new(repositories);
+        self.namespaces
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(namespace.to_string(), (now, Arc::clone(&repositories)));
+        repositories
+    }
+
+    /// The HelmRepositories of `namespace`, listing them if not cached
+    pub async fn repositories(
+        &self,
+        client: &Client,
+        namespace: &str,
+    ) -> Result<Arc<Vec<HelmRepository>>> {
+        if let Some(repositories) = self.get(namespace, Instant::now()) {
+            return Ok(repositories);
+        }
+
+        let api: Api<HelmRepository> = Api::namespaced(client.clone(), namespace);
+        let repositories = api.list(&ListParams::default()).await?.items;
+        debug!(
+            "Listed {} HelmRepositories in namespace {}",
+            repositories.len(),
+            namespace
+        );
+        Ok(self.insert(namespace, repositories, Instant::now()))
+    }
+}
+
+/// The HelmRepository named `repo_name` among `repositories`
+pub fn find_repository<'a>(
+    repositories: &'a [HelmRepository],
+    namespace: &str,
+    repo_name: &str,
+) -> Result<&'a HelmRepository> {
+    repositories
+        .iter()
+        .find(|repository| repository.name_any() == repo_name)
+        .ok_or_else(|| anyhow!("HelmRepository {}/{} not found", namespace, repo_name))
+}
+
+/// The HelmRepository `repo_name` in `namespace`
+pub async fn discover_helm_repo(
+    client: &Client,
+    namespace: &str,
+    repo_name: &str,
+) -> Result<HelmRepository> {
+    let repositories = CACHE.repositories(client, namespace).await?;
+    find_repository(&repositories, namespace, repo_name).cloned()
+}
+
+/// `spec.url` of the HelmRepository `repo_name` in `namespace`
+pub async fn discover_helm_repo_url(
+    client: &Client,
+    namespace: &str,
+    repo_name: &str,
+) -> Result<String> {
+    Ok(discover_helm_repo(client, namespace, repo_name)
+        .await?
+        .spec
+        .url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// HelmRepositories as `kubectl get helmrepositories -o yaml` lists them
+    fn fixtures() -> Vec<HelmRepository> {
+        serde_yaml::from_str(
+            r#"
+- apiVersion: source.toolkit.fluxcd.io/v1
+  kind: HelmRepository
+  metadata:
+    name: bitnami
+    namespace: flux-system
+  spec:
+    url: https://charts.bitnami.com/bitnami
+    interval: 1h
+- apiVersion: source.toolkit.fluxcd.io/v1
+  kind: HelmRepository
+  metadata:
+    name: podinfo
+    namespace: flux-system
+  spec:
+    type: oci
+    url: oci://ghcr.io/stefanprodan/charts
+    secretRef:
+      name: ghcr-credentials
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_find_repository() {
+        let repositories = fixtures();
+
+        let bitnami = find_repository(&repositories, "flux-system", "bitnami").unwrap();
+        assert_eq!(bitnami.spec.url, "https://charts.bitnami.com/bitnami");
+
+        let podinfo = find_repository(&repositories, "flux-system", "podinfo").unwrap();
+        assert_eq!(podinfo.spec.url, "oci://ghcr.io/stefanprodan/charts");
+        assert_eq!(podinfo.spec.repository_type.as_deref(), Some("oci"));
+        assert_eq!(
+            podinfo.spec.secret_ref.as_ref().map(|s| s.name.as_str()),
+            Some("ghcr-credentials")
+        );
+
+        let err = find_repository(&repositories, "flux-system", "jetstack").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "HelmRepository flux-system/jetstack not found"
+        );
+    }
+
+    #[test]
+    fn test_cache_expires_per_namespace() {
+        let cache = HelmRepositoryCache::new(CACHE_TTL);
+        let listed_at = Instant::now();
+        cache.insert("flux-system", fixtures(), listed_at);
+        cache.insert("apps", Vec::new(), listed_at + Duration::from_secs(30));
+
+        let cached = cache
+            .get("flux-system", listed_at + Duration::from_secs(59))
+            .unwrap();
+        assert_eq!(cached.len(), 2);
+        assert!(cache.get("default", listed_at).is_none());
+
+        // Each namespace expires on its own
+        let later = listed_at + CACHE_TTL;
+        assert!(cache.get("flux-system", later).is_none());
+        assert!(cache.get("apps", later).unwrap().is_empty());
+
+        // Listing again replaces the expired entry
+        cache.insert("flux-system", fixtures()[..1].to_vec(), later);
+        assert_eq!(cache.get("flux-system", later).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,477 @@
+use crate::models::kustomization::{Kustomization, KustomizationSpec};
+use crate::models::policy::annotations;
+use anyhow::Result;
+use chrono::Utc;
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{
+    Api, Client,
+    api::{Patch, PatchParams},
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+/// Annotation Flux watches to reconcile a resource outside its interval
+pub const RECONCILE_REQUESTED_AT: &str = "reconcile.fluxcd.io/requestedAt";
+
+/// Flux Kustomization substitution settings parsed from resource annotations
+#[derive(Debug, Clone, PartialEq)]
+pub struct FluxKustomizationConfig {
+    /// Namespace of the Kustomization, None for the resource's namespace
+    pub namespace: Option<String>,
+    pub name: String,
+    /// Substitution variable holding the tag, None to derive it from the container
+    pub var_name: Option<String>,
+}
+
+impl FluxKustomizationConfig {
+    /// Returns Some when `headwind.sh/flux-kustomization` is set to `name` or
+    /// `namespace/name`. `headwind.sh/flux-substitute-var` overrides the variable name.
+    pub fn from_annotations(annotations: &BTreeMap<String, String>) -> Option<Self> {
+        let value = annotations.get(annotations::FLUX_KUSTOMIZATION)?.trim();
+        let (namespace, name) = match value.split_once('/') {
+            Some((namespace, name)) => (Some(namespace.trim().to_string()), name.trim()),
+            None => (None, value),
+        };
+        if name.is_empty() || namespace.as_deref() == Some("") {
+            return None;
+        }
+
+        let var_name = annotations
+            .get(annotations::FLUX_SUBSTITUTE_VAR)
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+
+        Some(Self {
+            namespace,
+            name: name.to_string(),
+            var_name,
+        })
+    }
+
+    /// The configured variable, or `<container>_image_tag` with characters Flux
+    /// does not allow in variable names replaced by `_`
+    pub fn var_name_for(&self, container_name: &str) -> String {
+        self.var_name.clone().unwrap_or_else(|| {
+            let container: String = container_name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            format!("{}_image_tag", container)
+        })
+    }
+}
+
+/// Names of the ConfigMaps in the Kustomization's `spec.postBuild.substituteFrom`
+pub fn substitute_configmaps(spec: &KustomizationSpec) -> Vec<String> {
+    spec.post_build
+        .iter()
+        .flat_map(|post_build| post_build.substitute_from.iter().flatten())
+        .filter(|reference| reference.kind == "ConfigMap")
+        .map(|reference| reference.name.clone())
+        .collect()
+}
+
+/// Whether workloads in `workload_namespace` may update the Kustomization:
+/// always from its own namespace, otherwise only when listed in its
+/// `headwind.sh/flux-allowed-namespaces` annotation
+pub fn allows_namespace(kustomization: &Kustomization, workload_namespace: &str) -> bool {
+    if kustomization.metadata.namespace.as_deref() == Some(workload_namespace) {
+        return true;
+    }
+    kustomization
+        .metadata
+        .annotations
+        .as_ref()
+        .and_then(|a| a.get(annotations::FLUX_ALLOWED_NAMESPACES))
+        .is_some_and(|allowed| {
+            allowed
+                .split(',')
+                .map(str::trim)
+                .any(|ns| ns == "*" || ns == workload_namespace)
+        })
+}
+
+/// Set `var_name` to `new_tag` in the ConfigMap the Kustomization substitutes
+/// variables from, then request a reconciliation of the Kustomization.
+///
+/// When several ConfigMaps are referenced the first one that already defines
+/// `var_name` is patched, falling back to the first ConfigMap. A Kustomization
+/// in another namespace than `workload_namespace` must allow it (see
+/// [`allows_namespace`]).
+pub async fn update_kustomization_image_substitute(
+    client: &Client,
+    workload_namespace: &str,
+    namespace: &str,
+    kustomization_name: &str,
+    var_name: &str,
+    new_tag: &str,
+) -> Result<()> {
+    let kustomizations: Api<Kustomization> = Api::namespaced(client.clone(), namespace);
+    let kustomization = kustomizations.get(kustomization_name).await?;
+    if !allows_namespace(&kustomization, workload_namespace) {
+        return Err(anyhow::anyhow!(
+            "Kustomization {}/{} does not allow updates from namespace {} ({})",
+            namespace,
+            kustomization_name,
+            workload_namespace,
+            annotations::FLUX_ALLOWED_NAMESPACES
+        ));
+    }
+
+    let candidates = substitute_configmaps(&kustomization.spec);
+    if candidates.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Kustomization {}/{} has no ConfigMap in spec.postBuild.substituteFrom",
+            namespace,
+            kustomization_name
+        ));
+    }
+
+    let config_maps: Api<ConfigMap> = Api::namespaced(client.clone(), namespace);
+    let mut target = None;
+    for name in &candidates {
+        let defines_var = config_maps
+            .get_opt(name)
+            .await?
+            .and_then(|cm| cm.data)
+            .is_some_and(|data| data.contains_key(var_name));
+        if defines_var {
+            target = Some(name);
+            break;
+        }
+    }
+    let config_map = target.unwrap_or(&candidates[0]);
+
+    config_maps
+        .patch(
+            config_map,
+            &PatchParams::default(),
+            &Patch::Merge(&json!({"data": {var_name: new_tag}})),
+        )
+        .await?;
+    debug!(
+        "Set {}={} in ConfigMap {}/{}",
+        var_name, new_tag, namespace, config_map
+    );
+
+    kustomizations
+        .patch(
+            kustomization_name,
+            &PatchParams::default(),
+            &Patch::Merge(&json!({
+                "metadata": {
+                    "annotations": {RECONCILE_REQUESTED_AT: Utc::now().to_rfc3339()}
+                }
+            })),
+        )
+        .await?;
+
+    info!(
+        "Updated {} to {} for Kustomization {}/{} via ConfigMap {}",
+        var_name, new_tag, namespace, kustomization_name, config_map
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::kustomization::{PostBuild, SubstituteReference};
+    use axum::{
+        Json, Router,
+        body::Bytes,
+        extract::{Path, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        routing::get,
+    };
+    use std::sync::{Arc, Mutex};
+
+    fn annotations(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn reference(kind: &str, name: &str) -> SubstituteReference {
+        SubstituteReference {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            optional: false,
+        }
+    }
+
+    fn spec(substitute_from: Vec<SubstituteReference>) -> KustomizationSpec {
+        KustomizationSpec {
+            interval: Some("10m".to_string()),
+            path: Some("./apps/web".to_string()),
+            source_ref: None,
+            post_build: Some(PostBuild {
+                substitute: None,
+                substitute_from: Some(substitute_from),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_config_from_annotations() {
+        let config = FluxKustomizationConfig::from_annotations(&annotations(&[(
+            annotations::FLUX_KUSTOMIZATION,
+            "apps",
+        )]))
+        .unwrap();
+        assert_eq!(config.namespace, None);
+        assert_eq!(config.name, "apps");
+        assert_eq!(config.var_name_for("web-server"), "web_server_image_tag");
+
+        let config = FluxKustomizationConfig::from_annotations(&annotations(&[
+            (annotations::FLUX_KUSTOMIZATION, "flux-system/apps"),
+            (annotations::FLUX_SUBSTITUTE_VAR, "web_tag"),
+        ]))
+        .unwrap();
+        assert_eq!(config.namespace.as_deref(), Some("flux-system"));
+        assert_eq!(config.var_name_for("web-server"), "web_tag");
+
+        for invalid in ["", "/apps", "flux-system/"] {
+            assert!(
+                FluxKustomizationConfig::from_annotations(&annotations(&[(
+                    annotations::FLUX_KUSTOMIZATION,
+                    invalid,
+                )]))
+                .is_none()
+            );
+        }
+        assert!(FluxKustomizationConfig::from_annotations(&BTreeMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_substitute_configmaps() {
+        let spec = spec(vec![
+            reference("Secret", "credentials"),
+            reference("ConfigMap", "cluster-vars"),
+            reference("ConfigMap", "image-tags"),
+        ]);
+        assert_eq!(
+            substitute_configmaps(&spec),
+            vec!["cluster-vars".to_string(), "image-tags".to_string()]
+        );
+
+        let mut without_post_build = spec.clone();
+        without_post_build.post_build = None;
+        assert!(substitute_configmaps(&without_post_build).is_empty());
+    }
+
+    #[test]
+    fn test_kustomization_deserializes_flux_manifest() {
+        let kustomization: Kustomization = serde_json::from_value(json!({
+            "apiVersion": "kustomize.toolkit.fluxcd.io/v1",
+            "kind": "Kustomization",
+            "metadata": {"name": "apps", "namespace": "flux-system"},
+            "spec": {
+                "interval": "10m",
+                "path": "./apps",
+                "prune": true,
+                "sourceRef": {"kind": "GitRepository", "name": "flux-system"},
+                "postBuild": {
+                    "substitute": {"cluster": "prod"},
+                    "substituteFrom": [
+                        {"kind": "ConfigMap", "name": "image-tags"},
+                        {"kind": "Secret", "name": "secrets", "optional": true}
+                    ]
+                }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            kustomization.spec.source_ref.as_ref().unwrap().kind,
+            "GitRepository"
+        );
+        assert_eq!(
+            substitute_configmaps(&kustomization.spec),
+            vec!["image-tags".to_string()]
+        );
+    }
+
+    // GET requests have no body
+    fn json_body(body: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(body).unwrap_or(serde_json::Value::Null)
+    }
+
+    /// Requests received by the fake API server as (method, path, body)
+    type Requests = Arc<Mutex<Vec<(String, String, serde_json::Value)>>>;
+
+    /// Minimal Kubernetes API server with one Kustomization referencing two ConfigMaps
+    async fn fake_api_server() -> (Client, Requests) {
+        let requests: Requests = Arc::new(Mutex::new(Vec::new()));
+
+        async fn kustomization(
+            State(requests): State<Requests>,
+            method: axum::http::Method,
+            body: Bytes,
+        ) -> Response {
+            requests.lock().unwrap().push((
+                method.to_string(),
+                "kustomization".to_string(),
+                json_body(&body),
+            ));
+            Json(json!({
+                "apiVersion": "kustomize.toolkit.fluxcd.io/v1",
+                "kind": "Kustomization",
+                "metadata": {"name": "apps", "namespace": "default"},
+                "spec": {
+                    "postBuild": {"substituteFrom": [
+                        {"kind": "ConfigMap", "name": "cluster-vars"},
+                        {"kind": "ConfigMap", "name": "image-tags"}
+                    ]}
+                }
+            }))
+            .into_response()
+        }
+
+        async fn config_map(
+            State(requests): State<Requests>,
+            method: axum::http::Method,
+            Path(name): Path<String>,
+            body: Bytes,
+        ) -> Response {
+            requests
+                .lock()
+                .unwrap()
+                .push((method.to_string(), name.clone(), json_body(&body)));
+            let data = match name.as_str() {
+                "cluster-vars" => json!({"cluster": "prod"}),
+                "image-tags" => json!({"web_image_tag": "1.0.0"}),
+                _ => return StatusCode::NOT_FOUND.into_response(),
+            };
+            Json(json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMap",
+                "metadata": {"name": name, "namespace": "default"},
+                "data": data
+            }))
+            .into_response()
+        }
+
+        let app = Router::new()
+            .route(
+                "/apis/kustomize.toolkit.fluxcd.io/v1/namespaces/default/kustomizations/apps",
+                get(kustomization).patch(kustomization),
+            )
+            .route(
+                "/api/v1/namespaces/default/configmaps/{name}",
+                get(config_map).patch(config_map),
+            )
+            .with_state(requests.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let config = kube::Config::new(format!("http://{}", addr).parse().unwrap());
+        (Client::try_from(config).unwrap(), requests)
+    }
+
+    #[tokio::test]
+    async fn test_update_patches_configmap_defining_var() {
+        let (client, requests) = fake_api_server().await;
+
+        update_kustomization_image_substitute(
+            &client,
+            "default",
+            "default",
+            "apps",
+            "web_image_tag",
+            "1.1.0",
+        )
+        .await
+        .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let patches: Vec<_> = requests
+            .iter()
+            .filter(|(method, _, _)| method == "PATCH")
+            .collect();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].1, "image-tags");
+        assert_eq!(patches[0].2, json!({"data": {"web_image_tag": "1.1.0"}}));
+        assert_eq!(patches[1].1, "kustomization");
+        assert!(patches[1].2["metadata"]["annotations"][RECONCILE_REQUESTED_AT].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_update_falls_back_to_first_configmap() {
+        let (client, requests) = fake_api_server().await;
+
+        update_kustomization_image_substitute(
+            &client,
+            "default",
+            "default",
+            "apps",
+            "api_image_tag",
+            "2.0.0",
+        )
+        .await
+        .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let patch = requests
+            .iter()
+            .find(|(method, _, _)| method == "PATCH")
+            .unwrap();
+        assert_eq!(patch.1, "cluster-vars");
+        assert_eq!(patch.2, json!({"data": {"api_image_tag": "2.0.0"}}));
+    }
+
+    #[tokio::test]
+    async fn test_update_from_other_namespace_is_rejected() {
+        let (client, requests) = fake_api_server().await;
+
+        let err = update_kustomization_image_substitute(
+            &client,
+            "team-a",
+            "default",
+            "apps",
+            "web_image_tag",
+            "1.1.0",
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not allow updates from namespace team-a")
+        );
+
+        let requests = requests.lock().unwrap();
+        assert!(!requests.iter().any(|(method, _, _)| method == "PATCH"));
+    }
+
+    #[test]
+    fn test_allows_namespace() {
+        let mut kustomization: Kustomization = serde_json::from_value(json!({
+            "apiVersion": "kustomize.toolkit.fluxcd.io/v1",
+            "kind": "Kustomization",
+            "metadata": {"name": "apps", "namespace": "flux-system"},
+            "spec": {}
+        }))
+        .unwrap();
+        assert!(allows_namespace(&kustomization, "flux-system"));
+        assert!(!allows_namespace(&kustomization, "team-a"));
+
+        kustomization.metadata.annotations = Some(annotations(&[(
+            annotations::FLUX_ALLOWED_NAMESPACES,
+            "team-a, team-b",
+        )]));
+        assert!(allows_namespace(&kustomization, "team-a"));
+        assert!(allows_namespace(&kustomization, "team-b"));
+        assert!(!allows_namespace(&kustomization, "team-c"));
+
+        kustomization.metadata.annotations =
+            Some(annotations(&[(annotations::FLUX_ALLOWED_NAMESPACES, "*")]));
+        assert!(allows_namespace(&kustomization, "team-c"));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod helm_repository;
+pub mod kustomization;
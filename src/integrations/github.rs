@@ -0,0 +1,828 @@
+use crate::gitops::git::{commit_message, replace_image_tag};
+use crate::models::policy::annotations;
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use hmac::{Hmac, Mac};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::{Patch, PatchParams};
+use kube::{Api, Client, ResourceExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use tracing::{debug, info};
+
+/// Value of `headwind.sh/approval-mode` that routes approvals through GitHub PRs
+pub const GITHUB_PR_MODE: &str = "github-pr";
+
+/// Key in the token Secret holding the GitHub token
+pub const TOKEN_SECRET_KEY: &str = "token";
+
+const DEFAULT_API_URL: &str = "https://api.github.com";
+const MARKER_PREFIX: &str = "<!-- headwind:";
+const MARKER_SUFFIX: &str = "-->";
+
+/// Minimal GitHub REST API client for opening update pull requests
+#[derive(Clone)]
+pub struct GitHubClient {
+    pub token: String,
+    pub owner: String,
+    pub repo: String,
+    api_url: String,
+    http: reqwest::Client,
+}
+
+impl GitHubClient {
+    pub fn new(token: String, owner: String, repo: String) -> Self {
+        Self {
+            token,
+            owner,
+            repo,
+            api_url: DEFAULT_API_URL.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Override the API base URL (GitHub Enterprise or tests)
+    pub fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/repos/{}/{}/{}",
+            self.api_url, self.owner, self.repo, path
+        )
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, self.url(path))
+            .bearer_auth(&self.token)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "headwind")
+    }
+
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<serde_json::Value> {
+        let response = builder.send().await.context("GitHub API request failed")?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or(serde_json::Value::Null);
+
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "GitHub API returned {}: {}",
+                status,
+                body.get("message")
+                    .and_then(|m| m.as_str())
+                    .unwrap_or("unknown error")
+            ));
+        }
+
+        Ok(body)
+    }
+
+    /// Create `head_branch` pointing at the current tip of `base_branch`
+    pub async fn create_branch(&self, base_branch: &str, head_branch: &str) -> Result<()> {
+        let base = self
+            .send(self.request(
+                reqwest::Method::GET,
+                &format!("git/ref/heads/{}", base_branch),
+            ))
+            .await?;
+        let sha = base["object"]["sha"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Branch {} has no commit SHA", base_branch))?;
+
+        self.send(
+            self.request(reqwest::Method::POST, "git/refs")
+                .json(&json!({
+                    "ref": format!("refs/heads/{}", head_branch),
+                    "sha": sha
+                })),
+        )
+        .await?;
+
+        debug!("Created branch {} from {}", head_branch, base_branch);
+        Ok(())
+    }
+
+    /// Replace the image tag in `file_path` on `branch` and commit the change
+    pub async fn commit_image_update(
+        &self,
+        branch: &str,
+        file_path: &str,
+        image: &str,
+        new_tag: &str,
+    ) -> Result<()> {
+        let file = self
+            .send(
+                self.request(reqwest::Method::GET, &format!("contents/{}", file_path))
+                    .query(&[("ref", branch)]),
+            )
+            .await?;
+
+        let file_sha = file["sha"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("{} has no blob SHA", file_path))?;
+        let encoded: String = file["content"]
+            .as_str()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        let content = String::from_utf8(BASE64.decode(encoded)?)?;
+
+        let updated = replace_image_tag(&content, image, new_tag)
+            .ok_or_else(|| anyhow::anyhow!("Image {} not found in {}", image, file_path))?;
+
+        self.send(
+            self.request(reqwest::Method::PUT, &format!("contents/{}", file_path))
+                .json(&json!({
+                    "message": commit_message(image, new_tag),
+                    "content": BASE64.encode(updated),
+                    "sha": file_sha,
+                    "branch": branch
+                })),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Open a pull request and return its number
+    pub async fn create_pr(
+        &self,
+        base_branch: &str,
+        head_branch: &str,
+        title: &str,
+        body: &str,
+    ) -> Result<u64> {
+        let pr = self
+            .send(self.request(reqwest::Method::POST, "pulls").json(&json!({
+                "title": title,
+                "head": head_branch,
+                "base": base_branch,
+                "body": body
+            })))
+            .await?;
+
+        pr["number"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("GitHub response did not include a PR number"))
+    }
+
+    /// Create a branch with the image tag change and open a PR for it
+    pub async fn open_image_update_pr(
+        &self,
+        config: &GitHubPrConfig,
+        target: &PrTarget,
+        image: &str,
+        current_tag: &str,
+        new_tag: &str,
+    ) -> Result<u64> {
+        let head_branch = format!(
+            "headwind/{}-{}-{}",
+            target.namespace,
+            target.name,
+            new_tag.replace([':', '/'], "-")
+        );
+
+        self.create_branch(&config.base_branch, &head_branch)
+            .await?;
+        self.commit_image_update(&head_branch, &config.file_path, image, new_tag)
+            .await?;
+
+        let title = format!("Update {} to {}", image, new_tag);
+        let body = format!(
+            "Headwind detected a new version for {} `{}/{}`.\n\n\
+             | | |\n|---|---|\n| Image | `{}` |\n| Current | `{}` |\n| New | `{}` |\n\n\
+             Merging this pull request applies the update to the cluster.\n\n{}",
+            target.kind,
+            target.namespace,
+            target.name,
+            image,
+            current_tag,
+            new_tag,
+            target.to_marker()
+        );
+
+        let number = self
+            .create_pr(&config.base_branch, &head_branch, &title, &body)
+            .await?;
+        info!(
+            "Opened pull request #{} in {}/{} for {}/{}",
+            number, self.owner, self.repo, target.namespace, target.name
+        );
+
+        Ok(number)
+    }
+}
+
+/// GitHub PR approval settings parsed from resource annotations
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitHubPrConfig {
+    pub owner: String,
+    pub repo: String,
+    pub token_secret: String,
+    pub file_path: String,
+    pub base_branch: String,
+}
+
+impl GitHubPrConfig {
+    /// Returns Some when `headwind.sh/approval-mode: github-pr` is set along with
+    /// `headwind.sh/github-repo`, `headwind.sh/github-token-secret` and `headwind.sh/git-file-path`
+    pub fn from_annotations(annotations: &BTreeMap<String, String>) -> Option<Self> {
+        if annotations
+            .get(annotations::APPROVAL_MODE)
+            .map(String::as_str)
+            != Some(GITHUB_PR_MODE)
+        {
+            return None;
+        }
+
+        let (owner, repo) = annotations.get(annotations::GITHUB_REPO)?.split_once('/')?;
+        let token_secret = annotations.get(annotations::GITHUB_TOKEN_SECRET)?;
+        let file_path = annotations.get(annotations::GIT_FILE_PATH)?;
+        let base_branch = annotations
+            .get(annotations::GIT_BRANCH)
+            .cloned()
+            .unwrap_or_else(|| "main".to_string());
+
+        Some(Self {
+            owner: owner.trim().to_string(),
+            repo: repo.trim().to_string(),
+            token_secret: token_secret.trim().to_string(),
+            file_path: file_path.trim().to_string(),
+            base_branch,
+        })
+    }
+
+    /// Build a client using the token stored in the referenced Secret
+    pub async fn client(&self, client: &Client, namespace: &str) -> Result<GitHubClient> {
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), namespace);
+        let secret = secrets
+            .get(&self.token_secret)
+            .await
+            .with_context(|| format!("Failed to read Secret {}", self.token_secret))?;
+
+        let token = secret
+            .data
+            .as_ref()
+            .and_then(|d| d.get(TOKEN_SECRET_KEY))
+            .map(|v| String::from_utf8_lossy(&v.0).trim().to_string())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Secret {} has no '{}' key",
+                    self.token_secret,
+                    TOKEN_SECRET_KEY
+                )
+            })?;
+
+        Ok(GitHubClient::new(
+            token,
+            self.owner.clone(),
+            self.repo.clone(),
+        ))
+    }
+}
+
+/// Workload an update PR applies to, embedded in the PR body so the merge
+/// webhook knows what to patch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrTarget {
+    pub kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub container: String,
+    pub new_image: String,
+}
+
+impl PrTarget {
+    pub fn to_marker(&self) -> String {
+        format!(
+            "{} {} {}",
+            MARKER_PREFIX,
+            serde_json::to_string(self).unwrap_or_default(),
+            MARKER_SUFFIX
+        )
+    }
+
+    pub fn from_body(body: &str) -> Option<Self> {
+        let start = body.find(MARKER_PREFIX)? + MARKER_PREFIX.len();
+        let end = body[start..].find(MARKER_SUFFIX)? + start;
+        serde_json::from_str(body[start..end].trim()).ok()
+    }
+}
+
+/// Subset of the GitHub `pull_request` webhook payload
+#[derive(Debug, Deserialize)]
+pub struct PullRequestEvent {
+    pub action: String,
+    pub pull_request: PullRequest,
+    pub repository: Repository,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    /// `owner/repo`
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    #[serde(default)]
+    pub merged: bool,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub merged_by: Option<GitHubUser>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitHubUser {
+    pub login: String,
+}
+
+impl PullRequestEvent {
+    /// The update target if this event is a merged headwind PR
+    pub fn merged_target(&self) -> Option<PrTarget> {
+        if self.action != "closed" || !self.pull_request.merged {
+            return None;
+        }
+        PrTarget::from_body(self.pull_request.body.as_deref()?)
+    }
+}
+
+/// Where a merged PR or MR came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeSource {
+    /// `repository.full_name` of a GitHub webhook
+    GitHub { repository: String },
+    /// Project of a GitLab merge request
+    GitLab { project_id: u64 },
+}
+
+/// Images proposed by the open PRs or MRs of a workload, by container
+pub fn proposed_images(annotations: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    annotations
+        .get(annotations::PROPOSED_IMAGES)
+        .and_then(|v| serde_json::from_str(v).ok())
+        .unwrap_or_default()
+}
+
+async fn patch_proposed_images(
+    client: &Client,
+    namespace: &str,
+    name: &str,
+    proposals: &BTreeMap<String, String>,
+) -> Result<()> {
+    let value = if proposals.is_empty() {
+        serde_json::Value::Null
+    } else {
+        json!(serde_json::to_string(proposals)?)
+    };
+    let deployments: Api<Deployment> = Api::namespaced(client.clone(), namespace);
+    deployments
+        .patch(
+            name,
+            &PatchParams::default(),
+            &Patch::Merge(json!({
+                "metadata": { "annotations": { annotations::PROPOSED_IMAGES: value } }
+            })),
+        )
+        .await
+        .with_context(|| format!("Failed to record proposed images of {}/{}", namespace, name))?;
+    Ok(())
+}
+
+/// Remember that a PR or MR proposing `image` for `container` was opened, so
+/// only that image is applied when it is merged
+pub async fn record_proposed_image(
+    client: &Client,
+    deployment: &Deployment,
+    container: &str,
+    image: &str,
+) -> Result<()> {
+    let mut proposals = proposed_images(deployment.annotations());
+    proposals.insert(container.to_string(), image.to_string());
+    patch_proposed_images(
+        client,
+        &deployment.namespace().unwrap_or_default(),
+        &deployment.name_any(),
+        &proposals,
+    )
+    .await
+}
+
+/// Check a merged PR or MR against the annotations of the live workload: the
+/// marker in its description is written by whoever can edit it, so only the
+/// image headwind proposed, from the repository or project the workload names,
+/// is trusted
+pub fn verify_merged_target(
+    annotations: &BTreeMap<String, String>,
+    target: &PrTarget,
+    source: &MergeSource,
+) -> Result<()> {
+    match source {
+        MergeSource::GitHub { repository } => {
+            let config = GitHubPrConfig::from_annotations(annotations).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{}/{} is not approved through GitHub pull requests",
+                    target.namespace,
+                    target.name
+                )
+            })?;
+            let configured = format!("{}/{}", config.owner, config.repo);
+            if !configured.eq_ignore_ascii_case(repository) {
+                return Err(anyhow::anyhow!(
+                    "{}/{} is approved through {}, not {}",
+                    target.namespace,
+                    target.name,
+                    configured,
+                    repository
+                ));
+            }
+        },
+        MergeSource::GitLab { project_id } => {
+            let config = crate::integrations::gitlab::GitLabMrConfig::from_annotations(annotations)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "{}/{} is not approved through GitLab merge requests",
+                        target.namespace,
+                        target.name
+                    )
+                })?;
+            if config.project_id != *project_id {
+                return Err(anyhow::anyhow!(
+                    "{}/{} is approved through GitLab project {}, not {}",
+                    target.namespace,
+                    target.name,
+                    config.project_id,
+                    project_id
+                ));
+            }
+        },
+    }
+
+    let proposed = proposed_images(annotations);
+    if proposed.get(&target.container) != Some(&target.new_image) {
+        return Err(anyhow::anyhow!(
+            "headwind did not propose {} for container {} of {}/{}",
+            target.new_image,
+            target.container,
+            target.namespace,
+            target.name
+        ));
+    }
+    Ok(())
+}
+
+/// Apply the update described by a merged PR or MR, after checking it
+/// against the live workload with [`verify_merged_target`]
+pub async fn apply_merged_update(
+    client: Client,
+    target: &PrTarget,
+    source: &MergeSource,
+    merged_by: Option<String>,
+) -> Result<()> {
+    if target.kind != "Deployment" {
+        return Err(anyhow::anyhow!(
+            "Unsupported resource kind for GitHub PR approval: {}",
+            target.kind
+        ));
+    }
+
+    let deployments: Api<Deployment> = Api::namespaced(client.clone(), &target.namespace);
+    let deployment = deployments.get(&target.name).await.with_context(|| {
+        format!(
+            "Failed to get Deployment {}/{}",
+            target.namespace, target.name
+        )
+    })?;
+    verify_merged_target(deployment.annotations(), target, source)?;
+
+    crate::controller::update_deployment_image_with_tracking(
+        client.clone(),
+        &target.namespace,
+        &target.name,
+        &target.container,
+        &target.new_image,
+        None,
+        merged_by,
+    )
+    .await?;
+
+    // A replayed webhook must not apply the image again
+    let mut proposals = proposed_images(deployment.annotations());
+    proposals.remove(&target.container);
+    patch_proposed_images(&client, &target.namespace, &target.name, &proposals).await
+}
+
+/// Verify the `X-Hub-Signature-256` header against the webhook secret
+pub fn verify_signature(secret: &str, payload: &[u8], signature_header: &str) -> bool {
+    let Some(signature) = signature_header
+        .strip_prefix("sha256=")
+        .and_then(|s| hex::decode(s).ok())
+    else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(payload);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        extract::Path,
+        routing::{get, post},
+    };
+
+    async fn mock_github() -> String {
+        let app = Router::new()
+            .route(
+                "/repos/org/deploy/git/ref/heads/{branch}",
+                get(|Path(branch): Path<String>| async move {
+                    assert_eq!(branch, "main");
+                    Json(json!({"object": {"sha": "abc123"}}))
+                }),
+            )
+            .route(
+                "/repos/org/deploy/git/refs",
+                post(|Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(body["sha"], "abc123");
+                    Json(json!({"ref": body["ref"]}))
+                }),
+            )
+            .route(
+                "/repos/org/deploy/contents/{*path}",
+                get(|| async {
+                    Json(json!({
+                        "sha": "blob1",
+                        "content": BASE64.encode("image: nginx:1.25.0\n")
+                    }))
+                })
+                .put(|Json(body): Json<serde_json::Value>| async move {
+                    let content = BASE64.decode(body["content"].as_str().unwrap()).unwrap();
+                    assert_eq!(String::from_utf8(content).unwrap(), "image: nginx:1.26.0\n");
+                    assert_eq!(body["sha"], "blob1");
+                    Json(json!({"commit": {"sha": "def456"}}))
+                }),
+            )
+            .route(
+                "/repos/org/deploy/pulls",
+                post(|Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(body["base"], "main");
+                    Json(json!({"number": 42}))
+                }),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        format!("http://{}", addr)
+    }
+
+    fn target() -> PrTarget {
+        PrTarget {
+            kind: "Deployment".to_string(),
+            namespace: "default".to_string(),
+            name: "web".to_string(),
+            container: "nginx".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_image_update_pr() {
+        let api_url = mock_github().await;
+        let client =
+            GitHubClient::new("token".to_string(), "org".to_string(), "deploy".to_string())
+                .with_api_url(api_url);
+        let config = GitHubPrConfig {
+            owner: "org".to_string(),
+            repo: "deploy".to_string(),
+            token_secret: "github-token".to_string(),
+            file_path: "apps/web.yaml".to_string(),
+            base_branch: "main".to_string(),
+        };
+
+        let number = client
+            .open_image_update_pr(&config, &target(), "nginx", "1.25.0", "1.26.0")
+            .await
+            .unwrap();
+        assert_eq!(number, 42);
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_reported() {
+        let app = Router::new().route(
+            "/repos/org/deploy/pulls",
+            post(|| async {
+                (
+                    axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+                    Json(json!({"message": "Validation Failed"})),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let client =
+            GitHubClient::new("token".to_string(), "org".to_string(), "deploy".to_string())
+                .with_api_url(format!("http://{}", addr));
+        let err = client
+            .create_pr("main", "feature", "title", "body")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Validation Failed"));
+    }
+
+    #[test]
+    fn test_pr_target_marker_roundtrip() {
+        let body = format!("Some description\n\n{}", target().to_marker());
+        assert_eq!(PrTarget::from_body(&body), Some(target()));
+        assert_eq!(PrTarget::from_body("no marker here"), None);
+    }
+
+    #[test]
+    fn test_merged_target() {
+        let event: PullRequestEvent = serde_json::from_value(json!({
+            "action": "closed",
+            "pull_request": {
+                "number": 42,
+                "merged": true,
+                "body": target().to_marker(),
+                "merged_by": {"login": "octocat"}
+            },
+            "repository": {"full_name": "org/deploy"}
+        }))
+        .unwrap();
+        assert_eq!(event.merged_target(), Some(target()));
+
+        let event: PullRequestEvent = serde_json::from_value(json!({
+            "action": "closed",
+            "pull_request": {"number": 42, "merged": false, "body": target().to_marker()},
+            "repository": {"full_name": "org/deploy"}
+        }))
+        .unwrap();
+        assert_eq!(event.merged_target(), None);
+    }
+
+    #[test]
+    fn test_github_pr_config_from_annotations() {
+        let mut annotations = BTreeMap::new();
+        annotations.insert(
+            annotations::APPROVAL_MODE.to_string(),
+            "github-pr".to_string(),
+        );
+        annotations.insert(
+            annotations::GITHUB_REPO.to_string(),
+            "org/deploy".to_string(),
+        );
+        annotations.insert(
+            annotations::GITHUB_TOKEN_SECRET.to_string(),
+            "github-token".to_string(),
+        );
+        annotations.insert(
+            annotations::GIT_FILE_PATH.to_string(),
+            "apps/web.yaml".to_string(),
+        );
+
+        let config = GitHubPrConfig::from_annotations(&annotations).unwrap();
+        assert_eq!(config.owner, "org");
+        assert_eq!(config.repo, "deploy");
+        assert_eq!(config.base_branch, "main");
+
+        annotations.insert(annotations::GITHUB_REPO.to_string(), "invalid".to_string());
+        assert!(GitHubPrConfig::from_annotations(&annotations).is_none());
+    }
+
+    fn github_annotations() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (
+                annotations::APPROVAL_MODE.to_string(),
+                "github-pr".to_string(),
+            ),
+            (
+                annotations::GITHUB_REPO.to_string(),
+                "org/deploy".to_string(),
+            ),
+            (
+                annotations::GITHUB_TOKEN_SECRET.to_string(),
+                "github-token".to_string(),
+            ),
+            (
+                annotations::GIT_FILE_PATH.to_string(),
+                "apps/web.yaml".to_string(),
+            ),
+            (
+                annotations::PROPOSED_IMAGES.to_string(),
+                r#"{"nginx":"nginx:1.26.0"}"#.to_string(),
+            ),
+        ])
+    }
+
+    fn from_github(repository: &str) -> MergeSource {
+        MergeSource::GitHub {
+            repository: repository.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_verify_merged_target() {
+        assert!(
+            verify_merged_target(&github_annotations(), &target(), &from_github("org/deploy"))
+                .is_ok()
+        );
+        assert!(
+            verify_merged_target(&github_annotations(), &target(), &from_github("Org/Deploy"))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_forged_marker_is_rejected() {
+        // Another image than the one headwind proposed
+        let forged = PrTarget {
+            new_image: "attacker/miner:latest".to_string(),
+            ..target()
+        };
+        let err = verify_merged_target(&github_annotations(), &forged, &from_github("org/deploy"))
+            .unwrap_err();
+        assert!(err.to_string().contains("did not propose"), "{}", err);
+
+        // Another container, or a workload with nothing proposed
+        let other_container = PrTarget {
+            container: "sidecar".to_string(),
+            ..target()
+        };
+        assert!(
+            verify_merged_target(
+                &github_annotations(),
+                &other_container,
+                &from_github("org/deploy")
+            )
+            .is_err()
+        );
+        let mut annotations = github_annotations();
+        annotations.remove(annotations::PROPOSED_IMAGES);
+        assert!(verify_merged_target(&annotations, &target(), &from_github("org/deploy")).is_err());
+
+        // A PR merged in another repository
+        let err = verify_merged_target(&github_annotations(), &target(), &from_github("evil/fork"))
+            .unwrap_err();
+        assert!(err.to_string().contains("not evil/fork"), "{}", err);
+
+        // A workload that isn't approved through GitHub at all
+        let mut annotations = github_annotations();
+        annotations.remove(annotations::APPROVAL_MODE);
+        assert!(verify_merged_target(&annotations, &target(), &from_github("org/deploy")).is_err());
+        assert!(
+            verify_merged_target(
+                &github_annotations(),
+                &target(),
+                &MergeSource::GitLab { project_id: 7 }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_proposed_images() {
+        assert_eq!(
+            proposed_images(&github_annotations()),
+            BTreeMap::from([("nginx".to_string(), "nginx:1.26.0".to_string())])
+        );
+        let invalid = BTreeMap::from([(
+            annotations::PROPOSED_IMAGES.to_string(),
+            "nginx=nginx:1.26.0".to_string(),
+        )]);
+        assert!(proposed_images(&invalid).is_empty());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(b"payload");
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_signature("secret", b"payload", &signature));
+        assert!(!verify_signature("other", b"payload", &signature));
+        assert!(!verify_signature("secret", b"payload", "sha256=zz"));
+    }
+}
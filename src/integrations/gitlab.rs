@@ -0,0 +1,631 @@
+use crate::gitops::git::{commit_message, replace_image_tag};
+use crate::integrations::github::{MergeSource, PrTarget, apply_merged_update};
+use crate::models::policy::annotations;
+use crate::models::webhook::{GitLabPipelineEvent, GitLabUser};
+use anyhow::{Context, Result};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{Api, Client};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::{debug, info, warn};
+
+/// Value of `headwind.sh/approval-mode` that routes approvals through GitLab MRs
+pub const GITLAB_MR_MODE: &str = "gitlab-mr";
+
+/// Key in the token Secret holding the GitLab access token
+pub const TOKEN_SECRET_KEY: &str = "token";
+
+/// Base URL of the GitLab instance, e.g. `https://gitlab.example.com`
+pub const GITLAB_URL_ENV: &str = "HEADWIND_GITLAB_URL";
+
+/// Access token used to look up merged MRs when a pipeline webhook arrives
+pub const GITLAB_TOKEN_ENV: &str = "HEADWIND_GITLAB_TOKEN";
+
+const DEFAULT_BASE_URL: &str = "https://gitlab.com";
+
+/// Minimal GitLab REST API client for opening update merge requests
+#[derive(Clone)]
+pub struct GitLabClient {
+    pub base_url: String,
+    pub private_token: String,
+    pub project_id: u64,
+    http: reqwest::Client,
+}
+
+/// Merge request as returned by the GitLab API
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeRequest {
+    pub iid: u64,
+    pub state: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub merged_by: Option<GitLabUser>,
+}
+
+impl MergeRequest {
+    /// The update target if this is a merged headwind MR
+    pub fn merged_target(&self) -> Option<PrTarget> {
+        if self.state != "merged" {
+            return None;
+        }
+        PrTarget::from_body(self.description.as_deref()?)
+    }
+}
+
+impl GitLabClient {
+    pub fn new(base_url: impl Into<String>, private_token: String, project_id: u64) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            private_token,
+            project_id,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Client for `project_id` on the instance from `HEADWIND_GITLAB_URL`
+    pub fn from_env(private_token: String, project_id: u64) -> Self {
+        let base_url = std::env::var(GITLAB_URL_ENV)
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string());
+        Self::new(base_url, private_token, project_id)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}/api/v4/projects/{}/{}",
+            self.base_url, self.project_id, path
+        )
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, self.url(path))
+            .header("PRIVATE-TOKEN", &self.private_token)
+            .header("User-Agent", "headwind")
+    }
+
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<serde_json::Value> {
+        let response = builder.send().await.context("GitLab API request failed")?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or(serde_json::Value::Null);
+
+        if !status.is_success() {
+            // `message` is a string or an object of field errors
+            let message = match body.get("message").or_else(|| body.get("error")) {
+                Some(serde_json::Value::String(message)) => message.clone(),
+                Some(message) => message.to_string(),
+                None => "unknown error".to_string(),
+            };
+            return Err(anyhow::anyhow!(
+                "GitLab API returned {}: {}",
+                status,
+                message
+            ));
+        }
+
+        Ok(body)
+    }
+
+    /// Create `branch` from the current tip of `base_branch`
+    pub async fn create_branch(&self, base_branch: &str, branch: &str) -> Result<()> {
+        self.send(
+            self.request(reqwest::Method::POST, "repository/branches")
+                .query(&[("branch", branch), ("ref", base_branch)]),
+        )
+        .await?;
+
+        debug!("Created branch {} from {}", branch, base_branch);
+        Ok(())
+    }
+
+    /// Replace the image tag in `file_path` on `branch` and commit the change
+    pub async fn commit_image_update(
+        &self,
+        branch: &str,
+        file_path: &str,
+        image: &str,
+        new_tag: &str,
+    ) -> Result<()> {
+        let path = format!("repository/files/{}", encode_path(file_path));
+        let file = self
+            .send(
+                self.request(reqwest::Method::GET, &path)
+                    .query(&[("ref", branch)]),
+            )
+            .await?;
+
+        let encoded: String = file["content"]
+            .as_str()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        let content = String::from_utf8(BASE64.decode(encoded)?)?;
+
+        let updated = replace_image_tag(&content, image, new_tag)
+            .ok_or_else(|| anyhow::anyhow!("Image {} not found in {}", image, file_path))?;
+
+        self.send(self.request(reqwest::Method::PUT, &path).json(&json!({
+            "branch": branch,
+            "content": updated,
+            "commit_message": commit_message(image, new_tag),
+            "last_commit_id": file["last_commit_id"]
+        })))
+        .await?;
+
+        Ok(())
+    }
+
+    /// Open a merge request and return its IID
+    pub async fn create_merge_request(
+        &self,
+        source_branch: &str,
+        target_branch: &str,
+        title: &str,
+        description: &str,
+    ) -> Result<u64> {
+        let mr = self
+            .send(
+                self.request(reqwest::Method::POST, "merge_requests")
+                    .json(&json!({
+                        "source_branch": source_branch,
+                        "target_branch": target_branch,
+                        "title": title,
+                        "description": description,
+                        "remove_source_branch": true
+                    })),
+            )
+            .await?;
+
+        mr["iid"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("GitLab response did not include a merge request IID"))
+    }
+
+    /// Merge requests that introduced commit `sha`
+    pub async fn merge_requests_for_commit(&self, sha: &str) -> Result<Vec<MergeRequest>> {
+        let body = self
+            .send(self.request(
+                reqwest::Method::GET,
+                &format!("repository/commits/{}/merge_requests", sha),
+            ))
+            .await?;
+        Ok(serde_json::from_value(body)?)
+    }
+
+    /// Create a branch with the image tag change and open an MR for it
+    pub async fn open_image_update_mr(
+        &self,
+        config: &GitLabMrConfig,
+        target: &PrTarget,
+        image: &str,
+        current_tag: &str,
+        new_tag: &str,
+    ) -> Result<u64> {
+        let source_branch = format!(
+            "headwind/{}-{}-{}",
+            target.namespace,
+            target.name,
+            new_tag.replace([':', '/'], "-")
+        );
+
+        self.create_branch(&config.base_branch, &source_branch)
+            .await?;
+        self.commit_image_update(&source_branch, &config.file_path, image, new_tag)
+            .await?;
+
+        let title = format!("Update {} to {}", image, new_tag);
+        let description = format!(
+            "Headwind detected a new version for {} `{}/{}`.\n\n\
+             | | |\n|---|---|\n| Image | `{}` |\n| Current | `{}` |\n| New | `{}` |\n\n\
+             Merging this merge request applies the update to the cluster once the pipeline \
+             on {} succeeds.\n\n{}",
+            target.kind,
+            target.namespace,
+            target.name,
+            image,
+            current_tag,
+            new_tag,
+            config.base_branch,
+            target.to_marker()
+        );
+
+        let iid = self
+            .create_merge_request(&source_branch, &config.base_branch, &title, &description)
+            .await?;
+        info!(
+            "Opened merge request !{} in GitLab project {} for {}/{}",
+            iid, self.project_id, target.namespace, target.name
+        );
+
+        Ok(iid)
+    }
+}
+
+/// Percent-encode a repository file path for the files API, which expects
+/// e.g. `apps%2Fweb%2Edeployment.yaml`
+fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// GitLab MR approval settings parsed from resource annotations
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitLabMrConfig {
+    pub project_id: u64,
+    pub token_secret: String,
+    pub file_path: String,
+    pub base_branch: String,
+}
+
+impl GitLabMrConfig {
+    /// Returns Some when `headwind.sh/approval-mode: gitlab-mr` is set along with
+    /// `headwind.sh/gitlab-project-id`, `headwind.sh/gitlab-token-secret` and `headwind.sh/git-file-path`
+    pub fn from_annotations(annotations: &BTreeMap<String, String>) -> Option<Self> {
+        if annotations
+            .get(annotations::APPROVAL_MODE)
+            .map(String::as_str)
+            != Some(GITLAB_MR_MODE)
+        {
+            return None;
+        }
+
+        let project_id = annotations
+            .get(annotations::GITLAB_PROJECT_ID)?
+            .trim()
+            .parse()
+            .ok()?;
+        let token_secret = annotations.get(annotations::GITLAB_TOKEN_SECRET)?;
+        let file_path = annotations.get(annotations::GIT_FILE_PATH)?;
+        let base_branch = annotations
+            .get(annotations::GIT_BRANCH)
+            .cloned()
+            .unwrap_or_else(|| "main".to_string());
+
+        Some(Self {
+            project_id,
+            token_secret: token_secret.trim().to_string(),
+            file_path: file_path.trim().to_string(),
+            base_branch,
+        })
+    }
+
+    /// Build a client using the token stored in the referenced Secret
+    pub async fn client(&self, client: &Client, namespace: &str) -> Result<GitLabClient> {
+        let secrets: Api<Secret> = Api::namespaced(client.clone(), namespace);
+        let secret = secrets
+            .get(&self.token_secret)
+            .await
+            .with_context(|| format!("Failed to read Secret {}", self.token_secret))?;
+
+        let token = secret
+            .data
+            .as_ref()
+            .and_then(|d| d.get(TOKEN_SECRET_KEY))
+            .map(|v| String::from_utf8_lossy(&v.0).trim().to_string())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Secret {} has no '{}' key",
+                    self.token_secret,
+                    TOKEN_SECRET_KEY
+                )
+            })?;
+
+        Ok(GitLabClient::from_env(token, self.project_id))
+    }
+}
+
+/// Apply the updates of headwind MRs merged by the commit a successful
+/// pipeline ran for. Returns the number of updates applied.
+///
+/// Pipeline events do not say which MR was merged, so the MRs of the commit
+/// are looked up with the token from `HEADWIND_GITLAB_TOKEN`.
+pub async fn apply_pipeline_updates(
+    client: Client,
+    gitlab: &GitLabClient,
+    event: &GitLabPipelineEvent,
+) -> Result<usize> {
+    if !event.succeeded() {
+        return Ok(0);
+    }
+
+    let merge_requests = gitlab
+        .merge_requests_for_commit(&event.object_attributes.sha)
+        .await?;
+
+    let mut applied = 0;
+    for mr in merge_requests {
+        let Some(target) = mr.merged_target() else {
+            continue;
+        };
+        info!(
+            "GitLab merge request !{} merged, applying update for {} {}/{}",
+            mr.iid, target.kind, target.namespace, target.name
+        );
+        let merged_by = mr.merged_by.map(|u| u.username);
+        let source = MergeSource::GitLab {
+            project_id: gitlab.project_id,
+        };
+        match apply_merged_update(client.clone(), &target, &source, merged_by).await {
+            Ok(()) => applied += 1,
+            Err(e) => warn!(
+                "Failed to apply merged MR update for {}/{}: {}",
+                target.namespace, target.name, e
+            ),
+        }
+    }
+
+    Ok(applied)
+}
+
+/// Compare the `X-Gitlab-Token` header with the webhook secret in constant time
+pub fn verify_token(secret: &str, token_header: &str) -> bool {
+    let (secret, token) = (secret.as_bytes(), token_header.as_bytes());
+    secret.len() == token.len()
+        && secret
+            .iter()
+            .zip(token)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        extract::{Path, Query},
+        http::HeaderMap,
+        routing::{get, post},
+    };
+    use std::collections::HashMap;
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    async fn mock_gitlab() -> String {
+        let app = Router::new()
+            .route(
+                "/api/v4/projects/7/repository/branches",
+                post(
+                    |headers: HeaderMap, Query(query): Query<HashMap<String, String>>| async move {
+                        assert_eq!(headers["PRIVATE-TOKEN"], "token");
+                        assert_eq!(query["ref"], "main");
+                        Json(json!({"name": query["branch"]}))
+                    },
+                ),
+            )
+            .route(
+                "/api/v4/projects/7/repository/files/{path}",
+                get(|Path(path): Path<String>| async move {
+                    assert_eq!(path, "apps/web.yaml");
+                    Json(json!({
+                        "content": BASE64.encode("image: nginx:1.25.0\n"),
+                        "last_commit_id": "abc123"
+                    }))
+                })
+                .put(|Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(body["content"], "image: nginx:1.26.0\n");
+                    assert_eq!(body["last_commit_id"], "abc123");
+                    assert!(
+                        body["branch"]
+                            .as_str()
+                            .unwrap()
+                            .starts_with("headwind/default-web-")
+                    );
+                    Json(json!({"file_path": "apps/web.yaml"}))
+                }),
+            )
+            .route(
+                "/api/v4/projects/7/merge_requests",
+                post(|Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(body["target_branch"], "main");
+                    assert_eq!(body["source_branch"], "headwind/default-web-1.26.0");
+                    assert!(PrTarget::from_body(body["description"].as_str().unwrap()).is_some());
+                    Json(json!({"iid": 12, "id": 3401}))
+                }),
+            );
+
+        serve(app).await
+    }
+
+    fn target() -> PrTarget {
+        PrTarget {
+            kind: "Deployment".to_string(),
+            namespace: "default".to_string(),
+            name: "web".to_string(),
+            container: "nginx".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_open_image_update_mr() {
+        let client = GitLabClient::new(mock_gitlab().await, "token".to_string(), 7);
+        let config = GitLabMrConfig {
+            project_id: 7,
+            token_secret: "gitlab-token".to_string(),
+            file_path: "apps/web.yaml".to_string(),
+            base_branch: "main".to_string(),
+        };
+
+        let iid = client
+            .open_image_update_mr(&config, &target(), "nginx", "1.25.0", "1.26.0")
+            .await
+            .unwrap();
+        assert_eq!(iid, 12);
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_reported() {
+        let app = Router::new().route(
+            "/api/v4/projects/7/merge_requests",
+            post(|| async {
+                (
+                    axum::http::StatusCode::CONFLICT,
+                    Json(json!({"message": ["Another open merge request already exists for this source branch: !11"]})),
+                )
+            }),
+        );
+        let client = GitLabClient::new(serve(app).await, "token".to_string(), 7);
+        let err = client
+            .create_merge_request("feature", "main", "title", "description")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("409"));
+        assert!(
+            err.to_string()
+                .contains("Another open merge request already exists")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_requests_for_commit() {
+        let app = Router::new().route(
+            "/api/v4/projects/7/repository/commits/{sha}/merge_requests",
+            get(|Path(sha): Path<String>| async move {
+                assert_eq!(sha, "bcbb5ec");
+                Json(json!([
+                    {
+                        "iid": 12,
+                        "state": "merged",
+                        "description": format!("Update\n\n{}", target().to_marker()),
+                        "merged_by": {"username": "jdoe"}
+                    },
+                    {"iid": 13, "state": "opened", "description": target().to_marker()},
+                    {"iid": 14, "state": "merged", "description": null}
+                ]))
+            }),
+        );
+        let client = GitLabClient::new(serve(app).await, "token".to_string(), 7);
+
+        let merge_requests = client.merge_requests_for_commit("bcbb5ec").await.unwrap();
+        let targets: Vec<_> = merge_requests
+            .iter()
+            .filter_map(|mr| mr.merged_target().map(|t| (mr.iid, t)))
+            .collect();
+        assert_eq!(targets, vec![(12, target())]);
+        assert_eq!(
+            merge_requests[0].merged_by.as_ref().unwrap().username,
+            "jdoe"
+        );
+    }
+
+    #[test]
+    fn test_encode_path() {
+        assert_eq!(encode_path("apps/web.yaml"), "apps%2Fweb%2Eyaml");
+        assert_eq!(encode_path("my app/v1"), "my%20app%2Fv1");
+    }
+
+    #[test]
+    fn test_gitlab_mr_config_from_annotations() {
+        let mut annotations = BTreeMap::new();
+        annotations.insert(
+            annotations::APPROVAL_MODE.to_string(),
+            "gitlab-mr".to_string(),
+        );
+        annotations.insert(annotations::GITLAB_PROJECT_ID.to_string(), "7".to_string());
+        annotations.insert(
+            annotations::GITLAB_TOKEN_SECRET.to_string(),
+            "gitlab-token".to_string(),
+        );
+        annotations.insert(
+            annotations::GIT_FILE_PATH.to_string(),
+            "apps/web.yaml".to_string(),
+        );
+
+        let config = GitLabMrConfig::from_annotations(&annotations).unwrap();
+        assert_eq!(config.project_id, 7);
+        assert_eq!(config.token_secret, "gitlab-token");
+        assert_eq!(config.base_branch, "main");
+
+        annotations.insert(
+            annotations::GITLAB_PROJECT_ID.to_string(),
+            "ops/deploy".to_string(),
+        );
+        assert!(GitLabMrConfig::from_annotations(&annotations).is_none());
+
+        annotations.insert(
+            annotations::APPROVAL_MODE.to_string(),
+            "github-pr".to_string(),
+        );
+        assert!(GitLabMrConfig::from_annotations(&annotations).is_none());
+    }
+
+    #[test]
+    fn test_merged_target_must_come_from_configured_project() {
+        use crate::integrations::github::verify_merged_target;
+
+        let annotations = BTreeMap::from([
+            (
+                annotations::APPROVAL_MODE.to_string(),
+                "gitlab-mr".to_string(),
+            ),
+            (annotations::GITLAB_PROJECT_ID.to_string(), "7".to_string()),
+            (
+                annotations::GITLAB_TOKEN_SECRET.to_string(),
+                "gitlab-token".to_string(),
+            ),
+            (
+                annotations::GIT_FILE_PATH.to_string(),
+                "apps/web.yaml".to_string(),
+            ),
+            (
+                annotations::PROPOSED_IMAGES.to_string(),
+                r#"{"nginx":"nginx:1.26.0"}"#.to_string(),
+            ),
+        ]);
+
+        assert!(
+            verify_merged_target(
+                &annotations,
+                &target(),
+                &MergeSource::GitLab { project_id: 7 }
+            )
+            .is_ok()
+        );
+
+        // An MR in another project the token can read
+        let err = verify_merged_target(
+            &annotations,
+            &target(),
+            &MergeSource::GitLab { project_id: 8 },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("project 7, not 8"), "{}", err);
+
+        // A description marker naming another image
+        let forged = PrTarget {
+            new_image: "attacker/miner:latest".to_string(),
+            ..target()
+        };
+        assert!(
+            verify_merged_target(
+                &annotations,
+                &forged,
+                &MergeSource::GitLab { project_id: 7 }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_verify_token() {
+        assert!(verify_token("secret", "secret"));
+        assert!(!verify_token("secret", "other"));
+        assert!(!verify_token("secret", ""));
+    }
+}
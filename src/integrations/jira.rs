@@ -0,0 +1,469 @@
+use crate::models::crd::UpdateRequest;
+use crate::models::policy::annotations;
+use anyhow::{Context, Result};
+use kube::api::{Patch, PatchParams};
+use kube::{Api, Client, ResourceExt};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tracing::{debug, info, warn};
+
+/// Issue type used when `HEADWIND_JIRA_ISSUE_TYPE` is not set
+pub const DEFAULT_ISSUE_TYPE: &str = "Task";
+
+/// Name of the transition applied when an UpdateRequest completes
+pub const DONE_TRANSITION: &str = "Done";
+
+/// Minimal JIRA Cloud REST API (v3) client for tracking UpdateRequests
+#[derive(Clone)]
+pub struct JiraClient {
+    pub base_url: String,
+    pub username: String,
+    pub api_token: String,
+    pub project_key: String,
+    pub issue_type: String,
+    http: reqwest::Client,
+}
+
+impl JiraClient {
+    pub fn new(
+        base_url: String,
+        username: String,
+        api_token: String,
+        project_key: String,
+        issue_type: String,
+    ) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            username,
+            api_token,
+            project_key,
+            issue_type,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Client for `project_key` using `HEADWIND_JIRA_URL`, `HEADWIND_JIRA_USERNAME`,
+    /// `HEADWIND_JIRA_API_TOKEN` and `HEADWIND_JIRA_ISSUE_TYPE`. Returns None unless
+    /// the URL and credentials are set.
+    pub fn from_env(project_key: &str) -> Option<Self> {
+        let base_url = std::env::var("HEADWIND_JIRA_URL").ok()?;
+        let username = std::env::var("HEADWIND_JIRA_USERNAME").ok()?;
+        let api_token = std::env::var("HEADWIND_JIRA_API_TOKEN").ok()?;
+        let issue_type = std::env::var("HEADWIND_JIRA_ISSUE_TYPE")
+            .unwrap_or_else(|_| DEFAULT_ISSUE_TYPE.to_string());
+
+        Some(Self::new(
+            base_url,
+            username,
+            api_token,
+            project_key.to_string(),
+            issue_type,
+        ))
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.http
+            .request(method, format!("{}/rest/api/3/{}", self.base_url, path))
+            .basic_auth(&self.username, Some(&self.api_token))
+            .header("Accept", "application/json")
+    }
+
+    async fn send(&self, builder: reqwest::RequestBuilder) -> Result<serde_json::Value> {
+        let response = builder.send().await.context("JIRA API request failed")?;
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or(serde_json::Value::Null);
+
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "JIRA API returned {}: {}",
+                status,
+                error_message(&body)
+            ));
+        }
+
+        Ok(body)
+    }
+
+    /// Create an issue in the project and return its key (e.g. `OPS-123`)
+    pub async fn create_issue(
+        &self,
+        summary: &str,
+        description: &str,
+        labels: Vec<String>,
+    ) -> Result<String> {
+        let issue = self
+            .send(self.request(reqwest::Method::POST, "issue").json(&json!({
+                "fields": {
+                    "project": {"key": self.project_key},
+                    "issuetype": {"name": self.issue_type},
+                    "summary": summary,
+                    "description": adf_document(description),
+                    "labels": labels
+                }
+            })))
+            .await?;
+
+        issue["key"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow::anyhow!("JIRA response did not include an issue key"))
+    }
+
+    /// Apply the transition named `name` (or leading to a status named `name`) to `key`
+    pub async fn transition_issue(&self, key: &str, name: &str) -> Result<()> {
+        let path = format!("issue/{}/transitions", key);
+        let available = self.send(self.request(reqwest::Method::GET, &path)).await?;
+
+        let id = available["transitions"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|t| {
+                [&t["name"], &t["to"]["name"]]
+                    .iter()
+                    .filter_map(|n| n.as_str())
+                    .any(|n| n.eq_ignore_ascii_case(name))
+            })
+            .and_then(|t| t["id"].as_str())
+            .ok_or_else(|| anyhow::anyhow!("Issue {} has no {} transition", key, name))?;
+
+        self.send(
+            self.request(reqwest::Method::POST, &path)
+                .json(&json!({"transition": {"id": id}})),
+        )
+        .await?;
+
+        debug!("Transitioned JIRA issue {} to {}", key, name);
+        Ok(())
+    }
+}
+
+/// Join `errorMessages` and field `errors` from a JIRA error response
+fn error_message(body: &serde_json::Value) -> String {
+    let mut messages: Vec<String> = body["errorMessages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.as_str().map(str::to_string))
+        .collect();
+    if let Some(errors) = body["errors"].as_object() {
+        messages.extend(
+            errors
+                .iter()
+                .map(|(field, m)| format!("{}: {}", field, m.as_str().unwrap_or_default())),
+        );
+    }
+
+    if messages.is_empty() {
+        "unknown error".to_string()
+    } else {
+        messages.join("; ")
+    }
+}
+
+/// Wrap plain text in an Atlassian Document Format document, one paragraph per line
+fn adf_document(text: &str) -> serde_json::Value {
+    let paragraphs: Vec<_> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            json!({
+                "type": "paragraph",
+                "content": [{"type": "text", "text": line}]
+            })
+        })
+        .collect();
+
+    json!({"type": "doc", "version": 1, "content": paragraphs})
+}
+
+/// Open an issue for a newly created UpdateRequest when the target resource is
+/// annotated with `headwind.sh/jira-project`, and record its key in the
+/// UpdateRequest's `headwind.sh/jira-issue` annotation. Failures are logged;
+/// the UpdateRequest is still usable without a ticket.
+pub async fn open_issue_for_update_request(
+    client: &Client,
+    resource_annotations: Option<&BTreeMap<String, String>>,
+    update_request: &UpdateRequest,
+) {
+    let Some(project) = resource_annotations.and_then(|a| a.get(annotations::JIRA_PROJECT)) else {
+        return;
+    };
+    let Some(jira) = JiraClient::from_env(project) else {
+        warn!(
+            "{} is set to {} but HEADWIND_JIRA_URL, HEADWIND_JIRA_USERNAME and HEADWIND_JIRA_API_TOKEN are not all configured",
+            annotations::JIRA_PROJECT,
+            project
+        );
+        return;
+    };
+
+    let name = update_request.name_any();
+    let namespace = update_request.spec.target_ref.namespace.clone();
+    let key = match jira
+        .create_issue(
+            &issue_summary(update_request),
+            &issue_description(update_request),
+            vec!["headwind".to_string(), namespace.clone()],
+        )
+        .await
+    {
+        Ok(key) => key,
+        Err(e) => {
+            warn!(
+                "Failed to create JIRA issue for UpdateRequest {}/{}: {}",
+                namespace, name, e
+            );
+            return;
+        },
+    };
+
+    let update_requests: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
+    let patch = json!({"metadata": {"annotations": {annotations::JIRA_ISSUE: key}}});
+    match update_requests
+        .patch(&name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await
+    {
+        Ok(_) => info!(
+            "Created JIRA issue {} for UpdateRequest {}/{}",
+            key, namespace, name
+        ),
+        Err(e) => warn!(
+            "Created JIRA issue {} but failed to annotate UpdateRequest {}/{}: {}",
+            key, namespace, name, e
+        ),
+    }
+}
+
+/// Transition the issue recorded on a completed UpdateRequest to Done
+pub async fn complete_issue_for_update_request(update_request: &UpdateRequest) {
+    let Some(key) = update_request.annotations().get(annotations::JIRA_ISSUE) else {
+        return;
+    };
+    let project = key.split('-').next().unwrap_or_default();
+    let Some(jira) = JiraClient::from_env(project) else {
+        warn!(
+            "Cannot transition JIRA issue {}: JIRA is not configured",
+            key
+        );
+        return;
+    };
+
+    if let Err(e) = jira.transition_issue(key, DONE_TRANSITION).await {
+        warn!("Failed to transition JIRA issue {} to Done: {}", key, e);
+    }
+}
+
+fn issue_summary(update_request: &UpdateRequest) -> String {
+    let target = &update_request.spec.target_ref;
+    format!(
+        "Update {} {}/{} to {}",
+        target.kind, target.namespace, target.name, update_request.spec.new_image
+    )
+}
+
+fn issue_description(update_request: &UpdateRequest) -> String {
+    let spec = &update_request.spec;
+    let mut lines = vec![
+        format!(
+            "Headwind created UpdateRequest {} for {} {}/{}.",
+            update_request.name_any(),
+            spec.target_ref.kind,
+            spec.target_ref.namespace,
+            spec.target_ref.name
+        ),
+        format!("Current: {}", spec.current_image),
+        format!("New: {}", spec.new_image),
+    ];
+    if let Some(container) = &spec.container_name {
+        lines.push(format!("Container: {}", container));
+    }
+    if let Some(reason) = &spec.reason {
+        lines.push(format!("Reason: {}", reason));
+    }
+    lines.push("This issue is moved to Done when the update is applied.".to_string());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::crd::{TargetRef, UpdatePolicyType, UpdateRequestSpec, UpdateType};
+    use axum::{
+        Json, Router,
+        extract::Path,
+        http::{HeaderMap, StatusCode},
+        routing::{get, post},
+    };
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    fn client(base_url: String) -> JiraClient {
+        JiraClient::new(
+            base_url,
+            "ops@example.com".to_string(),
+            "api-token".to_string(),
+            "OPS".to_string(),
+            "Task".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_create_issue() {
+        let app = Router::new().route(
+            "/rest/api/3/issue",
+            post(
+                |headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                    // base64("ops@example.com:api-token")
+                    assert_eq!(
+                        headers["authorization"],
+                        "Basic b3BzQGV4YW1wbGUuY29tOmFwaS10b2tlbg=="
+                    );
+                    let fields = &body["fields"];
+                    assert_eq!(fields["project"]["key"], "OPS");
+                    assert_eq!(fields["issuetype"]["name"], "Task");
+                    assert_eq!(fields["summary"], "Update web to nginx:1.26.0");
+                    assert_eq!(fields["labels"], json!(["headwind", "default"]));
+                    assert_eq!(fields["description"]["type"], "doc");
+                    assert_eq!(
+                        fields["description"]["content"][1]["content"][0]["text"],
+                        "New: nginx:1.26.0"
+                    );
+                    (
+                        StatusCode::CREATED,
+                        Json(json!({"id": "10001", "key": "OPS-123"})),
+                    )
+                },
+            ),
+        );
+        let jira = client(serve(app).await);
+
+        let key = jira
+            .create_issue(
+                "Update web to nginx:1.26.0",
+                "Current: nginx:1.25.0\nNew: nginx:1.26.0",
+                vec!["headwind".to_string(), "default".to_string()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(key, "OPS-123");
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_reported() {
+        let app = Router::new().route(
+            "/rest/api/3/issue",
+            post(|| async {
+                (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "errorMessages": [],
+                        "errors": {"project": "valid project is required"}
+                    })),
+                )
+            }),
+        );
+        let jira = client(serve(app).await);
+
+        let err = jira
+            .create_issue("summary", "description", Vec::new())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("400"));
+        assert!(
+            err.to_string()
+                .contains("project: valid project is required")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transition_issue() {
+        let app = Router::new().route(
+            "/rest/api/3/issue/{key}/transitions",
+            get(|Path(key): Path<String>| async move {
+                assert_eq!(key, "OPS-123");
+                Json(json!({"transitions": [
+                    {"id": "11", "name": "Start Progress", "to": {"name": "In Progress"}},
+                    {"id": "31", "name": "Resolve", "to": {"name": "Done"}}
+                ]}))
+            })
+            .post(|Json(body): Json<serde_json::Value>| async move {
+                assert_eq!(body["transition"]["id"], "31");
+                StatusCode::NO_CONTENT
+            }),
+        );
+        let jira = client(serve(app).await);
+
+        jira.transition_issue("OPS-123", "Done").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_transition_issue_missing_transition() {
+        let app = Router::new().route(
+            "/rest/api/3/issue/{key}/transitions",
+            get(|| async {
+                Json(json!({"transitions": [
+                    {"id": "11", "name": "Start Progress", "to": {"name": "In Progress"}}
+                ]}))
+            })
+            .post(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+        );
+        let jira = client(serve(app).await);
+
+        let err = jira.transition_issue("OPS-123", "Done").await.unwrap_err();
+        assert!(err.to_string().contains("no Done transition"));
+    }
+
+    #[test]
+    fn test_issue_summary_and_description() {
+        let update_request = UpdateRequest::new(
+            "web-nginx-1-26-0",
+            UpdateRequestSpec {
+                target_ref: TargetRef {
+                    api_version: "apps/v1".to_string(),
+                    kind: "Deployment".to_string(),
+                    name: "web".to_string(),
+                    namespace: "default".to_string(),
+                },
+                update_type: UpdateType::Image,
+                container_name: Some("nginx".to_string()),
+                container_kind: Default::default(),
+                current_image: "nginx:1.25.0".to_string(),
+                new_image: "nginx:1.26.0".to_string(),
+                policy: UpdatePolicyType::Minor,
+                reason: None,
+                require_approval: true,
+                expires_at: None,
+                scheduled_at: None,
+                priority: None,
+                updates: Vec::new(),
+                discussion_thread: Vec::new(),
+                sla_deadline: None,
+                impact: None,
+            },
+        );
+
+        assert_eq!(
+            issue_summary(&update_request),
+            "Update Deployment default/web to nginx:1.26.0"
+        );
+        let description = issue_description(&update_request);
+        assert!(description.contains("UpdateRequest web-nginx-1-26-0"));
+        assert!(description.contains("Container: nginx"));
+        assert!(!description.contains("Reason:"));
+    }
+
+    #[test]
+    fn test_adf_document_skips_blank_lines() {
+        let doc = adf_document("first\n\nsecond");
+        assert_eq!(doc["content"].as_array().unwrap().len(), 2);
+        assert_eq!(doc["content"][1]["content"][0]["text"], "second");
+    }
+}
@@ -0,0 +1,5 @@
+pub mod argocd;
+pub mod flux;
+pub mod github;
+pub mod gitlab;
+pub mod jira;
@@ -3,16 +3,28 @@
 // This file exposes internal modules for integration tests while keeping
 // the binary entrypoint in main.rs
 
+// The metrics lazy_static! block outgrows the default macro recursion limit
+#![recursion_limit = "256"]
+
 pub mod approval;
+pub mod archive;
+pub mod cache;
+pub mod cli;
 pub mod config;
 pub mod controller;
+pub mod gitops;
 pub mod helm;
+pub mod integrations;
 pub mod metrics;
 pub mod models;
 pub mod notifications;
 pub mod policy;
 pub mod polling;
 pub mod rollback;
+pub mod schedule;
+pub mod security;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod ui;
 pub mod webhook;
 
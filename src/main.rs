@@ -1,7 +1,7 @@
 use anyhow::Result;
 use headwind::{approval, config, controller, metrics, notifications, polling, ui, webhook};
 use kube::Client;
-use tracing::info;
+use tracing::{error, info};
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
@@ -15,6 +15,8 @@ async fn main() -> Result<()> {
         .init();
 
     info!("Starting Headwind - Kubernetes Update Operator");
+    metrics::status::record_start();
+    config::features::log_enabled_features();
 
     // Create Kubernetes client
     let client = Client::try_default().await?;
@@ -22,6 +24,19 @@ async fn main() -> Result<()> {
     // Start configuration watcher for hot-reload
     config::start_config_watcher(client.clone()).await;
 
+    // Report configuration problems before starting any servers
+    let startup_config = config::get_cached_config().unwrap_or_default();
+    let config_errors = config::validation::validate_config(&startup_config, &client).await;
+    for e in &config_errors {
+        error!("Invalid configuration: {}", e);
+    }
+    if !config_errors.is_empty() && config::validation::strict_validation_enabled() {
+        anyhow::bail!(
+            "Found {} configuration error(s) and HEADWIND_STRICT_CONFIG_VALIDATION is enabled",
+            config_errors.len()
+        );
+    }
+
     // Initialize notification manager
     notifications::init_notifications();
 
@@ -42,6 +57,15 @@ async fn main() -> Result<()> {
             .ok()
             .and_then(|v| v.parse().ok())
             .unwrap_or(300),
+        max_concurrent_polls: std::env::var("HEADWIND_POLLING_MAX_CONCURRENT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10),
+        insecure_registries: polling::insecure_registries_from_env(),
+        tls: polling::RegistryTlsConfig::from_env()?,
+        tag_normalization: headwind::models::TagNormalizationRule::from_env(),
+        ignore_images: headwind::policy::ignore::ignore_images_from_env(),
+        tag_sort: polling::TagSortStrategy::from_env()?,
     };
     let poller =
         polling::RegistryPoller::new(polling_config, event_sender, chart_event_sender).await?;
@@ -50,6 +74,17 @@ async fn main() -> Result<()> {
     // Initialize approval API server
     let approval_handle = approval::start_approval_server().await?;
 
+    // Initialize UpdateRequest admission webhook (optional, disabled by default)
+    let admission_handle = webhook::admission::start_admission_server(client.clone()).await?;
+    let admission_wait = async move {
+        match admission_handle {
+            Some(handle) => {
+                let _ = handle.await;
+            },
+            None => std::future::pending::<()>().await,
+        }
+    };
+
     // Initialize Web UI server
     let ui_handle = tokio::spawn(async move {
         if let Err(e) = ui::start_ui_server().await {
@@ -63,6 +98,9 @@ async fn main() -> Result<()> {
     // Start gauge updater to periodically update resource counts
     let gauge_updater_handle = metrics::start_gauge_updater(client.clone());
 
+    // Sample how long UpdateRequests have been waiting for approval
+    let queue_age_handle = metrics::queue::start_queue_age_recorder(client.clone());
+
     info!("Headwind is running");
 
     // Wait for all services
@@ -71,9 +109,11 @@ async fn main() -> Result<()> {
         _ = webhook_handle => info!("Webhook server stopped"),
         _ = polling_handle => info!("Registry poller stopped"),
         _ = approval_handle => info!("Approval server stopped"),
+        _ = admission_wait => info!("Admission webhook server stopped"),
         _ = ui_handle => info!("Web UI server stopped"),
         _ = controller_handle => info!("Controllers stopped"),
         _ = gauge_updater_handle => info!("Gauge updater stopped"),
+        _ = queue_age_handle => info!("Queue age recorder stopped"),
     }
 
     Ok(())
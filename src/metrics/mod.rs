@@ -1,14 +1,26 @@
 use anyhow::Result;
 use axum::{Router, http::StatusCode, response::IntoResponse, routing::get};
 use lazy_static::lazy_static;
-use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Registry, TextEncoder};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
+};
 use tokio::task::JoinHandle;
 use tracing::info;
 
 pub mod client;
+pub mod queue;
+pub mod status;
 
 lazy_static! {
-    pub static ref REGISTRY: Registry = Registry::new();
+    // In multi-cluster mode every metric carries the cluster it comes from
+    pub static ref REGISTRY: Registry = match crate::ui::clusters::current_cluster() {
+        Some(cluster) => Registry::new_custom(
+            None,
+            Some(std::collections::HashMap::from([("cluster".to_string(), cluster)])),
+        ).unwrap(),
+        None => Registry::new(),
+    };
 
     // Webhook metrics
     pub static ref WEBHOOK_EVENTS_TOTAL: IntCounter = IntCounter::new(
@@ -21,6 +33,11 @@ lazy_static! {
         "Total number of webhook events successfully processed"
     ).unwrap();
 
+    pub static ref WEBHOOK_QUEUE_DEPTH: IntGauge = IntGauge::new(
+        "headwind_webhook_queue_depth",
+        "Number of image push events waiting for a webhook worker"
+    ).unwrap();
+
     // Update metrics
     pub static ref UPDATES_PENDING: IntGauge = IntGauge::new(
         "headwind_updates_pending",
@@ -32,6 +49,76 @@ lazy_static! {
         "Total number of updates approved"
     ).unwrap();
 
+    pub static ref APPROVALS_THROTTLED_TOTAL: IntCounter = IntCounter::new(
+        "headwind_approvals_throttled_total",
+        "Total number of approved updates that waited for a free update slot"
+    ).unwrap();
+
+    pub static ref APPROVAL_QUEUE_DEPTH: IntGauge = IntGauge::new(
+        "headwind_approval_queue_depth",
+        "Number of approved updates waiting to be applied"
+    ).unwrap();
+
+    // Age of Pending UpdateRequests, sampled every minute by the queue recorder
+    pub static ref PENDING_UPDATE_AGE_SECONDS: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "headwind_pending_update_age_seconds",
+            "Time Pending UpdateRequests have waited for approval, sampled every minute"
+        ).buckets(queue::AGE_BUCKETS.to_vec()),
+        &["namespace", "policy"]
+    ).unwrap();
+
+    pub static ref APPROVAL_LATENCY_SECONDS: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "headwind_approval_latency_seconds",
+            "Time from the creation of an UpdateRequest to its approval"
+        ).buckets(queue::AGE_BUCKETS.to_vec()),
+        &["namespace", "policy"]
+    ).unwrap();
+
+    pub static ref STEP_DURATION_SECONDS: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "headwind_update_step_duration_seconds",
+            "Duration of the steps recorded in UpdateRequest progress"
+        ).buckets(vec![0.01, 0.1, 0.5, 1.0, 5.0, 30.0, 60.0, 300.0, 600.0]),
+        &["step"]
+    ).unwrap();
+
+    pub static ref LOCK_ACQUISITIONS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_lock_acquisitions_total",
+        "Total number of UpdateRequest Leases taken before applying an update"
+    ).unwrap();
+
+    pub static ref LOCK_CONTENTIONS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_lock_contentions_total",
+        "Total number of UpdateRequests skipped because another replica held their Lease"
+    ).unwrap();
+
+    pub static ref APPROVAL_RBAC_DENIALS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_approval_rbac_denials_total",
+        "Total number of approvals denied because the caller lacks the headwind.sh/approved-by-role role"
+    ).unwrap();
+
+    pub static ref ADMISSION_DENIALS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_admission_denials_total",
+        "Total number of UpdateRequest changes denied by the admission webhook"
+    ).unwrap();
+
+    pub static ref SIGNATURE_ADMISSION_DENIALS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_signature_admission_denials_total",
+        "Total number of pods and workloads denied because an image has no valid cosign signature"
+    ).unwrap();
+
+    pub static ref SIGNATURE_VIOLATIONS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_signature_violations_total",
+        "Total number of provisionally admitted images whose cosign signature failed verification"
+    ).unwrap();
+
+    pub static ref PATCH_PREVIEWS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_patch_previews_total",
+        "Total number of UpdateRequest patch previews generated"
+    ).unwrap();
+
     pub static ref UPDATES_REJECTED: IntCounter = IntCounter::new(
         "headwind_updates_rejected_total",
         "Total number of updates rejected"
@@ -60,6 +147,47 @@ lazy_static! {
         "Total number of reconciliation errors"
     ).unwrap();
 
+    pub static ref STORE_SIZE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "headwind_informer_store_size",
+            "Number of objects held in the shared informer cache"
+        ),
+        &["kind"]
+    ).unwrap();
+
+    // Controller queue metrics (HEADWIND_RECONCILE_QUEUE_METRICS=true)
+    pub static ref CONTROLLER_QUEUE_DEPTH: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "headwind_controller_queue_depth",
+            "Number of objects waiting to be reconciled"
+        ),
+        &["controller"]
+    ).unwrap();
+
+    pub static ref RECONCILE_CONCURRENT_IN_FLIGHT: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "headwind_reconcile_concurrent_in_flight",
+            "Number of reconciles currently running"
+        ),
+        &["controller"]
+    ).unwrap();
+
+    pub static ref CONTROLLER_QUEUE_LATENCY_SECONDS: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "headwind_controller_queue_latency_seconds",
+            "Time from a watch event to the start of its reconcile"
+        ).buckets(vec![0.001, 0.01, 0.1, 0.5, 1.0, 5.0, 30.0, 60.0]),
+        &["controller"]
+    ).unwrap();
+
+    pub static ref CONTROLLER_WORK_DURATION_SECONDS: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "headwind_controller_work_duration_seconds",
+            "Time spent executing a reconcile"
+        ).buckets(vec![0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0]),
+        &["controller"]
+    ).unwrap();
+
     // Resource metrics
     pub static ref DEPLOYMENTS_WATCHED: IntGauge = IntGauge::new(
         "headwind_deployments_watched",
@@ -81,12 +209,78 @@ lazy_static! {
         "Number of DaemonSets being watched"
     ).unwrap();
 
+    pub static ref REPLICASETS_WATCHED: IntGauge = IntGauge::new(
+        "headwind_replicasets_watched",
+        "Number of standalone ReplicaSets being watched"
+    ).unwrap();
+
+    pub static ref KNATIVE_SERVICES_WATCHED: IntGauge = IntGauge::new(
+        "headwind_knative_services_watched",
+        "Number of Knative Services being watched"
+    ).unwrap();
+
+    pub static ref CROSSPLANE_COMPOSITIONS_WATCHED: IntGauge = IntGauge::new(
+        "headwind_crossplane_compositions_watched",
+        "Number of Crossplane Compositions being watched"
+    ).unwrap();
+
     // Polling metrics
     pub static ref POLLING_CYCLES_TOTAL: IntCounter = IntCounter::new(
         "headwind_polling_cycles_total",
         "Total number of registry polling cycles"
     ).unwrap();
 
+    pub static ref REGISTRY_CLIENTS_ACTIVE: IntGauge = IntGauge::new(
+        "headwind_registry_clients_active",
+        "OCI registry clients shared by the current polling cycle"
+    ).unwrap();
+
+    pub static ref POLLING_CYCLE_DURATION_SECONDS: Histogram = Histogram::with_opts(
+        HistogramOpts::new(
+            "headwind_polling_cycle_duration_seconds",
+            "End-to-end duration of a registry polling cycle"
+        ).buckets(vec![1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0])
+    ).unwrap();
+
+    pub static ref MANIFEST_FETCHES_TOTAL: IntCounter = IntCounter::new(
+        "headwind_manifest_fetches_total",
+        "Total number of image manifests and configs fetched to read the push date of a tag"
+    ).unwrap();
+
+    pub static ref TAG_CACHE_HITS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_tag_cache_hits_total",
+        "Total number of tag list lookups served from the cache"
+    ).unwrap();
+
+    pub static ref TAG_CACHE_MISSES_TOTAL: IntCounter = IntCounter::new(
+        "headwind_tag_cache_misses_total",
+        "Total number of tag list lookups that required a registry request"
+    ).unwrap();
+
+    pub static ref CACHE_SHARD_CONTENTION_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "headwind_cache_shard_contention_total",
+            "Total number of image cache writes that waited for a locked shard, by shard"
+        ),
+        &["shard"]
+    ).unwrap();
+
+    pub static ref CREDENTIAL_REFRESH_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "headwind_credential_refresh_total",
+            "Total number of proactive refreshes of expiring registry credentials, by registry"
+        ),
+        &["registry"]
+    ).unwrap();
+
+    pub static ref CREDENTIAL_REFRESH_FAILURES_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "headwind_credential_refresh_failures_total",
+            "Total number of failed proactive refreshes of registry credentials, by registry"
+        ),
+        &["registry"]
+    ).unwrap();
+
     pub static ref POLLING_ERRORS_TOTAL: IntCounter = IntCounter::new(
         "headwind_polling_errors_total",
         "Total number of registry polling errors"
@@ -112,6 +306,21 @@ lazy_static! {
         "Total number of new Helm chart versions discovered via polling"
     ).unwrap();
 
+    pub static ref WEBHOOK_TLS_HANDSHAKE_FAILURES_TOTAL: IntCounter = IntCounter::new(
+        "headwind_webhook_tls_handshake_failures_total",
+        "Total number of failed TLS handshakes on the webhook server, including untrusted client certificates"
+    ).unwrap();
+
+    pub static ref POLLING_QUAY_REQUESTS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_polling_quay_requests_total",
+        "Total number of Quay.io REST API requests made to list tags"
+    ).unwrap();
+
+    pub static ref POLLING_ARTIFACTORY_REQUESTS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_polling_artifactory_requests_total",
+        "Total number of JFrog Artifactory API requests made to detect registries and list tags"
+    ).unwrap();
+
     pub static ref POLLING_RESOURCES_FILTERED: IntCounter = IntCounter::new(
         "headwind_polling_resources_filtered_total",
         "Total number of resources filtered out from polling due to event-source annotation"
@@ -161,9 +370,12 @@ lazy_static! {
     ).unwrap();
 
     // Rollback metrics
-    pub static ref ROLLBACKS_TOTAL: IntCounter = IntCounter::new(
-        "headwind_rollbacks_total",
-        "Total number of rollback operations performed"
+    pub static ref ROLLBACKS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "headwind_rollbacks_total",
+            "Total number of rollback operations performed, by reason"
+        ),
+        &["reason"]
     ).unwrap();
 
     pub static ref ROLLBACKS_MANUAL: IntCounter = IntCounter::new(
@@ -212,16 +424,120 @@ lazy_static! {
         "Total number of notifications sent to Microsoft Teams"
     ).unwrap();
 
+    pub static ref NOTIFICATIONS_TELEGRAM_SENT: IntCounter = IntCounter::new(
+        "headwind_notifications_telegram_sent_total",
+        "Total number of notifications sent to Telegram"
+    ).unwrap();
+
+    pub static ref NOTIFICATIONS_VICTOROPS_SENT: IntCounter = IntCounter::new(
+        "headwind_notifications_victorops_sent_total",
+        "Total number of notifications sent to VictorOps"
+    ).unwrap();
+
     pub static ref NOTIFICATIONS_WEBHOOK_SENT: IntCounter = IntCounter::new(
         "headwind_notifications_webhook_sent_total",
         "Total number of notifications sent via generic webhook"
     ).unwrap();
 
+    pub static ref NOTIFICATIONS_OBSERVER_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "headwind_notifications_observer_total",
+            "Total number of notifications sent to observers from headwind.sh/notify-observers, by observer type"
+        ),
+        &["observer_type"]
+    ).unwrap();
+
     // Update interval metrics
     pub static ref UPDATES_SKIPPED_INTERVAL: IntCounter = IntCounter::new(
         "headwind_updates_skipped_interval_total",
         "Total number of updates skipped due to minimum interval not elapsed"
     ).unwrap();
+
+    pub static ref COMMENTS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_comments_total",
+        "Total number of comments added to UpdateRequest discussion threads"
+    ).unwrap();
+
+    pub static ref DIGEST_PIN_CORRECTIONS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_digest_pin_corrections_total",
+        "Total number of containers restored to the digest pinned by headwind.sh/pin-digest"
+    ).unwrap();
+
+    pub static ref UPDATE_REQUEST_RATE_LIMITED_TOTAL: IntCounter = IntCounter::new(
+        "headwind_update_request_rate_limited_total",
+        "Total number of UpdateRequests not created because the resource exceeded its rate limit"
+    ).unwrap();
+
+    pub static ref CLEANUP_DELETED_TOTAL: IntCounter = IntCounter::new(
+        "headwind_cleanup_deleted_total",
+        "Total number of finished UpdateRequests deleted after the retention period"
+    ).unwrap();
+
+    pub static ref ESCALATION_EVENTS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_escalation_events_total",
+        "Total number of priority escalations of UpdateRequests pending approval"
+    ).unwrap();
+
+    pub static ref SLA_BREACHES_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "headwind_sla_breaches_total",
+            "Total number of UpdateRequests still pending after their SLA deadline"
+        ),
+        &["namespace", "policy"]
+    ).unwrap();
+
+    pub static ref EPHEMERAL_CONTAINER_REPORTS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_ephemeral_container_reports_total",
+        "Total number of ephemeral containers found running an image tracked by Headwind"
+    ).unwrap();
+
+    pub static ref TTL_EXTENSIONS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_ttl_extensions_total",
+        "Total number of UpdateRequest expiry extensions, through the API or headwind.sh/auto-extend-ttl"
+    ).unwrap();
+
+    pub static ref EXPIRED_UPDATE_REQUESTS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_expired_update_requests_total",
+        "Total number of pending UpdateRequests marked Expired after their expiry"
+    ).unwrap();
+
+    pub static ref CLEANUP_ARCHIVED_TOTAL: IntCounter = IntCounter::new(
+        "headwind_cleanup_archived_total",
+        "Total number of UpdateRequests archived to headwind-archive ConfigMaps before deletion"
+    ).unwrap();
+
+    pub static ref ARCHIVE_OBJECTS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_archive_objects_total",
+        "Total number of UpdateRequests uploaded to the S3 archive"
+    ).unwrap();
+
+    pub static ref ARCHIVE_BYTES_TOTAL: IntCounter = IntCounter::new(
+        "headwind_archive_bytes_total",
+        "Total number of gzipped bytes uploaded to the S3 archive"
+    ).unwrap();
+
+    pub static ref STATUS_WEBHOOK_CALLS_TOTAL: IntCounter = IntCounter::new(
+        "headwind_status_webhook_calls_total",
+        "Total number of UpdateRequest phase changes sent to headwind.sh/status-webhook-url"
+    ).unwrap();
+
+    pub static ref STATUS_WEBHOOK_FAILURES_TOTAL: IntCounter = IntCounter::new(
+        "headwind_status_webhook_failures_total",
+        "Total number of UpdateRequest phase changes not delivered to headwind.sh/status-webhook-url after all retries"
+    ).unwrap();
+
+    pub static ref SLSA_VERIFICATION_FAILURES_TOTAL: IntCounter = IntCounter::new(
+        "headwind_slsa_verification_failures_total",
+        "Total number of updates skipped because the new image's SLSA provenance could not be verified"
+    ).unwrap();
+
+    pub static ref OPA_DECISIONS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "headwind_opa_decisions_total",
+            "Total number of update authorization decisions made by OPA, by decision"
+        ),
+        &["decision"]
+    ).unwrap();
 }
 
 pub fn register_metrics() {
@@ -231,13 +547,47 @@ pub fn register_metrics() {
     REGISTRY
         .register(Box::new(WEBHOOK_EVENTS_PROCESSED.clone()))
         .ok();
+    REGISTRY
+        .register(Box::new(WEBHOOK_QUEUE_DEPTH.clone()))
+        .ok();
     REGISTRY.register(Box::new(UPDATES_PENDING.clone())).ok();
     REGISTRY.register(Box::new(UPDATES_APPROVED.clone())).ok();
+    REGISTRY
+        .register(Box::new(APPROVALS_THROTTLED_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(APPROVAL_QUEUE_DEPTH.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(ADMISSION_DENIALS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(SIGNATURE_ADMISSION_DENIALS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(SIGNATURE_VIOLATIONS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(PATCH_PREVIEWS_TOTAL.clone()))
+        .ok();
     REGISTRY.register(Box::new(UPDATES_REJECTED.clone())).ok();
     REGISTRY.register(Box::new(UPDATES_APPLIED.clone())).ok();
     REGISTRY.register(Box::new(UPDATES_FAILED.clone())).ok();
     REGISTRY.register(Box::new(RECONCILE_DURATION.clone())).ok();
     REGISTRY.register(Box::new(RECONCILE_ERRORS.clone())).ok();
+    REGISTRY.register(Box::new(STORE_SIZE.clone())).ok();
+    REGISTRY
+        .register(Box::new(RECONCILE_CONCURRENT_IN_FLIGHT.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CONTROLLER_QUEUE_DEPTH.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CONTROLLER_QUEUE_LATENCY_SECONDS.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CONTROLLER_WORK_DURATION_SECONDS.clone()))
+        .ok();
     REGISTRY
         .register(Box::new(DEPLOYMENTS_WATCHED.clone()))
         .ok();
@@ -248,9 +598,42 @@ pub fn register_metrics() {
         .register(Box::new(STATEFULSETS_WATCHED.clone()))
         .ok();
     REGISTRY.register(Box::new(DAEMONSETS_WATCHED.clone())).ok();
+    REGISTRY
+        .register(Box::new(REPLICASETS_WATCHED.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(KNATIVE_SERVICES_WATCHED.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CROSSPLANE_COMPOSITIONS_WATCHED.clone()))
+        .ok();
     REGISTRY
         .register(Box::new(POLLING_CYCLES_TOTAL.clone()))
         .ok();
+    REGISTRY
+        .register(Box::new(POLLING_CYCLE_DURATION_SECONDS.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(REGISTRY_CLIENTS_ACTIVE.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(MANIFEST_FETCHES_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(TAG_CACHE_HITS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(TAG_CACHE_MISSES_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CACHE_SHARD_CONTENTION_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CREDENTIAL_REFRESH_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CREDENTIAL_REFRESH_FAILURES_TOTAL.clone()))
+        .ok();
     REGISTRY
         .register(Box::new(POLLING_ERRORS_TOTAL.clone()))
         .ok();
@@ -266,6 +649,15 @@ pub fn register_metrics() {
     REGISTRY
         .register(Box::new(POLLING_HELM_NEW_VERSIONS_FOUND.clone()))
         .ok();
+    REGISTRY
+        .register(Box::new(POLLING_QUAY_REQUESTS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(POLLING_ARTIFACTORY_REQUESTS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(WEBHOOK_TLS_HANDSHAKE_FAILURES_TOTAL.clone()))
+        .ok();
     REGISTRY
         .register(Box::new(POLLING_RESOURCES_FILTERED.clone()))
         .ok();
@@ -315,12 +707,83 @@ pub fn register_metrics() {
     REGISTRY
         .register(Box::new(NOTIFICATIONS_TEAMS_SENT.clone()))
         .ok();
+    REGISTRY
+        .register(Box::new(NOTIFICATIONS_TELEGRAM_SENT.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(NOTIFICATIONS_VICTOROPS_SENT.clone()))
+        .ok();
     REGISTRY
         .register(Box::new(NOTIFICATIONS_WEBHOOK_SENT.clone()))
         .ok();
+    REGISTRY
+        .register(Box::new(NOTIFICATIONS_OBSERVER_TOTAL.clone()))
+        .ok();
     REGISTRY
         .register(Box::new(UPDATES_SKIPPED_INTERVAL.clone()))
         .ok();
+    REGISTRY
+        .register(Box::new(SLSA_VERIFICATION_FAILURES_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(OPA_DECISIONS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(UPDATE_REQUEST_RATE_LIMITED_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CLEANUP_DELETED_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(ESCALATION_EVENTS_TOTAL.clone()))
+        .ok();
+    REGISTRY.register(Box::new(SLA_BREACHES_TOTAL.clone())).ok();
+    REGISTRY
+        .register(Box::new(EPHEMERAL_CONTAINER_REPORTS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(TTL_EXTENSIONS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(EXPIRED_UPDATE_REQUESTS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(DIGEST_PIN_CORRECTIONS_TOTAL.clone()))
+        .ok();
+    REGISTRY.register(Box::new(COMMENTS_TOTAL.clone())).ok();
+    REGISTRY
+        .register(Box::new(STATUS_WEBHOOK_CALLS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(STATUS_WEBHOOK_FAILURES_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(CLEANUP_ARCHIVED_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(ARCHIVE_OBJECTS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(ARCHIVE_BYTES_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(PENDING_UPDATE_AGE_SECONDS.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(APPROVAL_LATENCY_SECONDS.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(STEP_DURATION_SECONDS.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(APPROVAL_RBAC_DENIALS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(LOCK_ACQUISITIONS_TOTAL.clone()))
+        .ok();
+    REGISTRY
+        .register(Box::new(LOCK_CONTENTIONS_TOTAL.clone()))
+        .ok();
 
     info!("Metrics registered");
 }
@@ -378,16 +841,15 @@ async fn health_check() -> impl IntoResponse {
 
 /// Update resource gauge metrics by querying Kubernetes
 pub async fn update_resource_gauges(client: kube::Client) -> Result<()> {
-    use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+    use crate::cache::list_all;
+    use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
     use kube::api::{Api, ListParams};
 
     use crate::models::policy::annotations;
 
     // Count Deployments with Headwind annotations
-    let deployments: Api<Deployment> = Api::all(client.clone());
-    let deploy_list = deployments.list(&ListParams::default()).await?;
+    let deploy_list = list_all::<Deployment>(&client).await?;
     let deploy_count = deploy_list
-        .items
         .iter()
         .filter(|d| {
             d.metadata
@@ -400,10 +862,8 @@ pub async fn update_resource_gauges(client: kube::Client) -> Result<()> {
     DEPLOYMENTS_WATCHED.set(deploy_count as i64);
 
     // Count StatefulSets with Headwind annotations
-    let statefulsets: Api<StatefulSet> = Api::all(client.clone());
-    let sts_list = statefulsets.list(&ListParams::default()).await?;
+    let sts_list = list_all::<StatefulSet>(&client).await?;
     let sts_count = sts_list
-        .items
         .iter()
         .filter(|s| {
             s.metadata
@@ -416,10 +876,8 @@ pub async fn update_resource_gauges(client: kube::Client) -> Result<()> {
     STATEFULSETS_WATCHED.set(sts_count as i64);
 
     // Count DaemonSets with Headwind annotations
-    let daemonsets: Api<DaemonSet> = Api::all(client.clone());
-    let ds_list = daemonsets.list(&ListParams::default()).await?;
+    let ds_list = list_all::<DaemonSet>(&client).await?;
     let ds_count = ds_list
-        .items
         .iter()
         .filter(|d| {
             d.metadata
@@ -431,9 +889,24 @@ pub async fn update_resource_gauges(client: kube::Client) -> Result<()> {
         .count();
     DAEMONSETS_WATCHED.set(ds_count as i64);
 
+    // Count standalone ReplicaSets with Headwind annotations
+    let rs_list = list_all::<ReplicaSet>(&client).await?;
+    let rs_count = rs_list
+        .iter()
+        .filter(|r| {
+            crate::controller::is_standalone_replicaset(r)
+                && r.metadata
+                    .annotations
+                    .as_ref()
+                    .and_then(|a| a.get(annotations::POLICY))
+                    .is_some()
+        })
+        .count();
+    REPLICASETS_WATCHED.set(rs_count as i64);
+
     // Count HelmReleases with Headwind annotations
     use crate::models::HelmRelease;
-    let helm_releases: Api<HelmRelease> = Api::all(client);
+    let helm_releases: Api<HelmRelease> = Api::all(client.clone());
     let hr_list = helm_releases.list(&ListParams::default()).await?;
     let hr_count = hr_list
         .items
@@ -448,6 +921,44 @@ pub async fn update_resource_gauges(client: kube::Client) -> Result<()> {
         .count();
     HELM_RELEASES_WATCHED.set(hr_count as i64);
 
+    // Count Knative Services with Headwind annotations
+    if crate::controller::knative_enabled() {
+        use crate::models::KnativeService;
+        let services: Api<KnativeService> = crate::controller::watched_api(client.clone());
+        let service_list = services.list(&ListParams::default()).await?;
+        let service_count = service_list
+            .items
+            .iter()
+            .filter(|s| {
+                s.metadata
+                    .annotations
+                    .as_ref()
+                    .and_then(|a| a.get(annotations::POLICY))
+                    .is_some()
+            })
+            .count();
+        KNATIVE_SERVICES_WATCHED.set(service_count as i64);
+    }
+
+    // Count Crossplane Compositions with Headwind annotations
+    if crate::controller::crossplane_enabled() {
+        use crate::models::Composition;
+        let compositions: Api<Composition> = Api::all(client.clone());
+        let composition_list = compositions.list(&ListParams::default()).await?;
+        let composition_count = composition_list
+            .items
+            .iter()
+            .filter(|c| {
+                c.metadata
+                    .annotations
+                    .as_ref()
+                    .and_then(|a| a.get(annotations::POLICY))
+                    .is_some()
+            })
+            .count();
+        CROSSPLANE_COMPOSITIONS_WATCHED.set(composition_count as i64);
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,197 @@
+//! How long UpdateRequests wait for approval.
+//!
+//! A background task samples the age of every Pending UpdateRequest into
+//! `headwind_pending_update_age_seconds` once a minute, and approvals record
+//! the time since creation in `headwind_approval_latency_seconds`.
+
+use super::{APPROVAL_LATENCY_SECONDS, PENDING_UPDATE_AGE_SECONDS};
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use chrono::{DateTime, Utc};
+use kube::api::{Api, ListParams};
+use kube::{Client, ResourceExt};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// 1h, 6h, 12h, 24h, 48h, 72h and 1w
+pub const AGE_BUCKETS: [f64; 7] = [
+    3_600.0, 21_600.0, 43_200.0, 86_400.0, 172_800.0, 259_200.0, 604_800.0,
+];
+
+/// How often Pending UpdateRequests are sampled
+pub const SAMPLE_INTERVAL_SECS: u64 = 60;
+
+/// `namespace` and `policy` label values of an UpdateRequest
+pub fn labels(update_request: &UpdateRequest) -> [String; 2] {
+    let policy = serde_json::to_value(&update_request.spec.policy)
+        .ok()
+        .and_then(|p| p.as_str().map(str::to_string))
+        .unwrap_or_default();
+    [update_request.namespace().unwrap_or_default(), policy]
+}
+
+/// Seconds from creation until `at`, never negative. None without a creation timestamp.
+pub fn seconds_since_creation(update_request: &UpdateRequest, at: DateTime<Utc>) -> Option<f64> {
+    let created = update_request.metadata.creation_timestamp.as_ref()?.0;
+    Some((at - created).num_milliseconds().max(0) as f64 / 1000.0)
+}
+
+/// Age at `now` of an UpdateRequest still waiting for approval, None for any other phase
+pub fn pending_age_seconds(update_request: &UpdateRequest, now: DateTime<Utc>) -> Option<f64> {
+    let pending = update_request
+        .status
+        .as_ref()
+        .is_none_or(|s| s.phase == UpdatePhase::Pending);
+    if !pending {
+        return None;
+    }
+    seconds_since_creation(update_request, now)
+}
+
+/// Observe the age of every Pending UpdateRequest and return how many were observed
+pub fn record_pending_ages(update_requests: &[UpdateRequest], now: DateTime<Utc>) -> usize {
+    let mut observed = 0;
+    for update_request in update_requests {
+        if let Some(age) = pending_age_seconds(update_request, now) {
+            let [namespace, policy] = labels(update_request);
+            PENDING_UPDATE_AGE_SECONDS
+                .with_label_values(&[namespace.as_str(), policy.as_str()])
+                .observe(age);
+            observed += 1;
+        }
+    }
+    observed
+}
+
+/// Record the time from creation to approval of an UpdateRequest approved at `approved_at`
+pub fn record_approval(update_request: &UpdateRequest, approved_at: DateTime<Utc>) {
+    if let Some(latency) = seconds_since_creation(update_request, approved_at) {
+        let [namespace, policy] = labels(update_request);
+        APPROVAL_LATENCY_SECONDS
+            .with_label_values(&[namespace.as_str(), policy.as_str()])
+            .observe(latency);
+    }
+}
+
+/// Start a background task sampling Pending UpdateRequest ages every minute
+pub fn start_queue_age_recorder(client: Client) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let api: Api<UpdateRequest> = Api::all(client);
+        let mut interval =
+            tokio::time::interval(tokio::time::Duration::from_secs(SAMPLE_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            match api.list(&ListParams::default()).await {
+                Ok(list) => {
+                    record_pending_ages(&list.items, Utc::now());
+                },
+                Err(e) => warn!("Failed to list UpdateRequests for queue metrics: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing;
+    use chrono::Duration;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+    fn update_request(
+        namespace: &str,
+        created: Option<DateTime<Utc>>,
+        phase: Option<UpdatePhase>,
+    ) -> UpdateRequest {
+        let mut update_request = match phase {
+            Some(phase) => testing::update_request_in_phase(namespace, "web-1-26-0", phase),
+            None => testing::update_request(namespace, "web-1-26-0"),
+        };
+        update_request.metadata.creation_timestamp = created.map(Time);
+        update_request
+    }
+
+    #[test]
+    fn test_pending_age_seconds() {
+        let now = Utc::now();
+        let created = now - Duration::hours(6);
+
+        // No status yet counts as Pending
+        assert_eq!(
+            pending_age_seconds(&update_request("default", Some(created), None), now),
+            Some(21_600.0)
+        );
+        assert_eq!(
+            pending_age_seconds(
+                &update_request("default", Some(created), Some(UpdatePhase::Pending)),
+                now
+            ),
+            Some(21_600.0)
+        );
+
+        for phase in [
+            UpdatePhase::Approved,
+            UpdatePhase::Completed,
+            UpdatePhase::Rejected,
+        ] {
+            assert_eq!(
+                pending_age_seconds(&update_request("default", Some(created), Some(phase)), now),
+                None
+            );
+        }
+        assert_eq!(
+            pending_age_seconds(&update_request("default", None, None), now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_age_is_never_negative() {
+        let now = Utc::now();
+        // Clock skew between the API server and Headwind
+        let created = now + Duration::seconds(30);
+        assert_eq!(
+            seconds_since_creation(&update_request("default", Some(created), None), now),
+            Some(0.0)
+        );
+        assert_eq!(
+            seconds_since_creation(
+                &update_request("default", Some(now - Duration::milliseconds(1500)), None),
+                now
+            ),
+            Some(1.5)
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        let update_request = update_request("production", None, None);
+        assert_eq!(
+            labels(&update_request),
+            ["production".to_string(), "minor".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_record_pending_ages_and_approval() {
+        let now = Utc::now();
+        let update_requests = [
+            update_request("queue-test", Some(now - Duration::hours(30)), None),
+            update_request(
+                "queue-test",
+                Some(now - Duration::hours(2)),
+                Some(UpdatePhase::Completed),
+            ),
+        ];
+
+        let pending = PENDING_UPDATE_AGE_SECONDS.with_label_values(&["queue-test", "minor"]);
+        let before = pending.get_sample_count();
+        assert_eq!(record_pending_ages(&update_requests, now), 1);
+        assert_eq!(pending.get_sample_count(), before + 1);
+
+        let latency = APPROVAL_LATENCY_SECONDS.with_label_values(&["queue-test", "minor"]);
+        let before_sum = latency.get_sample_sum();
+        record_approval(&update_requests[0], now);
+        assert_eq!(latency.get_sample_sum() - before_sum, 30.0 * 3_600.0);
+    }
+}
@@ -0,0 +1,220 @@
+//! Process status behind `GET /api/v1/status` and `headwindctl status`.
+//!
+//! Controllers, the registry poller and the notification manager record what
+//! they are doing here as they run. [`StatusRecorder::snapshot`] aggregates
+//! that into the [`SystemStatus`] served by the Web UI.
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+lazy_static! {
+    /// Status of this process, started when first recorded to
+    pub static ref STATUS: StatusRecorder = StatusRecorder::new(Utc::now());
+}
+
+/// State of one controller
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControllerState {
+    Running,
+    Stopped,
+}
+
+/// One registry poll cycle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PollCycle {
+    pub finished_at: DateTime<Utc>,
+    pub duration: Duration,
+}
+
+/// Status of the Headwind process as reported by the API
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SystemStatus {
+    pub version: String,
+    pub uptime_seconds: u64,
+    /// Replica answering. Replicas run every controller and take a Lease per
+    /// UpdateRequest instead of electing a leader.
+    pub leader: String,
+    pub controllers: BTreeMap<String, ControllerState>,
+    /// End of the last registry poll cycle, None until one has run
+    pub last_poll_cycle: Option<DateTime<Utc>>,
+    pub poll_cycle_duration_ms: Option<u64>,
+    /// UpdateRequests waiting for approval, None if they could not be listed
+    pub pending_updates: Option<usize>,
+    /// `ok` or `error: ...` per channel, from its last delivery
+    pub notifications: BTreeMap<String, String>,
+}
+
+/// Collects the state reported by each part of the process
+#[derive(Debug)]
+pub struct StatusRecorder {
+    started_at: DateTime<Utc>,
+    controllers: RwLock<BTreeMap<String, ControllerState>>,
+    last_poll: RwLock<Option<PollCycle>>,
+    notifications: RwLock<BTreeMap<String, String>>,
+}
+
+impl StatusRecorder {
+    pub fn new(started_at: DateTime<Utc>) -> Self {
+        Self {
+            started_at,
+            controllers: RwLock::new(BTreeMap::new()),
+            last_poll: RwLock::new(None),
+            notifications: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn set_controller(&self, name: &str, state: ControllerState) {
+        self.controllers
+            .write()
+            .unwrap()
+            .insert(name.to_string(), state);
+    }
+
+    pub fn record_poll_cycle(&self, finished_at: DateTime<Utc>, duration: Duration) {
+        *self.last_poll.write().unwrap() = Some(PollCycle {
+            finished_at,
+            duration,
+        });
+    }
+
+    /// Record the outcome of a delivery through the notifier called `notifier`
+    pub fn record_notification(&self, notifier: &str, result: Result<(), String>) {
+        let health = match result {
+            Ok(()) => "ok".to_string(),
+            Err(e) => format!("error: {}", e),
+        };
+        self.notifications
+            .write()
+            .unwrap()
+            .insert(channel_key(notifier), health);
+    }
+
+    /// Status at `now` as answered by replica `leader`
+    pub fn snapshot(
+        &self,
+        now: DateTime<Utc>,
+        leader: String,
+        pending_updates: Option<usize>,
+    ) -> SystemStatus {
+        let last_poll = *self.last_poll.read().unwrap();
+        SystemStatus {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_seconds: (now - self.started_at).num_seconds().max(0) as u64,
+            leader,
+            controllers: self.controllers.read().unwrap().clone(),
+            last_poll_cycle: last_poll.map(|p| p.finished_at),
+            poll_cycle_duration_ms: last_poll.map(|p| p.duration.as_millis() as u64),
+            pending_updates,
+            notifications: self.notifications.read().unwrap().clone(),
+        }
+    }
+}
+
+/// Key of a notifier in [`SystemStatus::notifications`], e.g. `teams` for
+/// "Microsoft Teams"
+pub fn channel_key(notifier: &str) -> String {
+    match notifier {
+        "Microsoft Teams" => "teams".to_string(),
+        other => other.to_lowercase().replace(' ', "_"),
+    }
+}
+
+/// Start the uptime clock; called once at startup
+pub fn record_start() {
+    lazy_static::initialize(&STATUS);
+}
+
+/// Run `controller` as `name`, reporting it running until it returns
+pub async fn track_controller<F>(name: &str, controller: F)
+where
+    F: std::future::Future<Output = ()>,
+{
+    STATUS.set_controller(name, ControllerState::Running);
+    controller.await;
+    STATUS.set_controller(name, ControllerState::Stopped);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_snapshot_aggregates_recorded_state() {
+        let started = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let recorder = StatusRecorder::new(started);
+
+        let empty = recorder.snapshot(started, "headwind-0".to_string(), Some(0));
+        assert_eq!(empty.uptime_seconds, 0);
+        assert!(empty.controllers.is_empty());
+        assert_eq!(empty.last_poll_cycle, None);
+        assert_eq!(empty.poll_cycle_duration_ms, None);
+
+        recorder.set_controller("deployment", ControllerState::Running);
+        recorder.set_controller("cleanup", ControllerState::Running);
+        recorder.set_controller("cleanup", ControllerState::Stopped);
+        let polled = started + chrono::Duration::seconds(90);
+        recorder.record_poll_cycle(polled, Duration::from_millis(1_250));
+        recorder.record_notification("Slack", Ok(()));
+        recorder.record_notification("Microsoft Teams", Err("HTTP 502".to_string()));
+
+        let now = started + chrono::Duration::seconds(125);
+        let status = recorder.snapshot(now, "headwind-0".to_string(), Some(3));
+        assert_eq!(status.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(status.uptime_seconds, 125);
+        assert_eq!(status.leader, "headwind-0");
+        assert_eq!(
+            status.controllers,
+            BTreeMap::from([
+                ("cleanup".to_string(), ControllerState::Stopped),
+                ("deployment".to_string(), ControllerState::Running),
+            ])
+        );
+        assert_eq!(status.last_poll_cycle, Some(polled));
+        assert_eq!(status.poll_cycle_duration_ms, Some(1_250));
+        assert_eq!(status.pending_updates, Some(3));
+        assert_eq!(status.notifications["slack"], "ok");
+        assert_eq!(status.notifications["teams"], "error: HTTP 502");
+    }
+
+    #[test]
+    fn test_last_delivery_decides_channel_health() {
+        let recorder = StatusRecorder::new(Utc::now());
+        recorder.record_notification("Webhook", Err("timed out".to_string()));
+        recorder.record_notification("Webhook", Ok(()));
+        let status = recorder.snapshot(Utc::now(), "headwind".to_string(), None);
+        assert_eq!(status.notifications["webhook"], "ok");
+    }
+
+    #[test]
+    fn test_status_json_shape() {
+        let started = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let recorder = StatusRecorder::new(started);
+        recorder.set_controller("deployment", ControllerState::Running);
+        let json = serde_json::to_value(recorder.snapshot(started, "h".to_string(), None)).unwrap();
+        assert_eq!(json["controllers"]["deployment"], "running");
+        assert!(json["last_poll_cycle"].is_null());
+        assert!(json["pending_updates"].is_null());
+        assert_eq!(json["uptime_seconds"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_track_controller_reports_stopped_after_return() {
+        track_controller("status-test", async {
+            assert_eq!(
+                STATUS.controllers.read().unwrap()["status-test"],
+                ControllerState::Running
+            );
+        })
+        .await;
+        assert_eq!(
+            STATUS.controllers.read().unwrap()["status-test"],
+            ControllerState::Stopped
+        );
+    }
+}
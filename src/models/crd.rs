@@ -35,6 +35,10 @@ pub struct UpdateRequestSpec {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub container_name: Option<String>,
 
+    /// Whether the container is a regular or an init container
+    #[serde(default)]
+    pub container_kind: ContainerKind,
+
     /// Current image or chart version
     pub current_image: String,
 
@@ -55,12 +59,204 @@ pub struct UpdateRequestSpec {
     /// Optional expiration time for this update request
     #[serde(skip_serializing_if = "Option::is_none")]
     pub expires_at: Option<DateTime<Utc>>,
+
+    /// Time at which this update request is approved automatically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_at: Option<DateTime<Utc>>,
+
+    /// Priority of the update, raised while it waits for approval
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+
+    /// Image updates of several containers, applied together in one patch.
+    /// When empty, `containerName`, `currentImage` and `newImage` describe the
+    /// only update
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub updates: Vec<ContainerUpdate>,
+
+    /// Comments left by reviewers before approving, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub discussion_thread: Vec<Comment>,
+
+    /// Time by which the update must be reviewed, from `headwind.sh/sla-hours`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sla_deadline: Option<DateTime<Utc>>,
+
+    /// Estimated blast radius of the rollout, assessed when the request was
+    /// created (Deployments only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impact: Option<UpdateImpact>,
+}
+
+/// Estimated effect of rolling out an update
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateImpact {
+    /// Replicas restarted by the rollout
+    pub replica_count: i32,
+
+    /// The most restrictive PodDisruptionBudget selecting the pods, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disruption_budget: Option<PdbSummary>,
+
+    /// Pods taken down at once: `maxUnavailable` of a rolling update, every
+    /// replica with `Recreate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_unavailable: Option<i32>,
+
+    /// Seconds the Deployment runs with fewer ready replicas than desired,
+    /// None without a readiness probe to estimate from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_downtime_seconds: Option<u64>,
+}
+
+/// A PodDisruptionBudget as it applies to a Deployment's replicas
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PdbSummary {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_available: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_unavailable: Option<String>,
+
+    /// Pods the budget lets be unavailable at once
+    pub disruptions_allowed: i32,
+}
+
+impl UpdateImpact {
+    /// Whether the rollout takes down more pods at once than the budget allows
+    pub fn violates_disruption_budget(&self) -> bool {
+        match (&self.disruption_budget, self.max_unavailable) {
+            (Some(pdb), Some(max_unavailable)) => max_unavailable > pdb.disruptions_allowed,
+            _ => false,
+        }
+    }
+
+    /// One line for notifications, e.g. `3 replicas, 1 unavailable at a
+    /// time, ~30s degraded, PDB web (1 disruption allowed)`
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!(
+            "{} replica{}",
+            self.replica_count,
+            if self.replica_count == 1 { "" } else { "s" }
+        )];
+        if let Some(max_unavailable) = self.max_unavailable {
+            parts.push(format!("{} unavailable at a time", max_unavailable));
+        }
+        if let Some(seconds) = self.estimated_downtime_seconds {
+            parts.push(format!("~{}s degraded", seconds));
+        }
+        match &self.disruption_budget {
+            Some(pdb) => parts.push(format!(
+                "PDB {} ({} disruption{} allowed)",
+                pdb.name,
+                pdb.disruptions_allowed,
+                if pdb.disruptions_allowed == 1 {
+                    ""
+                } else {
+                    "s"
+                }
+            )),
+            None => parts.push("no PDB".to_string()),
+        }
+        parts.join(", ")
+    }
+}
+
+/// A comment in the discussion thread of an UpdateRequest
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Comment {
+    /// Who wrote the comment
+    pub author: String,
+
+    /// Text of the comment
+    pub body: String,
+
+    /// When the comment was added
+    pub created_at: DateTime<Utc>,
+}
+
+impl UpdateRequestSpec {
+    /// Every container update of the request: `updates`, or the single update
+    /// of `container_name` if there are none
+    pub fn container_updates(&self) -> Vec<ContainerUpdate> {
+        if !self.updates.is_empty() {
+            return self.updates.clone();
+        }
+        self.container_name
+            .iter()
+            .map(|container_name| ContainerUpdate {
+                container_name: container_name.clone(),
+                current_image: self.current_image.clone(),
+                new_image: self.new_image.clone(),
+            })
+            .collect()
+    }
 }
 
 fn default_require_approval() -> bool {
     true
 }
 
+/// BatchUpdateRequest groups the pending image updates of every container in
+/// a workload's pod template so they are approved and applied together
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[kube(
+    group = "headwind.sh",
+    version = "v1alpha1",
+    kind = "BatchUpdateRequest",
+    plural = "batchupdaterequests",
+    shortname = "bur",
+    namespaced,
+    status = "UpdateRequestStatus",
+    printcolumn = r#"{"name":"Kind", "type":"string", "jsonPath":".spec.targetRef.kind"}"#,
+    printcolumn = r#"{"name":"Target", "type":"string", "jsonPath":".spec.targetRef.name"}"#,
+    printcolumn = r#"{"name":"Policy", "type":"string", "jsonPath":".spec.policy"}"#,
+    printcolumn = r#"{"name":"Phase", "type":"string", "jsonPath":".status.phase"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchUpdateRequestSpec {
+    /// Reference to the workload to update
+    pub target_ref: TargetRef,
+
+    /// One image update per container
+    pub updates: Vec<SingleUpdate>,
+
+    /// Policy that triggered the updates
+    pub policy: UpdatePolicyType,
+
+    /// Human-readable reason for the batch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Optional expiration time for this batch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A single container image update within a BatchUpdateRequest or a
+/// multi-container UpdateRequest
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SingleUpdate {
+    /// Name of the container to update
+    pub container_name: String,
+
+    /// Current image of the container
+    pub current_image: String,
+
+    /// Image to update the container to
+    pub new_image: String,
+}
+
+/// A container image update within a multi-container UpdateRequest
+pub type ContainerUpdate = SingleUpdate;
+
 /// Reference to the target Kubernetes resource
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -88,6 +284,15 @@ pub enum UpdateType {
     HelmChart,
 }
 
+/// Pod template list a container belongs to
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ContainerKind {
+    #[default]
+    Container,
+    InitContainer,
+}
+
 /// Policy type for the update
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
@@ -132,6 +337,123 @@ pub struct UpdateRequestStatus {
     /// Last time this status was updated
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_updated: Option<DateTime<Utc>>,
+
+    /// Git commit SHA when the update was applied via git push mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_sha: Option<String>,
+
+    /// Argo CD sync operation when the update was applied through an Argo CD Application
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub argocd_sync_operation: Option<String>,
+
+    /// Rollbacks of the applied update, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rollback_history: Vec<RollbackRecord>,
+
+    /// Stages the update went through, in the order they started
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub progress_steps: Vec<ProgressStep>,
+
+    /// Observations about the request, e.g. `SlaBreached`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conditions: Vec<UpdateRequestCondition>,
+
+    /// Extensions of `spec.expiresAt`, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ttl_extensions: Vec<TtlExtension>,
+}
+
+/// An extension of the expiry of an UpdateRequest
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TtlExtension {
+    /// When the expiry was extended
+    pub extended_at: DateTime<Utc>,
+
+    /// Hours after `extendedAt` the request now expires
+    pub extend_by_hours: i64,
+
+    /// Expiry before the extension
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_expires_at: Option<DateTime<Utc>>,
+
+    /// Expiry set by the extension
+    pub expires_at: DateTime<Utc>,
+
+    /// Whether `headwind.sh/auto-extend-ttl` extended the request rather than
+    /// the API
+    #[serde(default)]
+    pub automatic: bool,
+}
+
+/// A condition of an UpdateRequest, like the conditions of built-in resources
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateRequestCondition {
+    /// Kind of condition, e.g. `SlaBreached`
+    #[serde(rename = "type")]
+    pub type_: String,
+
+    /// `True`, `False` or `Unknown`
+    pub status: String,
+
+    /// Machine-readable reason for the last transition
+    pub reason: String,
+
+    /// Human-readable details
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// When the condition last changed status
+    pub last_transition_time: DateTime<Utc>,
+}
+
+/// A stage of detecting and applying an update, e.g. `KubernetesPatch`
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressStep {
+    /// Name of the stage
+    pub step: String,
+
+    /// Outcome of the stage so far
+    pub status: StepStatus,
+
+    /// When the stage started
+    pub started_at: DateTime<Utc>,
+
+    /// When the stage finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+
+    /// Details, e.g. why the stage failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Outcome of a [`ProgressStep`]
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, JsonSchema, PartialEq, Eq)]
+pub enum StepStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+/// A rollback of the update applied by an UpdateRequest
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackRecord {
+    /// When the rollback was triggered
+    pub triggered_at: DateTime<Utc>,
+
+    /// Why the rollback was triggered
+    pub reason: String,
+
+    /// Image the workload was rolled back to
+    pub rolled_back_to: String,
+
+    /// User or system that triggered the rollback
+    pub triggered_by: String,
 }
 
 /// Phase of the UpdateRequest lifecycle
@@ -145,6 +467,8 @@ pub enum UpdatePhase {
     Completed,
     Failed,
     Expired,
+    /// Records a manual rollback; never applied
+    RolledBack,
 }
 
 #[cfg(test)]
@@ -162,12 +486,19 @@ mod tests {
             },
             update_type: UpdateType::Image,
             container_name: Some("nginx".to_string()),
+            container_kind: Default::default(),
             current_image: "nginx:1.25.0".to_string(),
             new_image: "nginx:1.26.0".to_string(),
             policy: UpdatePolicyType::Minor,
             reason: Some("New minor version available".to_string()),
             require_approval: true,
             expires_at: None,
+            scheduled_at: None,
+            priority: None,
+            updates: Vec::new(),
+            discussion_thread: Vec::new(),
+            sla_deadline: None,
+            impact: None,
         };
 
         assert_eq!(spec.target_ref.name, "nginx");
@@ -175,6 +506,52 @@ mod tests {
         assert_eq!(spec.policy, UpdatePolicyType::Minor);
     }
 
+    #[test]
+    fn test_container_updates() {
+        let mut spec: UpdateRequestSpec = serde_json::from_value(serde_json::json!({
+            "targetRef": {
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "name": "web",
+                "namespace": "default"
+            },
+            "updateType": "image",
+            "containerName": "app",
+            "currentImage": "example/app:1.2.3",
+            "newImage": "example/app:1.3.0",
+            "policy": "minor"
+        }))
+        .unwrap();
+
+        // Without `updates` the single-container fields are the only update
+        assert_eq!(
+            spec.container_updates(),
+            vec![ContainerUpdate {
+                container_name: "app".to_string(),
+                current_image: "example/app:1.2.3".to_string(),
+                new_image: "example/app:1.3.0".to_string(),
+            }]
+        );
+
+        spec.updates = vec![
+            ContainerUpdate {
+                container_name: "app".to_string(),
+                current_image: "example/app:1.2.3".to_string(),
+                new_image: "example/app:1.3.0".to_string(),
+            },
+            ContainerUpdate {
+                container_name: "sidecar".to_string(),
+                current_image: "example/sidecar:1.2.3".to_string(),
+                new_image: "example/sidecar:1.3.0".to_string(),
+            },
+        ];
+        assert_eq!(spec.container_updates(), spec.updates);
+
+        spec.updates.clear();
+        spec.container_name = None;
+        assert!(spec.container_updates().is_empty());
+    }
+
     #[test]
     fn test_update_phase_default() {
         let status = UpdateRequestStatus::default();
@@ -0,0 +1,135 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Crossplane Composition (minimal subset of fields used by Headwind).
+/// Only Compositions using `spec.resources` are supported; pipeline mode
+/// Compositions keep their resources inside function inputs.
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[kube(
+    group = "apiextensions.crossplane.io",
+    version = "v1",
+    kind = "Composition"
+)]
+#[serde(rename_all = "camelCase")]
+pub struct CompositionSpec {
+    /// Composed resource templates
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resources: Vec<ComposedTemplate>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComposedTemplate {
+    /// Template name (optional in Crossplane v1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Base manifest of the composed resource
+    #[serde(default)]
+    pub base: Value,
+}
+
+/// An image referenced at `spec.resources[i].base.spec.containers[j].image`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompositionImageRef {
+    pub resource_index: usize,
+    pub resource_name: Option<String>,
+    pub container_index: usize,
+    pub container_name: Option<String>,
+    pub image: String,
+}
+
+impl CompositionImageRef {
+    /// JSON Pointer to the image field, for JSON Patch operations
+    pub fn json_pointer(&self) -> String {
+        format!(
+            "/spec/resources/{}/base/spec/containers/{}/image",
+            self.resource_index, self.container_index
+        )
+    }
+}
+
+impl Composition {
+    /// All container images referenced by the composed resource bases
+    pub fn image_refs(&self) -> Vec<CompositionImageRef> {
+        let mut refs = Vec::new();
+
+        for (resource_index, resource) in self.spec.resources.iter().enumerate() {
+            let Some(containers) = resource
+                .base
+                .pointer("/spec/containers")
+                .and_then(Value::as_array)
+            else {
+                continue;
+            };
+
+            for (container_index, container) in containers.iter().enumerate() {
+                if let Some(image) = container.get("image").and_then(Value::as_str) {
+                    refs.push(CompositionImageRef {
+                        resource_index,
+                        resource_name: resource.name.clone(),
+                        container_index,
+                        container_name: container
+                            .get("name")
+                            .and_then(Value::as_str)
+                            .map(String::from),
+                        image: image.to_string(),
+                    });
+                }
+            }
+        }
+
+        refs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_image_refs() {
+        let composition: Composition = serde_json::from_value(json!({
+            "apiVersion": "apiextensions.crossplane.io/v1",
+            "kind": "Composition",
+            "metadata": { "name": "platform-app" },
+            "spec": {
+                "compositeTypeRef": {
+                    "apiVersion": "platform.example.org/v1alpha1",
+                    "kind": "XApp"
+                },
+                "resources": [
+                    {
+                        "name": "bucket",
+                        "base": { "apiVersion": "s3.aws.upbound.io/v1beta1", "kind": "Bucket" }
+                    },
+                    {
+                        "name": "worker",
+                        "base": {
+                            "spec": {
+                                "containers": [
+                                    { "name": "app", "image": "ghcr.io/org/worker:1.2.0" },
+                                    { "name": "sidecar", "image": "envoyproxy/envoy:v1.30.0" }
+                                ]
+                            }
+                        }
+                    }
+                ]
+            }
+        }))
+        .unwrap();
+
+        let refs = composition.image_refs();
+        assert_eq!(refs.len(), 2);
+        assert_eq!(refs[0].resource_name.as_deref(), Some("worker"));
+        assert_eq!(refs[0].container_name.as_deref(), Some("app"));
+        assert_eq!(refs[0].image, "ghcr.io/org/worker:1.2.0");
+        assert_eq!(
+            refs[1].json_pointer(),
+            "/spec/resources/1/base/spec/containers/1/image"
+        );
+    }
+}
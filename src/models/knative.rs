@@ -0,0 +1,119 @@
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Annotation Knative clients bump to force a new Revision
+pub const KNATIVE_UPDATE_TIMESTAMP_ANNOTATION: &str = "client.knative.dev/updateTimestamp";
+
+/// Knative Serving Service (minimal subset of fields used by Headwind)
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[kube(
+    group = "serving.knative.dev",
+    version = "v1",
+    kind = "Service",
+    root = "KnativeService",
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct KnativeServiceSpec {
+    /// Template for the Revisions created by this Service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<KnativeRevisionTemplate>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KnativeRevisionTemplate {
+    /// Revision metadata (annotations are used to trigger new revisions)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<KnativeTemplateMetadata>,
+
+    /// Revision spec holding the containers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spec: Option<KnativeRevisionSpec>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KnativeTemplateMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KnativeRevisionSpec {
+    #[serde(default)]
+    pub containers: Vec<KnativeContainer>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KnativeContainer {
+    /// Container name (optional in Knative when there is a single container)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Container image reference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+}
+
+impl KnativeService {
+    /// Containers declared in the revision template
+    pub fn containers(&self) -> &[KnativeContainer] {
+        self.spec
+            .template
+            .as_ref()
+            .and_then(|t| t.spec.as_ref())
+            .map(|s| s.containers.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_knative_service() {
+        let json = serde_json::json!({
+            "apiVersion": "serving.knative.dev/v1",
+            "kind": "Service",
+            "metadata": {
+                "name": "hello",
+                "namespace": "default"
+            },
+            "spec": {
+                "template": {
+                    "metadata": {
+                        "annotations": {
+                            "autoscaling.knative.dev/target": "10"
+                        }
+                    },
+                    "spec": {
+                        "containers": [{
+                            "image": "ghcr.io/knative/helloworld-go:1.0.0",
+                            "env": [{"name": "TARGET", "value": "World"}]
+                        }]
+                    }
+                }
+            }
+        });
+
+        let svc: KnativeService = serde_json::from_value(json).unwrap();
+        assert_eq!(svc.containers().len(), 1);
+        assert_eq!(
+            svc.containers()[0].image.as_deref(),
+            Some("ghcr.io/knative/helloworld-go:1.0.0")
+        );
+        assert!(svc.containers()[0].name.is_none());
+    }
+
+    #[test]
+    fn test_containers_without_template() {
+        let svc = KnativeService::new("hello", KnativeServiceSpec { template: None });
+        assert!(svc.containers().is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+use super::helmrelease::SourceReference;
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Kustomization is a Flux CD custom resource that applies a kustomize overlay
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[kube(
+    group = "kustomize.toolkit.fluxcd.io",
+    version = "v1",
+    kind = "Kustomization",
+    namespaced
+)]
+#[serde(rename_all = "camelCase")]
+pub struct KustomizationSpec {
+    /// Interval at which to reconcile the Kustomization
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+
+    /// Path to the directory containing the kustomization.yaml file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// SourceRef is the reference to the Source the manifests are read from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_ref: Option<SourceReference>,
+
+    /// PostBuild describes variable substitutions applied after kustomize build
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_build: Option<PostBuild>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PostBuild {
+    /// Substitute holds inline variables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub substitute: Option<BTreeMap<String, String>>,
+
+    /// SubstituteFrom holds references to ConfigMaps and Secrets with variables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub substitute_from: Option<Vec<SubstituteReference>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SubstituteReference {
+    /// Kind of the values referent (ConfigMap or Secret)
+    pub kind: String,
+
+    /// Name of the values referent, in the Kustomization's namespace
+    pub name: String,
+
+    /// Optional references are skipped by Flux when missing
+    #[serde(default)]
+    pub optional: bool,
+}
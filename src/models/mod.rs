@@ -1,13 +1,19 @@
 pub mod crd;
+pub mod crossplane;
 pub mod helmrelease;
 pub mod helmrepository;
+pub mod knative;
+pub mod kustomization;
 pub mod policy;
 pub mod update;
 pub mod webhook;
 
 #[allow(unused_imports)]
 pub use crd::*;
+pub use crossplane::*;
 pub use helmrelease::*;
 pub use helmrepository::*;
+pub use knative::*;
+pub use kustomization::*;
 pub use policy::*;
 pub use webhook::{ChartPushEvent, ImagePushEvent};
@@ -1,6 +1,13 @@
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use thiserror::Error;
+use tracing::warn;
+
+/// Default tag normalization rule for resources without a tag normalization
+/// annotation
+pub const TAG_NORMALIZATION_RULES_ENV: &str = "HEADWIND_TAG_NORMALIZATION_RULES";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -35,12 +42,161 @@ pub enum EventSource {
     None,
 }
 
+/// Where registry credentials for a resource's images come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialSource {
+    /// imagePullSecrets of the namespace's default service account (default)
+    #[default]
+    ImagePullSecrets,
+    /// KV v2 secret in HashiCorp Vault
+    Vault,
+}
+
+/// How tags are rewritten before the current and new versions are compared
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum TagNormalization {
+    /// Compare tags as they are (default)
+    #[default]
+    None,
+    /// Ignore a leading `v` before a digit, so `v1.2.3` equals `1.2.3`
+    StripVPrefix,
+    /// Ignore a custom leading prefix such as `release-`
+    Custom(String),
+    /// Rewrite tags with a `headwind.sh/tag-normalization` rule
+    Rule(TagNormalizationRule),
+}
+
+/// Rewriting of a tag before versions are compared, applied in field order.
+///
+/// Written as comma-separated `strip-prefix=<prefix>`, `strip-suffix=<suffix>`
+/// and `regex-replace=<pattern>=><replacement>`. `regex-replace` takes the
+/// rest of the value, commas included, so it comes last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub struct TagNormalizationRule {
+    pub prefix_to_strip: Option<String>,
+    pub suffix_to_strip: Option<String>,
+    /// Pattern and replacement, which may refer to groups as `$1`
+    pub regex_replace: Option<(String, String)>,
+}
+
+impl TagNormalizationRule {
+    /// Rule from `HEADWIND_TAG_NORMALIZATION_RULES`, ignored with a warning if invalid
+    pub fn from_env() -> Option<Self> {
+        let value = std::env::var(TAG_NORMALIZATION_RULES_ENV)
+            .ok()
+            .filter(|v| !v.trim().is_empty())?;
+        value
+            .parse()
+            .inspect_err(|e| warn!("Ignoring {}: {}", TAG_NORMALIZATION_RULES_ENV, e))
+            .ok()
+    }
+
+    /// `tag` rewritten by this rule; steps whose prefix, suffix or pattern
+    /// is absent leave the tag unchanged
+    pub fn apply(&self, tag: &str) -> String {
+        let mut tag = tag;
+        if let Some(prefix) = &self.prefix_to_strip {
+            tag = tag.strip_prefix(prefix.as_str()).unwrap_or(tag);
+        }
+        if let Some(suffix) = &self.suffix_to_strip {
+            tag = tag.strip_suffix(suffix.as_str()).unwrap_or(tag);
+        }
+        match &self.regex_replace {
+            // The pattern was validated when the rule was parsed
+            Some((pattern, replacement)) => match regex::Regex::new(pattern) {
+                Ok(re) => re.replace(tag, replacement.as_str()).into_owned(),
+                Err(_) => tag.to_string(),
+            },
+            None => tag.to_string(),
+        }
+    }
+}
+
+impl FromStr for TagNormalizationRule {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PolicyError::InvalidTagNormalization(s.to_string());
+        let (steps, regex) = match s.find("regex-replace=") {
+            Some(i) => (&s[..i], Some(&s[i + "regex-replace=".len()..])),
+            None => (s, None),
+        };
+
+        let mut rule = Self::default();
+        for step in steps.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match step.split_once('=') {
+                Some(("strip-prefix", prefix)) if !prefix.is_empty() => {
+                    rule.prefix_to_strip = Some(prefix.to_string())
+                },
+                Some(("strip-suffix", suffix)) if !suffix.is_empty() => {
+                    rule.suffix_to_strip = Some(suffix.to_string())
+                },
+                _ => return Err(invalid()),
+            }
+        }
+        if let Some(regex) = regex {
+            let (pattern, replacement) = regex.trim().split_once("=>").ok_or_else(invalid)?;
+            if pattern.is_empty() || regex::Regex::new(pattern).is_err() {
+                return Err(invalid());
+            }
+            rule.regex_replace = Some((pattern.to_string(), replacement.to_string()));
+        }
+
+        if rule == Self::default() {
+            return Err(invalid());
+        }
+        Ok(rule)
+    }
+}
+
+static GLOBAL_TAG_NORMALIZATION: Lazy<TagNormalization> = Lazy::new(|| {
+    TagNormalizationRule::from_env()
+        .map(TagNormalization::Rule)
+        .unwrap_or_default()
+});
+
+/// Tag normalization of resources without a tag normalization annotation
+pub fn global_tag_normalization() -> TagNormalization {
+    GLOBAL_TAG_NORMALIZATION.clone()
+}
+
+/// Tag normalization of a resource: `headwind.sh/tag-normalization`, then
+/// `headwind.sh/normalize-tags`, then `default`
+pub fn parse_tag_normalization(
+    annotations: &BTreeMap<String, String>,
+    default: &TagNormalization,
+) -> Result<TagNormalization, PolicyError> {
+    if let Some(rule) = annotations.get(annotations::TAG_NORMALIZATION) {
+        return Ok(TagNormalization::Rule(rule.parse()?));
+    }
+    match annotations.get(annotations::NORMALIZE_TAGS) {
+        Some(normalization) => normalization.parse(),
+        None => Ok(default.clone()),
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum PolicyError {
     #[error("Invalid policy: {0}")]
     InvalidPolicy(String),
     #[error("Invalid event source: {0}")]
     InvalidEventSource(String),
+    #[error("Invalid credential source: {0}")]
+    InvalidCredentialSource(String),
+    #[error("Invalid tag normalization: {0}")]
+    InvalidTagNormalization(String),
+    #[error("Invalid SLSA level: {0} (expected 1, 2 or 3)")]
+    InvalidSlsaLevel(String),
+}
+
+/// SLSA build level from a `headwind.sh/require-slsa-level` value
+pub fn parse_slsa_level(value: &str) -> Result<u8, PolicyError> {
+    match value.trim().parse() {
+        Ok(level @ 1..=3) => Ok(level),
+        _ => Err(PolicyError::InvalidSlsaLevel(value.to_string())),
+    }
 }
 
 impl FromStr for UpdatePolicy {
@@ -74,6 +230,36 @@ impl FromStr for EventSource {
     }
 }
 
+impl FromStr for CredentialSource {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "image-pull-secrets" | "secret" | "secrets" => Ok(CredentialSource::ImagePullSecrets),
+            "vault" => Ok(CredentialSource::Vault),
+            _ => Err(PolicyError::InvalidCredentialSource(s.to_string())),
+        }
+    }
+}
+
+impl FromStr for TagNormalization {
+    type Err = PolicyError;
+
+    /// `none`, `strip-v`, or `strip-prefix:<prefix>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(TagNormalization::None),
+            "strip-v" => Ok(TagNormalization::StripVPrefix),
+            _ => match s.strip_prefix("strip-prefix:") {
+                Some(prefix) if !prefix.is_empty() => {
+                    Ok(TagNormalization::Custom(prefix.to_string()))
+                },
+                _ => Err(PolicyError::InvalidTagNormalization(s.to_string())),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourcePolicy {
     /// Update policy to apply
@@ -91,11 +277,69 @@ pub struct ResourcePolicy {
     /// Images to track (if empty, track all)
     pub images: Vec<String>,
 
+    /// Names of the containers to update (if empty, update all)
+    pub container_names: Vec<String>,
+
     /// Event source configuration (webhook, polling, both, none)
     pub event_source: EventSource,
 
     /// Per-resource polling interval in seconds (overrides global setting)
     pub polling_interval: Option<u64>,
+
+    /// Collect updates for all containers into one BatchUpdateRequest
+    pub batch_mode: bool,
+
+    /// Rewriting applied to tags before versions are compared
+    pub tag_normalization: TagNormalization,
+
+    /// Email addresses and HTTPS webhook URLs notified about UpdateRequests
+    /// in addition to the global notification channels
+    pub observers: Vec<String>,
+
+    /// Range of chart versions a HelmRelease may move to, e.g.
+    /// `>= 2.0.0, < 3.0.0`, applied before the update policy
+    #[serde(skip)]
+    pub helm_version_constraint: Option<semver::VersionReq>,
+
+    /// Also track the images of init containers
+    pub include_init_containers: bool,
+
+    /// Minimum SLSA build level the provenance of a new image must prove
+    pub require_slsa_level: Option<u8>,
+
+    /// Builder the provenance must name, e.g. `https://github.com/actions/runner`
+    pub slsa_builder: Option<String>,
+
+    /// OPA package whose `allow` rule must allow an update before the update
+    /// policy is evaluated, e.g. `headwind/update_policy`
+    pub opa_policy: Option<String>,
+}
+
+impl ResourcePolicy {
+    /// Whether the container named `name` is updated under this policy
+    pub fn targets_container(&self, name: &str) -> bool {
+        self.container_names.is_empty() || self.container_names.iter().any(|n| n == name)
+    }
+}
+
+/// Container names from a `headwind.sh/container-names` value such as "app, sidecar"
+pub fn parse_container_names(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Observers from a `headwind.sh/notify-observers` value such as
+/// "dba-team@example.com, https://hooks.example.com/dba". Entries that are
+/// neither an email address nor an HTTPS URL are dropped.
+pub fn parse_observers(value: &str) -> Vec<String> {
+    parse_container_names(value)
+        .into_iter()
+        .filter(|observer| observer.parse::<crate::notifications::Observer>().is_ok())
+        .collect()
 }
 
 impl Default for ResourcePolicy {
@@ -106,8 +350,17 @@ impl Default for ResourcePolicy {
             require_approval: true,
             min_update_interval: Some(300), // 5 minutes
             images: Vec::new(),
+            container_names: Vec::new(),
             event_source: EventSource::default(),
             polling_interval: None,
+            batch_mode: false,
+            tag_normalization: TagNormalization::default(),
+            observers: Vec::new(),
+            helm_version_constraint: None,
+            include_init_containers: false,
+            require_slsa_level: None,
+            slsa_builder: None,
+            opa_policy: None,
         }
     }
 }
@@ -116,9 +369,20 @@ impl Default for ResourcePolicy {
 pub mod annotations {
     pub const POLICY: &str = "headwind.sh/policy";
     pub const PATTERN: &str = "headwind.sh/pattern";
+    pub const HELM_VERSION_CONSTRAINT: &str = "headwind.sh/helm-version-constraint";
+    // Chart repository URL used instead of the HelmRelease's HelmRepository
+    pub const HELM_REPO_URL: &str = "headwind.sh/helm-repo-url";
+    // Excludes the resource from all processing, whatever its other annotations
+    pub const IGNORE: &str = "headwind.sh/ignore";
     pub const REQUIRE_APPROVAL: &str = "headwind.sh/require-approval";
     pub const MIN_UPDATE_INTERVAL: &str = "headwind.sh/min-update-interval";
     pub const IMAGES: &str = "headwind.sh/images";
+    pub const CONTAINER_NAMES: &str = "headwind.sh/container-names";
+    pub const INCLUDE_INIT_CONTAINERS: &str = "headwind.sh/include-init-containers";
+    pub const REQUIRE_SLSA_LEVEL: &str = "headwind.sh/require-slsa-level";
+    pub const SLSA_BUILDER: &str = "headwind.sh/slsa-builder";
+    // OPA package deciding whether updates are allowed
+    pub const OPA_POLICY: &str = "headwind.sh/opa-policy";
     #[allow(dead_code)]
     pub const LAST_UPDATE: &str = "headwind.sh/last-update";
 
@@ -130,4 +394,121 @@ pub mod annotations {
     pub const AUTO_ROLLBACK: &str = "headwind.sh/auto-rollback";
     pub const ROLLBACK_TIMEOUT: &str = "headwind.sh/rollback-timeout";
     pub const HEALTH_CHECK_RETRIES: &str = "headwind.sh/health-check-retries";
+
+    // Link to the service's external status page, included in approval notifications
+    pub const STATUS_PAGE: &str = "headwind.sh/status-page";
+
+    // HTTPS URL receiving every phase change of the resource's UpdateRequests
+    pub const STATUS_WEBHOOK_URL: &str = "headwind.sh/status-webhook-url";
+
+    // GitOps annotations (commit image changes to git instead of patching)
+    pub const GITOPS_MODE: &str = "headwind.sh/gitops-mode";
+    pub const GIT_REPO_URL: &str = "headwind.sh/git-repo-url";
+    pub const GIT_FILE_PATH: &str = "headwind.sh/git-file-path";
+    pub const GIT_BRANCH: &str = "headwind.sh/git-branch";
+
+    // Approval via GitHub pull requests or GitLab merge requests instead of UpdateRequest CRDs
+    pub const APPROVAL_MODE: &str = "headwind.sh/approval-mode";
+    pub const GITHUB_REPO: &str = "headwind.sh/github-repo";
+    pub const GITHUB_TOKEN_SECRET: &str = "headwind.sh/github-token-secret";
+    pub const GITLAB_PROJECT_ID: &str = "headwind.sh/gitlab-project-id";
+    pub const GITLAB_TOKEN_SECRET: &str = "headwind.sh/gitlab-token-secret";
+    // Images of the workload's open PRs or MRs as a JSON object by container; a
+    // merge only applies the image headwind proposed
+    pub const PROPOSED_IMAGES: &str = "headwind.sh/proposed-images";
+
+    // Seconds to wait between nodes when rolling out a DaemonSet update
+    pub const ROLLOUT_PAUSE_BETWEEN_STEPS: &str = "headwind.sh/rollout-pause-between-steps";
+    // Update strategy a paused DaemonSet rollout replaced with OnDelete, as JSON,
+    // restored once the rollout is over
+    pub const PAUSED_ROLLOUT_STRATEGY: &str = "headwind.sh/paused-rollout-strategy";
+    // Why a paused DaemonSet rollout failed; the saved strategy is not restored
+    // while it is set
+    pub const PAUSED_ROLLOUT_FAILED: &str = "headwind.sh/paused-rollout-failed";
+
+    // Cron expression restricting when updates are applied (e.g. "0 2 * * Mon-Fri")
+    pub const UPDATE_SCHEDULE: &str = "headwind.sh/update-schedule";
+    // Cron windows with an optional time zone, e.g. "0 2 * * 1-5[America/New_York]";
+    // takes precedence over UPDATE_SCHEDULE
+    pub const UPDATE_WINDOW: &str = "headwind.sh/update-window";
+
+    // Group updates for all containers into a single BatchUpdateRequest
+    pub const BATCH_UPDATES: &str = "headwind.sh/batch-updates";
+
+    // Registry credential source used when polling ("vault" or "image-pull-secrets")
+    pub const CREDENTIAL_SOURCE: &str = "headwind.sh/credential-source";
+
+    // Deploy new images as name:tag@sha256:digest instead of name:tag
+    pub const DIGEST_PINNING: &str = "headwind.sh/digest-pinning";
+
+    // Freeze containers at this digest, restoring it if they drift
+    pub const PIN_DIGEST: &str = "headwind.sh/pin-digest";
+
+    // Namespace annotation: only admit pods whose images have a valid cosign signature
+    pub const REQUIRE_SIGNATURE: &str = "headwind.sh/require-signature";
+
+    // JIRA project to open an issue in for each UpdateRequest, and the key of that issue
+    pub const JIRA_PROJECT: &str = "headwind.sh/jira-project";
+    pub const JIRA_ISSUE: &str = "headwind.sh/jira-issue";
+
+    // Update the tag through a Flux Kustomization's postBuild substitution ConfigMap
+    pub const FLUX_KUSTOMIZATION: &str = "headwind.sh/flux-kustomization";
+    pub const FLUX_SUBSTITUTE_VAR: &str = "headwind.sh/flux-substitute-var";
+    // On a Kustomization: comma-separated namespaces (or "*") whose workloads may
+    // update it, besides the Kustomization's own namespace
+    pub const FLUX_ALLOWED_NAMESPACES: &str = "headwind.sh/flux-allowed-namespaces";
+
+    // Update the image override of an Argo CD Application and sync it
+    pub const ARGOCD_APP: &str = "headwind.sh/argocd-app";
+    // On an Application: comma-separated namespaces (or "*") whose workloads may
+    // update it, besides its own and its destination namespace
+    pub const ARGOCD_ALLOWED_NAMESPACES: &str = "headwind.sh/argocd-allowed-namespaces";
+
+    // Tag normalization before comparing versions ("none", "strip-v" or "strip-prefix:<prefix>")
+    pub const NORMALIZE_TAGS: &str = "headwind.sh/normalize-tags";
+
+    // Tag rewriting rule ("strip-prefix=v,strip-suffix=-alpine"), overrides NORMALIZE_TAGS
+    pub const TAG_NORMALIZATION: &str = "headwind.sh/tag-normalization";
+
+    // Wait for ResourceQuota room for the rolling update's surge pods
+    pub const CHECK_QUOTA: &str = "headwind.sh/check-quota";
+
+    // Most UpdateRequests the resource may create per period ("count/seconds")
+    pub const UPDATE_RATE_LIMIT: &str = "headwind.sh/update-rate-limit";
+
+    // Comma-separated Deployments of the namespace to update before this one
+    pub const UPDATE_AFTER: &str = "headwind.sh/update-after";
+
+    // Read the new image's OCI labels to link its changelog in notifications
+    pub const FETCH_LABELS: &str = "headwind.sh/fetch-labels";
+
+    // Email addresses and HTTPS webhook URLs notified about UpdateRequests
+    pub const NOTIFY_OBSERVERS: &str = "headwind.sh/notify-observers";
+
+    // Image Headwind's own Deployment ran before its last self-update
+    pub const PREVIOUS_IMAGE: &str = "headwind.sh/previous-image";
+
+    // Only holders of this Role may approve the resource's UpdateRequests
+    pub const APPROVED_BY_ROLE: &str = "headwind.sh/approved-by-role";
+
+    // Keep a finished UpdateRequest when old UpdateRequests are cleaned up
+    pub const PRESERVE: &str = "headwind.sh/preserve";
+
+    // Set on UpdateRequests recreated from an export
+    pub const IMPORTED_AT: &str = "headwind.sh/imported-at";
+    pub const ORIGINAL_UID: &str = "headwind.sh/original-uid";
+
+    // Hours an UpdateRequest waits for approval before each priority escalation
+    pub const ESCALATION_THRESHOLD_HOURS: &str = "headwind.sh/escalation-threshold-hours";
+
+    // Hours an UpdateRequest may wait for review before its SLA is breached
+    pub const SLA_HOURS: &str = "headwind.sh/sla-hours";
+    // Set on UpdateRequests still pending after their SLA deadline
+    pub const SLA_BREACHED: &str = "headwind.sh/sla-breached";
+
+    // Label set on UpdateRequests whose rollout would exceed a PodDisruptionBudget
+    pub const PDB_WARNING: &str = "headwind.sh/pdb-warning";
+
+    // Extend an expired UpdateRequest by 24 hours once instead of marking it Expired
+    pub const AUTO_EXTEND_TTL: &str = "headwind.sh/auto-extend-ttl";
 }
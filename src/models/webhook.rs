@@ -100,3 +100,166 @@ impl ChartPushEvent {
         format!("oci://{}/{}", self.registry, self.repository)
     }
 }
+
+/// GitLab Pipeline Hook payload (`X-Gitlab-Event: Pipeline Hook`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitLabPipelineEvent {
+    pub object_kind: String,
+    pub object_attributes: GitLabPipelineAttributes,
+    pub project: GitLabProject,
+    #[serde(default)]
+    pub user: Option<GitLabUser>,
+    /// Set for merge request pipelines
+    #[serde(default)]
+    pub merge_request: Option<GitLabMergeRequestRef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitLabPipelineAttributes {
+    pub id: u64,
+    #[serde(rename = "ref")]
+    pub git_ref: String,
+    pub sha: String,
+    /// e.g. `pending`, `running`, `success`, `failed`
+    pub status: String,
+    /// What triggered the pipeline, e.g. `push` or `merge_request_event`
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitLabProject {
+    pub id: u64,
+    #[serde(default)]
+    pub path_with_namespace: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitLabUser {
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitLabMergeRequestRef {
+    pub iid: u64,
+    #[serde(default)]
+    pub state: Option<String>,
+}
+
+impl GitLabPipelineEvent {
+    /// Whether this is a pipeline that finished successfully
+    pub fn succeeded(&self) -> bool {
+        self.object_kind == "pipeline" && self.object_attributes.status == "success"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Awkward values for each component: empty, unicode, path traversal,
+    /// separators, control characters and very long strings
+    fn samples() -> Vec<String> {
+        vec![
+            String::new(),
+            "docker.io".to_string(),
+            "ghcr.io".to_string(),
+            "localhost:5000".to_string(),
+            "nginx".to_string(),
+            "myorg/myapp".to_string(),
+            "v1.2.3".to_string(),
+            "latest".to_string(),
+            "../../etc/passwd".to_string(),
+            "..\\..\\windows".to_string(),
+            "/".to_string(),
+            ":".to_string(),
+            "a:b:c".to_string(),
+            "@sha256:abc".to_string(),
+            "%2e%2e%2f".to_string(),
+            "\0".to_string(),
+            "\n\r\t".to_string(),
+            "注册表/镜像".to_string(),
+            "🚀".to_string(),
+            "e\u{301}".to_string(),
+            "\u{202e}gnp.exe".to_string(),
+            "x".repeat(10_000),
+        ]
+    }
+
+    #[test]
+    fn test_full_image_never_panics() {
+        let samples = samples();
+        for registry in &samples {
+            for repository in &samples {
+                for tag in &samples {
+                    let event = ImagePushEvent {
+                        registry: registry.clone(),
+                        repository: repository.clone(),
+                        tag: tag.clone(),
+                        digest: None,
+                    };
+                    let image = event.full_image();
+
+                    let expected_prefix = if registry.is_empty() || registry == "docker.io" {
+                        repository.clone()
+                    } else {
+                        format!("{}/{}", registry, repository)
+                    };
+                    assert!(image.starts_with(&expected_prefix));
+                    assert!(image.ends_with(&format!(":{}", tag)));
+                    assert_eq!(image.len(), expected_prefix.len() + 1 + tag.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_malformed_payloads_return_errors() {
+        let payloads: &[&[u8]] = &[
+            b"",
+            b"null",
+            b"[]",
+            b"{}",
+            b"{\"events\": null}",
+            b"{\"events\": [{\"action\": 1}]}",
+            b"{\"push_data\": {\"tag\": []}}",
+            b"{\"events\": [",
+            b"\xff\xfe\x00",
+            "{\"events\": [{\"action\": \"push\", \"target\": {\"repository\": \"\u{1F680}\"}}]}"
+                .as_bytes(),
+        ];
+
+        for payload in payloads {
+            assert!(serde_json::from_slice::<RegistryWebhook>(payload).is_err());
+            assert!(serde_json::from_slice::<DockerHubWebhook>(payload).is_err());
+            assert!(serde_json::from_slice::<GitLabPipelineEvent>(payload).is_err());
+        }
+    }
+
+    #[test]
+    fn test_gitlab_pipeline_event() {
+        let event: GitLabPipelineEvent = serde_json::from_value(serde_json::json!({
+            "object_kind": "pipeline",
+            "object_attributes": {
+                "id": 31,
+                "ref": "main",
+                "sha": "bcbb5ec396a2c0f828686f14fac9b80b780504f2",
+                "status": "success",
+                "source": "push"
+            },
+            "project": {"id": 7, "path_with_namespace": "ops/deploy"},
+            "user": {"username": "root", "name": "Administrator"},
+            "merge_request": null,
+            "builds": []
+        }))
+        .unwrap();
+        assert!(event.succeeded());
+        assert_eq!(event.object_attributes.git_ref, "main");
+        assert_eq!(event.project.id, 7);
+        assert!(event.merge_request.is_none());
+
+        let mut running = event.clone();
+        running.object_attributes.status = "running".to_string();
+        assert!(!running.succeeded());
+    }
+}
@@ -1,20 +1,34 @@
 use crate::metrics;
+use crate::models::UpdateImpact;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::{Arc, RwLock};
 use tracing::{error, info};
 
+pub mod observers;
+pub mod router;
 mod slack;
+pub mod status_webhook;
 mod teams;
+mod telegram;
+pub mod template;
+mod victorops;
 mod webhook;
 
+pub use observers::Observer;
 pub use slack::SlackNotifier;
 pub use teams::TeamsNotifier;
+pub use telegram::TelegramNotifier;
+pub use victorops::VictorOpsNotifier;
 pub use webhook::WebhookNotifier;
 
+/// Longest chart changelog included in chat messages
+pub const MAX_CHANGELOG_CHARS: usize = 2000;
+
 /// Notification event types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -37,6 +51,14 @@ pub enum NotificationEvent {
     RollbackCompleted,
     /// Rollback failed
     RollbackFailed,
+    /// Headwind is about to update, and restart, its own Deployment
+    SelfUpdateStarted,
+    /// UpdateRequest pending for long enough to have its priority raised
+    UpdateEscalated,
+    /// UpdateRequest still pending after its SLA deadline
+    SlaBreached,
+    /// UpdateRequest whose rollout would exceed a PodDisruptionBudget
+    PdbWarning,
 }
 
 impl NotificationEvent {
@@ -51,6 +73,10 @@ impl NotificationEvent {
             Self::RollbackTriggered => "rollback.triggered",
             Self::RollbackCompleted => "rollback.completed",
             Self::RollbackFailed => "rollback.failed",
+            Self::SelfUpdateStarted => "self_update.started",
+            Self::UpdateEscalated => "update.escalated",
+            Self::SlaBreached => "update.sla_breached",
+            Self::PdbWarning => "update.pdb_warning",
         }
     }
 
@@ -65,6 +91,10 @@ impl NotificationEvent {
             Self::RollbackTriggered => "🔄",
             Self::RollbackCompleted => "✅",
             Self::RollbackFailed => "💥",
+            Self::SelfUpdateStarted => "🚨",
+            Self::UpdateEscalated => "⏫",
+            Self::SlaBreached => "🚨",
+            Self::PdbWarning => "⚠️",
         }
     }
 
@@ -79,6 +109,10 @@ impl NotificationEvent {
             Self::RollbackTriggered => "#FF9800",    // Orange
             Self::RollbackCompleted => "#4CAF50",    // Green
             Self::RollbackFailed => "#F44336",       // Red
+            Self::SelfUpdateStarted => "#F44336",    // Red
+            Self::UpdateEscalated => "#FF9800",      // Orange
+            Self::SlaBreached => "#F44336",          // Red
+            Self::PdbWarning => "#FF9800",           // Orange
         }
     }
 }
@@ -99,6 +133,8 @@ pub struct NotificationPayload {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ui_url: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_page_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub approved_by: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rejection_reason: Option<String>,
@@ -108,6 +144,24 @@ pub struct NotificationPayload {
     pub update_request_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub metadata: Option<HashMap<String, String>>,
+    /// Slack channel chosen by a namespace notification route
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slack_channel: Option<String>,
+    /// PagerDuty service chosen by a namespace notification route
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagerduty_service: Option<String>,
+    /// Release notes or commit of the new image, from its OCI labels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changelog_url: Option<String>,
+    /// Commit the new image was built from (`org.opencontainers.image.revision`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_revision: Option<String>,
+    /// Changes between the current and new Helm chart versions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+    /// Estimated blast radius of the rollout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub impact: Option<UpdateImpact>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -128,6 +182,8 @@ pub struct DeploymentInfo {
 pub struct NotificationConfig {
     pub slack: SlackConfig,
     pub teams: TeamsConfig,
+    pub telegram: TelegramConfig,
+    pub victorops: VictorOpsConfig,
     pub webhook: WebhookConfig,
 }
 
@@ -146,6 +202,56 @@ pub struct TeamsConfig {
     pub webhook_url: Option<String>,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct TelegramConfig {
+    pub enabled: bool,
+    pub bot_token: String,
+    /// Chat, group or channel ID (e.g. `-1001234567890`) or `@channelusername`
+    pub chat_id: String,
+    pub parse_mode: TelegramParseMode,
+}
+
+/// Telegram message formatting mode
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TelegramParseMode {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl TelegramParseMode {
+    /// Value of the Bot API `parse_mode` parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Markdown => "Markdown",
+            Self::Html => "HTML",
+        }
+    }
+}
+
+impl FromStr for TelegramParseMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "markdown" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            _ => Err(anyhow::anyhow!(
+                "Invalid Telegram parse mode '{}', expected Markdown or HTML",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VictorOpsConfig {
+    pub enabled: bool,
+    /// REST endpoint URL of the VictorOps REST integration, including the API key
+    pub rest_endpoint_url: String,
+    pub routing_key: String,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct WebhookConfig {
     pub enabled: bool,
@@ -160,6 +266,8 @@ impl Default for NotificationConfig {
         Self {
             slack: SlackConfig::default(),
             teams: TeamsConfig::default(),
+            telegram: TelegramConfig::default(),
+            victorops: VictorOpsConfig::default(),
             webhook: WebhookConfig {
                 enabled: false,
                 url: None,
@@ -177,6 +285,8 @@ impl NotificationConfig {
         Self {
             slack: SlackConfig::from_env(),
             teams: TeamsConfig::from_env(),
+            telegram: TelegramConfig::from_env(),
+            victorops: VictorOpsConfig::from_env(),
             webhook: WebhookConfig::from_env(),
         }
     }
@@ -234,13 +344,19 @@ impl NotificationConfig {
         Self {
             slack: SlackConfig::from_configmap_config(cm_config.slack),
             teams: TeamsConfig::from_configmap_config(cm_config.teams),
+            telegram: TelegramConfig::from_configmap_config(cm_config.telegram),
+            victorops: VictorOpsConfig::from_configmap_config(cm_config.victorops),
             webhook: WebhookConfig::from_configmap_config(cm_config.webhook),
         }
     }
 
     /// Check if any notification channels are enabled
     pub fn has_enabled_channels(&self) -> bool {
-        self.slack.enabled || self.teams.enabled || self.webhook.enabled
+        self.slack.enabled
+            || self.teams.enabled
+            || self.telegram.enabled
+            || self.victorops.enabled
+            || self.webhook.enabled
     }
 }
 
@@ -252,6 +368,10 @@ struct ConfigMapNotificationConfig {
     #[serde(default)]
     teams: Option<ConfigMapTeamsConfig>,
     #[serde(default)]
+    telegram: Option<ConfigMapTelegramConfig>,
+    #[serde(default)]
+    victorops: Option<ConfigMapVictorOpsConfig>,
+    #[serde(default)]
     webhook: Option<ConfigMapWebhookConfig>,
 }
 
@@ -270,6 +390,21 @@ struct ConfigMapTeamsConfig {
     webhook_url: Option<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+struct ConfigMapTelegramConfig {
+    enabled: Option<bool>,
+    bot_token: Option<String>,
+    chat_id: Option<String>,
+    parse_mode: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ConfigMapVictorOpsConfig {
+    enabled: Option<bool>,
+    rest_endpoint_url: Option<String>,
+    routing_key: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 struct ConfigMapWebhookConfig {
     enabled: Option<bool>,
@@ -351,6 +486,70 @@ impl TeamsConfig {
     }
 }
 
+impl TelegramConfig {
+    /// Load Telegram configuration from environment variables
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("TELEGRAM_ENABLED")
+                .unwrap_or_default()
+                .parse()
+                .unwrap_or(false),
+            bot_token: std::env::var("TELEGRAM_BOT_TOKEN").unwrap_or_default(),
+            chat_id: std::env::var("TELEGRAM_CHAT_ID").unwrap_or_default(),
+            parse_mode: std::env::var("TELEGRAM_PARSE_MODE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Load Telegram configuration from ConfigMap, falling back to environment variables
+    fn from_configmap_config(cm_config: Option<ConfigMapTelegramConfig>) -> Self {
+        if let Some(cm) = cm_config {
+            let env = Self::from_env();
+            Self {
+                enabled: cm.enabled.unwrap_or(env.enabled),
+                bot_token: cm.bot_token.unwrap_or(env.bot_token),
+                chat_id: cm.chat_id.unwrap_or(env.chat_id),
+                parse_mode: cm
+                    .parse_mode
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(env.parse_mode),
+            }
+        } else {
+            Self::from_env()
+        }
+    }
+}
+
+impl VictorOpsConfig {
+    /// Load VictorOps configuration from environment variables
+    pub fn from_env() -> Self {
+        Self {
+            enabled: std::env::var("VICTOROPS_ENABLED")
+                .unwrap_or_default()
+                .parse()
+                .unwrap_or(false),
+            rest_endpoint_url: std::env::var("VICTOROPS_REST_ENDPOINT_URL").unwrap_or_default(),
+            routing_key: std::env::var("VICTOROPS_ROUTING_KEY").unwrap_or_default(),
+        }
+    }
+
+    /// Load VictorOps configuration from ConfigMap, falling back to environment variables
+    fn from_configmap_config(cm_config: Option<ConfigMapVictorOpsConfig>) -> Self {
+        if let Some(cm) = cm_config {
+            let env = Self::from_env();
+            Self {
+                enabled: cm.enabled.unwrap_or(env.enabled),
+                rest_endpoint_url: cm.rest_endpoint_url.unwrap_or(env.rest_endpoint_url),
+                routing_key: cm.routing_key.unwrap_or(env.routing_key),
+            }
+        } else {
+            Self::from_env()
+        }
+    }
+}
+
 impl WebhookConfig {
     /// Load webhook configuration from environment variables
     pub fn from_env() -> Self {
@@ -411,6 +610,16 @@ pub trait Notifier: Send + Sync {
     fn is_enabled(&self) -> bool;
 }
 
+/// Notifiers tried in turn for escalations that should reach one on-call
+/// channel rather than all of them, paging first
+pub const ESCALATION_ORDER: &[&str] = &[
+    "VictorOps",
+    "Slack",
+    "Microsoft Teams",
+    "Telegram",
+    "Webhook",
+];
+
 /// Main notification manager that coordinates all notifiers
 pub struct NotificationManager {
     notifiers: Vec<Box<dyn Notifier>>,
@@ -436,6 +645,22 @@ impl NotificationManager {
             }
         }
 
+        // Add Telegram notifier if enabled
+        if config.telegram.enabled {
+            match TelegramNotifier::new(config.telegram.clone()) {
+                Ok(notifier) => notifiers.push(Box::new(notifier)),
+                Err(e) => error!("Failed to create Telegram notifier: {}", e),
+            }
+        }
+
+        // Add VictorOps notifier if enabled
+        if config.victorops.enabled {
+            match VictorOpsNotifier::new(config.victorops.clone()) {
+                Ok(notifier) => notifiers.push(Box::new(notifier)),
+                Err(e) => error!("Failed to create VictorOps notifier: {}", e),
+            }
+        }
+
         // Add webhook notifier if enabled
         if config.webhook.enabled {
             match WebhookNotifier::new(config.webhook.clone()) {
@@ -473,12 +698,15 @@ impl NotificationManager {
             match notifier.send(payload).await {
                 Ok(()) => {
                     info!("Notification sent successfully via {}", notifier.name());
+                    metrics::status::STATUS.record_notification(notifier.name(), Ok(()));
                     metrics::NOTIFICATIONS_SENT_TOTAL.inc();
 
                     // Increment per-channel metrics
                     match notifier.name() {
                         "Slack" => metrics::NOTIFICATIONS_SLACK_SENT.inc(),
                         "Microsoft Teams" => metrics::NOTIFICATIONS_TEAMS_SENT.inc(),
+                        "Telegram" => metrics::NOTIFICATIONS_TELEGRAM_SENT.inc(),
+                        "VictorOps" => metrics::NOTIFICATIONS_VICTOROPS_SENT.inc(),
                         "Webhook" => metrics::NOTIFICATIONS_WEBHOOK_SENT.inc(),
                         _ => {},
                     }
@@ -486,9 +714,44 @@ impl NotificationManager {
                 Err(e) => {
                     error!("Failed to send notification via {}: {}", notifier.name(), e);
                     metrics::NOTIFICATIONS_FAILED_TOTAL.inc();
+                    metrics::status::STATUS
+                        .record_notification(notifier.name(), Err(e.to_string()));
+                },
+            }
+        }
+    }
+
+    /// Send through the first enabled notifier in [`ESCALATION_ORDER`] that
+    /// delivers it, falling back to the next one on failure. Returns the name
+    /// of the notifier used.
+    pub async fn notify_highest_priority(
+        &self,
+        payload: &NotificationPayload,
+    ) -> Option<&'static str> {
+        for name in ESCALATION_ORDER {
+            let Some(notifier) = self
+                .notifiers
+                .iter()
+                .find(|n| n.name() == *name && n.is_enabled())
+            else {
+                continue;
+            };
+            match notifier.send(payload).await {
+                Ok(()) => {
+                    info!("Escalation sent via {}", notifier.name());
+                    metrics::NOTIFICATIONS_SENT_TOTAL.inc();
+                    metrics::status::STATUS.record_notification(notifier.name(), Ok(()));
+                    return Some(notifier.name());
+                },
+                Err(e) => {
+                    error!("Failed to send escalation via {}: {}", notifier.name(), e);
+                    metrics::NOTIFICATIONS_FAILED_TOTAL.inc();
+                    metrics::status::STATUS
+                        .record_notification(notifier.name(), Err(e.to_string()));
                 },
             }
         }
+        None
     }
 
     /// Check if any notifiers are enabled
@@ -512,11 +775,18 @@ impl NotificationPayload {
             requires_approval: None,
             approval_url: None,
             ui_url: None,
+            status_page_url: None,
             approved_by: None,
             rejection_reason: None,
             error_message: None,
             update_request_name: None,
             metadata: None,
+            slack_channel: None,
+            pagerduty_service: None,
+            changelog_url: None,
+            source_revision: None,
+            changelog: None,
+            impact: None,
         }
     }
 
@@ -540,6 +810,11 @@ impl NotificationPayload {
         self
     }
 
+    pub fn with_status_page_url(mut self, url: impl Into<String>) -> Self {
+        self.status_page_url = Some(url.into());
+        self
+    }
+
     pub fn with_approved_by(mut self, approver: impl Into<String>) -> Self {
         self.approved_by = Some(approver.into());
         self
@@ -565,6 +840,41 @@ impl NotificationPayload {
         self
     }
 
+    /// Link the changes in the new image using its OCI labels
+    pub fn with_image_labels(mut self, labels: &HashMap<String, String>) -> Self {
+        self.changelog_url = crate::polling::labels::changelog_url(labels);
+        self.source_revision = crate::polling::labels::source_revision(labels);
+        self
+    }
+
+    pub fn with_impact(mut self, impact: UpdateImpact) -> Self {
+        self.impact = Some(impact);
+        self
+    }
+
+    pub fn with_changelog(mut self, changelog: impl Into<String>) -> Self {
+        self.changelog = Some(changelog.into());
+        self
+    }
+
+    /// `changelog` cut to `MAX_CHANGELOG_CHARS` for chat messages
+    pub fn changelog_excerpt(&self) -> Option<String> {
+        let changelog = self.changelog.as_deref()?;
+        if changelog.chars().count() <= MAX_CHANGELOG_CHARS {
+            return Some(changelog.to_string());
+        }
+        let mut excerpt: String = changelog.chars().take(MAX_CHANGELOG_CHARS).collect();
+        excerpt.push_str("\n…");
+        Some(excerpt)
+    }
+
+    /// `source_revision` shortened to 7 characters, as git displays commits
+    pub fn short_revision(&self) -> Option<&str> {
+        self.source_revision
+            .as_deref()
+            .map(|r| r.get(..7).unwrap_or(r))
+    }
+
     /// Generate a human-readable title for the notification
     pub fn title(&self) -> String {
         let resource_kind = self
@@ -611,6 +921,27 @@ impl NotificationPayload {
             NotificationEvent::RollbackFailed => {
                 format!("Rollback failed: {}", resource_ref)
             },
+            NotificationEvent::SelfUpdateStarted => {
+                format!("Headwind is updating itself: {}", resource_ref)
+            },
+            NotificationEvent::UpdateEscalated => {
+                format!(
+                    "[ESCALATED] Update still pending approval: {}",
+                    resource_ref
+                )
+            },
+            NotificationEvent::SlaBreached => {
+                format!(
+                    "[SLA BREACHED] Update not reviewed in time: {}",
+                    resource_ref
+                )
+            },
+            NotificationEvent::PdbWarning => {
+                format!(
+                    "[PDB WARNING] Update would exceed the disruption budget: {}",
+                    resource_ref
+                )
+            },
         }
     }
 
@@ -637,6 +968,32 @@ impl NotificationPayload {
             desc.push_str(&format!("\nError: {}", error));
         }
 
+        if self.event == NotificationEvent::UpdateEscalated
+            && let Some(metadata) = &self.metadata
+            && let (Some(hours), Some(priority)) =
+                (metadata.get("pendingHours"), metadata.get("priority"))
+        {
+            desc.push_str(&format!(
+                "\nPending for {}h, priority raised to {}",
+                hours, priority
+            ));
+        }
+
+        if self.event == NotificationEvent::SlaBreached
+            && let Some(metadata) = &self.metadata
+            && let (Some(hours), Some(deadline)) =
+                (metadata.get("slaHours"), metadata.get("slaDeadline"))
+        {
+            desc.push_str(&format!(
+                "\nSLA of {}h passed at {} without a review",
+                hours, deadline
+            ));
+        }
+
+        if let Some(impact) = &self.impact {
+            desc.push_str(&format!("\nImpact: {}", impact.summary()));
+        }
+
         desc
     }
 }
@@ -662,10 +1019,42 @@ pub fn notify(payload: NotificationPayload) {
 
     if let Some(manager) = notifier {
         // Spawn a background task to send notifications asynchronously
+        let payload = payload.clone();
         tokio::spawn(async move {
+            let mut payload = payload;
+            router::route_notification(&mut payload).await;
             manager.notify(&payload).await;
         });
     }
+
+    // Observers are notified whether or not global channels are configured
+    if observers::notifies_observers(payload.event) && tokio::runtime::Handle::try_current().is_ok()
+    {
+        tokio::spawn(async move {
+            observers::notify_observers(&payload).await;
+        });
+    }
+}
+
+/// Send an escalation through the highest-priority configured notifier only,
+/// see [`ESCALATION_ORDER`]
+pub fn notify_escalation(payload: NotificationPayload) {
+    let notifier = GLOBAL_NOTIFIER.read().unwrap().clone();
+
+    if let Some(manager) = notifier {
+        tokio::spawn(async move {
+            let mut payload = payload;
+            router::route_notification(&mut payload).await;
+            if manager.notify_highest_priority(&payload).await.is_none() {
+                error!(
+                    "No notifier delivered the {} escalation for {}/{}",
+                    payload.event.as_str(),
+                    payload.deployment.namespace,
+                    payload.deployment.name
+                );
+            }
+        });
+    }
 }
 
 /// Helper function to send update detected notification
@@ -680,13 +1069,44 @@ pub fn notify_update_request_created(
     policy: String,
     requires_approval: bool,
     update_request_name: String,
+    status_page_url: Option<String>,
+    image_labels: Option<HashMap<String, String>>,
 ) {
+    notify(update_request_created_payload(
+        deployment,
+        policy,
+        requires_approval,
+        update_request_name,
+        status_page_url,
+        image_labels,
+    ));
+}
+
+/// UpdateRequest created notification, for callers adding details before sending
+pub fn update_request_created_payload(
+    deployment: DeploymentInfo,
+    policy: String,
+    requires_approval: bool,
+    update_request_name: String,
+    status_page_url: Option<String>,
+    image_labels: Option<HashMap<String, String>>,
+) -> NotificationPayload {
     let mut payload =
         NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment.clone())
             .with_policy(policy)
             .with_requires_approval(requires_approval)
             .with_update_request(update_request_name);
 
+    // Link what changed when the image's labels were fetched
+    if let Some(labels) = image_labels {
+        payload = payload.with_image_labels(&labels);
+    }
+
+    // Link to the service's status page so approvers can check health first
+    if let Some(url) = status_page_url {
+        payload = payload.with_status_page_url(url);
+    }
+
     // Add approval URL if requires_approval is true
     if requires_approval {
         // Use environment variable or default to localhost for testing
@@ -718,6 +1138,20 @@ pub fn notify_update_request_created(
         payload = payload.with_ui_url(ui_url);
     }
 
+    payload
+}
+
+/// Helper function to send the warning about an UpdateRequest whose rollout
+/// would take down more pods than its PodDisruptionBudget allows
+pub fn notify_pdb_warning(
+    deployment: DeploymentInfo,
+    update_request_name: String,
+    impact: UpdateImpact,
+) {
+    let payload = NotificationPayload::new(NotificationEvent::PdbWarning, deployment)
+        .with_update_request(update_request_name)
+        .with_impact(impact);
+
     notify(payload);
 }
 
@@ -857,4 +1291,88 @@ mod tests {
         assert!(desc.contains("Policy: minor"));
         assert!(desc.contains("Approved by: admin@example.com"));
     }
+
+    /// Local endpoint answering every POST with `status`; returns its URL
+    /// and the number of requests received
+    async fn start_receiver(
+        status: axum::http::StatusCode,
+    ) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let received = Arc::new(AtomicUsize::new(0));
+        let counter = received.clone();
+        let app = axum::Router::new().fallback(move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                status
+            }
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}", addr), received)
+    }
+
+    fn escalation_manager(victorops_url: String, webhook_url: String) -> NotificationManager {
+        NotificationManager::new(NotificationConfig {
+            victorops: VictorOpsConfig {
+                enabled: true,
+                rest_endpoint_url: victorops_url,
+                routing_key: "payments".to_string(),
+            },
+            webhook: WebhookConfig {
+                enabled: true,
+                url: Some(webhook_url),
+                secret: None,
+                timeout_seconds: 5,
+                max_retries: 0,
+            },
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_escalation_uses_highest_priority_notifier() {
+        use axum::http::StatusCode;
+        use std::sync::atomic::Ordering;
+        let payload = NotificationPayload::new(
+            NotificationEvent::SlaBreached,
+            DeploymentInfo {
+                name: "web".to_string(),
+                namespace: "payments".to_string(),
+                current_image: "web:1.0.0".to_string(),
+                new_image: "web:1.0.1".to_string(),
+                container: None,
+                resource_kind: None,
+            },
+        );
+
+        // Paging comes first, and only one channel is notified
+        let (victorops_url, victorops) = start_receiver(StatusCode::OK).await;
+        let (webhook_url, webhook) = start_receiver(StatusCode::OK).await;
+        let manager = escalation_manager(victorops_url, webhook_url);
+        assert_eq!(
+            manager.notify_highest_priority(&payload).await,
+            Some("VictorOps")
+        );
+        assert_eq!(victorops.load(Ordering::SeqCst), 1);
+        assert_eq!(webhook.load(Ordering::SeqCst), 0);
+
+        // The next channel is used when the first fails
+        let (victorops_url, victorops) = start_receiver(StatusCode::BAD_GATEWAY).await;
+        let (webhook_url, webhook) = start_receiver(StatusCode::OK).await;
+        let manager = escalation_manager(victorops_url, webhook_url);
+        assert_eq!(
+            manager.notify_highest_priority(&payload).await,
+            Some("Webhook")
+        );
+        assert_eq!(victorops.load(Ordering::SeqCst), 1);
+        assert_eq!(webhook.load(Ordering::SeqCst), 1);
+
+        // Nothing configured
+        let manager = NotificationManager::new(NotificationConfig::default());
+        assert_eq!(manager.notify_highest_priority(&payload).await, None);
+    }
 }
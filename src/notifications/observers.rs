@@ -0,0 +1,633 @@
+//! Observers: extra recipients of UpdateRequest notifications.
+//!
+//! `headwind.sh/notify-observers` on a workload lists email addresses and
+//! HTTPS webhook URLs, e.g. for a DBA team that doesn't manage the Deployment
+//! but wants to hear about database image updates. Observers are notified in
+//! addition to, and independently of, the global notification channels:
+//! webhook observers receive the same JSON payload as the generic webhook
+//! notifier. Email observers need an SMTP notifier, which Headwind doesn't
+//! have yet, so they are logged and skipped.
+//!
+//! Notifications are batched per observer for
+//! `HEADWIND_OBSERVER_BATCH_WINDOW_SECS` (default 30), so approving an update
+//! of a base image shared by 200 Deployments sends each observer one digest
+//! instead of 200 messages. A batch holding a single notification is sent as
+//! the plain payload.
+
+use super::{NotificationEvent, NotificationPayload};
+use crate::metrics::NOTIFICATIONS_OBSERVER_TOTAL;
+use crate::models::{annotations, parse_observers};
+use anyhow::{Context, Result, anyhow, bail};
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use kube::{Api, Client};
+use once_cell::sync::OnceCell;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Timeout of a request to a webhook observer
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Default time notifications for an observer are collected before sending
+const DEFAULT_BATCH_WINDOW_SECS: u64 = 30;
+
+/// Notifications an observer's batch holds before it is sent early
+pub const MAX_BATCH_SIZE: usize = 100;
+
+/// A recipient named in `headwind.sh/notify-observers`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Observer {
+    Email(String),
+    Webhook(String),
+}
+
+impl Observer {
+    /// Value of the `observer_type` metric label
+    pub fn type_label(&self) -> &'static str {
+        match self {
+            Self::Email(_) => "email",
+            Self::Webhook(_) => "webhook",
+        }
+    }
+}
+
+impl FromStr for Observer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.chars().any(char::is_whitespace) {
+            bail!("Invalid observer '{}'", s);
+        }
+        if let Some(host) = s.strip_prefix("https://") {
+            if host.is_empty() {
+                bail!("Invalid observer URL '{}'", s);
+            }
+            return Ok(Self::Webhook(s.to_string()));
+        }
+        match s.split_once('@') {
+            Some((local, domain))
+                if !local.is_empty() && domain.contains('.') && !domain.contains('@') =>
+            {
+                Ok(Self::Email(s.to_string()))
+            },
+            _ => Err(anyhow!(
+                "Observer '{}' is neither an email address nor an HTTPS URL",
+                s
+            )),
+        }
+    }
+}
+
+/// Whether observers hear about `event`: UpdateRequests being created and
+/// changing state, including rollbacks
+pub fn notifies_observers(event: NotificationEvent) -> bool {
+    !matches!(
+        event,
+        NotificationEvent::UpdateDetected | NotificationEvent::SelfUpdateStarted
+    )
+}
+
+/// Sends notifications to observers
+pub struct ObserverDispatcher {
+    http: reqwest::Client,
+}
+
+impl ObserverDispatcher {
+    pub fn new() -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Self { http })
+    }
+
+    /// Notify every observer and return how many were reached. A failing
+    /// observer doesn't stop the others.
+    pub async fn dispatch(&self, payload: &NotificationPayload, observers: &[Observer]) -> usize {
+        let mut delivered = 0;
+        for observer in observers {
+            if self
+                .send(observer, &serde_json::to_value(payload).unwrap_or_default())
+                .await
+            {
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+
+    /// Send the notifications batched for `observer`: the payload itself for a
+    /// single notification, otherwise a digest listing all of them. Returns
+    /// whether the observer was reached.
+    pub async fn dispatch_batch(
+        &self,
+        observer: &Observer,
+        payloads: &[NotificationPayload],
+    ) -> bool {
+        let body = match payloads {
+            [] => return false,
+            [payload] => serde_json::to_value(payload).unwrap_or_default(),
+            _ => digest(payloads),
+        };
+        self.send(observer, &body).await
+    }
+
+    async fn send(&self, observer: &Observer, body: &serde_json::Value) -> bool {
+        let result = match observer {
+            Observer::Webhook(url) => self.send_webhook(url, body).await,
+            Observer::Email(address) => {
+                warn!(
+                    "Not notifying observer {}: email notifications are not supported",
+                    address
+                );
+                return false;
+            },
+        };
+
+        match result {
+            Ok(()) => {
+                debug!("Notified observer {:?}", observer);
+                NOTIFICATIONS_OBSERVER_TOTAL
+                    .with_label_values(&[observer.type_label()])
+                    .inc();
+                true
+            },
+            Err(e) => {
+                warn!("Failed to notify observer {:?}: {}", observer, e);
+                false
+            },
+        }
+    }
+
+    async fn send_webhook(&self, url: &str, body: &serde_json::Value) -> Result<()> {
+        let response = self.http.post(url).json(body).send().await?;
+        if !response.status().is_success() {
+            bail!("Observer webhook returned {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+/// Digest of several notifications sent to one observer
+fn digest(payloads: &[NotificationPayload]) -> serde_json::Value {
+    json!({
+        "event": "digest",
+        "count": payloads.len(),
+        "updateRequests": payloads
+            .iter()
+            .filter_map(|p| p.update_request_name.as_deref())
+            .collect::<Vec<_>>(),
+        "notifications": payloads,
+    })
+}
+
+/// Collects notifications per observer and sends each observer one message
+/// per flush. A batch reaching the maximum size is sent right away.
+pub struct ObserverBatcher {
+    pending: Mutex<HashMap<Observer, Vec<NotificationPayload>>>,
+    dispatcher: ObserverDispatcher,
+    max_batch_size: usize,
+}
+
+impl ObserverBatcher {
+    pub fn new(dispatcher: ObserverDispatcher, max_batch_size: usize) -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+            dispatcher,
+            max_batch_size: max_batch_size.max(1),
+        }
+    }
+
+    /// Queue `payload` for `observer`, sending the batch if it is full
+    pub async fn add(&self, observer: Observer, payload: NotificationPayload) {
+        let full_batch = {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            let batch = pending.entry(observer.clone()).or_default();
+            batch.push(payload);
+            if batch.len() >= self.max_batch_size {
+                pending.remove(&observer)
+            } else {
+                None
+            }
+        };
+
+        if let Some(batch) = full_batch {
+            debug!(
+                "Batch for observer {:?} reached {} notifications, sending now",
+                observer,
+                batch.len()
+            );
+            self.dispatcher.dispatch_batch(&observer, &batch).await;
+        }
+    }
+
+    /// Send every pending batch and return how many observers were reached
+    pub async fn flush(&self) -> usize {
+        let batches: Vec<_> = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .drain()
+            .collect();
+
+        let mut delivered = 0;
+        for (observer, batch) in batches {
+            if self.dispatcher.dispatch_batch(&observer, &batch).await {
+                delivered += 1;
+            }
+        }
+        delivered
+    }
+
+    /// Flush the pending batches every `window`
+    pub fn spawn_flusher(self: &Arc<Self>, window: Duration) {
+        let batcher = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(window);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                batcher.flush().await;
+            }
+        });
+    }
+}
+
+/// `HEADWIND_OBSERVER_BATCH_WINDOW_SECS`; zero disables batching
+fn batch_window() -> Duration {
+    Duration::from_secs(
+        std::env::var("HEADWIND_OBSERVER_BATCH_WINDOW_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_BATCH_WINDOW_SECS),
+    )
+}
+
+/// Batcher shared by all observer notifications, `None` when batching is
+/// disabled. The flush task starts with the first notification.
+fn batcher() -> Result<Option<Arc<ObserverBatcher>>> {
+    static BATCHER: OnceCell<Option<Arc<ObserverBatcher>>> = OnceCell::new();
+    BATCHER
+        .get_or_try_init(|| {
+            let window = batch_window();
+            if window.is_zero() {
+                return Ok(None);
+            }
+            let batcher = Arc::new(ObserverBatcher::new(
+                ObserverDispatcher::new()?,
+                MAX_BATCH_SIZE,
+            ));
+            batcher.spawn_flusher(window);
+            info!(
+                "Batching observer notifications every {}s",
+                window.as_secs()
+            );
+            Ok(Some(batcher))
+        })
+        .cloned()
+}
+
+/// Notify the observers annotated on the workload `payload` is about
+pub async fn notify_observers(payload: &NotificationPayload) {
+    if !notifies_observers(payload.event) {
+        return;
+    }
+
+    let observers = match workload_observers(payload).await {
+        Ok(observers) => observers,
+        Err(e) => {
+            warn!(
+                "Failed to read observers of {}/{}: {}",
+                payload.deployment.namespace, payload.deployment.name, e
+            );
+            return;
+        },
+    };
+    if observers.is_empty() {
+        return;
+    }
+
+    info!(
+        "Notifying {} observers of {}/{} about {}",
+        observers.len(),
+        payload.deployment.namespace,
+        payload.deployment.name,
+        payload.event.as_str()
+    );
+    match batcher() {
+        Ok(Some(batcher)) => {
+            for observer in observers {
+                batcher.add(observer, payload.clone()).await;
+            }
+        },
+        Ok(None) => match ObserverDispatcher::new() {
+            Ok(dispatcher) => {
+                dispatcher.dispatch(payload, &observers).await;
+            },
+            Err(e) => warn!("Failed to notify observers: {}", e),
+        },
+        Err(e) => warn!("Failed to notify observers: {}", e),
+    }
+}
+
+/// Observers in the `headwind.sh/notify-observers` annotation of the workload
+async fn workload_observers(payload: &NotificationPayload) -> Result<Vec<Observer>> {
+    let deployment = &payload.deployment;
+    let client = Client::try_default().await?;
+    let annotations = workload_annotations(
+        client,
+        deployment.resource_kind.as_deref().unwrap_or("Deployment"),
+        &deployment.namespace,
+        &deployment.name,
+    )
+    .await?;
+
+    Ok(annotations
+        .as_ref()
+        .and_then(|a| a.get(annotations::NOTIFY_OBSERVERS))
+        .map(|value| {
+            parse_observers(value)
+                .iter()
+                .filter_map(|observer| observer.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Annotations of a Deployment, StatefulSet, DaemonSet or ReplicaSet; `None`
+/// for other kinds
+pub(crate) async fn workload_annotations(
+    client: Client,
+    kind: &str,
+    namespace: &str,
+    name: &str,
+) -> Result<Option<BTreeMap<String, String>>> {
+    let annotations: Option<BTreeMap<String, String>> = match kind {
+        "Deployment" => {
+            Api::<Deployment>::namespaced(client, namespace)
+                .get_metadata(name)
+                .await?
+                .metadata
+                .annotations
+        },
+        "StatefulSet" => {
+            Api::<StatefulSet>::namespaced(client, namespace)
+                .get_metadata(name)
+                .await?
+                .metadata
+                .annotations
+        },
+        "DaemonSet" => {
+            Api::<DaemonSet>::namespaced(client, namespace)
+                .get_metadata(name)
+                .await?
+                .metadata
+                .annotations
+        },
+        "ReplicaSet" => {
+            Api::<ReplicaSet>::namespaced(client, namespace)
+                .get_metadata(name)
+                .await?
+                .metadata
+                .annotations
+        },
+        _ => None,
+    };
+    Ok(annotations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::{
+        DeploymentInfo, NotificationConfig, NotificationManager, WebhookConfig,
+    };
+    use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+    use serde_json::Value;
+    use std::sync::{Arc, Mutex};
+
+    /// Mock webhook answering `status` and recording request bodies
+    async fn start_webhook(status: StatusCode) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    move |State(received): State<Arc<Mutex<Vec<Value>>>>,
+                          Json(body): Json<Value>| async move {
+                        received.lock().unwrap().push(body);
+                        status
+                    },
+                ),
+            )
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}/hook", addr), received)
+    }
+
+    fn payload() -> NotificationPayload {
+        update_request_payload("postgres-16-4")
+    }
+
+    fn update_request_payload(update_request: &str) -> NotificationPayload {
+        NotificationPayload::new(
+            NotificationEvent::UpdateRequestCreated,
+            DeploymentInfo {
+                name: "postgres".to_string(),
+                namespace: "databases".to_string(),
+                current_image: "postgres:16.3".to_string(),
+                new_image: "postgres:16.4".to_string(),
+                container: Some("postgres".to_string()),
+                resource_kind: Some("StatefulSet".to_string()),
+            },
+        )
+        .with_update_request(update_request.to_string())
+    }
+
+    fn global_webhook(url: String) -> NotificationManager {
+        NotificationManager::new(NotificationConfig {
+            webhook: WebhookConfig {
+                enabled: true,
+                url: Some(url),
+                secret: None,
+                timeout_seconds: 5,
+                max_retries: 0,
+            },
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_parse_observer() {
+        assert_eq!(
+            "dba-team@example.com".parse::<Observer>().unwrap(),
+            Observer::Email("dba-team@example.com".to_string())
+        );
+        assert_eq!(
+            "https://hooks.example.com/dba".parse::<Observer>().unwrap(),
+            Observer::Webhook("https://hooks.example.com/dba".to_string())
+        );
+        assert!("http://hooks.example.com".parse::<Observer>().is_err());
+        assert!("https://".parse::<Observer>().is_err());
+        assert!("dba-team".parse::<Observer>().is_err());
+        assert!("dba@localhost".parse::<Observer>().is_err());
+        assert!("dba team@example.com".parse::<Observer>().is_err());
+    }
+
+    #[test]
+    fn test_notifies_observers() {
+        assert!(notifies_observers(NotificationEvent::UpdateRequestCreated));
+        assert!(notifies_observers(NotificationEvent::UpdateCompleted));
+        assert!(notifies_observers(NotificationEvent::RollbackCompleted));
+        assert!(!notifies_observers(NotificationEvent::UpdateDetected));
+    }
+
+    #[tokio::test]
+    async fn test_observers_notified_when_global_channel_fails() {
+        let (global_url, global) = start_webhook(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let (observer_url, observer) = start_webhook(StatusCode::OK).await;
+        let payload = payload();
+
+        global_webhook(global_url).notify(&payload).await;
+        let delivered = ObserverDispatcher::new()
+            .unwrap()
+            .dispatch(&payload, &[Observer::Webhook(observer_url)])
+            .await;
+
+        assert_eq!(global.lock().unwrap().len(), 1);
+        assert_eq!(delivered, 1);
+        let received = observer.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0]["event"], "update_request_created");
+        assert_eq!(received[0]["deployment"]["name"], "postgres");
+    }
+
+    #[tokio::test]
+    async fn test_failing_observer_does_not_affect_others() {
+        let (global_url, global) = start_webhook(StatusCode::OK).await;
+        let (failing_url, _) = start_webhook(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let (observer_url, observer) = start_webhook(StatusCode::OK).await;
+        let payload = payload();
+        let before = NOTIFICATIONS_OBSERVER_TOTAL
+            .with_label_values(&["webhook"])
+            .get();
+
+        let delivered = ObserverDispatcher::new()
+            .unwrap()
+            .dispatch(
+                &payload,
+                &[
+                    Observer::Webhook(failing_url),
+                    Observer::Email("dba-team@example.com".to_string()),
+                    Observer::Webhook(observer_url),
+                ],
+            )
+            .await;
+        global_webhook(global_url).notify(&payload).await;
+
+        assert_eq!(delivered, 1);
+        assert_eq!(observer.lock().unwrap().len(), 1);
+        assert_eq!(global.lock().unwrap().len(), 1);
+        // Other tests run concurrently, so only a lower bound holds
+        assert!(
+            NOTIFICATIONS_OBSERVER_TOTAL
+                .with_label_values(&["webhook"])
+                .get()
+                > before
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batcher_sends_one_digest_per_observer() {
+        let (dba_url, dba) = start_webhook(StatusCode::OK).await;
+        let (sre_url, sre) = start_webhook(StatusCode::OK).await;
+        let batcher = ObserverBatcher::new(ObserverDispatcher::new().unwrap(), MAX_BATCH_SIZE);
+
+        for name in ["web-1", "web-2", "web-3"] {
+            batcher
+                .add(
+                    Observer::Webhook(dba_url.clone()),
+                    update_request_payload(name),
+                )
+                .await;
+        }
+        batcher
+            .add(Observer::Webhook(sre_url), update_request_payload("web-1"))
+            .await;
+        assert!(dba.lock().unwrap().is_empty());
+
+        assert_eq!(batcher.flush().await, 2);
+        assert_eq!(batcher.flush().await, 0);
+
+        let dba = dba.lock().unwrap();
+        assert_eq!(dba.len(), 1);
+        assert_eq!(dba[0]["event"], "digest");
+        assert_eq!(dba[0]["count"], 3);
+        assert_eq!(
+            dba[0]["updateRequests"],
+            serde_json::json!(["web-1", "web-2", "web-3"])
+        );
+        assert_eq!(dba[0]["notifications"].as_array().unwrap().len(), 3);
+
+        // A single notification is sent as the plain payload
+        let sre = sre.lock().unwrap();
+        assert_eq!(sre.len(), 1);
+        assert_eq!(sre[0]["event"], "update_request_created");
+    }
+
+    #[tokio::test]
+    async fn test_batcher_flushes_full_batch() {
+        let (url, received) = start_webhook(StatusCode::OK).await;
+        let batcher = ObserverBatcher::new(ObserverDispatcher::new().unwrap(), 3);
+
+        for i in 0..4 {
+            batcher
+                .add(
+                    Observer::Webhook(url.clone()),
+                    update_request_payload(&format!("web-{}", i)),
+                )
+                .await;
+        }
+
+        {
+            let received = received.lock().unwrap();
+            assert_eq!(received.len(), 1);
+            assert_eq!(received[0]["count"], 3);
+        }
+
+        // The fourth notification starts a new batch
+        batcher.flush().await;
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1]["deployment"]["name"], "postgres");
+    }
+
+    #[tokio::test]
+    async fn test_batcher_flushes_on_interval() {
+        let (url, received) = start_webhook(StatusCode::OK).await;
+        let batcher = Arc::new(ObserverBatcher::new(
+            ObserverDispatcher::new().unwrap(),
+            MAX_BATCH_SIZE,
+        ));
+        batcher.spawn_flusher(Duration::from_millis(50));
+
+        batcher
+            .add(Observer::Webhook(url), update_request_payload("web-1"))
+            .await;
+
+        for _ in 0..50 {
+            if !received.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,265 @@
+//! Per-namespace notification routing.
+//!
+//! `notifications.routes` in the headwind ConfigMap maps namespaces, selected
+//! by their labels, to a Slack channel and/or PagerDuty service. When several
+//! routes match, the one with the most selector requirements wins; among
+//! equally specific routes the first one listed wins. A route with an empty
+//! selector matches every namespace and so acts as a default.
+
+use super::NotificationPayload;
+use crate::config::{NotificationRoute, get_cached_config};
+use k8s_openapi::api::core::v1::Namespace;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, LabelSelectorRequirement};
+use kube::{Api, Client};
+use std::collections::BTreeMap;
+use tracing::{debug, warn};
+
+/// The most specific route whose selector matches `namespace_labels`
+pub fn resolve_route<'a>(
+    namespace_labels: &BTreeMap<String, String>,
+    routes: &'a [NotificationRoute],
+) -> Option<&'a NotificationRoute> {
+    routes
+        .iter()
+        .filter(|route| selector_matches(&route.namespace_selector, namespace_labels))
+        // max_by_key keeps the last maximum, so iterate in reverse to prefer earlier routes
+        .rev()
+        .max_by_key(|route| specificity(&route.namespace_selector))
+}
+
+/// Apply the fields set in `route` to `payload`
+pub fn apply_route(payload: &mut NotificationPayload, route: &NotificationRoute) {
+    if let Some(channel) = &route.slack_channel {
+        payload.slack_channel = Some(channel.clone());
+    }
+    if let Some(service) = &route.pagerduty_service {
+        payload.pagerduty_service = Some(service.clone());
+    }
+}
+
+/// Route `payload` by the labels of its resource's namespace. Without
+/// configured routes, or if the namespace can't be read, the payload is unchanged.
+pub async fn route_notification(payload: &mut NotificationPayload) {
+    let routes = get_cached_config()
+        .map(|config| config.notifications.routes)
+        .unwrap_or_default();
+    if routes.is_empty() {
+        return;
+    }
+
+    let namespace = &payload.deployment.namespace;
+    let labels = match namespace_labels(namespace).await {
+        Ok(labels) => labels,
+        Err(e) => {
+            warn!(
+                "Failed to read labels of namespace {}, using global notification settings: {}",
+                namespace, e
+            );
+            return;
+        },
+    };
+
+    if let Some(route) = resolve_route(&labels, &routes) {
+        debug!(
+            "Notification for namespace {} routed by {:?}",
+            namespace, route
+        );
+        apply_route(payload, route);
+    }
+}
+
+async fn namespace_labels(namespace: &str) -> Result<BTreeMap<String, String>, kube::Error> {
+    let client = Client::try_default().await?;
+    let namespaces: Api<Namespace> = Api::all(client);
+    Ok(namespaces
+        .get(namespace)
+        .await?
+        .metadata
+        .labels
+        .unwrap_or_default())
+}
+
+/// Number of requirements in the selector
+fn specificity(selector: &LabelSelector) -> usize {
+    selector.match_labels.as_ref().map_or(0, BTreeMap::len)
+        + selector.match_expressions.as_ref().map_or(0, Vec::len)
+}
+
+/// Kubernetes label selector semantics: every requirement must hold
+pub(crate) fn selector_matches(
+    selector: &LabelSelector,
+    labels: &BTreeMap<String, String>,
+) -> bool {
+    let match_labels = selector
+        .match_labels
+        .iter()
+        .flatten()
+        .all(|(key, value)| labels.get(key) == Some(value));
+
+    match_labels
+        && selector
+            .match_expressions
+            .iter()
+            .flatten()
+            .all(|requirement| requirement_matches(requirement, labels))
+}
+
+fn requirement_matches(
+    requirement: &LabelSelectorRequirement,
+    labels: &BTreeMap<String, String>,
+) -> bool {
+    let value = labels.get(&requirement.key);
+    let values = requirement.values.as_deref().unwrap_or_default();
+
+    match requirement.operator.as_str() {
+        "In" => value.is_some_and(|v| values.contains(v)),
+        "NotIn" => value.is_none_or(|v| !values.contains(v)),
+        "Exists" => value.is_some(),
+        "DoesNotExist" => value.is_none(),
+        operator => {
+            warn!("Unknown label selector operator {}", operator);
+            false
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::{DeploymentInfo, NotificationEvent};
+
+    fn labels(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn route(
+        match_labels: &[(&str, &str)],
+        match_expressions: Vec<LabelSelectorRequirement>,
+        channel: &str,
+    ) -> NotificationRoute {
+        NotificationRoute {
+            namespace_selector: LabelSelector {
+                match_labels: (!match_labels.is_empty()).then(|| labels(match_labels)),
+                match_expressions: (!match_expressions.is_empty()).then_some(match_expressions),
+            },
+            slack_channel: Some(channel.to_string()),
+            pagerduty_service: None,
+        }
+    }
+
+    fn expression(key: &str, operator: &str, values: &[&str]) -> LabelSelectorRequirement {
+        LabelSelectorRequirement {
+            key: key.to_string(),
+            operator: operator.to_string(),
+            values: (!values.is_empty()).then(|| values.iter().map(|v| v.to_string()).collect()),
+        }
+    }
+
+    fn channel(route: Option<&NotificationRoute>) -> Option<&str> {
+        route.and_then(|r| r.slack_channel.as_deref())
+    }
+
+    #[test]
+    fn test_most_specific_route_wins() {
+        let routes = vec![
+            route(&[], vec![], "#default"),
+            route(&[("team", "payments")], vec![], "#payments"),
+            route(
+                &[("team", "payments"), ("env", "prod")],
+                vec![],
+                "#payments-prod",
+            ),
+        ];
+
+        let prod = labels(&[("team", "payments"), ("env", "prod")]);
+        assert_eq!(
+            channel(resolve_route(&prod, &routes)),
+            Some("#payments-prod")
+        );
+
+        let staging = labels(&[("team", "payments"), ("env", "staging")]);
+        assert_eq!(channel(resolve_route(&staging, &routes)), Some("#payments"));
+
+        let other = labels(&[("team", "search")]);
+        assert_eq!(channel(resolve_route(&other, &routes)), Some("#default"));
+    }
+
+    #[test]
+    fn test_first_route_wins_ties() {
+        let routes = vec![
+            route(&[("team", "payments")], vec![], "#first"),
+            route(&[("env", "prod")], vec![], "#second"),
+        ];
+
+        let namespace = labels(&[("team", "payments"), ("env", "prod")]);
+        assert_eq!(channel(resolve_route(&namespace, &routes)), Some("#first"));
+    }
+
+    #[test]
+    fn test_no_matching_route() {
+        let routes = vec![route(&[("team", "payments")], vec![], "#payments")];
+
+        assert!(resolve_route(&labels(&[("team", "search")]), &routes).is_none());
+        assert!(resolve_route(&labels(&[]), &routes).is_none());
+        assert!(resolve_route(&labels(&[("team", "payments")]), &[]).is_none());
+    }
+
+    #[test]
+    fn test_match_expressions() {
+        let routes = vec![
+            route(
+                &[],
+                vec![expression("env", "In", &["prod", "staging"])],
+                "#envs",
+            ),
+            route(
+                &[],
+                vec![
+                    expression("env", "In", &["prod", "staging"]),
+                    expression("critical", "Exists", &[]),
+                ],
+                "#critical",
+            ),
+            route(
+                &[],
+                vec![expression("env", "NotIn", &["prod", "staging"])],
+                "#dev",
+            ),
+        ];
+
+        let critical = labels(&[("env", "prod"), ("critical", "true")]);
+        assert_eq!(
+            channel(resolve_route(&critical, &routes)),
+            Some("#critical")
+        );
+        let staging = labels(&[("env", "staging")]);
+        assert_eq!(channel(resolve_route(&staging, &routes)), Some("#envs"));
+        // NotIn also matches namespaces without the label
+        assert_eq!(channel(resolve_route(&labels(&[]), &routes)), Some("#dev"));
+
+        let unknown = vec![route(&[], vec![expression("env", "Gt", &["1"])], "#x")];
+        assert!(resolve_route(&staging, &unknown).is_none());
+    }
+
+    #[test]
+    fn test_apply_route_overrides_only_set_fields() {
+        let deployment = DeploymentInfo {
+            name: "web".to_string(),
+            namespace: "payments".to_string(),
+            current_image: "web:1.0.0".to_string(),
+            new_image: "web:1.1.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+        let mut payload = NotificationPayload::new(NotificationEvent::UpdateCompleted, deployment);
+        payload.pagerduty_service = Some("PGLOBAL".to_string());
+
+        apply_route(&mut payload, &route(&[], vec![], "#payments"));
+
+        assert_eq!(payload.slack_channel.as_deref(), Some("#payments"));
+        assert_eq!(payload.pagerduty_service.as_deref(), Some("PGLOBAL"));
+    }
+}
@@ -1,4 +1,4 @@
-use super::{NotificationPayload, Notifier, SlackConfig};
+use super::{NotificationPayload, Notifier, SlackConfig, template};
 use anyhow::{Context, Result, anyhow};
 use reqwest::Client;
 use serde_json::json;
@@ -68,6 +68,32 @@ impl SlackNotifier {
             }));
         }
 
+        // Add the estimated blast radius of the rollout if assessed
+        if let Some(impact) = &payload.impact {
+            let warning = if impact.violates_disruption_budget() {
+                " ⚠️ exceeds PDB"
+            } else {
+                ""
+            };
+            fields.push(json!({
+                "type": "mrkdwn",
+                "text": format!("*Impact:*\n{}{}", impact.summary(), warning)
+            }));
+        }
+
+        // Link the changes in the new image if its labels were fetched
+        match (&payload.changelog_url, payload.short_revision()) {
+            (Some(url), revision) => fields.push(json!({
+                "type": "mrkdwn",
+                "text": format!("*Changelog:*\n<{}|{}>", url, revision.unwrap_or("View changes"))
+            })),
+            (None, Some(revision)) => fields.push(json!({
+                "type": "mrkdwn",
+                "text": format!("*Revision:*\n`{}`", revision)
+            })),
+            (None, None) => {},
+        }
+
         // Format "HelmRelease" as "Helm Release" for better readability
         let resource_kind = payload
             .deployment
@@ -120,6 +146,17 @@ impl SlackNotifier {
             }));
         }
 
+        // Add the chart changelog of Helm updates
+        if let Some(changelog) = payload.changelog_excerpt() {
+            blocks.push(json!({
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": format!("*Changes:*\n```{}```", changelog)
+                }
+            }));
+        }
+
         // Add action buttons if URLs are present
         let mut action_elements = Vec::new();
 
@@ -152,6 +189,20 @@ impl SlackNotifier {
             }));
         }
 
+        // Add "View Service Status" button if a status page is annotated
+        if let Some(status_page_url) = &payload.status_page_url {
+            action_elements.push(json!({
+                "type": "button",
+                "text": {
+                    "type": "plain_text",
+                    "text": "View Service Status",
+                    "emoji": true
+                },
+                "url": status_page_url,
+                "action_id": "view_service_status"
+            }));
+        }
+
         // Add action block if there are any buttons
         if !action_elements.is_empty() {
             blocks.push(json!({
@@ -174,7 +225,7 @@ impl SlackNotifier {
             ]
         }));
 
-        let mut message = json!({
+        let message = json!({
             "blocks": blocks,
             "attachments": [{
                 "color": color,
@@ -182,9 +233,47 @@ impl SlackNotifier {
             }]
         });
 
+        self.with_sender(payload, message)
+    }
+
+    /// Slack message with `text`, rendered from a custom template, as its only block
+    fn build_templated_message(
+        &self,
+        payload: &NotificationPayload,
+        text: &str,
+    ) -> serde_json::Value {
+        let message = json!({
+            "text": text,
+            "blocks": [{
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": text
+                }
+            }],
+            "attachments": [{
+                "color": payload.event.color(),
+                "fallback": payload.title()
+            }]
+        });
+
+        self.with_sender(payload, message)
+    }
+
+    /// Add the channel, username and icon to `message`
+    fn with_sender(
+        &self,
+        payload: &NotificationPayload,
+        mut message: serde_json::Value,
+    ) -> serde_json::Value {
         // Note: For Incoming Webhooks, the channel is pre-configured in the webhook URL
         // and cannot be overridden in the payload. The channel config is ignored for
         // Incoming Webhooks but may be used in the future for other Slack integration methods.
+        // A channel picked by a namespace route is sent anyway, for legacy webhooks
+        // and Slack-compatible endpoints that honour it.
+        if let Some(channel) = &payload.slack_channel {
+            message["channel"] = json!(channel);
+        }
 
         // Add username if configured
         if let Some(username) = &self.config.username {
@@ -213,7 +302,10 @@ impl Notifier for SlackNotifier {
             .as_ref()
             .ok_or_else(|| anyhow!("Slack webhook URL not configured"))?;
 
-        let message = self.build_message(payload);
+        let message = match template::render_slack_template(payload).await {
+            Some(text) => self.build_templated_message(payload, &text),
+            None => self.build_message(payload),
+        };
 
         debug!("Sending Slack notification to: {}", webhook_url);
         debug!("Webhook URL length: {} chars", webhook_url.len());
@@ -353,6 +445,36 @@ mod tests {
         assert!(!message["blocks"].as_array().unwrap().is_empty());
     }
 
+    #[test]
+    fn test_build_templated_message() {
+        let config = SlackConfig {
+            enabled: true,
+            webhook_url: Some("https://hooks.slack.com/services/TEST".to_string()),
+            channel: None,
+            username: None,
+            icon_emoji: None,
+        };
+        let notifier = SlackNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "nginx".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+        let payload = NotificationPayload::new(NotificationEvent::UpdateCompleted, deployment);
+
+        let message = notifier.build_templated_message(&payload, "*nginx* updated");
+
+        assert_eq!(message["text"], "*nginx* updated");
+        assert_eq!(message["blocks"][0]["text"]["text"], "*nginx* updated");
+        assert_eq!(message["attachments"][0]["color"], "#4CAF50");
+        assert_eq!(message["username"], "Headwind");
+        assert_eq!(message["icon_emoji"], ":robot_face:");
+    }
+
     #[test]
     fn test_build_message_with_error() {
         let config = SlackConfig {
@@ -382,4 +504,186 @@ mod tests {
         let message_str = serde_json::to_string(&message).unwrap();
         assert!(message_str.contains("Failed to pull image"));
     }
+
+    #[test]
+    fn test_build_message_with_routed_channel() {
+        let config = SlackConfig {
+            enabled: true,
+            webhook_url: Some("https://hooks.slack.com/services/TEST".to_string()),
+            channel: Some("#deployments".to_string()),
+            username: None,
+            icon_emoji: None,
+        };
+
+        let notifier = SlackNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "nginx".to_string(),
+            namespace: "payments".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+
+        let mut payload = NotificationPayload::new(NotificationEvent::UpdateCompleted, deployment);
+        assert!(notifier.build_message(&payload).get("channel").is_none());
+
+        payload.slack_channel = Some("#payments-deploys".to_string());
+        assert_eq!(
+            notifier.build_message(&payload)["channel"],
+            "#payments-deploys"
+        );
+    }
+
+    #[test]
+    fn test_status_page_button() {
+        let config = SlackConfig {
+            enabled: true,
+            webhook_url: Some("https://hooks.slack.com/services/TEST".to_string()),
+            channel: None,
+            username: None,
+            icon_emoji: None,
+        };
+
+        let notifier = SlackNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "nginx".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:2.0.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+
+        let payload = NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment)
+            .with_requires_approval(true)
+            .with_status_page_url("https://status.example.com");
+
+        let message = notifier.build_message(&payload);
+
+        let message_str = serde_json::to_string(&message).unwrap();
+        assert!(message_str.contains("View Service Status"));
+        assert!(message_str.contains("https://status.example.com"));
+    }
+
+    #[test]
+    fn test_changelog_link() {
+        let config = SlackConfig {
+            enabled: true,
+            webhook_url: Some("https://hooks.slack.com/services/TEST".to_string()),
+            channel: None,
+            username: None,
+            icon_emoji: None,
+        };
+
+        let notifier = SlackNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "nginx".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:2.0.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+
+        let labels = [
+            (
+                "org.opencontainers.image.source".to_string(),
+                "https://github.com/acme/nginx".to_string(),
+            ),
+            (
+                "org.opencontainers.image.revision".to_string(),
+                "4f2c9e1d8a7b".to_string(),
+            ),
+        ]
+        .into();
+        let payload = NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment)
+            .with_image_labels(&labels);
+
+        let message_str = serde_json::to_string(&notifier.build_message(&payload)).unwrap();
+        assert!(
+            message_str.contains("<https://github.com/acme/nginx/commit/4f2c9e1d8a7b|4f2c9e1>")
+        );
+    }
+
+    #[test]
+    fn test_chart_changelog() {
+        let config = SlackConfig {
+            enabled: true,
+            webhook_url: Some("https://hooks.slack.com/services/TEST".to_string()),
+            channel: None,
+            username: None,
+            icon_emoji: None,
+        };
+
+        let notifier = SlackNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "ingress-nginx".to_string(),
+            namespace: "ingress".to_string(),
+            current_image: "ingress-nginx:4.10.0".to_string(),
+            new_image: "ingress-nginx:4.11.0".to_string(),
+            container: None,
+            resource_kind: Some("HelmRelease".to_string()),
+        };
+
+        let long_changelog = "- Fixed: something\n".repeat(200);
+        let payload = NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment)
+            .with_changelog(long_changelog);
+
+        let message_str = serde_json::to_string(&notifier.build_message(&payload)).unwrap();
+        assert!(message_str.contains("*Changes:*"));
+        // Cut to MAX_CHANGELOG_CHARS so the block stays under Slack's 3000 character limit
+        let excerpt = payload.changelog_excerpt().unwrap();
+        assert_eq!(
+            excerpt.chars().count(),
+            crate::notifications::MAX_CHANGELOG_CHARS + 2
+        );
+        assert!(excerpt.ends_with('…'));
+    }
+
+    #[test]
+    fn test_impact_field() {
+        let config = SlackConfig {
+            enabled: true,
+            webhook_url: Some("https://hooks.slack.com/services/TEST".to_string()),
+            channel: None,
+            username: None,
+            icon_emoji: None,
+        };
+
+        let notifier = SlackNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "nginx".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+
+        let impact = crate::models::UpdateImpact {
+            replica_count: 3,
+            disruption_budget: Some(crate::models::PdbSummary {
+                name: "nginx".to_string(),
+                min_available: Some("3".to_string()),
+                max_unavailable: None,
+                disruptions_allowed: 0,
+            }),
+            max_unavailable: Some(1),
+            estimated_downtime_seconds: None,
+        };
+        let payload =
+            NotificationPayload::new(NotificationEvent::PdbWarning, deployment).with_impact(impact);
+
+        let message_str = serde_json::to_string(&notifier.build_message(&payload)).unwrap();
+        assert!(message_str.contains(
+            "*Impact:*\\n3 replicas, 1 unavailable at a time, PDB nginx (0 disruptions allowed) ⚠️ exceeds PDB"
+        ));
+        assert!(message_str.contains("[PDB WARNING]"));
+    }
 }
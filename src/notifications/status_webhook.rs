@@ -0,0 +1,394 @@
+//! Status webhooks: every phase change of an UpdateRequest, sent to the
+//! resource's own endpoint.
+//!
+//! `headwind.sh/status-webhook-url` on a workload names an HTTPS URL that
+//! receives a `POST` whenever one of its UpdateRequests changes phase, for
+//! external orchestration such as Terraform or Ansible. It is independent of
+//! the global webhook notifier and of observers:
+//!
+//! ```json
+//! {"event": "status_changed", "update_request": {...}, "previous_phase": "Pending", "new_phase": "Approved"}
+//! ```
+//!
+//! Phase changes are found by watching UpdateRequests, so changes made by
+//! any component, or with kubectl, are reported. Delivery is attempted three
+//! times with exponential backoff.
+
+use crate::metrics::{STATUS_WEBHOOK_CALLS_TOTAL, STATUS_WEBHOOK_FAILURES_TOTAL};
+use crate::models::annotations;
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use anyhow::{Context, Result, anyhow, bail};
+use futures::StreamExt;
+use kube::runtime::{WatchStreamExt, watcher};
+use kube::{Client, ResourceExt};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Attempts to deliver one phase change
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry, doubled for each further one
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Timeout of one request to a status webhook
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The URL of a `headwind.sh/status-webhook-url` value, which must be HTTPS
+pub fn parse_status_webhook_url(value: &str) -> Result<reqwest::Url> {
+    let url = reqwest::Url::parse(value.trim())
+        .with_context(|| format!("Invalid status webhook URL '{}'", value))?;
+    if url.scheme() != "https" {
+        bail!("Status webhook URL '{}' must use HTTPS", value);
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        bail!("Status webhook URL '{}' has no host", value);
+    }
+    Ok(url)
+}
+
+/// Phase of `update_request`; one without a status is pending
+fn phase(update_request: &UpdateRequest) -> UpdatePhase {
+    update_request
+        .status
+        .as_ref()
+        .map(|status| status.phase.clone())
+        .unwrap_or_default()
+}
+
+/// Last seen phase of each UpdateRequest, by UID
+#[derive(Default)]
+pub struct PhaseTracker {
+    phases: HashMap<String, UpdatePhase>,
+}
+
+impl PhaseTracker {
+    /// Record the phase of `update_request`, returning the previous phase if it
+    /// changed. An UpdateRequest seen for the first time starts as Pending,
+    /// unless `initial` is set while the watch lists existing objects.
+    pub fn observe(
+        &mut self,
+        update_request: &UpdateRequest,
+        initial: bool,
+    ) -> Option<UpdatePhase> {
+        let uid = update_request.uid()?;
+        let phase = phase(update_request);
+        let previous = match self.phases.insert(uid, phase.clone()) {
+            Some(previous) => previous,
+            None if initial => return None,
+            None => UpdatePhase::Pending,
+        };
+        (previous != phase).then_some(previous)
+    }
+
+    pub fn forget(&mut self, update_request: &UpdateRequest) {
+        if let Some(uid) = update_request.uid() {
+            self.phases.remove(&uid);
+        }
+    }
+}
+
+/// Body of the `POST` for a phase change
+pub fn status_changed_body(
+    update_request: &UpdateRequest,
+    previous_phase: &UpdatePhase,
+    new_phase: &UpdatePhase,
+) -> Value {
+    json!({
+        "event": "status_changed",
+        "update_request": update_request,
+        "previous_phase": previous_phase,
+        "new_phase": new_phase,
+    })
+}
+
+/// Posts phase changes to status webhooks
+pub struct StatusWebhookSender {
+    http: reqwest::Client,
+    initial_backoff: Duration,
+}
+
+impl StatusWebhookSender {
+    pub fn new() -> Result<Self> {
+        Self::with_backoff(INITIAL_BACKOFF)
+    }
+
+    pub fn with_backoff(initial_backoff: Duration) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Self {
+            http,
+            initial_backoff,
+        })
+    }
+
+    /// Post `body` to `url`, retrying with exponential backoff
+    pub async fn send(&self, url: &str, body: &Value) -> Result<()> {
+        STATUS_WEBHOOK_CALLS_TOTAL.inc();
+        let mut backoff = self.initial_backoff;
+        let mut last_error = None;
+        for attempt in 1..=MAX_ATTEMPTS {
+            if attempt > 1 {
+                debug!(
+                    "Retrying status webhook {} (attempt {}/{})",
+                    url, attempt, MAX_ATTEMPTS
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            match self.http.post(url).json(body).send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    last_error = Some(anyhow!("Status webhook returned {}", response.status()))
+                },
+                Err(e) => last_error = Some(anyhow!("HTTP request failed: {}", e)),
+            }
+        }
+        STATUS_WEBHOOK_FAILURES_TOTAL.inc();
+        Err(last_error.unwrap_or_else(|| anyhow!("Status webhook failed")))
+    }
+}
+
+/// `headwind.sh/status-webhook-url` of the resource `update_request` targets
+async fn status_webhook_url(
+    client: &Client,
+    update_request: &UpdateRequest,
+) -> Result<Option<String>> {
+    let target = &update_request.spec.target_ref;
+    let annotations = super::observers::workload_annotations(
+        client.clone(),
+        &target.kind,
+        &target.namespace,
+        &target.name,
+    )
+    .await?;
+    let Some(value) = annotations
+        .as_ref()
+        .and_then(|a| a.get(annotations::STATUS_WEBHOOK_URL))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(parse_status_webhook_url(value)?.to_string()))
+}
+
+/// Report a phase change of `update_request` to its resource's status webhook
+async fn report(
+    client: Client,
+    sender: std::sync::Arc<StatusWebhookSender>,
+    update_request: UpdateRequest,
+    previous_phase: UpdatePhase,
+) {
+    let namespace = update_request.namespace().unwrap_or_default();
+    let name = update_request.name_any();
+    let url = match status_webhook_url(&client, &update_request).await {
+        Ok(Some(url)) => url,
+        Ok(None) => return,
+        Err(e) => {
+            warn!(
+                "Not reporting phase change of UpdateRequest {}/{}: {}",
+                namespace, name, e
+            );
+            return;
+        },
+    };
+
+    let new_phase = phase(&update_request);
+    let body = status_changed_body(&update_request, &previous_phase, &new_phase);
+    match sender.send(&url, &body).await {
+        Ok(()) => debug!(
+            "Reported UpdateRequest {}/{} {:?} -> {:?} to {}",
+            namespace, name, previous_phase, new_phase, url
+        ),
+        Err(e) => warn!(
+            "Failed to report UpdateRequest {}/{} {:?} -> {:?} to {}: {}",
+            namespace, name, previous_phase, new_phase, url, e
+        ),
+    }
+}
+
+/// Watch UpdateRequests and report their phase changes
+pub fn start_status_webhooks(client: Client) -> Result<JoinHandle<()>> {
+    let sender = std::sync::Arc::new(StatusWebhookSender::new()?);
+    info!("Reporting UpdateRequest phase changes to headwind.sh/status-webhook-url");
+    Ok(tokio::spawn(async move {
+        let api = crate::controller::watched_api::<UpdateRequest>(client.clone());
+        let mut tracker = PhaseTracker::default();
+        let mut events = watcher(api, watcher::Config::default())
+            .default_backoff()
+            .boxed();
+
+        while let Some(event) = events.next().await {
+            let (update_request, initial) = match event {
+                Ok(watcher::Event::Apply(ur)) => (ur, false),
+                Ok(watcher::Event::InitApply(ur)) => (ur, true),
+                Ok(watcher::Event::Delete(ur)) => {
+                    tracker.forget(&ur);
+                    continue;
+                },
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("UpdateRequest watch for status webhooks failed: {}", e);
+                    continue;
+                },
+            };
+            if let Some(previous_phase) = tracker.observe(&update_request, initial) {
+                tokio::spawn(report(
+                    client.clone(),
+                    sender.clone(),
+                    update_request,
+                    previous_phase,
+                ));
+            }
+        }
+        warn!("UpdateRequest watch for status webhooks ended");
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing;
+    use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    struct MockWebhook {
+        /// Requests answered with an error before succeeding
+        failures: usize,
+        calls: AtomicUsize,
+        received: Mutex<Vec<Value>>,
+    }
+
+    /// Mock status webhook failing the first `failures` requests
+    async fn start_webhook(failures: usize) -> (String, Arc<MockWebhook>) {
+        let mock = Arc::new(MockWebhook {
+            failures,
+            calls: AtomicUsize::new(0),
+            received: Mutex::new(Vec::new()),
+        });
+        let app = Router::new()
+            .route(
+                "/headwind-events",
+                post(
+                    |State(mock): State<Arc<MockWebhook>>, Json(body): Json<Value>| async move {
+                        mock.received.lock().unwrap().push(body);
+                        if mock.calls.fetch_add(1, Ordering::SeqCst) < mock.failures {
+                            StatusCode::SERVICE_UNAVAILABLE
+                        } else {
+                            StatusCode::OK
+                        }
+                    },
+                ),
+            )
+            .with_state(mock.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/headwind-events", addr), mock)
+    }
+
+    fn update_request(phase: Option<UpdatePhase>) -> UpdateRequest {
+        let mut update_request = match phase {
+            Some(phase) => testing::update_request_in_phase("default", "web-nginx-1-26-0", phase),
+            None => testing::update_request("default", "web-nginx-1-26-0"),
+        };
+        update_request.metadata.uid = Some("5f2c".to_string());
+        update_request
+    }
+
+    #[test]
+    fn test_parse_status_webhook_url() {
+        assert_eq!(
+            parse_status_webhook_url("https://my-system.example.com/headwind-events")
+                .unwrap()
+                .as_str(),
+            "https://my-system.example.com/headwind-events"
+        );
+        assert!(parse_status_webhook_url("http://my-system.example.com/hook").is_err());
+        assert!(parse_status_webhook_url("my-system.example.com/hook").is_err());
+        assert!(parse_status_webhook_url("https://").is_err());
+    }
+
+    #[test]
+    fn test_phase_tracker() {
+        let mut tracker = PhaseTracker::default();
+
+        // Existing UpdateRequests listed at startup are only recorded
+        assert_eq!(
+            tracker.observe(&update_request(Some(UpdatePhase::Approved)), true),
+            None
+        );
+        assert_eq!(
+            tracker.observe(&update_request(Some(UpdatePhase::Approved)), false),
+            None
+        );
+        assert_eq!(
+            tracker.observe(&update_request(Some(UpdatePhase::Completed)), false),
+            Some(UpdatePhase::Approved)
+        );
+
+        // A new UpdateRequest starts as Pending
+        tracker.forget(&update_request(None));
+        assert_eq!(tracker.observe(&update_request(None), false), None);
+        tracker.forget(&update_request(None));
+        assert_eq!(
+            tracker.observe(&update_request(Some(UpdatePhase::Rejected)), false),
+            Some(UpdatePhase::Pending)
+        );
+    }
+
+    #[test]
+    fn test_status_changed_body() {
+        let update_request = update_request(Some(UpdatePhase::Approved));
+        let body = status_changed_body(
+            &update_request,
+            &UpdatePhase::Pending,
+            &UpdatePhase::Approved,
+        );
+
+        assert_eq!(body["event"], "status_changed");
+        assert_eq!(body["previous_phase"], "Pending");
+        assert_eq!(body["new_phase"], "Approved");
+        assert_eq!(
+            body["update_request"]["metadata"]["name"],
+            "web-nginx-1-26-0"
+        );
+        assert_eq!(body["update_request"]["status"]["phase"], "Approved");
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_until_delivered() {
+        let (url, mock) = start_webhook(2).await;
+        let sender = StatusWebhookSender::with_backoff(Duration::from_millis(1)).unwrap();
+        let body = status_changed_body(
+            &update_request(Some(UpdatePhase::Approved)),
+            &UpdatePhase::Pending,
+            &UpdatePhase::Approved,
+        );
+
+        sender.send(&url, &body).await.unwrap();
+
+        let received = mock.received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        assert!(received.iter().all(|request| *request == body));
+    }
+
+    #[tokio::test]
+    async fn test_send_gives_up_after_three_attempts() {
+        let (url, mock) = start_webhook(usize::MAX).await;
+        let sender = StatusWebhookSender::with_backoff(Duration::from_millis(1)).unwrap();
+        let failures = STATUS_WEBHOOK_FAILURES_TOTAL.get();
+
+        let result = sender.send(&url, &json!({"event": "status_changed"})).await;
+
+        assert!(result.is_err());
+        assert_eq!(mock.calls.load(Ordering::SeqCst), 3);
+        // Other tests run concurrently, so only a lower bound holds
+        assert!(STATUS_WEBHOOK_FAILURES_TOTAL.get() > failures);
+    }
+}
@@ -85,7 +85,20 @@ impl TeamsNotifier {
             }));
         }
 
-        let sections = vec![json!({
+        // Link the changes in the new image if its labels were fetched
+        match (&payload.changelog_url, payload.short_revision()) {
+            (Some(url), revision) => facts.push(json!({
+                "title": "Changelog",
+                "value": format!("[{}]({})", revision.unwrap_or("View changes"), url)
+            })),
+            (None, Some(revision)) => facts.push(json!({
+                "title": "Revision",
+                "value": revision
+            })),
+            (None, None) => {},
+        }
+
+        let mut sections = vec![json!({
             "activityTitle": format!("{} {}", emoji, title),
             "activitySubtitle": format!("Event: {}", payload.event.as_str()),
             "activityImage": "https://raw.githubusercontent.com/kubernetes/kubernetes/master/logo/logo.png",
@@ -93,6 +106,15 @@ impl TeamsNotifier {
             "markdown": true
         })];
 
+        // Add the chart changelog of Helm updates
+        if let Some(changelog) = payload.changelog_excerpt() {
+            sections.push(json!({
+                "title": "Changes",
+                "text": changelog,
+                "markdown": true
+            }));
+        }
+
         // Build actions array for buttons
         let mut potential_actions = Vec::new();
 
@@ -120,6 +142,18 @@ impl TeamsNotifier {
             }));
         }
 
+        // Add "View Service Status" button if a status page is annotated
+        if let Some(status_page_url) = &payload.status_page_url {
+            potential_actions.push(json!({
+                "@type": "OpenUri",
+                "name": "View Service Status",
+                "targets": [{
+                    "os": "default",
+                    "uri": status_page_url
+                }]
+            }));
+        }
+
         let mut card = json!({
             "@type": "MessageCard",
             "@context": "https://schema.org/extensions",
@@ -301,4 +335,94 @@ mod tests {
         assert!(card_str.contains("admin@example.com"));
         assert!(card_str.contains("Approved By"));
     }
+
+    #[test]
+    fn test_status_page_button() {
+        let config = TeamsConfig {
+            enabled: true,
+            webhook_url: Some("https://outlook.office.com/webhook/test".to_string()),
+        };
+
+        let notifier = TeamsNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "nginx".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:2.0.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+
+        let payload = NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment)
+            .with_requires_approval(true)
+            .with_status_page_url("https://status.example.com");
+
+        let card = notifier.build_adaptive_card(&payload);
+
+        let actions = card["potentialAction"].as_array().unwrap();
+        assert!(actions.iter().any(|a| a["name"] == "View Service Status"
+            && a["targets"][0]["uri"] == "https://status.example.com"));
+    }
+
+    #[test]
+    fn test_changelog_link() {
+        let config = TeamsConfig {
+            enabled: true,
+            webhook_url: Some("https://outlook.office.com/webhook/TEST".to_string()),
+        };
+
+        let notifier = TeamsNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "nginx".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:2.0.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+
+        let labels = [(
+            "org.opencontainers.image.description".to_string(),
+            "https://github.com/acme/nginx/releases/tag/2.0.0".to_string(),
+        )]
+        .into();
+        let payload = NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment)
+            .with_image_labels(&labels);
+
+        let card = notifier.build_adaptive_card(&payload);
+        let facts = card["sections"][0]["facts"].as_array().unwrap();
+        assert!(facts.iter().any(|f| f["title"] == "Changelog"
+            && f["value"] == "[View changes](https://github.com/acme/nginx/releases/tag/2.0.0)"));
+    }
+
+    #[test]
+    fn test_chart_changelog_section() {
+        let config = TeamsConfig {
+            enabled: true,
+            webhook_url: Some("https://outlook.office.com/webhook/TEST".to_string()),
+        };
+
+        let notifier = TeamsNotifier::new(config).unwrap();
+
+        let deployment = DeploymentInfo {
+            name: "ingress-nginx".to_string(),
+            namespace: "ingress".to_string(),
+            current_image: "ingress-nginx:4.10.0".to_string(),
+            new_image: "ingress-nginx:4.11.0".to_string(),
+            container: None,
+            resource_kind: Some("HelmRelease".to_string()),
+        };
+
+        let payload = NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment)
+            .with_changelog("- Added: Topology spread constraints");
+
+        let card = notifier.build_adaptive_card(&payload);
+        assert_eq!(card["sections"][1]["title"], "Changes");
+        assert_eq!(
+            card["sections"][1]["text"],
+            "- Added: Topology spread constraints"
+        );
+    }
 }
@@ -0,0 +1,455 @@
+use super::{NotificationPayload, Notifier, TelegramConfig, TelegramParseMode};
+use anyhow::{Context, Result, anyhow};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+const TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+/// Attempts per message, including retries after `429 Too Many Requests`
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Error body of the Bot API, e.g. `{"ok":false,"error_code":429,"parameters":{"retry_after":5}}`
+#[derive(Debug, Default, Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    parameters: Option<ResponseParameters>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ResponseParameters {
+    retry_after: Option<u64>,
+}
+
+pub struct TelegramNotifier {
+    config: TelegramConfig,
+    client: Client,
+    api_url: String,
+}
+
+impl TelegramNotifier {
+    pub fn new(config: TelegramConfig) -> Result<Self> {
+        if !config.enabled {
+            return Err(anyhow!("Telegram notifier is disabled"));
+        }
+
+        if config.bot_token.is_empty() {
+            return Err(anyhow!("Telegram bot token is required"));
+        }
+
+        if config.chat_id.is_empty() {
+            return Err(anyhow!("Telegram chat ID is required"));
+        }
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self {
+            config,
+            client,
+            api_url: TELEGRAM_API_URL.to_string(),
+        })
+    }
+
+    #[cfg(test)]
+    fn with_api_url(mut self, api_url: impl Into<String>) -> Self {
+        self.api_url = api_url.into();
+        self
+    }
+
+    /// Build the message text in the configured parse mode
+    fn build_message(&self, payload: &NotificationPayload) -> String {
+        let mode = self.config.parse_mode;
+        let title = payload.title();
+        // Titles are "<event>: <kind> <namespace>/<name>"
+        let (heading, resource) = title.split_once(": ").unwrap_or((&title, ""));
+
+        let mut lines = vec![format!(
+            "{} {}: {}",
+            payload.event.emoji(),
+            escape(mode, heading),
+            bold(mode, resource)
+        )];
+
+        lines.push(format!(
+            "Image: {} → {}",
+            code(mode, &payload.deployment.current_image),
+            code(mode, &payload.deployment.new_image)
+        ));
+
+        if let Some(container) = &payload.deployment.container {
+            lines.push(format!("Container: {}", code(mode, container)));
+        }
+
+        if let Some(policy) = &payload.policy {
+            lines.push(format!("Policy: {}", escape(mode, policy)));
+        }
+
+        if payload.requires_approval == Some(true) {
+            lines.push("Approval required".to_string());
+        }
+
+        if let Some(approver) = &payload.approved_by {
+            lines.push(format!("Approved by: {}", escape(mode, approver)));
+        }
+
+        if let Some(reason) = &payload.rejection_reason {
+            lines.push(format!("Reason: {}", escape(mode, reason)));
+        }
+
+        if let Some(error) = &payload.error_message {
+            lines.push(format!("Error: {}", escape(mode, error)));
+        }
+
+        if let Some(revision) = payload.short_revision() {
+            lines.push(format!("Revision: {}", code(mode, revision)));
+        }
+
+        let mut links = Vec::new();
+        if let Some(ui_url) = &payload.ui_url {
+            links.push(link(mode, "View in Headwind", ui_url));
+        }
+        if let Some(status_page_url) = &payload.status_page_url {
+            links.push(link(mode, "Service Status", status_page_url));
+        }
+        if let Some(changelog_url) = &payload.changelog_url {
+            links.push(link(mode, "Changelog", changelog_url));
+        }
+        if !links.is_empty() {
+            lines.push(String::new());
+            lines.push(links.join(" | "));
+        }
+
+        lines.join("\n")
+    }
+
+    /// Body of the `sendMessage` request
+    fn build_request(&self, payload: &NotificationPayload) -> serde_json::Value {
+        json!({
+            "chat_id": self.config.chat_id,
+            "text": self.build_message(payload),
+            "parse_mode": self.config.parse_mode.as_str(),
+            "disable_web_page_preview": true,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for TelegramNotifier {
+    async fn send(&self, payload: &NotificationPayload) -> Result<()> {
+        let url = format!("{}/bot{}/sendMessage", self.api_url, self.config.bot_token);
+        let body = self.build_request(payload);
+
+        let mut attempt = 1;
+        loop {
+            // The URL contains the bot token, so keep it out of error messages
+            let response = self
+                .client
+                .post(&url)
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| {
+                    anyhow!("Failed to send Telegram notification: {}", e.without_url())
+                })?;
+
+            let status = response.status();
+            if status.is_success() {
+                debug!("Telegram notification sent successfully");
+                return Ok(());
+            }
+
+            let error: ApiError = response.json().await.unwrap_or_default();
+            let retry_after = error.parameters.and_then(|p| p.retry_after);
+            if status == StatusCode::TOO_MANY_REQUESTS && attempt < MAX_ATTEMPTS {
+                let retry_after = retry_after.unwrap_or(1);
+                warn!(
+                    "Telegram rate limit hit, retrying in {}s (attempt {}/{})",
+                    retry_after, attempt, MAX_ATTEMPTS
+                );
+                tokio::time::sleep(Duration::from_secs(retry_after)).await;
+                attempt += 1;
+                continue;
+            }
+
+            return Err(anyhow!(
+                "Telegram API returned error {}: {}",
+                status,
+                error
+                    .description
+                    .unwrap_or_else(|| "no description".to_string())
+            ));
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "Telegram"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.config.enabled && !self.config.bot_token.is_empty() && !self.config.chat_id.is_empty()
+    }
+}
+
+/// Escape text outside of entities
+fn escape(mode: TelegramParseMode, text: &str) -> String {
+    match mode {
+        // Legacy Markdown only treats these as special
+        TelegramParseMode::Markdown => text
+            .chars()
+            .flat_map(|c| match c {
+                '_' | '*' | '`' | '[' => vec!['\\', c],
+                _ => vec![c],
+            })
+            .collect(),
+        TelegramParseMode::Html => html_escape(text),
+    }
+}
+
+fn bold(mode: TelegramParseMode, text: &str) -> String {
+    match mode {
+        // Entities can't be nested in legacy Markdown, so only '*' needs removing
+        TelegramParseMode::Markdown => format!("*{}*", text.replace('*', "")),
+        TelegramParseMode::Html => format!("<b>{}</b>", html_escape(text)),
+    }
+}
+
+fn code(mode: TelegramParseMode, text: &str) -> String {
+    match mode {
+        TelegramParseMode::Markdown => format!("`{}`", text.replace('`', "'")),
+        TelegramParseMode::Html => format!("<code>{}</code>", html_escape(text)),
+    }
+}
+
+fn link(mode: TelegramParseMode, text: &str, url: &str) -> String {
+    match mode {
+        TelegramParseMode::Markdown => format!("[{}]({})", text, url.replace(')', "%29")),
+        TelegramParseMode::Html => {
+            format!("<a href=\"{}\">{}</a>", html_escape(url), html_escape(text))
+        },
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::{DeploymentInfo, NotificationEvent};
+    use axum::{
+        Json, Router,
+        extract::{Path, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        routing::post,
+    };
+    use serde_json::Value;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct MockTelegram {
+        /// Status and body returned for each request, then 200 once empty
+        responses: VecDeque<(StatusCode, Value)>,
+        requests: Vec<(String, Value)>,
+    }
+
+    type Shared = Arc<Mutex<MockTelegram>>;
+
+    async fn send_message(
+        State(state): State<Shared>,
+        Path(bot): Path<String>,
+        Json(body): Json<Value>,
+    ) -> Response {
+        let mut state = state.lock().unwrap();
+        state.requests.push((bot, body));
+        match state.responses.pop_front() {
+            Some((status, body)) => (status, Json(body)).into_response(),
+            None => Json(json!({ "ok": true, "result": { "message_id": 1 } })).into_response(),
+        }
+    }
+
+    async fn start_mock_telegram(responses: Vec<(StatusCode, Value)>) -> (String, Shared) {
+        let state = Arc::new(Mutex::new(MockTelegram {
+            responses: responses.into(),
+            ..Default::default()
+        }));
+        let app = Router::new()
+            .route("/{bot}/sendMessage", post(send_message))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}", addr), state)
+    }
+
+    fn rate_limited(retry_after: u64) -> (StatusCode, Value) {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            json!({
+                "ok": false,
+                "error_code": 429,
+                "description": format!("Too Many Requests: retry after {}", retry_after),
+                "parameters": { "retry_after": retry_after },
+            }),
+        )
+    }
+
+    fn config(parse_mode: TelegramParseMode) -> TelegramConfig {
+        TelegramConfig {
+            enabled: true,
+            bot_token: "123:ABC".to_string(),
+            chat_id: "-100200300".to_string(),
+            parse_mode,
+        }
+    }
+
+    fn payload() -> NotificationPayload {
+        let deployment = DeploymentInfo {
+            name: "web_api".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+            container: Some("nginx".to_string()),
+            resource_kind: None,
+        };
+
+        NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment)
+            .with_policy("minor")
+            .with_requires_approval(true)
+            .with_ui_url("https://headwind.example.com/updates/production/web-api-1-26-0")
+    }
+
+    #[test]
+    fn test_telegram_notifier_validation() {
+        assert!(TelegramNotifier::new(config(TelegramParseMode::Markdown)).is_ok());
+
+        let mut disabled = config(TelegramParseMode::Markdown);
+        disabled.enabled = false;
+        assert!(TelegramNotifier::new(disabled).is_err());
+
+        let mut no_token = config(TelegramParseMode::Markdown);
+        no_token.bot_token = String::new();
+        assert!(TelegramNotifier::new(no_token).is_err());
+
+        let mut no_chat = config(TelegramParseMode::Markdown);
+        no_chat.chat_id = String::new();
+        assert!(TelegramNotifier::new(no_chat).is_err());
+    }
+
+    #[test]
+    fn test_build_markdown_message() {
+        let notifier = TelegramNotifier::new(config(TelegramParseMode::Markdown)).unwrap();
+        let payload = payload().with_error("pull failed: no_such_tag");
+
+        let message = notifier.build_message(&payload);
+
+        assert!(
+            message.starts_with("📦 Update request created: *Deployment production/web_api*\n")
+        );
+        assert!(message.contains("Image: `nginx:1.25.0` → `nginx:1.26.0`"));
+        assert!(message.contains("Container: `nginx`"));
+        assert!(message.contains("Policy: minor"));
+        assert!(message.contains("Error: pull failed: no\\_such\\_tag"));
+        assert!(message.ends_with(
+            "[View in Headwind](https://headwind.example.com/updates/production/web-api-1-26-0)"
+        ));
+    }
+
+    #[test]
+    fn test_build_html_message() {
+        let notifier = TelegramNotifier::new(config(TelegramParseMode::Html)).unwrap();
+        let payload = payload().with_rejection_reason("breaks <auth> & login");
+
+        let message = notifier.build_message(&payload);
+
+        assert!(message.contains("<b>Deployment production/web_api</b>"));
+        assert!(message.contains("<code>nginx:1.26.0</code>"));
+        assert!(message.contains("Reason: breaks &lt;auth&gt; &amp; login"));
+        assert!(message.contains(
+            "<a href=\"https://headwind.example.com/updates/production/web-api-1-26-0\">View in Headwind</a>"
+        ));
+        assert_eq!(notifier.build_request(&payload)["parse_mode"], "HTML");
+    }
+
+    #[tokio::test]
+    async fn test_send_message() {
+        let (api_url, state) = start_mock_telegram(vec![]).await;
+        let notifier = TelegramNotifier::new(config(TelegramParseMode::Markdown))
+            .unwrap()
+            .with_api_url(api_url);
+
+        notifier.send(&payload()).await.unwrap();
+
+        let state = state.lock().unwrap();
+        assert_eq!(state.requests.len(), 1);
+        let (bot, body) = &state.requests[0];
+        assert_eq!(bot, "bot123:ABC");
+        assert_eq!(body["chat_id"], "-100200300");
+        assert_eq!(body["parse_mode"], "Markdown");
+        assert!(
+            body["text"]
+                .as_str()
+                .unwrap()
+                .contains("*Deployment production/web_api*")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_honors_retry_after() {
+        let (api_url, state) = start_mock_telegram(vec![rate_limited(1)]).await;
+        let notifier = TelegramNotifier::new(config(TelegramParseMode::Markdown))
+            .unwrap()
+            .with_api_url(api_url);
+
+        let started = std::time::Instant::now();
+        notifier.send(&payload()).await.unwrap();
+
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(state.lock().unwrap().requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let responses = (0..MAX_ATTEMPTS).map(|_| rate_limited(0)).collect();
+        let (api_url, state) = start_mock_telegram(responses).await;
+        let notifier = TelegramNotifier::new(config(TelegramParseMode::Markdown))
+            .unwrap()
+            .with_api_url(api_url);
+
+        let error = notifier.send(&payload()).await.unwrap_err();
+
+        assert!(error.to_string().contains("429"));
+        assert_eq!(state.lock().unwrap().requests.len(), MAX_ATTEMPTS as usize);
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let (api_url, _state) = start_mock_telegram(vec![(
+            StatusCode::BAD_REQUEST,
+            json!({ "ok": false, "error_code": 400, "description": "Bad Request: chat not found" }),
+        )])
+        .await;
+        let notifier = TelegramNotifier::new(config(TelegramParseMode::Markdown))
+            .unwrap()
+            .with_api_url(api_url);
+
+        let error = notifier.send(&payload()).await.unwrap_err();
+
+        assert!(error.to_string().contains("chat not found"));
+        assert!(!error.to_string().contains("123:ABC"));
+    }
+}
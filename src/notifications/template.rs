@@ -0,0 +1,255 @@
+//! Custom Slack message templates.
+//!
+//! `slack.messageTemplateConfigMap` in the headwind ConfigMap names a ConfigMap,
+//! in Headwind's namespace, holding message templates. The
+//! `<event>.template` key (e.g. `update_completed.template`) is used for that
+//! event and the `template` key for every other one. Templates replace
+//! `{{ variable }}` placeholders with the variables in [`TEMPLATE_VARIABLES`].
+//!
+//! A missing ConfigMap or key, or a template that fails to render, falls back
+//! to the built-in message with a warning.
+
+use super::{NotificationEvent, NotificationPayload};
+use crate::config::{get_cached_config, headwind_namespace};
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{Api, Client};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use tracing::warn;
+
+/// Variables available to message templates
+pub const TEMPLATE_VARIABLES: &[&str] = &[
+    "resource_name",
+    "namespace",
+    "current_image",
+    "new_image",
+    "policy",
+    "requires_approval",
+    "update_request_name",
+    "dashboard_url",
+];
+
+#[derive(Debug, Error, PartialEq)]
+pub enum TemplateError {
+    #[error("Unclosed '{{{{' at byte {0}")]
+    Unclosed(usize),
+    #[error("Unknown template variable '{0}'")]
+    UnknownVariable(String),
+}
+
+/// Template variables for `payload`. Unset values render as empty strings.
+pub fn template_context(payload: &NotificationPayload) -> BTreeMap<&'static str, String> {
+    let optional = |value: &Option<String>| value.clone().unwrap_or_default();
+    BTreeMap::from([
+        ("resource_name", payload.deployment.name.clone()),
+        ("namespace", payload.deployment.namespace.clone()),
+        ("current_image", payload.deployment.current_image.clone()),
+        ("new_image", payload.deployment.new_image.clone()),
+        ("policy", optional(&payload.policy)),
+        (
+            "requires_approval",
+            payload
+                .requires_approval
+                .map(|required| required.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "update_request_name",
+            optional(&payload.update_request_name),
+        ),
+        ("dashboard_url", optional(&payload.ui_url)),
+    ])
+}
+
+/// Replace the `{{ variable }}` placeholders of `template` with their values
+pub fn render(template: &str, context: &BTreeMap<&str, String>) -> Result<String, TemplateError> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let offset = template.len() - rest.len() + start;
+        let end = rest[start..]
+            .find("}}")
+            .ok_or(TemplateError::Unclosed(offset))?;
+        let name = rest[start + 2..start + end].trim();
+        let value = context
+            .get(name)
+            .ok_or_else(|| TemplateError::UnknownVariable(name.to_string()))?;
+        output.push_str(value);
+        rest = &rest[start + end + 2..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// ConfigMap key of the template for `event`, e.g. `update_completed.template`
+pub fn template_key(event: NotificationEvent) -> String {
+    let name = serde_json::to_value(event)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default();
+    format!("{}.template", name)
+}
+
+/// The template for `event` in ConfigMap `data`, falling back to `template`
+pub fn select_template(data: &BTreeMap<String, String>, event: NotificationEvent) -> Option<&str> {
+    data.get(&template_key(event))
+        .or_else(|| data.get("template"))
+        .map(String::as_str)
+        .filter(|template| !template.trim().is_empty())
+}
+
+/// The Slack message text for `payload` from the configured template, or
+/// `None` to use the built-in message
+pub async fn render_slack_template(payload: &NotificationPayload) -> Option<String> {
+    let name = get_cached_config()?
+        .notifications
+        .slack
+        .message_template_configmap?;
+
+    let namespace = headwind_namespace();
+    let data = match template_configmap(&namespace, &name).await {
+        Ok(data) => data,
+        Err(e) => {
+            warn!(
+                "Failed to read Slack template ConfigMap {}/{}, using the built-in message: {}",
+                namespace, name, e
+            );
+            return None;
+        },
+    };
+
+    let template = select_template(&data, payload.event)?;
+    match render(template, &template_context(payload)) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            warn!(
+                "Failed to render Slack template {} from ConfigMap {}/{}, using the built-in message: {}",
+                template_key(payload.event),
+                namespace,
+                name,
+                e
+            );
+            None
+        },
+    }
+}
+
+async fn template_configmap(
+    namespace: &str,
+    name: &str,
+) -> Result<BTreeMap<String, String>, kube::Error> {
+    let client = Client::try_default().await?;
+    let config_maps: Api<ConfigMap> = Api::namespaced(client, namespace);
+    Ok(config_maps.get(name).await?.data.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::DeploymentInfo;
+
+    fn payload() -> NotificationPayload {
+        let deployment = DeploymentInfo {
+            name: "nginx".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+            container: None,
+            resource_kind: None,
+        };
+        let mut payload =
+            NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment)
+                .with_policy("minor")
+                .with_requires_approval(true)
+                .with_update_request("nginx-1-26-0");
+        payload.ui_url = Some("https://headwind.example.com/updates/nginx-1-26-0".to_string());
+        payload
+    }
+
+    #[test]
+    fn test_render_all_variables() {
+        let template = TEMPLATE_VARIABLES
+            .iter()
+            .map(|name| format!("{}={{{{ {} }}}}", name, name))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let rendered = render(&template, &template_context(&payload())).unwrap();
+
+        assert_eq!(
+            rendered,
+            "resource_name=nginx\n\
+             namespace=production\n\
+             current_image=nginx:1.25.0\n\
+             new_image=nginx:1.26.0\n\
+             policy=minor\n\
+             requires_approval=true\n\
+             update_request_name=nginx-1-26-0\n\
+             dashboard_url=https://headwind.example.com/updates/nginx-1-26-0"
+        );
+    }
+
+    #[test]
+    fn test_render_unset_values_and_spacing() {
+        let payload = NotificationPayload::new(
+            NotificationEvent::UpdateDetected,
+            payload().deployment.clone(),
+        );
+
+        let rendered = render(
+            "*{{resource_name}}* [{{ policy }}] {{  dashboard_url  }}",
+            &template_context(&payload),
+        )
+        .unwrap();
+
+        assert_eq!(rendered, "*nginx* [] ");
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let context = template_context(&payload());
+        assert_eq!(
+            render("{{ image }}", &context),
+            Err(TemplateError::UnknownVariable("image".to_string()))
+        );
+        assert_eq!(
+            render("Updating {{ new_image", &context),
+            Err(TemplateError::Unclosed(9))
+        );
+    }
+
+    #[test]
+    fn test_select_template() {
+        let data = BTreeMap::from([
+            ("template".to_string(), "default".to_string()),
+            (
+                "update_completed.template".to_string(),
+                "completed".to_string(),
+            ),
+            ("update_failed.template".to_string(), " ".to_string()),
+        ]);
+
+        assert_eq!(
+            template_key(NotificationEvent::UpdateRequestCreated),
+            "update_request_created.template"
+        );
+        assert_eq!(
+            select_template(&data, NotificationEvent::UpdateCompleted),
+            Some("completed")
+        );
+        assert_eq!(
+            select_template(&data, NotificationEvent::UpdateApproved),
+            Some("default")
+        );
+        // A blank template uses the built-in message
+        assert_eq!(
+            select_template(&data, NotificationEvent::UpdateFailed),
+            None
+        );
+        assert_eq!(
+            select_template(&BTreeMap::new(), NotificationEvent::UpdateApproved),
+            None
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use super::{NotificationEvent, NotificationPayload, Notifier, VictorOpsConfig};
+use anyhow::{Context, Result, anyhow};
+use reqwest::Client;
+use serde_json::json;
+use std::time::Duration;
+use tracing::debug;
+
+/// VictorOps (Splunk On-Call) incident message types
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Critical,
+    Warning,
+    Info,
+    Recovery,
+}
+
+impl MessageType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Critical => "CRITICAL",
+            Self::Warning => "WARNING",
+            Self::Info => "INFO",
+            Self::Recovery => "RECOVERY",
+        }
+    }
+
+    /// Updates waiting for approval open a warning, failures a critical
+    /// incident, and finished updates resolve it
+    pub fn for_payload(payload: &NotificationPayload) -> Self {
+        match payload.event {
+            NotificationEvent::UpdateRequestCreated if payload.requires_approval == Some(true) => {
+                Self::Warning
+            },
+            NotificationEvent::UpdateFailed | NotificationEvent::RollbackFailed => Self::Critical,
+            NotificationEvent::RollbackTriggered => Self::Warning,
+            NotificationEvent::UpdateCompleted
+            | NotificationEvent::UpdateRejected
+            | NotificationEvent::RollbackCompleted => Self::Recovery,
+            _ => Self::Info,
+        }
+    }
+}
+
+pub struct VictorOpsNotifier {
+    config: VictorOpsConfig,
+    client: Client,
+}
+
+impl VictorOpsNotifier {
+    pub fn new(config: VictorOpsConfig) -> Result<Self> {
+        if !config.enabled {
+            return Err(anyhow!("VictorOps notifier is disabled"));
+        }
+
+        if config.rest_endpoint_url.is_empty() {
+            return Err(anyhow!("VictorOps REST endpoint URL is required"));
+        }
+
+        if config.routing_key.is_empty() {
+            return Err(anyhow!("VictorOps routing key is required"));
+        }
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .context("Failed to create HTTP client")?;
+
+        Ok(Self { config, client })
+    }
+
+    fn url(&self) -> String {
+        format!(
+            "{}/{}",
+            self.config.rest_endpoint_url.trim_end_matches('/'),
+            self.config.routing_key
+        )
+    }
+
+    /// Incident the notification belongs to. Every notification about an
+    /// UpdateRequest uses its name, so the recovery resolves the warning.
+    fn entity_id(payload: &NotificationPayload) -> String {
+        match &payload.update_request_name {
+            Some(name) => name.clone(),
+            None => format!(
+                "{}/{}",
+                payload.deployment.namespace, payload.deployment.name
+            ),
+        }
+    }
+
+    /// Body of the REST endpoint request
+    fn build_request(&self, payload: &NotificationPayload) -> serde_json::Value {
+        let mut state_message = payload.description();
+        if let Some(ui_url) = &payload.ui_url {
+            state_message.push_str(&format!("\nView in Headwind: {}", ui_url));
+        }
+        if let Some(approval_url) = &payload.approval_url {
+            state_message.push_str(&format!("\nApprove: {}", approval_url));
+        }
+
+        json!({
+            "message_type": MessageType::for_payload(payload).as_str(),
+            "entity_id": Self::entity_id(payload),
+            "entity_display_name": payload.title(),
+            "state_message": state_message,
+            "state_start_time": payload.timestamp.timestamp(),
+            "monitoring_tool": "headwind",
+            "event": payload.event.as_str(),
+            "namespace": payload.deployment.namespace,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Notifier for VictorOpsNotifier {
+    async fn send(&self, payload: &NotificationPayload) -> Result<()> {
+        // The URL contains the API key, so keep it out of error messages
+        let response = self
+            .client
+            .post(self.url())
+            .json(&self.build_request(payload))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to send VictorOps notification: {}", e.without_url()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("VictorOps returned error {}: {}", status, body));
+        }
+
+        debug!("VictorOps notification sent successfully");
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "VictorOps"
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.config.enabled
+            && !self.config.rest_endpoint_url.is_empty()
+            && !self.config.routing_key.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::DeploymentInfo;
+    use axum::{Json, Router, extract::Path, routing::post};
+
+    fn deployment() -> DeploymentInfo {
+        DeploymentInfo {
+            name: "web".to_string(),
+            namespace: "production".to_string(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+            container: Some("nginx".to_string()),
+            resource_kind: None,
+        }
+    }
+
+    fn notifier(rest_endpoint_url: &str) -> VictorOpsNotifier {
+        VictorOpsNotifier::new(VictorOpsConfig {
+            enabled: true,
+            rest_endpoint_url: rest_endpoint_url.to_string(),
+            routing_key: "platform".to_string(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_message_types() {
+        let created = |requires_approval| {
+            NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment())
+                .with_requires_approval(requires_approval)
+        };
+        assert_eq!(
+            MessageType::for_payload(&created(true)),
+            MessageType::Warning
+        );
+        assert_eq!(MessageType::for_payload(&created(false)), MessageType::Info);
+
+        for (event, expected) in [
+            (NotificationEvent::UpdateFailed, MessageType::Critical),
+            (NotificationEvent::RollbackFailed, MessageType::Critical),
+            (NotificationEvent::UpdateCompleted, MessageType::Recovery),
+            (NotificationEvent::UpdateRejected, MessageType::Recovery),
+            (NotificationEvent::UpdateApproved, MessageType::Info),
+        ] {
+            let payload = NotificationPayload::new(event, deployment());
+            assert_eq!(MessageType::for_payload(&payload), expected, "{:?}", event);
+        }
+    }
+
+    #[test]
+    fn test_build_request() {
+        let notifier =
+            notifier("https://alert.victorops.com/integrations/generic/20131114/alert/key/");
+        assert_eq!(
+            notifier.url(),
+            "https://alert.victorops.com/integrations/generic/20131114/alert/key/platform"
+        );
+
+        let payload =
+            NotificationPayload::new(NotificationEvent::UpdateRequestCreated, deployment())
+                .with_requires_approval(true)
+                .with_update_request("web-nginx-1-26-0".to_string());
+        let body = notifier.build_request(&payload);
+        assert_eq!(body["message_type"], "WARNING");
+        assert_eq!(body["entity_id"], "web-nginx-1-26-0");
+        assert_eq!(
+            body["entity_display_name"],
+            "Update request created: Deployment production/web"
+        );
+        assert!(
+            body["state_message"]
+                .as_str()
+                .unwrap()
+                .contains("`nginx:1.25.0` → `nginx:1.26.0`")
+        );
+
+        // Without an UpdateRequest the resource identifies the incident
+        let payload = NotificationPayload::new(NotificationEvent::UpdateFailed, deployment());
+        assert_eq!(
+            notifier.build_request(&payload)["entity_id"],
+            "production/web"
+        );
+    }
+
+    #[test]
+    fn test_requires_endpoint_and_routing_key() {
+        for (rest_endpoint_url, routing_key) in [("", "platform"), ("https://alert", "")] {
+            assert!(
+                VictorOpsNotifier::new(VictorOpsConfig {
+                    enabled: true,
+                    rest_endpoint_url: rest_endpoint_url.to_string(),
+                    routing_key: routing_key.to_string(),
+                })
+                .is_err()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let app = Router::new()
+            .route(
+                "/alert/{key}/{routing_key}",
+                post(
+                    move |Path((key, routing_key)): Path<(String, String)>,
+                          Json(body): Json<serde_json::Value>| {
+                        let tx = tx.clone();
+                        async move {
+                            tx.send((key, routing_key, body)).unwrap();
+                            Json(json!({"result": "success", "entity_id": "web"}))
+                        }
+                    },
+                ),
+            )
+            .route(
+                "/broken/{key}/{routing_key}",
+                post(|| async {
+                    (
+                        axum::http::StatusCode::BAD_REQUEST,
+                        r#"{"result":"failure","message":"Missing fields"}"#,
+                    )
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let payload = NotificationPayload::new(NotificationEvent::UpdateCompleted, deployment())
+            .with_update_request("web-nginx-1-26-0".to_string());
+        notifier(&format!("http://{}/alert/key", addr))
+            .send(&payload)
+            .await
+            .unwrap();
+
+        let (key, routing_key, body) = rx.recv().await.unwrap();
+        assert_eq!(key, "key");
+        assert_eq!(routing_key, "platform");
+        assert_eq!(body["message_type"], "RECOVERY");
+        assert_eq!(body["entity_id"], "web-nginx-1-26-0");
+
+        let err = notifier(&format!("http://{}/broken/key", addr))
+            .send(&payload)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("400"));
+        assert!(err.to_string().contains("Missing fields"));
+    }
+}
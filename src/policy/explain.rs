@@ -0,0 +1,886 @@
+//! Structured explanation of an update decision.
+//!
+//! [`PolicyEngine::explain`](super::PolicyEngine::explain) covers the update
+//! policy itself. [`apply_resource_checks`] adds the gates that can still hold
+//! back an update the policy allows: `headwind.sh/min-update-interval`,
+//! `headwind.sh/update-schedule` and, with `headwind.sh/check-quota`, the
+//! namespace's ResourceQuotas. [`explain_update`] resolves all of it for a
+//! workload in the cluster.
+//!
+//! [`resolve_policy`] shows how a workload's annotations become its
+//! [`ResourcePolicy`], for `GET /api/v1/policy-evaluation`.
+
+use crate::controller::quota::{QuotaCheckResult, check_resource_quota};
+use crate::models::policy::{TagNormalization, annotations, global_tag_normalization};
+use crate::models::{ResourcePolicy, UpdatePolicy};
+use crate::policy::ignore::{ignore_patterns, is_image_ignored, is_resource_ignored};
+use crate::schedule::deferred_until;
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::core::v1::PodSpec;
+use kube::{Api, Client, ResourceExt};
+use oci_distribution::Reference;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use thiserror::Error;
+use tracing::warn;
+
+use super::PolicyEngine;
+
+/// Workload kinds `GET /api/v1/explain` can resolve
+pub const EXPLAINABLE_KINDS: &[&str] = &["Deployment", "StatefulSet", "DaemonSet"];
+
+/// Whether an update would be applied, and why
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyDecision {
+    pub allowed: bool,
+    pub reason: PolicyDecisionReason,
+    pub evaluated_at: DateTime<Utc>,
+}
+
+impl PolicyDecision {
+    /// A decision evaluated now; allowed only for [`PolicyDecisionReason::Allowed`]
+    pub fn new(reason: PolicyDecisionReason) -> Self {
+        Self {
+            allowed: matches!(reason, PolicyDecisionReason::Allowed { .. }),
+            reason,
+            evaluated_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all_fields = "camelCase")]
+pub enum PolicyDecisionReason {
+    /// Every check passed
+    Allowed { detail: String },
+    /// The update policy does not allow the candidate tag
+    PolicyRejected { detail: String },
+    /// `headwind.sh/min-update-interval` has not elapsed since the last update
+    MinIntervalNotMet { elapsed: u64, required: u64 },
+    /// `headwind.sh/update-schedule` defers the update to its next window
+    MaintenanceWindow {
+        schedule: String,
+        next_window: DateTime<Utc>,
+    },
+    /// The rolling update's surge pods do not fit in the namespace's quotas
+    QuotaInsufficient { detail: String },
+}
+
+impl fmt::Display for PolicyDecisionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allowed { detail } => write!(f, "Allowed: {}", detail),
+            Self::PolicyRejected { detail } => write!(f, "Rejected by policy: {}", detail),
+            Self::MinIntervalNotMet { elapsed, required } => write!(
+                f,
+                "Minimum update interval not met: last update {}s ago, {}s required",
+                elapsed, required
+            ),
+            Self::MaintenanceWindow {
+                schedule,
+                next_window,
+            } => write!(
+                f,
+                "Outside update schedule '{}': next window at {}",
+                schedule,
+                next_window.to_rfc3339()
+            ),
+            Self::QuotaInsufficient { detail } => write!(f, "Insufficient quota: {}", detail),
+        }
+    }
+}
+
+/// Hold back a decision the policy allows if the resource's own gates would.
+/// `quota` is the result of the ResourceQuota check, when it applies.
+pub fn apply_resource_checks(
+    decision: PolicyDecision,
+    policy: &ResourcePolicy,
+    annotations: &BTreeMap<String, String>,
+    quota: Option<&QuotaCheckResult>,
+) -> PolicyDecision {
+    if !decision.allowed {
+        return decision;
+    }
+
+    let now = decision.evaluated_at;
+    let reason = min_interval_not_met(policy, annotations, now)
+        .or_else(|| {
+            deferred_until(annotations, now).map(|(schedule, next_window)| {
+                PolicyDecisionReason::MaintenanceWindow {
+                    schedule,
+                    next_window,
+                }
+            })
+        })
+        .or_else(|| match quota {
+            Some(QuotaCheckResult::Insufficient { reason }) => {
+                Some(PolicyDecisionReason::QuotaInsufficient {
+                    detail: reason.clone(),
+                })
+            },
+            _ => None,
+        });
+
+    match reason {
+        Some(reason) => PolicyDecision {
+            allowed: false,
+            reason,
+            evaluated_at: now,
+        },
+        None => decision,
+    }
+}
+
+fn min_interval_not_met(
+    policy: &ResourcePolicy,
+    annotations: &BTreeMap<String, String>,
+    now: DateTime<Utc>,
+) -> Option<PolicyDecisionReason> {
+    let required = policy.min_update_interval?;
+    let last_update = annotations.get(annotations::LAST_UPDATE)?;
+    let last_update = DateTime::parse_from_rfc3339(last_update).ok()?;
+    let elapsed = now
+        .signed_duration_since(last_update.with_timezone(&Utc))
+        .num_seconds()
+        .max(0) as u64;
+
+    (elapsed < required).then_some(PolicyDecisionReason::MinIntervalNotMet { elapsed, required })
+}
+
+/// Response of `GET /api/v1/explain`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Explanation {
+    pub resource_kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub container: String,
+    pub current_image: String,
+    pub candidate_tag: String,
+    /// Human-readable summary of `decision.reason`
+    pub message: String,
+    pub decision: PolicyDecision,
+}
+
+#[derive(Debug, Error)]
+pub enum ExplainError {
+    #[error("Unsupported resource kind '{0}', expected one of: Deployment, StatefulSet, DaemonSet")]
+    UnsupportedKind(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("Kubernetes API error: {0}")]
+    Kube(#[from] kube::Error),
+}
+
+/// Explain whether `container` (default: the first container the policy
+/// targets) of a workload would be updated to `candidate_tag`
+pub async fn explain_update(
+    client: &Client,
+    resource_kind: &str,
+    namespace: &str,
+    name: &str,
+    container: Option<&str>,
+    candidate_tag: &str,
+) -> Result<Explanation, ExplainError> {
+    let (annotations, pod_spec, deployment) =
+        get_workload(client, resource_kind, namespace, name).await?;
+
+    let policy = crate::webhook::parse_policy_from_annotations(&annotations);
+    let target =
+        select_container(pod_spec.as_ref(), policy.as_ref().ok(), container).ok_or_else(|| {
+            ExplainError::NotFound(match container {
+                Some(c) => format!("Container '{}' not found in {}/{}", c, namespace, name),
+                None => format!(
+                    "No container of {}/{} is targeted by its policy",
+                    namespace, name
+                ),
+            })
+        })?;
+    let (container_name, current_image) = target;
+
+    let decision = match &policy {
+        Ok(policy) => {
+            let current_tag = Reference::try_from(current_image.as_str())
+                .ok()
+                .and_then(|r| r.tag().map(String::from))
+                .unwrap_or_else(|| "latest".to_string());
+            let decision = PolicyEngine.explain(policy, &current_tag, candidate_tag);
+
+            let check_quota = annotations
+                .get(annotations::CHECK_QUOTA)
+                .is_some_and(|v| v == "true");
+            let quota = match &deployment {
+                Some(deployment) if decision.allowed && check_quota => {
+                    match check_resource_quota(client, namespace, deployment).await {
+                        Ok(result) => Some(result),
+                        Err(e) => {
+                            warn!("Quota check for {}/{} failed: {}", namespace, name, e);
+                            None
+                        },
+                    }
+                },
+                _ => None,
+            };
+            apply_resource_checks(decision, policy, &annotations, quota.as_ref())
+        },
+        Err(e) => PolicyDecision::new(PolicyDecisionReason::PolicyRejected {
+            detail: format!("Invalid policy annotations: {:#}", e),
+        }),
+    };
+
+    Ok(Explanation {
+        resource_kind: resource_kind.to_string(),
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        container: container_name,
+        current_image,
+        candidate_tag: candidate_tag.to_string(),
+        message: decision.reason.to_string(),
+        decision,
+    })
+}
+
+/// Annotations and pod spec of a workload, and the Deployment itself if it is one
+async fn get_workload(
+    client: &Client,
+    resource_kind: &str,
+    namespace: &str,
+    name: &str,
+) -> Result<
+    (
+        BTreeMap<String, String>,
+        Option<PodSpec>,
+        Option<Deployment>,
+    ),
+    ExplainError,
+> {
+    let workload = match resource_kind {
+        "Deployment" => {
+            let deployment = get::<Deployment>(client, namespace, name).await?;
+            let pod_spec = deployment
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.spec.clone());
+            (deployment.annotations().clone(), pod_spec, Some(deployment))
+        },
+        "StatefulSet" => {
+            let statefulset = get::<StatefulSet>(client, namespace, name).await?;
+            let pod_spec = statefulset
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.spec.clone());
+            (statefulset.annotations().clone(), pod_spec, None)
+        },
+        "DaemonSet" => {
+            let daemonset = get::<DaemonSet>(client, namespace, name).await?;
+            let pod_spec = daemonset
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.spec.clone());
+            (daemonset.annotations().clone(), pod_spec, None)
+        },
+        other => return Err(ExplainError::UnsupportedKind(other.to_string())),
+    };
+    Ok(workload)
+}
+
+/// A container of a workload and whether Headwind tracks its image
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedContainer {
+    pub name: String,
+    pub image: String,
+    /// Whether the policy tracks the container and its image isn't ignored
+    pub tracked: bool,
+    /// Whether the registry poller has the image's tag list cached; absent
+    /// when polling doesn't run in this process
+    pub tag_list_cached: Option<bool>,
+}
+
+/// Response of `GET /api/v1/policy-evaluation/{namespace}/{resource_kind}/{name}`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyEvaluation {
+    pub resource_kind: String,
+    pub namespace: String,
+    pub name: String,
+    /// The resolved policy, absent if the annotations are invalid
+    pub policy: Option<ResourcePolicy>,
+    /// Why the annotations couldn't be resolved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Each step of the resolution, in order
+    pub resolution_trace: Vec<String>,
+    pub containers: Vec<TrackedContainer>,
+}
+
+/// Annotations read into a [`ResourcePolicy`], in the order they are resolved
+const POLICY_ANNOTATIONS: &[&str] = &[
+    annotations::POLICY,
+    annotations::PATTERN,
+    annotations::REQUIRE_APPROVAL,
+    annotations::MIN_UPDATE_INTERVAL,
+    annotations::IMAGES,
+    annotations::CONTAINER_NAMES,
+    annotations::BATCH_UPDATES,
+    annotations::NOTIFY_OBSERVERS,
+    annotations::TAG_NORMALIZATION,
+    annotations::NORMALIZE_TAGS,
+    annotations::REQUIRE_SLSA_LEVEL,
+    annotations::SLSA_BUILDER,
+    annotations::OPA_POLICY,
+];
+
+/// Resolve the policy of a workload with `annotations` the way its controller
+/// does, tracing which annotations and defaults it came from
+pub fn resolve_policy(
+    annotations: &BTreeMap<String, String>,
+) -> (Result<ResourcePolicy, String>, Vec<String>) {
+    resolve_policy_with_default(annotations, &global_tag_normalization())
+}
+
+fn resolve_policy_with_default(
+    annotations: &BTreeMap<String, String>,
+    default_normalization: &TagNormalization,
+) -> (Result<ResourcePolicy, String>, Vec<String>) {
+    let mut trace = Vec::new();
+
+    if is_resource_ignored(annotations) {
+        trace.push(format!(
+            "{} is \"true\": the resource is skipped",
+            annotations::IGNORE
+        ));
+    }
+    for key in POLICY_ANNOTATIONS {
+        if let Some(value) = annotations.get(*key) {
+            trace.push(format!("Read {}: \"{}\"", key, value));
+        }
+    }
+    if !annotations.contains_key(annotations::POLICY) {
+        trace.push(format!(
+            "{} not set: defaulting to none",
+            annotations::POLICY
+        ));
+    }
+    if !annotations.contains_key(annotations::TAG_NORMALIZATION)
+        && !annotations.contains_key(annotations::NORMALIZE_TAGS)
+        && *default_normalization != TagNormalization::None
+    {
+        trace.push(format!(
+            "Tag normalization inherited from HEADWIND_TAG_NORMALIZATION_RULES: {:?}",
+            default_normalization
+        ));
+    }
+
+    let policy = crate::webhook::parse_policy_from_annotations(annotations);
+    match &policy {
+        Ok(policy) if policy.policy == UpdatePolicy::None => {
+            trace.push("Policy none: no updates are applied".to_string())
+        },
+        Ok(policy) => trace.push(format!(
+            "Resolved policy {:?}, approval {}",
+            policy.policy,
+            if policy.require_approval {
+                "required"
+            } else {
+                "not required"
+            }
+        )),
+        Err(e) => trace.push(format!("Invalid policy annotations: {:#}", e)),
+    }
+
+    (policy.map_err(|e| format!("{:#}", e)), trace)
+}
+
+/// Whether a container is tracked under `policy`, as the workload
+/// controllers decide it
+fn is_tracked(policy: &ResourcePolicy, container: &str, image: &str) -> bool {
+    policy.policy != UpdatePolicy::None
+        && (policy.images.is_empty() || policy.images.iter().any(|i| i == container))
+        && policy.targets_container(container)
+        && !is_image_ignored(ignore_patterns(), image)
+}
+
+/// Evaluate the policy of a workload from its annotations and pod spec
+pub fn evaluate_policy(
+    resource_kind: &str,
+    namespace: &str,
+    name: &str,
+    annotations: &BTreeMap<String, String>,
+    pod_spec: Option<&PodSpec>,
+) -> PolicyEvaluation {
+    let (policy, mut resolution_trace) = resolve_policy(annotations);
+    let ignored = is_resource_ignored(annotations);
+
+    let containers: Vec<TrackedContainer> = pod_spec
+        .map(|pod| pod.containers.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|c| {
+            let image = c.image.clone()?;
+            let tracked = !ignored
+                && policy
+                    .as_ref()
+                    .is_ok_and(|p| is_tracked(p, &c.name, &image));
+            Some(TrackedContainer {
+                name: c.name.clone(),
+                tag_list_cached: crate::polling::is_tag_list_cached(&image),
+                image,
+                tracked,
+            })
+        })
+        .collect();
+    let tracked: Vec<&str> = containers
+        .iter()
+        .filter(|c| c.tracked)
+        .map(|c| c.name.as_str())
+        .collect();
+    resolution_trace.push(if tracked.is_empty() {
+        "No container is tracked".to_string()
+    } else {
+        format!("Tracked containers: {}", tracked.join(", "))
+    });
+
+    let (policy, error) = match policy {
+        Ok(policy) => (Some(policy), None),
+        Err(e) => (None, Some(e)),
+    };
+    PolicyEvaluation {
+        resource_kind: resource_kind.to_string(),
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        policy,
+        error,
+        resolution_trace,
+        containers,
+    }
+}
+
+/// Fetch a workload and evaluate its policy
+pub async fn evaluate_workload_policy(
+    client: &Client,
+    resource_kind: &str,
+    namespace: &str,
+    name: &str,
+) -> Result<PolicyEvaluation, ExplainError> {
+    let (annotations, pod_spec, _) = get_workload(client, resource_kind, namespace, name).await?;
+    Ok(evaluate_policy(
+        resource_kind,
+        namespace,
+        name,
+        &annotations,
+        pod_spec.as_ref(),
+    ))
+}
+
+async fn get<K>(client: &Client, namespace: &str, name: &str) -> Result<K, ExplainError>
+where
+    K: kube::Resource<Scope = k8s_openapi::NamespaceResourceScope>
+        + Clone
+        + serde::de::DeserializeOwned
+        + fmt::Debug,
+    <K as kube::Resource>::DynamicType: Default,
+{
+    let api: Api<K> = Api::namespaced(client.clone(), namespace);
+    match api.get_opt(name).await? {
+        Some(resource) => Ok(resource),
+        None => Err(ExplainError::NotFound(format!(
+            "{} {}/{} not found",
+            K::kind(&Default::default()),
+            namespace,
+            name
+        ))),
+    }
+}
+
+/// Name and image of the requested container, or of the first one the policy
+/// targets
+fn select_container(
+    pod_spec: Option<&PodSpec>,
+    policy: Option<&ResourcePolicy>,
+    requested: Option<&str>,
+) -> Option<(String, String)> {
+    pod_spec?
+        .containers
+        .iter()
+        .filter(|c| match requested {
+            Some(requested) => c.name == requested,
+            None => policy.is_none_or(|p| p.targets_container(&c.name)),
+        })
+        .find_map(|c| Some((c.name.clone(), c.image.clone()?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::UpdatePolicy;
+    use chrono::Duration;
+    use k8s_openapi::api::core::v1::Container;
+
+    fn policy(update_policy: UpdatePolicy) -> ResourcePolicy {
+        ResourcePolicy {
+            policy: update_policy,
+            ..Default::default()
+        }
+    }
+
+    fn allowed() -> PolicyDecision {
+        PolicyDecision::new(PolicyDecisionReason::Allowed {
+            detail: "Policy 'minor' allows 1.26.0 -> 1.27.0".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_explain_allowed() {
+        let decision = PolicyEngine.explain(&policy(UpdatePolicy::Minor), "1.26.0", "1.27.0");
+        assert!(decision.allowed);
+        assert!(matches!(
+            decision.reason,
+            PolicyDecisionReason::Allowed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_explain_policy_rejected() {
+        let engine = PolicyEngine;
+
+        let decision = engine.explain(&policy(UpdatePolicy::Minor), "1.26.0", "2.0.0");
+        assert!(!decision.allowed);
+        assert_eq!(
+            decision.reason,
+            PolicyDecisionReason::PolicyRejected {
+                detail: "Policy 'minor' only allows minor and patch updates, not 1.26.0 -> 2.0.0"
+                    .to_string()
+            }
+        );
+
+        let decision = engine.explain(&policy(UpdatePolicy::Patch), "1.26.0", "1.25.0");
+        assert_eq!(
+            decision.reason,
+            PolicyDecisionReason::PolicyRejected {
+                detail: "1.25.0 is not newer than 1.26.0".to_string()
+            }
+        );
+
+        // Unparseable versions are a rejection, not an error
+        let decision = engine.explain(&policy(UpdatePolicy::Minor), "1.26.0", "latest");
+        assert!(!decision.allowed);
+        assert!(decision.reason.to_string().contains("latest"));
+
+        let decision = engine.explain(&policy(UpdatePolicy::None), "1.26.0", "1.27.0");
+        assert!(!decision.allowed);
+    }
+
+    #[test]
+    fn test_min_interval_not_met() {
+        let policy = ResourcePolicy {
+            min_update_interval: Some(600),
+            ..policy(UpdatePolicy::Minor)
+        };
+        let decision = allowed();
+        let last_update = decision.evaluated_at - Duration::seconds(120);
+        let annotations = BTreeMap::from([(
+            annotations::LAST_UPDATE.to_string(),
+            last_update.to_rfc3339(),
+        )]);
+
+        let decision = apply_resource_checks(decision, &policy, &annotations, None);
+        assert!(!decision.allowed);
+        assert_eq!(
+            decision.reason,
+            PolicyDecisionReason::MinIntervalNotMet {
+                elapsed: 120,
+                required: 600
+            }
+        );
+
+        // Interval elapsed
+        let decision = allowed();
+        let last_update = decision.evaluated_at - Duration::seconds(900);
+        let annotations = BTreeMap::from([(
+            annotations::LAST_UPDATE.to_string(),
+            last_update.to_rfc3339(),
+        )]);
+        assert!(apply_resource_checks(decision, &policy, &annotations, None).allowed);
+    }
+
+    #[test]
+    fn test_maintenance_window() {
+        let decision = allowed();
+        // A window in a minute that is not the current one
+        let minute = (decision.evaluated_at + Duration::minutes(30)).format("%M");
+        let annotations = BTreeMap::from([(
+            annotations::UPDATE_SCHEDULE.to_string(),
+            format!("{} * * * *", minute),
+        )]);
+
+        let decision =
+            apply_resource_checks(decision, &policy(UpdatePolicy::Minor), &annotations, None);
+        assert!(!decision.allowed);
+        match decision.reason {
+            PolicyDecisionReason::MaintenanceWindow {
+                schedule,
+                next_window,
+            } => {
+                assert_eq!(schedule, format!("{} * * * *", minute));
+                assert!(next_window > decision.evaluated_at);
+            },
+            other => panic!("unexpected reason: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_quota_insufficient() {
+        let quota = QuotaCheckResult::Insufficient {
+            reason: "quota compute: requests.cpu needs 500m".to_string(),
+        };
+        let decision = apply_resource_checks(
+            allowed(),
+            &policy(UpdatePolicy::Minor),
+            &BTreeMap::new(),
+            Some(&quota),
+        );
+        assert!(!decision.allowed);
+        assert_eq!(
+            decision.reason,
+            PolicyDecisionReason::QuotaInsufficient {
+                detail: "quota compute: requests.cpu needs 500m".to_string()
+            }
+        );
+
+        let decision = apply_resource_checks(
+            allowed(),
+            &policy(UpdatePolicy::Minor),
+            &BTreeMap::new(),
+            Some(&QuotaCheckResult::Sufficient),
+        );
+        assert!(decision.allowed);
+    }
+
+    #[test]
+    fn test_rejected_decision_is_not_rechecked() {
+        let rejected = PolicyEngine.explain(&policy(UpdatePolicy::Patch), "1.26.0", "1.27.0");
+        let quota = QuotaCheckResult::Insufficient {
+            reason: "full".to_string(),
+        };
+        let decision = apply_resource_checks(
+            rejected.clone(),
+            &policy(UpdatePolicy::Patch),
+            &BTreeMap::new(),
+            Some(&quota),
+        );
+        assert_eq!(decision, rejected);
+    }
+
+    #[test]
+    fn test_decision_serialization() {
+        let decision = PolicyDecision::new(PolicyDecisionReason::MinIntervalNotMet {
+            elapsed: 30,
+            required: 300,
+        });
+        let json = serde_json::to_value(&decision).unwrap();
+        assert_eq!(json["allowed"], false);
+        assert_eq!(json["reason"]["type"], "MinIntervalNotMet");
+        assert_eq!(json["reason"]["elapsed"], 30);
+        assert!(json["evaluatedAt"].is_string());
+
+        let reason = PolicyDecisionReason::MaintenanceWindow {
+            schedule: "0 2 * * *".to_string(),
+            next_window: Utc::now(),
+        };
+        assert!(serde_json::to_value(&reason).unwrap()["nextWindow"].is_string());
+    }
+
+    #[test]
+    fn test_select_container() {
+        let pod_spec = PodSpec {
+            containers: vec![
+                Container {
+                    name: "app".to_string(),
+                    image: Some("nginx:1.26.0".to_string()),
+                    ..Default::default()
+                },
+                Container {
+                    name: "sidecar".to_string(),
+                    image: Some("envoy:1.30.0".to_string()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let sidecar_only = ResourcePolicy {
+            container_names: vec!["sidecar".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            select_container(Some(&pod_spec), None, None),
+            Some(("app".to_string(), "nginx:1.26.0".to_string()))
+        );
+        assert_eq!(
+            select_container(Some(&pod_spec), Some(&sidecar_only), None),
+            Some(("sidecar".to_string(), "envoy:1.30.0".to_string()))
+        );
+        assert_eq!(
+            select_container(Some(&pod_spec), None, Some("app")),
+            Some(("app".to_string(), "nginx:1.26.0".to_string()))
+        );
+        assert_eq!(select_container(Some(&pod_spec), None, Some("db")), None);
+    }
+
+    fn two_containers() -> PodSpec {
+        PodSpec {
+            containers: vec![
+                Container {
+                    name: "app".to_string(),
+                    image: Some("nginx:1.26.0".to_string()),
+                    ..Default::default()
+                },
+                Container {
+                    name: "sidecar".to_string(),
+                    image: Some("envoy:1.30.0".to_string()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn evaluate(annotations: &[(&str, &str)]) -> PolicyEvaluation {
+        let annotations: BTreeMap<String, String> = annotations
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        evaluate_policy(
+            "Deployment",
+            "default",
+            "web",
+            &annotations,
+            Some(&two_containers()),
+        )
+    }
+
+    fn tracked(evaluation: &PolicyEvaluation) -> Vec<&str> {
+        evaluation
+            .containers
+            .iter()
+            .filter(|c| c.tracked)
+            .map(|c| c.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate_policy_without_annotations() {
+        let evaluation = evaluate(&[]);
+        assert_eq!(
+            evaluation.policy.as_ref().unwrap().policy,
+            UpdatePolicy::None
+        );
+        assert!(
+            evaluation
+                .resolution_trace
+                .contains(&"headwind.sh/policy not set: defaulting to none".to_string())
+        );
+        assert!(tracked(&evaluation).is_empty());
+        // No poller runs in tests
+        assert!(
+            evaluation
+                .containers
+                .iter()
+                .all(|c| c.tag_list_cached.is_none())
+        );
+    }
+
+    #[test]
+    fn test_evaluate_policy_traces_annotations() {
+        let evaluation = evaluate(&[
+            (annotations::POLICY, "minor"),
+            (annotations::REQUIRE_APPROVAL, "false"),
+        ]);
+        let policy = evaluation.policy.as_ref().unwrap();
+        assert_eq!(policy.policy, UpdatePolicy::Minor);
+        assert!(!policy.require_approval);
+        assert_eq!(
+            evaluation.resolution_trace,
+            vec![
+                "Read headwind.sh/policy: \"minor\"",
+                "Read headwind.sh/require-approval: \"false\"",
+                "Resolved policy Minor, approval not required",
+                "Tracked containers: app, sidecar",
+            ]
+        );
+        assert_eq!(tracked(&evaluation), vec!["app", "sidecar"]);
+    }
+
+    #[test]
+    fn test_evaluate_policy_container_selection() {
+        let evaluation = evaluate(&[
+            (annotations::POLICY, "patch"),
+            (annotations::CONTAINER_NAMES, "sidecar"),
+        ]);
+        assert_eq!(tracked(&evaluation), vec!["sidecar"]);
+
+        let evaluation = evaluate(&[(annotations::POLICY, "patch"), (annotations::IMAGES, "app")]);
+        assert_eq!(tracked(&evaluation), vec!["app"]);
+
+        let evaluation = evaluate(&[
+            (annotations::POLICY, "patch"),
+            (annotations::IGNORE, "true"),
+        ]);
+        assert!(tracked(&evaluation).is_empty());
+        assert_eq!(
+            evaluation.resolution_trace[0],
+            "headwind.sh/ignore is \"true\": the resource is skipped"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_policy_invalid_annotations() {
+        let evaluation = evaluate(&[
+            (annotations::POLICY, "minor"),
+            (annotations::REQUIRE_SLSA_LEVEL, "4"),
+        ]);
+        assert!(evaluation.policy.is_none());
+        assert!(evaluation.error.as_deref().unwrap().contains("SLSA level"));
+        assert!(
+            evaluation
+                .resolution_trace
+                .iter()
+                .any(|step| step.starts_with("Invalid policy annotations"))
+        );
+        assert!(tracked(&evaluation).is_empty());
+
+        let json = serde_json::to_value(&evaluation).unwrap();
+        assert!(json["policy"].is_null());
+        assert!(json["resolutionTrace"].is_array());
+    }
+
+    #[test]
+    fn test_resolve_policy_inherits_tag_normalization() {
+        let annotations = BTreeMap::from([(annotations::POLICY.to_string(), "minor".to_string())]);
+        let (_, trace) = resolve_policy_with_default(&annotations, &TagNormalization::StripVPrefix);
+        assert!(
+            trace
+                .iter()
+                .any(|step| step.starts_with("Tag normalization inherited"))
+        );
+
+        // The resource's own annotation wins
+        let annotations = BTreeMap::from([
+            (annotations::POLICY.to_string(), "minor".to_string()),
+            (
+                annotations::NORMALIZE_TAGS.to_string(),
+                "strip-v".to_string(),
+            ),
+        ]);
+        let (_, trace) = resolve_policy_with_default(&annotations, &TagNormalization::StripVPrefix);
+        assert!(
+            !trace
+                .iter()
+                .any(|step| step.starts_with("Tag normalization inherited"))
+        );
+    }
+}
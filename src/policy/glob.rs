@@ -0,0 +1,185 @@
+//! Glob matching for `headwind.sh/pattern`.
+//!
+//! `*` matches any run of characters (including `/` and none at all) and `?`
+//! matches exactly one character. Every other character matches itself.
+//! Matching works on `char`s, so `?` matches one Unicode scalar value, and
+//! runs in O(pattern × text) time however many `*` the pattern contains.
+
+/// Whether `text` matches the glob `pattern`
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut pi, mut ti) = (0, 0);
+    // Position after the last `*` seen and the text position it is matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while ti < text.len() {
+        match pattern.get(pi) {
+            Some('*') => {
+                pi += 1;
+                backtrack = Some((pi, ti));
+            },
+            Some(&c) if c == '?' || c == text[ti] => {
+                pi += 1;
+                ti += 1;
+            },
+            _ => match backtrack {
+                // Let the last `*` swallow one more character and retry
+                Some((star_pi, star_ti)) => {
+                    pi = star_pi;
+                    ti = star_ti + 1;
+                    backtrack = Some((star_pi, ti));
+                },
+                None => return false,
+            },
+        }
+    }
+
+    pattern[pi..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reference matcher: textbook dynamic programming over prefixes
+    fn reference_match(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+
+        // matches[j] is whether the pattern prefix so far matches text[..j]
+        let mut matches = vec![false; text.len() + 1];
+        matches[0] = true;
+        for &p in &pattern {
+            let mut next = vec![false; text.len() + 1];
+            for j in 0..=text.len() {
+                next[j] = match p {
+                    '*' => matches[j] || (j > 0 && next[j - 1]),
+                    '?' => j > 0 && matches[j - 1],
+                    c => j > 0 && matches[j - 1] && text[j - 1] == c,
+                };
+            }
+            matches = next;
+        }
+        matches[text.len()]
+    }
+
+    /// Deterministic xorshift generator so failures are reproducible
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        /// String of up to `max_len` characters drawn from `alphabet`
+        fn string(&mut self, alphabet: &[char], max_len: usize) -> String {
+            let len = self.below(max_len + 1);
+            (0..len)
+                .map(|_| alphabet[self.below(alphabet.len())])
+                .collect()
+        }
+    }
+
+    /// Small alphabets so random patterns and texts actually match sometimes
+    const TEXT_CHARS: &[char] = &['a', 'b', '.', '-', '/', '1', 'é', '日', '🚀'];
+    const PATTERN_CHARS: &[char] = &['a', 'b', '.', '-', '/', '1', 'é', '日', '🚀', '*', '?'];
+    const CASES: usize = 5_000;
+
+    #[test]
+    fn test_literal_and_wildcard_patterns() {
+        assert!(glob_match("v1.0", "v1.0"));
+        assert!(!glob_match("v1.0", "v1.1"));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("v1.*", "v1.2.3"));
+        assert!(!glob_match("v1.*", "v2.0"));
+        assert!(glob_match("*-beta", "v1.0-beta"));
+        assert!(!glob_match("*-beta", "v1.0"));
+        assert!(glob_match("v1.*.0", "v1.99.0"));
+        assert!(!glob_match("v1.*.0", "v1.2.1"));
+        assert!(glob_match("v*-stable", "v1.2.3-stable"));
+        assert!(!glob_match("v*-stable", "v1.2.3-beta"));
+        assert!(glob_match("v1.?", "v1.5"));
+        assert!(!glob_match("v1.?", "v1.10"));
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        // Consecutive stars behave like one and cross `/`
+        assert!(glob_match("**", "a/b/c"));
+        assert!(glob_match("a**c", "a/b/c"));
+        assert!(glob_match("***", ""));
+        // Pattern longer than the text
+        assert!(!glob_match("abcd", "abc"));
+        assert!(!glob_match("abc?", "abc"));
+        assert!(glob_match("abc*", "abc"));
+        // Empty inputs
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+        // Unicode: `?` is one character, not one byte
+        assert!(glob_match("v?", "v日"));
+        assert!(glob_match("🚀*", "🚀-release"));
+        assert!(!glob_match("?", "🚀🚀"));
+        // Many stars do not blow up
+        assert!(!glob_match(&"*a".repeat(30), &"a".repeat(29)));
+    }
+
+    #[test]
+    fn test_property_star_matches_everything() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..CASES {
+            let text = rng.string(PATTERN_CHARS, 24);
+            assert!(glob_match("*", &text), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_property_literal_matches_itself() {
+        let mut rng = Rng(0xdead_beef_cafe_f00d);
+        for _ in 0..CASES {
+            let text = rng.string(TEXT_CHARS, 24);
+            assert!(glob_match(&text, &text), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_property_agrees_with_reference() {
+        let mut rng = Rng(0x0123_4567_89ab_cdef);
+        for _ in 0..CASES {
+            let pattern = rng.string(PATTERN_CHARS, 10);
+            let text = rng.string(TEXT_CHARS, 14);
+            assert_eq!(
+                glob_match(&pattern, &text),
+                reference_match(&pattern, &text),
+                "pattern {:?}, text {:?}",
+                pattern,
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_property_prefix_and_suffix() {
+        let mut rng = Rng(0x5151_5151_5151_5151);
+        for _ in 0..CASES {
+            let prefix = rng.string(TEXT_CHARS, 6);
+            let middle = rng.string(PATTERN_CHARS, 8);
+            let suffix = rng.string(TEXT_CHARS, 6);
+            let text = format!("{}{}{}", prefix, middle, suffix);
+            assert!(
+                glob_match(&format!("{}*{}", prefix, suffix), &text),
+                "{:?}",
+                text
+            );
+        }
+    }
+}
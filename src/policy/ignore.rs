@@ -0,0 +1,157 @@
+//! Images and resources Headwind never touches.
+//!
+//! `HEADWIND_IGNORE_IMAGES` holds comma-separated [glob](super::glob_match)
+//! patterns. A pattern matches an image if it matches the whole reference
+//! (`registry.k8s.io/pause:3.9`) or, when the pattern has no `/`, the last
+//! path segment (`pause:3.9`), so `pause:*` ignores pause containers from
+//! any registry. Ignored images are never polled, updated or matched
+//! against webhook events.
+
+use super::glob_match;
+use crate::models::annotations;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+pub const IGNORE_IMAGES_ENV: &str = "HEADWIND_IGNORE_IMAGES";
+
+static IGNORE_PATTERNS: Lazy<Vec<String>> = Lazy::new(ignore_images_from_env);
+
+/// Patterns from `HEADWIND_IGNORE_IMAGES`
+pub fn ignore_images_from_env() -> Vec<String> {
+    std::env::var(IGNORE_IMAGES_ENV)
+        .map(|v| parse_ignore_patterns(&v))
+        .unwrap_or_default()
+}
+
+/// Comma-separated patterns, skipping empty entries
+pub fn parse_ignore_patterns(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// `HEADWIND_IGNORE_IMAGES`, read once
+pub fn ignore_patterns() -> &'static [String] {
+    &IGNORE_PATTERNS
+}
+
+/// Whether `pattern` matches `image`
+pub fn pattern_matches(pattern: &str, image: &str) -> bool {
+    if glob_match(pattern, image) {
+        return true;
+    }
+    !pattern.contains('/')
+        && image
+            .rsplit_once('/')
+            .is_some_and(|(_, name)| glob_match(pattern, name))
+}
+
+/// Whether any of `patterns` matches `image`
+pub fn is_image_ignored(patterns: &[String], image: &str) -> bool {
+    patterns.iter().any(|p| pattern_matches(p, image))
+}
+
+/// Whether `headwind.sh/ignore: "true"` excludes the whole resource
+pub fn is_resource_ignored(annotations: &BTreeMap<String, String>) -> bool {
+    annotations
+        .get(annotations::IGNORE)
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
+
+/// An ignore pattern and the images in the cluster it matches
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IgnoredImages {
+    pub pattern: String,
+    pub images: Vec<String>,
+}
+
+/// The images among `images` each of `patterns` matches, sorted and deduplicated
+pub fn ignored_images<'a>(
+    patterns: &[String],
+    images: impl IntoIterator<Item = &'a str>,
+) -> Vec<IgnoredImages> {
+    let images: BTreeSet<&str> = images.into_iter().collect();
+    patterns
+        .iter()
+        .map(|pattern| IgnoredImages {
+            pattern: pattern.clone(),
+            images: images
+                .iter()
+                .filter(|image| pattern_matches(pattern, image))
+                .map(|image| image.to_string())
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns() -> Vec<String> {
+        parse_ignore_patterns("k8s.gcr.io/*, pause:*,,docker.io/calico/*")
+    }
+
+    #[test]
+    fn test_parse_ignore_patterns() {
+        assert_eq!(
+            patterns(),
+            vec!["k8s.gcr.io/*", "pause:*", "docker.io/calico/*"]
+        );
+        assert!(parse_ignore_patterns(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_is_image_ignored() {
+        let patterns = patterns();
+
+        assert!(is_image_ignored(&patterns, "k8s.gcr.io/kube-proxy:v1.28.2"));
+        assert!(is_image_ignored(&patterns, "k8s.gcr.io/pause:3.9"));
+        // Patterns without `/` also match the image name in any registry
+        assert!(is_image_ignored(&patterns, "registry.k8s.io/pause:3.9"));
+        assert!(is_image_ignored(&patterns, "pause:3.9"));
+        assert!(is_image_ignored(&patterns, "docker.io/calico/node:v3.26.1"));
+
+        assert!(!is_image_ignored(&patterns, "nginx:1.25"));
+        assert!(!is_image_ignored(
+            &patterns,
+            "registry.k8s.io/kube-proxy:v1.28.2"
+        ));
+        // `*` matches across `/`, but the registry must match
+        assert!(!is_image_ignored(&patterns, "ghcr.io/k8s.gcr.io/app:1.0"));
+        assert!(!is_image_ignored(&patterns, "calico/node:v3.26.1"));
+        assert!(!is_image_ignored(&[], "pause:3.9"));
+    }
+
+    #[test]
+    fn test_ignored_images() {
+        let images = [
+            "registry.k8s.io/pause:3.9",
+            "nginx:1.25",
+            "k8s.gcr.io/kube-proxy:v1.28.2",
+            "registry.k8s.io/pause:3.9",
+        ];
+        let matches = ignored_images(&patterns(), images);
+
+        assert_eq!(matches.len(), 3);
+        assert_eq!(matches[0].images, vec!["k8s.gcr.io/kube-proxy:v1.28.2"]);
+        assert_eq!(matches[1].pattern, "pause:*");
+        assert_eq!(matches[1].images, vec!["registry.k8s.io/pause:3.9"]);
+        assert!(matches[2].images.is_empty());
+    }
+
+    #[test]
+    fn test_is_resource_ignored() {
+        let mut annotations = BTreeMap::new();
+        assert!(!is_resource_ignored(&annotations));
+
+        annotations.insert(annotations::IGNORE.to_string(), "True".to_string());
+        assert!(is_resource_ignored(&annotations));
+
+        annotations.insert(annotations::IGNORE.to_string(), "false".to_string());
+        assert!(!is_resource_ignored(&annotations));
+    }
+}
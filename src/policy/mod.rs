@@ -1,3 +1,13 @@
+pub mod explain;
+pub mod glob;
+pub mod ignore;
+pub mod normalize;
+pub mod opa;
+
+pub use explain::{PolicyDecision, PolicyDecisionReason};
+pub use glob::glob_match;
+pub use normalize::normalize_tag;
+
 use crate::models::{ResourcePolicy, UpdatePolicy};
 use anyhow::{Context, Result};
 use semver::Version;
@@ -13,26 +23,73 @@ impl PolicyEngine {
         current_version: &str,
         new_version: &str,
     ) -> Result<bool> {
+        let (allowed, _) = self.evaluate(policy, current_version, new_version)?;
+        Ok(allowed)
+    }
+
+    /// Evaluate the policy like [`should_update`](Self::should_update) and
+    /// report why the update is allowed or not. Versions that cannot be
+    /// parsed are a rejection rather than an error.
+    pub fn explain(
+        &self,
+        policy: &ResourcePolicy,
+        current_version: &str,
+        candidate_version: &str,
+    ) -> PolicyDecision {
+        let reason = match self.evaluate(policy, current_version, candidate_version) {
+            Ok((true, detail)) => PolicyDecisionReason::Allowed { detail },
+            Ok((false, detail)) => PolicyDecisionReason::PolicyRejected { detail },
+            Err(e) => PolicyDecisionReason::PolicyRejected {
+                detail: format!("{:#}", e),
+            },
+        };
+        PolicyDecision::new(reason)
+    }
+
+    /// Whether the policy allows the update, with a human-readable reason
+    fn evaluate(
+        &self,
+        policy: &ResourcePolicy,
+        current_version: &str,
+        new_version: &str,
+    ) -> Result<(bool, String)> {
+        let current_version = &normalize_tag(current_version, &policy.tag_normalization);
+        let new_version = &normalize_tag(new_version, &policy.tag_normalization);
+
         match policy.policy {
             UpdatePolicy::None => {
                 debug!("Policy is 'none', skipping update");
-                Ok(false)
+                Ok((false, "Policy is 'none'".to_string()))
             },
             UpdatePolicy::Force => {
                 info!("Policy is 'force', allowing update");
-                Ok(true)
+                Ok((true, "Policy is 'force'".to_string()))
             },
             UpdatePolicy::All => {
                 info!("Policy is 'all', allowing update");
-                Ok(current_version != new_version)
+                if current_version != new_version {
+                    Ok((
+                        true,
+                        format!("Policy 'all' allows any tag change to {}", new_version),
+                    ))
+                } else {
+                    Ok((false, format!("{} is already the current tag", new_version)))
+                }
             },
             UpdatePolicy::Glob => {
                 if let Some(pattern) = &policy.pattern {
                     let matches = glob_match(pattern, new_version);
                     debug!("Glob pattern '{}' match: {}", pattern, matches);
-                    Ok(matches)
+                    let verb = if matches { "matches" } else { "does not match" };
+                    Ok((
+                        matches,
+                        format!("{} {} pattern '{}'", new_version, verb, pattern),
+                    ))
                 } else {
-                    Ok(false)
+                    Ok((
+                        false,
+                        "Policy 'glob' has no headwind.sh/pattern".to_string(),
+                    ))
                 }
             },
             UpdatePolicy::Patch | UpdatePolicy::Minor | UpdatePolicy::Major => {
@@ -41,36 +98,45 @@ impl PolicyEngine {
         }
     }
 
-    fn check_semver_policy(&self, policy: UpdatePolicy, current: &str, new: &str) -> Result<bool> {
+    fn check_semver_policy(
+        &self,
+        policy: UpdatePolicy,
+        current: &str,
+        new: &str,
+    ) -> Result<(bool, String)> {
         // Try to parse as semver, stripping common prefixes
         let current_version = Self::parse_version(current)
-            .context(format!("Failed to parse current version: {}", current))?;
-        let new_version =
-            Self::parse_version(new).context(format!("Failed to parse new version: {}", new))?;
+            .with_context(|| format!("Failed to parse current version: {}", current))?;
+        let new_version = Self::parse_version(new)
+            .with_context(|| format!("Failed to parse new version: {}", new))?;
 
         if new_version <= current_version {
             debug!(
                 "New version {} is not greater than current version {}",
                 new, current
             );
-            return Ok(false);
+            return Ok((false, format!("{} is not newer than {}", new, current)));
         }
 
-        let should_update = match policy {
-            UpdatePolicy::Patch => {
+        let (should_update, name, allows) = match policy {
+            UpdatePolicy::Patch => (
                 // Only update if major and minor are the same
                 new_version.major == current_version.major
-                    && new_version.minor == current_version.minor
-            },
-            UpdatePolicy::Minor => {
+                    && new_version.minor == current_version.minor,
+                "patch",
+                "patch updates",
+            ),
+            UpdatePolicy::Minor => (
                 // Update if major is the same
-                new_version.major == current_version.major
-            },
+                new_version.major == current_version.major,
+                "minor",
+                "minor and patch updates",
+            ),
             UpdatePolicy::Major => {
                 // Update to any newer version
-                true
+                (true, "major", "any newer version")
             },
-            _ => false,
+            _ => (false, "unknown", "no semver updates"),
         };
 
         info!(
@@ -78,7 +144,15 @@ impl PolicyEngine {
             policy, current, new, should_update
         );
 
-        Ok(should_update)
+        let detail = if should_update {
+            format!("Policy '{}' allows {} -> {}", name, current, new)
+        } else {
+            format!(
+                "Policy '{}' only allows {}, not {} -> {}",
+                name, allows, current, new
+            )
+        };
+        Ok((should_update, detail))
     }
 
     fn parse_version(version: &str) -> Result<Version> {
@@ -92,28 +166,10 @@ impl PolicyEngine {
     }
 }
 
-#[allow(dead_code)]
-fn glob_match(pattern: &str, text: &str) -> bool {
-    // Simple glob matching - can be enhanced with a proper glob library
-    if pattern == "*" {
-        return true;
-    }
-
-    if pattern.contains('*') {
-        let parts: Vec<&str> = pattern.split('*').collect();
-        if parts.len() == 2 {
-            let prefix = parts[0];
-            let suffix = parts[1];
-            return text.starts_with(prefix) && text.ends_with(suffix);
-        }
-    }
-
-    pattern == text
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::TagNormalization;
 
     #[test]
     fn test_patch_policy() {
@@ -177,6 +233,26 @@ mod tests {
         assert!(engine.should_update(&policy, "v1.2.3", "v1.2.4").unwrap());
     }
 
+    #[test]
+    fn test_tag_normalization() {
+        let engine = PolicyEngine;
+        let mut policy = ResourcePolicy {
+            policy: UpdatePolicy::All,
+            ..Default::default()
+        };
+
+        // Without normalization a re-prefixed tag looks like a new version
+        assert!(engine.should_update(&policy, "1.2.3", "v1.2.3").unwrap());
+
+        policy.tag_normalization = TagNormalization::StripVPrefix;
+        assert!(!engine.should_update(&policy, "1.2.3", "v1.2.3").unwrap());
+        assert!(engine.should_update(&policy, "1.2.3", "v1.2.4").unwrap());
+
+        policy.policy = UpdatePolicy::Glob;
+        policy.pattern = Some("1.2.*".to_string());
+        assert!(engine.should_update(&policy, "1.2.3", "v1.2.4").unwrap());
+    }
+
     #[test]
     fn test_glob_matching() {
         assert!(glob_match("*", "anything"));
@@ -0,0 +1,165 @@
+//! Tag normalization, so registries that tag `v1.2.3` can be compared with
+//! resources running `1.2.3`. Only comparisons use the normalized tag; images
+//! are always patched with the tag exactly as the registry publishes it.
+
+use crate::models::TagNormalization;
+
+/// `tag` rewritten according to `normalization`; unchanged when the prefix is absent
+pub fn normalize_tag(tag: &str, normalization: &TagNormalization) -> String {
+    match normalization {
+        TagNormalization::None => tag.to_string(),
+        TagNormalization::StripVPrefix => match tag.strip_prefix(['v', 'V']) {
+            Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest.to_string(),
+            _ => tag.to_string(),
+        },
+        TagNormalization::Custom(prefix) => {
+            tag.strip_prefix(prefix.as_str()).unwrap_or(tag).to_string()
+        },
+        TagNormalization::Rule(rule) => rule.apply(tag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{TagNormalizationRule, annotations, parse_tag_normalization};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_no_normalization() {
+        assert_eq!(normalize_tag("v1.2.3", &TagNormalization::None), "v1.2.3");
+    }
+
+    #[test]
+    fn test_strip_v_prefix() {
+        let strip = TagNormalization::StripVPrefix;
+        assert_eq!(normalize_tag("v1.2.3", &strip), "1.2.3");
+        assert_eq!(normalize_tag("V1.2.3", &strip), "1.2.3");
+        // No-op without a leading v
+        assert_eq!(normalize_tag("1.2.3", &strip), "1.2.3");
+        // Only a v followed by a version is a prefix
+        assert_eq!(normalize_tag("vanilla", &strip), "vanilla");
+        assert_eq!(normalize_tag("v", &strip), "v");
+    }
+
+    #[test]
+    fn test_custom_prefix() {
+        let custom = TagNormalization::Custom("release-".to_string());
+        assert_eq!(normalize_tag("release-1.2.3", &custom), "1.2.3");
+        assert_eq!(normalize_tag("1.2.3", &custom), "1.2.3");
+        assert_eq!(normalize_tag("v1.2.3", &custom), "v1.2.3");
+    }
+
+    fn rule(value: &str) -> TagNormalization {
+        TagNormalization::Rule(value.parse().unwrap())
+    }
+
+    #[test]
+    fn test_rule_strip_prefix() {
+        let strip = rule("strip-prefix=release-");
+        assert_eq!(normalize_tag("release-1.2.3", &strip), "1.2.3");
+        assert_eq!(normalize_tag("1.2.3", &strip), "1.2.3");
+    }
+
+    #[test]
+    fn test_rule_strip_suffix() {
+        let strip = rule("strip-suffix=-alpine");
+        assert_eq!(normalize_tag("1.2.3-alpine", &strip), "1.2.3");
+        assert_eq!(normalize_tag("1.2.3-debian", &strip), "1.2.3-debian");
+    }
+
+    #[test]
+    fn test_rule_regex_replace() {
+        // Build numbers become the patch version
+        let replace = rule(r"regex-replace=^(\d+)\.(\d+)-build(\d+)$=>$1.$2.$3");
+        assert_eq!(normalize_tag("1.4-build17", &replace), "1.4.17");
+        assert_eq!(normalize_tag("latest", &replace), "latest");
+    }
+
+    #[test]
+    fn test_rule_combination() {
+        // Steps apply in order: prefix, suffix, then the regex
+        let combined = rule(r"strip-prefix=v, strip-suffix=-alpine, regex-replace=_=>.");
+        assert_eq!(normalize_tag("v1_2.3-alpine", &combined), "1.2.3");
+        // A regex containing commas takes the rest of the value
+        let TagNormalization::Rule(parsed) =
+            rule(r"strip-suffix=-slim,regex-replace=^v(\d{1,3})=>$1")
+        else {
+            unreachable!()
+        };
+        assert_eq!(parsed.suffix_to_strip.as_deref(), Some("-slim"));
+        assert_eq!(
+            parsed.regex_replace,
+            Some((r"^v(\d{1,3})".to_string(), "$1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        for invalid in [
+            "",
+            "strip-prefix=",
+            "lowercase=true",
+            "regex-replace=no-arrow",
+            "regex-replace=([=>x",
+        ] {
+            assert!(
+                invalid.parse::<TagNormalizationRule>().is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_tag_normalization_precedence() {
+        let default = rule("strip-prefix=release-");
+        let mut annotations = BTreeMap::new();
+        assert_eq!(
+            parse_tag_normalization(&annotations, &default).unwrap(),
+            default
+        );
+
+        annotations.insert(
+            annotations::NORMALIZE_TAGS.to_string(),
+            "strip-v".to_string(),
+        );
+        assert_eq!(
+            parse_tag_normalization(&annotations, &default).unwrap(),
+            TagNormalization::StripVPrefix
+        );
+
+        annotations.insert(
+            annotations::TAG_NORMALIZATION.to_string(),
+            "strip-prefix=v".to_string(),
+        );
+        assert_eq!(
+            parse_tag_normalization(&annotations, &default).unwrap(),
+            rule("strip-prefix=v")
+        );
+
+        annotations.insert(
+            annotations::TAG_NORMALIZATION.to_string(),
+            "strip-v".to_string(),
+        );
+        assert!(parse_tag_normalization(&annotations, &default).is_err());
+    }
+
+    #[test]
+    fn test_parse_annotation_value() {
+        assert_eq!(
+            "strip-v".parse::<TagNormalization>().unwrap(),
+            TagNormalization::StripVPrefix
+        );
+        assert_eq!(
+            "none".parse::<TagNormalization>().unwrap(),
+            TagNormalization::None
+        );
+        assert_eq!(
+            "strip-prefix:Release-".parse::<TagNormalization>().unwrap(),
+            TagNormalization::Custom("Release-".to_string())
+        );
+        assert!("strip-prefix:".parse::<TagNormalization>().is_err());
+        assert!("lowercase".parse::<TagNormalization>().is_err());
+    }
+}
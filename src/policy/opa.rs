@@ -0,0 +1,420 @@
+//! Update authorization by Open Policy Agent.
+//!
+//! With `headwind.sh/opa-policy: headwind/update_policy` set, every update is
+//! first sent to `POST {HEADWIND_OPA_ENDPOINT}/v1/data/headwind/update_policy/allow`
+//! and only checked against the local [`PolicyEngine`](super::PolicyEngine)
+//! if OPA allows it. Decisions are cached for a minute per resource and new
+//! image. An update is skipped when OPA cannot be asked, as it would be if
+//! OPA denied it.
+
+use crate::metrics::OPA_DECISIONS_TOTAL;
+use crate::models::ResourcePolicy;
+use anyhow::{Context, Result, bail};
+use k8s_openapi::api::core::v1::ObjectReference;
+use kube::Client;
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+pub const OPA_ENDPOINT_ENV: &str = "HEADWIND_OPA_ENDPOINT";
+
+/// Reason of the warning event published when OPA does not allow an update
+pub const OPA_DENIED_REASON: &str = "OpaPolicyDenied";
+
+/// How long a decision is reused for the same resource and new image
+pub const DECISION_TTL: Duration = Duration::from_secs(60);
+
+/// How long to wait for OPA
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static DECISIONS: Lazy<Mutex<DecisionCache>> = Lazy::new(Mutex::default);
+
+/// The update OPA decides on, sent as `input`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OpaInput {
+    pub resource_kind: String,
+    pub namespace: String,
+    pub current_image: String,
+    pub new_image: String,
+    pub policy: String,
+}
+
+/// Whether OPA allows an update and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpaDecision {
+    pub allowed: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpaResponse {
+    /// Absent when the rule is undefined
+    result: Option<bool>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Decisions by (resource UID, new image), with a TTL
+#[derive(Default)]
+pub struct DecisionCache {
+    entries: HashMap<(String, String), (OpaDecision, Instant)>,
+}
+
+impl DecisionCache {
+    /// Decision for `uid` and `new_image` made less than [`DECISION_TTL`]
+    /// before `now`
+    pub fn get(&self, uid: &str, new_image: &str, now: Instant) -> Option<OpaDecision> {
+        self.entries
+            .get(&(uid.to_string(), new_image.to_string()))
+            .filter(|(_, decided_at)| now.duration_since(*decided_at) < DECISION_TTL)
+            .map(|(decision, _)| decision.clone())
+    }
+
+    pub fn insert(&mut self, uid: &str, new_image: &str, decision: OpaDecision, now: Instant) {
+        self.entries
+            .retain(|_, (_, decided_at)| now.duration_since(*decided_at) < DECISION_TTL);
+        self.entries
+            .insert((uid.to_string(), new_image.to_string()), (decision, now));
+    }
+}
+
+/// Asks OPA whether updates are allowed by the rule `allow` of `package`
+#[derive(Debug, Clone)]
+pub struct OpaClient {
+    /// OPA base URL, e.g. `http://opa.opa-system:8181`
+    pub endpoint: String,
+    /// Package of the `allow` rule, e.g. `headwind/update_policy`
+    pub package: String,
+    http: reqwest::Client,
+}
+
+impl OpaClient {
+    pub fn new(endpoint: impl Into<String>, package: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            package: package.into().trim_matches('/').replace('.', "/"),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Client for `package` at `HEADWIND_OPA_ENDPOINT`, or `None` if unset
+    pub fn from_env(package: &str) -> Option<Self> {
+        std::env::var(OPA_ENDPOINT_ENV)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|endpoint| Self::new(endpoint, package))
+    }
+
+    fn url(&self) -> String {
+        format!("{}/v1/data/{}/allow", self.endpoint, self.package)
+    }
+
+    /// Ask OPA about `input`. An undefined rule denies the update.
+    pub async fn evaluate(&self, input: &OpaInput) -> Result<OpaDecision> {
+        let response = self
+            .http
+            .post(self.url())
+            .timeout(REQUEST_TIMEOUT)
+            .json(&json!({ "input": input }))
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach OPA at {}", self.endpoint))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("OPA query failed with {}: {}", status, body.trim());
+        }
+        let response: OpaResponse = response.json().await.context("Invalid OPA response")?;
+
+        Ok(match response.result {
+            Some(allowed) => OpaDecision {
+                allowed,
+                reason: response.reason,
+            },
+            None => OpaDecision {
+                allowed: false,
+                reason: Some(format!("{}/allow is undefined", self.package)),
+            },
+        })
+    }
+
+    /// Decision for the resource with `uid`, from the cache when fresh
+    pub async fn decide(&self, uid: &str, input: &OpaInput) -> Result<OpaDecision> {
+        let cached = DECISIONS.lock().unwrap_or_else(|e| e.into_inner()).get(
+            uid,
+            &input.new_image,
+            Instant::now(),
+        );
+        if let Some(decision) = cached {
+            debug!("Using cached OPA decision for {}", input.new_image);
+            return Ok(decision);
+        }
+
+        let decision = self.evaluate(input).await?;
+        OPA_DECISIONS_TOTAL
+            .with_label_values(&[if decision.allowed {
+                "allowed"
+            } else {
+                "denied"
+            }])
+            .inc();
+        DECISIONS.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            uid,
+            &input.new_image,
+            decision.clone(),
+            Instant::now(),
+        );
+        Ok(decision)
+    }
+}
+
+/// Name of a policy as OPA sees it, e.g. `minor`
+pub fn policy_name(policy: &ResourcePolicy) -> String {
+    serde_json::to_value(policy.policy)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Whether OPA allows the update of `object_ref` to `new_image`. True when
+/// the resource has no `headwind.sh/opa-policy`; otherwise a denial, or a
+/// failure to ask OPA, is logged and published as an `OpaPolicyDenied`
+/// warning event on the resource.
+pub async fn check_update(
+    client: &Client,
+    object_ref: &ObjectReference,
+    policy: &ResourcePolicy,
+    current_image: &str,
+    new_image: &str,
+) -> bool {
+    let Some(package) = &policy.opa_policy else {
+        return true;
+    };
+    let input = OpaInput {
+        resource_kind: object_ref.kind.clone().unwrap_or_default(),
+        namespace: object_ref.namespace.clone().unwrap_or_default(),
+        current_image: current_image.to_string(),
+        new_image: new_image.to_string(),
+        policy: policy_name(policy),
+    };
+
+    let reason = match OpaClient::from_env(package) {
+        None => format!(
+            "headwind.sh/opa-policy is set but {} is not, skipping update to {}",
+            OPA_ENDPOINT_ENV, new_image
+        ),
+        Some(opa) => {
+            let uid = object_ref.uid.as_deref().unwrap_or_default();
+            match opa.decide(uid, &input).await {
+                Ok(OpaDecision { allowed: true, .. }) => return true,
+                Ok(OpaDecision { reason, .. }) => format!(
+                    "OPA policy {} denied the update to {}: {}",
+                    opa.package,
+                    new_image,
+                    reason.as_deref().unwrap_or("no reason given")
+                ),
+                Err(e) => format!(
+                    "Could not ask OPA policy {} about the update to {}: {:#}",
+                    opa.package, new_image, e
+                ),
+            }
+        },
+    };
+
+    warn!(
+        "Skipping update of {}/{}: {}",
+        input.namespace,
+        object_ref.name.as_deref().unwrap_or_default(),
+        reason
+    );
+    publish_opa_denied(client, object_ref, &reason).await;
+    false
+}
+
+async fn publish_opa_denied(client: &Client, object_ref: &ObjectReference, note: &str) {
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    let event = Event {
+        type_: EventType::Warning,
+        reason: OPA_DENIED_REASON.to_string(),
+        note: Some(note.to_string()),
+        action: "UpdateImage".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(&event, object_ref).await {
+        warn!(
+            "Failed to publish {} event for {}/{}: {}",
+            OPA_DENIED_REASON,
+            object_ref.namespace.as_deref().unwrap_or_default(),
+            object_ref.name.as_deref().unwrap_or_default(),
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::UpdatePolicy;
+    use axum::{
+        Json, Router,
+        extract::{Path, State},
+        http::StatusCode,
+        response::{IntoResponse, Response},
+        routing::post,
+    };
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    /// Inputs received by the mock OPA, by package path
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// Allows updates to `:1.x` tags of `web` and denies the rest; the
+    /// `broken` package answers 500
+    async fn allow(
+        State(received): State<Received>,
+        Path(package): Path<String>,
+        Json(body): Json<Value>,
+    ) -> Response {
+        received
+            .lock()
+            .unwrap()
+            .push((package.clone(), body.clone()));
+        match package.as_str() {
+            "headwind/update_policy/allow" => {
+                let new_image = body["input"]["new_image"].as_str().unwrap_or_default();
+                if new_image.starts_with("web:1.") {
+                    Json(json!({ "result": true })).into_response()
+                } else {
+                    Json(json!({
+                        "result": false,
+                        "reason": "major upgrades need a change ticket"
+                    }))
+                    .into_response()
+                }
+            },
+            "headwind/undefined/allow" => Json(json!({})).into_response(),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, "rego_type_error").into_response(),
+        }
+    }
+
+    async fn start_mock() -> (String, Received) {
+        let received = Received::default();
+        let app = Router::new()
+            .route("/v1/data/{*package}", post(allow))
+            .with_state(received.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}", addr), received)
+    }
+
+    fn input(new_image: &str) -> OpaInput {
+        OpaInput {
+            resource_kind: "Deployment".to_string(),
+            namespace: "production".to_string(),
+            current_image: "web:1.4.0".to_string(),
+            new_image: new_image.to_string(),
+            policy: "minor".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_sends_input_and_reads_decision() {
+        let (url, received) = start_mock().await;
+        let opa = OpaClient::new(format!("{}/", url), "headwind/update_policy");
+
+        let decision = opa.evaluate(&input("web:1.5.0")).await.unwrap();
+        assert_eq!(
+            decision,
+            OpaDecision {
+                allowed: true,
+                reason: None
+            }
+        );
+
+        let decision = opa.evaluate(&input("web:2.0.0")).await.unwrap();
+        assert!(!decision.allowed);
+        assert_eq!(
+            decision.reason.as_deref(),
+            Some("major upgrades need a change ticket")
+        );
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received[0].0, "headwind/update_policy/allow");
+        assert_eq!(
+            received[0].1,
+            json!({
+                "input": {
+                    "resource_kind": "Deployment",
+                    "namespace": "production",
+                    "current_image": "web:1.4.0",
+                    "new_image": "web:1.5.0",
+                    "policy": "minor"
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_undefined_rule_denies_and_errors_are_reported() {
+        let (url, _) = start_mock().await;
+
+        let undefined = OpaClient::new(&url, "headwind.undefined");
+        let decision = undefined.evaluate(&input("web:1.5.0")).await.unwrap();
+        assert!(!decision.allowed);
+
+        let broken = OpaClient::new(&url, "headwind/broken");
+        let error = broken.evaluate(&input("web:1.5.0")).await.unwrap_err();
+        assert!(error.to_string().contains("500"));
+    }
+
+    #[tokio::test]
+    async fn test_decisions_are_cached_per_resource_and_image() {
+        let (url, received) = start_mock().await;
+        let opa = OpaClient::new(&url, "headwind/update_policy");
+        let uid = "5b4a8a4e-opa-cache-test";
+
+        assert!(opa.decide(uid, &input("web:1.5.0")).await.unwrap().allowed);
+        assert!(opa.decide(uid, &input("web:1.5.0")).await.unwrap().allowed);
+        assert_eq!(received.lock().unwrap().len(), 1);
+
+        // Another image or resource is asked about again
+        assert!(!opa.decide(uid, &input("web:2.0.0")).await.unwrap().allowed);
+        assert!(
+            opa.decide("another-uid-opa-cache-test", &input("web:1.5.0"))
+                .await
+                .unwrap()
+                .allowed
+        );
+        assert_eq!(received.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_cache_expires_after_ttl() {
+        let mut cache = DecisionCache::default();
+        let now = Instant::now();
+        let denied = OpaDecision {
+            allowed: false,
+            reason: Some("frozen".to_string()),
+        };
+        cache.insert("uid", "web:2.0.0", denied.clone(), now);
+
+        assert_eq!(cache.get("uid", "web:2.0.0", now), Some(denied));
+        assert_eq!(cache.get("uid", "web:2.0.1", now), None);
+        assert_eq!(cache.get("uid", "web:2.0.0", now + DECISION_TTL), None);
+    }
+
+    #[test]
+    fn test_policy_name() {
+        let policy = ResourcePolicy {
+            policy: UpdatePolicy::Minor,
+            ..Default::default()
+        };
+        assert_eq!(policy_name(&policy), "minor");
+    }
+}
@@ -0,0 +1,476 @@
+//! Azure Container Registry credentials from a Managed Identity.
+//!
+//! An Azure AD token for `https://management.azure.com/` is requested from the
+//! Instance Metadata Service (IMDS), exchanged for an ACR refresh token at
+//! `POST https://{registry}/oauth2/exchange`, and the refresh token for an
+//! access token at `POST https://{registry}/oauth2/token`. Tokens are cached
+//! per registry until five minutes before the access token expires.
+//!
+//! The OCI client only speaks HTTP Basic, and ACR accepts its refresh token as
+//! the password of the `00000000-0000-0000-0000-000000000000` user, so that is
+//! what [`AcrCredentialProvider::get_credentials`] returns.
+
+use super::auth::RegistryCredentials;
+use anyhow::{Context, Result, bail};
+use base64::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+/// IMDS endpoint issuing tokens for the VM's or pod's managed identity
+const IMDS_TOKEN_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+/// Resource the Azure AD token is requested for
+const AZURE_MANAGEMENT_RESOURCE: &str = "https://management.azure.com/";
+
+/// Username ACR expects with a refresh token as password
+pub const ACR_REFRESH_TOKEN_USERNAME: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Scope of the access token; pulls need per-repository scopes, which the
+/// registry client requests itself with the refresh token
+const ACCESS_TOKEN_SCOPE: &str = "registry:catalog:*";
+
+/// Tokens are refreshed this long before the access token expires
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// Lifetime assumed for access tokens whose expiry cannot be read
+const DEFAULT_TOKEN_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Whether `registry` is an Azure Container Registry
+pub fn is_acr(registry: &str) -> bool {
+    registry.ends_with(".azurecr.io")
+}
+
+#[derive(Debug, Deserialize)]
+struct ImdsToken {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExchangeResponse {
+    refresh_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtClaims {
+    exp: u64,
+}
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    refresh_token: String,
+    access_token: String,
+    refresh_at: Instant,
+    expires_at: Instant,
+}
+
+impl CachedToken {
+    fn credentials(&self, registry: &str) -> RegistryCredentials {
+        RegistryCredentials {
+            registry: registry.to_string(),
+            username: ACR_REFRESH_TOKEN_USERNAME.to_string(),
+            password: self.refresh_token.clone(),
+        }
+    }
+}
+
+/// Obtains ACR tokens with the managed identity of the node or pod
+pub struct AcrCredentialProvider {
+    /// Azure AD tenant of the registry
+    pub tenant_id: String,
+    /// Client ID of a user-assigned identity; the system-assigned one if unset
+    pub client_id: Option<String>,
+    imds_url: String,
+    /// Registry base URL override; `https://{registry}` if unset
+    registry_url: Option<String>,
+    http: reqwest::Client,
+    tokens: HashMap<String, CachedToken>,
+}
+
+impl AcrCredentialProvider {
+    pub fn new(tenant_id: impl Into<String>, client_id: Option<String>) -> Self {
+        Self {
+            tenant_id: tenant_id.into(),
+            client_id,
+            imds_url: IMDS_TOKEN_URL.to_string(),
+            registry_url: None,
+            http: reqwest::Client::new(),
+            tokens: HashMap::new(),
+        }
+    }
+
+    /// Provider configured by `HEADWIND_ACR_TENANT_ID` and optionally
+    /// `HEADWIND_ACR_CLIENT_ID`, or `None` unless the tenant is set
+    pub fn from_env() -> Option<Self> {
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+        Some(Self::new(
+            var("HEADWIND_ACR_TENANT_ID")?,
+            var("HEADWIND_ACR_CLIENT_ID"),
+        ))
+    }
+
+    /// Talk to mock IMDS and registry endpoints instead of Azure
+    #[cfg(test)]
+    pub fn with_endpoints(mut self, imds_url: &str, registry_url: &str) -> Self {
+        self.imds_url = imds_url.to_string();
+        self.registry_url = Some(registry_url.to_string());
+        self
+    }
+
+    /// Credentials for `registry` obtained earlier and not due for refresh
+    pub fn cached_credentials(&self, registry: &str) -> Option<RegistryCredentials> {
+        self.cached(registry)
+            .map(|token| token.credentials(registry))
+    }
+
+    /// Basic credentials for `registry`, from the cache or a new token exchange
+    pub async fn get_credentials(&mut self, registry: &str) -> Result<RegistryCredentials> {
+        Ok(self.token(registry).await?.credentials(registry))
+    }
+
+    /// Registries with a cached token and when it expires
+    pub fn expiries(&self) -> Vec<(String, Instant)> {
+        self.tokens
+            .iter()
+            .map(|(registry, token)| (registry.clone(), token.expires_at))
+            .collect()
+    }
+
+    /// Replace the cached token of `registry` with a new one, keeping the old
+    /// token if that fails
+    pub async fn refresh(&mut self, registry: &str) -> Result<()> {
+        self.fetch_token(registry).await.map(|_| ())
+    }
+
+    /// Forget the cached token of `registry`
+    pub fn invalidate(&mut self, registry: &str) {
+        self.tokens.remove(registry);
+    }
+
+    /// Bearer token for calling the registry API of `registry` directly
+    #[allow(dead_code)] // The OCI client authenticates with get_credentials
+    pub async fn access_token(&mut self, registry: &str) -> Result<String> {
+        Ok(self.token(registry).await?.access_token)
+    }
+
+    /// Forget all cached tokens
+    #[allow(dead_code)] // Mirrors AuthManager::clear_cache
+    pub fn clear_cache(&mut self) {
+        self.tokens.clear();
+    }
+
+    fn cached(&self, registry: &str) -> Option<&CachedToken> {
+        self.tokens
+            .get(registry)
+            .filter(|token| Instant::now() < token.refresh_at)
+    }
+
+    async fn token(&mut self, registry: &str) -> Result<CachedToken> {
+        if let Some(token) = self.cached(registry) {
+            debug!("Using cached ACR token for {}", registry);
+            return Ok(token.clone());
+        }
+        self.fetch_token(registry).await
+    }
+
+    async fn fetch_token(&mut self, registry: &str) -> Result<CachedToken> {
+        let aad_token = self.aad_token().await?;
+        let refresh_token = self.exchange(registry, &aad_token).await?;
+        let access_token = self.access_token_for(registry, &refresh_token).await?;
+
+        let ttl = jwt_ttl(&access_token).unwrap_or(DEFAULT_TOKEN_TTL);
+        let now = Instant::now();
+        let token = CachedToken {
+            refresh_token,
+            access_token,
+            refresh_at: now + ttl.saturating_sub(REFRESH_MARGIN),
+            expires_at: now + ttl,
+        };
+        debug!(
+            "Obtained ACR token for {} valid for {}s",
+            registry,
+            ttl.as_secs()
+        );
+        self.tokens.insert(registry.to_string(), token.clone());
+        Ok(token)
+    }
+
+    /// Azure AD token of the managed identity from IMDS
+    async fn aad_token(&self) -> Result<String> {
+        let mut query = vec![
+            ("api-version", "2018-02-01"),
+            ("resource", AZURE_MANAGEMENT_RESOURCE),
+        ];
+        if let Some(client_id) = &self.client_id {
+            query.push(("client_id", client_id));
+        }
+
+        let response = self
+            .http
+            .get(&self.imds_url)
+            .header("Metadata", "true")
+            .query(&query)
+            .send()
+            .await
+            .context("Failed to reach the Azure Instance Metadata Service")?;
+        let token: ImdsToken = check_status(response, "IMDS token request")
+            .await?
+            .json()
+            .await
+            .context("Invalid IMDS token response")?;
+        Ok(token.access_token)
+    }
+
+    /// Exchange an Azure AD token for an ACR refresh token
+    async fn exchange(&self, registry: &str, aad_token: &str) -> Result<String> {
+        let response = self
+            .http
+            .post(format!("{}/oauth2/exchange", self.registry_url(registry)))
+            .form(&[
+                ("grant_type", "access_token"),
+                ("service", registry),
+                ("tenant", &self.tenant_id),
+                ("access_token", aad_token),
+            ])
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", registry))?;
+        let exchange: ExchangeResponse = check_status(response, "ACR token exchange")
+            .await?
+            .json()
+            .await
+            .context("Invalid ACR token exchange response")?;
+        Ok(exchange.refresh_token)
+    }
+
+    /// Exchange an ACR refresh token for an access token
+    async fn access_token_for(&self, registry: &str, refresh_token: &str) -> Result<String> {
+        let response = self
+            .http
+            .post(format!("{}/oauth2/token", self.registry_url(registry)))
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("service", registry),
+                ("scope", ACCESS_TOKEN_SCOPE),
+                ("refresh_token", refresh_token),
+            ])
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", registry))?;
+        let token: TokenResponse = check_status(response, "ACR access token request")
+            .await?
+            .json()
+            .await
+            .context("Invalid ACR access token response")?;
+        Ok(token.access_token)
+    }
+
+    fn registry_url(&self, registry: &str) -> String {
+        self.registry_url
+            .clone()
+            .unwrap_or_else(|| format!("https://{}", registry))
+    }
+}
+
+async fn check_status(response: reqwest::Response, action: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    bail!("{} failed with {}: {}", action, status, body.trim())
+}
+
+/// Time left until the `exp` claim of a JWT
+fn jwt_ttl(token: &str) -> Option<Duration> {
+    let payload = token.split('.').nth(1)?;
+    let claims: JwtClaims =
+        serde_json::from_slice(&BASE64_URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(claims.exp.saturating_sub(now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Form, Json, Router,
+        extract::{Query, State},
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+    };
+    use serde_json::{Value, json};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct MockAzure {
+        /// Seconds until issued access tokens expire
+        access_token_ttl: u64,
+        imds_queries: Vec<HashMap<String, String>>,
+        exchanges: Vec<HashMap<String, String>>,
+        token_requests: Vec<HashMap<String, String>>,
+    }
+
+    type Shared = Arc<Mutex<MockAzure>>;
+
+    fn jwt(exp: u64) -> String {
+        let payload = BASE64_URL_SAFE_NO_PAD.encode(json!({ "exp": exp }).to_string());
+        format!("eyJhbGciOiJSUzI1NiJ9.{}.signature", payload)
+    }
+
+    async fn imds(
+        State(state): State<Shared>,
+        headers: HeaderMap,
+        Query(query): Query<HashMap<String, String>>,
+    ) -> Response {
+        if headers.get("metadata").is_none_or(|v| v != "true") {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Required metadata header not specified",
+            )
+                .into_response();
+        }
+        state.lock().unwrap().imds_queries.push(query);
+        Json(json!({ "access_token": "aad-token", "expires_in": "3599" })).into_response()
+    }
+
+    async fn exchange(
+        State(state): State<Shared>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Response {
+        if form.get("access_token").map(String::as_str) != Some("aad-token") {
+            return (StatusCode::UNAUTHORIZED, "invalid token").into_response();
+        }
+        let mut state = state.lock().unwrap();
+        state.exchanges.push(form);
+        let n = state.exchanges.len();
+        Json(json!({ "refresh_token": format!("refresh-{}", n) })).into_response()
+    }
+
+    async fn token(
+        State(state): State<Shared>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Json<Value> {
+        let mut state = state.lock().unwrap();
+        state.token_requests.push(form);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        Json(json!({ "access_token": jwt(now + state.access_token_ttl) }))
+    }
+
+    async fn start_mock(state: Shared) -> String {
+        let app = Router::new()
+            .route("/metadata/identity/oauth2/token", get(imds))
+            .route("/oauth2/exchange", post(exchange))
+            .route("/oauth2/token", post(token))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    async fn provider(state: &Shared, client_id: Option<&str>) -> AcrCredentialProvider {
+        let url = start_mock(state.clone()).await;
+        AcrCredentialProvider::new("tenant-1", client_id.map(String::from))
+            .with_endpoints(&format!("{}/metadata/identity/oauth2/token", url), &url)
+    }
+
+    #[test]
+    fn test_is_acr() {
+        assert!(is_acr("myregistry.azurecr.io"));
+        assert!(!is_acr("azurecr.io.example.com"));
+        assert!(!is_acr("ghcr.io"));
+    }
+
+    #[test]
+    fn test_jwt_ttl() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let ttl = jwt_ttl(&jwt(now + 3600)).unwrap();
+        assert!(ttl <= Duration::from_secs(3600) && ttl >= Duration::from_secs(3590));
+        assert_eq!(jwt_ttl(&jwt(now - 10)), Some(Duration::ZERO));
+        assert_eq!(jwt_ttl("not-a-jwt"), None);
+    }
+
+    #[tokio::test]
+    async fn test_token_exchange_flow() {
+        let state = Shared::new(Mutex::new(MockAzure {
+            access_token_ttl: 3600,
+            ..Default::default()
+        }));
+        let mut provider = provider(&state, Some("identity-1")).await;
+
+        let credentials = provider
+            .get_credentials("myregistry.azurecr.io")
+            .await
+            .unwrap();
+        assert_eq!(credentials.username, ACR_REFRESH_TOKEN_USERNAME);
+        assert_eq!(credentials.password, "refresh-1");
+        assert!(
+            provider
+                .access_token("myregistry.azurecr.io")
+                .await
+                .unwrap()
+                .starts_with("eyJ")
+        );
+
+        let state = state.lock().unwrap();
+        let imds = &state.imds_queries[0];
+        assert_eq!(imds["resource"], "https://management.azure.com/");
+        assert_eq!(imds["client_id"], "identity-1");
+
+        let exchange = &state.exchanges[0];
+        assert_eq!(exchange["grant_type"], "access_token");
+        assert_eq!(exchange["service"], "myregistry.azurecr.io");
+        assert_eq!(exchange["tenant"], "tenant-1");
+
+        let token = &state.token_requests[0];
+        assert_eq!(token["grant_type"], "refresh_token");
+        assert_eq!(token["refresh_token"], "refresh-1");
+
+        // The second lookup came from the cache
+        assert_eq!(state.exchanges.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_token_refreshed_near_expiry() {
+        // Access tokens expiring within the five minute margin are never reused
+        let state = Shared::new(Mutex::new(MockAzure {
+            access_token_ttl: 240,
+            ..Default::default()
+        }));
+        let mut provider = provider(&state, None).await;
+
+        let first = provider.get_credentials("a.azurecr.io").await.unwrap();
+        assert!(provider.cached_credentials("a.azurecr.io").is_none());
+        let second = provider.get_credentials("a.azurecr.io").await.unwrap();
+        assert_ne!(first.password, second.password);
+
+        let state = state.lock().unwrap();
+        assert_eq!(state.exchanges.len(), 2);
+        assert!(!state.imds_queries[0].contains_key("client_id"));
+    }
+
+    #[tokio::test]
+    async fn test_imds_failure() {
+        let state = Shared::default();
+        let mut provider = provider(&state, None).await;
+        provider.imds_url = format!("{}/missing", provider.registry_url("unused"));
+
+        let err = provider.get_credentials("a.azurecr.io").await.unwrap_err();
+        assert!(err.to_string().contains("IMDS token request failed"));
+        assert!(state.lock().unwrap().exchanges.is_empty());
+    }
+}
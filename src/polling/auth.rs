@@ -1,3 +1,7 @@
+use super::acr::{AcrCredentialProvider, is_acr};
+use super::gcp::{GcpWorkloadIdentityProvider, is_gcp_registry};
+use super::vault::VaultCredentialProvider;
+use crate::models::policy::CredentialSource;
 use anyhow::{Context, Result};
 use base64::prelude::*;
 use k8s_openapi::api::core::v1::{Secret, ServiceAccount};
@@ -5,6 +9,7 @@ use kube::{Api, Client};
 use oci_distribution::secrets::RegistryAuth;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::time::Instant;
 use tracing::{debug, warn};
 
 /// Docker config.json structure
@@ -33,11 +38,46 @@ pub struct RegistryCredentials {
     pub password: String,
 }
 
+/// Provider of credentials that expire and can be refreshed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CredentialProvider {
+    /// ACR tokens from a Managed Identity
+    Acr,
+    /// GCP access token from Workload Identity
+    Gcp,
+    /// Credentials read from Vault
+    Vault,
+}
+
+impl std::fmt::Display for CredentialProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Acr => "ACR",
+            Self::Gcp => "GCP",
+            Self::Vault => "Vault",
+        })
+    }
+}
+
+/// A cached credential that expires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedCredentialExpiry {
+    pub provider: CredentialProvider,
+    pub registry: String,
+    pub expires_at: Instant,
+}
+
 /// Manager for registry authentication
 pub struct AuthManager {
     client: Client,
     /// Cache of registry -> credentials
     credentials_cache: HashMap<String, RegistryCredentials>,
+    /// Vault provider, when `HEADWIND_VAULT_*` is configured
+    vault: Option<VaultCredentialProvider>,
+    /// ACR Managed Identity provider, when `HEADWIND_ACR_TENANT_ID` is set
+    acr: Option<AcrCredentialProvider>,
+    /// GKE Workload Identity provider, unless `HEADWIND_GCP_WORKLOAD_IDENTITY=false`
+    gcp: Option<GcpWorkloadIdentityProvider>,
 }
 
 impl AuthManager {
@@ -45,21 +85,85 @@ impl AuthManager {
         Self {
             client,
             credentials_cache: HashMap::new(),
+            vault: VaultCredentialProvider::from_env(),
+            acr: AcrCredentialProvider::from_env(),
+            gcp: GcpWorkloadIdentityProvider::from_env(),
         }
     }
 
+    /// Previously looked up credentials for the registry of `image`, if any
+    pub fn cached_auth_for_image(
+        &self,
+        image: &str,
+        source: CredentialSource,
+    ) -> Option<RegistryAuth> {
+        let registry = extract_registry_from_image(image);
+        let creds = match source {
+            CredentialSource::ImagePullSecrets => self
+                .acr
+                .as_ref()
+                .filter(|_| is_acr(&registry))
+                .and_then(|acr| acr.cached_credentials(&registry))
+                .or_else(|| {
+                    self.gcp
+                        .as_ref()
+                        .filter(|_| is_gcp_registry(&registry))
+                        .and_then(|gcp| gcp.cached_credentials(&registry))
+                })
+                .or_else(|| self.credentials_cache.get(&registry).cloned()),
+            CredentialSource::Vault => self.vault.as_ref()?.cached_credentials(&registry),
+        };
+        creds.map(|creds| RegistryAuth::Basic(creds.username, creds.password))
+    }
+
     /// Get authentication for a specific image
     /// Returns RegistryAuth for use with OCI client
     pub async fn get_auth_for_image(
         &mut self,
         image: &str,
         namespace: &str,
+        source: CredentialSource,
     ) -> Result<RegistryAuth> {
         // Extract registry from image
         let registry = extract_registry_from_image(image);
 
+        if source == CredentialSource::Vault {
+            return Ok(self.get_vault_auth(&registry).await);
+        }
+
         debug!("Getting auth for registry: {} (image: {})", registry, image);
 
+        if is_acr(&registry)
+            && let Some(acr) = self.acr.as_mut()
+        {
+            match acr.get_credentials(&registry).await {
+                Ok(creds) => {
+                    debug!("Using Managed Identity token for {}", registry);
+                    return Ok(RegistryAuth::Basic(creds.username, creds.password));
+                },
+                Err(e) => warn!(
+                    "Error fetching ACR token for {} with Managed Identity, falling back to imagePullSecrets: {:#}",
+                    registry, e
+                ),
+            }
+        }
+
+        if is_gcp_registry(&registry)
+            && let Some(gcp) = self.gcp.as_mut()
+        {
+            match gcp.get_credentials(&registry).await {
+                Ok(Some(creds)) => {
+                    debug!("Using Google service account credentials for {}", registry);
+                    return Ok(RegistryAuth::Basic(creds.username, creds.password));
+                },
+                Ok(None) => {},
+                Err(e) => warn!(
+                    "Error fetching GCP credentials for {}, falling back to imagePullSecrets: {:#}",
+                    registry, e
+                ),
+            }
+        }
+
         // Check cache first
         if let Some(creds) = self.credentials_cache.get(&registry) {
             debug!("Using cached credentials for {}", registry);
@@ -91,6 +195,31 @@ impl AuthManager {
         }
     }
 
+    /// Credentials for `registry` from Vault, anonymous if Vault isn't configured or fails
+    async fn get_vault_auth(&mut self, registry: &str) -> RegistryAuth {
+        let Some(vault) = self.vault.as_mut() else {
+            warn!(
+                "Credential source for {} is vault but HEADWIND_VAULT_ADDR, HEADWIND_VAULT_ROLE and HEADWIND_VAULT_CREDENTIAL_PATH are not all set",
+                registry
+            );
+            return RegistryAuth::Anonymous;
+        };
+
+        match vault.get_credentials(registry).await {
+            Ok(creds) => {
+                debug!("Found credentials for {} in Vault", registry);
+                RegistryAuth::Basic(creds.username, creds.password)
+            },
+            Err(e) => {
+                warn!(
+                    "Error fetching credentials for {} from Vault: {:#}",
+                    registry, e
+                );
+                RegistryAuth::Anonymous
+            },
+        }
+    }
+
     /// Fetch credentials from Kubernetes secrets
     async fn fetch_credentials_from_k8s(
         &self,
@@ -237,6 +366,64 @@ impl AuthManager {
         Ok(None)
     }
 
+    /// Cached credentials of all providers that expire, with their expiry.
+    /// imagePullSecrets do not expire and are not listed.
+    pub fn credential_expiries(&self) -> Vec<CachedCredentialExpiry> {
+        let entries = |provider, expiries: Vec<(String, Instant)>| {
+            expiries
+                .into_iter()
+                .map(move |(registry, expires_at)| CachedCredentialExpiry {
+                    provider,
+                    registry,
+                    expires_at,
+                })
+        };
+        let mut expiries = Vec::new();
+        if let Some(acr) = &self.acr {
+            expiries.extend(entries(CredentialProvider::Acr, acr.expiries()));
+        }
+        if let Some(gcp) = &self.gcp {
+            expiries.extend(entries(CredentialProvider::Gcp, gcp.expiries()));
+        }
+        if let Some(vault) = &self.vault {
+            expiries.extend(entries(CredentialProvider::Vault, vault.expiries()));
+        }
+        expiries
+    }
+
+    /// Fetch new credentials for `registry` from `provider`. The cached ones
+    /// are kept when that fails.
+    pub async fn refresh_credentials(
+        &mut self,
+        provider: CredentialProvider,
+        registry: &str,
+    ) -> Result<()> {
+        match provider {
+            CredentialProvider::Acr => match self.acr.as_mut() {
+                Some(acr) => acr.refresh(registry).await,
+                None => Ok(()),
+            },
+            CredentialProvider::Gcp => match self.gcp.as_mut() {
+                Some(gcp) => gcp.refresh().await,
+                None => Ok(()),
+            },
+            CredentialProvider::Vault => match self.vault.as_mut() {
+                Some(vault) => vault.refresh(registry).await,
+                None => Ok(()),
+            },
+        }
+    }
+
+    /// Forget the cached credentials of `registry` from `provider`, so the next
+    /// lookup fetches new ones
+    pub fn invalidate_credentials(&mut self, provider: CredentialProvider, registry: &str) {
+        match provider {
+            CredentialProvider::Acr => self.acr.as_mut().map(|acr| acr.invalidate(registry)),
+            CredentialProvider::Gcp => self.gcp.as_mut().map(|gcp| gcp.invalidate()),
+            CredentialProvider::Vault => self.vault.as_mut().map(|v| v.invalidate(registry)),
+        };
+    }
+
     /// Clear the credentials cache (useful for testing or credential rotation)
     #[allow(dead_code)] // Available for future credential rotation feature
     pub fn clear_cache(&mut self) {
@@ -330,6 +517,19 @@ mod tests {
         assert!(!registry_matches("other.io", "docker.io"));
     }
 
+    #[test]
+    fn test_credential_source_from_str() {
+        assert_eq!(
+            "vault".parse::<CredentialSource>().unwrap(),
+            CredentialSource::Vault
+        );
+        assert_eq!(
+            "Image-Pull-Secrets".parse::<CredentialSource>().unwrap(),
+            CredentialSource::ImagePullSecrets
+        );
+        assert!("aws-secrets-manager".parse::<CredentialSource>().is_err());
+    }
+
     #[test]
     fn test_parse_auth_entry() {
         // This test would require a k8s client, skip for now
@@ -0,0 +1,347 @@
+use crate::metrics::CACHE_SHARD_CONTENTION_TOTAL;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::{RwLock, TryLockError};
+use std::time::{Duration, Instant};
+
+/// Shards of a [`ShardedCache`] unless given otherwise
+pub const DEFAULT_SHARDS: usize = 16;
+
+/// Cache key: (registry, repository)
+type TagListKey = (String, String);
+
+struct CachedTagList {
+    tags: Vec<String>,
+    fetched_at: Instant,
+    /// Position in the recency index
+    last_used: u64,
+}
+
+/// LRU cache of registry tag lists with a TTL.
+///
+/// Many workloads often share an image, so caching the tag list avoids listing
+/// the same repository several times per poll cycle.
+pub struct TagListCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: HashMap<TagListKey, CachedTagList>,
+    /// Keys ordered from least to most recently used
+    recency: BTreeMap<u64, TagListKey>,
+    tick: u64,
+}
+
+impl TagListCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            max_entries: max_entries.max(1),
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Build a cache from `HEADWIND_TAG_CACHE_TTL_SECS` (default 120) and
+    /// `HEADWIND_TAG_CACHE_MAX_ENTRIES` (default 5000)
+    pub fn from_env() -> Self {
+        let ttl = std::env::var("HEADWIND_TAG_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(120);
+        let max_entries = std::env::var("HEADWIND_TAG_CACHE_MAX_ENTRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5000);
+
+        Self::new(Duration::from_secs(ttl), max_entries)
+    }
+
+    /// Cached tags for a repository, if present and not expired
+    pub fn get(&mut self, registry: &str, repository: &str) -> Option<Vec<String>> {
+        self.get_at(registry, repository, Instant::now())
+    }
+
+    fn get_at(&mut self, registry: &str, repository: &str, now: Instant) -> Option<Vec<String>> {
+        let key = (registry.to_string(), repository.to_string());
+        let entry = self.entries.get(&key)?;
+
+        if now.duration_since(entry.fetched_at) >= self.ttl {
+            let last_used = entry.last_used;
+            self.entries.remove(&key);
+            self.recency.remove(&last_used);
+            return None;
+        }
+
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(&key)?;
+        self.recency.remove(&entry.last_used);
+        entry.last_used = tick;
+        let tags = entry.tags.clone();
+        self.recency.insert(tick, key);
+
+        Some(tags)
+    }
+
+    /// Store the tag list for a repository, evicting the least recently used
+    /// entries beyond the size limit
+    pub fn insert(&mut self, registry: &str, repository: &str, tags: Vec<String>) {
+        self.insert_at(registry, repository, tags, Instant::now());
+    }
+
+    fn insert_at(&mut self, registry: &str, repository: &str, tags: Vec<String>, now: Instant) {
+        let key = (registry.to_string(), repository.to_string());
+        let tick = self.next_tick();
+
+        if let Some(old) = self.entries.remove(&key) {
+            self.recency.remove(&old.last_used);
+        }
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            CachedTagList {
+                tags,
+                fetched_at: now,
+                last_used: tick,
+            },
+        );
+
+        while self.entries.len() > self.max_entries {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether an unexpired tag list is cached for a repository. Unlike
+    /// [`get`](Self::get) this leaves the recency order untouched.
+    pub fn contains(&self, registry: &str, repository: &str) -> bool {
+        self.contains_at(registry, repository, Instant::now())
+    }
+
+    fn contains_at(&self, registry: &str, repository: &str, now: Instant) -> bool {
+        self.entries
+            .get(&(registry.to_string(), repository.to_string()))
+            .is_some_and(|entry| now.duration_since(entry.fetched_at) < self.ttl)
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+/// Map split into shards by key hash, each behind its own lock.
+///
+/// Poll workers mostly touch different images, so they rarely wait for each
+/// other, where a single lock would serialize every cache update.
+pub struct ShardedCache<V> {
+    shards: Vec<RwLock<HashMap<String, V>>>,
+}
+
+impl<V: Clone> ShardedCache<V> {
+    pub fn new(shards: usize) -> Self {
+        Self {
+            shards: (0..shards.max(1))
+                .map(|_| RwLock::new(HashMap::new()))
+                .collect(),
+        }
+    }
+
+    /// The value for `key`, if cached
+    pub fn get(&self, key: &str) -> Option<V> {
+        let shard = &self.shards[self.shard_index(key)];
+        let map = shard.read().unwrap_or_else(|e| e.into_inner());
+        map.get(key).cloned()
+    }
+
+    /// Cache `value` for `key`, replacing any previous value
+    pub fn insert(&self, key: &str, value: V) {
+        let index = self.shard_index(key);
+        let shard = &self.shards[index];
+        let mut map = match shard.try_write() {
+            Ok(map) => map,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                CACHE_SHARD_CONTENTION_TOTAL
+                    .with_label_values(&[&index.to_string()])
+                    .inc();
+                shard.write().unwrap_or_else(|e| e.into_inner())
+            },
+        };
+        map.insert(key.to_string(), value);
+    }
+
+    /// Number of cached entries across all shards
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap_or_else(|e| e.into_inner()).len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn shard_index(&self, key: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+}
+
+impl<V: Clone> Default for ShardedCache<V> {
+    fn default() -> Self {
+        Self::new(DEFAULT_SHARDS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_hit_and_miss() {
+        let mut cache = TagListCache::new(Duration::from_secs(120), 10);
+        assert!(cache.get("docker.io", "library/nginx").is_none());
+
+        cache.insert("docker.io", "library/nginx", tags(&["1.25.0", "1.26.0"]));
+        assert_eq!(
+            cache.get("docker.io", "library/nginx"),
+            Some(tags(&["1.25.0", "1.26.0"]))
+        );
+        // Same repository on another registry is a different entry
+        assert!(cache.get("ghcr.io", "library/nginx").is_none());
+    }
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let mut cache = TagListCache::new(Duration::from_secs(120), 10);
+        let start = Instant::now();
+        cache.insert_at("docker.io", "library/nginx", tags(&["1.25.0"]), start);
+
+        assert!(
+            cache
+                .get_at(
+                    "docker.io",
+                    "library/nginx",
+                    start + Duration::from_secs(119)
+                )
+                .is_some()
+        );
+        assert!(
+            cache
+                .get_at(
+                    "docker.io",
+                    "library/nginx",
+                    start + Duration::from_secs(120)
+                )
+                .is_none()
+        );
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_contains_leaves_entries_untouched() {
+        let mut cache = TagListCache::new(Duration::from_secs(120), 2);
+        let start = Instant::now();
+        cache.insert_at("docker.io", "a", tags(&["1"]), start);
+        cache.insert_at("docker.io", "b", tags(&["1"]), start);
+
+        assert!(cache.contains_at("docker.io", "a", start + Duration::from_secs(119)));
+        assert!(!cache.contains_at("docker.io", "a", start + Duration::from_secs(120)));
+        assert!(!cache.contains("ghcr.io", "a"));
+        // An expired entry is reported missing but not removed
+        assert_eq!(cache.len(), 2);
+
+        // Looking "a" up didn't make it more recent, so it is evicted first
+        cache.insert("docker.io", "c", tags(&["1"]));
+        assert!(!cache.contains("docker.io", "a"));
+        assert!(cache.contains("docker.io", "b"));
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = TagListCache::new(Duration::from_secs(120), 2);
+        cache.insert("docker.io", "a", tags(&["1"]));
+        cache.insert("docker.io", "b", tags(&["1"]));
+
+        // Touch "a" so "b" becomes the least recently used
+        assert!(cache.get("docker.io", "a").is_some());
+        cache.insert("docker.io", "c", tags(&["1"]));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("docker.io", "a").is_some());
+        assert!(cache.get("docker.io", "b").is_none());
+        assert!(cache.get("docker.io", "c").is_some());
+    }
+
+    #[test]
+    fn test_reinsert_replaces_entry() {
+        let mut cache = TagListCache::new(Duration::from_secs(120), 2);
+        cache.insert("docker.io", "a", tags(&["1"]));
+        cache.insert("docker.io", "a", tags(&["1", "2"]));
+
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get("docker.io", "a"), Some(tags(&["1", "2"])));
+    }
+
+    #[test]
+    fn test_sharded_cache_get_and_insert() {
+        let cache: ShardedCache<String> = ShardedCache::default();
+        assert!(cache.is_empty());
+        assert_eq!(cache.get("nginx:1.25"), None);
+
+        for i in 0..100 {
+            cache.insert(&format!("app-{}", i), format!("1.{}.0", i));
+        }
+        cache.insert("app-7", "2.0.0".to_string());
+
+        assert_eq!(cache.len(), 100);
+        assert_eq!(cache.get("app-7"), Some("2.0.0".to_string()));
+        assert_eq!(cache.get("app-42"), Some("1.42.0".to_string()));
+    }
+
+    #[test]
+    fn test_keys_spread_over_shards() {
+        let cache: ShardedCache<u32> = ShardedCache::new(4);
+        let used: std::collections::HashSet<usize> = (0..100)
+            .map(|i| cache.shard_index(&format!("registry.example.com/app-{}", i)))
+            .collect();
+        assert_eq!(used.len(), 4);
+        // A key always maps to the same shard
+        assert_eq!(cache.shard_index("nginx"), cache.shard_index("nginx"));
+        // Zero shards is treated as one
+        assert_eq!(ShardedCache::<u32>::new(0).shard_index("nginx"), 0);
+    }
+
+    #[test]
+    fn test_concurrent_inserts() {
+        let cache = std::sync::Arc::new(ShardedCache::new(DEFAULT_SHARDS));
+        let handles: Vec<_> = (0..8)
+            .map(|worker| {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    for i in 0..250 {
+                        cache.insert(&format!("{}-{}", worker, i), i);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(cache.len(), 2000);
+        assert_eq!(cache.get("3-249"), Some(249));
+    }
+}
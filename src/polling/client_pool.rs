@@ -0,0 +1,133 @@
+//! OCI clients shared between the images and charts of a poll cycle.
+//!
+//! An `OciClient` keeps a connection pool and the tokens it obtained, so
+//! reusing it for every repository of a registry saves a TLS handshake and a
+//! token request per image. A client also pins the first credentials it is
+//! given for a registry, so clients are keyed by registry and credentials:
+//! anonymous requests and each set of credentials get their own client. The
+//! pool is cleared at the start of every poll cycle, so rotated credentials
+//! and TLS settings take effect on the next cycle.
+
+use crate::metrics::REGISTRY_CLIENTS_ACTIVE;
+use oci_distribution::Client as OciClient;
+use oci_distribution::secrets::RegistryAuth;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+/// OCI clients by registry and credentials
+#[derive(Default)]
+pub struct RegistryClientPool {
+    clients: Mutex<HashMap<String, Arc<OciClient>>>,
+}
+
+impl RegistryClientPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The client for `registry` and `auth`, built with `build` on first use
+    pub fn get_or_insert_with(
+        &self,
+        registry: &str,
+        auth: &RegistryAuth,
+        build: impl FnOnce() -> OciClient,
+    ) -> Arc<OciClient> {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        let client = clients
+            .entry(pool_key(registry, auth))
+            .or_insert_with(|| Arc::new(build()))
+            .clone();
+        REGISTRY_CLIENTS_ACTIVE.set(clients.len() as i64);
+        client
+    }
+
+    /// Drop all clients, so the next requests build them afresh
+    pub fn clear(&self) {
+        self.clients
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        REGISTRY_CLIENTS_ACTIVE.set(0);
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Registry plus the credentials, without keeping the password in the key
+fn pool_key(registry: &str, auth: &RegistryAuth) -> String {
+    match auth {
+        RegistryAuth::Anonymous => registry.to_string(),
+        RegistryAuth::Basic(username, password) => {
+            let mut hasher = DefaultHasher::new();
+            password.hash(&mut hasher);
+            format!("{}#{}:{:x}", registry, username, hasher.finish())
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oci_distribution::client::ClientConfig;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_pool_is_send_and_sync() {
+        assert_send_sync::<RegistryClientPool>();
+    }
+
+    #[test]
+    fn test_clients_are_reused_per_registry_and_credentials() {
+        let pool = RegistryClientPool::new();
+        let mut built = 0;
+        let mut client = |registry: &str, auth: &RegistryAuth| {
+            pool.get_or_insert_with(registry, auth, || {
+                built += 1;
+                OciClient::new(ClientConfig::default())
+            })
+        };
+
+        let anonymous = RegistryAuth::Anonymous;
+        let alice = RegistryAuth::Basic("alice".to_string(), "secret".to_string());
+        let rotated = RegistryAuth::Basic("alice".to_string(), "rotated".to_string());
+
+        let first = client("ghcr.io", &anonymous);
+        assert!(Arc::ptr_eq(&first, &client("ghcr.io", &anonymous)));
+        assert!(!Arc::ptr_eq(&first, &client("docker.io", &anonymous)));
+        let authenticated = client("ghcr.io", &alice);
+        assert!(!Arc::ptr_eq(&first, &authenticated));
+        assert!(Arc::ptr_eq(&authenticated, &client("ghcr.io", &alice)));
+        assert!(!Arc::ptr_eq(&authenticated, &client("ghcr.io", &rotated)));
+        assert_eq!(built, 4);
+        assert_eq!(pool.len(), 4);
+
+        pool.clear();
+        assert!(pool.is_empty());
+        assert!(!Arc::ptr_eq(
+            &first,
+            &pool.get_or_insert_with("ghcr.io", &anonymous, || {
+                OciClient::new(ClientConfig::default())
+            })
+        ));
+    }
+
+    #[test]
+    fn test_pool_key_hides_password() {
+        let key = pool_key(
+            "ghcr.io",
+            &RegistryAuth::Basic("alice".to_string(), "secret".to_string()),
+        );
+        assert!(key.starts_with("ghcr.io#alice:"));
+        assert!(!key.contains("secret"));
+        assert_eq!(pool_key("ghcr.io", &RegistryAuth::Anonymous), "ghcr.io");
+    }
+}
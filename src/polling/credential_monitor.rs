@@ -0,0 +1,300 @@
+//! Proactive refresh of registry credentials that expire.
+//!
+//! ACR tokens, GCP access tokens and Vault credentials are otherwise only
+//! renewed when a poll finds them expired, and a poll that cannot renew them
+//! falls back to anonymous access. Every minute the monitor refreshes cached
+//! credentials expiring within ten minutes instead. A failed refresh keeps the
+//! cached credentials, is retried with backoff, and emits a
+//! `CredentialExpirySoon` warning event on the Headwind pod; after three
+//! consecutive failures the credentials are dropped so the next poll fetches
+//! new ones itself.
+
+use super::auth::{AuthManager, CachedCredentialExpiry, CredentialProvider};
+use crate::metrics::{CREDENTIAL_REFRESH_FAILURES_TOTAL, CREDENTIAL_REFRESH_TOTAL};
+use k8s_openapi::api::core::v1::ObjectReference;
+use kube::Client;
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// How often cached credentials are checked
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Credentials expiring within this window are refreshed
+pub const REFRESH_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Consecutive failed refreshes after which credentials are dropped
+pub const MAX_CONSECUTIVE_FAILURES: u32 = 3;
+
+/// Reason of the warning event emitted when a refresh fails
+pub const CREDENTIAL_EXPIRY_SOON_REASON: &str = "CredentialExpirySoon";
+
+/// Whether `expiry` is within [`REFRESH_WINDOW`] of `now`, or already past
+fn due_for_refresh(expiry: &CachedCredentialExpiry, now: Instant) -> bool {
+    expiry.expires_at.saturating_duration_since(now) <= REFRESH_WINDOW
+}
+
+/// Wait before retrying after `failures` consecutive failures: one check
+/// interval, doubling with every further failure
+fn retry_delay(failures: u32) -> Duration {
+    CHECK_INTERVAL * 2u32.pow(failures.saturating_sub(1))
+}
+
+type CredentialKey = (CredentialProvider, String);
+
+struct FailureState {
+    failures: u32,
+    retry_at: Instant,
+}
+
+/// Consecutive refresh failures per credential
+#[derive(Default)]
+struct RefreshFailures {
+    entries: HashMap<CredentialKey, FailureState>,
+}
+
+impl RefreshFailures {
+    /// Whether a refresh of `key` may be attempted at `now`
+    fn may_retry(&self, key: &CredentialKey, now: Instant) -> bool {
+        self.entries
+            .get(key)
+            .is_none_or(|state| now >= state.retry_at)
+    }
+
+    /// Count a failure of `key` at `now`, returning the consecutive failures
+    fn record_failure(&mut self, key: CredentialKey, now: Instant) -> u32 {
+        let state = self.entries.entry(key).or_insert(FailureState {
+            failures: 0,
+            retry_at: now,
+        });
+        state.failures += 1;
+        state.retry_at = now + retry_delay(state.failures);
+        state.failures
+    }
+
+    fn clear(&mut self, key: &CredentialKey) {
+        self.entries.remove(key);
+    }
+
+    /// Forget failures of credentials that are no longer cached
+    fn retain_cached(&mut self, expiries: &[CachedCredentialExpiry]) {
+        self.entries.retain(|(provider, registry), _| {
+            expiries
+                .iter()
+                .any(|e| e.provider == *provider && e.registry == *registry)
+        });
+    }
+}
+
+/// Refreshes cached registry credentials before they expire
+pub struct CredentialExpiryMonitor {
+    auth_manager: Arc<RwLock<AuthManager>>,
+    client: Client,
+    failures: RefreshFailures,
+}
+
+impl CredentialExpiryMonitor {
+    pub fn new(auth_manager: Arc<RwLock<AuthManager>>, client: Client) -> Self {
+        Self {
+            auth_manager,
+            client,
+            failures: RefreshFailures::default(),
+        }
+    }
+
+    /// Check cached credentials every [`CHECK_INTERVAL`]
+    pub fn start(mut self) -> JoinHandle<()> {
+        info!(
+            "Starting credential expiry monitor (interval: {}s, refresh window: {}s)",
+            CHECK_INTERVAL.as_secs(),
+            REFRESH_WINDOW.as_secs()
+        );
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                self.check(Instant::now()).await;
+            }
+        })
+    }
+
+    /// Refresh the credentials due at `now`
+    pub async fn check(&mut self, now: Instant) {
+        let expiries = self.auth_manager.read().await.credential_expiries();
+        self.failures.retain_cached(&expiries);
+
+        for expiry in expiries {
+            let key = (expiry.provider, expiry.registry.clone());
+            if !due_for_refresh(&expiry, now) || !self.failures.may_retry(&key, now) {
+                continue;
+            }
+            self.refresh(expiry, key, now).await;
+        }
+    }
+
+    async fn refresh(&mut self, expiry: CachedCredentialExpiry, key: CredentialKey, now: Instant) {
+        let mut auth_manager = self.auth_manager.write().await;
+        // Credentials shared between registries may have been refreshed already
+        let still_due = auth_manager.credential_expiries().iter().any(|e| {
+            e.provider == expiry.provider
+                && e.registry == expiry.registry
+                && due_for_refresh(e, now)
+        });
+        if !still_due {
+            return;
+        }
+
+        debug!(
+            "Refreshing {} credentials for {}, expiring in {}s",
+            expiry.provider,
+            expiry.registry,
+            expiry.expires_at.saturating_duration_since(now).as_secs()
+        );
+        CREDENTIAL_REFRESH_TOTAL
+            .with_label_values(&[&expiry.registry])
+            .inc();
+        let error = match auth_manager
+            .refresh_credentials(expiry.provider, &expiry.registry)
+            .await
+        {
+            Ok(()) => {
+                info!(
+                    "Refreshed {} credentials for {}",
+                    expiry.provider, expiry.registry
+                );
+                self.failures.clear(&key);
+                return;
+            },
+            Err(e) => e,
+        };
+
+        CREDENTIAL_REFRESH_FAILURES_TOTAL
+            .with_label_values(&[&expiry.registry])
+            .inc();
+        let failures = self.failures.record_failure(key.clone(), now);
+        let note = format!(
+            "Refreshing {} credentials for {} failed ({}/{}), they expire in {}s: {:#}",
+            expiry.provider,
+            expiry.registry,
+            failures,
+            MAX_CONSECUTIVE_FAILURES,
+            expiry.expires_at.saturating_duration_since(now).as_secs(),
+            error
+        );
+        warn!("{}", note);
+        if failures >= MAX_CONSECUTIVE_FAILURES {
+            warn!(
+                "Dropping {} credentials for {} after {} failed refreshes",
+                expiry.provider, expiry.registry, failures
+            );
+            auth_manager.invalidate_credentials(expiry.provider, &expiry.registry);
+            self.failures.clear(&key);
+        }
+        drop(auth_manager);
+
+        publish_credential_expiry_soon(&self.client, &note).await;
+    }
+}
+
+/// Emit a `CredentialExpirySoon` warning event on the Headwind pod
+async fn publish_credential_expiry_soon(client: &Client, note: &str) {
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    let event = Event {
+        type_: EventType::Warning,
+        reason: CREDENTIAL_EXPIRY_SOON_REASON.to_string(),
+        note: Some(note.to_string()),
+        action: "RefreshCredentials".to_string(),
+        secondary: None,
+    };
+    let pod = ObjectReference {
+        api_version: Some("v1".to_string()),
+        kind: Some("Pod".to_string()),
+        name: Some(crate::controller::lock::holder_identity()),
+        namespace: Some(crate::config::headwind_namespace()),
+        ..Default::default()
+    };
+    if let Err(e) = recorder.publish(&event, &pod).await {
+        warn!(
+            "Failed to publish {} event: {}",
+            CREDENTIAL_EXPIRY_SOON_REASON, e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expiry(expires_in: Duration, now: Instant) -> CachedCredentialExpiry {
+        CachedCredentialExpiry {
+            provider: CredentialProvider::Acr,
+            registry: "myregistry.azurecr.io".to_string(),
+            expires_at: now + expires_in,
+        }
+    }
+
+    #[test]
+    fn test_refresh_window() {
+        let now = Instant::now();
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+
+        assert!(!due_for_refresh(&expiry(minutes(60), now), now));
+        assert!(!due_for_refresh(&expiry(minutes(11), now), now));
+        assert!(due_for_refresh(&expiry(minutes(10), now), now));
+        assert!(due_for_refresh(&expiry(minutes(5), now), now));
+        // Already expired
+        assert!(due_for_refresh(&expiry(minutes(0), now), now + minutes(1)));
+
+        // Becomes due once the window is reached
+        let later = expiry(minutes(30), now);
+        assert!(!due_for_refresh(&later, now + minutes(19)));
+        assert!(due_for_refresh(&later, now + minutes(20)));
+    }
+
+    #[test]
+    fn test_failures_back_off() {
+        let now = Instant::now();
+        let key = (CredentialProvider::Vault, "ghcr.io".to_string());
+        let mut failures = RefreshFailures::default();
+        assert!(failures.may_retry(&key, now));
+
+        assert_eq!(failures.record_failure(key.clone(), now), 1);
+        assert!(!failures.may_retry(&key, now + Duration::from_secs(59)));
+        assert!(failures.may_retry(&key, now + CHECK_INTERVAL));
+
+        let now = now + CHECK_INTERVAL;
+        assert_eq!(failures.record_failure(key.clone(), now), 2);
+        assert!(!failures.may_retry(&key, now + CHECK_INTERVAL));
+        assert!(failures.may_retry(&key, now + CHECK_INTERVAL * 2));
+
+        assert_eq!(
+            failures.record_failure(key.clone(), now + CHECK_INTERVAL * 2),
+            MAX_CONSECUTIVE_FAILURES
+        );
+
+        // A success starts over
+        failures.clear(&key);
+        assert!(failures.may_retry(&key, now));
+        assert_eq!(failures.record_failure(key, now), 1);
+    }
+
+    #[test]
+    fn test_failures_of_dropped_credentials_are_forgotten() {
+        let now = Instant::now();
+        let cached = expiry(Duration::from_secs(60), now);
+        let acr = (cached.provider, cached.registry.clone());
+        let vault = (CredentialProvider::Vault, "ghcr.io".to_string());
+        let mut failures = RefreshFailures::default();
+        failures.record_failure(acr.clone(), now);
+        failures.record_failure(vault.clone(), now);
+
+        failures.retain_cached(&[cached]);
+        assert!(!failures.may_retry(&acr, now));
+        assert!(failures.may_retry(&vault, now));
+    }
+}
@@ -0,0 +1,181 @@
+//! Digest pinning: resolve a tag to the manifest digest it currently points at,
+//! so containers run `image:tag@sha256:…` and a re-pushed tag can't change what
+//! is deployed. The tag is kept in the reference so policies keep comparing tags.
+
+use super::auth::AuthManager;
+use super::insecure_registries_from_env;
+use crate::config::features::features;
+use crate::models::policy::{CredentialSource, annotations};
+use anyhow::{Context, Result};
+use kube::Client;
+use oci_distribution::client::{ClientConfig, ClientProtocol};
+use oci_distribution::{Client as OciClient, Reference, secrets::RegistryAuth};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Image a container is updated to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageReference {
+    /// `name:tag`
+    Tag(String),
+    /// `name:tag@sha256:…`, pinned to the digest the tag resolved to
+    Digest(String),
+}
+
+impl ImageReference {
+    /// Pin `image` to `digest`, replacing any digest it already carries
+    pub fn pinned(image: &str, digest: &str) -> Self {
+        Self::Digest(format!("{}@{}", strip_digest(image), digest))
+    }
+
+    /// The image as written to the pod spec
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Tag(image) | Self::Digest(image) => image,
+        }
+    }
+}
+
+/// `image` without an `@digest` suffix
+pub fn strip_digest(image: &str) -> &str {
+    image.split_once('@').map_or(image, |(name, _)| name)
+}
+
+/// Split an image into repository and the tag (with any digest), as the
+/// StatefulSet, DaemonSet and ReplicaSet update functions expect
+pub fn split_tag(image: &str) -> Option<(&str, &str)> {
+    let name_start = image.rfind('/').map_or(0, |i| i + 1);
+    let colon = image[name_start..].find(':')? + name_start;
+    Some((&image[..colon], &image[colon + 1..]))
+}
+
+/// Digest of the manifest `image` (`name:tag`) points at, e.g. `sha256:…`.
+///
+/// Sends `HEAD /v2/{repo}/manifests/{tag}` and reads `Docker-Content-Digest`.
+pub async fn resolve_digest(
+    image: &str,
+    auth: &RegistryAuth,
+    insecure_registries: &[String],
+) -> Result<String> {
+    let reference = Reference::from_str(strip_digest(image))
+        .with_context(|| format!("Invalid image reference {}", image))?;
+    let client = OciClient::new(ClientConfig {
+        protocol: ClientProtocol::HttpsExcept(insecure_registries.to_vec()),
+        ..Default::default()
+    });
+
+    client
+        .fetch_manifest_digest(&reference, auth)
+        .await
+        .with_context(|| format!("Failed to resolve digest of {}", image))
+}
+
+/// Resolve `image` with credentials from `namespace` and return it pinned to its digest
+pub async fn pin_image(
+    client: &Client,
+    namespace: &str,
+    image: &str,
+    source: CredentialSource,
+) -> Result<ImageReference> {
+    let auth = AuthManager::new(client.clone())
+        .get_auth_for_image(image, namespace, source)
+        .await?;
+    let digest = resolve_digest(image, &auth, &insecure_registries_from_env()).await?;
+
+    Ok(ImageReference::pinned(image, &digest))
+}
+
+/// The image to deploy for `image`: pinned to its digest when the
+/// `digest_pinning` feature is enabled and the resource is annotated
+/// `headwind.sh/digest-pinning: "true"`, as is otherwise
+pub async fn target_image(
+    client: &Client,
+    namespace: &str,
+    resource_annotations: Option<&BTreeMap<String, String>>,
+    image: &str,
+) -> Result<ImageReference> {
+    let pinning = resource_annotations
+        .and_then(|a| a.get(annotations::DIGEST_PINNING))
+        .is_some_and(|v| v == "true");
+    if !features().digest_pinning || !pinning {
+        return Ok(ImageReference::Tag(image.to_string()));
+    }
+
+    let source = resource_annotations
+        .and_then(|a| a.get(annotations::CREDENTIAL_SOURCE))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_default();
+    pin_image(client, namespace, image, source)
+        .await
+        .context("Digest pinning failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeRegistry;
+
+    #[test]
+    fn test_pinned_reference() {
+        let pinned = ImageReference::pinned("nginx:1.26.0", "sha256:abc");
+        assert_eq!(
+            pinned,
+            ImageReference::Digest("nginx:1.26.0@sha256:abc".to_string())
+        );
+        assert_eq!(pinned.as_str(), "nginx:1.26.0@sha256:abc");
+
+        // Re-pinning replaces the old digest
+        let repinned = ImageReference::pinned(pinned.as_str(), "sha256:def");
+        assert_eq!(repinned.as_str(), "nginx:1.26.0@sha256:def");
+
+        assert_eq!(
+            ImageReference::Tag("nginx:1.26.0".to_string()).as_str(),
+            "nginx:1.26.0"
+        );
+    }
+
+    #[test]
+    fn test_split_tag() {
+        assert_eq!(split_tag("nginx:1.25.0"), Some(("nginx", "1.25.0")));
+        assert_eq!(
+            split_tag("registry.io:5000/team/app:v2"),
+            Some(("registry.io:5000/team/app", "v2"))
+        );
+        assert_eq!(
+            split_tag("nginx:1.25.0@sha256:abc"),
+            Some(("nginx", "1.25.0@sha256:abc"))
+        );
+        assert_eq!(split_tag("registry.io:5000/team/app"), None);
+    }
+
+    #[test]
+    fn test_strip_digest() {
+        assert_eq!(strip_digest("nginx:1.26.0@sha256:abc"), "nginx:1.26.0");
+        assert_eq!(
+            strip_digest("localhost:5000/app:1.0"),
+            "localhost:5000/app:1.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_digest() {
+        let registry = FakeRegistry::start().await.unwrap();
+        registry.add_tags("team/app", &["1.0.0", "1.1.0"]);
+
+        let image = format!("{}/team/app:1.1.0", registry.host());
+        let digest = resolve_digest(&image, &RegistryAuth::Anonymous, &[registry.host()])
+            .await
+            .unwrap();
+
+        assert!(digest.starts_with("sha256:"));
+        assert_eq!(Some(digest), registry.digest("team/app", "1.1.0"));
+
+        let missing = format!("{}/team/app:9.9.9", registry.host());
+        assert!(
+            resolve_digest(&missing, &RegistryAuth::Anonymous, &[registry.host()])
+                .await
+                .is_err()
+        );
+        registry.shutdown().await;
+    }
+}
@@ -0,0 +1,454 @@
+//! Google Container Registry and Artifact Registry credentials from GKE
+//! Workload Identity.
+//!
+//! On GKE an access token of the pod's Google service account is requested
+//! from the metadata server at
+//! `GET http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token`
+//! and cached until one minute before it expires. Whether Headwind runs on GKE
+//! is detected once, by checking that the metadata server answers. Elsewhere
+//! the service account key in `GOOGLE_APPLICATION_CREDENTIALS` is used.
+//!
+//! The OCI client only speaks HTTP Basic. GCR and Artifact Registry accept an
+//! access token as the password of the `oauth2accesstoken` user and a key
+//! file as the password of the `_json_key` user, so that is what
+//! [`GcpWorkloadIdentityProvider::get_credentials`] returns.
+
+use super::auth::RegistryCredentials;
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Metadata server of GCE and GKE nodes
+const METADATA_URL: &str = "http://metadata.google.internal/computeMetadata/v1";
+
+/// Token of the service account the pod runs as under Workload Identity
+const TOKEN_PATH: &str = "/instance/service-accounts/default/token";
+
+/// Header the metadata server requires on requests and sets on responses
+const METADATA_FLAVOR: (&str, &str) = ("Metadata-Flavor", "Google");
+
+/// Username GCR and Artifact Registry expect with an access token as password
+pub const ACCESS_TOKEN_USERNAME: &str = "oauth2accesstoken";
+
+/// Username GCR and Artifact Registry expect with a key file as password
+pub const JSON_KEY_USERNAME: &str = "_json_key";
+
+/// Tokens are refreshed this long before they expire
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// How long to wait for the metadata server when detecting GKE
+const DETECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Whether `registry` is Google Container Registry or Artifact Registry
+pub fn is_gcp_registry(registry: &str) -> bool {
+    matches!(
+        registry,
+        "gcr.io" | "us.gcr.io" | "eu.gcr.io" | "asia.gcr.io"
+    ) || registry.ends_with(".pkg.dev")
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataToken {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+    expires_at: Instant,
+}
+
+/// Obtains GCR and Artifact Registry credentials without a key file on GKE
+pub struct GcpWorkloadIdentityProvider {
+    metadata_url: String,
+    /// Service account key used when not running on GKE
+    key_file: Option<PathBuf>,
+    http: reqwest::Client,
+    /// Whether the metadata server answered, once checked
+    on_gke: Option<bool>,
+    token: Option<CachedToken>,
+    /// Registries the access token was handed out for
+    registries: HashSet<String>,
+    /// Contents of `key_file`, once read
+    json_key: Option<String>,
+}
+
+impl GcpWorkloadIdentityProvider {
+    pub fn new(key_file: Option<PathBuf>) -> Self {
+        Self {
+            metadata_url: METADATA_URL.to_string(),
+            key_file,
+            http: reqwest::Client::new(),
+            on_gke: None,
+            token: None,
+            registries: HashSet::new(),
+            json_key: None,
+        }
+    }
+
+    /// Provider falling back to `GOOGLE_APPLICATION_CREDENTIALS`, or `None` if
+    /// `HEADWIND_GCP_WORKLOAD_IDENTITY` is `false`
+    pub fn from_env() -> Option<Self> {
+        if std::env::var("HEADWIND_GCP_WORKLOAD_IDENTITY").is_ok_and(|v| v == "false") {
+            return None;
+        }
+        let key_file = std::env::var("GOOGLE_APPLICATION_CREDENTIALS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from);
+        Some(Self::new(key_file))
+    }
+
+    /// Talk to a mock metadata server instead of GKE's
+    #[cfg(test)]
+    pub fn with_metadata_url(mut self, metadata_url: &str) -> Self {
+        self.metadata_url = metadata_url.to_string();
+        self
+    }
+
+    /// Credentials for `registry` obtained earlier and not due for refresh
+    pub fn cached_credentials(&self, registry: &str) -> Option<RegistryCredentials> {
+        if let Some(token) = self.cached() {
+            return Some(credentials(
+                registry,
+                ACCESS_TOKEN_USERNAME,
+                &token.access_token,
+            ));
+        }
+        self.json_key
+            .as_ref()
+            .map(|key| credentials(registry, JSON_KEY_USERNAME, key))
+    }
+
+    /// Basic credentials for `registry`, or `None` when neither the metadata
+    /// server nor a key file is available
+    pub async fn get_credentials(&mut self, registry: &str) -> Result<Option<RegistryCredentials>> {
+        if self.on_gke().await {
+            let token = self.access_token().await?;
+            self.registries.insert(registry.to_string());
+            return Ok(Some(credentials(registry, ACCESS_TOKEN_USERNAME, &token)));
+        }
+
+        if self.json_key.is_none()
+            && let Some(key_file) = &self.key_file
+        {
+            let key = tokio::fs::read_to_string(key_file)
+                .await
+                .with_context(|| format!("Failed to read {}", key_file.display()))?;
+            self.json_key = Some(key);
+        }
+        Ok(self
+            .json_key
+            .as_ref()
+            .map(|key| credentials(registry, JSON_KEY_USERNAME, key)))
+    }
+
+    /// Access token of the pod's Google service account, from the cache or the metadata server
+    pub async fn access_token(&mut self) -> Result<String> {
+        if let Some(token) = self.cached() {
+            debug!("Using cached GCP access token");
+            return Ok(token.access_token.clone());
+        }
+        self.fetch_access_token().await
+    }
+
+    /// Registries the cached access token is used for and when it expires.
+    /// Key files do not expire and are not listed.
+    pub fn expiries(&self) -> Vec<(String, Instant)> {
+        let Some(token) = &self.token else {
+            return Vec::new();
+        };
+        self.registries
+            .iter()
+            .map(|registry| (registry.clone(), token.expires_at))
+            .collect()
+    }
+
+    /// Replace the cached access token with a new one, keeping the old token
+    /// if that fails. The token is shared by all registries.
+    pub async fn refresh(&mut self) -> Result<()> {
+        self.fetch_access_token().await.map(|_| ())
+    }
+
+    /// Forget the cached access token
+    pub fn invalidate(&mut self) {
+        self.token = None;
+    }
+
+    async fn fetch_access_token(&mut self) -> Result<String> {
+        let response = self
+            .http
+            .get(format!("{}{}", self.metadata_url, TOKEN_PATH))
+            .header(METADATA_FLAVOR.0, METADATA_FLAVOR.1)
+            .send()
+            .await
+            .context("Failed to reach the GKE metadata server")?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!(
+                "Metadata server token request failed with {}: {}",
+                status,
+                body.trim()
+            );
+        }
+        let token: MetadataToken = response
+            .json()
+            .await
+            .context("Invalid metadata server token response")?;
+
+        let ttl = Duration::from_secs(token.expires_in);
+        debug!("Obtained GCP access token valid for {}s", ttl.as_secs());
+        let now = Instant::now();
+        self.token = Some(CachedToken {
+            access_token: token.access_token.clone(),
+            refresh_at: now + ttl.saturating_sub(REFRESH_MARGIN),
+            expires_at: now + ttl,
+        });
+        Ok(token.access_token)
+    }
+
+    /// Forget the cached token and key
+    #[allow(dead_code)] // Mirrors AuthManager::clear_cache
+    pub fn clear_cache(&mut self) {
+        self.token = None;
+        self.json_key = None;
+    }
+
+    fn cached(&self) -> Option<&CachedToken> {
+        self.token
+            .as_ref()
+            .filter(|token| Instant::now() < token.refresh_at)
+    }
+
+    /// Whether the metadata server is reachable, checked on first use
+    async fn on_gke(&mut self) -> bool {
+        if let Some(on_gke) = self.on_gke {
+            return on_gke;
+        }
+
+        let on_gke = self
+            .http
+            .get(format!("{}/", self.metadata_url))
+            .header(METADATA_FLAVOR.0, METADATA_FLAVOR.1)
+            .timeout(DETECT_TIMEOUT)
+            .send()
+            .await
+            .is_ok_and(|response| {
+                response
+                    .headers()
+                    .get(METADATA_FLAVOR.0)
+                    .is_some_and(|v| v == METADATA_FLAVOR.1)
+            });
+        if on_gke {
+            info!(
+                "GKE metadata server found, using Workload Identity for GCR and Artifact Registry"
+            );
+        } else {
+            debug!("GKE metadata server not reachable");
+        }
+        self.on_gke = Some(on_gke);
+        on_gke
+    }
+}
+
+fn credentials(registry: &str, username: &str, password: &str) -> RegistryCredentials {
+    RegistryCredentials {
+        registry: registry.to_string(),
+        username: username.to_string(),
+        password: password.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+    };
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct MockMetadata {
+        /// Seconds until issued tokens expire
+        expires_in: u64,
+        token_requests: usize,
+        /// Answer token requests with 503
+        unavailable: bool,
+    }
+
+    type Shared = Arc<Mutex<MockMetadata>>;
+
+    fn flavor_header_missing(headers: &HeaderMap) -> bool {
+        headers.get("metadata-flavor").is_none_or(|v| v != "Google")
+    }
+
+    async fn root(headers: HeaderMap) -> Response {
+        if flavor_header_missing(&headers) {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        ([("Metadata-Flavor", "Google")], "computeMetadata/\n").into_response()
+    }
+
+    async fn token(State(state): State<Shared>, headers: HeaderMap) -> Response {
+        if flavor_header_missing(&headers) {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        let mut state = state.lock().unwrap();
+        if state.unavailable {
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+        state.token_requests += 1;
+        (
+            [("Metadata-Flavor", "Google")],
+            Json(json!({
+                "access_token": format!("ya29.token-{}", state.token_requests),
+                "expires_in": state.expires_in,
+                "token_type": "Bearer"
+            })),
+        )
+            .into_response()
+    }
+
+    async fn start_mock(state: Shared) -> String {
+        let app = Router::new()
+            .route("/computeMetadata/v1/", get(root))
+            .route(
+                "/computeMetadata/v1/instance/service-accounts/default/token",
+                get(token),
+            )
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/computeMetadata/v1", addr)
+    }
+
+    #[test]
+    fn test_is_gcp_registry() {
+        for registry in [
+            "gcr.io",
+            "us.gcr.io",
+            "eu.gcr.io",
+            "asia.gcr.io",
+            "europe-west1-docker.pkg.dev",
+        ] {
+            assert!(is_gcp_registry(registry), "{}", registry);
+        }
+        assert!(!is_gcp_registry("gcr.io.example.com"));
+        assert!(!is_gcp_registry("pkg.dev.example.com"));
+        assert!(!is_gcp_registry("ghcr.io"));
+    }
+
+    #[tokio::test]
+    async fn test_metadata_server_token() {
+        let state = Shared::new(Mutex::new(MockMetadata {
+            expires_in: 3599,
+            ..Default::default()
+        }));
+        let url = start_mock(state.clone()).await;
+        let mut provider = GcpWorkloadIdentityProvider::new(None).with_metadata_url(&url);
+
+        let credentials = provider
+            .get_credentials("us-docker.pkg.dev")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(credentials.username, ACCESS_TOKEN_USERNAME);
+        assert_eq!(credentials.password, "ya29.token-1");
+
+        // Served from the cache for every registry
+        let cached = provider.cached_credentials("gcr.io").unwrap();
+        assert_eq!(cached.password, "ya29.token-1");
+        provider.get_credentials("gcr.io").await.unwrap();
+        assert_eq!(state.lock().unwrap().token_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_token_refreshed_near_expiry() {
+        // Tokens expiring within the one minute margin are never reused
+        let state = Shared::new(Mutex::new(MockMetadata {
+            expires_in: 30,
+            ..Default::default()
+        }));
+        let url = start_mock(state.clone()).await;
+        let mut provider = GcpWorkloadIdentityProvider::new(None).with_metadata_url(&url);
+
+        let first = provider.access_token().await.unwrap();
+        assert!(provider.cached_credentials("gcr.io").is_none());
+        let second = provider.access_token().await.unwrap();
+        assert_ne!(first, second);
+        assert_eq!(state.lock().unwrap().token_requests, 2);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_replaces_token_and_keeps_it_on_failure() {
+        let state = Shared::new(Mutex::new(MockMetadata {
+            expires_in: 300,
+            ..Default::default()
+        }));
+        let url = start_mock(state.clone()).await;
+        let mut provider = GcpWorkloadIdentityProvider::new(None).with_metadata_url(&url);
+        assert!(provider.expiries().is_empty());
+
+        provider.get_credentials("gcr.io").await.unwrap();
+        let expiries = provider.expiries();
+        assert_eq!(expiries.len(), 1);
+        assert_eq!(expiries[0].0, "gcr.io");
+        assert!(expiries[0].1 <= Instant::now() + Duration::from_secs(300));
+
+        provider.refresh().await.unwrap();
+        assert_eq!(
+            provider.cached_credentials("gcr.io").unwrap().password,
+            "ya29.token-2"
+        );
+
+        state.lock().unwrap().unavailable = true;
+        assert!(provider.refresh().await.is_err());
+        assert_eq!(
+            provider.cached_credentials("gcr.io").unwrap().password,
+            "ya29.token-2"
+        );
+
+        provider.invalidate();
+        assert!(provider.cached_credentials("gcr.io").is_none());
+        assert!(provider.expiries().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_key_file_off_gke() {
+        let key_file =
+            std::env::temp_dir().join(format!("headwind-gcp-key-{}.json", std::process::id()));
+        let key = r#"{"type": "service_account", "project_id": "my-project"}"#;
+        std::fs::write(&key_file, key).unwrap();
+
+        // Nothing listens on port 9 of localhost
+        let mut provider = GcpWorkloadIdentityProvider::new(Some(key_file.clone()))
+            .with_metadata_url("http://127.0.0.1:9/computeMetadata/v1");
+        let credentials = provider.get_credentials("gcr.io").await.unwrap().unwrap();
+        std::fs::remove_file(&key_file).unwrap();
+
+        assert_eq!(credentials.username, JSON_KEY_USERNAME);
+        assert_eq!(credentials.password, key);
+        assert_eq!(provider.on_gke, Some(false));
+        assert_eq!(
+            provider.cached_credentials("eu.gcr.io").unwrap().password,
+            key
+        );
+
+        // Without a key file there is nothing to offer
+        let mut provider = GcpWorkloadIdentityProvider::new(None)
+            .with_metadata_url("http://127.0.0.1:9/computeMetadata/v1");
+        assert!(provider.get_credentials("gcr.io").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,182 @@
+//! Image labels: read the OCI annotations an image was built with (source
+//! repository, revision, description) from its config blob, so notifications
+//! can link to what changed in the new version.
+
+use super::auth::AuthManager;
+use super::digest::strip_digest;
+use super::insecure_registries_from_env;
+use crate::models::policy::CredentialSource;
+use anyhow::{Context, Result};
+use kube::Client;
+use oci_distribution::client::{ClientConfig, ClientProtocol};
+use oci_distribution::{Client as OciClient, Reference, secrets::RegistryAuth};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+pub const SOURCE: &str = "org.opencontainers.image.source";
+pub const REVISION: &str = "org.opencontainers.image.revision";
+pub const DESCRIPTION: &str = "org.opencontainers.image.description";
+
+/// Labels in the image config of `image`.
+///
+/// Pulls the manifest, then its config blob, and returns `config.Labels`
+/// (empty when the image has none).
+pub async fn fetch_image_labels(
+    image: &str,
+    auth: &RegistryAuth,
+    insecure_registries: &[String],
+) -> Result<HashMap<String, String>> {
+    let reference = Reference::from_str(strip_digest(image))
+        .with_context(|| format!("Invalid image reference {}", image))?;
+    let client = OciClient::new(ClientConfig {
+        protocol: ClientProtocol::HttpsExcept(insecure_registries.to_vec()),
+        ..Default::default()
+    });
+
+    let (_, _, config) = client
+        .pull_manifest_and_config(&reference, auth)
+        .await
+        .with_context(|| format!("Failed to pull image config of {}", image))?;
+    let config: serde_json::Value = serde_json::from_str(&config)
+        .with_context(|| format!("Invalid image config of {}", image))?;
+
+    Ok(config["config"]["Labels"]
+        .as_object()
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Fetch the labels of `image` with credentials from `namespace`
+pub async fn labels_for_image(
+    client: &Client,
+    namespace: &str,
+    image: &str,
+    source: CredentialSource,
+) -> Result<HashMap<String, String>> {
+    let auth = AuthManager::new(client.clone())
+        .get_auth_for_image(image, namespace, source)
+        .await?;
+    fetch_image_labels(image, &auth, &insecure_registries_from_env()).await
+}
+
+/// Commit the image was built from
+pub fn source_revision(labels: &HashMap<String, String>) -> Option<String> {
+    labels.get(REVISION).filter(|r| !r.is_empty()).cloned()
+}
+
+/// Where to read about the changes in an image.
+///
+/// A description that is a URL wins; otherwise the built commit in the source
+/// repository, or the repository itself when the revision is unknown.
+pub fn changelog_url(labels: &HashMap<String, String>) -> Option<String> {
+    if let Some(description) = labels.get(DESCRIPTION).filter(|d| is_url(d)) {
+        return Some(description.clone());
+    }
+
+    let source = labels.get(SOURCE).filter(|s| is_url(s))?;
+    let source = source.trim_end_matches('/').trim_end_matches(".git");
+    Some(match source_revision(labels) {
+        Some(revision) => format!("{}/commit/{}", source, revision),
+        None => source.to_string(),
+    })
+}
+
+fn is_url(value: &str) -> bool {
+    value.starts_with("https://") || value.starts_with("http://")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::FakeRegistry;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_changelog_url() {
+        // Commit link from source and revision
+        let commit = labels(&[
+            (SOURCE, "https://github.com/acme/app.git"),
+            (REVISION, "4f2c9e1"),
+        ]);
+        assert_eq!(
+            changelog_url(&commit).as_deref(),
+            Some("https://github.com/acme/app/commit/4f2c9e1")
+        );
+        assert_eq!(source_revision(&commit).as_deref(), Some("4f2c9e1"));
+
+        // A description URL takes precedence
+        let described = labels(&[
+            (SOURCE, "https://github.com/acme/app"),
+            (
+                DESCRIPTION,
+                "https://github.com/acme/app/releases/tag/v1.2.0",
+            ),
+        ]);
+        assert_eq!(
+            changelog_url(&described).as_deref(),
+            Some("https://github.com/acme/app/releases/tag/v1.2.0")
+        );
+
+        // Plain-text descriptions and non-URL sources are ignored
+        let repo_only = labels(&[
+            (SOURCE, "https://github.com/acme/app/"),
+            (DESCRIPTION, "The Acme app"),
+        ]);
+        assert_eq!(
+            changelog_url(&repo_only).as_deref(),
+            Some("https://github.com/acme/app")
+        );
+        assert_eq!(changelog_url(&labels(&[(SOURCE, "acme/app")])), None);
+        assert_eq!(source_revision(&labels(&[(REVISION, "")])), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_image_labels() {
+        let registry = FakeRegistry::start().await.unwrap();
+        registry.add_tags("team/app", &["1.0.0", "1.1.0"]);
+        registry.set_labels(
+            "team/app",
+            "1.1.0",
+            &[
+                (SOURCE, "https://github.com/acme/app"),
+                (REVISION, "4f2c9e1"),
+            ],
+        );
+
+        let image = format!("{}/team/app:1.1.0", registry.host());
+        let fetched = fetch_image_labels(&image, &RegistryAuth::Anonymous, &[registry.host()])
+            .await
+            .unwrap();
+        assert_eq!(
+            fetched,
+            labels(&[
+                (SOURCE, "https://github.com/acme/app"),
+                (REVISION, "4f2c9e1"),
+            ])
+        );
+        assert!(
+            registry
+                .requests()
+                .iter()
+                .any(|r| r.path.starts_with("/v2/team/app/blobs/sha256:"))
+        );
+
+        // An image without labels has none
+        let image = format!("{}/team/app:1.0.0", registry.host());
+        let fetched = fetch_image_labels(&image, &RegistryAuth::Anonymous, &[registry.host()])
+            .await
+            .unwrap();
+        assert!(fetched.is_empty());
+    }
+}
@@ -1,18 +1,47 @@
+mod acr;
 mod auth;
+mod cache;
+pub mod client_pool;
+mod credential_monitor;
+pub mod digest;
+mod gcp;
+pub mod labels;
+pub mod push_date;
+pub mod registry;
+mod simulate;
+pub mod tls;
+mod vault;
 
 use self::auth::AuthManager;
+pub use self::cache::ShardedCache;
+use self::cache::TagListCache;
+use self::registry::{ArtifactoryTagLister, NexusTagLister, QuayTagLister};
+use crate::cache::list_all;
+use crate::config::features::features;
 use crate::metrics::{
-    POLLING_CYCLES_TOTAL, POLLING_HELM_CHARTS_CHECKED, POLLING_HELM_NEW_VERSIONS_FOUND,
-    POLLING_IMAGES_CHECKED, POLLING_NEW_TAGS_FOUND, POLLING_RESOURCES_FILTERED,
+    POLLING_CYCLE_DURATION_SECONDS, POLLING_CYCLES_TOTAL, POLLING_HELM_CHARTS_CHECKED,
+    POLLING_HELM_NEW_VERSIONS_FOUND, POLLING_IMAGES_CHECKED, POLLING_NEW_TAGS_FOUND,
+    POLLING_RESOURCES_FILTERED, TAG_CACHE_HITS_TOTAL, TAG_CACHE_MISSES_TOTAL,
+};
+use crate::models::policy::{
+    CredentialSource, EventSource, ResourcePolicy, TagNormalization, TagNormalizationRule,
+    UpdatePolicy, annotations, parse_container_names, parse_tag_normalization,
 };
-use crate::models::policy::{EventSource, ResourcePolicy, UpdatePolicy, annotations};
 use crate::models::webhook::{ChartPushEvent, ImagePushEvent};
-use crate::models::{HelmRelease, HelmRepository};
-use crate::policy::PolicyEngine;
+use crate::models::{Composition, HelmRelease, HelmRepository, KnativeService};
+use crate::policy::ignore::{is_image_ignored, is_resource_ignored};
+use crate::policy::{PolicyEngine, normalize_tag};
 use anyhow::Result;
-use k8s_openapi::api::apps::v1::Deployment;
+use client_pool::RegistryClientPool;
+use credential_monitor::CredentialExpiryMonitor;
+use futures::StreamExt;
+use k8s_openapi::api::apps::v1::{Deployment, ReplicaSet};
+use k8s_openapi::api::core::v1::PodSpec;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use kube::{Api, Client};
+use oci_distribution::errors::{OciDistributionError, OciErrorCode};
 use oci_distribution::{Client as OciClient, Reference, secrets::RegistryAuth};
+use push_date::{CreatedDateCache, sort_by_push_date};
 use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 use std::sync::Arc;
@@ -21,6 +50,10 @@ use tokio::sync::RwLock;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
 
+pub use push_date::TagSortStrategy;
+pub use simulate::SimulationResult;
+pub use tls::RegistryTlsConfig;
+
 /// Configuration for registry polling
 #[derive(Clone, Debug)]
 pub struct PollingConfig {
@@ -28,6 +61,48 @@ pub struct PollingConfig {
     pub interval: u64,
     /// Enable/disable polling
     pub enabled: bool,
+    /// Maximum number of images or charts polled at the same time
+    pub max_concurrent_polls: usize,
+    /// Registries (`host[:port]`) to reach over plain HTTP instead of HTTPS
+    pub insecure_registries: Vec<String>,
+    /// Extra CA certificates and certificate verification for registries
+    pub tls: RegistryTlsConfig,
+    /// Tag normalization of images without a tag normalization annotation
+    /// (`HEADWIND_TAG_NORMALIZATION_RULES`)
+    pub tag_normalization: Option<TagNormalizationRule>,
+    /// Glob patterns of images never polled (`HEADWIND_IGNORE_IMAGES`)
+    pub ignore_images: Vec<String>,
+    /// How tags are ordered to find the newest (`HEADWIND_POLLING_TAG_SORT`)
+    pub tag_sort: TagSortStrategy,
+}
+
+/// Tag list cache of the poller running in this process
+static RUNNING_TAG_CACHE: std::sync::OnceLock<Arc<std::sync::Mutex<TagListCache>>> =
+    std::sync::OnceLock::new();
+
+/// Whether the running poller has an unexpired tag list for the repository of
+/// `image`, or `None` if no poller is polling in this process
+pub fn is_tag_list_cached(image: &str) -> Option<bool> {
+    let cache = RUNNING_TAG_CACHE.get()?;
+    let reference = Reference::from_str(digest::strip_digest(image)).ok()?;
+    Some(
+        cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains(reference.resolve_registry(), reference.repository()),
+    )
+}
+
+/// Registries to reach over plain HTTP (`HEADWIND_POLLING_INSECURE_REGISTRIES`, comma-separated)
+pub fn insecure_registries_from_env() -> Vec<String> {
+    std::env::var("HEADWIND_POLLING_INSECURE_REGISTRIES")
+        .map(|v| {
+            v.split(',')
+                .map(|r| r.trim().to_string())
+                .filter(|r| !r.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 impl Default for PollingConfig {
@@ -35,13 +110,19 @@ impl Default for PollingConfig {
         Self {
             interval: 300,  // 5 minutes
             enabled: false, // Disabled by default, webhooks preferred
+            max_concurrent_polls: 10,
+            insecure_registries: Vec::new(),
+            tls: RegistryTlsConfig::default(),
+            tag_normalization: None,
+            ignore_images: Vec::new(),
+            tag_sort: TagSortStrategy::default(),
         }
     }
 }
 
 /// Metadata for an image to track
 #[derive(Clone, Debug)]
-pub(crate) struct ImageToTrack {
+pub struct ImageToTrack {
     image: String,
     policy: UpdatePolicy,
     #[allow(dead_code)] // Will be used for semver/glob matching in future
@@ -49,6 +130,10 @@ pub(crate) struct ImageToTrack {
     namespace: String,
     /// Per-resource polling interval in seconds (overrides global interval)
     polling_interval: Option<u64>,
+    /// Where registry credentials come from
+    credential_source: CredentialSource,
+    /// Rewriting applied to tags before versions are compared
+    tag_normalization: TagNormalization,
 }
 
 /// Metadata for a Helm chart to track
@@ -65,6 +150,8 @@ pub(crate) struct HelmChartToTrack {
     release_name: String, // HelmRelease name for correlation
     /// Per-resource polling interval in seconds (overrides global interval)
     polling_interval: Option<u64>,
+    /// Where registry credentials come from
+    credential_source: CredentialSource,
 }
 
 /// Type of Helm repository
@@ -88,7 +175,7 @@ struct CachedChartInfo {
 }
 
 /// Tracks the last seen tag and digest for each image
-type ImageCache = Arc<RwLock<HashMap<String, CachedImageInfo>>>;
+type ImageCache = Arc<ShardedCache<CachedImageInfo>>;
 
 /// Tracks the last seen version for each Helm chart
 type ChartCache = Arc<RwLock<HashMap<String, CachedChartInfo>>>;
@@ -96,6 +183,36 @@ type ChartCache = Arc<RwLock<HashMap<String, CachedChartInfo>>>;
 /// Tracks the last poll time for each resource (by unique key)
 type LastPollCache = Arc<RwLock<HashMap<String, std::time::Instant>>>;
 
+/// Number of tags requested per page when listing tags
+const TAG_PAGE_SIZE: usize = 100;
+
+/// Upper bound on pages fetched for one repository, in case a registry ignores `last`
+const MAX_TAG_PAGES: usize = 1000;
+
+impl ImageToTrack {
+    pub fn new(
+        image: impl Into<String>,
+        policy: UpdatePolicy,
+        namespace: impl Into<String>,
+    ) -> Self {
+        Self {
+            image: image.into(),
+            policy,
+            pattern: None,
+            namespace: namespace.into(),
+            polling_interval: None,
+            credential_source: CredentialSource::default(),
+            tag_normalization: TagNormalization::default(),
+        }
+    }
+
+    /// Look up registry credentials from `source` instead of imagePullSecrets
+    pub fn with_credential_source(mut self, source: CredentialSource) -> Self {
+        self.credential_source = source;
+        self
+    }
+}
+
 pub struct RegistryPoller {
     config: PollingConfig,
     cache: ImageCache,
@@ -105,6 +222,9 @@ pub struct RegistryPoller {
     chart_event_sender: crate::webhook::ChartEventSender,
     client: Client,
     auth_manager: Arc<RwLock<AuthManager>>,
+    tag_cache: Arc<std::sync::Mutex<TagListCache>>,
+    client_pool: Arc<RegistryClientPool>,
+    created_dates: Arc<std::sync::Mutex<CreatedDateCache>>,
 }
 
 impl RegistryPoller {
@@ -114,23 +234,58 @@ impl RegistryPoller {
         chart_event_sender: crate::webhook::ChartEventSender,
     ) -> Result<Self> {
         let client = Client::try_default().await?;
+        Ok(Self::with_client(
+            config,
+            event_sender,
+            chart_event_sender,
+            client,
+        ))
+    }
+
+    /// Create a poller that uses an existing Kubernetes client
+    pub fn with_client(
+        config: PollingConfig,
+        event_sender: crate::webhook::EventSender,
+        chart_event_sender: crate::webhook::ChartEventSender,
+        client: Client,
+    ) -> Self {
         let auth_manager = AuthManager::new(client.clone());
-        Ok(Self {
+        Self {
             config,
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            cache: Arc::new(ShardedCache::default()),
             chart_cache: Arc::new(RwLock::new(HashMap::new())),
             last_poll_cache: Arc::new(RwLock::new(HashMap::new())),
             event_sender,
             chart_event_sender,
             client,
             auth_manager: Arc::new(RwLock::new(auth_manager)),
+            tag_cache: Arc::new(std::sync::Mutex::new(TagListCache::from_env())),
+            client_pool: Arc::new(RegistryClientPool::new()),
+            created_dates: Arc::new(std::sync::Mutex::new(CreatedDateCache::new())),
+        }
+    }
+
+    /// OCI client for `registry` and `auth` honouring the configured insecure
+    /// registries and TLS settings, shared for the rest of the poll cycle
+    fn oci_client(&self, registry: &str, auth: &RegistryAuth) -> Arc<OciClient> {
+        self.client_pool.get_or_insert_with(registry, auth, || {
+            OciClient::new(
+                self.config
+                    .tls
+                    .client_config(registry, &self.config.insecure_registries),
+            )
         })
     }
 
+    /// OCI clients of the current poll cycle
+    pub fn client_pool(&self) -> &RegistryClientPool {
+        &self.client_pool
+    }
+
     pub async fn start(self) -> JoinHandle<()> {
         info!(
-            "Starting registry poller (enabled: {}, interval: {}s)",
-            self.config.enabled, self.config.interval
+            "Starting registry poller (enabled: {}, interval: {}s, max concurrent polls: {})",
+            self.config.enabled, self.config.interval, self.config.max_concurrent_polls
         );
 
         tokio::spawn(async move {
@@ -145,12 +300,30 @@ impl RegistryPoller {
                 }
             }
 
+            RUNNING_TAG_CACHE.get_or_init(|| self.tag_cache.clone());
+
+            let _monitor =
+                CredentialExpiryMonitor::new(self.auth_manager.clone(), self.client.clone())
+                    .start();
+
+            let interval = Duration::from_secs(self.config.interval);
             loop {
+                let started = std::time::Instant::now();
                 if let Err(e) = self.poll_registries().await {
                     error!("Error polling registries: {}", e);
                 }
-
-                tokio::time::sleep(Duration::from_secs(self.config.interval)).await;
+                let elapsed = started.elapsed();
+                POLLING_CYCLE_DURATION_SECONDS.observe(elapsed.as_secs_f64());
+                crate::metrics::status::STATUS.record_poll_cycle(chrono::Utc::now(), elapsed);
+
+                match next_cycle_delay(interval, elapsed) {
+                    Some(delay) => tokio::time::sleep(delay).await,
+                    None => warn!(
+                        "Poll cycle took {:.1}s, longer than the {}s interval; starting the next cycle immediately",
+                        elapsed.as_secs_f64(),
+                        self.config.interval
+                    ),
+                }
             }
         })
     }
@@ -159,106 +332,168 @@ impl RegistryPoller {
         debug!("Starting registry poll cycle");
         POLLING_CYCLES_TOTAL.inc();
 
+        // Pick up rotated credentials and TLS settings
+        self.client_pool.clear();
+
         let now = std::time::Instant::now();
 
         // Get list of images to track from Kubernetes
         let images = self.get_tracked_images().await?;
         info!("Found {} images to track", images.len());
 
-        // Poll each image for updates, respecting per-resource intervals
+        // Poll due images concurrently, respecting per-resource intervals
+        let mut due_images = Vec::new();
         for image_info in images {
             let key = format!("image::{}", image_info.image);
             let interval = image_info.polling_interval.unwrap_or(self.config.interval);
 
-            // Check if enough time has elapsed since last poll
-            let should_poll = {
-                let last_poll_cache = self.last_poll_cache.read().await;
-                match last_poll_cache.get(&key) {
-                    Some(last_poll) => now.duration_since(*last_poll).as_secs() >= interval,
-                    None => true, // Never polled before
-                }
-            };
-
-            if !should_poll {
+            if !self.is_due(&key, interval, now).await {
                 debug!(
                     "Skipping image {} - interval {}s not elapsed",
                     image_info.image, interval
                 );
                 continue;
             }
-
-            if let Err(e) = self.poll_image(&image_info).await {
-                error!("Failed to poll image {}: {}", image_info.image, e);
-            }
-
-            // Update last poll time
-            let mut last_poll_cache = self.last_poll_cache.write().await;
-            last_poll_cache.insert(key, now);
+            due_images.push((key, image_info));
         }
 
+        let polled = run_concurrently(
+            due_images,
+            self.config.max_concurrent_polls,
+            |(key, image_info)| async move {
+                if let Err(e) = self.poll_image(&image_info).await {
+                    error!("Failed to poll image {}: {}", image_info.image, e);
+                }
+                key
+            },
+        )
+        .await;
+        self.record_polled(polled, now).await;
+
         // Get list of Helm charts to track from Kubernetes
         let charts = self.get_tracked_helm_releases().await?;
         info!("Found {} Helm charts to track", charts.len());
 
-        // Poll each chart for updates based on repository type, respecting per-resource intervals
+        // Poll due charts concurrently based on repository type, respecting per-resource intervals
+        let mut due_charts = Vec::new();
         for chart_info in charts {
             let key = format!("chart::{}", chart_info.repository_url);
             let interval = chart_info.polling_interval.unwrap_or(self.config.interval);
 
-            // Check if enough time has elapsed since last poll
-            let should_poll = {
-                let last_poll_cache = self.last_poll_cache.read().await;
-                match last_poll_cache.get(&key) {
-                    Some(last_poll) => now.duration_since(*last_poll).as_secs() >= interval,
-                    None => true, // Never polled before
-                }
-            };
-
-            if !should_poll {
+            if !self.is_due(&key, interval, now).await {
                 debug!(
                     "Skipping chart {} - interval {}s not elapsed",
                     chart_info.chart_name, interval
                 );
                 continue;
             }
+            due_charts.push((key, chart_info));
+        }
 
-            let result = match chart_info.repository_type {
-                HelmRepositoryType::Oci => self.poll_oci_helm_chart(&chart_info).await,
-                HelmRepositoryType::Http => self.poll_http_helm_chart(&chart_info).await,
-            };
+        let polled = run_concurrently(
+            due_charts,
+            self.config.max_concurrent_polls,
+            |(key, chart_info)| async move {
+                let result = match chart_info.repository_type {
+                    HelmRepositoryType::Oci => self.poll_oci_helm_chart(&chart_info).await,
+                    HelmRepositoryType::Http => self.poll_http_helm_chart(&chart_info).await,
+                };
+
+                if let Err(e) = result {
+                    error!(
+                        "Failed to poll {:?} Helm chart {}: {}",
+                        chart_info.repository_type, chart_info.chart_name, e
+                    );
+                }
+                key
+            },
+        )
+        .await;
+        self.record_polled(polled, now).await;
+
+        let cached_tag_lists = self
+            .tag_cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len();
+        info!(
+            "Registry poll cycle completed ({} tag lists cached)",
+            cached_tag_lists
+        );
+        Ok(())
+    }
 
-            if let Err(e) = result {
-                error!(
-                    "Failed to poll {:?} Helm chart {}: {}",
-                    chart_info.repository_type, chart_info.chart_name, e
-                );
-            }
+    /// Whether enough time has elapsed since `key` was last polled
+    async fn is_due(&self, key: &str, interval: u64, now: std::time::Instant) -> bool {
+        let last_poll_cache = self.last_poll_cache.read().await;
+        match last_poll_cache.get(key) {
+            Some(last_poll) => now.duration_since(*last_poll).as_secs() >= interval,
+            None => true, // Never polled before
+        }
+    }
 
-            // Update last poll time
-            let mut last_poll_cache = self.last_poll_cache.write().await;
+    /// Record the poll time for every polled key
+    async fn record_polled(&self, keys: Vec<String>, now: std::time::Instant) {
+        let mut last_poll_cache = self.last_poll_cache.write().await;
+        for key in keys {
             last_poll_cache.insert(key, now);
         }
-
-        info!("Registry poll cycle completed");
-        Ok(())
     }
 
-    /// Get the list of images to track from Kubernetes Deployments
+    /// Get the list of images to track from Kubernetes Deployments,
+    /// standalone ReplicaSets and, when enabled, Crossplane Compositions and
+    /// Knative Services
     async fn get_tracked_images(&self) -> Result<Vec<ImageToTrack>> {
-        let deployments: Api<Deployment> = Api::all(self.client.clone());
-        let deployment_list = deployments.list(&Default::default()).await?;
+        let deployment_list = list_all::<Deployment>(&self.client).await?;
+        let replicaset_list = list_all::<ReplicaSet>(&self.client).await?;
+        let composition_list = if crate::controller::crossplane_enabled() {
+            let compositions: Api<Composition> = Api::all(self.client.clone());
+            match compositions.list(&Default::default()).await {
+                Ok(list) => list.items,
+                Err(e) => {
+                    warn!("Failed to list Crossplane Compositions: {}", e);
+                    Vec::new()
+                },
+            }
+        } else {
+            Vec::new()
+        };
+        let knative_service_list = if crate::controller::knative_enabled() {
+            let services: Api<KnativeService> = crate::controller::watched_api(self.client.clone());
+            match services.list(&Default::default()).await {
+                Ok(list) => list.items,
+                Err(e) => {
+                    warn!("Failed to list Knative Services: {}", e);
+                    Vec::new()
+                },
+            }
+        } else {
+            Vec::new()
+        };
+        let mut workloads = tracked_workloads(&deployment_list, &replicaset_list);
+        workloads.extend(tracked_compositions(&composition_list));
+        workloads.extend(tracked_knative_services(&knative_service_list));
 
         let mut images = Vec::new();
         let mut seen = HashSet::new(); // Track unique image+policy combinations
-
-        for deployment in deployment_list.items {
-            let metadata = &deployment.metadata;
+        let default_normalization = self
+            .config
+            .tag_normalization
+            .clone()
+            .map(TagNormalization::Rule)
+            .unwrap_or_default();
+
+        for (kind, metadata, containers) in workloads {
             let annotations = match &metadata.annotations {
                 Some(ann) => ann,
                 None => continue,
             };
 
-            // Skip deployments without headwind policy annotation
+            if is_resource_ignored(annotations) {
+                continue;
+            }
+
+            // Skip workloads without headwind policy annotation
             let policy_str = match annotations.get(annotations::POLICY) {
                 Some(p) if p != "none" => p,
                 _ => continue,
@@ -280,7 +515,8 @@ impl RegistryPoller {
 
             if event_source != EventSource::Polling && event_source != EventSource::Both {
                 debug!(
-                    "Skipping deployment {}/{} - event source is {:?}, not polling",
+                    "Skipping {} {}/{} - event source is {:?}, not polling",
+                    kind,
                     metadata
                         .namespace
                         .as_ref()
@@ -299,8 +535,22 @@ impl RegistryPoller {
                 .get(annotations::POLLING_INTERVAL)
                 .and_then(|v| v.parse::<u64>().ok());
 
+            let credential_source = annotations
+                .get(annotations::CREDENTIAL_SOURCE)
+                .and_then(|v| v.parse::<CredentialSource>().ok())
+                .unwrap_or_default();
+
+            let tag_normalization = parse_tag_normalization(annotations, &default_normalization)
+                .unwrap_or_else(|_| default_normalization.clone());
+
+            let container_names = annotations
+                .get(annotations::CONTAINER_NAMES)
+                .map(|v| parse_container_names(v))
+                .unwrap_or_default();
+
             debug!(
-                "Processing deployment {}/{} with policy {:?}",
+                "Processing {} {}/{} with policy {:?}",
+                kind,
                 metadata
                     .namespace
                     .as_ref()
@@ -309,28 +559,31 @@ impl RegistryPoller {
                 policy
             );
 
-            // Extract images from pod template
-            if let Some(spec) = &deployment.spec
-                && let Some(template) = &spec.template.spec
-            {
-                for container in &template.containers {
-                    if let Some(image) = &container.image {
-                        // Create unique key for deduplication
-                        let key = format!("{}::{:?}", image, policy);
-                        if seen.insert(key) {
-                            debug!("  Adding image to track: {} (policy: {:?})", image, policy);
-                            images.push(ImageToTrack {
-                                image: image.clone(),
-                                policy,
-                                pattern: pattern.clone(),
-                                namespace: metadata
-                                    .namespace
-                                    .clone()
-                                    .unwrap_or_else(|| "default".to_string()),
-                                polling_interval,
-                            });
-                        }
-                    }
+            // Extract images from the containers
+            for (container_name, image) in containers {
+                if !container_names.is_empty() && !container_names.contains(&container_name) {
+                    continue;
+                }
+                if is_image_ignored(&self.config.ignore_images, &image) {
+                    debug!("  Ignoring image {}", image);
+                    continue;
+                }
+                // Create unique key for deduplication
+                let key = format!("{}::{:?}", image, policy);
+                if seen.insert(key) {
+                    debug!("  Adding image to track: {} (policy: {:?})", image, policy);
+                    images.push(ImageToTrack {
+                        image,
+                        policy,
+                        pattern: pattern.clone(),
+                        namespace: metadata
+                            .namespace
+                            .clone()
+                            .unwrap_or_else(|| "default".to_string()),
+                        polling_interval,
+                        credential_source,
+                        tag_normalization: tag_normalization.clone(),
+                    });
                 }
             }
         }
@@ -340,10 +593,11 @@ impl RegistryPoller {
 
     /// Poll a specific image for updates
     /// Checks both for digest changes (same-tag updates) and new tags (new versions)
-    #[allow(dead_code)]
-    #[allow(private_interfaces)]
     pub async fn poll_image(&self, image_info: &ImageToTrack) -> Result<Option<String>> {
         let image = &image_info.image;
+        if is_image_ignored(&self.config.ignore_images, image) {
+            return Ok(None);
+        }
         let reference = Reference::try_from(image.as_str())?;
         let current_tag = reference.tag().unwrap_or("latest");
 
@@ -353,23 +607,11 @@ impl RegistryPoller {
         );
         POLLING_IMAGES_CHECKED.inc();
 
-        // Create OCI client
-        let client = OciClient::new(Default::default());
-
-        // Get authentication for this image
-        let mut auth_manager = self.auth_manager.write().await;
-        let auth = auth_manager
-            .get_auth_for_image(&image_info.image, &image_info.namespace)
-            .await?;
-        drop(auth_manager);
-
         // Step 1: Check if the current tag's digest has changed
-        let current_digest = match client.fetch_manifest_digest(&reference, &auth).await {
-            Ok(d) => d,
-            Err(e) => {
-                warn!("Failed to fetch digest for {}: {}", image, e);
-                return Ok(None);
-            },
+        let Some((client, auth, current_digest)) =
+            self.fetch_digest_with_auth(&reference, image_info).await?
+        else {
+            return Ok(None);
         };
 
         debug!(
@@ -380,10 +622,7 @@ impl RegistryPoller {
         );
 
         // Check cache
-        let cache = self.cache.read().await;
-        let cached_info = cache.get(image).cloned(); // Clone to avoid borrow issues
-        let cache_key = image.to_string();
-        drop(cache);
+        let cached_info = self.cache.get(image);
 
         // Check if current tag's digest changed (same-tag update detection)
         if let Some(cached) = cached_info {
@@ -397,15 +636,13 @@ impl RegistryPoller {
                 );
 
                 // Update cache
-                let mut cache = self.cache.write().await;
-                cache.insert(
-                    cache_key.clone(),
+                self.cache.insert(
+                    image,
                     CachedImageInfo {
                         tag: current_tag.to_string(),
                         digest: current_digest.clone(),
                     },
                 );
-                drop(cache);
 
                 // Send event for digest change
                 self.send_update_event(&reference, current_tag, &current_digest)?;
@@ -415,15 +652,13 @@ impl RegistryPoller {
         } else {
             // First time seeing this image
             debug!("First poll for {}, caching current state", image);
-            let mut cache = self.cache.write().await;
-            cache.insert(
-                cache_key.clone(),
+            self.cache.insert(
+                image,
                 CachedImageInfo {
                     tag: current_tag.to_string(),
                     digest: current_digest.clone(),
                 },
             );
-            drop(cache);
         }
 
         // Step 2: Check for new tags (if policy allows)
@@ -438,21 +673,22 @@ impl RegistryPoller {
                 image, current_tag, new_tag
             );
 
-            // Fetch digest for the new tag
-            let new_ref_str = format!("{}:{}", reference.repository(), new_tag);
-            let new_ref = Reference::try_from(new_ref_str.as_str())?;
+            // Fetch digest for the new tag, from the same registry
+            let new_ref = Reference::with_tag(
+                reference.registry().to_string(),
+                reference.repository().to_string(),
+                new_tag.clone(),
+            );
 
             if let Ok(new_digest) = client.fetch_manifest_digest(&new_ref, &auth).await {
                 // Update cache to new tag
-                let mut cache = self.cache.write().await;
-                cache.insert(
-                    cache_key,
+                self.cache.insert(
+                    image,
                     CachedImageInfo {
                         tag: new_tag.clone(),
                         digest: new_digest.clone(),
                     },
                 );
-                drop(cache);
 
                 // Send event for new tag
                 self.send_update_event(&reference, &new_tag, &new_digest)?;
@@ -464,6 +700,111 @@ impl RegistryPoller {
         Ok(None)
     }
 
+    /// Fetch the digest of `reference`, returning the client and credentials
+    /// that worked so later requests reuse them.
+    ///
+    /// Requests are anonymous unless credentials for the registry are already
+    /// cached. A 401 triggers a lookup of the namespace's imagePullSecrets and
+    /// one retry with the credentials found.
+    async fn fetch_digest_with_auth(
+        &self,
+        reference: &Reference,
+        image_info: &ImageToTrack,
+    ) -> Result<Option<(Arc<OciClient>, RegistryAuth, String)>> {
+        let image = &image_info.image;
+        let auth = self
+            .auth_manager
+            .read()
+            .await
+            .cached_auth_for_image(image, image_info.credential_source)
+            .unwrap_or(RegistryAuth::Anonymous);
+
+        let client = self.oci_client(reference.resolve_registry(), &auth);
+        let error = match client.fetch_manifest_digest(reference, &auth).await {
+            Ok(digest) => return Ok(Some((client, auth, digest))),
+            Err(e) => e,
+        };
+
+        if !matches!(auth, RegistryAuth::Anonymous) || !is_unauthorized(&error) {
+            warn!("Failed to fetch digest for {}: {}", image, error);
+            return Ok(None);
+        }
+
+        debug!(
+            "Registry requires authentication for {}, looking up credentials",
+            image
+        );
+        let auth = self
+            .auth_manager
+            .write()
+            .await
+            .get_auth_for_image(image, &image_info.namespace, image_info.credential_source)
+            .await?;
+        if matches!(auth, RegistryAuth::Anonymous) {
+            warn!(
+                "Registry requires authentication for {} but no credentials were found in namespace {}",
+                image, image_info.namespace
+            );
+            return Ok(None);
+        }
+
+        // The previous client has the anonymous credentials stored for this registry
+        let client = self.oci_client(reference.resolve_registry(), &auth);
+        match client.fetch_manifest_digest(reference, &auth).await {
+            Ok(digest) => Ok(Some((client, auth, digest))),
+            Err(e) => {
+                warn!("Failed to fetch digest for {}: {}", image, e);
+                Ok(None)
+            },
+        }
+    }
+
+    /// List the tags of a repository, served from the tag list cache when possible
+    async fn list_tags_cached(
+        &self,
+        client: &OciClient,
+        reference: &Reference,
+        auth: &RegistryAuth,
+    ) -> Result<Vec<String>> {
+        let registry = reference.resolve_registry().to_string();
+        let repository = reference.repository().to_string();
+
+        let cached = self
+            .tag_cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&registry, &repository);
+        if let Some(tags) = cached {
+            TAG_CACHE_HITS_TOTAL.inc();
+            debug!("Tag list cache hit for {}/{}", registry, repository);
+            return Ok(tags);
+        }
+        TAG_CACHE_MISSES_TOTAL.inc();
+
+        let tags = if registry::quay::is_quay(&registry) {
+            QuayTagLister::from_env().list_tags(&repository).await?
+        } else if let Some(nexus) = NexusTagLister::for_registry(&registry) {
+            nexus.list_tags(&repository).await?
+        } else if let Some(artifactory) = ArtifactoryTagLister::for_registry(&registry) {
+            artifactory.list_tags(&repository).await?
+        } else {
+            match list_all_tags(client, reference, auth).await {
+                Ok(tags) => tags,
+                // Self-hosted Artifactory only serves tags under its own API
+                Err(e) => match ArtifactoryTagLister::detect(&registry).await {
+                    Some(artifactory) => artifactory.list_tags(&repository).await?,
+                    None => return Err(e),
+                },
+            }
+        };
+        self.tag_cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(&registry, &repository, tags.clone());
+
+        Ok(tags)
+    }
+
     /// Check for new tags that match the policy
     async fn check_for_new_tags(
         &self,
@@ -473,8 +814,8 @@ impl RegistryPoller {
         image_info: &ImageToTrack,
     ) -> Result<Option<String>> {
         // List available tags
-        let tag_response = match client.list_tags(reference, auth, None, None).await {
-            Ok(resp) => resp,
+        let tags = match self.list_tags_cached(client, reference, auth).await {
+            Ok(tags) => tags,
             Err(e) => {
                 debug!(
                     "Failed to list tags for {}: {} (registry may not support listing)",
@@ -489,7 +830,7 @@ impl RegistryPoller {
 
         debug!(
             "Found {} tags for {} (current: {}, policy: {:?})",
-            tag_response.tags.len(),
+            tags.len(),
             reference.repository(),
             current_tag,
             image_info.policy
@@ -502,64 +843,33 @@ impl RegistryPoller {
             require_approval: true,
             min_update_interval: None,
             images: vec![],
+            container_names: Vec::new(),
             event_source: Default::default(),
             polling_interval: None,
+            batch_mode: false,
+            tag_normalization: image_info.tag_normalization.clone(),
+            observers: Vec::new(),
+            helm_version_constraint: None,
+            include_init_containers: false,
+            require_slsa_level: None,
+            slsa_builder: None,
+            opa_policy: None,
         };
 
-        let policy_engine = PolicyEngine;
-        let mut best_version: Option<String> = None;
-
-        // Find the best matching tag according to policy
-        for tag in &tag_response.tags {
-            // Skip non-version-looking tags for semver policies
-            if matches!(
-                image_info.policy,
-                UpdatePolicy::Patch | UpdatePolicy::Minor | UpdatePolicy::Major
-            ) {
-                // Quick sanity check: does it look like a version?
-                // Must start with digit or 'v'
-                if !tag
-                    .chars()
-                    .next()
-                    .is_some_and(|c| c.is_ascii_digit() || c == 'v')
-                {
-                    debug!("Skipping non-version tag: {}", tag);
-                    continue;
-                }
-            }
-
-            // Check if this tag should be considered for update
-            match policy_engine.should_update(&resource_policy, current_tag, tag) {
-                Ok(true) => {
-                    debug!("Tag {} matches policy {:?}", tag, image_info.policy);
-
-                    // If we don't have a best version yet, or this one is better
-                    if best_version.is_none() {
-                        best_version = Some(tag.clone());
-                    } else if let Some(ref current_best) = best_version {
-                        // Check if new tag is better than current best
-                        match policy_engine.should_update(&resource_policy, current_best, tag) {
-                            Ok(true) => {
-                                debug!("Tag {} is better than current best {}", tag, current_best);
-                                best_version = Some(tag.clone());
-                            },
-                            Ok(false) => {
-                                debug!("Tag {} is not better than {}", tag, current_best);
-                            },
-                            Err(e) => {
-                                debug!("Failed to compare {} with {}: {}", tag, current_best, e);
-                            },
-                        }
-                    }
-                },
-                Ok(false) => {
-                    debug!("Tag {} does not match policy", tag);
-                },
-                Err(e) => {
-                    debug!("Failed to check if tag {} matches policy: {}", tag, e);
-                },
-            }
-        }
+        let best_version = match self.config.tag_sort {
+            TagSortStrategy::Semver => best_tag(&tags, current_tag, &resource_policy),
+            TagSortStrategy::PushDate => {
+                self.newest_pushed_tag(
+                    client,
+                    reference,
+                    auth,
+                    &tags,
+                    current_tag,
+                    &resource_policy,
+                )
+                .await
+            },
+        };
 
         if let Some(ref best) = best_version {
             info!(
@@ -574,6 +884,50 @@ impl RegistryPoller {
         Ok(best_version)
     }
 
+    /// The most recently pushed tag the policy allows, if it was pushed after
+    /// `current_tag`
+    async fn newest_pushed_tag(
+        &self,
+        client: &OciClient,
+        reference: &Reference,
+        auth: &RegistryAuth,
+        tags: &[String],
+        current_tag: &str,
+        policy: &ResourcePolicy,
+    ) -> Option<String> {
+        let policy_engine = PolicyEngine;
+        let candidates: Vec<String> = tags
+            .iter()
+            .filter(|tag| {
+                tag.as_str() == current_tag
+                    || matches!(
+                        policy_engine.should_update(policy, current_tag, tag),
+                        Ok(true)
+                    )
+            })
+            .cloned()
+            .collect();
+
+        let dated = sort_by_push_date(
+            client,
+            reference,
+            auth,
+            &candidates,
+            &self.created_dates,
+            self.config.max_concurrent_polls,
+        )
+        .await;
+
+        let (newest, created) = dated.into_iter().next()?;
+        debug!(
+            "Most recently pushed tag of {}: {} ({})",
+            reference.repository(),
+            newest,
+            created
+        );
+        (newest != current_tag).then_some(newest)
+    }
+
     /// Send an update event for a new image version
     fn send_update_event(&self, reference: &Reference, tag: &str, digest: &str) -> Result<()> {
         let event = ImagePushEvent {
@@ -650,6 +1004,11 @@ impl RegistryPoller {
                 .get(annotations::POLLING_INTERVAL)
                 .and_then(|v| v.parse::<u64>().ok());
 
+            let credential_source = annotations
+                .get(annotations::CREDENTIAL_SOURCE)
+                .and_then(|v| v.parse::<CredentialSource>().ok())
+                .unwrap_or_default();
+
             // Get chart information from HelmRelease spec
             let chart_name = &helm_release.spec.chart.spec.chart;
             let source_ref = &helm_release.spec.chart.spec.source_ref;
@@ -682,6 +1041,13 @@ impl RegistryPoller {
             // Determine repository type and build URL
             let repo_url = &helm_repo.spec.url;
             let (repository_url, repository_type) = if repo_url.starts_with("oci://") {
+                if !features().helm_oci_polling {
+                    debug!(
+                        "Skipping HelmRelease {}/{} from OCI repository {}, HEADWIND_FEATURE_HELM_OCI_POLLING is not enabled",
+                        namespace, release_name, repo_url
+                    );
+                    continue;
+                }
                 // OCI repository: oci://registry.io/charts/mychart
                 (
                     format!("{}/{}", repo_url.trim_end_matches('/'), chart_name),
@@ -727,6 +1093,7 @@ impl RegistryPoller {
                     namespace,
                     release_name,
                     polling_interval,
+                    credential_source,
                 });
             }
         }
@@ -752,19 +1119,22 @@ impl RegistryPoller {
         let reference_str = format!("{}:{}", url_without_scheme, chart_info.current_version);
         let reference = Reference::try_from(reference_str.as_str())?;
 
-        // Create OCI client
-        let client = OciClient::new(Default::default());
-
         // Get authentication for this chart (charts use same auth as images)
         let mut auth_manager = self.auth_manager.write().await;
         let auth = auth_manager
-            .get_auth_for_image(&chart_info.repository_url, &chart_info.namespace)
+            .get_auth_for_image(
+                &chart_info.repository_url,
+                &chart_info.namespace,
+                chart_info.credential_source,
+            )
             .await?;
         drop(auth_manager);
 
+        let client = self.oci_client(reference.resolve_registry(), &auth);
+
         // List available versions (tags)
-        let tag_response = match client.list_tags(&reference, &auth, None, None).await {
-            Ok(resp) => resp,
+        let tags = match self.list_tags_cached(&client, &reference, &auth).await {
+            Ok(tags) => tags,
             Err(e) => {
                 debug!(
                     "Failed to list tags for {}: {} (registry may not support listing)",
@@ -785,14 +1155,23 @@ impl RegistryPoller {
             require_approval: true,
             min_update_interval: None,
             images: Vec::new(),
+            container_names: Vec::new(),
             event_source: Default::default(),
             polling_interval: None,
+            batch_mode: false,
+            tag_normalization: Default::default(),
+            observers: Vec::new(),
+            helm_version_constraint: None,
+            include_init_containers: false,
+            require_slsa_level: None,
+            slsa_builder: None,
+            opa_policy: None,
         };
 
         let mut best_version: Option<String> = None;
 
         // Check each tag to find the best match
-        for tag in &tag_response.tags {
+        for tag in &tags {
             // Skip non-semantic version tags for semver policies
             if matches!(
                 chart_info.policy,
@@ -958,8 +1337,17 @@ impl RegistryPoller {
             require_approval: true,
             min_update_interval: None,
             images: Vec::new(),
+            container_names: Vec::new(),
             event_source: Default::default(),
             polling_interval: None,
+            batch_mode: false,
+            tag_normalization: Default::default(),
+            observers: Vec::new(),
+            helm_version_constraint: None,
+            include_init_containers: false,
+            require_slsa_level: None,
+            slsa_builder: None,
+            opa_policy: None,
         };
 
         let mut best_version: Option<String> = None;
@@ -1120,6 +1508,134 @@ impl RegistryPoller {
     }
 }
 
+/// Run `f` over `items` with at most `limit` futures in flight.
+/// Results are returned in completion order.
+async fn run_concurrently<T, R, F, Fut>(items: Vec<T>, limit: usize, f: F) -> Vec<R>
+where
+    F: FnMut(T) -> Fut,
+    Fut: std::future::Future<Output = R>,
+{
+    futures::stream::iter(items)
+        .map(f)
+        .buffer_unordered(limit.max(1))
+        .collect()
+        .await
+}
+
+/// The tag `policy` would update `current_tag` to, picking the best of all
+/// tags the policy allows
+fn best_tag(tags: &[String], current_tag: &str, policy: &ResourcePolicy) -> Option<String> {
+    let policy_engine = PolicyEngine;
+    let mut best_version: Option<String> = None;
+
+    // Find the best matching tag according to policy
+    for tag in tags {
+        // Skip non-version-looking tags for semver policies
+        if matches!(
+            policy.policy,
+            UpdatePolicy::Patch | UpdatePolicy::Minor | UpdatePolicy::Major
+        ) {
+            // Quick sanity check: does it look like a version?
+            // Must start with digit or 'v'
+            if !normalize_tag(tag, &policy.tag_normalization)
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_digit() || c == 'v')
+            {
+                debug!("Skipping non-version tag: {}", tag);
+                continue;
+            }
+        }
+
+        // Check if this tag should be considered for update
+        match policy_engine.should_update(policy, current_tag, tag) {
+            Ok(true) => {
+                debug!("Tag {} matches policy {:?}", tag, policy.policy);
+
+                // If we don't have a best version yet, or this one is better
+                if best_version.is_none() {
+                    best_version = Some(tag.clone());
+                } else if let Some(ref current_best) = best_version {
+                    // Check if new tag is better than current best
+                    match policy_engine.should_update(policy, current_best, tag) {
+                        Ok(true) => {
+                            debug!("Tag {} is better than current best {}", tag, current_best);
+                            best_version = Some(tag.clone());
+                        },
+                        Ok(false) => {
+                            debug!("Tag {} is not better than {}", tag, current_best);
+                        },
+                        Err(e) => {
+                            debug!("Failed to compare {} with {}: {}", tag, current_best, e);
+                        },
+                    }
+                }
+            },
+            Ok(false) => {
+                debug!("Tag {} does not match policy", tag);
+            },
+            Err(e) => {
+                debug!("Failed to check if tag {} matches policy: {}", tag, e);
+            },
+        }
+    }
+
+    best_version
+}
+
+/// List every tag of a repository, following `n`/`last` pagination until a
+/// short page is returned
+async fn list_all_tags(
+    client: &OciClient,
+    reference: &Reference,
+    auth: &RegistryAuth,
+) -> Result<Vec<String>> {
+    let mut tags: Vec<String> = Vec::new();
+
+    for _ in 0..MAX_TAG_PAGES {
+        let last = tags.last().map(String::as_str);
+        let page = client
+            .list_tags(reference, auth, Some(TAG_PAGE_SIZE), last)
+            .await?
+            .tags;
+
+        // A registry that ignores `last` would return the same page again
+        let repeated = !page.is_empty() && page.last() == tags.last();
+        let done = page.len() < TAG_PAGE_SIZE || repeated;
+        if !repeated {
+            tags.extend(page);
+        }
+        if done {
+            return Ok(tags);
+        }
+    }
+
+    warn!(
+        "Stopped listing tags for {} after {} pages",
+        reference.repository(),
+        MAX_TAG_PAGES
+    );
+    Ok(tags)
+}
+
+/// Whether a registry error means the request needs credentials
+fn is_unauthorized(error: &OciDistributionError) -> bool {
+    match error {
+        OciDistributionError::UnauthorizedError { .. } => true,
+        OciDistributionError::RegistryError { envelope, .. } => envelope
+            .errors
+            .iter()
+            .any(|e| matches!(e.code, OciErrorCode::Unauthorized | OciErrorCode::Denied)),
+        _ => false,
+    }
+}
+
+/// How long to sleep before the next poll cycle, or None if the cycle
+/// overran the interval and the next one should start immediately
+fn next_cycle_delay(interval: Duration, elapsed: Duration) -> Option<Duration> {
+    interval.checked_sub(elapsed).filter(|d| !d.is_zero())
+}
+
 fn extract_registry(registry: &str) -> String {
     if registry.is_empty() {
         "docker.io".to_string()
@@ -1128,15 +1644,247 @@ fn extract_registry(registry: &str) -> String {
     }
 }
 
+/// A resource polled for image updates: kind, metadata and its containers as
+/// (name, image)
+type TrackedWorkload<'a> = (&'static str, &'a ObjectMeta, Vec<(String, String)>);
+
+/// Containers of a pod spec as (name, image)
+fn pod_containers(pod_spec: Option<&PodSpec>) -> Vec<(String, String)> {
+    pod_spec
+        .map(|spec| spec.containers.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|c| Some((c.name.clone(), c.image.clone()?)))
+        .collect()
+}
+
+/// Workloads polled for image updates: Deployments, and ReplicaSets not owned
+/// by a Deployment, whose images are already tracked through the Deployment
+fn tracked_workloads<'a>(
+    deployments: &'a [Arc<Deployment>],
+    replicasets: &'a [Arc<ReplicaSet>],
+) -> Vec<TrackedWorkload<'a>> {
+    let deployments = deployments.iter().map(|d| {
+        let pod_spec = d.spec.as_ref().and_then(|s| s.template.spec.as_ref());
+        ("deployment", &d.metadata, pod_containers(pod_spec))
+    });
+    let replicasets = replicasets
+        .iter()
+        .filter(|rs| crate::controller::is_standalone_replicaset(rs))
+        .map(|rs| {
+            let pod_spec = rs
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.as_ref())
+                .and_then(|t| t.spec.as_ref());
+            ("replicaset", &rs.metadata, pod_containers(pod_spec))
+        });
+    deployments.chain(replicasets).collect()
+}
+
+/// Crossplane Compositions polled for image updates, with the containers of
+/// their composed resource bases
+fn tracked_compositions(compositions: &[Composition]) -> Vec<TrackedWorkload<'_>> {
+    compositions
+        .iter()
+        .map(|composition| {
+            let containers = composition
+                .image_refs()
+                .into_iter()
+                .map(|r| (r.container_name.unwrap_or_default(), r.image))
+                .collect();
+            ("composition", &composition.metadata, containers)
+        })
+        .collect()
+}
+
+/// Knative Services polled for image updates, with the containers of their
+/// revision template
+fn tracked_knative_services(services: &[KnativeService]) -> Vec<TrackedWorkload<'_>> {
+    services
+        .iter()
+        .map(|service| {
+            let containers = service
+                .containers()
+                .iter()
+                .filter_map(|c| Some((c.name.clone().unwrap_or_default(), c.image.clone()?)))
+                .collect();
+            ("knative-service", &service.metadata, containers)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_best_tag_keeps_registry_tag_format() {
+        let policy = ResourcePolicy {
+            policy: UpdatePolicy::Minor,
+            tag_normalization: TagNormalization::Rule(
+                "strip-prefix=release-,strip-suffix=-alpine"
+                    .parse()
+                    .unwrap(),
+            ),
+            ..Default::default()
+        };
+        let tags: Vec<String> = ["release-1.2.3-alpine", "release-1.3.0-alpine", "2.0.0"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        // Versions are compared normalized, the tag is patched as published
+        assert_eq!(
+            best_tag(&tags, "1.2.3", &policy).as_deref(),
+            Some("release-1.3.0-alpine")
+        );
+    }
+
+    #[test]
+    fn test_tracked_workloads_skip_deployment_owned_replicasets() {
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+
+        let replicaset = |name: &str, owner: Option<&str>| {
+            let mut rs = ReplicaSet::default();
+            rs.metadata.name = Some(name.to_string());
+            rs.metadata.owner_references = owner.map(|kind| {
+                vec![OwnerReference {
+                    kind: kind.to_string(),
+                    name: "web".to_string(),
+                    ..Default::default()
+                }]
+            });
+            Arc::new(rs)
+        };
+        let mut deployment = Deployment::default();
+        deployment.metadata.name = Some("web".to_string());
+
+        let deployments = vec![Arc::new(deployment)];
+        let replicasets = vec![
+            replicaset("web-7d9f8", Some("Deployment")),
+            replicaset("legacy", None),
+        ];
+
+        let tracked: Vec<_> = tracked_workloads(&deployments, &replicasets)
+            .into_iter()
+            .map(|(kind, metadata, _)| (kind, metadata.name.clone().unwrap()))
+            .collect();
+        assert_eq!(
+            tracked,
+            vec![
+                ("deployment", "web".to_string()),
+                ("replicaset", "legacy".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tracked_compositions() {
+        let composition: Composition = serde_json::from_value(serde_json::json!({
+            "apiVersion": "apiextensions.crossplane.io/v1",
+            "kind": "Composition",
+            "metadata": { "name": "platform-app" },
+            "spec": {
+                "resources": [{
+                    "name": "worker",
+                    "base": {
+                        "spec": {
+                            "containers": [{ "name": "app", "image": "ghcr.io/org/worker:1.2.0" }]
+                        }
+                    }
+                }]
+            }
+        }))
+        .unwrap();
+
+        let compositions = [composition];
+        let tracked = tracked_compositions(&compositions);
+        assert_eq!(tracked.len(), 1);
+        assert_eq!(tracked[0].0, "composition");
+        assert_eq!(
+            tracked[0].2,
+            vec![("app".to_string(), "ghcr.io/org/worker:1.2.0".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_tracked_knative_services() {
+        let service: KnativeService = serde_json::from_value(serde_json::json!({
+            "apiVersion": "serving.knative.dev/v1",
+            "kind": "Service",
+            "metadata": { "name": "hello", "namespace": "apps" },
+            "spec": {
+                "template": {
+                    "spec": {
+                        "containers": [{ "image": "ghcr.io/org/hello:1.0.0" }]
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let services = [service];
+        let tracked = tracked_knative_services(&services);
+        assert_eq!(tracked.len(), 1);
+        assert_eq!(tracked[0].1.namespace.as_deref(), Some("apps"));
+        assert_eq!(
+            tracked[0].2,
+            vec![(String::new(), "ghcr.io/org/hello:1.0.0".to_string())]
+        );
+    }
+
     #[test]
     fn test_polling_config_default() {
         let config = PollingConfig::default();
         assert_eq!(config.interval, 300);
         assert!(!config.enabled);
+        assert_eq!(config.max_concurrent_polls, 10);
+    }
+
+    #[tokio::test]
+    async fn test_run_concurrently_is_order_independent() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+
+        // Earlier items take longer, so they complete out of order
+        let items: Vec<u64> = (0..20).collect();
+        let mut results = run_concurrently(items, 4, |i| {
+            let (in_flight, max_in_flight) = (&in_flight, &max_in_flight);
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20 - i)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                i * 2
+            }
+        })
+        .await;
+
+        assert_eq!(results.len(), 20);
+        assert!(max_in_flight.load(Ordering::SeqCst) <= 4);
+
+        results.sort();
+        assert_eq!(results, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_run_concurrently_zero_limit() {
+        let results = run_concurrently(vec![1, 2, 3], 0, |i| async move { i }).await;
+        assert_eq!(results.len(), 3);
+    }
+
+    #[test]
+    fn test_next_cycle_delay() {
+        let interval = Duration::from_secs(300);
+        assert_eq!(
+            next_cycle_delay(interval, Duration::from_secs(100)),
+            Some(Duration::from_secs(200))
+        );
+        assert_eq!(next_cycle_delay(interval, Duration::from_secs(300)), None);
+        assert_eq!(next_cycle_delay(interval, Duration::from_secs(450)), None);
     }
 
     #[test]
@@ -0,0 +1,240 @@
+//! Tag ordering by push date, for repositories whose tags are build
+//! timestamps or commit hashes (`20240315-abc1234`) rather than versions.
+//!
+//! The creation date of a tag is `created` in its image config, which takes a
+//! manifest and a blob request per tag, so dates are cached per repository and
+//! tag for ten minutes and fetched concurrently.
+
+use super::run_concurrently;
+use crate::metrics::MANIFEST_FETCHES_TOTAL;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use oci_distribution::{Client as OciClient, Reference, secrets::RegistryAuth};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// How long the creation date of a tag is cached
+pub const CREATED_DATE_TTL: Duration = Duration::from_secs(600);
+
+/// How the poller orders the tags of a repository to find the newest one
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagSortStrategy {
+    /// By version, according to the update policy
+    #[default]
+    Semver,
+    /// By the creation date in the image config, newest first
+    PushDate,
+}
+
+impl TagSortStrategy {
+    /// Strategy from `HEADWIND_POLLING_TAG_SORT` (`semver` or `push-date`)
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("HEADWIND_POLLING_TAG_SORT") {
+            Ok(value) => value.parse(),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+}
+
+impl FromStr for TagSortStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "semver" => Ok(Self::Semver),
+            "push-date" | "pushdate" => Ok(Self::PushDate),
+            other => anyhow::bail!(
+                "Unknown tag sort strategy '{}' (expected semver or push-date)",
+                other
+            ),
+        }
+    }
+}
+
+/// Creation dates of tags by (repository, tag), with a TTL
+#[derive(Default)]
+pub struct CreatedDateCache {
+    entries: HashMap<(String, String), (DateTime<Utc>, Instant)>,
+}
+
+impl CreatedDateCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached creation date of `repo:tag`, if fetched less than
+    /// [`CREATED_DATE_TTL`] before `now`
+    pub fn get(&self, repo: &str, tag: &str, now: Instant) -> Option<DateTime<Utc>> {
+        self.entries
+            .get(&(repo.to_string(), tag.to_string()))
+            .filter(|(_, fetched_at)| now.duration_since(*fetched_at) < CREATED_DATE_TTL)
+            .map(|(created, _)| *created)
+    }
+
+    pub fn insert(&mut self, repo: &str, tag: &str, created: DateTime<Utc>, now: Instant) {
+        self.entries
+            .retain(|_, (_, fetched_at)| now.duration_since(*fetched_at) < CREATED_DATE_TTL);
+        self.entries
+            .insert((repo.to_string(), tag.to_string()), (created, now));
+    }
+}
+
+/// `created` of the image config of `reference`
+pub async fn fetch_created(
+    client: &OciClient,
+    reference: &Reference,
+    auth: &RegistryAuth,
+) -> Result<DateTime<Utc>> {
+    MANIFEST_FETCHES_TOTAL.inc();
+    let (_, _, config) = client
+        .pull_manifest_and_config(reference, auth)
+        .await
+        .with_context(|| format!("Failed to pull image config of {}", reference))?;
+    let config: serde_json::Value = serde_json::from_str(&config)
+        .with_context(|| format!("Invalid image config of {}", reference))?;
+    let created = config["created"]
+        .as_str()
+        .with_context(|| format!("Image config of {} has no creation date", reference))?;
+
+    Ok(DateTime::parse_from_rfc3339(created)
+        .with_context(|| format!("Invalid creation date '{}' of {}", created, reference))?
+        .with_timezone(&Utc))
+}
+
+/// `tags` of the repository of `reference` with their creation dates, newest
+/// first. Dates come from `cache` when fresh and are fetched `concurrency` at
+/// a time otherwise; tags whose date cannot be read are left out.
+pub async fn sort_by_push_date(
+    client: &OciClient,
+    reference: &Reference,
+    auth: &RegistryAuth,
+    tags: &[String],
+    cache: &std::sync::Mutex<CreatedDateCache>,
+    concurrency: usize,
+) -> Vec<(String, DateTime<Utc>)> {
+    let repo = format!(
+        "{}/{}",
+        reference.resolve_registry(),
+        reference.repository()
+    );
+
+    let mut dated: Vec<(String, DateTime<Utc>)> =
+        run_concurrently(tags.to_vec(), concurrency, |tag| {
+            tag_created(client, reference, auth, &repo, tag, cache)
+        })
+        .await
+        .into_iter()
+        .flatten()
+        .collect();
+
+    sort_newest_first(&mut dated);
+    dated
+}
+
+/// Creation date of `tag`, from `cache` when fresh
+async fn tag_created(
+    client: &OciClient,
+    reference: &Reference,
+    auth: &RegistryAuth,
+    repo: &str,
+    tag: String,
+    cache: &std::sync::Mutex<CreatedDateCache>,
+) -> Option<(String, DateTime<Utc>)> {
+    let cached = cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(repo, &tag, Instant::now());
+    if let Some(created) = cached {
+        return Some((tag, created));
+    }
+
+    let tag_reference = Reference::with_tag(
+        reference.registry().to_string(),
+        reference.repository().to_string(),
+        tag.clone(),
+    );
+    match fetch_created(client, &tag_reference, auth).await {
+        Ok(created) => {
+            cache.lock().unwrap_or_else(|e| e.into_inner()).insert(
+                repo,
+                &tag,
+                created,
+                Instant::now(),
+            );
+            Some((tag, created))
+        },
+        Err(e) => {
+            debug!("Skipping tag {} without a push date: {:#}", tag, e);
+            None
+        },
+    }
+}
+
+/// Newest first; tags pushed at the same time by name, descending
+fn sort_newest_first(dated: &mut [(String, DateTime<Utc>)]) {
+    dated.sort_by(|(a_tag, a), (b_tag, b)| b.cmp(a).then_with(|| b_tag.cmp(a_tag)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_strategy() {
+        assert_eq!(
+            "semver".parse::<TagSortStrategy>().unwrap(),
+            TagSortStrategy::Semver
+        );
+        assert_eq!(
+            "Push-Date".parse::<TagSortStrategy>().unwrap(),
+            TagSortStrategy::PushDate
+        );
+        assert!("alphabetical".parse::<TagSortStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_cache_expires_after_ttl() {
+        let mut cache = CreatedDateCache::new();
+        let now = Instant::now();
+        let created = date("2024-03-15T10:00:00Z");
+        cache.insert("ghcr.io/app", "20240315-abc1234", created, now);
+
+        assert_eq!(
+            cache.get("ghcr.io/app", "20240315-abc1234", now),
+            Some(created)
+        );
+        assert_eq!(cache.get("ghcr.io/other", "20240315-abc1234", now), None);
+        assert_eq!(
+            cache.get("ghcr.io/app", "20240315-abc1234", now + CREATED_DATE_TTL),
+            None
+        );
+    }
+
+    #[test]
+    fn test_sort_newest_first() {
+        let mut dated = vec![
+            ("20240315-abc1234".to_string(), date("2024-03-15T10:00:00Z")),
+            ("20240401-def5678".to_string(), date("2024-04-01T08:00:00Z")),
+            ("rebuild-a".to_string(), date("2024-03-15T10:00:00Z")),
+            ("20240101-0a1b2c3".to_string(), date("2024-01-01T00:00:00Z")),
+        ];
+        sort_newest_first(&mut dated);
+
+        let tags: Vec<&str> = dated.iter().map(|(tag, _)| tag.as_str()).collect();
+        assert_eq!(
+            tags,
+            [
+                "20240401-def5678",
+                "rebuild-a",
+                "20240315-abc1234",
+                "20240101-0a1b2c3"
+            ]
+        );
+    }
+}
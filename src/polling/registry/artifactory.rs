@@ -0,0 +1,328 @@
+//! Tag listing for JFrog Artifactory.
+//!
+//! Artifactory serves each Docker repository's V2 API under
+//! `{base_url}/artifactory/api/docker/{repo-key}/v2/`, so the first path
+//! segment of an image on an Artifactory host names the repository key:
+//! `acme.jfrog.io/docker-local/team/app` lists tags from
+//! `{base_url}/artifactory/api/docker/docker-local/v2/team/app/tags/list`.
+//!
+//! Hosts are recognised by `*.jfrog.io`, the comma-separated patterns in
+//! `HEADWIND_ARTIFACTORY_REGISTRIES`, or, when the standard tag listing of a
+//! registry fails, by the `X-Artifactory-Id` header on its `/v2/` response.
+
+use super::nexus::{BasicAuth, matches_registry, next_page};
+use crate::metrics::POLLING_ARTIFACTORY_REQUESTS_TOTAL;
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+/// Upper bound on pages followed through `Link` headers
+const MAX_PAGES: usize = 1000;
+
+/// Hosts of JFrog's SaaS offering, recognised without configuration
+const JFROG_CLOUD_PATTERN: &str = "*.jfrog.io";
+
+/// Header every Artifactory response carries
+const ARTIFACTORY_ID_HEADER: &str = "x-artifactory-id";
+
+/// Header carrying an API key or access token when no username is set
+const API_KEY_HEADER: &str = "X-JFrog-Art-Api";
+
+/// How requests to Artifactory authenticate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactoryAuth {
+    /// Username with an API key or access token as the password
+    Basic(BasicAuth),
+    /// API key or access token in the `X-JFrog-Art-Api` header
+    ApiKey(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct TagList {
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+pub struct ArtifactoryTagLister {
+    base_url: String,
+    auth: Option<ArtifactoryAuth>,
+    http: reqwest::Client,
+}
+
+impl ArtifactoryTagLister {
+    pub fn new(base_url: &str, auth: Option<ArtifactoryAuth>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            auth,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Lister for `registry` if it is a JFrog cloud host or matches
+    /// `HEADWIND_ARTIFACTORY_REGISTRIES`
+    pub fn for_registry(registry: &str) -> Option<Self> {
+        let patterns = std::env::var("HEADWIND_ARTIFACTORY_REGISTRIES").unwrap_or_default();
+        if !matches_registry(registry, JFROG_CLOUD_PATTERN)
+            && !matches_registry(registry, &patterns)
+        {
+            return None;
+        }
+        Some(Self::from_env(registry))
+    }
+
+    /// Lister for `registry` if its `/v2/` endpoint answers with an
+    /// `X-Artifactory-Id` header
+    pub async fn detect(registry: &str) -> Option<Self> {
+        let lister = Self::from_env(registry);
+        lister.is_artifactory().await.then_some(lister)
+    }
+
+    /// `HEADWIND_ARTIFACTORY_BASE_URL` overrides the default `https://{registry}`.
+    /// `HEADWIND_ARTIFACTORY_API_KEY` authenticates, as the password of
+    /// `HEADWIND_ARTIFACTORY_USERNAME` when that is set and in the
+    /// `X-JFrog-Art-Api` header otherwise.
+    fn from_env(registry: &str) -> Self {
+        let non_empty = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let base_url = non_empty("HEADWIND_ARTIFACTORY_BASE_URL")
+            .unwrap_or_else(|| format!("https://{}", registry));
+        let auth = non_empty("HEADWIND_ARTIFACTORY_API_KEY").map(|api_key| {
+            match non_empty("HEADWIND_ARTIFACTORY_USERNAME") {
+                Some(username) => ArtifactoryAuth::Basic(BasicAuth {
+                    username,
+                    password: api_key,
+                }),
+                None => ArtifactoryAuth::ApiKey(api_key),
+            }
+        });
+        Self::new(&base_url, auth)
+    }
+
+    /// Whether the server identifies itself as Artifactory. The header is
+    /// sent on `401` responses too, so no credentials are needed.
+    pub async fn is_artifactory(&self) -> bool {
+        POLLING_ARTIFACTORY_REQUESTS_TOTAL.inc();
+        match self.http.get(format!("{}/v2/", self.base_url)).send().await {
+            Ok(response) => response.headers().contains_key(ARTIFACTORY_ID_HEADER),
+            Err(e) => {
+                debug!("Artifactory detection for {} failed: {}", self.base_url, e);
+                false
+            },
+        }
+    }
+
+    /// All tags of `repository`, whose first segment is the repository key
+    pub async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        let Some((repo_key, image)) = repository.split_once('/') else {
+            bail!(
+                "Artifactory image {} must start with the repository key",
+                repository
+            );
+        };
+
+        let mut url = format!(
+            "{}/artifactory/api/docker/{}/v2/{}/tags/list",
+            self.base_url, repo_key, image
+        );
+        let mut tags = Vec::new();
+
+        for _ in 0..MAX_PAGES {
+            let mut request = self.http.get(&url);
+            match &self.auth {
+                Some(ArtifactoryAuth::Basic(auth)) => {
+                    request = request.basic_auth(&auth.username, Some(&auth.password));
+                },
+                Some(ArtifactoryAuth::ApiKey(key)) => {
+                    request = request.header(API_KEY_HEADER, key);
+                },
+                None => {},
+            }
+
+            POLLING_ARTIFACTORY_REQUESTS_TOTAL.inc();
+            let response = request.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                bail!("Artifactory returned {} for {}", status, url);
+            }
+
+            let next = next_page(response.headers()).map(|next| self.resolve(repo_key, &next));
+            let page: TagList = response.json().await?;
+            tags.extend(page.tags.unwrap_or_default());
+
+            match next {
+                Some(next) => url = next,
+                None => return Ok(tags),
+            }
+        }
+
+        warn!(
+            "Stopped listing Artifactory tags for {} after {} pages",
+            repository, MAX_PAGES
+        );
+        Ok(tags)
+    }
+
+    /// Absolute URL for a `Link` target. Artifactory may send the path of the
+    /// registry API (`/v2/...`) without its `/artifactory/api/docker/{repo-key}` prefix.
+    fn resolve(&self, repo_key: &str, link: &str) -> String {
+        if link.starts_with("http://") || link.starts_with("https://") {
+            link.to_string()
+        } else if link.starts_with("/v2/") {
+            format!(
+                "{}/artifactory/api/docker/{}{}",
+                self.base_url, repo_key, link
+            )
+        } else {
+            format!("{}{}", self.base_url, link)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        extract::{Path, Query},
+        http::{HeaderMap, StatusCode, header},
+        response::IntoResponse,
+        routing::get,
+    };
+    use base64::Engine;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Fake Artifactory serving `count` tags of `docker-local/team/app` in
+    /// pages of 2 linked with `Link` headers, to `admin:secret` or the API
+    /// key `secret` only
+    async fn start_artifactory(count: usize) -> String {
+        let app = Router::new()
+            .route(
+                "/v2/",
+                get(|| async { (StatusCode::UNAUTHORIZED, [("x-artifactory-id", "a1b2c3")]) }),
+            )
+            .route(
+                "/artifactory/api/docker/{repo_key}/v2/{*path}",
+                get(
+                    move |Path((repo_key, path)): Path<(String, String)>,
+                          Query(query): Query<HashMap<String, String>>,
+                          headers: HeaderMap| async move {
+                        let basic = format!(
+                            "Basic {}",
+                            base64::engine::general_purpose::STANDARD.encode("admin:secret")
+                        );
+                        let authorized = headers
+                            .get(header::AUTHORIZATION)
+                            .is_some_and(|v| v.as_bytes() == basic.as_bytes())
+                            || headers
+                                .get("x-jfrog-art-api")
+                                .is_some_and(|v| v.as_bytes() == b"secret");
+                        if !authorized {
+                            return StatusCode::UNAUTHORIZED.into_response();
+                        }
+                        if repo_key != "docker-local" || path != "team/app/tags/list" {
+                            return StatusCode::NOT_FOUND.into_response();
+                        }
+
+                        let start: usize =
+                            query.get("last").map(|l| l.parse().unwrap()).unwrap_or(0);
+                        let end = count.min(start + 2);
+                        let tags: Vec<String> =
+                            (start..end).map(|i| format!("1.0.{}", i)).collect();
+                        let body = Json(json!({ "name": "team/app", "tags": tags }));
+
+                        let mut response = ([("x-artifactory-id", "a1b2c3")], body).into_response();
+                        if end < count {
+                            response.headers_mut().insert(
+                                header::LINK,
+                                format!("</v2/team/app/tags/list?n=2&last={}>; rel=\"next\"", end)
+                                    .parse()
+                                    .unwrap(),
+                            );
+                        }
+                        response
+                    },
+                ),
+            );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn admin() -> Option<ArtifactoryAuth> {
+        Some(ArtifactoryAuth::Basic(BasicAuth {
+            username: "admin".to_string(),
+            password: "secret".to_string(),
+        }))
+    }
+
+    #[test]
+    fn test_jfrog_cloud_is_recognised() {
+        assert!(ArtifactoryTagLister::for_registry("acme.jfrog.io").is_some());
+        assert!(ArtifactoryTagLister::for_registry("docker.io").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_tags_follows_link_headers() {
+        let url = start_artifactory(5).await;
+        let lister = ArtifactoryTagLister::new(&url, admin());
+        let before = POLLING_ARTIFACTORY_REQUESTS_TOTAL.get();
+
+        assert_eq!(
+            lister.list_tags("docker-local/team/app").await.unwrap(),
+            vec!["1.0.0", "1.0.1", "1.0.2", "1.0.3", "1.0.4"]
+        );
+        // Other tests run concurrently, so only a lower bound holds
+        assert!(POLLING_ARTIFACTORY_REQUESTS_TOTAL.get() >= before + 3);
+    }
+
+    #[tokio::test]
+    async fn test_list_tags_with_api_key_header() {
+        let url = start_artifactory(1).await;
+        let lister =
+            ArtifactoryTagLister::new(&url, Some(ArtifactoryAuth::ApiKey("secret".to_string())));
+
+        assert_eq!(
+            lister.list_tags("docker-local/team/app").await.unwrap(),
+            vec!["1.0.0"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_tags_errors() {
+        let url = start_artifactory(1).await;
+
+        let err = ArtifactoryTagLister::new(&url, None)
+            .list_tags("docker-local/team/app")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("401"), "{}", err);
+
+        let err = ArtifactoryTagLister::new(&url, admin())
+            .list_tags("docker-local/team/missing")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("404"), "{}", err);
+
+        let err = ArtifactoryTagLister::new(&url, admin())
+            .list_tags("app")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("repository key"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_is_artifactory() {
+        let url = start_artifactory(1).await;
+        assert!(ArtifactoryTagLister::new(&url, None).is_artifactory().await);
+
+        // A plain registry doesn't send X-Artifactory-Id
+        let registry = crate::testing::FakeRegistry::start().await.unwrap();
+        assert!(
+            !ArtifactoryTagLister::new(&registry.url(), None)
+                .is_artifactory()
+                .await
+        );
+    }
+}
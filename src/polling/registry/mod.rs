@@ -0,0 +1,10 @@
+//! Registries whose tags are listed through their own API or URL layout
+//! instead of the OCI distribution `tags/list` endpoint of the image's host.
+
+pub mod artifactory;
+pub mod nexus;
+pub mod quay;
+
+pub use artifactory::ArtifactoryTagLister;
+pub use nexus::NexusTagLister;
+pub use quay::QuayTagLister;
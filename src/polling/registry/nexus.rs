@@ -0,0 +1,267 @@
+//! Tag listing for Sonatype Nexus Repository Manager.
+//!
+//! Nexus (3.x Pro and OSS alike) serves each Docker repository's V2 API under
+//! `{base_url}/repository/{repository}/v2/`, so the first path segment of an
+//! image on a Nexus host names the Nexus repository:
+//! `nexus.example.com/docker-hosted/team/app` lists tags from
+//! `{base_url}/repository/docker-hosted/v2/team/app/tags/list`.
+//!
+//! Hosts are recognised by the comma-separated patterns in
+//! `HEADWIND_NEXUS_REGISTRIES` (`nexus.example.com`, `*.nexus.example.com`).
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+/// Upper bound on pages followed through `Link` headers
+const MAX_PAGES: usize = 1000;
+
+/// Username and password for Nexus basic authentication
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: String,
+}
+
+/// Whether `registry` matches one of `patterns`; `*.` matches any subdomain
+pub fn matches_registry(registry: &str, patterns: &str) -> bool {
+    patterns
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .any(|pattern| match pattern.strip_prefix("*.") {
+            Some(domain) => registry
+                .strip_suffix(domain)
+                .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+            None => registry == pattern,
+        })
+}
+
+#[derive(Debug, Deserialize)]
+struct TagList {
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+}
+
+pub struct NexusTagLister {
+    base_url: String,
+    credentials: Option<BasicAuth>,
+    http: reqwest::Client,
+}
+
+impl NexusTagLister {
+    pub fn new(base_url: &str, credentials: Option<BasicAuth>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            credentials,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Lister for `registry` if it matches `HEADWIND_NEXUS_REGISTRIES`.
+    /// `HEADWIND_NEXUS_BASE_URL` overrides the default `https://{registry}`,
+    /// and `HEADWIND_NEXUS_USERNAME`/`HEADWIND_NEXUS_PASSWORD` enable basic auth.
+    pub fn for_registry(registry: &str) -> Option<Self> {
+        let patterns = std::env::var("HEADWIND_NEXUS_REGISTRIES").ok()?;
+        if !matches_registry(registry, &patterns) {
+            return None;
+        }
+
+        let non_empty = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let base_url =
+            non_empty("HEADWIND_NEXUS_BASE_URL").unwrap_or_else(|| format!("https://{}", registry));
+        let credentials = non_empty("HEADWIND_NEXUS_USERNAME").map(|username| BasicAuth {
+            username,
+            password: non_empty("HEADWIND_NEXUS_PASSWORD").unwrap_or_default(),
+        });
+        Some(Self::new(&base_url, credentials))
+    }
+
+    /// All tags of `repository`, whose first segment is the Nexus repository
+    pub async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        let Some((nexus_repository, image)) = repository.split_once('/') else {
+            bail!(
+                "Nexus image {} must start with the Nexus repository name",
+                repository
+            );
+        };
+
+        let mut url = format!(
+            "{}/repository/{}/v2/{}/tags/list",
+            self.base_url, nexus_repository, image
+        );
+        let mut tags = Vec::new();
+
+        for _ in 0..MAX_PAGES {
+            let mut request = self.http.get(&url);
+            if let Some(auth) = &self.credentials {
+                request = request.basic_auth(&auth.username, Some(&auth.password));
+            }
+
+            let response = request.send().await?;
+            let status = response.status();
+            if !status.is_success() {
+                bail!("Nexus returned {} for {}", status, url);
+            }
+            if let Some(id) = response.headers().get("x-nexus-request-id") {
+                debug!("Nexus request {:?} for {}", id, url);
+            }
+
+            let next = next_page(response.headers()).map(|next| self.resolve(&next));
+            let page: TagList = response.json().await?;
+            tags.extend(page.tags.unwrap_or_default());
+
+            match next {
+                Some(next) => url = next,
+                None => return Ok(tags),
+            }
+        }
+
+        warn!(
+            "Stopped listing Nexus tags for {} after {} pages",
+            repository, MAX_PAGES
+        );
+        Ok(tags)
+    }
+
+    /// Absolute URL for a `Link` target, which Nexus sends as a path
+    fn resolve(&self, link: &str) -> String {
+        if link.starts_with("http://") || link.starts_with("https://") {
+            link.to_string()
+        } else {
+            format!("{}{}", self.base_url, link)
+        }
+    }
+}
+
+/// Target of a `Link: <...>; rel="next"` header
+pub(super) fn next_page(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let link = headers.get(reqwest::header::LINK)?.to_str().ok()?;
+    link.split(',').find_map(|part| {
+        let (target, params) = part.split_once(';')?;
+        params.contains("rel=\"next\"").then(|| {
+            target
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        extract::{Path, Query},
+        http::{HeaderMap, StatusCode, header},
+        response::IntoResponse,
+        routing::get,
+    };
+    use base64::Engine;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Fake Nexus serving `count` tags of `docker-hosted/team/app` in pages of
+    /// 2 linked with `Link` headers, to `admin:secret` only
+    async fn start_nexus(count: usize) -> String {
+        let app = Router::new().route(
+            "/repository/{repository}/v2/{*path}",
+            get(
+                move |Path((repository, path)): Path<(String, String)>,
+                      Query(query): Query<HashMap<String, String>>,
+                      headers: HeaderMap| async move {
+                    let expected = format!(
+                        "Basic {}",
+                        base64::engine::general_purpose::STANDARD.encode("admin:secret")
+                    );
+                    if headers.get(header::AUTHORIZATION).and_then(|v| v.to_str().ok())
+                        != Some(expected.as_str())
+                    {
+                        return StatusCode::UNAUTHORIZED.into_response();
+                    }
+                    if repository != "docker-hosted" || path != "team/app/tags/list" {
+                        return StatusCode::NOT_FOUND.into_response();
+                    }
+
+                    let start: usize = query.get("last").map(|l| l.parse().unwrap()).unwrap_or(0);
+                    let end = count.min(start + 2);
+                    let tags: Vec<String> = (start..end).map(|i| format!("1.0.{}", i)).collect();
+                    let body = Json(json!({ "name": "team/app", "tags": tags }));
+
+                    let mut response = ([("x-nexus-request-id", "42")], body).into_response();
+                    if end < count {
+                        response.headers_mut().insert(
+                            header::LINK,
+                            format!(
+                                "</repository/docker-hosted/v2/team/app/tags/list?n=2&last={}>; rel=\"next\"",
+                                end
+                            )
+                            .parse()
+                            .unwrap(),
+                        );
+                    }
+                    response
+                },
+            ),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    fn admin() -> Option<BasicAuth> {
+        Some(BasicAuth {
+            username: "admin".to_string(),
+            password: "secret".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_matches_registry() {
+        let patterns = "nexus.example.com, *.corp.example.com";
+        assert!(matches_registry("nexus.example.com", patterns));
+        assert!(matches_registry("docker.corp.example.com", patterns));
+        assert!(!matches_registry("corp.example.com", patterns));
+        assert!(!matches_registry("evilcorp.example.com", patterns));
+        assert!(!matches_registry("docker.io", patterns));
+        assert!(!matches_registry("docker.io", ""));
+    }
+
+    #[tokio::test]
+    async fn test_list_tags_follows_link_headers() {
+        let url = start_nexus(5).await;
+        let lister = NexusTagLister::new(&url, admin());
+
+        assert_eq!(
+            lister.list_tags("docker-hosted/team/app").await.unwrap(),
+            vec!["1.0.0", "1.0.1", "1.0.2", "1.0.3", "1.0.4"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_tags_errors() {
+        let url = start_nexus(1).await;
+
+        let err = NexusTagLister::new(&url, None)
+            .list_tags("docker-hosted/team/app")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("401"), "{}", err);
+
+        let err = NexusTagLister::new(&url, admin())
+            .list_tags("docker-hosted/team/missing")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("404"), "{}", err);
+
+        let err = NexusTagLister::new(&url, admin())
+            .list_tags("app")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Nexus repository name"), "{}", err);
+    }
+}
@@ -0,0 +1,233 @@
+//! Tag listing through the Quay.io REST API.
+//!
+//! `GET /api/v1/repository/{namespace}/{repository}/tag/` returns active tags
+//! page by page with a `has_additional` flag, and accepts OAuth application
+//! tokens (`HEADWIND_QUAY_TOKEN`) for private repositories.
+
+use crate::metrics::POLLING_QUAY_REQUESTS_TOTAL;
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+const QUAY_URL: &str = "https://quay.io";
+
+/// Tags requested per page, the maximum Quay allows
+const PAGE_SIZE: usize = 100;
+
+/// Upper bound on pages fetched for one repository
+const MAX_PAGES: usize = 1000;
+
+/// Whether `registry` is Quay.io
+pub fn is_quay(registry: &str) -> bool {
+    registry == "quay.io"
+}
+
+#[derive(Debug, Deserialize)]
+struct TagPage {
+    tags: Vec<QuayTag>,
+    page: usize,
+    has_additional: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuayTag {
+    name: String,
+}
+
+pub struct QuayTagLister {
+    base_url: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl QuayTagLister {
+    pub fn new(base_url: &str, token: Option<String>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Quay.io, authenticated with `HEADWIND_QUAY_TOKEN` when it is set
+    pub fn from_env() -> Self {
+        let token = std::env::var("HEADWIND_QUAY_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty());
+        Self::new(QUAY_URL, token)
+    }
+
+    /// Names of all active tags of `repository` (`namespace/name`)
+    pub async fn list_tags(&self, repository: &str) -> Result<Vec<String>> {
+        let mut tags = Vec::new();
+
+        for page in 1..=MAX_PAGES {
+            let response = self.fetch_page(repository, page).await?;
+            debug!(
+                "Quay returned {} tags for {} (page {})",
+                response.tags.len(),
+                repository,
+                response.page
+            );
+            tags.extend(response.tags.into_iter().map(|t| t.name));
+
+            if !response.has_additional {
+                return Ok(tags);
+            }
+        }
+
+        warn!(
+            "Stopped listing Quay tags for {} after {} pages",
+            repository, MAX_PAGES
+        );
+        Ok(tags)
+    }
+
+    async fn fetch_page(&self, repository: &str, page: usize) -> Result<TagPage> {
+        let mut request = self
+            .http
+            .get(format!(
+                "{}/api/v1/repository/{}/tag/",
+                self.base_url, repository
+            ))
+            .query(&[
+                ("onlyActiveTags", "true".to_string()),
+                ("page", page.to_string()),
+                ("limit", PAGE_SIZE.to_string()),
+            ]);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+
+        POLLING_QUAY_REQUESTS_TOTAL.inc();
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|body| {
+                    body.get("error_message")
+                        .or_else(|| body.get("detail"))
+                        .and_then(|m| m.as_str())
+                        .map(String::from)
+                })
+                .unwrap_or_default();
+            bail!("Quay API returned {}: {}", status, message);
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        extract::{Path, Query},
+        http::{HeaderMap, StatusCode},
+        routing::get,
+    };
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+
+    /// Fake Quay API serving `count` tags of `team/app` in pages of `limit`,
+    /// and only to requests with the token `secret`
+    async fn start_quay(count: usize) -> String {
+        let app = Router::new().route(
+            "/api/v1/repository/{namespace}/{repository}/tag/",
+            get(
+                move |Path((namespace, repository)): Path<(String, String)>,
+                      Query(query): Query<HashMap<String, String>>,
+                      headers: HeaderMap| async move {
+                    if headers.get("authorization").and_then(|v| v.to_str().ok())
+                        != Some("Bearer secret")
+                    {
+                        return (
+                            StatusCode::UNAUTHORIZED,
+                            Json(json!({"error_message": "Invalid token"})),
+                        );
+                    }
+                    if (namespace.as_str(), repository.as_str()) != ("team", "app") {
+                        return (StatusCode::NOT_FOUND, Json(json!({"detail": "Not Found"})));
+                    }
+                    assert_eq!(query["onlyActiveTags"], "true");
+
+                    let page: usize = query["page"].parse().unwrap();
+                    let limit: usize = query["limit"].parse().unwrap();
+                    let start = (page - 1) * limit;
+                    let tags: Vec<Value> = (start..count.min(start + limit))
+                        .map(|i| json!({"name": format!("1.0.{}", i), "reversion": false}))
+                        .collect();
+                    (
+                        StatusCode::OK,
+                        Json(json!({
+                            "tags": tags,
+                            "page": page,
+                            "has_additional": start + limit < count
+                        })),
+                    )
+                },
+            ),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_is_quay() {
+        assert!(is_quay("quay.io"));
+        assert!(!is_quay("ghcr.io"));
+        assert!(!is_quay("quay.example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_list_tags_single_page() {
+        let url = start_quay(3).await;
+        let lister = QuayTagLister::new(&url, Some("secret".to_string()));
+
+        assert_eq!(
+            lister.list_tags("team/app").await.unwrap(),
+            vec!["1.0.0", "1.0.1", "1.0.2"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_tags_multiple_pages() {
+        let url = start_quay(250).await;
+        let lister = QuayTagLister::new(&url, Some("secret".to_string()));
+        let before = POLLING_QUAY_REQUESTS_TOTAL.get();
+
+        let tags = lister.list_tags("team/app").await.unwrap();
+        assert_eq!(tags.len(), 250);
+        assert_eq!(tags.first().unwrap(), "1.0.0");
+        assert_eq!(tags.last().unwrap(), "1.0.249");
+        // Other tests run concurrently, so only a lower bound holds
+        assert!(POLLING_QUAY_REQUESTS_TOTAL.get() >= before + 3);
+    }
+
+    #[tokio::test]
+    async fn test_list_tags_errors() {
+        let url = start_quay(3).await;
+
+        let err = QuayTagLister::new(&url, None)
+            .list_tags("team/app")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Quay API returned 401 Unauthorized: Invalid token"
+        );
+
+        let err = QuayTagLister::new(&url, Some("secret".to_string()))
+            .list_tags("team/missing")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("404"));
+    }
+}
@@ -0,0 +1,242 @@
+//! Dry-run policy evaluation.
+//!
+//! Lists the tags available for every container image of Deployments with a
+//! `headwind.sh/policy` annotation and reports which update the policy would
+//! pick, without creating UpdateRequests, sending events or patching anything.
+
+use super::{ImageToTrack, RegistryPoller, best_tag};
+use crate::models::policy::{
+    CredentialSource, ResourcePolicy, UpdatePolicy, annotations, global_tag_normalization,
+    parse_tag_normalization,
+};
+use anyhow::Result;
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::Api;
+use oci_distribution::Reference;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tracing::{debug, warn};
+
+/// What the update policy would do for one container image
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SimulationResult {
+    pub resource_kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub current_image: String,
+    /// The tag the policy would update to, or the newest tag if it would not update
+    pub latest_available: Option<String>,
+    pub would_update: bool,
+    /// Why no update would happen, when `would_update` is false
+    pub policy_rejection_reason: Option<String>,
+}
+
+impl RegistryPoller {
+    /// Evaluate every tracked Deployment image against the registry.
+    ///
+    /// `namespace` restricts the Deployments considered; `policy` overrides
+    /// each Deployment's own `headwind.sh/policy` annotation.
+    pub async fn simulate(
+        &self,
+        namespace: Option<&str>,
+        policy: Option<UpdatePolicy>,
+    ) -> Result<Vec<SimulationResult>> {
+        let deployments: Api<Deployment> = match namespace {
+            Some(namespace) => Api::namespaced(self.client.clone(), namespace),
+            None => Api::all(self.client.clone()),
+        };
+
+        let mut results = Vec::new();
+        for deployment in deployments.list(&Default::default()).await?.items {
+            let metadata = &deployment.metadata;
+            let Some(policy_str) = metadata
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(annotations::POLICY))
+            else {
+                continue;
+            };
+
+            let resource_policy = match policy {
+                Some(policy) => policy,
+                None => match UpdatePolicy::from_str(policy_str) {
+                    Ok(p) => p,
+                    Err(e) => {
+                        warn!("Invalid policy '{}': {}", policy_str, e);
+                        continue;
+                    },
+                },
+            };
+            let resource_policy = ResourcePolicy {
+                policy: resource_policy,
+                pattern: metadata
+                    .annotations
+                    .as_ref()
+                    .and_then(|a| a.get(annotations::PATTERN))
+                    .cloned(),
+                tag_normalization: parse_tag_normalization(
+                    metadata.annotations.as_ref().unwrap_or(&Default::default()),
+                    &global_tag_normalization(),
+                )
+                .unwrap_or_else(|_| global_tag_normalization()),
+                ..Default::default()
+            };
+            let credential_source = metadata
+                .annotations
+                .as_ref()
+                .and_then(|a| a.get(annotations::CREDENTIAL_SOURCE))
+                .and_then(|v| v.parse::<CredentialSource>().ok())
+                .unwrap_or_default();
+
+            let namespace = metadata
+                .namespace
+                .clone()
+                .unwrap_or_else(|| "default".to_string());
+            let name = metadata.name.clone().unwrap_or_default();
+            let containers = deployment
+                .spec
+                .as_ref()
+                .and_then(|spec| spec.template.spec.as_ref())
+                .map(|spec| spec.containers.as_slice())
+                .unwrap_or_default();
+
+            for image in containers.iter().filter_map(|c| c.image.as_ref()) {
+                debug!("Simulating {} for {}/{}", image, namespace, name);
+                let (latest_available, policy_rejection_reason) = self
+                    .simulate_image(image, &namespace, &resource_policy, credential_source)
+                    .await;
+
+                results.push(SimulationResult {
+                    resource_kind: "Deployment".to_string(),
+                    namespace: namespace.clone(),
+                    name: name.clone(),
+                    current_image: image.clone(),
+                    latest_available,
+                    would_update: policy_rejection_reason.is_none(),
+                    policy_rejection_reason,
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// The tag to report for `image` and, if the policy would not update, why
+    async fn simulate_image(
+        &self,
+        image: &str,
+        namespace: &str,
+        policy: &ResourcePolicy,
+        credential_source: CredentialSource,
+    ) -> (Option<String>, Option<String>) {
+        if matches!(policy.policy, UpdatePolicy::None) {
+            return (None, Some("Policy is none".to_string()));
+        }
+
+        let reference = match Reference::try_from(image) {
+            Ok(reference) => reference,
+            Err(e) => return (None, Some(format!("Invalid image reference: {}", e))),
+        };
+        let current_tag = reference.tag().unwrap_or("latest");
+
+        let image_info = ImageToTrack::new(image, policy.policy, namespace)
+            .with_credential_source(credential_source);
+        let tags = match self.fetch_digest_with_auth(&reference, &image_info).await {
+            Ok(Some((client, auth, _))) => self.list_tags_cached(&client, &reference, &auth).await,
+            Ok(None) => Err(anyhow::anyhow!("registry request failed")),
+            Err(e) => Err(e),
+        };
+        let tags = match tags {
+            Ok(tags) => tags,
+            Err(e) => return (None, Some(format!("Failed to list tags: {}", e))),
+        };
+
+        evaluate_tags(&tags, current_tag, policy)
+    }
+}
+
+/// Decide the reported tag and rejection reason from the repository's tags
+fn evaluate_tags(
+    tags: &[String],
+    current_tag: &str,
+    policy: &ResourcePolicy,
+) -> (Option<String>, Option<String>) {
+    if let Some(best) = best_tag(tags, current_tag, policy) {
+        return (Some(best), None);
+    }
+
+    // The newest version regardless of policy, to show what is being held back
+    let newest = best_tag(
+        tags,
+        current_tag,
+        &ResourcePolicy {
+            policy: UpdatePolicy::Major,
+            ..Default::default()
+        },
+    );
+    let reason = match &newest {
+        Some(newest) => format!(
+            "{} is not allowed by the {:?} policy",
+            newest, policy.policy
+        ),
+        None if tags.is_empty() => "No tags found in the registry".to_string(),
+        None => format!("No newer version than {}", current_tag),
+    };
+
+    (newest, Some(reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|t| t.to_string()).collect()
+    }
+
+    fn policy(policy: UpdatePolicy) -> ResourcePolicy {
+        ResourcePolicy {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_evaluate_tags_would_update() {
+        let (latest, reason) = evaluate_tags(
+            &tags(&["1.0.0", "1.0.1", "1.1.0", "2.0.0"]),
+            "1.0.0",
+            &policy(UpdatePolicy::Minor),
+        );
+        assert_eq!(latest.as_deref(), Some("1.1.0"));
+        assert_eq!(reason, None);
+    }
+
+    #[test]
+    fn test_evaluate_tags_held_back_by_policy() {
+        let (latest, reason) = evaluate_tags(
+            &tags(&["1.0.0", "2.0.0"]),
+            "1.0.0",
+            &policy(UpdatePolicy::Patch),
+        );
+        assert_eq!(latest.as_deref(), Some("2.0.0"));
+        assert_eq!(
+            reason.as_deref(),
+            Some("2.0.0 is not allowed by the Patch policy")
+        );
+    }
+
+    #[test]
+    fn test_evaluate_tags_up_to_date() {
+        let (latest, reason) = evaluate_tags(
+            &tags(&["0.9.0", "1.0.0"]),
+            "1.0.0",
+            &policy(UpdatePolicy::Major),
+        );
+        assert_eq!(latest, None);
+        assert_eq!(reason.as_deref(), Some("No newer version than 1.0.0"));
+
+        let (_, reason) = evaluate_tags(&[], "1.0.0", &policy(UpdatePolicy::Major));
+        assert_eq!(reason.as_deref(), Some("No tags found in the registry"));
+    }
+}
@@ -0,0 +1,146 @@
+//! TLS settings for registry connections.
+//!
+//! Private registries often present certificates from an internal CA. The
+//! PEM bundle in `HEADWIND_REGISTRY_CA_CERT_FILE` is trusted for every
+//! registry, and `HEADWIND_REGISTRY_TLS_{HOST}` adds a bundle for a single
+//! registry: `HEADWIND_REGISTRY_TLS_REGISTRY_EXAMPLE_COM_5000` applies to
+//! `registry.example.com:5000`. `HEADWIND_REGISTRY_INSECURE=true` disables
+//! certificate verification altogether.
+
+use anyhow::{Context, Result};
+use oci_distribution::client::{Certificate, CertificateEncoding, ClientConfig, ClientProtocol};
+use std::collections::HashMap;
+use tracing::warn;
+
+const PER_REGISTRY_PREFIX: &str = "HEADWIND_REGISTRY_TLS_";
+
+/// Extra trust for registry certificates
+#[derive(Debug, Clone, Default)]
+pub struct RegistryTlsConfig {
+    /// CA certificates (PEM) trusted for every registry
+    pub ca_cert_pem: Option<String>,
+    /// Accept any certificate, including expired and self-signed ones
+    pub insecure_skip_verify: bool,
+    /// CA certificates (PEM) per registry, keyed by [`env_suffix`] of the host
+    pub registry_ca_certs: HashMap<String, String>,
+}
+
+impl RegistryTlsConfig {
+    /// Load the settings and read the certificate files they point at
+    pub fn from_env() -> Result<Self> {
+        let ca_cert_pem = std::env::var("HEADWIND_REGISTRY_CA_CERT_FILE")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(|path| read_pem(&path))
+            .transpose()?;
+        let insecure_skip_verify = std::env::var("HEADWIND_REGISTRY_INSECURE")
+            .is_ok_and(|v| v.eq_ignore_ascii_case("true"));
+        if insecure_skip_verify {
+            warn!("Registry certificate verification is disabled (HEADWIND_REGISTRY_INSECURE)");
+        }
+
+        let mut registry_ca_certs = HashMap::new();
+        for (name, path) in std::env::vars() {
+            if let Some(suffix) = name.strip_prefix(PER_REGISTRY_PREFIX)
+                && !suffix.is_empty()
+                && !path.is_empty()
+            {
+                registry_ca_certs.insert(suffix.to_string(), read_pem(&path)?);
+            }
+        }
+
+        Ok(Self {
+            ca_cert_pem,
+            insecure_skip_verify,
+            registry_ca_certs,
+        })
+    }
+
+    /// CA certificates to trust for `registry` (`host[:port]`)
+    pub fn ca_certs_for(&self, registry: &str) -> Vec<&str> {
+        self.ca_cert_pem
+            .iter()
+            .chain(self.registry_ca_certs.get(&env_suffix(registry)))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// OCI client configuration for `registry`
+    pub fn client_config(&self, registry: &str, insecure_registries: &[String]) -> ClientConfig {
+        ClientConfig {
+            protocol: ClientProtocol::HttpsExcept(insecure_registries.to_vec()),
+            accept_invalid_certificates: self.insecure_skip_verify,
+            extra_root_certificates: self
+                .ca_certs_for(registry)
+                .into_iter()
+                .map(|pem| Certificate {
+                    encoding: CertificateEncoding::Pem,
+                    data: pem.as_bytes().to_vec(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+/// `registry` as it appears in `HEADWIND_REGISTRY_TLS_{HOST}`: upper case,
+/// with every character other than letters and digits replaced by `_`
+pub fn env_suffix(registry: &str) -> String {
+    registry
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn read_pem(path: &str) -> Result<String> {
+    let pem = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read registry CA certificate {}", path))?;
+    if !pem.contains("-----BEGIN CERTIFICATE-----") {
+        anyhow::bail!("No PEM certificate found in {}", path);
+    }
+    Ok(pem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_suffix() {
+        assert_eq!(
+            env_suffix("registry.example.com:5000"),
+            "REGISTRY_EXAMPLE_COM_5000"
+        );
+        assert_eq!(env_suffix("ghcr.io"), "GHCR_IO");
+    }
+
+    #[test]
+    fn test_ca_certs_for() {
+        let config = RegistryTlsConfig {
+            ca_cert_pem: Some("global".to_string()),
+            insecure_skip_verify: false,
+            registry_ca_certs: [("REGISTRY_EXAMPLE_COM".to_string(), "own".to_string())].into(),
+        };
+
+        assert_eq!(
+            config.ca_certs_for("registry.example.com"),
+            vec!["global", "own"]
+        );
+        assert_eq!(config.ca_certs_for("ghcr.io"), vec!["global"]);
+        assert!(
+            RegistryTlsConfig::default()
+                .ca_certs_for("ghcr.io")
+                .is_empty()
+        );
+
+        let client_config = config.client_config("registry.example.com", &[]);
+        assert_eq!(client_config.extra_root_certificates.len(), 2);
+        assert!(!client_config.accept_invalid_certificates);
+    }
+}
@@ -0,0 +1,527 @@
+//! Registry credentials from HashiCorp Vault.
+//!
+//! Logs in with the Kubernetes auth method using the pod's service account
+//! token and reads `username`/`password` from a KV v2 secret. The Vault token
+//! is renewed once four fifths of its lease have passed (falling back to a
+//! fresh login), and credentials are cached per registry.
+
+use super::auth::RegistryCredentials;
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Service account token mounted into every pod
+const SERVICE_ACCOUNT_TOKEN_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+
+/// How long credentials read from Vault are reused when the secret has no lease
+const CREDENTIAL_TTL: Duration = Duration::from_secs(300);
+
+/// Placeholder in the credential path replaced by the registry host
+const REGISTRY_PLACEHOLDER: &str = "{registry}";
+
+#[derive(Debug, Deserialize)]
+struct AuthResponse {
+    auth: AuthInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthInfo {
+    client_token: String,
+    #[serde(default)]
+    lease_duration: u64,
+    #[serde(default)]
+    renewable: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct KvV2Response {
+    #[serde(default)]
+    lease_duration: u64,
+    data: KvV2Data,
+}
+
+#[derive(Debug, Deserialize)]
+struct KvV2Data {
+    data: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct CachedToken {
+    token: String,
+    renewable: bool,
+    /// When to renew; `None` for tokens without a lease
+    renew_at: Option<Instant>,
+    expires_at: Option<Instant>,
+}
+
+impl CachedToken {
+    fn from_auth(auth: AuthInfo) -> Self {
+        let now = Instant::now();
+        let lease = Duration::from_secs(auth.lease_duration);
+        let has_lease = auth.lease_duration > 0;
+        Self {
+            token: auth.client_token,
+            renewable: auth.renewable,
+            renew_at: has_lease.then(|| now + lease * 4 / 5),
+            expires_at: has_lease.then(|| now + lease),
+        }
+    }
+
+    fn needs_renewal(&self, now: Instant) -> bool {
+        self.renew_at.is_some_and(|at| now >= at)
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|at| now >= at)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedCredential {
+    credentials: RegistryCredentials,
+    expires_at: Instant,
+}
+
+/// Reads registry credentials from a Vault KV v2 secret
+pub struct VaultCredentialProvider {
+    /// Vault address, e.g. `https://vault.example.com:8200`
+    pub addr: String,
+    /// Role of the Kubernetes auth method
+    pub role: String,
+    /// KV v2 API path, e.g. `secret/data/headwind/{registry}`
+    pub path: String,
+    token_path: PathBuf,
+    http: reqwest::Client,
+    token: Option<CachedToken>,
+    credentials: HashMap<String, CachedCredential>,
+}
+
+impl VaultCredentialProvider {
+    pub fn new(addr: impl Into<String>, role: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into().trim_end_matches('/').to_string(),
+            role: role.into(),
+            path: path.into().trim_matches('/').to_string(),
+            token_path: PathBuf::from(SERVICE_ACCOUNT_TOKEN_PATH),
+            http: reqwest::Client::new(),
+            token: None,
+            credentials: HashMap::new(),
+        }
+    }
+
+    /// Provider configured by `HEADWIND_VAULT_ADDR`, `HEADWIND_VAULT_ROLE` and
+    /// `HEADWIND_VAULT_CREDENTIAL_PATH`, or `None` unless all three are set
+    pub fn from_env() -> Option<Self> {
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+        Some(Self::new(
+            var("HEADWIND_VAULT_ADDR")?,
+            var("HEADWIND_VAULT_ROLE")?,
+            var("HEADWIND_VAULT_CREDENTIAL_PATH")?,
+        ))
+    }
+
+    /// Read the service account token from `path` instead of the default mount
+    #[cfg(test)]
+    pub fn with_token_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.token_path = path.into();
+        self
+    }
+
+    /// Credentials for `registry` read earlier and not yet expired
+    pub fn cached_credentials(&self, registry: &str) -> Option<RegistryCredentials> {
+        self.credentials
+            .get(registry)
+            .filter(|cached| Instant::now() < cached.expires_at)
+            .map(|cached| cached.credentials.clone())
+    }
+
+    /// Credentials for `registry`, from the cache or Vault
+    pub async fn get_credentials(&mut self, registry: &str) -> Result<RegistryCredentials> {
+        if let Some(credentials) = self.cached_credentials(registry) {
+            debug!("Using cached Vault credentials for {}", registry);
+            return Ok(credentials);
+        }
+        self.fetch_credentials(registry).await
+    }
+
+    /// Registries with cached credentials and when they expire
+    pub fn expiries(&self) -> Vec<(String, Instant)> {
+        self.credentials
+            .iter()
+            .map(|(registry, cached)| (registry.clone(), cached.expires_at))
+            .collect()
+    }
+
+    /// Read the credentials of `registry` from Vault again, keeping the cached
+    /// ones if that fails
+    pub async fn refresh(&mut self, registry: &str) -> Result<()> {
+        self.fetch_credentials(registry).await.map(|_| ())
+    }
+
+    /// Forget the cached credentials of `registry`
+    pub fn invalidate(&mut self, registry: &str) {
+        self.credentials.remove(registry);
+    }
+
+    async fn fetch_credentials(&mut self, registry: &str) -> Result<RegistryCredentials> {
+        let token = self.token().await?;
+        let (credentials, ttl) = match self.read_secret(&token, registry).await {
+            Err(e) if is_forbidden(&e) => {
+                // The token may have been revoked; log in again once
+                debug!("Vault rejected the cached token, logging in again");
+                self.token = None;
+                let token = self.token().await?;
+                self.read_secret(&token, registry).await?
+            },
+            result => result?,
+        };
+
+        self.credentials.insert(
+            registry.to_string(),
+            CachedCredential {
+                credentials: credentials.clone(),
+                expires_at: Instant::now() + ttl,
+            },
+        );
+        Ok(credentials)
+    }
+
+    /// Forget the Vault token and all cached credentials
+    #[allow(dead_code)] // Mirrors AuthManager::clear_cache
+    pub fn clear_cache(&mut self) {
+        self.token = None;
+        self.credentials.clear();
+    }
+
+    /// A valid Vault token, renewing or logging in as needed
+    async fn token(&mut self) -> Result<String> {
+        let now = Instant::now();
+        if let Some(cached) = self.token.clone() {
+            if !cached.needs_renewal(now) {
+                return Ok(cached.token);
+            }
+            if cached.renewable && !cached.expired(now) {
+                match self.renew(&cached.token).await {
+                    Ok(renewed) => {
+                        debug!("Renewed Vault token");
+                        let token = renewed.token.clone();
+                        self.token = Some(renewed);
+                        return Ok(token);
+                    },
+                    Err(e) => warn!("Failed to renew Vault token, logging in again: {}", e),
+                }
+            }
+        }
+
+        let token = self.login().await?;
+        let value = token.token.clone();
+        self.token = Some(token);
+        Ok(value)
+    }
+
+    async fn login(&self) -> Result<CachedToken> {
+        let jwt = tokio::fs::read_to_string(&self.token_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to read service account token from {}",
+                    self.token_path.display()
+                )
+            })?;
+
+        debug!("Logging in to Vault at {} as role {}", self.addr, self.role);
+        let response = self
+            .http
+            .post(format!("{}/v1/auth/kubernetes/login", self.addr))
+            .json(&json!({ "role": self.role, "jwt": jwt.trim() }))
+            .send()
+            .await
+            .context("Failed to reach Vault")?;
+        let response: AuthResponse = check_status(response, "Vault login")
+            .await?
+            .json()
+            .await
+            .context("Invalid Vault login response")?;
+
+        Ok(CachedToken::from_auth(response.auth))
+    }
+
+    async fn renew(&self, token: &str) -> Result<CachedToken> {
+        let response = self
+            .http
+            .post(format!("{}/v1/auth/token/renew-self", self.addr))
+            .header("X-Vault-Token", token)
+            .send()
+            .await
+            .context("Failed to reach Vault")?;
+        let response: AuthResponse = check_status(response, "Vault token renewal")
+            .await?
+            .json()
+            .await
+            .context("Invalid Vault token renewal response")?;
+
+        Ok(CachedToken::from_auth(response.auth))
+    }
+
+    /// Read the KV v2 secret for `registry`, returning its credentials and cache TTL
+    async fn read_secret(
+        &self,
+        token: &str,
+        registry: &str,
+    ) -> Result<(RegistryCredentials, Duration)> {
+        let path = self.path.replace(REGISTRY_PLACEHOLDER, registry);
+        let response = self
+            .http
+            .get(format!("{}/v1/{}", self.addr, path))
+            .header("X-Vault-Token", token)
+            .send()
+            .await
+            .context("Failed to reach Vault")?;
+        let secret: KvV2Response = check_status(response, "Vault secret read")
+            .await?
+            .json()
+            .await
+            .with_context(|| format!("Vault secret {} is not a KV v2 secret", path))?;
+
+        let field = |name: &str| {
+            secret
+                .data
+                .data
+                .get(name)
+                .cloned()
+                .ok_or_else(|| anyhow!("Vault secret {} has no '{}' key", path, name))
+        };
+        let credentials = RegistryCredentials {
+            registry: registry.to_string(),
+            username: field("username")?,
+            password: field("password")?,
+        };
+        let ttl = match secret.lease_duration {
+            0 => CREDENTIAL_TTL,
+            secs => Duration::from_secs(secs),
+        };
+        Ok((credentials, ttl))
+    }
+}
+
+/// Error returned by Vault with a non-success status
+#[derive(Debug)]
+struct VaultError {
+    status: reqwest::StatusCode,
+    message: String,
+}
+
+impl std::fmt::Display for VaultError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.message, self.status)
+    }
+}
+
+impl std::error::Error for VaultError {}
+
+async fn check_status(response: reqwest::Response, action: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(VaultError {
+        status,
+        message: format!("{} failed: {}", action, body.trim()),
+    }
+    .into())
+}
+
+fn is_forbidden(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<VaultError>()
+        .is_some_and(|e| e.status == reqwest::StatusCode::FORBIDDEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        Json, Router,
+        extract::{Path, State},
+        http::{HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::{get, post},
+    };
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct MockVault {
+        lease_duration: u64,
+        logins: Vec<Value>,
+        renewals: usize,
+        reads: Vec<String>,
+        issued: usize,
+        revoked: Vec<String>,
+    }
+
+    type Shared = Arc<Mutex<MockVault>>;
+
+    async fn login(State(state): State<Shared>, Json(body): Json<Value>) -> Response {
+        let mut state = state.lock().unwrap();
+        if body["jwt"] != "sa-token" || body["role"] != "headwind" {
+            return (StatusCode::FORBIDDEN, "permission denied").into_response();
+        }
+        state.logins.push(body);
+        state.issued += 1;
+        Json(json!({
+            "auth": {
+                "client_token": format!("token-{}", state.issued),
+                "lease_duration": state.lease_duration,
+                "renewable": true,
+            }
+        }))
+        .into_response()
+    }
+
+    async fn renew(State(state): State<Shared>, headers: HeaderMap) -> Json<Value> {
+        let mut state = state.lock().unwrap();
+        state.renewals += 1;
+        Json(json!({
+            "auth": {
+                "client_token": headers["x-vault-token"].to_str().unwrap(),
+                "lease_duration": state.lease_duration,
+                "renewable": true,
+            }
+        }))
+    }
+
+    async fn read(
+        State(state): State<Shared>,
+        Path(path): Path<String>,
+        headers: HeaderMap,
+    ) -> Response {
+        let mut state = state.lock().unwrap();
+        let token = headers
+            .get("x-vault-token")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        if !token.starts_with("token-") || state.revoked.iter().any(|t| t == token) {
+            return (StatusCode::FORBIDDEN, "permission denied").into_response();
+        }
+        state.reads.push(path.clone());
+        if path != "secret/data/registries/ghcr.io" {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        Json(json!({
+            "lease_duration": 0,
+            "data": {
+                "data": { "username": "robot", "password": "s3cret" },
+                "metadata": { "version": 1 },
+            }
+        }))
+        .into_response()
+    }
+
+    async fn start_mock_vault(lease_duration: u64) -> (String, Shared) {
+        let state = Arc::new(Mutex::new(MockVault {
+            lease_duration,
+            ..Default::default()
+        }));
+        let app = Router::new()
+            .route("/v1/auth/kubernetes/login", post(login))
+            .route("/v1/auth/token/renew-self", post(renew))
+            .route("/v1/{*path}", get(read))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}", addr), state)
+    }
+
+    fn provider(addr: &str) -> VaultCredentialProvider {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let token_path = std::env::temp_dir().join(format!(
+            "headwind-vault-test-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        std::fs::write(&token_path, "sa-token\n").unwrap();
+        VaultCredentialProvider::new(addr, "headwind", "secret/data/registries/{registry}")
+            .with_token_path(token_path)
+    }
+
+    #[tokio::test]
+    async fn test_reads_credentials_and_caches_them() {
+        let (addr, state) = start_mock_vault(3600).await;
+        let mut provider = provider(&addr);
+
+        assert!(provider.cached_credentials("ghcr.io").is_none());
+        let credentials = provider.get_credentials("ghcr.io").await.unwrap();
+        assert_eq!(credentials.username, "robot");
+        assert_eq!(credentials.password, "s3cret");
+
+        // Second lookup is served from the cache
+        provider.get_credentials("ghcr.io").await.unwrap();
+        assert!(provider.cached_credentials("ghcr.io").is_some());
+
+        let state = state.lock().unwrap();
+        assert_eq!(state.logins.len(), 1);
+        assert_eq!(state.logins[0]["jwt"], "sa-token");
+        assert_eq!(state.reads, vec!["secret/data/registries/ghcr.io"]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_secret_is_an_error() {
+        let (addr, _state) = start_mock_vault(3600).await;
+        let mut provider = provider(&addr);
+
+        let error = provider.get_credentials("quay.io").await.unwrap_err();
+        assert!(error.to_string().contains("Vault secret read failed"));
+        assert!(provider.cached_credentials("quay.io").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_login_failure() {
+        let (addr, state) = start_mock_vault(3600).await;
+        let mut provider = provider(&addr);
+        provider.role = "other".to_string();
+
+        let error = provider.get_credentials("ghcr.io").await.unwrap_err();
+        assert!(error.to_string().contains("Vault login failed"));
+        assert!(state.lock().unwrap().reads.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_renews_token_before_expiry() {
+        let (addr, state) = start_mock_vault(1).await;
+        let mut provider = provider(&addr);
+
+        provider.get_credentials("ghcr.io").await.unwrap();
+        // Past four fifths of the one second lease, but not expired
+        tokio::time::sleep(Duration::from_millis(850)).await;
+        provider.credentials.clear();
+        provider.get_credentials("ghcr.io").await.unwrap();
+
+        let state = state.lock().unwrap();
+        assert_eq!(state.logins.len(), 1);
+        assert_eq!(state.renewals, 1);
+    }
+
+    #[tokio::test]
+    async fn test_logs_in_again_after_token_revoked() {
+        let (addr, state) = start_mock_vault(3600).await;
+        let mut provider = provider(&addr);
+
+        provider.get_credentials("ghcr.io").await.unwrap();
+        state.lock().unwrap().revoked.push("token-1".to_string());
+        provider.credentials.clear();
+
+        let credentials = provider.get_credentials("ghcr.io").await.unwrap();
+        assert_eq!(credentials.username, "robot");
+        assert_eq!(state.lock().unwrap().logins.len(), 2);
+    }
+}
@@ -0,0 +1,485 @@
+//! Manual rollback to the image saved in `headwind.sh/previous-image`.
+//!
+//! Every image update of a Deployment, StatefulSet, DaemonSet or ReplicaSet
+//! saves the image it replaced in the `headwind.sh/previous-image`
+//! annotation. Rolling back restores that image through the same tracked
+//! update path, which in turn saves the image being rolled back from, so a
+//! second rollback undoes the first. Each rollback is documented by an
+//! UpdateRequest in the `RolledBack` phase and a `ManualRollback` event on the
+//! workload.
+//!
+//! A forced rollback names the image to restore instead, for workloads whose
+//! annotation is missing, e.g. because they were last updated outside Headwind.
+
+use super::RollbackReason;
+use crate::controller::{
+    update_daemonset_image_with_tracking, update_deployment_image_with_tracking,
+    update_replicaset_image_with_tracking, update_statefulset_image_with_tracking,
+};
+use crate::models::crd::{
+    RollbackRecord, TargetRef, UpdatePhase, UpdatePolicyType, UpdateRequest, UpdateRequestSpec,
+    UpdateRequestStatus, UpdateType,
+};
+use crate::models::policy::annotations;
+use crate::notifications::{self, DeploymentInfo};
+use crate::polling::digest::split_tag;
+use chrono::Utc;
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use k8s_openapi::api::core::v1::{Container, ObjectReference, PodSpec};
+use kube::api::{Patch, PatchParams, PostParams};
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use kube::{Api, Client, Resource, ResourceExt};
+use oci_distribution::Reference;
+use serde::Serialize;
+use std::fmt;
+use thiserror::Error;
+use tracing::{info, warn};
+
+/// Resource kinds that can be rolled back to their previous image
+pub const ROLLBACK_KINDS: &[&str] = &["Deployment", "StatefulSet", "DaemonSet", "ReplicaSet"];
+
+/// Reason of the event emitted on a rolled back workload
+pub const MANUAL_ROLLBACK_REASON: &str = "ManualRollback";
+
+/// Response of `POST /api/v1/rollback/{namespace}/{resource_kind}/{name}`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManualRollback {
+    pub resource_kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub container: String,
+    pub rolled_back_from: String,
+    pub rolled_back_to: String,
+    pub rolled_back_by: String,
+    /// UpdateRequest documenting the rollback, unless it could not be created
+    pub update_request: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum ManualRollbackError {
+    #[error(
+        "Unsupported resource kind '{0}', expected one of: Deployment, StatefulSet, DaemonSet, ReplicaSet"
+    )]
+    UnsupportedKind(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0} has no headwind.sh/previous-image annotation to roll back to")]
+    NoPreviousImage(String),
+    #[error("No container of {0} runs an image of {1}")]
+    NoMatchingContainer(String, String),
+    #[error("{0} already runs {1}")]
+    AlreadyRolledBack(String, String),
+    #[error("Kubernetes API error: {0}")]
+    Kube(#[from] kube::Error),
+    #[error("Rollback failed: {0:#}")]
+    Update(anyhow::Error),
+}
+
+/// Roll the container of a workload that runs the image saved in
+/// `headwind.sh/previous-image` back to that image
+pub async fn rollback_to_previous_image(
+    client: &Client,
+    resource_kind: &str,
+    namespace: &str,
+    name: &str,
+    rolled_back_by: &str,
+) -> Result<ManualRollback, ManualRollbackError> {
+    rollback_workload(client, resource_kind, namespace, name, None, rolled_back_by).await
+}
+
+/// Roll the container of a workload that runs an image of the same
+/// repository as `image` back to `image`, whether or not the workload has a
+/// `headwind.sh/previous-image` annotation
+pub async fn rollback_to_image(
+    client: &Client,
+    resource_kind: &str,
+    namespace: &str,
+    name: &str,
+    image: &str,
+    rolled_back_by: &str,
+) -> Result<ManualRollback, ManualRollbackError> {
+    rollback_workload(
+        client,
+        resource_kind,
+        namespace,
+        name,
+        Some(image),
+        rolled_back_by,
+    )
+    .await
+}
+
+/// Roll back to `image`, or to the annotated previous image when None
+async fn rollback_workload(
+    client: &Client,
+    resource_kind: &str,
+    namespace: &str,
+    name: &str,
+    image: Option<&str>,
+    rolled_back_by: &str,
+) -> Result<ManualRollback, ManualRollbackError> {
+    let (annotations, pod_spec, object_ref) = match resource_kind {
+        "Deployment" => {
+            let deployment = get::<Deployment>(client, namespace, name).await?;
+            let pod_spec = deployment
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.spec.clone());
+            (
+                deployment.annotations().clone(),
+                pod_spec,
+                deployment.object_ref(&()),
+            )
+        },
+        "StatefulSet" => {
+            let statefulset = get::<StatefulSet>(client, namespace, name).await?;
+            let pod_spec = statefulset
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.spec.clone());
+            (
+                statefulset.annotations().clone(),
+                pod_spec,
+                statefulset.object_ref(&()),
+            )
+        },
+        "DaemonSet" => {
+            let daemonset = get::<DaemonSet>(client, namespace, name).await?;
+            let pod_spec = daemonset
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.spec.clone());
+            (
+                daemonset.annotations().clone(),
+                pod_spec,
+                daemonset.object_ref(&()),
+            )
+        },
+        "ReplicaSet" => {
+            let replicaset = get::<ReplicaSet>(client, namespace, name).await?;
+            let pod_spec = replicaset
+                .spec
+                .as_ref()
+                .and_then(|s| s.template.as_ref())
+                .and_then(|t| t.spec.clone());
+            (
+                replicaset.annotations().clone(),
+                pod_spec,
+                replicaset.object_ref(&()),
+            )
+        },
+        other => return Err(ManualRollbackError::UnsupportedKind(other.to_string())),
+    };
+
+    let resource = format!("{} {}/{}", resource_kind, namespace, name);
+    let previous_image = match image {
+        Some(image) => image.to_string(),
+        None => annotations
+            .get(annotations::PREVIOUS_IMAGE)
+            .filter(|image| !image.is_empty())
+            .ok_or_else(|| ManualRollbackError::NoPreviousImage(resource.clone()))?
+            .clone(),
+    };
+    let container =
+        select_rollback_container(pod_spec.as_ref(), &previous_image).ok_or_else(|| {
+            ManualRollbackError::NoMatchingContainer(resource.clone(), previous_image.clone())
+        })?;
+    let current_image = container.image.clone().unwrap_or_default();
+    if current_image == previous_image {
+        return Err(ManualRollbackError::AlreadyRolledBack(
+            resource,
+            previous_image,
+        ));
+    }
+
+    info!(
+        "Rolling back {} container {} from {} to {} for {}",
+        resource, container.name, current_image, previous_image, rolled_back_by
+    );
+
+    let deployment_info = DeploymentInfo {
+        name: name.to_string(),
+        namespace: namespace.to_string(),
+        current_image: current_image.clone(),
+        new_image: previous_image.clone(),
+        container: Some(container.name.clone()),
+        resource_kind: Some(resource_kind.to_string()),
+    };
+
+    crate::metrics::ROLLBACKS_TOTAL
+        .with_label_values(&[RollbackReason::Manual.as_str()])
+        .inc();
+    crate::metrics::ROLLBACKS_MANUAL.inc();
+
+    if let Err(e) = apply_previous_image(
+        client,
+        resource_kind,
+        namespace,
+        name,
+        &container.name,
+        &previous_image,
+        rolled_back_by,
+    )
+    .await
+    {
+        crate::metrics::ROLLBACKS_FAILED.inc();
+        notifications::notify_rollback_failed(deployment_info, format!("{:#}", e));
+        return Err(ManualRollbackError::Update(e));
+    }
+
+    let mut rollback = ManualRollback {
+        resource_kind: resource_kind.to_string(),
+        namespace: namespace.to_string(),
+        name: name.to_string(),
+        container: container.name.clone(),
+        rolled_back_from: current_image,
+        rolled_back_to: previous_image,
+        rolled_back_by: rolled_back_by.to_string(),
+        update_request: None,
+    };
+
+    // The rollback has happened; failing to document it is only logged
+    match record_rollback_request(client, &rollback).await {
+        Ok(update_request) => rollback.update_request = Some(update_request),
+        Err(e) => warn!(
+            "Failed to create UpdateRequest documenting the rollback of {}: {}",
+            resource, e
+        ),
+    }
+    publish_manual_rollback(client, &object_ref, &rollback).await;
+    notifications::notify_rollback_completed(deployment_info);
+
+    Ok(rollback)
+}
+
+/// The container running an image from the same repository as
+/// `previous_image`, or the only container
+fn select_rollback_container<'a>(
+    pod_spec: Option<&'a PodSpec>,
+    previous_image: &str,
+) -> Option<&'a Container> {
+    let containers = &pod_spec?.containers;
+    let repository = image_repository(previous_image);
+    containers
+        .iter()
+        .find(|c| c.image.as_deref().map(image_repository) == Some(repository.clone()))
+        .or(match containers.as_slice() {
+            [only] => Some(only),
+            _ => None,
+        })
+}
+
+/// Registry and repository of an image, without tag or digest
+fn image_repository(image: &str) -> String {
+    match Reference::try_from(image) {
+        Ok(reference) => format!("{}/{}", reference.registry(), reference.repository()),
+        Err(_) => split_tag(image)
+            .map_or(image, |(repository, _)| repository)
+            .to_string(),
+    }
+}
+
+async fn apply_previous_image(
+    client: &Client,
+    resource_kind: &str,
+    namespace: &str,
+    name: &str,
+    container_name: &str,
+    previous_image: &str,
+    rolled_back_by: &str,
+) -> anyhow::Result<()> {
+    if resource_kind == "Deployment" {
+        return update_deployment_image_with_tracking(
+            client.clone(),
+            namespace,
+            name,
+            container_name,
+            previous_image,
+            None,
+            Some(rolled_back_by.to_string()),
+        )
+        .await;
+    }
+
+    let (image, version) = split_tag(previous_image)
+        .ok_or_else(|| anyhow::anyhow!("Previous image {} has no tag", previous_image))?;
+    let approver = Some(rolled_back_by);
+    match resource_kind {
+        "StatefulSet" => {
+            update_statefulset_image_with_tracking(
+                client, namespace, name, image, version, approver,
+            )
+            .await
+        },
+        "DaemonSet" => {
+            update_daemonset_image_with_tracking(client, namespace, name, image, version, approver)
+                .await
+        },
+        "ReplicaSet" => {
+            update_replicaset_image_with_tracking(client, namespace, name, image, version, approver)
+                .await
+        },
+        other => Err(anyhow::anyhow!("Unsupported resource kind '{}'", other)),
+    }
+}
+
+/// Create an UpdateRequest in the `RolledBack` phase for the audit trail
+async fn record_rollback_request(
+    client: &Client,
+    rollback: &ManualRollback,
+) -> Result<String, kube::Error> {
+    let now = Utc::now();
+    let request_name = format!("{}-rollback-{}", rollback.name, now.format("%Y%m%d%H%M%S"));
+    let reason = format!(
+        "Manual rollback from {} to {} by {}",
+        rollback.rolled_back_from, rollback.rolled_back_to, rollback.rolled_back_by
+    );
+
+    let update_request = UpdateRequest {
+        metadata: kube::api::ObjectMeta {
+            name: Some(request_name.clone()),
+            namespace: Some(rollback.namespace.clone()),
+            ..Default::default()
+        },
+        spec: UpdateRequestSpec {
+            target_ref: TargetRef {
+                api_version: "apps/v1".to_string(),
+                kind: rollback.resource_kind.clone(),
+                name: rollback.name.clone(),
+                namespace: rollback.namespace.clone(),
+            },
+            update_type: UpdateType::Image,
+            container_name: Some(rollback.container.clone()),
+            container_kind: Default::default(),
+            current_image: rollback.rolled_back_from.clone(),
+            new_image: rollback.rolled_back_to.clone(),
+            policy: UpdatePolicyType::None,
+            reason: Some(reason.clone()),
+            require_approval: false,
+            expires_at: None,
+            scheduled_at: None,
+            priority: None,
+            updates: Vec::new(),
+            discussion_thread: Vec::new(),
+            sla_deadline: None,
+            impact: None,
+        },
+        status: None,
+    };
+
+    let api: Api<UpdateRequest> = Api::namespaced(client.clone(), &rollback.namespace);
+    api.create(&PostParams::default(), &update_request).await?;
+
+    let status = UpdateRequestStatus {
+        phase: UpdatePhase::RolledBack,
+        approved_by: Some(rollback.rolled_back_by.clone()),
+        approved_at: Some(now),
+        message: Some(reason),
+        last_updated: Some(now),
+        rollback_history: vec![RollbackRecord {
+            triggered_at: now,
+            reason: "Manual rollback".to_string(),
+            rolled_back_to: rollback.rolled_back_to.clone(),
+            triggered_by: rollback.rolled_back_by.clone(),
+        }],
+        ..Default::default()
+    };
+    api.patch_status(
+        &request_name,
+        &PatchParams::default(),
+        &Patch::Merge(&serde_json::json!({ "status": status })),
+    )
+    .await?;
+
+    Ok(request_name)
+}
+
+/// Emit a `ManualRollback` event on the rolled back workload
+async fn publish_manual_rollback(
+    client: &Client,
+    object_ref: &ObjectReference,
+    rollback: &ManualRollback,
+) {
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    let event = Event {
+        type_: EventType::Normal,
+        reason: MANUAL_ROLLBACK_REASON.to_string(),
+        note: Some(format!(
+            "Container {} rolled back from {} to {} by {}",
+            rollback.container,
+            rollback.rolled_back_from,
+            rollback.rolled_back_to,
+            rollback.rolled_back_by
+        )),
+        action: "Rollback".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(&event, object_ref).await {
+        warn!(
+            "Failed to publish {} event for {}/{}: {}",
+            MANUAL_ROLLBACK_REASON, rollback.namespace, rollback.name, e
+        );
+    }
+}
+
+async fn get<K>(client: &Client, namespace: &str, name: &str) -> Result<K, ManualRollbackError>
+where
+    K: Resource<Scope = k8s_openapi::NamespaceResourceScope>
+        + Clone
+        + serde::de::DeserializeOwned
+        + fmt::Debug,
+    <K as Resource>::DynamicType: Default,
+{
+    let api: Api<K> = Api::namespaced(client.clone(), namespace);
+    match api.get_opt(name).await? {
+        Some(resource) => Ok(resource),
+        None => Err(ManualRollbackError::NotFound(format!(
+            "{} {}/{} not found",
+            K::kind(&Default::default()),
+            namespace,
+            name
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pod_spec(containers: serde_json::Value) -> PodSpec {
+        serde_json::from_value(json!({ "containers": containers })).unwrap()
+    }
+
+    #[test]
+    fn test_select_rollback_container() {
+        let spec = pod_spec(json!([
+            { "name": "proxy", "image": "envoy:1.30.0" },
+            { "name": "app", "image": "registry.io/app:1.2.0" }
+        ]));
+        let selected = |image| select_rollback_container(Some(&spec), image).map(|c| &c.name);
+
+        assert_eq!(selected("registry.io/app:1.1.0").unwrap(), "app");
+        assert_eq!(selected("docker.io/library/envoy:1.29.0").unwrap(), "proxy");
+        assert_eq!(selected("other.io/app:1.1.0"), None);
+
+        // A single container is rolled back even if its repository changed
+        let spec = pod_spec(json!([{ "name": "app", "image": "registry.io/app:1.2.0" }]));
+        assert_eq!(
+            select_rollback_container(Some(&spec), "mirror.io/app:1.1.0")
+                .unwrap()
+                .name,
+            "app"
+        );
+        assert!(select_rollback_container(None, "registry.io/app:1.1.0").is_none());
+    }
+
+    #[test]
+    fn test_rollback_kinds_are_exhaustive() {
+        let err = ManualRollbackError::UnsupportedKind("CronJob".to_string()).to_string();
+        for kind in ROLLBACK_KINDS {
+            assert!(err.contains(kind), "{} missing from '{}'", kind, err);
+        }
+    }
+}
@@ -5,10 +5,14 @@
 // 2. Allowing manual rollback to previous image versions
 // 3. Creating UpdateRequests for rollback operations
 
+pub mod manual;
+
+use crate::models::crd::{RollbackRecord, UpdateRequest};
 use anyhow::{Context, Result, anyhow};
 use chrono::{DateTime, Utc};
 use k8s_openapi::api::apps::v1::Deployment;
 use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Patch, PatchParams};
 use kube::{Api, Client};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -256,6 +260,122 @@ pub enum HealthStatus {
     Timeout,
 }
 
+/// Why a rollback happened, used as the `reason` label of `headwind_rollbacks_total`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollbackReason {
+    /// Health check timed out before the deployment became healthy
+    Timeout,
+    /// Containers crash looping or restarting repeatedly
+    CrashLoop,
+    /// Deployment failed to become available for any other reason
+    Unavailable,
+    /// Rollback requested through the API
+    Manual,
+}
+
+impl RollbackReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RollbackReason::Timeout => "timeout",
+            RollbackReason::CrashLoop => "crash_loop",
+            RollbackReason::Unavailable => "unavailable",
+            RollbackReason::Manual => "manual",
+        }
+    }
+
+    /// Classify an unhealthy health check result, or `None` if it is not a failure
+    pub fn from_health_status(status: &HealthStatus) -> Option<Self> {
+        match status {
+            HealthStatus::Failed(message)
+                if message.contains("CrashLoopBackOff") || message.contains("restart count") =>
+            {
+                Some(RollbackReason::CrashLoop)
+            },
+            HealthStatus::Failed(_) => Some(RollbackReason::Unavailable),
+            HealthStatus::Timeout => Some(RollbackReason::Timeout),
+            HealthStatus::Healthy | HealthStatus::Progressing => None,
+        }
+    }
+}
+
+/// Attempts at appending to the rollback history before giving up on conflicts
+const RECORD_ROLLBACK_ATTEMPTS: usize = 3;
+
+/// Append `record` to the rollback history of an UpdateRequest's status.
+///
+/// The whole history is written back together with the resourceVersion it was
+/// read at, so a concurrent status change makes the patch fail with a conflict
+/// and the append is retried instead of replacing a newer history.
+pub async fn record_rollback(
+    client: Client,
+    namespace: &str,
+    update_request_name: &str,
+    record: RollbackRecord,
+) -> Result<()> {
+    let api: Api<UpdateRequest> = Api::namespaced(client, namespace);
+
+    for attempt in 1..=RECORD_ROLLBACK_ATTEMPTS {
+        let update_request = api.get_status(update_request_name).await.with_context(|| {
+            format!(
+                "Failed to get UpdateRequest {}/{}",
+                namespace, update_request_name
+            )
+        })?;
+
+        let mut history = update_request
+            .status
+            .map(|status| status.rollback_history)
+            .unwrap_or_default();
+        history.push(record.clone());
+
+        let patch = serde_json::json!({
+            "metadata": {
+                "resourceVersion": update_request.metadata.resource_version,
+            },
+            "status": {
+                "rollbackHistory": history,
+            },
+        });
+
+        match api
+            .patch_status(
+                update_request_name,
+                &PatchParams::default(),
+                &Patch::Merge(&patch),
+            )
+            .await
+        {
+            Ok(_) => {
+                debug!(
+                    "Recorded rollback to {} on UpdateRequest {}/{}",
+                    record.rolled_back_to, namespace, update_request_name
+                );
+                return Ok(());
+            },
+            Err(kube::Error::Api(e)) if e.code == 409 && attempt < RECORD_ROLLBACK_ATTEMPTS => {
+                debug!(
+                    "Conflict recording rollback on UpdateRequest {}/{}, retrying",
+                    namespace, update_request_name
+                );
+            },
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Failed to record rollback on UpdateRequest {}/{}",
+                        namespace, update_request_name
+                    )
+                });
+            },
+        }
+    }
+
+    Err(anyhow!(
+        "Failed to record rollback on UpdateRequest {}/{}: too many conflicts",
+        namespace,
+        update_request_name
+    ))
+}
+
 /// Configuration for automatic rollback
 #[derive(Debug, Clone)]
 pub struct AutoRollbackConfig {
@@ -644,4 +764,32 @@ mod tests {
         assert!(json.contains("nginx:1.26.0"));
         assert!(json.contains("nginx-update"));
     }
+
+    #[test]
+    fn test_rollback_reason_from_health_status() {
+        let reason = |status: HealthStatus| RollbackReason::from_health_status(&status);
+
+        assert_eq!(reason(HealthStatus::Healthy), None);
+        assert_eq!(reason(HealthStatus::Progressing), None);
+        assert_eq!(reason(HealthStatus::Timeout), Some(RollbackReason::Timeout));
+        assert_eq!(
+            reason(HealthStatus::Failed(
+                "Container web is in CrashLoopBackOff".to_string()
+            )),
+            Some(RollbackReason::CrashLoop)
+        );
+        assert_eq!(
+            reason(HealthStatus::Failed(
+                "Container web has high restart count (7)".to_string()
+            )),
+            Some(RollbackReason::CrashLoop)
+        );
+        assert_eq!(
+            reason(HealthStatus::Failed(
+                "Deployment progress deadline exceeded".to_string()
+            )),
+            Some(RollbackReason::Unavailable)
+        );
+        assert_eq!(RollbackReason::CrashLoop.as_str(), "crash_loop");
+    }
 }
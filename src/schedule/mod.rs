@@ -0,0 +1,524 @@
+//! Cron-based update scheduling.
+//!
+//! `headwind.sh/update-schedule: "0 2 * * Mon-Fri"` restricts when updates are
+//! applied. Updates detected outside the schedule are kept in memory and applied
+//! at the next fire time. Pending updates are not persisted; after a restart they
+//! are picked up again by the next webhook or polling cycle.
+
+pub mod window;
+
+use crate::models::policy::{UpdatePolicy, annotations};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+
+/// How far ahead to search for the next fire time before giving up
+/// (covers schedules like "0 0 29 2 *" that only fire in leap years)
+const MAX_SEARCH_YEARS: i32 = 5;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A parsed five-field cron expression (minute hour day-of-month month day-of-week),
+/// evaluated in UTC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Day-of-month and day-of-week were both restricted; a day matches if either does
+    day_or: bool,
+}
+
+/// Parse a cron expression such as `0 2 * * Mon-Fri`.
+///
+/// Supports `*`, lists (`1,15`), ranges (`1-5`), steps (`*/15`, `0-30/10`),
+/// month and weekday names, and the `@hourly`, `@daily`, `@weekly`, `@monthly`
+/// and `@yearly` shortcuts.
+pub fn parse_cron_schedule(expr: &str) -> Result<CronSchedule> {
+    let expression = expr.trim();
+    let expanded = match expression {
+        "@hourly" => "0 * * * *",
+        "@daily" | "@midnight" => "0 0 * * *",
+        "@weekly" => "0 0 * * 0",
+        "@monthly" => "0 0 1 * *",
+        "@yearly" | "@annually" => "0 0 1 1 *",
+        other => other,
+    };
+
+    let fields: Vec<&str> = expanded.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err(anyhow::anyhow!(
+            "Invalid cron expression '{}': expected 5 fields, found {}",
+            expression,
+            fields.len()
+        ));
+    }
+
+    let parse = |field: &str, min: u32, max: u32, names: &[&str], name: &str| {
+        parse_field(field, min, max, names)
+            .with_context(|| format!("Invalid {} field '{}' in '{}'", name, field, expression))
+    };
+
+    let minutes = parse(fields[0], 0, 59, &[], "minute")?;
+    let hours = parse(fields[1], 0, 23, &[], "hour")?;
+    let days_of_month = parse(fields[2], 1, 31, &[], "day-of-month")?;
+    let months = parse(fields[3], 1, 12, &MONTH_NAMES, "month")?;
+    let mut days_of_week = parse(fields[4], 0, 7, &DAY_NAMES, "day-of-week")?;
+    // 7 is an alias for Sunday
+    if days_of_week & (1 << 7) != 0 {
+        days_of_week = (days_of_week & !(1 << 7)) | 1;
+    }
+
+    Ok(CronSchedule {
+        expression: expression.to_string(),
+        minutes,
+        hours,
+        days_of_month,
+        months,
+        days_of_week,
+        day_or: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+    })
+}
+
+/// Parse one cron field into a bitmask of allowed values
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let mut mask = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().context("invalid step")?;
+                if step == 0 {
+                    return Err(anyhow::anyhow!("step must be greater than zero"));
+                }
+                (range, step)
+            },
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, min, names)?,
+                parse_value(end, min, names)?,
+            )
+        } else {
+            let value = parse_value(range, min, names)?;
+            // "5/15" means "5-max/15"
+            (value, if part.contains('/') { max } else { value })
+        };
+
+        if start < min || end > max || start > end {
+            return Err(anyhow::anyhow!(
+                "{}-{} is outside the allowed range {}-{}",
+                start,
+                end,
+                min,
+                max
+            ));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, names: &[&str]) -> Result<u32> {
+    if let Ok(number) = value.parse() {
+        return Ok(number);
+    }
+
+    let lower = value.to_lowercase();
+    names
+        .iter()
+        .position(|name| *name == lower)
+        .map(|index| index as u32 + min)
+        .ok_or_else(|| anyhow::anyhow!("invalid value '{}'", value))
+}
+
+fn has(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+impl CronSchedule {
+    /// The expression this schedule was parsed from
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let dom = has(self.days_of_month, date.day());
+        let dow = has(self.days_of_week, date.weekday().num_days_from_sunday());
+        if self.day_or { dom || dow } else { dom && dow }
+    }
+
+    /// Whether the schedule fires during the minute containing `time`
+    pub fn matches(&self, time: DateTime<Utc>) -> bool {
+        has(self.months, time.month())
+            && self.day_matches(time.date_naive())
+            && has(self.hours, time.hour())
+            && has(self.minutes, time.minute())
+    }
+
+    /// The first fire time strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = start.year() + MAX_SEARCH_YEARS;
+        let mut date = start.date_naive();
+        let (mut hour, mut minute) = (start.hour(), start.minute());
+
+        while date.year() <= limit {
+            if !has(self.months, date.month()) {
+                date = first_of_next_month(date)?;
+                (hour, minute) = (0, 0);
+                continue;
+            }
+            if !self.day_matches(date) {
+                date = date.succ_opt()?;
+                (hour, minute) = (0, 0);
+                continue;
+            }
+
+            let next_hour = (hour..24).find(|h| has(self.hours, *h));
+            if let Some(h) = next_hour {
+                let from = if h == hour { minute } else { 0 };
+                if let Some(m) = (from..60).find(|m| has(self.minutes, *m)) {
+                    return Utc
+                        .from_local_datetime(&date.and_hms_opt(h, m, 0)?)
+                        .single();
+                }
+                // No minute left in this hour, try the next one
+                if h < 23 {
+                    (hour, minute) = (h + 1, 0);
+                    continue;
+                }
+            }
+
+            date = date.succ_opt()?;
+            (hour, minute) = (0, 0);
+        }
+
+        None
+    }
+
+    /// Upcoming fire times after now
+    pub fn upcoming(&self) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        std::iter::successors(self.next_after(Utc::now()), move |t| self.next_after(*t))
+    }
+}
+
+fn first_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    if date.month() == 12 {
+        NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1)
+    }
+}
+
+/// Read `headwind.sh/update-schedule` from resource annotations.
+/// Invalid expressions are logged and ignored.
+pub fn schedule_from_annotations(annotations: &BTreeMap<String, String>) -> Option<CronSchedule> {
+    let expr = annotations.get(annotations::UPDATE_SCHEDULE)?;
+    match parse_cron_schedule(expr) {
+        Ok(schedule) => Some(schedule),
+        Err(e) => {
+            warn!("Ignoring {}: {:#}", annotations::UPDATE_SCHEDULE, e);
+            None
+        },
+    }
+}
+
+/// When an update for a resource with these annotations has to wait for its
+/// schedule, returns the schedule expression and the time to apply it.
+/// `headwind.sh/update-window` takes precedence over
+/// `headwind.sh/update-schedule`. Returns None if the resource has neither or
+/// its window is open in the current minute.
+pub fn deferred_until(
+    annotations: &BTreeMap<String, String>,
+    now: DateTime<Utc>,
+) -> Option<(String, DateTime<Utc>)> {
+    if let Some(window) = window::window_from_annotations(annotations) {
+        if window.is_open(now) {
+            return None;
+        }
+        let next = window.next_open(now)?;
+        return Some((window.expression().to_string(), next));
+    }
+
+    let schedule = schedule_from_annotations(annotations)?;
+    if schedule.matches(now) {
+        return None;
+    }
+    let next = schedule.next_after(now)?;
+    Some((schedule.expression().to_string(), next))
+}
+
+/// Identifies the container a pending update applies to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceKey {
+    pub kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub container: Option<String>,
+}
+
+/// An update that passed policy checks and is waiting for its schedule
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingUpdate {
+    pub kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub container: Option<String>,
+    pub current_image: String,
+    pub new_image: String,
+    pub policy: UpdatePolicy,
+    pub schedule: String,
+    pub scheduled_for: DateTime<Utc>,
+    pub queued_at: DateTime<Utc>,
+}
+
+impl PendingUpdate {
+    pub fn key(&self) -> ResourceKey {
+        ResourceKey {
+            kind: self.kind.clone(),
+            namespace: self.namespace.clone(),
+            name: self.name.clone(),
+            container: self.container.clone(),
+        }
+    }
+}
+
+pub type PendingUpdateStore = Arc<RwLock<HashMap<ResourceKey, PendingUpdate>>>;
+
+static PENDING_UPDATES: once_cell::sync::Lazy<PendingUpdateStore> =
+    once_cell::sync::Lazy::new(|| Arc::new(RwLock::new(HashMap::new())));
+
+/// The global store of updates waiting for their schedule
+pub fn pending_updates() -> PendingUpdateStore {
+    PENDING_UPDATES.clone()
+}
+
+/// All pending updates, soonest first
+pub async fn list_pending_updates() -> Vec<PendingUpdate> {
+    let mut updates: Vec<PendingUpdate> = PENDING_UPDATES.read().await.values().cloned().collect();
+    updates.sort_by_key(|u| u.scheduled_for);
+    updates
+}
+
+/// Store `update` and run `apply` at its scheduled time.
+///
+/// A newer update for the same container replaces the stored one; the timer of
+/// the replaced update then finds a different entry and does nothing.
+pub async fn defer_update<F, Fut>(update: PendingUpdate, apply: F)
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    let key = update.key();
+    info!(
+        "Deferring update of {} {}/{} to {} until {} (schedule '{}')",
+        update.kind,
+        update.namespace,
+        update.name,
+        update.new_image,
+        update.scheduled_for,
+        update.schedule
+    );
+
+    let scheduled_for = update.scheduled_for;
+    let new_image = update.new_image.clone();
+    PENDING_UPDATES.write().await.insert(key.clone(), update);
+
+    tokio::spawn(async move {
+        let wait = (scheduled_for - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        {
+            let mut store = PENDING_UPDATES.write().await;
+            match store.get(&key) {
+                Some(pending)
+                    if pending.new_image == new_image && pending.scheduled_for == scheduled_for =>
+                {
+                    store.remove(&key);
+                },
+                _ => return,
+            }
+        }
+
+        info!(
+            "Applying scheduled update of {} {}/{} to {}",
+            key.kind, key.namespace, key.name, new_image
+        );
+        if let Err(e) = apply().await {
+            error!(
+                "Scheduled update of {} {}/{} failed: {}",
+                key.kind, key.namespace, key.name, e
+            );
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_weekday_schedule() {
+        let schedule = parse_cron_schedule("0 2 * * Mon-Fri").unwrap();
+        assert_eq!(schedule.expression(), "0 2 * * Mon-Fri");
+
+        // 2025-01-06 is a Monday
+        assert!(schedule.matches(utc(2025, 1, 6, 2, 0)));
+        assert!(!schedule.matches(utc(2025, 1, 6, 2, 1)));
+        // Saturday
+        assert!(!schedule.matches(utc(2025, 1, 11, 2, 0)));
+    }
+
+    #[test]
+    fn test_next_after_skips_weekend() {
+        let schedule = parse_cron_schedule("0 2 * * Mon-Fri").unwrap();
+
+        // Friday afternoon -> Monday 02:00
+        assert_eq!(
+            schedule.next_after(utc(2025, 1, 10, 15, 30)),
+            Some(utc(2025, 1, 13, 2, 0))
+        );
+        // Tuesday 01:59 -> Tuesday 02:00
+        assert_eq!(
+            schedule.next_after(utc(2025, 1, 7, 1, 59)),
+            Some(utc(2025, 1, 7, 2, 0))
+        );
+        // Exactly at a fire time -> the next one
+        assert_eq!(
+            schedule.next_after(utc(2025, 1, 7, 2, 0)),
+            Some(utc(2025, 1, 8, 2, 0))
+        );
+    }
+
+    #[test]
+    fn test_steps_lists_and_names() {
+        let schedule = parse_cron_schedule("*/15 9-17 * jan,jul 1,3,5").unwrap();
+        assert_eq!(
+            schedule.next_after(utc(2025, 1, 6, 9, 50)),
+            Some(utc(2025, 1, 6, 10, 0))
+        );
+        // February is excluded, so the next fire is in July
+        assert_eq!(
+            schedule.next_after(utc(2025, 1, 31, 17, 45)),
+            Some(utc(2025, 7, 2, 9, 0))
+        );
+    }
+
+    #[test]
+    fn test_day_of_month_or_day_of_week() {
+        // Fires on the 1st of the month and on every Sunday (7 = Sunday)
+        let schedule = parse_cron_schedule("30 4 1 * 7").unwrap();
+        // 2025-01-05 is a Sunday
+        assert!(schedule.matches(utc(2025, 1, 5, 4, 30)));
+        assert!(schedule.matches(utc(2025, 1, 1, 4, 30)));
+        assert!(!schedule.matches(utc(2025, 1, 2, 4, 30)));
+    }
+
+    #[test]
+    fn test_shortcuts() {
+        let schedule = parse_cron_schedule("@daily").unwrap();
+        assert_eq!(
+            schedule.next_after(utc(2025, 12, 31, 12, 0)),
+            Some(utc(2026, 1, 1, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_leap_day_schedule() {
+        let schedule = parse_cron_schedule("0 0 29 2 *").unwrap();
+        assert_eq!(
+            schedule.next_after(utc(2025, 3, 1, 0, 0)),
+            Some(utc(2028, 2, 29, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        assert!(parse_cron_schedule("0 2 * *").is_err());
+        assert!(parse_cron_schedule("60 2 * * *").is_err());
+        assert!(parse_cron_schedule("0 2 * * Funday").is_err());
+        assert!(parse_cron_schedule("*/0 * * * *").is_err());
+        assert!(parse_cron_schedule("0 5-2 * * *").is_err());
+    }
+
+    #[test]
+    fn test_deferred_until() {
+        let annotations = BTreeMap::from([(
+            annotations::UPDATE_SCHEDULE.to_string(),
+            "0 2 * * *".to_string(),
+        )]);
+
+        assert_eq!(
+            deferred_until(&annotations, utc(2025, 1, 6, 12, 0)).map(|(_, t)| t),
+            Some(utc(2025, 1, 7, 2, 0))
+        );
+        // Inside the fire minute the update is applied right away
+        assert!(deferred_until(&annotations, utc(2025, 1, 6, 2, 0)).is_none());
+        // No schedule annotation
+        assert!(deferred_until(&BTreeMap::new(), utc(2025, 1, 6, 12, 0)).is_none());
+
+        // An update window replaces the schedule
+        let mut annotations = annotations;
+        annotations.insert(
+            annotations::UPDATE_WINDOW.to_string(),
+            "0 10 * * *[UTC]".to_string(),
+        );
+        assert_eq!(
+            deferred_until(&annotations, utc(2025, 1, 6, 2, 0)),
+            Some(("0 10 * * *[UTC]".to_string(), utc(2025, 1, 6, 10, 0)))
+        );
+        assert!(deferred_until(&annotations, utc(2025, 1, 6, 10, 0)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_newer_update_replaces_pending() {
+        let update = |new_image: &str| PendingUpdate {
+            kind: "Deployment".to_string(),
+            namespace: "schedule-test".to_string(),
+            name: "web".to_string(),
+            container: Some("app".to_string()),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: new_image.to_string(),
+            policy: UpdatePolicy::Minor,
+            schedule: "0 2 * * *".to_string(),
+            scheduled_for: Utc::now() + Duration::hours(1),
+            queued_at: Utc::now(),
+        };
+
+        defer_update(update("nginx:1.26.0"), || async { Ok(()) }).await;
+        defer_update(update("nginx:1.27.0"), || async { Ok(()) }).await;
+
+        let pending: Vec<_> = list_pending_updates()
+            .await
+            .into_iter()
+            .filter(|u| u.namespace == "schedule-test")
+            .collect();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].new_image, "nginx:1.27.0");
+    }
+}
@@ -0,0 +1,611 @@
+//! `headwind.sh/update-window`: cron schedules in a time zone.
+//!
+//! `0 2 * * 1-5[America/New_York]` opens at 02:00 New York time on weekdays.
+//! Several windows are separated by `;`, each with its own zone, UTC if none
+//! is given: `0 2 * * 1-5[Europe/Berlin];0 10 * * 6-7[Europe/Berlin]`. Like
+//! `headwind.sh/update-schedule`, a window is open during the minutes its
+//! expression matches, here on the local wall clock.
+//!
+//! Zones come from the system time zone database (`$ZONEINFO`, or
+//! `/usr/share/zoneinfo`). The POSIX TZ rule at the end of each zone file
+//! gives the zone's current offsets and daylight saving time transitions;
+//! earlier rules of the zone are not applied, since windows only matter from
+//! now on. Local times skipped when the clocks go forward open the window as
+//! much later as the clocks jumped (02:30 becomes 03:30), and local times
+//! repeated when they go back open it only the first time.
+
+use super::{CronSchedule, parse_cron_schedule};
+use crate::models::policy::annotations;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Timelike, Utc};
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use tracing::warn;
+
+const DEFAULT_ZONEINFO: &str = "/usr/share/zoneinfo";
+
+/// Zones read from the time zone database, by name
+static ZONES: Lazy<Mutex<HashMap<String, TimeZoneRule>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// One or more cron windows, each in its own time zone
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateWindow {
+    expression: String,
+    windows: Vec<(CronSchedule, TimeZoneRule)>,
+}
+
+impl UpdateWindow {
+    /// Parse windows such as `0 2 * * 1-5[America/New_York];0 10 * * 6-7`
+    pub fn parse(value: &str) -> Result<Self> {
+        let windows = value
+            .split(';')
+            .map(str::trim)
+            .filter(|w| !w.is_empty())
+            .map(parse_window)
+            .collect::<Result<Vec<_>>>()?;
+        if windows.is_empty() {
+            bail!("Empty update window");
+        }
+        Ok(Self {
+            expression: value.trim().to_string(),
+            windows,
+        })
+    }
+
+    /// The annotation value this window was parsed from
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Whether a window opens during the minute containing `at`
+    pub fn is_open(&self, at: DateTime<Utc>) -> bool {
+        let Some(minute) = at.with_second(0).and_then(|t| t.with_nanosecond(0)) else {
+            return false;
+        };
+        self.windows
+            .iter()
+            .any(|(schedule, zone)| opens_at(schedule, zone, minute))
+    }
+
+    /// The first time strictly after `from` a window opens
+    pub fn next_open(&self, from: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.windows
+            .iter()
+            .filter_map(|(schedule, zone)| next_open(schedule, zone, from))
+            .min()
+    }
+}
+
+/// `<cron>` or `<cron>[<zone>]`
+fn parse_window(window: &str) -> Result<(CronSchedule, TimeZoneRule)> {
+    let (expr, zone) = match window.strip_suffix(']') {
+        Some(rest) => {
+            let (expr, zone) = rest
+                .rsplit_once('[')
+                .ok_or_else(|| anyhow!("Invalid update window '{}': unmatched ']'", window))?;
+            (expr, load_zone(zone.trim())?)
+        },
+        None => (window, TimeZoneRule::utc()),
+    };
+    Ok((parse_cron_schedule(expr)?, zone))
+}
+
+/// Whether the window opens at `minute`. Openings are walked from a few hours
+/// earlier, since a local time skipped by a clock change opens later than its
+/// wall clock time says.
+fn opens_at(schedule: &CronSchedule, zone: &TimeZoneRule, minute: DateTime<Utc>) -> bool {
+    let mut from = minute - Duration::hours(3);
+    while let Some(opens) = next_open(schedule, zone, from) {
+        if opens >= minute {
+            return opens == minute;
+        }
+        from = opens;
+    }
+    false
+}
+
+fn next_open(
+    schedule: &CronSchedule,
+    zone: &TimeZoneRule,
+    from: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    // CronSchedule works on UTC; feed it the wall clock as if it were UTC
+    let mut local = zone.local(from);
+    loop {
+        let fire = schedule
+            .next_after(Utc.from_utc_datetime(&local))?
+            .naive_utc();
+        let opens = zone
+            .from_local(fire)
+            .first()
+            .copied()
+            .unwrap_or_else(|| zone.skipped_local(fire));
+        if opens > from {
+            return Some(opens);
+        }
+        local = fire;
+    }
+}
+
+/// Read `headwind.sh/update-window` from resource annotations.
+/// Invalid windows are logged and ignored.
+pub fn window_from_annotations(annotations: &BTreeMap<String, String>) -> Option<UpdateWindow> {
+    let value = annotations.get(annotations::UPDATE_WINDOW)?;
+    match UpdateWindow::parse(value) {
+        Ok(window) => Some(window),
+        Err(e) => {
+            warn!("Ignoring {}: {:#}", annotations::UPDATE_WINDOW, e);
+            None
+        },
+    }
+}
+
+/// Offsets of a zone from a POSIX TZ rule such as `EST5EDT,M3.2.0,M11.1.0`.
+/// Offsets are seconds east of UTC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeZoneRule {
+    std_offset: i32,
+    dst: Option<DstRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DstRule {
+    offset: i32,
+    start: TransitionDate,
+    end: TransitionDate,
+}
+
+/// `Mm.w.d/time`: weekday `d` (0 = Sunday) of week `w` (5 = last) of month
+/// `m`, at `time` seconds of local time, which may exceed a day or be negative
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TransitionDate {
+    month: u32,
+    week: u32,
+    weekday: u32,
+    time: i32,
+}
+
+impl TimeZoneRule {
+    pub fn utc() -> Self {
+        Self {
+            std_offset: 0,
+            dst: None,
+        }
+    }
+
+    /// Parse a POSIX TZ rule. Only the `M` form of transition dates is
+    /// supported, which is what zone files use.
+    pub fn parse(rule: &str) -> Result<Self> {
+        let invalid = || anyhow!("Invalid TZ rule '{}'", rule);
+        let mut rest = rule;
+
+        skip_name(&mut rest).ok_or_else(invalid)?;
+        let std_offset = -parse_offset(&mut rest).ok_or_else(invalid)?;
+        if rest.is_empty() {
+            return Ok(Self {
+                std_offset,
+                dst: None,
+            });
+        }
+
+        skip_name(&mut rest).ok_or_else(invalid)?;
+        let offset = if rest.starts_with(',') {
+            std_offset + 3600
+        } else {
+            -parse_offset(&mut rest).ok_or_else(invalid)?
+        };
+        let start = rest
+            .strip_prefix(',')
+            .and_then(|r| {
+                rest = r;
+                parse_transition(&mut rest)
+            })
+            .ok_or_else(invalid)?;
+        let end = rest
+            .strip_prefix(',')
+            .and_then(|r| {
+                rest = r;
+                parse_transition(&mut rest)
+            })
+            .ok_or_else(invalid)?;
+        if !rest.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            std_offset,
+            dst: Some(DstRule { offset, start, end }),
+        })
+    }
+
+    /// Offset from UTC in effect at `at`
+    pub fn offset_at(&self, at: DateTime<Utc>) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+        let year = (at + Duration::seconds(self.std_offset.into())).year();
+        // Transitions happen at local time, DST starts in standard time and ends in DST
+        let (Some(start), Some(end)) = (
+            dst.start.instant(year, self.std_offset),
+            dst.end.instant(year, dst.offset),
+        ) else {
+            return self.std_offset;
+        };
+        let in_dst = if start < end {
+            start <= at && at < end
+        } else {
+            // Southern hemisphere: DST spans the turn of the year
+            at < end || start <= at
+        };
+        if in_dst { dst.offset } else { self.std_offset }
+    }
+
+    /// Wall clock time at `at`
+    pub fn local(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        at.naive_utc() + Duration::seconds(self.offset_at(at).into())
+    }
+
+    /// The instants the wall clock shows `local`, earliest first: none in a
+    /// gap, two when the clocks go back
+    pub fn from_local(&self, local: NaiveDateTime) -> Vec<DateTime<Utc>> {
+        let mut offsets = vec![self.std_offset];
+        offsets.extend(self.dst.as_ref().map(|d| d.offset));
+        let mut instants: Vec<DateTime<Utc>> = offsets
+            .into_iter()
+            .map(|offset| Utc.from_utc_datetime(&(local - Duration::seconds(offset.into()))))
+            .filter(|t| self.local(*t) == local)
+            .collect();
+        instants.sort();
+        instants.dedup();
+        instants
+    }
+
+    /// `local`, skipped when the clocks went forward, moved forward by the
+    /// size of the jump
+    fn skipped_local(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        let largest = self
+            .dst
+            .as_ref()
+            .map_or(self.std_offset, |d| d.offset.max(self.std_offset));
+        let well_before =
+            Utc.from_utc_datetime(&local) - Duration::seconds(largest.into()) - Duration::hours(1);
+        let offset_before = self.offset_at(well_before);
+        Utc.from_utc_datetime(&(local - Duration::seconds(offset_before.into())))
+    }
+}
+
+impl TransitionDate {
+    /// When this transition happens in `year`, for a clock at `offset`
+    fn instant(&self, year: i32, offset: i32) -> Option<DateTime<Utc>> {
+        let first = NaiveDate::from_ymd_opt(year, self.month, 1)?;
+        let first_weekday = first.weekday().num_days_from_sunday();
+        let mut day = 1 + (self.weekday + 7 - first_weekday) % 7 + (self.week - 1) * 7;
+        // Week 5 means the last such weekday of the month
+        while NaiveDate::from_ymd_opt(year, self.month, day).is_none() {
+            day -= 7;
+        }
+        let midnight = NaiveDate::from_ymd_opt(year, self.month, day)?.and_hms_opt(0, 0, 0)?;
+        let local = midnight + Duration::seconds(self.time.into());
+        Some(Utc.from_utc_datetime(&(local - Duration::seconds(offset.into()))))
+    }
+}
+
+/// Skip a zone abbreviation: `EST` or `<+0530>`
+fn skip_name(rest: &mut &str) -> Option<()> {
+    let len = match rest.strip_prefix('<') {
+        Some(quoted) => quoted.find('>')? + 2,
+        None => rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len()),
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+/// `[+-]hh[:mm[:ss]]` in seconds. Zone offsets are west of UTC in POSIX
+/// rules, so callers negate them.
+fn parse_offset(rest: &mut &str) -> Option<i32> {
+    let sign = if let Some(r) = rest.strip_prefix('-') {
+        *rest = r;
+        -1
+    } else {
+        *rest = rest.strip_prefix('+').unwrap_or(rest);
+        1
+    };
+    let len = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ':'))
+        .unwrap_or(rest.len());
+    let (time, tail) = rest.split_at(len);
+    let mut parts = time.split(':').map(|p| p.parse::<i32>().ok());
+    let hours = parts.next()??;
+    let minutes = parts.next().unwrap_or(Some(0))?;
+    let seconds = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() || hours > 167 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    *rest = tail;
+    Some(sign * (hours * 3600 + minutes * 60 + seconds))
+}
+
+/// `Mm.w.d[/time]`
+fn parse_transition(rest: &mut &str) -> Option<TransitionDate> {
+    let spec = rest.strip_prefix('M')?;
+    let len = spec.find([',', '/']).unwrap_or(spec.len());
+    let mut fields = spec[..len].split('.').map(|f| f.parse::<u32>().ok());
+    let (month, week, weekday) = (fields.next()??, fields.next()??, fields.next()??);
+    if fields.next().is_some()
+        || !(1..=12).contains(&month)
+        || !(1..=5).contains(&week)
+        || weekday > 6
+    {
+        return None;
+    }
+    *rest = &spec[len..];
+
+    let time = match rest.strip_prefix('/') {
+        Some(r) => {
+            *rest = r;
+            parse_offset(rest)?
+        },
+        None => 2 * 3600,
+    };
+    Some(TransitionDate {
+        month,
+        week,
+        weekday,
+        time,
+    })
+}
+
+/// The TZ rule of the zone `name`, such as `America/New_York`
+fn load_zone(name: &str) -> Result<TimeZoneRule> {
+    if matches!(name, "UTC" | "Etc/UTC" | "GMT" | "Etc/GMT") {
+        return Ok(TimeZoneRule::utc());
+    }
+    let valid = !name.is_empty()
+        && !name.starts_with('/')
+        && name.split('/').all(|part| part != ".." && part != ".")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'));
+    if !valid {
+        bail!("Invalid time zone '{}'", name);
+    }
+
+    let mut zones = ZONES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(zone) = zones.get(name) {
+        return Ok(zone.clone());
+    }
+    let dir = std::env::var("ZONEINFO").unwrap_or_else(|_| DEFAULT_ZONEINFO.to_string());
+    let path = std::path::Path::new(&dir).join(name);
+    let data = std::fs::read(&path).with_context(|| format!("Unknown time zone '{}'", name))?;
+    let zone = TimeZoneRule::parse(tzif_footer(&data).context(format!("Time zone '{}'", name))?)?;
+    zones.insert(name.to_string(), zone.clone());
+    Ok(zone)
+}
+
+/// The POSIX TZ rule at the end of a version 2+ zone file
+fn tzif_footer(data: &[u8]) -> Result<&str> {
+    if !data.starts_with(b"TZif") || data.get(4).is_none_or(|v| *v < b'2') {
+        bail!("not a version 2 or later TZif file");
+    }
+    let body = data
+        .strip_suffix(b"\n")
+        .ok_or_else(|| anyhow!("missing TZ rule"))?;
+    let start = body
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .ok_or_else(|| anyhow!("missing TZ rule"))?;
+    let footer = std::str::from_utf8(&body[start + 1..])?;
+    if footer.is_empty() {
+        bail!("empty TZ rule");
+    }
+    Ok(footer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn new_york() -> TimeZoneRule {
+        TimeZoneRule::parse("EST5EDT,M3.2.0,M11.1.0").unwrap()
+    }
+
+    fn window(expr: &str, zone: TimeZoneRule) -> UpdateWindow {
+        UpdateWindow {
+            expression: expr.to_string(),
+            windows: vec![(parse_cron_schedule(expr).unwrap(), zone)],
+        }
+    }
+
+    #[test]
+    fn test_parse_tz_rules() {
+        let zone = new_york();
+        assert_eq!(zone.std_offset, -5 * 3600);
+        let dst = zone.dst.as_ref().unwrap();
+        assert_eq!(dst.offset, -4 * 3600);
+        assert_eq!(
+            dst.start,
+            TransitionDate {
+                month: 3,
+                week: 2,
+                weekday: 0,
+                time: 7200
+            }
+        );
+
+        let india = TimeZoneRule::parse("IST-5:30").unwrap();
+        assert_eq!(india.std_offset, 5 * 3600 + 1800);
+        assert!(india.dst.is_none());
+
+        let quoted = TimeZoneRule::parse("<+0545>-5:45").unwrap();
+        assert_eq!(quoted.std_offset, 5 * 3600 + 45 * 60);
+
+        let sydney = TimeZoneRule::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(sydney.dst.as_ref().unwrap().end.time, 3 * 3600);
+
+        let greenland = TimeZoneRule::parse("<-02>2<-01>,M3.5.0/-1,M10.5.0/0").unwrap();
+        assert_eq!(greenland.dst.as_ref().unwrap().start.time, -3600);
+
+        for invalid in [
+            "",
+            "E5",
+            "EST",
+            "EST5EDT,M3.2.0",
+            "EST5EDT,J60,J300",
+            "EST5x",
+        ] {
+            assert!(TimeZoneRule::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_dst_offsets() {
+        let zone = new_york();
+        // 2025: DST from 2025-03-09 07:00 UTC to 2025-11-02 06:00 UTC
+        assert_eq!(zone.offset_at(utc(2025, 3, 9, 6, 59)), -5 * 3600);
+        assert_eq!(zone.offset_at(utc(2025, 3, 9, 7, 0)), -4 * 3600);
+        assert_eq!(zone.offset_at(utc(2025, 11, 2, 5, 59)), -4 * 3600);
+        assert_eq!(zone.offset_at(utc(2025, 11, 2, 6, 0)), -5 * 3600);
+
+        let sydney = TimeZoneRule::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(sydney.offset_at(utc(2025, 1, 15, 0, 0)), 11 * 3600);
+        assert_eq!(sydney.offset_at(utc(2025, 4, 5, 15, 59)), 11 * 3600);
+        assert_eq!(sydney.offset_at(utc(2025, 4, 5, 16, 0)), 10 * 3600);
+        assert_eq!(sydney.offset_at(utc(2025, 10, 4, 16, 0)), 11 * 3600);
+    }
+
+    #[test]
+    fn test_next_open_in_time_zone() {
+        let window = window("0 2 * * 1-5", new_york());
+        // Friday 2025-01-10 02:00 EST is 07:00 UTC
+        assert_eq!(
+            window.next_open(utc(2025, 1, 10, 0, 0)),
+            Some(utc(2025, 1, 10, 7, 0))
+        );
+        // After Friday's window the next one is on Monday
+        assert_eq!(
+            window.next_open(utc(2025, 1, 10, 7, 0)),
+            Some(utc(2025, 1, 13, 7, 0))
+        );
+        // In summer 02:00 EDT is 06:00 UTC
+        assert_eq!(
+            window.next_open(utc(2025, 7, 1, 0, 0)),
+            Some(utc(2025, 7, 1, 6, 0))
+        );
+        assert!(window.is_open(utc(2025, 7, 1, 6, 0)));
+        assert!(!window.is_open(utc(2025, 7, 1, 7, 0)));
+    }
+
+    #[test]
+    fn test_skipped_local_time_opens_after_the_jump() {
+        // 2025-03-09 02:30 doesn't exist in New York; the clocks jump from
+        // 02:00 EST to 03:00 EDT, so the window opens at 03:30 EDT
+        let window = window("30 2 * * *", new_york());
+        assert_eq!(
+            window.next_open(utc(2025, 3, 9, 5, 0)),
+            Some(utc(2025, 3, 9, 7, 30))
+        );
+        assert!(window.is_open(utc(2025, 3, 9, 7, 30)));
+        // The next day is back to normal, 02:30 EDT
+        assert_eq!(
+            window.next_open(utc(2025, 3, 9, 7, 30)),
+            Some(utc(2025, 3, 10, 6, 30))
+        );
+    }
+
+    #[test]
+    fn test_repeated_local_time_opens_once() {
+        // 2025-11-02 01:30 happens at 05:30 UTC (EDT) and 06:30 UTC (EST)
+        let window = window("30 1 * * *", new_york());
+        assert_eq!(
+            window.next_open(utc(2025, 11, 2, 4, 0)),
+            Some(utc(2025, 11, 2, 5, 30))
+        );
+        assert_eq!(
+            window.next_open(utc(2025, 11, 2, 5, 30)),
+            Some(utc(2025, 11, 3, 6, 30))
+        );
+        assert!(window.is_open(utc(2025, 11, 2, 5, 30)));
+        assert!(!window.is_open(utc(2025, 11, 2, 6, 30)));
+    }
+
+    #[test]
+    fn test_leap_second() {
+        let window = window("59 23 * * *", TimeZoneRule::utc());
+        // chrono represents 23:59:60 as 23:59:59 with over a second of nanoseconds
+        let leap = NaiveDate::from_ymd_opt(2016, 12, 31)
+            .unwrap()
+            .and_hms_milli_opt(23, 59, 59, 1500)
+            .unwrap()
+            .and_utc();
+        assert!(window.is_open(leap));
+        assert_eq!(window.next_open(leap), Some(utc(2017, 1, 1, 23, 59)));
+        assert_eq!(
+            window.next_open(utc(2016, 12, 31, 23, 58)),
+            Some(utc(2016, 12, 31, 23, 59))
+        );
+    }
+
+    #[test]
+    fn test_multiple_windows() {
+        let window = UpdateWindow::parse("0 2 * * 1-5; 0 10 * * 6-7[UTC]").unwrap();
+        assert_eq!(window.expression(), "0 2 * * 1-5; 0 10 * * 6-7[UTC]");
+        // Friday 2025-01-10 03:00: the weekend window on Saturday comes first
+        assert_eq!(
+            window.next_open(utc(2025, 1, 10, 3, 0)),
+            Some(utc(2025, 1, 11, 10, 0))
+        );
+        assert_eq!(
+            window.next_open(utc(2025, 1, 12, 10, 0)),
+            Some(utc(2025, 1, 13, 2, 0))
+        );
+        assert!(window.is_open(utc(2025, 1, 11, 10, 0)));
+        assert!(window.is_open(utc(2025, 1, 13, 2, 0)));
+        assert!(!window.is_open(utc(2025, 1, 11, 2, 0)));
+    }
+
+    #[test]
+    fn test_back_to_back_windows() {
+        let window = UpdateWindow::parse("59 1 * * *;0 2 * * *").unwrap();
+        assert!(window.is_open(utc(2025, 1, 6, 1, 59)));
+        assert!(window.is_open(utc(2025, 1, 6, 2, 0)));
+        assert_eq!(
+            window.next_open(utc(2025, 1, 6, 1, 59)),
+            Some(utc(2025, 1, 6, 2, 0))
+        );
+        assert_eq!(
+            window.next_open(utc(2025, 1, 6, 2, 0)),
+            Some(utc(2025, 1, 7, 1, 59))
+        );
+    }
+
+    #[test]
+    fn test_invalid_windows() {
+        assert!(UpdateWindow::parse("").is_err());
+        assert!(UpdateWindow::parse(" ; ").is_err());
+        assert!(UpdateWindow::parse("0 2 * *").is_err());
+        assert!(UpdateWindow::parse("0 2 * * *]").is_err());
+        assert!(UpdateWindow::parse("0 2 * * *[../../etc/passwd]").is_err());
+        assert!(UpdateWindow::parse("0 2 * * *[Mars/Olympus_Mons]").is_err());
+    }
+
+    #[test]
+    fn test_tzif_footer() {
+        let mut data = b"TZif2".to_vec();
+        data.extend_from_slice(&[0; 40]);
+        data.extend_from_slice(b"\nEST5EDT,M3.2.0,M11.1.0\n");
+        assert_eq!(tzif_footer(&data).unwrap(), "EST5EDT,M3.2.0,M11.1.0");
+
+        assert!(tzif_footer(b"TZif\0\0\0\0").is_err());
+        assert!(tzif_footer(b"not a zone file").is_err());
+    }
+}
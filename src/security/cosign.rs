@@ -0,0 +1,98 @@
+//! Cosign signature checks on images.
+//!
+//! Signatures are verified with `cosign verify`, against the key in
+//! `HEADWIND_COSIGN_KEY` or keylessly against Sigstore's public good instance,
+//! with the same settings as SLSA provenance checks (see [`CosignVerifier`]).
+
+use super::slsa::CosignVerifier;
+use anyhow::{Context, Result, bail};
+use async_trait::async_trait;
+use tokio::process::Command;
+
+/// Source of signature checks, so callers can be tested without cosign
+#[async_trait]
+pub trait SignatureVerifier: Send + Sync {
+    /// Ok if `image` has a signature that verifies
+    async fn verify_signature(&self, image: &str) -> Result<()>;
+}
+
+impl CosignVerifier {
+    fn verify_args(&self, image: &str) -> Vec<String> {
+        let mut args = vec!["verify".to_string()];
+        args.extend(self.identity_args());
+        args.push(image.to_string());
+        args
+    }
+}
+
+#[async_trait]
+impl SignatureVerifier for CosignVerifier {
+    async fn verify_signature(&self, image: &str) -> Result<()> {
+        let output = Command::new(&self.binary)
+            .args(self.verify_args(image))
+            .output()
+            .await
+            .with_context(|| format!("Failed to run {}", self.binary))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(
+                "cosign verify failed: {}",
+                stderr.lines().last().unwrap_or("no output").trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Ok if `image` has a valid cosign signature
+pub async fn verify_image_signature(image: &str) -> Result<()> {
+    CosignVerifier::from_env().verify_signature(image).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosign_verify_args() {
+        let keyless = CosignVerifier {
+            binary: "cosign".to_string(),
+            key: None,
+            certificate_identity_regexp: "^https://github.com/acme/".to_string(),
+            certificate_oidc_issuer_regexp: "https://token.actions.githubusercontent.com"
+                .to_string(),
+        };
+        assert_eq!(
+            keyless.verify_args("ghcr.io/acme/web:1.2.0"),
+            [
+                "verify",
+                "--certificate-identity-regexp",
+                "^https://github.com/acme/",
+                "--certificate-oidc-issuer-regexp",
+                "https://token.actions.githubusercontent.com",
+                "ghcr.io/acme/web:1.2.0"
+            ]
+        );
+
+        let with_key = CosignVerifier {
+            key: Some("k8s://headwind/cosign-pub".to_string()),
+            ..keyless
+        };
+        assert_eq!(
+            with_key.verify_args("web:1.0.0"),
+            ["verify", "--key", "k8s://headwind/cosign-pub", "web:1.0.0"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_binary_is_an_error() {
+        let verifier = CosignVerifier {
+            binary: "/nonexistent/cosign".to_string(),
+            key: None,
+            certificate_identity_regexp: ".*".to_string(),
+            certificate_oidc_issuer_regexp: ".*".to_string(),
+        };
+        let err = verifier.verify_signature("web:1.0.0").await.unwrap_err();
+        assert!(err.to_string().contains("Failed to run"), "{}", err);
+    }
+}
@@ -0,0 +1,4 @@
+//! Supply chain checks on new images before they are proposed or applied.
+
+pub mod cosign;
+pub mod slsa;
@@ -0,0 +1,526 @@
+//! SLSA build provenance checks on new images.
+//!
+//! With `headwind.sh/require-slsa-level` set, a new image is only proposed or
+//! applied if it carries a signed SLSA provenance attestation. Attestations
+//! are verified with `cosign verify-attestation`, against the key in
+//! `HEADWIND_COSIGN_KEY` or keylessly against Sigstore's public good instance.
+//!
+//! A verified attestation proves build level 2 (provenance signed by the build
+//! platform). Level 3 additionally needs a builder known to isolate builds
+//! from the workflows they run, see `L3_BUILDERS`.
+
+use crate::metrics::SLSA_VERIFICATION_FAILURES_TOTAL;
+use crate::models::ResourcePolicy;
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use k8s_openapi::api::core::v1::ObjectReference;
+use kube::Client;
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use serde_json::Value;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+/// Reason of the warning event published when a new image fails the check
+pub const PROVENANCE_VIOLATION_REASON: &str = "ProvenanceViolation";
+
+const SLSA_PROVENANCE_V02: &str = "https://slsa.dev/provenance/v0.2";
+const SLSA_PROVENANCE_V1: &str = "https://slsa.dev/provenance/v1";
+
+/// Builders whose provenance meets SLSA build level 3
+const L3_BUILDERS: &[&str] = &[
+    "https://github.com/slsa-framework/slsa-github-generator/",
+    "https://cloudbuild.googleapis.com/GoogleHostedWorker",
+];
+
+/// Verified provenance of an image
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProvenanceResult {
+    pub predicate_type: String,
+    pub builder_id: String,
+    pub build_type: Option<String>,
+    pub slsa_level: u8,
+}
+
+/// Source of verified attestations, so the checks can be tested without cosign
+#[async_trait]
+pub trait ProvenanceVerifier: Send + Sync {
+    /// Attestations of `image` whose signatures verified, one DSSE envelope
+    /// JSON per line as `cosign verify-attestation` prints them
+    async fn verified_attestations(&self, image: &str) -> Result<String>;
+}
+
+/// Verifies attestations with the `cosign` CLI
+#[derive(Debug, Clone)]
+pub struct CosignVerifier {
+    /// cosign binary, `HEADWIND_COSIGN_PATH` (default `cosign` on the PATH)
+    pub binary: String,
+    /// Public key or KMS URI, `HEADWIND_COSIGN_KEY`; keyless verification when unset
+    pub key: Option<String>,
+    /// Signing certificate identity for keyless verification,
+    /// `HEADWIND_COSIGN_CERTIFICATE_IDENTITY_REGEXP` (default any)
+    pub certificate_identity_regexp: String,
+    /// OIDC issuer for keyless verification,
+    /// `HEADWIND_COSIGN_CERTIFICATE_OIDC_ISSUER_REGEXP` (default any)
+    pub certificate_oidc_issuer_regexp: String,
+}
+
+impl CosignVerifier {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Self {
+            binary: var("HEADWIND_COSIGN_PATH").unwrap_or_else(|| "cosign".to_string()),
+            key: var("HEADWIND_COSIGN_KEY"),
+            certificate_identity_regexp: var("HEADWIND_COSIGN_CERTIFICATE_IDENTITY_REGEXP")
+                .unwrap_or_else(|| ".*".to_string()),
+            certificate_oidc_issuer_regexp: var("HEADWIND_COSIGN_CERTIFICATE_OIDC_ISSUER_REGEXP")
+                .unwrap_or_else(|| ".*".to_string()),
+        }
+    }
+
+    fn args(&self, attestation_type: &str, image: &str) -> Vec<String> {
+        let mut args = vec![
+            "verify-attestation".to_string(),
+            "--type".to_string(),
+            attestation_type.to_string(),
+        ];
+        args.extend(self.identity_args());
+        args.push(image.to_string());
+        args
+    }
+
+    /// The key, or the accepted keyless signer, as cosign verify options
+    pub(super) fn identity_args(&self) -> Vec<String> {
+        match &self.key {
+            Some(key) => vec!["--key".to_string(), key.clone()],
+            None => vec![
+                "--certificate-identity-regexp".to_string(),
+                self.certificate_identity_regexp.clone(),
+                "--certificate-oidc-issuer-regexp".to_string(),
+                self.certificate_oidc_issuer_regexp.clone(),
+            ],
+        }
+    }
+}
+
+#[async_trait]
+impl ProvenanceVerifier for CosignVerifier {
+    async fn verified_attestations(&self, image: &str) -> Result<String> {
+        let mut attestations = String::new();
+        let mut errors = Vec::new();
+
+        // cosign filters by predicate type, so ask for SLSA v1 and v0.2 in turn
+        for attestation_type in ["slsaprovenance1", "slsaprovenance"] {
+            let output = Command::new(&self.binary)
+                .args(self.args(attestation_type, image))
+                .output()
+                .await
+                .with_context(|| format!("Failed to run {}", self.binary))?;
+            if output.status.success() {
+                attestations.push_str(&String::from_utf8_lossy(&output.stdout));
+            } else {
+                errors.push(String::from_utf8_lossy(&output.stderr).trim().to_string());
+            }
+        }
+
+        if attestations.trim().is_empty() {
+            bail!(
+                "cosign verify-attestation failed: {}",
+                errors.last().map(String::as_str).unwrap_or("no output")
+            );
+        }
+        Ok(attestations)
+    }
+}
+
+/// Verified SLSA provenance of `image` built by `expected_builder` (any
+/// builder when `None`), the highest level one if there are several
+pub async fn verify_slsa_provenance(
+    image: &str,
+    expected_builder: Option<&str>,
+) -> Result<ProvenanceResult> {
+    verify_with(&CosignVerifier::from_env(), image, expected_builder).await
+}
+
+pub async fn verify_with(
+    verifier: &dyn ProvenanceVerifier,
+    image: &str,
+    expected_builder: Option<&str>,
+) -> Result<ProvenanceResult> {
+    let output = verifier.verified_attestations(image).await?;
+    let provenances = parse_attestations(&output)?;
+    if provenances.is_empty() {
+        bail!("{} has no SLSA provenance attestation", image);
+    }
+
+    let builders: Vec<String> = provenances.iter().map(|p| p.builder_id.clone()).collect();
+    provenances
+        .into_iter()
+        .filter(|p| expected_builder.is_none_or(|b| builder_matches(&p.builder_id, b)))
+        .max_by_key(|p| p.slsa_level)
+        .ok_or_else(|| {
+            anyhow!(
+                "Provenance of {} names builder {}, expected {}",
+                image,
+                builders.join(", "),
+                expected_builder.unwrap_or_default()
+            )
+        })
+}
+
+/// Check the provenance of `image` against the SLSA annotations of `policy`.
+/// Returns `None` when the policy requires no provenance.
+pub async fn evaluate_provenance(
+    verifier: &dyn ProvenanceVerifier,
+    policy: &ResourcePolicy,
+    image: &str,
+) -> Result<Option<ProvenanceResult>> {
+    let Some(required_level) = policy.require_slsa_level else {
+        return Ok(None);
+    };
+
+    let provenance = verify_with(verifier, image, policy.slsa_builder.as_deref()).await?;
+    if provenance.slsa_level < required_level {
+        bail!(
+            "Provenance of {} from {} meets SLSA level {}, level {} is required",
+            image,
+            provenance.builder_id,
+            provenance.slsa_level,
+            required_level
+        );
+    }
+    Ok(Some(provenance))
+}
+
+/// Whether `image` may be used under `policy`. Images that fail the check are
+/// counted in `SLSA_VERIFICATION_FAILURES_TOTAL` and reported with a
+/// `ProvenanceViolation` warning event on `object_ref`.
+pub async fn check_provenance(
+    client: &Client,
+    object_ref: &ObjectReference,
+    policy: &ResourcePolicy,
+    image: &str,
+) -> bool {
+    match evaluate_provenance(&CosignVerifier::from_env(), policy, image).await {
+        Ok(None) => true,
+        Ok(Some(provenance)) => {
+            info!(
+                "Verified SLSA level {} provenance of {} from {}",
+                provenance.slsa_level, image, provenance.builder_id
+            );
+            true
+        },
+        Err(e) => {
+            warn!("Skipping update to {}: {:#}", image, e);
+            SLSA_VERIFICATION_FAILURES_TOTAL.inc();
+            publish_provenance_violation(client, object_ref, &format!("{:#}", e)).await;
+            false
+        },
+    }
+}
+
+async fn publish_provenance_violation(client: &Client, object_ref: &ObjectReference, note: &str) {
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    let event = Event {
+        type_: EventType::Warning,
+        reason: PROVENANCE_VIOLATION_REASON.to_string(),
+        note: Some(note.to_string()),
+        action: "UpdateImage".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(&event, object_ref).await {
+        warn!(
+            "Failed to publish {} event for {}/{}: {}",
+            PROVENANCE_VIOLATION_REASON,
+            object_ref.namespace.as_deref().unwrap_or_default(),
+            object_ref.name.as_deref().unwrap_or_default(),
+            e
+        );
+    }
+}
+
+/// SLSA provenance in `cosign verify-attestation` output. Attestations of
+/// other predicate types are ignored.
+pub fn parse_attestations(output: &str) -> Result<Vec<ProvenanceResult>> {
+    let mut provenances = Vec::new();
+
+    for line in output.lines().filter(|l| !l.trim().is_empty()) {
+        let envelope: Value = serde_json::from_str(line).context("Invalid attestation envelope")?;
+        let payload = envelope["payload"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Attestation envelope has no payload"))?;
+        let statement: Value = serde_json::from_slice(
+            &STANDARD
+                .decode(payload)
+                .context("Attestation payload is not base64")?,
+        )
+        .context("Attestation payload is not an in-toto statement")?;
+
+        let predicate_type = statement["predicateType"].as_str().unwrap_or_default();
+        let predicate = &statement["predicate"];
+        let (builder_id, build_type) = match predicate_type {
+            SLSA_PROVENANCE_V1 => (
+                &predicate["runDetails"]["builder"]["id"],
+                &predicate["buildDefinition"]["buildType"],
+            ),
+            SLSA_PROVENANCE_V02 => (&predicate["builder"]["id"], &predicate["buildType"]),
+            other => {
+                debug!("Ignoring attestation of type {}", other);
+                continue;
+            },
+        };
+        let builder_id = builder_id
+            .as_str()
+            .ok_or_else(|| anyhow!("SLSA provenance has no builder id"))?;
+
+        provenances.push(ProvenanceResult {
+            predicate_type: predicate_type.to_string(),
+            builder_id: builder_id.to_string(),
+            build_type: build_type.as_str().map(String::from),
+            slsa_level: builder_level(builder_id),
+        });
+    }
+
+    Ok(provenances)
+}
+
+/// Build level of signed provenance from `builder_id`
+fn builder_level(builder_id: &str) -> u8 {
+    if L3_BUILDERS.iter().any(|b| builder_id.starts_with(b)) {
+        3
+    } else {
+        2
+    }
+}
+
+/// Whether `builder_id` is `expected` or a version or sub-builder of it, e.g.
+/// `https://github.com/actions/runner/github-hosted` for
+/// `https://github.com/actions/runner`
+fn builder_matches(builder_id: &str, expected: &str) -> bool {
+    let expected = expected.trim_end_matches('/');
+    builder_id
+        .strip_prefix(expected)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '@']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const GITHUB_HOSTED: &str = "https://github.com/actions/runner/github-hosted";
+    const SLSA_GENERATOR: &str = "https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_container_slsa3.yml@refs/tags/v2.0.0";
+
+    /// A line of `cosign verify-attestation` output
+    fn envelope(statement: Value) -> String {
+        json!({
+            "payloadType": "application/vnd.in-toto+json",
+            "payload": STANDARD.encode(statement.to_string()),
+            "signatures": [{"keyid": "", "sig": "MEUCIQ..."}]
+        })
+        .to_string()
+    }
+
+    fn provenance_v1(builder: &str) -> String {
+        envelope(json!({
+            "_type": "https://in-toto.io/Statement/v1",
+            "subject": [{"name": "ghcr.io/acme/web", "digest": {"sha256": "abc"}}],
+            "predicateType": SLSA_PROVENANCE_V1,
+            "predicate": {
+                "buildDefinition": {
+                    "buildType": "https://actions.github.io/buildtypes/workflow/v1"
+                },
+                "runDetails": {"builder": {"id": builder}}
+            }
+        }))
+    }
+
+    fn provenance_v02(builder: &str) -> String {
+        envelope(json!({
+            "_type": "https://in-toto.io/Statement/v0.1",
+            "predicateType": SLSA_PROVENANCE_V02,
+            "predicate": {
+                "builder": {"id": builder},
+                "buildType": "https://github.com/slsa-framework/slsa-github-generator/container@v1"
+            }
+        }))
+    }
+
+    struct MockVerifier(Result<String, String>);
+
+    #[async_trait]
+    impl ProvenanceVerifier for MockVerifier {
+        async fn verified_attestations(&self, _image: &str) -> Result<String> {
+            self.0.clone().map_err(|e| anyhow!(e))
+        }
+    }
+
+    fn policy(level: Option<u8>, builder: Option<&str>) -> ResourcePolicy {
+        ResourcePolicy {
+            require_slsa_level: level,
+            slsa_builder: builder.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_attestations() {
+        let vuln_scan = envelope(json!({
+            "predicateType": "https://cosign.sigstore.dev/attestation/vuln/v1",
+            "predicate": {}
+        }));
+        let output = format!(
+            "{}\n{}\n{}\n",
+            provenance_v1(GITHUB_HOSTED),
+            vuln_scan,
+            provenance_v02(SLSA_GENERATOR)
+        );
+
+        let provenances = parse_attestations(&output).unwrap();
+        assert_eq!(provenances.len(), 2);
+        assert_eq!(
+            provenances[0],
+            ProvenanceResult {
+                predicate_type: SLSA_PROVENANCE_V1.to_string(),
+                builder_id: GITHUB_HOSTED.to_string(),
+                build_type: Some("https://actions.github.io/buildtypes/workflow/v1".to_string()),
+                slsa_level: 2,
+            }
+        );
+        assert_eq!(provenances[1].builder_id, SLSA_GENERATOR);
+        assert_eq!(provenances[1].slsa_level, 3);
+
+        assert!(parse_attestations("not json").is_err());
+        assert!(parse_attestations(r#"{"payload": "%%%"}"#).is_err());
+    }
+
+    #[test]
+    fn test_builder_matches() {
+        assert!(builder_matches(
+            GITHUB_HOSTED,
+            "https://github.com/actions/runner"
+        ));
+        assert!(builder_matches(GITHUB_HOSTED, GITHUB_HOSTED));
+        assert!(builder_matches(
+            SLSA_GENERATOR,
+            "https://github.com/slsa-framework/slsa-github-generator/.github/workflows/generator_container_slsa3.yml"
+        ));
+        assert!(!builder_matches(
+            "https://github.com/actions/runner-evil",
+            "https://github.com/actions/runner"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_provenance() {
+        let verifier = MockVerifier(Ok(format!(
+            "{}\n{}",
+            provenance_v1(GITHUB_HOSTED),
+            provenance_v02(SLSA_GENERATOR)
+        )));
+
+        // Not required: nothing is verified
+        assert_eq!(
+            evaluate_provenance(
+                &MockVerifier(Err("unused".into())),
+                &policy(None, None),
+                "web"
+            )
+            .await
+            .unwrap(),
+            None
+        );
+
+        // The highest level provenance is used
+        let result = evaluate_provenance(&verifier, &policy(Some(3), None), "web")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(result.builder_id, SLSA_GENERATOR);
+
+        let result = evaluate_provenance(
+            &verifier,
+            &policy(Some(2), Some("https://github.com/actions/runner")),
+            "web",
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(result.builder_id, GITHUB_HOSTED);
+
+        // GitHub-hosted runners only prove level 2
+        let err = evaluate_provenance(
+            &verifier,
+            &policy(Some(3), Some("https://github.com/actions/runner")),
+            "web",
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("level 3 is required"), "{}", err);
+
+        let err = evaluate_provenance(
+            &verifier,
+            &policy(Some(2), Some("https://gitlab.com/gitlab-org/gitlab-runner")),
+            "web",
+        )
+        .await
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("expected https://gitlab.com"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unverified_images_fail() {
+        let unsigned = MockVerifier(Err("no matching attestations".into()));
+        assert!(
+            evaluate_provenance(&unsigned, &policy(Some(1), None), "web")
+                .await
+                .is_err()
+        );
+
+        let no_provenance = MockVerifier(Ok(envelope(json!({
+            "predicateType": "https://spdx.dev/Document",
+            "predicate": {}
+        }))));
+        let err = evaluate_provenance(&no_provenance, &policy(Some(1), None), "web")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no SLSA provenance"), "{}", err);
+    }
+
+    #[test]
+    fn test_cosign_args() {
+        let keyless = CosignVerifier {
+            binary: "cosign".to_string(),
+            key: None,
+            certificate_identity_regexp: "^https://github.com/acme/".to_string(),
+            certificate_oidc_issuer_regexp: "https://token.actions.githubusercontent.com"
+                .to_string(),
+        };
+        assert_eq!(
+            keyless.args("slsaprovenance1", "ghcr.io/acme/web:1.2.0"),
+            [
+                "verify-attestation",
+                "--type",
+                "slsaprovenance1",
+                "--certificate-identity-regexp",
+                "^https://github.com/acme/",
+                "--certificate-oidc-issuer-regexp",
+                "https://token.actions.githubusercontent.com",
+                "ghcr.io/acme/web:1.2.0"
+            ]
+        );
+
+        let with_key = CosignVerifier {
+            key: Some("k8s://headwind/cosign-pub".to_string()),
+            ..keyless
+        };
+        assert_eq!(
+            with_key.args("slsaprovenance", "web:1.0.0")[3..5],
+            ["--key", "k8s://headwind/cosign-pub"]
+        );
+    }
+}
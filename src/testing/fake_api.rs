@@ -0,0 +1,129 @@
+//! Fake Kubernetes API server for tests of code taking a `kube::Client`.
+//!
+//! Every request is answered in-process by a handler, without a network
+//! listener, and recorded so tests can check what was sent. Request bodies are
+//! parsed as JSON; handlers answer with a status code and a JSON body.
+
+use axum::http::{Method, Request, Response, StatusCode};
+use kube::Client;
+use kube::client::Body;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+
+/// A request received by a [`FakeApi`]
+#[derive(Clone, Debug)]
+pub struct ApiRequest {
+    pub method: Method,
+    pub path: String,
+    /// Decoded query parameters
+    pub query: HashMap<String, String>,
+    /// The JSON body, `Null` when empty
+    pub body: Value,
+}
+
+type Handler = dyn Fn(&ApiRequest) -> (StatusCode, Value) + Send + Sync;
+
+/// A Kubernetes API server answering requests with a handler
+#[derive(Clone)]
+pub struct FakeApi {
+    client: Client,
+    requests: Arc<Mutex<Vec<ApiRequest>>>,
+}
+
+impl FakeApi {
+    /// Answer every request with `handler`
+    pub fn new(
+        handler: impl Fn(&ApiRequest) -> (StatusCode, Value) + Send + Sync + 'static,
+    ) -> Self {
+        let handler: Arc<Handler> = Arc::new(handler);
+        let requests: Arc<Mutex<Vec<ApiRequest>>> = Arc::default();
+        let recorded = requests.clone();
+
+        let service = tower::service_fn(move |req: Request<Body>| {
+            let handler = handler.clone();
+            let recorded = recorded.clone();
+            async move {
+                let (parts, body) = req.into_parts();
+                let body = body.collect_bytes().await.unwrap_or_default();
+                let url =
+                    reqwest::Url::parse(&format!("http://fake{}", parts.uri)).expect("request URI");
+                let request = ApiRequest {
+                    method: parts.method,
+                    path: url.path().to_string(),
+                    query: url.query_pairs().into_owned().collect(),
+                    body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+                };
+                recorded
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(request.clone());
+
+                let (status, response) = handler(&request);
+                Ok::<_, Infallible>(
+                    Response::builder()
+                        .status(status)
+                        .header("content-type", "application/json")
+                        .body(Body::from(serde_json::to_vec(&response).unwrap()))
+                        .unwrap(),
+                )
+            }
+        });
+
+        Self {
+            client: Client::new(service, "default"),
+            requests,
+        }
+    }
+
+    /// Answer every request with `200 OK` and the body `handler` returns
+    pub fn ok(handler: impl Fn(&ApiRequest) -> Value + Send + Sync + 'static) -> Self {
+        Self::new(move |request| (StatusCode::OK, handler(request)))
+    }
+
+    /// A client sending its requests to this server
+    pub fn client(&self) -> Client {
+        self.client.clone()
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<ApiRequest> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Method and path of every request received so far, oldest first
+    pub fn calls(&self) -> Vec<(Method, String)> {
+        self.requests()
+            .into_iter()
+            .map(|request| (request.method, request.path))
+            .collect()
+    }
+
+    /// Bodies of the requests with `method` whose path contains `path_part`
+    pub fn bodies(&self, method: Method, path_part: &str) -> Vec<Value> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.method == method && request.path.contains(path_part))
+            .map(|request| request.body)
+            .collect()
+    }
+}
+
+/// A `Status` failure, as the API server answers failed requests
+pub fn api_error(status: StatusCode, reason: &str, message: &str) -> (StatusCode, Value) {
+    (
+        status,
+        json!({
+            "apiVersion": "v1",
+            "kind": "Status",
+            "status": "Failure",
+            "message": message,
+            "reason": reason,
+            "code": status.as_u16()
+        }),
+    )
+}
@@ -0,0 +1,266 @@
+//! In-process OpenID Connect provider for tests.
+//!
+//! Serves the discovery document, a JWKS and a token endpoint that exchanges
+//! codes registered with [`FakeOidcProvider::add_code`]. Tokens are signed
+//! with an ES256 key generated at startup; [`FakeOidcProvider::rotate_key`]
+//! replaces it with a new key under a new `kid`.
+
+use anyhow::{Result, anyhow};
+use axum::{
+    Form, Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use ring::rand::SystemRandom;
+use ring::signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+struct SigningKey {
+    kid: String,
+    key_pair: EcdsaKeyPair,
+}
+
+impl SigningKey {
+    fn generate(kid: String) -> Result<Self> {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .map_err(|_| anyhow!("failed to generate signing key"))?;
+        let key_pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                .map_err(|_| anyhow!("failed to load signing key"))?;
+        Ok(Self { kid, key_pair })
+    }
+
+    fn jwk(&self) -> Value {
+        // Uncompressed point: 0x04 || x || y
+        let point = self.key_pair.public_key().as_ref();
+        json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "alg": "ES256",
+            "use": "sig",
+            "kid": self.kid,
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+        })
+    }
+}
+
+struct ProviderState {
+    issuer: String,
+    key: SigningKey,
+    extra_keys: Vec<Value>,
+    codes: HashMap<String, String>,
+    jwks_fetches: usize,
+    token_requests: Vec<HashMap<String, String>>,
+}
+
+type SharedState = Arc<Mutex<ProviderState>>;
+
+/// A local OIDC provider issuing tokens for one client
+pub struct FakeOidcProvider {
+    addr: SocketAddr,
+    client_id: String,
+    state: SharedState,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FakeOidcProvider {
+    /// Start a provider whose default claims have `client_id` as audience
+    pub async fn start(client_id: &str) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let state = Arc::new(Mutex::new(ProviderState {
+            issuer: format!("http://{}", addr),
+            key: SigningKey::generate("key-1".to_string())?,
+            extra_keys: Vec::new(),
+            codes: HashMap::new(),
+            jwks_fetches: 0,
+            token_requests: Vec::new(),
+        }));
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/jwks", get(jwks))
+            .route("/token", post(token))
+            .with_state(state.clone());
+
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await;
+        });
+
+        Ok(Self {
+            addr,
+            client_id: client_id.to_string(),
+            state,
+            shutdown: Some(shutdown),
+            handle: Some(handle),
+        })
+    }
+
+    /// Issuer URL, e.g. `http://127.0.0.1:41234`
+    pub fn issuer(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Claims of a valid id_token for `sub`, expiring in five minutes
+    pub fn claims(&self, sub: &str) -> Value {
+        let now = chrono::Utc::now().timestamp();
+        json!({
+            "iss": self.issuer(),
+            "sub": sub,
+            "aud": self.client_id,
+            "iat": now,
+            "exp": now + 300,
+        })
+    }
+
+    /// Sign `claims` as an ES256 JWT with the current key
+    pub fn sign(&self, claims: &Value) -> String {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let header = json!({ "alg": "ES256", "typ": "JWT", "kid": state.key.kid });
+        let message = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = state
+            .key
+            .key_pair
+            .sign(&SystemRandom::new(), message.as_bytes())
+            .expect("ECDSA signing does not fail");
+        format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature.as_ref()))
+    }
+
+    /// Answer the token endpoint for `code` with `id_token`
+    pub fn add_code(&self, code: &str, id_token: &str) {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .codes
+            .insert(code.to_string(), id_token.to_string());
+    }
+
+    /// Publish an additional key in the JWKS, e.g. an RSA key the test signs with
+    pub fn add_jwk(&self, jwk: Value) {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .extra_keys
+            .push(jwk);
+    }
+
+    /// Replace the signing key with a new one under a new `kid`
+    pub fn rotate_key(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let next = state
+            .key
+            .kid
+            .strip_prefix("key-")
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or(0)
+            + 1;
+        state.key = SigningKey::generate(format!("key-{}", next))?;
+        Ok(())
+    }
+
+    /// Number of times the JWKS was fetched
+    pub fn jwks_fetches(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .jwks_fetches
+    }
+
+    /// Form parameters of every token endpoint request, oldest first
+    pub fn token_requests(&self) -> Vec<HashMap<String, String>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .token_requests
+            .clone()
+    }
+
+    /// Stop the server and wait for it to exit
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.await;
+        }
+    }
+}
+
+impl Drop for FakeOidcProvider {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+async fn discovery(State(state): State<SharedState>) -> Json<Value> {
+    let issuer = state
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .issuer
+        .clone();
+    Json(json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/authorize", issuer),
+        "token_endpoint": format!("{}/token", issuer),
+        "jwks_uri": format!("{}/jwks", issuer),
+        "response_types_supported": ["code"],
+        "id_token_signing_alg_values_supported": ["ES256", "RS256"],
+    }))
+}
+
+async fn jwks(State(state): State<SharedState>) -> Json<Value> {
+    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+    state.jwks_fetches += 1;
+    let mut keys = vec![state.key.jwk()];
+    keys.extend(state.extra_keys.iter().cloned());
+    Json(json!({ "keys": keys }))
+}
+
+async fn token(
+    State(state): State<SharedState>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Response {
+    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+    state.token_requests.push(form.clone());
+
+    let id_token = form
+        .get("code")
+        .filter(|_| form.get("grant_type").map(String::as_str) == Some("authorization_code"))
+        .and_then(|code| state.codes.remove(code));
+    match id_token {
+        Some(id_token) => Json(json!({
+            "access_token": "fake-access-token",
+            "token_type": "Bearer",
+            "expires_in": 300,
+            "id_token": id_token,
+        }))
+        .into_response(),
+        None => (
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "error": "invalid_grant" })),
+        )
+            .into_response(),
+    }
+}
@@ -0,0 +1,428 @@
+//! In-process OCI registry for tests.
+//!
+//! Serves the read-only parts of the OCI Distribution v2 API used by the
+//! registry poller: `GET /v2/`, `GET /v2/{repo}/tags/list` (with `n`/`last`
+//! pagination and a `Link` header), `GET`/`HEAD /v2/{repo}/manifests/{reference}`
+//! and `GET /v2/{repo}/blobs/{digest}` for image configs.
+//! Optionally every request requires HTTP basic authentication, and the
+//! registry can serve HTTPS with a given certificate.
+
+use anyhow::Result;
+use axum::{
+    Router,
+    body::Body,
+    extract::{Query, State},
+    http::{HeaderMap, Method, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio_rustls::TlsAcceptor;
+
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+const CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.image.config.v1+json";
+
+/// A request received by the fake registry
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    /// Whether the request carried valid credentials (always true without auth)
+    pub authorized: bool,
+}
+
+#[derive(Default)]
+struct RegistryState {
+    /// Tags per repository, in the order they were added
+    tags: HashMap<String, Vec<String>>,
+    /// Push count per (repository, tag); re-pushing a tag changes its digest
+    revisions: HashMap<(String, String), u32>,
+    /// Image config labels per (repository, tag)
+    labels: HashMap<(String, String), BTreeMap<String, String>>,
+    /// Image config creation date per (repository, tag)
+    created: HashMap<(String, String), String>,
+    credentials: Option<(String, String)>,
+    requests: Vec<RecordedRequest>,
+}
+
+type SharedState = Arc<Mutex<RegistryState>>;
+
+/// A local OCI registry serving tags added with [`FakeRegistry::add_tags`]
+pub struct FakeRegistry {
+    addr: SocketAddr,
+    /// `https` when serving TLS
+    scheme: &'static str,
+    state: SharedState,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FakeRegistry {
+    /// Start a registry that accepts anonymous requests
+    pub async fn start() -> Result<Self> {
+        Self::start_with_state(RegistryState::default(), None).await
+    }
+
+    /// Start a registry serving HTTPS with `cert_pem` and its PKCS#8 `key_pem`
+    pub async fn start_with_tls(cert_pem: &str, key_pem: &str) -> Result<Self> {
+        let certs =
+            CertificateDer::pem_slice_iter(cert_pem.as_bytes()).collect::<Result<Vec<_>, _>>()?;
+        let key = PrivateKeyDer::from_pem_slice(key_pem.as_bytes())?;
+        let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+
+        Self::start_with_state(
+            RegistryState::default(),
+            Some(TlsAcceptor::from(Arc::new(config))),
+        )
+        .await
+    }
+
+    /// Start a registry that answers 401 to requests without these credentials
+    pub async fn start_with_basic_auth(username: &str, password: &str) -> Result<Self> {
+        Self::start_with_state(
+            RegistryState {
+                credentials: Some((username.to_string(), password.to_string())),
+                ..Default::default()
+            },
+            None,
+        )
+        .await
+    }
+
+    async fn start_with_state(state: RegistryState, tls: Option<TlsAcceptor>) -> Result<Self> {
+        let state = Arc::new(Mutex::new(state));
+        let app = Router::new().fallback(handle).with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        let handle = match tls {
+            Some(acceptor) => tokio::spawn(async move {
+                tokio::select! {
+                    _ = serve_tls(listener, app, acceptor) => {},
+                    _ = shutdown_rx => {},
+                }
+            }),
+            None => tokio::spawn(async move {
+                let _ = axum::serve(listener, app)
+                    .with_graceful_shutdown(async {
+                        let _ = shutdown_rx.await;
+                    })
+                    .await;
+            }),
+        };
+
+        Ok(Self {
+            addr,
+            scheme,
+            state,
+            shutdown: Some(shutdown),
+            handle: Some(handle),
+        })
+    }
+
+    /// Push `tags` to `repo`. Adding a tag that already exists re-pushes it,
+    /// which changes its manifest digest.
+    pub fn add_tags(&self, repo: &str, tags: &[&str]) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        for tag in tags {
+            let known = state.tags.entry(repo.to_string()).or_default();
+            if !known.iter().any(|t| t == tag) {
+                known.push(tag.to_string());
+            }
+            *state
+                .revisions
+                .entry((repo.to_string(), tag.to_string()))
+                .or_default() += 1;
+        }
+    }
+
+    /// Set the labels in the image config of `repo:tag`, e.g.
+    /// `org.opencontainers.image.revision`. This changes its manifest digest.
+    pub fn set_labels(&self, repo: &str, tag: &str, labels: &[(&str, &str)]) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.labels.insert(
+            (repo.to_string(), tag.to_string()),
+            labels
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        );
+    }
+
+    /// Set `created` in the image config of `repo:tag` to an RFC 3339 date.
+    /// This changes its manifest digest.
+    pub fn set_created(&self, repo: &str, tag: &str, created: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .created
+            .insert((repo.to_string(), tag.to_string()), created.to_string());
+    }
+
+    /// Base URL of the registry, e.g. `http://127.0.0.1:41234`
+    pub fn url(&self) -> String {
+        format!("{}://{}", self.scheme, self.addr)
+    }
+
+    /// Port the registry listens on
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    /// Registry host as used in image references, e.g. `127.0.0.1:41234`
+    pub fn host(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// Current manifest digest of `repo:tag`
+    pub fn digest(&self, repo: &str, tag: &str) -> Option<String> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let revision = *state.revisions.get(&(repo.to_string(), tag.to_string()))?;
+        Some(manifest_digest(&manifest(&state, repo, tag, revision)))
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .requests
+            .clone()
+    }
+
+    /// Stop the server and wait for it to exit
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.await;
+        }
+    }
+}
+
+impl Drop for FakeRegistry {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Serve `app` over TLS until the listener fails
+async fn serve_tls(listener: tokio::net::TcpListener, app: Router, acceptor: TlsAcceptor) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            // Clients that don't trust the certificate abort the handshake
+            if let Ok(stream) = acceptor.accept(stream).await {
+                let _ = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), TowerToHyperService::new(app))
+                    .await;
+            }
+        });
+    }
+}
+
+async fn handle(
+    State(state): State<SharedState>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+
+    let authorized = match &state.credentials {
+        Some((username, password)) => {
+            let expected = format!(
+                "Basic {}",
+                STANDARD.encode(format!("{}:{}", username, password))
+            );
+            headers
+                .get(header::AUTHORIZATION)
+                .is_some_and(|value| value.as_bytes() == expected.as_bytes())
+        },
+        None => true,
+    };
+    state.requests.push(RecordedRequest {
+        method: method.clone(),
+        path: uri.path().to_string(),
+        query: query.clone(),
+        authorized,
+    });
+
+    if !authorized {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Basic realm=\"fake-registry\"")],
+            oci_error("UNAUTHORIZED", "authentication required"),
+        )
+            .into_response();
+    }
+
+    let path = uri.path();
+    if path == "/v2/" || path == "/v2" {
+        return axum::Json(json!({})).into_response();
+    }
+
+    let Some(rest) = path.strip_prefix("/v2/") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    if let Some(repo) = rest.strip_suffix("/tags/list") {
+        return list_tags(&state, repo, &query);
+    }
+
+    if let Some((repo, reference)) = rest.rsplit_once("/manifests/") {
+        return get_manifest(&state, repo, reference);
+    }
+
+    if let Some((repo, digest)) = rest.rsplit_once("/blobs/") {
+        return get_blob(&state, repo, digest);
+    }
+
+    StatusCode::NOT_FOUND.into_response()
+}
+
+fn list_tags(state: &RegistryState, repo: &str, query: &HashMap<String, String>) -> Response {
+    let Some(tags) = state.tags.get(repo) else {
+        return (
+            StatusCode::NOT_FOUND,
+            oci_error("NAME_UNKNOWN", "repository name not known to registry"),
+        )
+            .into_response();
+    };
+
+    let start = query
+        .get("last")
+        .and_then(|last| tags.iter().position(|t| t == last))
+        .map(|i| i + 1)
+        .unwrap_or(0)
+        .min(tags.len());
+    let n = query
+        .get("n")
+        .and_then(|n| n.parse::<usize>().ok())
+        .unwrap_or(tags.len());
+    let end = start.saturating_add(n).min(tags.len());
+    let page = &tags[start..end];
+
+    let mut response = axum::Json(json!({ "name": repo, "tags": page })).into_response();
+    if end < tags.len()
+        && let Some(last) = page.last()
+        && let Ok(link) = format!(
+            "</v2/{}/tags/list?n={}&last={}>; rel=\"next\"",
+            repo, n, last
+        )
+        .parse()
+    {
+        response.headers_mut().insert(header::LINK, link);
+    }
+    response
+}
+
+fn get_manifest(state: &RegistryState, repo: &str, reference: &str) -> Response {
+    let found = state.revisions.iter().find_map(|((r, tag), revision)| {
+        if r != repo {
+            return None;
+        }
+        let body = manifest(state, repo, tag, *revision);
+        let digest = manifest_digest(&body);
+        (tag == reference || digest == reference).then_some((body, digest))
+    });
+
+    match found {
+        Some((body, digest)) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, MANIFEST_MEDIA_TYPE)
+            .header("Docker-Content-Digest", digest)
+            .body(Body::from(body))
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+        None => (
+            StatusCode::NOT_FOUND,
+            oci_error("MANIFEST_UNKNOWN", "manifest unknown"),
+        )
+            .into_response(),
+    }
+}
+
+fn get_blob(state: &RegistryState, repo: &str, digest: &str) -> Response {
+    let found = state.revisions.iter().find_map(|((r, tag), revision)| {
+        let body = config(state, repo, tag, *revision);
+        (r == repo && manifest_digest(&body) == digest).then_some(body)
+    });
+
+    match found {
+        Some(body) => Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, CONFIG_MEDIA_TYPE)
+            .header("Docker-Content-Digest", digest)
+            .body(Body::from(body))
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+        None => (
+            StatusCode::NOT_FOUND,
+            oci_error("BLOB_UNKNOWN", "blob unknown to registry"),
+        )
+            .into_response(),
+    }
+}
+
+/// A minimal image manifest, unique per repository, tag and push
+fn manifest(state: &RegistryState, repo: &str, tag: &str, revision: u32) -> String {
+    let config = config(state, repo, tag, revision);
+    json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST_MEDIA_TYPE,
+        "config": {
+            "mediaType": CONFIG_MEDIA_TYPE,
+            "digest": manifest_digest(&config),
+            "size": config.len()
+        },
+        "layers": [],
+        "annotations": { "org.opencontainers.image.ref.name": tag }
+    })
+    .to_string()
+}
+
+/// Image config carrying the labels and creation date set for `repo:tag`
+fn config(state: &RegistryState, repo: &str, tag: &str, revision: u32) -> String {
+    let key = (repo.to_string(), tag.to_string());
+    let labels = state.labels.get(&key).cloned().unwrap_or_default();
+    let mut config = json!({
+        "architecture": "amd64",
+        "os": "linux",
+        "config": { "Labels": labels },
+        "rootfs": { "type": "layers", "diff_ids": [] },
+        "history": [{ "comment": format!("{}:{}:{}", repo, tag, revision) }]
+    });
+    if let Some(created) = state.created.get(&key) {
+        config["created"] = json!(created);
+    }
+    config.to_string()
+}
+
+fn manifest_digest(body: &str) -> String {
+    format!("sha256:{:x}", Sha256::digest(body.as_bytes()))
+}
+
+fn oci_error(code: &str, message: &str) -> axum::Json<serde_json::Value> {
+    axum::Json(json!({ "errors": [{ "code": code, "message": message }] }))
+}
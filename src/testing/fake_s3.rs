@@ -0,0 +1,266 @@
+//! In-process S3-compatible object store for tests.
+//!
+//! Accepts `PutObject` and `GetObject` requests, path-style
+//! (`/{bucket}/{key}`) or virtual-hosted-style (`Host: {bucket}.s3.test`),
+//! and checks their AWS Signature Version 4 against one set of credentials
+//! like MinIO does. Objects are kept in memory under their key as sent.
+
+use crate::archive::s3::{Credentials, sigv4_authorization};
+use anyhow::Result;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, Method, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// Domain under which buckets are addressed virtual-hosted-style
+pub const VIRTUAL_HOST_DOMAIN: &str = "s3.test";
+
+/// An object stored in the fake
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredObject {
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+struct StoreState {
+    credentials: Credentials,
+    objects: HashMap<(String, String), StoredObject>,
+}
+
+type SharedState = Arc<Mutex<StoreState>>;
+
+/// A local S3 API accepting requests signed with one set of credentials
+pub struct FakeS3 {
+    addr: SocketAddr,
+    state: SharedState,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FakeS3 {
+    pub async fn start(credentials: Credentials) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+
+        let state = Arc::new(Mutex::new(StoreState {
+            credentials,
+            objects: HashMap::new(),
+        }));
+        let app = Router::new().fallback(handle).with_state(state.clone());
+
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            let _ = axum::serve(listener, app)
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                })
+                .await;
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            shutdown: Some(shutdown),
+            handle: Some(handle),
+        })
+    }
+
+    /// Endpoint URL, e.g. `http://127.0.0.1:41234`
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The object stored as `key` in `bucket`
+    pub fn object(&self, bucket: &str, key: &str) -> Option<StoredObject> {
+        self.state
+            .lock()
+            .unwrap()
+            .objects
+            .get(&(bucket.to_string(), key.to_string()))
+            .cloned()
+    }
+
+    /// Stop the server and wait for it to exit
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.await;
+        }
+    }
+}
+
+impl Drop for FakeS3 {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+fn error(status: StatusCode, code: &str, message: &str) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/xml")],
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Error><Code>{}</Code><Message>{}</Message></Error>",
+            code, message
+        ),
+    )
+        .into_response()
+}
+
+/// Bucket and key addressed by a request
+fn locate(headers: &HeaderMap, path: &str) -> Option<(String, String)> {
+    let host = headers.get(header::HOST)?.to_str().ok()?;
+    let hostname = host.split(':').next().unwrap_or(host);
+    let path = path.strip_prefix('/')?;
+    match hostname.strip_suffix(&format!(".{}", VIRTUAL_HOST_DOMAIN)) {
+        Some(bucket) => Some((bucket.to_string(), path.to_string())),
+        None => {
+            let (bucket, key) = path.split_once('/')?;
+            Some((bucket.to_string(), key.to_string()))
+        },
+    }
+}
+
+/// S3 error response: status, code and message
+type S3Error = (StatusCode, &'static str, &'static str);
+
+/// Check the SigV4 `Authorization` header of a request
+fn verify_signature(
+    credentials: &Credentials,
+    method: &Method,
+    path: &str,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(), S3Error> {
+    let denied = |code, message| (StatusCode::FORBIDDEN, code, message);
+    let authorization = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| denied("AccessDenied", "Anonymous access is not allowed"))?;
+    let field = |name: &str| {
+        authorization
+            .split([' ', ','])
+            .find_map(|part| part.strip_prefix(name))
+            .unwrap_or_default()
+    };
+
+    let scope: Vec<&str> = field("Credential=").split('/').collect();
+    if scope.len() != 5 || scope[0] != credentials.access_key_id {
+        return Err(denied(
+            "InvalidAccessKeyId",
+            "The access key ID you provided does not exist in our records.",
+        ));
+    }
+
+    let payload_hash = hex::encode(Sha256::digest(body));
+    let header_value = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    };
+    if header_value("x-amz-content-sha256") != payload_hash {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "XAmzContentSHA256Mismatch",
+            "The provided 'x-amz-content-sha256' header does not match what was computed.",
+        ));
+    }
+
+    let signed: BTreeMap<String, String> = field("SignedHeaders=")
+        .split(';')
+        .map(|name| (name.to_string(), header_value(name)))
+        .collect();
+    let expected = sigv4_authorization(
+        credentials,
+        scope[2],
+        scope[3],
+        method.as_str(),
+        path,
+        &signed,
+        &payload_hash,
+        &header_value("x-amz-date"),
+    );
+    if expected != authorization {
+        return Err(denied(
+            "SignatureDoesNotMatch",
+            "The request signature we calculated does not match the signature you provided.",
+        ));
+    }
+    Ok(())
+}
+
+async fn handle(
+    State(state): State<SharedState>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if let Err((status, code, message)) =
+        verify_signature(&state.credentials, &method, uri.path(), &headers, &body)
+    {
+        return error(status, code, message);
+    }
+    let Some(location) = locate(&headers, uri.path()) else {
+        return error(
+            StatusCode::BAD_REQUEST,
+            "InvalidRequest",
+            "No bucket or key",
+        );
+    };
+
+    match method {
+        Method::PUT => {
+            let content_type = headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("binary/octet-stream")
+                .to_string();
+            state.objects.insert(
+                location,
+                StoredObject {
+                    content_type,
+                    body: body.to_vec(),
+                },
+            );
+            StatusCode::OK.into_response()
+        },
+        Method::GET => match state.objects.get(&location) {
+            Some(object) => (
+                [(header::CONTENT_TYPE, object.content_type.clone())],
+                object.body.clone(),
+            )
+                .into_response(),
+            None => error(
+                StatusCode::NOT_FOUND,
+                "NoSuchKey",
+                "The specified key does not exist.",
+            ),
+        },
+        _ => error(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "MethodNotAllowed",
+            "The specified method is not allowed against this resource.",
+        ),
+    }
+}
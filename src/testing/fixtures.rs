@@ -0,0 +1,50 @@
+//! Resources shared by tests.
+
+use crate::models::crd::{
+    TargetRef, UpdatePhase, UpdatePolicyType, UpdateRequest, UpdateRequestSpec,
+    UpdateRequestStatus, UpdateType,
+};
+
+/// UpdateRequest `namespace/name`, without a status, moving container `web`
+/// of the Deployment `web` in the same namespace from `nginx:1.25.0` to
+/// `nginx:1.26.0` under the minor policy. Tests change what they rely on.
+pub fn update_request(namespace: &str, name: &str) -> UpdateRequest {
+    let mut update_request = UpdateRequest::new(
+        name,
+        UpdateRequestSpec {
+            target_ref: TargetRef {
+                api_version: "apps/v1".to_string(),
+                kind: "Deployment".to_string(),
+                name: "web".to_string(),
+                namespace: namespace.to_string(),
+            },
+            update_type: UpdateType::Image,
+            container_name: Some("web".to_string()),
+            container_kind: Default::default(),
+            current_image: "nginx:1.25.0".to_string(),
+            new_image: "nginx:1.26.0".to_string(),
+            policy: UpdatePolicyType::Minor,
+            reason: None,
+            require_approval: true,
+            expires_at: None,
+            scheduled_at: None,
+            priority: None,
+            updates: Vec::new(),
+            discussion_thread: Vec::new(),
+            sla_deadline: None,
+            impact: None,
+        },
+    );
+    update_request.metadata.namespace = Some(namespace.to_string());
+    update_request
+}
+
+/// [`update_request`] in `phase`
+pub fn update_request_in_phase(namespace: &str, name: &str, phase: UpdatePhase) -> UpdateRequest {
+    let mut update_request = update_request(namespace, name);
+    update_request.status = Some(UpdateRequestStatus {
+        phase,
+        ..Default::default()
+    });
+    update_request
+}
@@ -0,0 +1,13 @@
+//! Test doubles for integration tests.
+
+pub mod fake_api;
+pub mod fake_oidc;
+pub mod fake_registry;
+pub mod fake_s3;
+pub mod fixtures;
+
+pub use fake_api::{ApiRequest, FakeApi, api_error};
+pub use fake_oidc::FakeOidcProvider;
+pub use fake_registry::FakeRegistry;
+pub use fake_s3::FakeS3;
+pub use fixtures::{update_request, update_request_in_phase};
@@ -1,14 +1,24 @@
 use axum::{
-    Json,
-    extract::FromRequestParts,
-    http::{StatusCode, header::AUTHORIZATION, request::Parts},
+    Json, Router,
+    extract::{FromRequestParts, Query, Request, State},
+    http::{
+        HeaderMap, Method, StatusCode,
+        header::{ACCEPT, AUTHORIZATION, COOKIE, HOST, LOCATION, SET_COOKIE},
+        request::Parts,
+    },
+    middleware::{self, Next},
     response::{IntoResponse, Response},
+    routing::get,
 };
 use k8s_openapi::api::authentication::v1::{TokenReview, TokenReviewSpec};
 use kube::{Api, Client, api::PostParams};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::env;
-use tracing::{debug, error, info};
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+use super::email_action::EMAIL_ACTION_PATH;
+use super::oidc::{CALLBACK_PATH, OidcClient, SESSION_COOKIE, STATE_COOKIE};
 
 /// Authentication mode for the Web UI
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,6 +31,8 @@ pub enum AuthMode {
     Token,
     /// Proxy/Ingress auth - reads username from HTTP headers
     Proxy,
+    /// OpenID Connect - login via the IdP, username from the id_token email/sub claim
+    Oidc,
 }
 
 impl AuthMode {
@@ -34,6 +46,7 @@ impl AuthMode {
             "simple" => AuthMode::Simple,
             "token" => AuthMode::Token,
             "proxy" => AuthMode::Proxy,
+            "oidc" => AuthMode::Oidc,
             _ => AuthMode::None,
         }
     }
@@ -71,6 +84,11 @@ where
     type Rejection = AuthError;
 
     async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // Identity already established by the OIDC middleware
+        if let Some(identity) = parts.extensions.get::<UserIdentity>() {
+            return Ok(identity.clone());
+        }
+
         let auth_mode = AuthMode::from_env();
 
         match auth_mode {
@@ -131,6 +149,12 @@ where
                 })
             },
 
+            AuthMode::Oidc => Err(AuthError {
+                // The middleware authenticates every request, so this is only
+                // reached on routes outside of it
+                error: "Not authenticated".to_string(),
+            }),
+
             AuthMode::Proxy => {
                 // Proxy mode - read username from configured header
                 let header_name = AuthMode::proxy_header();
@@ -154,6 +178,228 @@ where
     }
 }
 
+/// Require OIDC authentication for every route of `router`, and serve the
+/// login callback at `/auth/callback`
+pub fn with_oidc(router: Router, oidc: Arc<OidcClient>) -> Router {
+    router
+        .route(CALLBACK_PATH, get(oidc_callback).with_state(oidc.clone()))
+        .layer(middleware::from_fn_with_state(oidc, oidc_middleware))
+}
+
+/// Authenticate with a Bearer id_token or the session cookie. Browsers
+/// without a session are sent to the IdP; API clients get 401.
+async fn oidc_middleware(
+    State(oidc): State<Arc<OidcClient>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    // Email action links authenticate with their own signed token
+    if path == "/health"
+        || path == CALLBACK_PATH
+        || path == EMAIL_ACTION_PATH
+        || path.starts_with("/static/")
+    {
+        return next.run(request).await;
+    }
+
+    let headers = request.headers();
+    let username = match headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        Some(token) => match oidc.validate_token(token, None).await {
+            Ok(claims) => Some(claims.identity().to_string()),
+            Err(e) => {
+                warn!("OIDC bearer token rejected: {:#}", e);
+                return AuthError {
+                    error: format!("Token validation failed: {}", e),
+                }
+                .into_response();
+            },
+        },
+        None => cookie(headers, SESSION_COOKIE).and_then(|value| oidc.verify_session(value)),
+    };
+
+    if let Some(username) = username {
+        debug!("OIDC auth: username={}", username);
+        request.extensions_mut().insert(UserIdentity {
+            username,
+            auth_mode: AuthMode::Oidc,
+        });
+        return next.run(request).await;
+    }
+
+    if !wants_login_redirect(&request) {
+        return AuthError {
+            error: "Not authenticated".to_string(),
+        }
+        .into_response();
+    }
+
+    let return_to = request
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or("/");
+    let redirect_uri = redirect_uri(&oidc, request.headers());
+    match oidc.begin_login(&redirect_uri, return_to).await {
+        Ok((url, login)) => Response::builder()
+            .status(StatusCode::FOUND)
+            .header(LOCATION, url)
+            .header(
+                SET_COOKIE,
+                set_cookie(
+                    STATE_COOKIE,
+                    &oidc.login_cookie(&login),
+                    10 * 60,
+                    &redirect_uri,
+                ),
+            )
+            .body(Default::default())
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response()),
+        Err(e) => {
+            error!("Failed to start OIDC login: {:#}", e);
+            (
+                StatusCode::BAD_GATEWAY,
+                Json(AuthError {
+                    error: format!("Identity provider unavailable: {}", e),
+                }),
+            )
+                .into_response()
+        },
+    }
+}
+
+/// Query parameters the IdP sends to `/auth/callback`
+#[derive(Debug, Deserialize)]
+pub struct CallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+/// Complete the login: check the state, exchange the code and start a session
+async fn oidc_callback(
+    State(oidc): State<Arc<OidcClient>>,
+    headers: HeaderMap,
+    Query(query): Query<CallbackQuery>,
+) -> Response {
+    if let Some(error) = query.error {
+        warn!(
+            "OIDC login failed at the identity provider: {} {}",
+            error,
+            query.error_description.as_deref().unwrap_or_default()
+        );
+        return AuthError {
+            error: format!("Login failed: {}", error),
+        }
+        .into_response();
+    }
+
+    let login = cookie(&headers, STATE_COOKIE).and_then(|value| oidc.verify_login(value));
+    let (Some(login), Some(code)) = (login, query.code) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AuthError {
+                error: "Login expired or was not started here, please try again".to_string(),
+            }),
+        )
+            .into_response();
+    };
+    if query.state.as_deref() != Some(login.state.as_str()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(AuthError {
+                error: "Login state does not match".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let redirect_uri = redirect_uri(&oidc, &headers);
+    match oidc.finish_login(&code, &redirect_uri, &login).await {
+        Ok(claims) => {
+            let username = claims.identity().to_string();
+            info!("OIDC login succeeded for {}", username);
+            Response::builder()
+                .status(StatusCode::SEE_OTHER)
+                .header(LOCATION, &login.return_to)
+                .header(
+                    SET_COOKIE,
+                    set_cookie(
+                        SESSION_COOKIE,
+                        &oidc.session_cookie(&username),
+                        oidc.session_ttl_secs(),
+                        &redirect_uri,
+                    ),
+                )
+                .header(SET_COOKIE, set_cookie(STATE_COOKIE, "", 0, &redirect_uri))
+                .body(Default::default())
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        },
+        Err(e) => {
+            error!("OIDC login failed: {:#}", e);
+            AuthError {
+                error: format!("Login failed: {}", e),
+            }
+            .into_response()
+        },
+    }
+}
+
+/// Page loads are redirected to the IdP, API and htmx requests are not
+fn wants_login_redirect(request: &Request) -> bool {
+    let headers = request.headers();
+    request.method() == Method::GET
+        && !request.uri().path().starts_with("/api/")
+        && !headers.contains_key("HX-Request")
+        && headers
+            .get(ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_none_or(|accept| accept.contains("text/html") || accept.contains("*/*"))
+}
+
+/// Callback URL: `HEADWIND_OIDC_REDIRECT_URL`, or built from the request's host
+fn redirect_uri(oidc: &OidcClient, headers: &HeaderMap) -> String {
+    if let Some(url) = &oidc.config().redirect_url {
+        return url.clone();
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let scheme = header("X-Forwarded-Proto").unwrap_or("http");
+    let host = header("X-Forwarded-Host")
+        .or_else(|| header(HOST.as_str()))
+        .unwrap_or("localhost:8082");
+    format!("{}://{}{}", scheme, host, CALLBACK_PATH)
+}
+
+fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// `Secure` is set whenever the UI is reached over HTTPS
+fn set_cookie(name: &str, value: &str, max_age: i64, redirect_uri: &str) -> String {
+    let secure = if redirect_uri.starts_with("https://") {
+        "; Secure"
+    } else {
+        ""
+    };
+    format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Lax{}",
+        name, value, max_age, secure
+    )
+}
+
 /// Validate a Kubernetes token and extract the username
 async fn validate_token_and_get_username(token: &str) -> Result<String, String> {
     // Create a Kubernetes client using the operator's service account
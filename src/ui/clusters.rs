@@ -0,0 +1,314 @@
+//! Multi-cluster support for the Web UI.
+//!
+//! `HEADWIND_CLUSTERS` lists the clusters the UI can show as comma-separated
+//! `name:kubeconfig-context` pairs, e.g. `prod:gke_acme_europe-west1_prod,staging:kind-staging`.
+//! Pages and API endpoints take a `cluster` query parameter and talk to that
+//! cluster with a client built from its kubeconfig context. Without
+//! `HEADWIND_CLUSTERS` the UI only shows the cluster it runs in.
+//!
+//! `HEADWIND_CURRENT_CLUSTER` names the cluster this instance runs in. That
+//! cluster uses the in-cluster client and the local approval API, and its name
+//! is added as a `cluster` label to every metric.
+
+use axum::body::{Body, to_bytes};
+use axum::http::{Request, StatusCode, header::CONTENT_TYPE};
+use kube::config::KubeConfigOptions;
+use kube::{Client, Config};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use thiserror::Error;
+use tower::ServiceExt;
+use tracing::info;
+
+use crate::approval::ApprovalState;
+use crate::approval::rbac::REMOTE_USER_HEADER;
+
+/// Environment variable listing the clusters
+pub const CLUSTERS_ENV: &str = "HEADWIND_CLUSTERS";
+
+/// Environment variable naming the cluster this instance runs in
+pub const CURRENT_CLUSTER_ENV: &str = "HEADWIND_CURRENT_CLUSTER";
+
+/// Base URL of the approval API of the local cluster
+const LOCAL_APPROVAL_API: &str = "http://localhost:8081";
+
+static CLUSTERS: OnceCell<Clusters> = OnceCell::new();
+
+#[derive(Debug, Error)]
+pub enum ClusterError {
+    #[error("invalid {CLUSTERS_ENV} entry '{0}', expected name:kubeconfig-context")]
+    InvalidEntry(String),
+    #[error("cluster '{0}' is listed more than once in {CLUSTERS_ENV}")]
+    Duplicate(String),
+    #[error("unknown cluster '{0}'")]
+    Unknown(String),
+    #[error("failed to create client for cluster '{0}': {1}")]
+    Client(String, String),
+    #[error("failed to create Kubernetes client: {0}")]
+    Default(#[from] kube::Error),
+}
+
+impl ClusterError {
+    /// Status for responses failing with this error
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ClusterError::Unknown(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// `cluster` query parameter accepted by UI pages and API endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct ClusterQuery {
+    pub cluster: Option<String>,
+}
+
+/// A cluster from `HEADWIND_CLUSTERS`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterEntry {
+    pub name: String,
+    pub context: String,
+}
+
+/// Parse `name:context` pairs. Only the first `:` separates the name, since
+/// contexts such as EKS ARNs contain colons themselves. Names may contain
+/// letters, digits, `-`, `_` and `.`.
+pub fn parse_clusters(value: &str) -> Result<Vec<ClusterEntry>, ClusterError> {
+    let mut entries: Vec<ClusterEntry> = Vec::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, context) = entry
+            .split_once(':')
+            .map(|(name, context)| (name.trim(), context.trim()))
+            .filter(|(name, context)| is_valid_name(name) && !context.is_empty())
+            .ok_or_else(|| ClusterError::InvalidEntry(entry.to_string()))?;
+        if entries.iter().any(|e| e.name == name) {
+            return Err(ClusterError::Duplicate(name.to_string()));
+        }
+        entries.push(ClusterEntry {
+            name: name.to_string(),
+            context: context.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Names end up in URLs, so only allow characters that need no escaping
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Name of the cluster this instance runs in, when multi-cluster mode is on
+pub fn current_cluster() -> Option<String> {
+    std::env::var(CLUSTERS_ENV)
+        .ok()
+        .filter(|v| !v.trim().is_empty())?;
+    std::env::var(CURRENT_CLUSTER_ENV)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Clients for every cluster in `HEADWIND_CLUSTERS`
+struct Clusters {
+    clients: BTreeMap<String, Client>,
+    /// Shown when no `cluster` parameter is given
+    default: String,
+    /// The cluster this instance runs in
+    local: Option<String>,
+}
+
+/// Build a client per cluster from `HEADWIND_CLUSTERS`. Does nothing when it
+/// is unset.
+pub async fn init_clusters() -> Result<(), ClusterError> {
+    let entries = parse_clusters(&std::env::var(CLUSTERS_ENV).unwrap_or_default())?;
+    let Some(first) = entries.first() else {
+        return Ok(());
+    };
+    let local = current_cluster().filter(|name| entries.iter().any(|e| &e.name == name));
+    let default = local.clone().unwrap_or_else(|| first.name.clone());
+
+    let mut clients = BTreeMap::new();
+    for entry in &entries {
+        let client = if local.as_ref() == Some(&entry.name) {
+            Client::try_default().await?
+        } else {
+            client_for_context(&entry.context)
+                .await
+                .map_err(|e| ClusterError::Client(entry.name.clone(), e.to_string()))?
+        };
+        info!(
+            "Web UI cluster {} uses kubeconfig context {}",
+            entry.name, entry.context
+        );
+        clients.insert(entry.name.clone(), client);
+    }
+
+    CLUSTERS
+        .set(Clusters {
+            clients,
+            default,
+            local,
+        })
+        .ok();
+    Ok(())
+}
+
+async fn client_for_context(context: &str) -> anyhow::Result<Client> {
+    let options = KubeConfigOptions {
+        context: Some(context.to_string()),
+        ..Default::default()
+    };
+    let config = Config::from_kubeconfig(&options).await?;
+    Ok(Client::try_from(config)?)
+}
+
+/// Names of the configured clusters, empty outside multi-cluster mode
+pub fn cluster_names() -> Vec<String> {
+    CLUSTERS
+        .get()
+        .map(|c| c.clients.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// The cluster a request is for: `cluster` if given, else the default cluster.
+/// `None` outside multi-cluster mode.
+pub fn selected_cluster(cluster: Option<&str>) -> Option<String> {
+    let clusters = CLUSTERS.get()?;
+    let cluster = cluster.filter(|c| !c.is_empty());
+    Some(cluster.unwrap_or(&clusters.default).to_string())
+}
+
+/// Client for `cluster`, or for the default cluster when no cluster is given
+pub async fn client_for(cluster: Option<&str>) -> Result<Client, ClusterError> {
+    match (CLUSTERS.get(), cluster.filter(|c| !c.is_empty())) {
+        (Some(clusters), cluster) => {
+            let name = cluster.unwrap_or(&clusters.default);
+            clusters
+                .clients
+                .get(name)
+                .cloned()
+                .ok_or_else(|| ClusterError::Unknown(name.to_string()))
+        },
+        (None, Some(name)) => Err(ClusterError::Unknown(name.to_string())),
+        (None, None) => Ok(Client::try_default().await?),
+    }
+}
+
+/// POST `body` to the approval API `path` of `cluster`.
+///
+/// The local cluster is reached over HTTP as before; for other clusters the
+/// approval router runs in this process with that cluster's client. Returns
+/// the status and the JSON response, `None` if the response was not JSON.
+pub async fn post_approval_api(
+    cluster: Option<&str>,
+    path: &str,
+    remote_user: Option<&str>,
+    body: &serde_json::Value,
+) -> Result<(StatusCode, Option<serde_json::Value>), String> {
+    let cluster = cluster.filter(|c| !c.is_empty());
+    let remote = match CLUSTERS.get() {
+        Some(clusters) => {
+            let name = cluster.unwrap_or(&clusters.default);
+            if clusters.local.as_deref() == Some(name) {
+                None
+            } else {
+                Some(client_for(Some(name)).await.map_err(|e| e.to_string())?)
+            }
+        },
+        None => match cluster {
+            Some(name) => return Err(ClusterError::Unknown(name.to_string()).to_string()),
+            None => None,
+        },
+    };
+
+    let Some(client) = remote else {
+        let mut request = reqwest::Client::new()
+            .post(format!("{}{}", LOCAL_APPROVAL_API, path))
+            .json(body);
+        if let Some(user) = remote_user {
+            request = request.header(REMOTE_USER_HEADER, user);
+        }
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status();
+        return Ok((status, response.json().await.ok()));
+    };
+
+    let mut request = Request::post(path).header(CONTENT_TYPE, "application/json");
+    if let Some(user) = remote_user {
+        request = request.header(REMOTE_USER_HEADER, user);
+    }
+    let request = request
+        .body(Body::from(body.to_string()))
+        .map_err(|e| e.to_string())?;
+    let response = crate::approval::router(ApprovalState { client })
+        .oneshot(request)
+        .await
+        .map_err(|e| e.to_string())?;
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((status, serde_json::from_slice(&bytes).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clusters() {
+        let clusters = parse_clusters(
+            "prod:gke_acme_europe-west1_prod, eks:arn:aws:eks:eu-west-1:123456789012:cluster/eks,",
+        )
+        .unwrap();
+        assert_eq!(
+            clusters,
+            vec![
+                ClusterEntry {
+                    name: "prod".to_string(),
+                    context: "gke_acme_europe-west1_prod".to_string(),
+                },
+                ClusterEntry {
+                    name: "eks".to_string(),
+                    context: "arn:aws:eks:eu-west-1:123456789012:cluster/eks".to_string(),
+                },
+            ]
+        );
+        assert!(parse_clusters("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_clusters_rejects_invalid_entries() {
+        for value in ["prod", "prod:", ":context", "prod:a,staging", "prod east:a"] {
+            assert!(
+                matches!(parse_clusters(value), Err(ClusterError::InvalidEntry(_))),
+                "{} should be rejected",
+                value
+            );
+        }
+        assert!(matches!(
+            parse_clusters("prod:a,prod:b"),
+            Err(ClusterError::Duplicate(name)) if name == "prod"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_cluster_outside_multi_cluster_mode() {
+        assert!(cluster_names().is_empty());
+        assert_eq!(selected_cluster(Some("prod")), None);
+        // An empty parameter means no cluster was picked
+        assert!(!matches!(
+            client_for(Some("")).await,
+            Err(ClusterError::Unknown(_))
+        ));
+        assert!(matches!(
+            client_for(Some("prod")).await,
+            Err(ClusterError::Unknown(name)) if name == "prod"
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+//! Discussion threads on UpdateRequests.
+//!
+//! Reviewers discuss an update before approving it through
+//! `POST /api/v1/update-requests/{namespace}/{name}/comments`. Comments are
+//! appended to `spec.discussionThread` with a merge patch guarded by the
+//! resource version, so concurrent comments are retried instead of lost.
+
+use crate::metrics::COMMENTS_TOTAL;
+use crate::models::crd::{Comment, UpdateRequest};
+use crate::ui::auth::{AuthMode, UserIdentity};
+use chrono::Utc;
+use kube::Client;
+use kube::api::{Api, Patch, PatchParams};
+use serde::Deserialize;
+use thiserror::Error;
+use tracing::debug;
+
+/// Longest comment body accepted, in bytes
+pub const MAX_COMMENT_BYTES: usize = 4096;
+
+/// Attempts to append a comment before giving up on write conflicts
+const APPEND_ATTEMPTS: usize = 3;
+
+/// Body of `POST /api/v1/update-requests/{namespace}/{name}/comments`
+#[derive(Debug, Deserialize)]
+pub struct NewComment {
+    /// Used only without authentication; otherwise the signed-in user is the author
+    #[serde(default)]
+    pub author: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Error)]
+pub enum CommentError {
+    #[error("Comment body must not be empty")]
+    Empty,
+    #[error("Comment body is {0} bytes, at most {MAX_COMMENT_BYTES} are allowed")]
+    TooLong(usize),
+    #[error("UpdateRequest {0}/{1} not found")]
+    NotFound(String, String),
+    #[error("UpdateRequest {0}/{1} kept changing, try again")]
+    Conflict(String, String),
+    #[error("Kubernetes API error: {0}")]
+    Kube(#[from] kube::Error),
+}
+
+/// Check a comment body is non-blank and at most [`MAX_COMMENT_BYTES`] long
+pub fn validate_body(body: &str) -> Result<(), CommentError> {
+    if body.trim().is_empty() {
+        return Err(CommentError::Empty);
+    }
+    if body.len() > MAX_COMMENT_BYTES {
+        return Err(CommentError::TooLong(body.len()));
+    }
+    Ok(())
+}
+
+/// Author of a comment: the authenticated user, or without authentication
+/// the author given in the request
+pub fn comment_author(user: &UserIdentity, requested: Option<&str>) -> String {
+    match user.auth_mode {
+        AuthMode::None => requested
+            .map(str::trim)
+            .filter(|author| !author.is_empty())
+            .unwrap_or(&user.username)
+            .to_string(),
+        _ => user.username.clone(),
+    }
+}
+
+/// Append `comment` to the discussion thread of an UpdateRequest, returning
+/// the whole thread
+pub async fn append_comment(
+    client: Client,
+    namespace: &str,
+    name: &str,
+    comment: Comment,
+) -> Result<Vec<Comment>, CommentError> {
+    let api: Api<UpdateRequest> = Api::namespaced(client, namespace);
+
+    for attempt in 1..=APPEND_ATTEMPTS {
+        let update_request = api
+            .get_opt(name)
+            .await?
+            .ok_or_else(|| CommentError::NotFound(namespace.to_string(), name.to_string()))?;
+
+        let mut thread = update_request.spec.discussion_thread;
+        thread.push(comment.clone());
+
+        let patch = serde_json::json!({
+            "metadata": {
+                "resourceVersion": update_request.metadata.resource_version,
+            },
+            "spec": {
+                "discussionThread": thread,
+            },
+        });
+        match api
+            .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+        {
+            Ok(_) => {
+                COMMENTS_TOTAL.inc();
+                return Ok(thread);
+            },
+            Err(kube::Error::Api(e)) if e.code == 409 && attempt < APPEND_ATTEMPTS => {
+                debug!(
+                    "UpdateRequest {}/{} changed while commenting, retrying",
+                    namespace, name
+                );
+            },
+            Err(kube::Error::Api(e)) if e.code == 409 => {
+                return Err(CommentError::Conflict(
+                    namespace.to_string(),
+                    name.to_string(),
+                ));
+            },
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!("the last attempt always returns")
+}
+
+/// A comment by `author` written now
+pub fn new_comment(author: String, body: String) -> Comment {
+    Comment {
+        author,
+        body,
+        created_at: Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, FakeApi, api_error};
+    use std::sync::{Arc, Mutex};
+
+    fn update_request() -> UpdateRequest {
+        let mut update_request = testing::update_request("default", "web-update");
+        update_request.metadata.resource_version = Some("1".to_string());
+        update_request
+    }
+
+    /// Client for a fake API server holding one UpdateRequest. PATCH applies
+    /// the discussion thread unless the resource version is stale, and the
+    /// first `conflicts` PATCHes are refused as if another write came first.
+    fn fake_client(stored: Arc<Mutex<UpdateRequest>>, conflicts: usize) -> Client {
+        let conflicts = Mutex::new(conflicts);
+        let api = FakeApi::new(move |request| {
+            let mut current = stored.lock().unwrap();
+            if request.method == http::Method::PATCH {
+                let version = current.metadata.resource_version.clone().unwrap();
+                let next = (version.parse::<u64>().unwrap() + 1).to_string();
+                let mut remaining = conflicts.lock().unwrap();
+                if *remaining > 0 || request.body["metadata"]["resourceVersion"] != version.as_str()
+                {
+                    *remaining = remaining.saturating_sub(1);
+                    current.metadata.resource_version = Some(next);
+                    return api_error(http::StatusCode::CONFLICT, "Conflict", "conflict");
+                }
+                current.spec.discussion_thread =
+                    serde_json::from_value(request.body["spec"]["discussionThread"].clone())
+                        .unwrap();
+                current.metadata.resource_version = Some(next);
+            }
+            (
+                http::StatusCode::OK,
+                serde_json::to_value(&*current).unwrap(),
+            )
+        });
+        api.client()
+    }
+
+    #[test]
+    fn test_validate_body() {
+        assert!(validate_body("Tested in staging, LGTM").is_ok());
+        assert!(matches!(validate_body(""), Err(CommentError::Empty)));
+        assert!(matches!(validate_body(" \n\t"), Err(CommentError::Empty)));
+
+        assert!(validate_body(&"a".repeat(MAX_COMMENT_BYTES)).is_ok());
+        assert!(matches!(
+            validate_body(&"a".repeat(MAX_COMMENT_BYTES + 1)),
+            Err(CommentError::TooLong(4097))
+        ));
+        // The limit counts bytes, not characters
+        assert!(matches!(
+            validate_body(&"é".repeat(MAX_COMMENT_BYTES / 2 + 1)),
+            Err(CommentError::TooLong(_))
+        ));
+    }
+
+    #[test]
+    fn test_comment_author() {
+        let anonymous = UserIdentity {
+            username: "web-ui-user".to_string(),
+            auth_mode: AuthMode::None,
+        };
+        assert_eq!(comment_author(&anonymous, Some("alice")), "alice");
+        assert_eq!(comment_author(&anonymous, Some("  ")), "web-ui-user");
+        assert_eq!(comment_author(&anonymous, None), "web-ui-user");
+
+        // Authenticated users can't comment in someone else's name
+        let signed_in = UserIdentity {
+            username: "bob@example.com".to_string(),
+            auth_mode: AuthMode::Oidc,
+        };
+        assert_eq!(comment_author(&signed_in, Some("alice")), "bob@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_append_and_retrieve_comments() {
+        let stored = Arc::new(Mutex::new(update_request()));
+        let client = fake_client(stored.clone(), 0);
+
+        let first = new_comment("alice".to_string(), "Tested in staging, LGTM".to_string());
+        let thread = append_comment(client.clone(), "default", "web-update", first.clone())
+            .await
+            .unwrap();
+        assert_eq!(thread, vec![first.clone()]);
+
+        let second = new_comment("bob".to_string(), "Ship it".to_string());
+        append_comment(client.clone(), "default", "web-update", second.clone())
+            .await
+            .unwrap();
+
+        let api: Api<UpdateRequest> = Api::namespaced(client, "default");
+        let update_request = api.get("web-update").await.unwrap();
+        assert_eq!(update_request.spec.discussion_thread, vec![first, second]);
+    }
+
+    #[tokio::test]
+    async fn test_append_comment_retries_conflicts() {
+        let stored = Arc::new(Mutex::new(update_request()));
+        let comment = new_comment("alice".to_string(), "LGTM".to_string());
+
+        let client = fake_client(stored.clone(), APPEND_ATTEMPTS - 1);
+        append_comment(client, "default", "web-update", comment.clone())
+            .await
+            .unwrap();
+        assert_eq!(stored.lock().unwrap().spec.discussion_thread.len(), 1);
+
+        let client = fake_client(stored.clone(), APPEND_ATTEMPTS);
+        assert!(matches!(
+            append_comment(client, "default", "web-update", comment).await,
+            Err(CommentError::Conflict(..))
+        ));
+        assert_eq!(stored.lock().unwrap().spec.discussion_thread.len(), 1);
+    }
+}
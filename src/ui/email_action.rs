@@ -0,0 +1,509 @@
+//! Approve or reject UpdateRequests from links in notification emails.
+//!
+//! A link carries a token signed with `HEADWIND_EMAIL_ACTION_SECRET`. The token
+//! names the UpdateRequest by UID, the action, an expiry at most 48 hours
+//! ahead, and the recipient's email, which is recorded as the approver and
+//! sent to the approval API as `X-Remote-User`. Each token works once: it is
+//! used up when the approval API accepts the action, and can be retried if
+//! the action fails.
+
+use super::auth::AuditLogEntry;
+use crate::approval::rbac::REMOTE_USER_HEADER;
+use crate::config::features::features;
+use crate::models::crd::UpdateRequest;
+use anyhow::{Result, anyhow, bail};
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Json},
+};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use kube::{Api, Client, ResourceExt};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// Path of the email action endpoint, which bypasses Web UI authentication
+pub const EMAIL_ACTION_PATH: &str = "/api/v1/email-action";
+
+/// Longest time a link stays valid, in seconds
+pub const MAX_TOKEN_LIFETIME_SECS: i64 = 48 * 60 * 60;
+
+/// Tokens remembered for replay protection; the earliest to expire are
+/// forgotten first when full
+const USED_TOKEN_CAPACITY: usize = 10_000;
+
+lazy_static! {
+    static ref USED_TOKENS: Mutex<UsedTokens> = Mutex::new(UsedTokens::new(USED_TOKEN_CAPACITY));
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailAction {
+    Approve,
+    Reject,
+}
+
+impl EmailAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Approve => "approve",
+            Self::Reject => "reject",
+        }
+    }
+}
+
+impl fmt::Display for EmailAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EmailAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "approve" => Ok(Self::Approve),
+            "reject" => Ok(Self::Reject),
+            other => bail!("Unknown action {}", other),
+        }
+    }
+}
+
+/// Contents of an email action token
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailActionToken {
+    /// UID of the UpdateRequest
+    pub uid: String,
+    pub action: EmailAction,
+    /// Expiry as a Unix timestamp
+    pub exp: i64,
+    /// Recipient email, recorded as the approver or rejecter
+    pub claim: String,
+}
+
+impl EmailActionToken {
+    /// `payload.signature`, both base64url encoded
+    pub fn sign(&self, secret: &[u8]) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default());
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(secret).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    /// Decode `token`, checking its signature and that it is unexpired and
+    /// not valid for longer than [`MAX_TOKEN_LIFETIME_SECS`]
+    pub fn verify(secret: &[u8], token: &str, now: i64) -> Result<Self> {
+        let (payload, signature) = token
+            .split_once('.')
+            .ok_or_else(|| anyhow!("Malformed token"))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| anyhow!("Malformed token"))?;
+        let token: Self = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| anyhow!("Malformed token"))?;
+
+        token
+            .mac(secret)
+            .verify_slice(&signature)
+            .map_err(|_| anyhow!("Invalid token signature"))?;
+        if token.exp < now {
+            bail!("Link has expired");
+        }
+        if token.exp - now > MAX_TOKEN_LIFETIME_SECS {
+            bail!("Link is valid for longer than 48 hours");
+        }
+        Ok(token)
+    }
+
+    /// What the signature covers, `{uid}:{action}:{exp}:{claim}`. Tokens
+    /// encoding the same message differently are the same link, so this is
+    /// also what replay protection remembers.
+    pub fn signed_message(&self) -> String {
+        format!("{}:{}:{}:{}", self.uid, self.action, self.exp, self.claim)
+    }
+
+    /// HMAC-SHA256 over [`Self::signed_message`]
+    fn mac(&self, secret: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+        mac.update(self.signed_message().as_bytes());
+        mac
+    }
+}
+
+/// Signing secret from `HEADWIND_EMAIL_ACTION_SECRET`; email actions are off without it
+pub fn secret_from_env() -> Option<Vec<u8>> {
+    std::env::var("HEADWIND_EMAIL_ACTION_SECRET")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .map(String::into_bytes)
+}
+
+/// Approve and reject links for `recipient`, valid for 48 hours from `now`
+pub fn action_links(
+    base_url: &str,
+    secret: &[u8],
+    update_request_uid: &str,
+    recipient: &str,
+    now: i64,
+) -> (String, String) {
+    let link = |action: EmailAction| {
+        let token = EmailActionToken {
+            uid: update_request_uid.to_string(),
+            action,
+            exp: now + MAX_TOKEN_LIFETIME_SECS,
+            claim: recipient.to_string(),
+        };
+        format!(
+            "{}{}?token={}&action={}",
+            base_url.trim_end_matches('/'),
+            EMAIL_ACTION_PATH,
+            token.sign(secret),
+            action
+        )
+    };
+    (link(EmailAction::Approve), link(EmailAction::Reject))
+}
+
+/// Signed messages of the tokens that have been used, kept until they expire
+pub struct UsedTokens {
+    expiries: HashMap<String, i64>,
+    capacity: usize,
+}
+
+impl UsedTokens {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            expiries: HashMap::new(),
+            capacity,
+        }
+    }
+
+    /// Record `token`, returning false if it was already used
+    pub fn insert(&mut self, token: &str, exp: i64, now: i64) -> bool {
+        if self.expiries.contains_key(token) {
+            return false;
+        }
+
+        self.expiries.retain(|_, expiry| *expiry >= now);
+        while self.expiries.len() >= self.capacity {
+            let Some(earliest) = self
+                .expiries
+                .iter()
+                .min_by_key(|(_, expiry)| **expiry)
+                .map(|(token, _)| token.clone())
+            else {
+                break;
+            };
+            self.expiries.remove(&earliest);
+        }
+
+        self.expiries.insert(token.to_string(), exp);
+        true
+    }
+
+    /// Forget `token`, so it can be used again
+    pub fn remove(&mut self, token: &str) {
+        self.expiries.remove(token);
+    }
+
+    pub fn len(&self) -> usize {
+        self.expiries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expiries.is_empty()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailActionQuery {
+    token: String,
+    action: String,
+}
+
+/// `GET /api/v1/email-action?token=...&action=approve|reject`
+pub async fn email_action(Query(query): Query<EmailActionQuery>) -> impl IntoResponse {
+    let error = |status: StatusCode, message: String| {
+        (status, Json(serde_json::json!({ "error": message })))
+    };
+
+    if !features().email_approval {
+        return error(
+            StatusCode::NOT_FOUND,
+            "Email approval is not enabled".to_string(),
+        );
+    }
+    let Some(secret) = secret_from_env() else {
+        return error(
+            StatusCode::NOT_FOUND,
+            "Email actions are not configured".to_string(),
+        );
+    };
+
+    let now = Utc::now().timestamp();
+    let token = match EmailActionToken::verify(&secret, &query.token, now) {
+        Ok(token) => token,
+        Err(e) => {
+            warn!("Rejected email action token: {}", e);
+            return error(StatusCode::UNAUTHORIZED, e.to_string());
+        },
+    };
+    if query.action != token.action.as_str() {
+        return error(
+            StatusCode::BAD_REQUEST,
+            format!("Link is for {}, not {}", token.action, query.action),
+        );
+    }
+
+    let update_request = match find_by_uid(&token.uid).await {
+        Ok(Some(update_request)) => update_request,
+        Ok(None) => {
+            return error(
+                StatusCode::NOT_FOUND,
+                "UpdateRequest no longer exists".to_string(),
+            );
+        },
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+
+    // Reserve the token so concurrent clicks cannot both act; it is released
+    // below unless the approval API accepts the action
+    let signed_message = token.signed_message();
+    if !USED_TOKENS
+        .lock()
+        .unwrap()
+        .insert(&signed_message, token.exp, now)
+    {
+        return error(
+            StatusCode::CONFLICT,
+            "This link has already been used".to_string(),
+        );
+    }
+
+    let namespace = update_request.namespace().unwrap_or_default();
+    let name = update_request.name_any();
+    info!(
+        "Email {} of UpdateRequest {}/{} by {}",
+        token.action, namespace, name, token.claim
+    );
+
+    let mut body = serde_json::json!({ "approver": token.claim });
+    if token.action == EmailAction::Reject {
+        body["reason"] = "Rejected via email".into();
+    }
+    let url = format!(
+        "http://localhost:8081/api/v1/updates/{}/{}/{}",
+        namespace, name, token.action
+    );
+
+    // The recipient is the caller, so role-gated approvals are reviewed for them
+    let (status, response) = match reqwest::Client::new()
+        .post(&url)
+        .header(REMOTE_USER_HEADER, &token.claim)
+        .json(&body)
+        .send()
+        .await
+    {
+        Ok(response) => {
+            let status = response.status();
+            let body = response
+                .json::<serde_json::Value>()
+                .await
+                .unwrap_or_default();
+            (
+                StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY),
+                body,
+            )
+        },
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            serde_json::json!({ "error": format!("Approval API unreachable: {}", e) }),
+        ),
+    };
+    if !status.is_success() {
+        USED_TOKENS.lock().unwrap().remove(&signed_message);
+    }
+
+    AuditLogEntry::new(
+        token.claim,
+        format!("email_{}", token.action),
+        "UpdateRequest".to_string(),
+        namespace,
+        name,
+        if status.is_success() {
+            "success"
+        } else {
+            "failed"
+        }
+        .to_string(),
+        response
+            .get("error")
+            .and_then(|e| e.as_str())
+            .map(String::from),
+    )
+    .log();
+
+    (status, Json(response))
+}
+
+async fn find_by_uid(uid: &str) -> Result<Option<UpdateRequest>> {
+    let client = Client::try_default().await?;
+    let api: Api<UpdateRequest> = Api::all(client);
+    Ok(api
+        .list(&Default::default())
+        .await?
+        .items
+        .into_iter()
+        .find(|ur| ur.uid().as_deref() == Some(uid)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"email-secret";
+    const NOW: i64 = 1_700_000_000;
+
+    fn token(action: EmailAction, exp: i64) -> EmailActionToken {
+        EmailActionToken {
+            uid: "2b4f6c1e-uid".to_string(),
+            action,
+            exp,
+            claim: "alice@example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let token = token(EmailAction::Approve, NOW + 3600);
+        let signed = token.sign(SECRET);
+
+        assert_eq!(
+            EmailActionToken::verify(SECRET, &signed, NOW).unwrap(),
+            token
+        );
+        assert!(EmailActionToken::verify(b"other-secret", &signed, NOW).is_err());
+        assert!(EmailActionToken::verify(SECRET, "garbage", NOW).is_err());
+    }
+
+    #[test]
+    fn test_tampered_claim_is_rejected() {
+        let signed = token(EmailAction::Approve, NOW + 3600).sign(SECRET);
+        let (_, signature) = signed.split_once('.').unwrap();
+
+        let mut forged = token(EmailAction::Approve, NOW + 3600);
+        forged.claim = "mallory@example.com".to_string();
+        let forged_payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(&forged).unwrap());
+
+        let err =
+            EmailActionToken::verify(SECRET, &format!("{}.{}", forged_payload, signature), NOW)
+                .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid token signature");
+    }
+
+    #[test]
+    fn test_expiry() {
+        let expired = token(EmailAction::Reject, NOW - 1).sign(SECRET);
+        assert_eq!(
+            EmailActionToken::verify(SECRET, &expired, NOW)
+                .unwrap_err()
+                .to_string(),
+            "Link has expired"
+        );
+
+        let too_long = token(EmailAction::Reject, NOW + MAX_TOKEN_LIFETIME_SECS + 1).sign(SECRET);
+        assert!(EmailActionToken::verify(SECRET, &too_long, NOW).is_err());
+
+        let longest = token(EmailAction::Reject, NOW + MAX_TOKEN_LIFETIME_SECS).sign(SECRET);
+        assert!(EmailActionToken::verify(SECRET, &longest, NOW).is_ok());
+    }
+
+    #[test]
+    fn test_action_links() {
+        let (approve, reject) = action_links(
+            "https://headwind.example.com/",
+            SECRET,
+            "2b4f6c1e-uid",
+            "alice@example.com",
+            NOW,
+        );
+
+        assert!(approve.starts_with("https://headwind.example.com/api/v1/email-action?token="));
+        assert!(approve.ends_with("&action=approve"));
+        assert!(reject.ends_with("&action=reject"));
+
+        let token = approve
+            .split_once("token=")
+            .and_then(|(_, rest)| rest.split_once('&'))
+            .map(|(token, _)| token)
+            .unwrap();
+        let token = EmailActionToken::verify(SECRET, token, NOW).unwrap();
+        assert_eq!(token.action, EmailAction::Approve);
+        assert_eq!(token.exp, NOW + MAX_TOKEN_LIFETIME_SECS);
+        assert_eq!(token.claim, "alice@example.com");
+    }
+
+    #[test]
+    fn test_reencoded_token_is_the_same_link() {
+        let signed = token(EmailAction::Approve, NOW + 60).sign(SECRET);
+        let (payload, signature) = signed.split_once('.').unwrap();
+
+        // The same fields in another JSON encoding keep a valid signature
+        let json: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+        let reencoded = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec_pretty(&json).unwrap()),
+            signature
+        );
+        assert_ne!(reencoded, signed);
+
+        let first = EmailActionToken::verify(SECRET, &signed, NOW).unwrap();
+        let second = EmailActionToken::verify(SECRET, &reencoded, NOW).unwrap();
+        let mut used = UsedTokens::new(10);
+        assert!(used.insert(&first.signed_message(), first.exp, NOW));
+        assert!(!used.insert(&second.signed_message(), second.exp, NOW));
+    }
+
+    #[test]
+    fn test_used_tokens() {
+        let mut used = UsedTokens::new(2);
+        assert!(used.insert("a", NOW + 10, NOW));
+        assert!(!used.insert("a", NOW + 10, NOW));
+
+        // Expired entries are dropped
+        assert!(used.insert("b", NOW + 100, NOW + 20));
+        assert_eq!(used.len(), 1);
+
+        // At capacity the earliest to expire is forgotten
+        assert!(used.insert("c", NOW + 200, NOW + 20));
+        assert!(used.insert("d", NOW + 300, NOW + 20));
+        assert_eq!(used.len(), 2);
+        assert!(!used.insert("d", NOW + 300, NOW + 20));
+        assert!(used.insert("b", NOW + 100, NOW + 20));
+    }
+
+    #[test]
+    fn test_released_token_can_be_used_again() {
+        let mut used = UsedTokens::new(10);
+        assert!(used.insert("a", NOW + 10, NOW));
+
+        // A failed action releases the token for a retry
+        used.remove("a");
+        assert!(used.is_empty());
+        assert!(used.insert("a", NOW + 10, NOW));
+        assert!(!used.insert("a", NOW + 10, NOW));
+    }
+}
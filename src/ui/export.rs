@@ -0,0 +1,324 @@
+//! UpdateRequest audit exports.
+//!
+//! Flattens UpdateRequests into one row each and serializes them as CSV
+//! (RFC 4180) or JSON Lines for `GET /api/v1/update-requests/export`, or
+//! writes complete UpdateRequests for `POST /api/v1/update-requests/import`.
+
+use crate::models::crd::UpdateRequest;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::str::FromStr;
+
+/// Column order of the CSV export, matching the fields of [`ExportRow`]
+const CSV_HEADER: [&str; 12] = [
+    "name",
+    "namespace",
+    "resource_kind",
+    "resource_name",
+    "current_image",
+    "new_image",
+    "policy",
+    "status",
+    "created_at",
+    "approved_by",
+    "rejected_by",
+    "rejection_reason",
+];
+
+/// Output format of an export
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+    /// One complete UpdateRequest, including status, per line, for importing
+    Backup,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::JsonLines | ExportFormat::Backup => "application/x-ndjson",
+        }
+    }
+
+    /// Download file name, e.g. `headwind-export-2024-01-31.csv`
+    pub fn filename(&self, date: NaiveDate) -> String {
+        let (prefix, extension) = match self {
+            ExportFormat::Csv => ("export", "csv"),
+            ExportFormat::JsonLines => ("export", "jsonl"),
+            ExportFormat::Backup => ("backup", "jsonl"),
+        };
+        format!(
+            "headwind-{}-{}.{}",
+            prefix,
+            date.format("%Y-%m-%d"),
+            extension
+        )
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" | "jsonl" | "ndjson" => Ok(ExportFormat::JsonLines),
+            "json-lines" => Ok(ExportFormat::Backup),
+            _ => Err(anyhow!(
+                "Unknown export format '{}', expected csv, json or json-lines",
+                s
+            )),
+        }
+    }
+}
+
+/// One UpdateRequest, flattened for export
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ExportRow {
+    pub name: String,
+    pub namespace: String,
+    pub resource_kind: String,
+    pub resource_name: String,
+    pub current_image: String,
+    pub new_image: String,
+    pub policy: String,
+    pub status: String,
+    /// RFC 3339 creation timestamp
+    pub created_at: String,
+    pub approved_by: Option<String>,
+    pub rejected_by: Option<String>,
+    pub rejection_reason: Option<String>,
+}
+
+impl From<&UpdateRequest> for ExportRow {
+    fn from(ur: &UpdateRequest) -> Self {
+        let metadata = &ur.metadata;
+        let spec = &ur.spec;
+        let status = ur.status.as_ref();
+
+        ExportRow {
+            name: metadata.name.clone().unwrap_or_default(),
+            namespace: metadata.namespace.clone().unwrap_or_default(),
+            resource_kind: spec.target_ref.kind.clone(),
+            resource_name: spec.target_ref.name.clone(),
+            current_image: spec.current_image.clone(),
+            new_image: spec.new_image.clone(),
+            policy: format!("{:?}", spec.policy),
+            status: status
+                .map(|s| format!("{:?}", s.phase))
+                .unwrap_or_else(|| "Pending".to_string()),
+            created_at: metadata
+                .creation_timestamp
+                .as_ref()
+                .map(|ts| ts.0.to_rfc3339())
+                .unwrap_or_default(),
+            approved_by: status.and_then(|s| s.approved_by.clone()),
+            rejected_by: status.and_then(|s| s.rejected_by.clone()),
+            rejection_reason: status
+                .filter(|s| s.rejected_by.is_some())
+                .and_then(|s| s.message.clone()),
+        }
+    }
+}
+
+/// Parse the `since` filter, either a date (`2024-01-01`, midnight UTC) or an RFC 3339 timestamp
+pub fn parse_since(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(Default::default()).and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|ts| ts.with_timezone(&Utc))
+        .map_err(|_| {
+            anyhow!(
+                "Invalid since '{}', expected YYYY-MM-DD or an RFC 3339 timestamp",
+                value
+            )
+        })
+}
+
+/// Serialize `rows` in `format`. Backups are written from the UpdateRequests
+/// themselves with [`backup_line`].
+pub fn render(rows: &[ExportRow], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Csv => to_csv(rows),
+        ExportFormat::JsonLines | ExportFormat::Backup => to_json_lines(rows),
+    }
+}
+
+/// The UpdateRequest as one newline-terminated JSON line, without managed fields
+pub fn backup_line(update_request: &UpdateRequest) -> Option<String> {
+    let mut update_request = update_request.clone();
+    update_request.metadata.managed_fields = None;
+    serde_json::to_string(&update_request)
+        .ok()
+        .map(|line| line + "\n")
+}
+
+/// CSV with a header line, CRLF line endings and RFC 4180 quoting
+pub fn to_csv(rows: &[ExportRow]) -> String {
+    let mut out = csv_line(CSV_HEADER.iter().copied());
+    for row in rows {
+        out.push_str(&csv_line([
+            row.name.as_str(),
+            &row.namespace,
+            &row.resource_kind,
+            &row.resource_name,
+            &row.current_image,
+            &row.new_image,
+            &row.policy,
+            &row.status,
+            &row.created_at,
+            row.approved_by.as_deref().unwrap_or_default(),
+            row.rejected_by.as_deref().unwrap_or_default(),
+            row.rejection_reason.as_deref().unwrap_or_default(),
+        ]));
+    }
+    out
+}
+
+fn csv_line<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let mut line = fields
+        .into_iter()
+        .map(csv_field)
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Quote a field if it contains a delimiter, quote or line break, doubling inner quotes
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// One JSON object per line, newline-terminated
+pub fn to_json_lines(rows: &[ExportRow]) -> String {
+    rows.iter()
+        .filter_map(|row| serde_json::to_string(row).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(current_image: &str, rejection_reason: Option<&str>) -> ExportRow {
+        ExportRow {
+            name: "web-1-2-0".to_string(),
+            namespace: "production".to_string(),
+            resource_kind: "Deployment".to_string(),
+            resource_name: "web".to_string(),
+            current_image: current_image.to_string(),
+            new_image: "registry.io/web:1.2.0".to_string(),
+            policy: "Minor".to_string(),
+            status: "Rejected".to_string(),
+            created_at: "2024-01-02T03:04:05+00:00".to_string(),
+            approved_by: None,
+            rejected_by: rejection_reason.map(|_| "alice".to_string()),
+            rejection_reason: rejection_reason.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_csv_header_and_plain_row() {
+        let csv = to_csv(&[row("registry.io/web:1.1.0", None)]);
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+
+        assert_eq!(lines[0], CSV_HEADER.join(","));
+        assert_eq!(
+            lines[1],
+            "web-1-2-0,production,Deployment,web,registry.io/web:1.1.0,registry.io/web:1.2.0,Minor,Rejected,2024-01-02T03:04:05+00:00,,,"
+        );
+        assert_eq!(lines[2], "");
+    }
+
+    #[test]
+    fn test_csv_quotes_images_with_commas() {
+        let csv = to_csv(&[row("registry.io/web:1.1.0,extra", None)]);
+        let line = csv.split("\r\n").nth(1).unwrap();
+
+        assert!(line.contains(",\"registry.io/web:1.1.0,extra\","));
+        // Still twelve columns once quoted fields are accounted for
+        assert_eq!(line.matches(',').count(), 12);
+    }
+
+    #[test]
+    fn test_csv_escapes_quotes_and_newlines() {
+        let csv = to_csv(&[row(
+            "registry.io/web:1.1.0",
+            Some("breaks \"auth\", see\nticket"),
+        )]);
+
+        assert!(csv.ends_with(",alice,\"breaks \"\"auth\"\", see\nticket\"\r\n"));
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let rows = [
+            row("registry.io/web:1.1.0,extra", None),
+            row("registry.io/web:1.0.0", Some("no")),
+        ];
+        let output = to_json_lines(&rows);
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["current_image"], "registry.io/web:1.1.0,extra");
+        assert_eq!(first["approved_by"], serde_json::Value::Null);
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["rejection_reason"], "no");
+    }
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(
+            parse_since("2024-01-01").unwrap().to_rfc3339(),
+            "2024-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_since("2024-01-01T12:00:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-01-01T10:00:00+00:00"
+        );
+        assert!(parse_since("last week").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!("CSV".parse::<ExportFormat>().unwrap(), ExportFormat::Csv);
+        assert_eq!(
+            "json".parse::<ExportFormat>().unwrap(),
+            ExportFormat::JsonLines
+        );
+        assert_eq!(
+            "json-lines".parse::<ExportFormat>().unwrap(),
+            ExportFormat::Backup
+        );
+        assert!("xml".parse::<ExportFormat>().is_err());
+
+        let date = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        assert_eq!(
+            ExportFormat::Csv.filename(date),
+            "headwind-export-2024-01-31.csv"
+        );
+        assert_eq!(
+            ExportFormat::JsonLines.filename(date),
+            "headwind-export-2024-01-31.jsonl"
+        );
+        assert_eq!(
+            ExportFormat::Backup.filename(date),
+            "headwind-backup-2024-01-31.jsonl"
+        );
+    }
+}
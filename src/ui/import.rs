@@ -0,0 +1,320 @@
+//! UpdateRequest imports for disaster recovery.
+//!
+//! Recreates UpdateRequests from the JSON Lines written by
+//! `GET /api/v1/update-requests/export?format=json-lines`. Only UpdateRequests
+//! in a terminal phase are imported so nothing is applied again, and each
+//! keeps the UID it had in the exporting cluster in `headwind.sh/original-uid`.
+
+use crate::models::crd::{UpdatePhase, UpdateRequest};
+use crate::models::policy::annotations;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use kube::api::{DeleteParams, ListParams, ObjectMeta, Patch, PatchParams, PostParams};
+use kube::{Api, Client, ResourceExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// Request body limit of the import endpoint, exports of long histories exceed axum's default
+pub const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Outcome of an import
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    /// UpdateRequests whose original UID already exists in the cluster
+    pub skipped: usize,
+    /// `line N: reason` for every line that could not be imported
+    pub errors: Vec<String>,
+}
+
+/// Whether the phase is final, so recreating the UpdateRequest applies nothing
+pub fn is_terminal(phase: &UpdatePhase) -> bool {
+    matches!(
+        phase,
+        UpdatePhase::Rejected
+            | UpdatePhase::Completed
+            | UpdatePhase::Failed
+            | UpdatePhase::Expired
+            | UpdatePhase::RolledBack
+    )
+}
+
+/// UID of the UpdateRequest in the cluster it was first created in
+pub fn original_uid(update_request: &UpdateRequest) -> Option<String> {
+    update_request
+        .annotations()
+        .get(annotations::ORIGINAL_UID)
+        .cloned()
+        .or_else(|| update_request.metadata.uid.clone())
+}
+
+/// Parse one exported line into the UpdateRequest to create: metadata reduced
+/// to name, namespace, labels and annotations, marked as imported
+pub fn prepare(line: &str, imported_at: DateTime<Utc>) -> Result<(UpdateRequest, String)> {
+    let exported: UpdateRequest =
+        serde_json::from_str(line).map_err(|e| anyhow!("invalid UpdateRequest: {}", e))?;
+
+    let name = exported
+        .metadata
+        .name
+        .clone()
+        .ok_or_else(|| anyhow!("UpdateRequest has no name"))?;
+    let namespace = exported
+        .metadata
+        .namespace
+        .clone()
+        .ok_or_else(|| anyhow!("UpdateRequest {} has no namespace", name))?;
+    let phase = exported
+        .status
+        .as_ref()
+        .map(|s| s.phase.clone())
+        .unwrap_or_default();
+    if !is_terminal(&phase) {
+        return Err(anyhow!(
+            "UpdateRequest {}/{} is {:?}, only terminal phases are imported",
+            namespace,
+            name,
+            phase
+        ));
+    }
+    let uid = original_uid(&exported)
+        .ok_or_else(|| anyhow!("UpdateRequest {}/{} has no uid", namespace, name))?;
+
+    let mut annotations = exported.metadata.annotations.clone().unwrap_or_default();
+    annotations.insert(
+        annotations::IMPORTED_AT.to_string(),
+        imported_at.to_rfc3339(),
+    );
+    annotations.insert(annotations::ORIGINAL_UID.to_string(), uid.clone());
+
+    // Owner references and finalizers point at objects of the old cluster
+    let update_request = UpdateRequest {
+        metadata: ObjectMeta {
+            name: Some(name),
+            namespace: Some(namespace),
+            labels: exported.metadata.labels.clone(),
+            annotations: Some(annotations),
+            ..Default::default()
+        },
+        spec: exported.spec,
+        status: exported.status,
+    };
+    Ok((update_request, uid))
+}
+
+/// Original UIDs of the UpdateRequests already in the cluster
+async fn existing_uids(client: &Client) -> Result<HashSet<String>> {
+    let api: Api<UpdateRequest> = Api::all(client.clone());
+    Ok(api
+        .list(&ListParams::default())
+        .await?
+        .items
+        .iter()
+        .flat_map(|ur| {
+            [
+                ur.metadata.uid.clone(),
+                ur.annotations().get(annotations::ORIGINAL_UID).cloned(),
+            ]
+        })
+        .flatten()
+        .collect())
+}
+
+async fn create(client: &Client, update_request: &UpdateRequest) -> Result<()> {
+    let namespace = update_request.namespace().unwrap_or_default();
+    let name = update_request.name_any();
+    let api: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
+
+    api.create(&PostParams::default(), update_request)
+        .await
+        .map_err(|e| match e {
+            kube::Error::Api(err) if err.code == 409 => anyhow!(
+                "UpdateRequest {}/{} already exists with a different uid",
+                namespace,
+                name
+            ),
+            e => e.into(),
+        })?;
+
+    // Status is a subresource and ignored on create
+    let status_patch = json!({
+        "apiVersion": "headwind.sh/v1alpha1",
+        "kind": "UpdateRequest",
+        "status": update_request.status
+    });
+    if let Err(e) = api
+        .patch_status(&name, &PatchParams::default(), &Patch::Merge(&status_patch))
+        .await
+    {
+        // Without its status the UpdateRequest would be Pending, and could be
+        // applied again
+        if let Err(delete_error) = api.delete(&name, &DeleteParams::default()).await {
+            warn!(
+                "Failed to delete UpdateRequest {}/{} imported without status: {}",
+                namespace, name, delete_error
+            );
+        }
+        return Err(anyhow!(
+            "Failed to set the status of UpdateRequest {}/{}: {}",
+            namespace,
+            name,
+            e
+        ));
+    }
+    Ok(())
+}
+
+/// Recreate the UpdateRequests in `body`, one JSON object per line
+pub async fn import_update_requests(client: &Client, body: &str) -> Result<ImportSummary> {
+    let mut known = existing_uids(client).await?;
+    let imported_at = Utc::now();
+    let mut summary = ImportSummary::default();
+
+    for (index, line) in body.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = match prepare(line, imported_at) {
+            Ok((_, uid)) if known.contains(&uid) => {
+                summary.skipped += 1;
+                continue;
+            },
+            Ok((update_request, uid)) => create(client, &update_request).await.map(|_| uid),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(uid) => {
+                known.insert(uid);
+                summary.imported += 1;
+            },
+            Err(e) => {
+                warn!("Skipping line {} of UpdateRequest import: {}", index + 1, e);
+                summary.errors.push(format!("line {}: {}", index + 1, e));
+            },
+        }
+    }
+
+    info!(
+        "Imported {} UpdateRequests, skipped {} existing, {} errors",
+        summary.imported,
+        summary.skipped,
+        summary.errors.len()
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{FakeApi, api_error};
+    use http::{Method, StatusCode};
+
+    fn exported(phase: &str, annotations: serde_json::Value) -> String {
+        json!({
+            "apiVersion": "headwind.sh/v1alpha1",
+            "kind": "UpdateRequest",
+            "metadata": {
+                "name": "web-1-26-0",
+                "namespace": "default",
+                "uid": "1b2c3d4e",
+                "resourceVersion": "4711",
+                "creationTimestamp": "2026-01-01T00:00:00Z",
+                "labels": {"app": "web"},
+                "annotations": annotations,
+                "ownerReferences": [{
+                    "apiVersion": "apps/v1",
+                    "kind": "Deployment",
+                    "name": "web",
+                    "uid": "9f8e7d6c"
+                }]
+            },
+            "spec": {
+                "targetRef": {"apiVersion": "apps/v1", "kind": "Deployment", "name": "web", "namespace": "default"},
+                "updateType": "image",
+                "currentImage": "nginx:1.25.0",
+                "newImage": "nginx:1.26.0",
+                "policy": "minor"
+            },
+            "status": {"phase": phase, "approvedBy": "alice"}
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_prepare_marks_imported_and_strips_metadata() {
+        let now = Utc::now();
+        let (update_request, uid) = prepare(&exported("Completed", json!({})), now).unwrap();
+
+        assert_eq!(uid, "1b2c3d4e");
+        let metadata = &update_request.metadata;
+        assert_eq!(metadata.name.as_deref(), Some("web-1-26-0"));
+        assert_eq!(metadata.namespace.as_deref(), Some("default"));
+        assert_eq!(metadata.labels.as_ref().unwrap()["app"], "web");
+        assert!(metadata.uid.is_none());
+        assert!(metadata.resource_version.is_none());
+        assert!(metadata.owner_references.is_none());
+
+        let annotations = metadata.annotations.as_ref().unwrap();
+        assert_eq!(annotations[annotations::ORIGINAL_UID], "1b2c3d4e");
+        assert_eq!(annotations[annotations::IMPORTED_AT], now.to_rfc3339());
+        assert_eq!(
+            update_request.status.unwrap().approved_by.as_deref(),
+            Some("alice")
+        );
+    }
+
+    #[test]
+    fn test_prepare_keeps_uid_of_earlier_import() {
+        let line = exported(
+            "Rejected",
+            json!({annotations::ORIGINAL_UID: "first-cluster-uid"}),
+        );
+        let (_, uid) = prepare(&line, Utc::now()).unwrap();
+        assert_eq!(uid, "first-cluster-uid");
+    }
+
+    #[test]
+    fn test_prepare_rejects_non_terminal_and_invalid_lines() {
+        for phase in ["Pending", "Approved"] {
+            let err = prepare(&exported(phase, json!({})), Utc::now()).unwrap_err();
+            assert!(err.to_string().contains("only terminal phases"), "{}", err);
+        }
+        assert!(prepare("{not json", Utc::now()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failed_status_patch_deletes_created_request() {
+        let stored = prepare(&exported("Completed", json!({})), Utc::now())
+            .unwrap()
+            .0;
+        let object = serde_json::to_value(&stored).unwrap();
+        let api = FakeApi::new(move |request| match request.method {
+            Method::POST | Method::DELETE => (StatusCode::OK, object.clone()),
+            _ => api_error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "InternalError",
+                "etcdserver: request timed out",
+            ),
+        });
+
+        let err = create(&api.client(), &stored).await.unwrap_err();
+        assert!(
+            err.to_string().contains("Failed to set the status"),
+            "{}",
+            err
+        );
+
+        let path = "/apis/headwind.sh/v1alpha1/namespaces/default/updaterequests";
+        assert_eq!(
+            api.calls(),
+            [
+                (Method::POST, path.to_string()),
+                (Method::PATCH, format!("{}/web-1-26-0/status", path)),
+                (Method::DELETE, format!("{}/web-1-26-0", path)),
+            ]
+        );
+    }
+}
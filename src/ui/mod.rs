@@ -1,18 +1,33 @@
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
     routing::{get, post, put},
 };
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tracing::info;
 
 pub mod auth;
+pub mod clusters;
+pub mod comments;
+pub mod email_action;
+pub mod export;
+pub mod import;
+pub mod oidc;
 pub mod routes;
 pub mod static_files;
 pub mod templates;
 
 /// Start the Web UI server
 pub async fn start_ui_server() -> Result<(), Box<dyn std::error::Error>> {
-    let app = create_router();
+    clusters::init_clusters().await?;
+
+    let mut app = create_router();
+    if auth::AuthMode::from_env() == auth::AuthMode::Oidc {
+        let config = oidc::OidcConfig::from_env()?;
+        info!("Web UI authentication via OIDC issuer {}", config.issuer);
+        app = auth::with_oidc(app, Arc::new(oidc::OidcClient::new(config)));
+    }
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 8082));
     info!("Starting Web UI server on {}", addr);
@@ -36,6 +51,10 @@ fn create_router() -> Router {
         .route("/settings", get(routes::settings_page))
         // Observability page
         .route("/observability", get(routes::observability_page))
+        // System status page
+        .route("/status", get(routes::status_page))
+        // Dry-run policy simulation page
+        .route("/simulate", get(routes::simulate_page))
         // Individual update request detail view
         .route("/updates/{namespace}/{name}", get(routes::update_detail))
         // Settings API endpoints
@@ -51,6 +70,16 @@ fn create_router() -> Router {
             "/api/v1/metrics/timeseries/{metric_name}",
             get(routes::get_metrics_timeseries),
         )
+        // Dry-run policy evaluation
+        .route("/api/v1/simulate", get(routes::simulate))
+        .route("/api/v1/explain", get(routes::explain))
+        .route(
+            "/api/v1/policy-evaluation/{namespace}/{resource_kind}/{name}",
+            get(routes::policy_evaluation),
+        )
+        .route("/api/v1/features", get(routes::get_features))
+        // Process status for headwindctl status and the status page
+        .route("/api/v1/status", get(routes::get_status))
         // UpdateRequest API endpoint for counts
         .route("/api/v1/updates", get(routes::list_update_requests))
         // Individual approve/reject operations
@@ -62,6 +91,46 @@ fn create_router() -> Router {
             "/api/v1/updates/{namespace}/{name}/reject",
             post(routes::reject_update),
         )
+        // Audit export of UpdateRequests as CSV or JSON Lines
+        .route(
+            "/api/v1/update-requests/export",
+            get(routes::export_update_requests),
+        )
+        // Recreate UpdateRequests from a json-lines export
+        .route(
+            "/api/v1/update-requests/import",
+            post(routes::import_update_requests)
+                .layer(DefaultBodyLimit::max(import::IMPORT_BODY_LIMIT)),
+        )
+        // Patch that approving an UpdateRequest would apply
+        .route(
+            "/api/v1/update-requests/{namespace}/{name}/preview",
+            get(routes::preview_update),
+        )
+        // Discussion thread on an UpdateRequest
+        .route(
+            "/api/v1/update-requests/{namespace}/{name}/comments",
+            get(routes::list_comments).post(routes::add_comment),
+        )
+        // Manual rollback to the image saved before the last update
+        .route(
+            "/api/v1/rollback/{namespace}/{resource_kind}/{name}",
+            post(routes::trigger_rollback),
+        )
+        // Batch approve/reject operations
+        .route(
+            "/api/v1/batch-updates/{namespace}/{name}/approve",
+            post(routes::approve_batch_update),
+        )
+        .route(
+            "/api/v1/batch-updates/{namespace}/{name}/reject",
+            post(routes::reject_batch_update),
+        )
+        // Approve/reject from signed links in notification emails
+        .route(
+            email_action::EMAIL_ACTION_PATH,
+            get(email_action::email_action),
+        )
         // Bulk operations
         .route("/api/v1/updates/bulk/approve", post(routes::bulk_approve))
         .route("/api/v1/updates/bulk/reject", post(routes::bulk_reject))
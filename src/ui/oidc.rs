@@ -0,0 +1,595 @@
+//! OpenID Connect client for Web UI authentication.
+//!
+//! Implements the Authorization Code flow against the issuer configured with
+//! `HEADWIND_OIDC_ISSUER`: provider discovery, the authorization redirect,
+//! the code exchange, and id_token validation against the provider's JWKS.
+//! Authenticated users get an HMAC-signed session cookie, so the IdP is only
+//! involved when a session starts.
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::env;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+/// Path the IdP redirects back to after login
+pub const CALLBACK_PATH: &str = "/auth/callback";
+
+/// Cookie holding the signed session of an authenticated user
+pub const SESSION_COOKIE: &str = "headwind_session";
+
+/// Cookie holding the state and nonce of a login in progress
+pub const STATE_COOKIE: &str = "headwind_oidc_state";
+
+/// How long fetched JWKS keys are trusted before they are fetched again
+const JWKS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How long a session cookie stays valid
+const SESSION_TTL_SECS: i64 = 8 * 60 * 60;
+
+/// How long a login may take between the redirect and the callback
+const LOGIN_TTL_SECS: i64 = 10 * 60;
+
+/// Allowed clock skew when checking `exp`
+const CLOCK_SKEW_SECS: i64 = 60;
+
+/// OIDC settings from the environment
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL, e.g. `https://accounts.example.com`
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Callback URL registered with the IdP. Derived from the request's
+    /// `Host` and `X-Forwarded-Proto` headers when not set.
+    pub redirect_url: Option<String>,
+}
+
+impl OidcConfig {
+    /// Read `HEADWIND_OIDC_ISSUER`, `HEADWIND_OIDC_CLIENT_ID`,
+    /// `HEADWIND_OIDC_CLIENT_SECRET` and `HEADWIND_OIDC_REDIRECT_URL`
+    pub fn from_env() -> Result<Self> {
+        let required = |name: &str| {
+            env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| anyhow!("{} must be set when HEADWIND_UI_AUTH_MODE=oidc", name))
+        };
+
+        Ok(Self {
+            issuer: required("HEADWIND_OIDC_ISSUER")?,
+            client_id: required("HEADWIND_OIDC_CLIENT_ID")?,
+            client_secret: required("HEADWIND_OIDC_CLIENT_SECRET")?,
+            redirect_url: env::var("HEADWIND_OIDC_REDIRECT_URL")
+                .ok()
+                .filter(|v| !v.is_empty()),
+        })
+    }
+}
+
+/// The parts of `{issuer}/.well-known/openid-configuration` headwind uses
+#[derive(Debug, Clone, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+/// A public key from the provider's JWKS
+#[derive(Debug, Clone, Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    /// RSA modulus and exponent
+    n: Option<String>,
+    e: Option<String>,
+    /// EC curve and coordinates
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+struct CachedJwks {
+    keys: Vec<Jwk>,
+    fetched_at: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+/// `aud` is either a single client ID or a list of them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == client_id,
+            Audience::Many(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+}
+
+/// Validated claims of an id_token
+#[derive(Debug, Clone, Deserialize)]
+pub struct Claims {
+    pub iss: String,
+    pub sub: String,
+    aud: Audience,
+    pub exp: i64,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+}
+
+impl Claims {
+    /// Identity recorded as `approved_by`: the email if present, otherwise the subject
+    pub fn identity(&self) -> &str {
+        self.email
+            .as_deref()
+            .filter(|email| !email.is_empty())
+            .unwrap_or(&self.sub)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Session {
+    identity: String,
+    exp: i64,
+}
+
+/// A login in progress, kept in the state cookie until the callback
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginState {
+    pub state: String,
+    pub nonce: String,
+    /// Path to return to after login
+    pub return_to: String,
+    exp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// OIDC relying party for the Web UI
+pub struct OidcClient {
+    config: OidcConfig,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+    jwks: RwLock<Option<CachedJwks>>,
+    rng: SystemRandom,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            metadata: RwLock::new(None),
+            jwks: RwLock::new(None),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    /// Provider metadata, discovered on first use
+    async fn metadata(&self) -> Result<ProviderMetadata> {
+        if let Some(metadata) = self.metadata.read().await.as_ref() {
+            return Ok(metadata.clone());
+        }
+
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.config.issuer.trim_end_matches('/')
+        );
+        let metadata: ProviderMetadata = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Failed to fetch OIDC discovery document from {}", url))?
+            .json()
+            .await
+            .context("Invalid OIDC discovery document")?;
+
+        if !same_issuer(&metadata.issuer, &self.config.issuer) {
+            return Err(anyhow!(
+                "Discovery document issuer {} does not match {}",
+                metadata.issuer,
+                self.config.issuer
+            ));
+        }
+
+        info!("Discovered OIDC provider {}", metadata.issuer);
+        *self.metadata.write().await = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Signing keys, fetched again after [`JWKS_TTL`] or when `refresh` is set
+    async fn jwks(&self, refresh: bool) -> Result<Vec<Jwk>> {
+        if !refresh
+            && let Some(cached) = self.jwks.read().await.as_ref()
+            && cached.fetched_at.elapsed() < JWKS_TTL
+        {
+            return Ok(cached.keys.clone());
+        }
+
+        let metadata = self.metadata().await?;
+        let jwks: JwkSet = self
+            .http
+            .get(&metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Failed to fetch JWKS from {}", metadata.jwks_uri))?
+            .json()
+            .await
+            .context("Invalid JWKS")?;
+
+        debug!("Fetched {} JWKS key(s)", jwks.keys.len());
+        *self.jwks.write().await = Some(CachedJwks {
+            keys: jwks.keys.clone(),
+            fetched_at: Instant::now(),
+        });
+        Ok(jwks.keys)
+    }
+
+    /// Verify the signature and claims of an id_token. `nonce` must match the
+    /// token's nonce claim when given.
+    pub async fn validate_token(&self, token: &str, nonce: Option<&str>) -> Result<Claims> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(anyhow!("Token is not a JWT"));
+        };
+
+        let header: JwtHeader = decode_json(header).context("Invalid JWT header")?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .context("Invalid JWT signature encoding")?;
+        let message = &token[..header_and_payload_len(token)];
+
+        let key = match self.find_key(&header, false).await? {
+            Some(key) => key,
+            // The provider may have rotated its keys since they were cached
+            None => self
+                .find_key(&header, true)
+                .await?
+                .ok_or_else(|| anyhow!("No JWKS key matches kid {:?}", header.kid))?,
+        };
+        verify_signature(&header.alg, &key, message.as_bytes(), &signature)?;
+
+        let claims: Claims = decode_json(payload).context("Invalid JWT claims")?;
+        if !same_issuer(&claims.iss, &self.config.issuer) {
+            return Err(anyhow!("Token issuer {} is not trusted", claims.iss));
+        }
+        if !claims.aud.contains(&self.config.client_id) {
+            return Err(anyhow!("Token audience does not include this client"));
+        }
+        if claims.exp + CLOCK_SKEW_SECS < now() {
+            return Err(anyhow!("Token has expired"));
+        }
+        if let Some(expected) = nonce
+            && claims.nonce.as_deref() != Some(expected)
+        {
+            return Err(anyhow!("Token nonce does not match the login"));
+        }
+
+        Ok(claims)
+    }
+
+    async fn find_key(&self, header: &JwtHeader, refresh: bool) -> Result<Option<Jwk>> {
+        let kty = match header.alg.as_str() {
+            "RS256" => "RSA",
+            "ES256" => "EC",
+            alg => return Err(anyhow!("Unsupported JWT algorithm {}", alg)),
+        };
+
+        Ok(self.jwks(refresh).await?.into_iter().find(|key| {
+            key.kty == kty
+                && match &header.kid {
+                    Some(kid) => key.kid.as_ref() == Some(kid),
+                    None => true,
+                }
+        }))
+    }
+
+    /// Start a login: the IdP URL to redirect to and the state to remember until the callback
+    pub async fn begin_login(
+        &self,
+        redirect_uri: &str,
+        return_to: &str,
+    ) -> Result<(String, LoginState)> {
+        let metadata = self.metadata().await?;
+        let login = LoginState {
+            state: self.random_token()?,
+            nonce: self.random_token()?,
+            return_to: safe_return_to(return_to).to_string(),
+            exp: now() + LOGIN_TTL_SECS,
+        };
+
+        let url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", self.config.client_id.as_str()),
+                ("redirect_uri", redirect_uri),
+                ("scope", "openid email profile"),
+                ("state", login.state.as_str()),
+                ("nonce", login.nonce.as_str()),
+            ],
+        )
+        .context("Invalid authorization endpoint")?;
+
+        Ok((url.to_string(), login))
+    }
+
+    /// Exchange an authorization code and validate the returned id_token
+    pub async fn finish_login(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        login: &LoginState,
+    ) -> Result<Claims> {
+        let metadata = self.metadata().await?;
+        let response: TokenResponse = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("client_id", self.config.client_id.as_str()),
+                ("client_secret", self.config.client_secret.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .context("Token exchange failed")?
+            .json()
+            .await
+            .context("Token response has no id_token")?;
+
+        self.validate_token(&response.id_token, Some(&login.nonce))
+            .await
+    }
+
+    /// Signed session cookie value for `identity`
+    pub fn session_cookie(&self, identity: &str) -> String {
+        self.sign(&Session {
+            identity: identity.to_string(),
+            exp: now() + SESSION_TTL_SECS,
+        })
+    }
+
+    /// Identity of a valid, unexpired session cookie
+    pub fn verify_session(&self, value: &str) -> Option<String> {
+        self.verify::<Session>(value)
+            .filter(|session| session.exp >= now())
+            .map(|session| session.identity)
+    }
+
+    /// Signed state cookie value for a login in progress
+    pub fn login_cookie(&self, login: &LoginState) -> String {
+        self.sign(login)
+    }
+
+    /// The login in progress, if the state cookie is valid and unexpired
+    pub fn verify_login(&self, value: &str) -> Option<LoginState> {
+        self.verify::<LoginState>(value)
+            .filter(|login| login.exp >= now())
+    }
+
+    /// Session lifetime in seconds, for the cookie's `Max-Age`
+    pub fn session_ttl_secs(&self) -> i64 {
+        SESSION_TTL_SECS
+    }
+
+    /// `payload.signature`, both base64url encoded
+    fn sign<T: Serialize>(&self, value: &T) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(value).unwrap_or_default());
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+        format!("{}.{}", payload, signature)
+    }
+
+    fn verify<T: DeserializeOwned>(&self, value: &str) -> Option<T> {
+        let (payload, signature) = value.split_once('.')?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+        self.mac(payload).verify_slice(&signature).ok()?;
+        decode_json(payload).ok()
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        // Cookies are signed with the client secret, so every replica accepts them
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(self.config.client_secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"headwind-ui-session:");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    fn random_token(&self) -> Result<String> {
+        let mut bytes = [0u8; 24];
+        self.rng
+            .fill(&mut bytes)
+            .map_err(|_| anyhow!("Failed to generate random state"))?;
+        Ok(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+fn verify_signature(alg: &str, key: &Jwk, message: &[u8], sig: &[u8]) -> Result<()> {
+    let field = |value: &Option<String>, name: &str| -> Result<Vec<u8>> {
+        let value = value
+            .as_ref()
+            .ok_or_else(|| anyhow!("JWKS key is missing {}", name))?;
+        URL_SAFE_NO_PAD
+            .decode(value)
+            .with_context(|| format!("Invalid {} in JWKS key", name))
+    };
+
+    let verified = match alg {
+        "RS256" => RsaPublicKeyComponents {
+            n: field(&key.n, "n")?,
+            e: field(&key.e, "e")?,
+        }
+        .verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, sig),
+        "ES256" => {
+            if key.crv.as_deref() != Some("P-256") {
+                return Err(anyhow!("ES256 requires a P-256 key"));
+            }
+            // Uncompressed SEC1 point
+            let mut point = vec![0x04];
+            point.extend(field(&key.x, "x")?);
+            point.extend(field(&key.y, "y")?);
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point).verify(message, sig)
+        },
+        alg => return Err(anyhow!("Unsupported JWT algorithm {}", alg)),
+    };
+
+    verified.map_err(|_| anyhow!("Invalid token signature"))
+}
+
+/// Length of `header.payload`, the signed part of a JWT
+fn header_and_payload_len(token: &str) -> usize {
+    token.rfind('.').unwrap_or(token.len())
+}
+
+fn decode_json<T: DeserializeOwned>(part: &str) -> Result<T> {
+    let bytes = URL_SAFE_NO_PAD.decode(part)?;
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+fn same_issuer(a: &str, b: &str) -> bool {
+    a.trim_end_matches('/') == b.trim_end_matches('/')
+}
+
+/// Only local paths are allowed as post-login redirect targets
+fn safe_return_to(path: &str) -> &str {
+    if path.starts_with('/') && !path.starts_with("//") && !path.starts_with("/\\") {
+        path
+    } else {
+        "/"
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> OidcClient {
+        OidcClient::new(OidcConfig {
+            issuer: "https://idp.example.com".to_string(),
+            client_id: "headwind".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_url: None,
+        })
+    }
+
+    #[test]
+    fn test_session_cookie_round_trip() {
+        let client = client();
+        let cookie = client.session_cookie("alice@example.com");
+
+        assert_eq!(
+            client.verify_session(&cookie).as_deref(),
+            Some("alice@example.com")
+        );
+    }
+
+    #[test]
+    fn test_session_cookie_rejects_tampering() {
+        let client = client();
+        let cookie = client.session_cookie("alice@example.com");
+        let (_, signature) = cookie.split_once('.').unwrap();
+        let forged = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(r#"{"identity":"mallory@example.com","exp":9999999999}"#),
+            signature
+        );
+
+        assert_eq!(client.verify_session(&forged), None);
+        assert_eq!(client.verify_session("garbage"), None);
+
+        // A different client secret does not accept the cookie either
+        let other = OidcClient::new(OidcConfig {
+            client_secret: "other".to_string(),
+            ..client.config().clone()
+        });
+        assert_eq!(other.verify_session(&cookie), None);
+    }
+
+    #[test]
+    fn test_session_cookie_expires() {
+        let client = client();
+        let expired = client.sign(&Session {
+            identity: "alice@example.com".to_string(),
+            exp: now() - 1,
+        });
+
+        assert_eq!(client.verify_session(&expired), None);
+    }
+
+    #[test]
+    fn test_audience_and_identity() {
+        let claims: Claims = serde_json::from_value(serde_json::json!({
+            "iss": "https://idp.example.com",
+            "sub": "1234",
+            "aud": ["other", "headwind"],
+            "exp": 0
+        }))
+        .unwrap();
+        assert!(claims.aud.contains("headwind"));
+        assert!(!claims.aud.contains("unknown"));
+        assert_eq!(claims.identity(), "1234");
+
+        let claims = Claims {
+            email: Some("alice@example.com".to_string()),
+            ..claims
+        };
+        assert_eq!(claims.identity(), "alice@example.com");
+    }
+
+    #[test]
+    fn test_safe_return_to() {
+        assert_eq!(
+            safe_return_to("/updates/default/web"),
+            "/updates/default/web"
+        );
+        assert_eq!(safe_return_to("//evil.example.com"), "/");
+        assert_eq!(safe_return_to("/\\evil.example.com"), "/");
+        assert_eq!(safe_return_to("https://evil.example.com"), "/");
+    }
+}
@@ -1,7 +1,7 @@
 use axum::{
     Form,
     extract::{Path, Query},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, header},
     response::{
         IntoResponse, Json,
         sse::{Event, Sse},
@@ -10,15 +10,38 @@ use axum::{
 use chrono::{DateTime, Duration, Utc};
 use futures::StreamExt as FuturesStreamExt;
 use futures::stream::Stream;
-use kube::{Api, Client};
+use kube::{Api, Client, api::ListParams};
 use serde::Deserialize;
 use tracing::{error, info};
 
 use crate::config::HeadwindConfig;
-use crate::models::crd::UpdateRequest;
-use crate::ui::auth::{AuditLogEntry, UserIdentity};
+use crate::config::features::{FeatureFlags, features};
+use crate::controller::lock::holder_identity;
+use crate::controller::preview_update_request;
+use crate::metrics::PATCH_PREVIEWS_TOTAL;
+use crate::metrics::status::{STATUS, SystemStatus};
+use crate::models::crd::{BatchUpdateRequest, SingleUpdate, UpdatePhase, UpdateRequest};
+use crate::models::policy::UpdatePolicy;
+use crate::policy::explain::{
+    EXPLAINABLE_KINDS, ExplainError, evaluate_workload_policy, explain_update,
+};
+use crate::polling::{
+    PollingConfig, RegistryPoller, RegistryTlsConfig, insecure_registries_from_env,
+};
+use crate::rollback::manual::{
+    ManualRollbackError, ROLLBACK_KINDS, rollback_to_image, rollback_to_previous_image,
+};
+use crate::ui::auth::{AuditLogEntry, AuthMode, UserIdentity};
 
-use super::templates::{self, UpdateRequestView};
+use super::clusters::{
+    ClusterQuery, client_for, cluster_names, post_approval_api, selected_cluster,
+};
+use super::comments::{
+    CommentError, NewComment, append_comment, comment_author, new_comment, validate_body,
+};
+use super::export::{self, ExportFormat, ExportRow};
+use super::import;
+use super::templates::{self, BatchUpdateView, ClusterView, UpdateRequestView};
 
 /// Health check endpoint for the Web UI
 /// Returns 200 OK if the UI server is running and can connect to Kubernetes API
@@ -36,19 +59,101 @@ pub async fn health_check() -> impl IntoResponse {
     }
 }
 
+/// Status of this Headwind process for `headwindctl status` and the status page
+pub async fn get_status() -> Json<SystemStatus> {
+    let pending_updates = match count_pending_updates().await {
+        Ok(count) => Some(count),
+        Err(e) => {
+            error!("Failed to count pending UpdateRequests: {}", e);
+            None
+        },
+    };
+    Json(STATUS.snapshot(Utc::now(), holder_identity(), pending_updates))
+}
+
+/// UpdateRequests waiting for approval across all namespaces
+async fn count_pending_updates() -> Result<usize, kube::Error> {
+    let api: Api<UpdateRequest> = Api::all(Client::try_default().await?);
+    let list = api.list(&ListParams::default()).await?;
+    Ok(list
+        .items
+        .iter()
+        .filter(|ur| {
+            ur.status
+                .as_ref()
+                .is_none_or(|s| s.phase == UpdatePhase::Pending)
+        })
+        .count())
+}
+
+/// System status page
+pub async fn status_page() -> impl IntoResponse {
+    info!("Rendering status page");
+    templates::status()
+}
+
+/// Dashboard query parameters
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardQuery {
+    /// Label selector the dashboard is filtered by
+    pub label_selector: Option<String>,
+    /// Requirement to add to the selector, from the filter chip input
+    pub add_label: Option<String>,
+}
+
+/// Split a label selector into its requirements, keeping commas inside
+/// set-based requirements such as `env in (prod,staging)` together
+pub fn split_label_selector(selector: &str) -> Vec<String> {
+    let mut requirements = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+
+    for c in selector.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                requirements.push(std::mem::take(&mut current));
+                continue;
+            },
+            _ => {},
+        }
+        current.push(c);
+    }
+    requirements.push(current);
+
+    requirements
+        .into_iter()
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .collect()
+}
+
 /// Dashboard route - main page showing all update requests
-pub async fn dashboard() -> impl IntoResponse {
+pub async fn dashboard(
+    Query(query): Query<DashboardQuery>,
+    Query(cluster): Query<ClusterQuery>,
+) -> impl IntoResponse {
     info!("Rendering dashboard");
 
+    let mut label_filters = split_label_selector(query.label_selector.as_deref().unwrap_or(""));
+    for requirement in split_label_selector(query.add_label.as_deref().unwrap_or("")) {
+        if !label_filters.contains(&requirement) {
+            label_filters.push(requirement);
+        }
+    }
+
     // Get Kubernetes client
-    let client = Client::try_default()
-        .await
-        .expect("Failed to create Kubernetes client");
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => return (e.status(), e.to_string()).into_response(),
+    };
 
     // Query all UpdateRequest CRDs across all namespaces
-    let api: Api<UpdateRequest> = Api::all(client);
+    let api: Api<UpdateRequest> = Api::all(client.clone());
     let update_requests = api
-        .list(&Default::default())
+        .list(&ListParams::default().labels(&label_filters.join(",")))
         .await
         .map(|list| list.items)
         .unwrap_or_else(|e| {
@@ -59,31 +164,75 @@ pub async fn dashboard() -> impl IntoResponse {
     // Convert UpdateRequests to view models
     let mut pending_updates = Vec::new();
     let mut completed_updates = Vec::new();
+    let mut scheduled_approvals = Vec::new();
 
     for ur in update_requests {
         let view = convert_to_view(&ur);
 
         match view.status.as_str() {
-            "Pending" => pending_updates.push(view),
-            "Completed" | "Rejected" | "Failed" => completed_updates.push(view),
+            "Pending" => {
+                if let Some(scheduled_at) = ur.spec.scheduled_at {
+                    scheduled_approvals.push((scheduled_at, view.clone()));
+                }
+                pending_updates.push(view)
+            },
+            "Completed" | "Rejected" | "Failed" | "RolledBack" => completed_updates.push(view),
             _ => pending_updates.push(view), // Default to pending
         }
     }
 
-    templates::dashboard(&pending_updates, &completed_updates)
+    // Batches are grouped per workload rather than labelled, so they are not filtered
+    let batch_api: Api<BatchUpdateRequest> = Api::all(client);
+    let pending_batches: Vec<BatchUpdateView> = batch_api
+        .list(&ListParams::default())
+        .await
+        .map(|list| list.items)
+        .unwrap_or_else(|e| {
+            error!("Failed to list BatchUpdateRequests: {}", e);
+            Vec::new()
+        })
+        .iter()
+        .filter(|batch| {
+            batch
+                .status
+                .as_ref()
+                .is_none_or(|s| s.phase == UpdatePhase::Pending)
+        })
+        .map(convert_batch_to_view)
+        .collect();
+
+    scheduled_approvals.sort_by_key(|(scheduled_at, _)| *scheduled_at);
+    let scheduled_approvals: Vec<UpdateRequestView> = scheduled_approvals
+        .into_iter()
+        .map(|(_, view)| view)
+        .collect();
+
+    templates::dashboard(
+        &pending_updates,
+        &completed_updates,
+        &scheduled_approvals,
+        &pending_batches,
+        &label_filters,
+        &cluster_view(cluster.cluster.as_deref()),
+    )
+    .into_response()
 }
 
 /// Update detail route - show individual update request
-pub async fn update_detail(Path((namespace, name)): Path<(String, String)>) -> impl IntoResponse {
+pub async fn update_detail(
+    Path((namespace, name)): Path<(String, String)>,
+    Query(cluster): Query<ClusterQuery>,
+) -> impl IntoResponse {
     info!("Rendering detail view for {}/{}", namespace, name);
 
     // Get Kubernetes client
-    let client = Client::try_default()
-        .await
-        .expect("Failed to create Kubernetes client");
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => return (e.status(), e.to_string()).into_response(),
+    };
 
     // Get specific UpdateRequest
-    let api: Api<UpdateRequest> = Api::namespaced(client, &namespace);
+    let api: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
     let update_request = api.get(&name).await.unwrap_or_else(|e| {
         error!("Failed to get UpdateRequest {}/{}: {}", namespace, name, e);
         panic!("UpdateRequest not found");
@@ -91,7 +240,36 @@ pub async fn update_detail(Path((namespace, name)): Path<(String, String)>) -> i
 
     let view = convert_to_view(&update_request);
 
-    templates::detail(&view)
+    // Only pending requests still have a patch to apply
+    let patch_preview = if view.status == "Pending" {
+        match preview_update_request(&client, &update_request, false).await {
+            Ok(preview) => serde_json::to_string_pretty(&preview.patch).ok(),
+            Err(e) => {
+                error!(
+                    "Failed to build patch preview for {}/{}: {}",
+                    namespace, name, e
+                );
+                None
+            },
+        }
+    } else {
+        None
+    };
+
+    templates::detail(
+        &view,
+        patch_preview.as_deref(),
+        &cluster_view(cluster.cluster.as_deref()),
+    )
+    .into_response()
+}
+
+/// Clusters for the cluster selector, with the one a page shows selected
+fn cluster_view(cluster: Option<&str>) -> ClusterView {
+    ClusterView {
+        names: cluster_names(),
+        selected: selected_cluster(cluster),
+    }
 }
 
 /// Convert UpdateRequest CRD to view model
@@ -108,6 +286,7 @@ fn convert_to_view(ur: &UpdateRequest) -> UpdateRequestView {
         namespace: metadata.namespace.clone().unwrap_or_default(),
         resource_kind: spec.target_ref.kind.to_string(),
         resource_name: spec.target_ref.name.clone(),
+        container_name: spec.container_name.clone(),
         current_image: spec.current_image.clone(),
         new_image: spec.new_image.clone(),
         current_version,
@@ -124,9 +303,50 @@ fn convert_to_view(ur: &UpdateRequest) -> UpdateRequestView {
         approved_by: status.and_then(|s| s.approved_by.clone()),
         rejected_by: status.and_then(|s| s.rejected_by.clone()),
         rejection_reason: status.and_then(|s| s.message.clone()),
+        scheduled_at: spec
+            .scheduled_at
+            .map(|at| at.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+        rollback_history: status
+            .map(|s| s.rollback_history.clone())
+            .unwrap_or_default(),
+        progress_steps: status.map(|s| s.progress_steps.clone()).unwrap_or_default(),
+        container_updates: container_versions(&spec.updates),
+        comments: spec.discussion_thread.clone(),
+        impact: spec.impact.clone(),
     }
 }
 
+/// Convert BatchUpdateRequest CRD to view model
+fn convert_batch_to_view(batch: &BatchUpdateRequest) -> BatchUpdateView {
+    let metadata = &batch.metadata;
+    let spec = &batch.spec;
+
+    BatchUpdateView {
+        name: metadata.name.clone().unwrap_or_default(),
+        namespace: metadata.namespace.clone().unwrap_or_default(),
+        resource_kind: spec.target_ref.kind.clone(),
+        resource_name: spec.target_ref.name.clone(),
+        policy: format!("{:?}", spec.policy),
+        created_at: metadata
+            .creation_timestamp
+            .as_ref()
+            .map(|ts| ts.0.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_default(),
+        updates: container_versions(&spec.updates),
+    }
+}
+
+/// (container, current version, new version) of each container update
+fn container_versions(updates: &[SingleUpdate]) -> Vec<(String, String, String)> {
+    updates
+        .iter()
+        .map(|u| {
+            let (current_version, new_version) = extract_versions(&u.current_image, &u.new_image);
+            (u.container_name.clone(), current_version, new_version)
+        })
+        .collect()
+}
+
 /// Extract version tags from image strings
 fn extract_versions(current_image: &str, new_image: &str) -> (String, String) {
     let current_version = current_image
@@ -150,6 +370,11 @@ pub async fn settings_page() -> impl IntoResponse {
     templates::settings()
 }
 
+/// Feature flags enabled with `HEADWIND_FEATURE_*`
+pub async fn get_features() -> Json<FeatureFlags> {
+    Json(*features())
+}
+
 /// Get current settings from ConfigMap and Secret
 pub async fn get_settings() -> impl IntoResponse {
     info!("Getting Headwind settings");
@@ -226,7 +451,8 @@ pub async fn update_settings(Json(config): Json<HeadwindConfig>) -> impl IntoRes
 pub async fn test_notification(Json(payload): Json<serde_json::Value>) -> impl IntoResponse {
     use crate::notifications::{
         DeploymentInfo, NotificationEvent, NotificationPayload, Notifier, SlackConfig,
-        SlackNotifier, TeamsConfig, TeamsNotifier, WebhookConfig, WebhookNotifier,
+        SlackNotifier, TeamsConfig, TeamsNotifier, TelegramConfig, TelegramNotifier,
+        VictorOpsConfig, VictorOpsNotifier, WebhookConfig, WebhookNotifier,
     };
 
     info!("Testing notification: {:?}", payload);
@@ -362,6 +588,110 @@ pub async fn test_notification(Json(payload): Json<serde_json::Value>) -> impl I
                 },
             }
         },
+        "telegram" => {
+            let telegram_config = TelegramConfig {
+                enabled: config.notifications.telegram.enabled,
+                bot_token: config
+                    .notifications
+                    .telegram
+                    .bot_token
+                    .clone()
+                    .unwrap_or_default(),
+                chat_id: config
+                    .notifications
+                    .telegram
+                    .chat_id
+                    .clone()
+                    .unwrap_or_default(),
+                parse_mode: config
+                    .notifications
+                    .telegram
+                    .parse_mode
+                    .parse()
+                    .unwrap_or_default(),
+            };
+
+            match TelegramNotifier::new(telegram_config) {
+                Ok(notifier) => match notifier.send(&test_payload).await {
+                    Ok(_) => (
+                        StatusCode::OK,
+                        Json(serde_json::json!({
+                            "message": "Test Telegram notification sent successfully"
+                        })),
+                    )
+                        .into_response(),
+                    Err(e) => {
+                        error!("Failed to send test Telegram notification: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(serde_json::json!({
+                                "error": format!("Failed to send Telegram notification: {}", e)
+                            })),
+                        )
+                            .into_response()
+                    },
+                },
+                Err(e) => {
+                    error!("Failed to create Telegram notifier: {}", e);
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(serde_json::json!({
+                            "error": format!("Telegram not configured: {}", e)
+                        })),
+                    )
+                        .into_response()
+                },
+            }
+        },
+        "victorops" => {
+            let victorops_config = VictorOpsConfig {
+                enabled: config.notifications.victorops.enabled,
+                rest_endpoint_url: config
+                    .notifications
+                    .victorops
+                    .rest_endpoint_url
+                    .clone()
+                    .unwrap_or_default(),
+                routing_key: config
+                    .notifications
+                    .victorops
+                    .routing_key
+                    .clone()
+                    .unwrap_or_default(),
+            };
+
+            match VictorOpsNotifier::new(victorops_config) {
+                Ok(notifier) => match notifier.send(&test_payload).await {
+                    Ok(_) => (
+                        StatusCode::OK,
+                        Json(serde_json::json!({
+                            "message": "Test VictorOps notification sent successfully"
+                        })),
+                    )
+                        .into_response(),
+                    Err(e) => {
+                        error!("Failed to send test VictorOps notification: {}", e);
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(serde_json::json!({
+                                "error": format!("Failed to send VictorOps notification: {}", e)
+                            })),
+                        )
+                            .into_response()
+                    },
+                },
+                Err(e) => {
+                    error!("Failed to create VictorOps notifier: {}", e);
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(serde_json::json!({
+                            "error": format!("VictorOps not configured: {}", e)
+                        })),
+                    )
+                        .into_response()
+                },
+            }
+        },
         "webhook" => {
             let webhook_config = WebhookConfig {
                 enabled: config.notifications.webhook.enabled,
@@ -406,7 +736,7 @@ pub async fn test_notification(Json(payload): Json<serde_json::Value>) -> impl I
         _ => (
             StatusCode::BAD_REQUEST,
             Json(serde_json::json!({
-                "error": "Invalid notification type. Must be 'slack', 'teams', or 'webhook'"
+                "error": "Invalid notification type. Must be 'slack', 'teams', 'telegram', 'victorops', or 'webhook'"
             })),
         )
             .into_response(),
@@ -419,6 +749,624 @@ pub async fn observability_page() -> impl IntoResponse {
     templates::observability()
 }
 
+/// Simulation page - dry-run policy evaluation
+pub async fn simulate_page() -> impl IntoResponse {
+    info!("Rendering simulation page");
+    templates::simulate()
+}
+
+/// Query parameters for `GET /api/v1/update-requests/export`
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportQuery {
+    /// `csv` (default), `json` for JSON Lines or `json-lines` for complete
+    /// UpdateRequests that can be imported again
+    pub format: Option<String>,
+    /// Only UpdateRequests created at or after this date or RFC 3339 timestamp
+    pub since: Option<String>,
+    /// Only UpdateRequests in this namespace
+    pub namespace: Option<String>,
+}
+
+/// Download UpdateRequests as an audit report
+pub async fn export_update_requests(
+    Query(query): Query<ExportQuery>,
+    Query(cluster): Query<ClusterQuery>,
+) -> impl IntoResponse {
+    let format = match query
+        .format
+        .as_deref()
+        .filter(|f| !f.is_empty())
+        .map(str::parse::<ExportFormat>)
+        .transpose()
+    {
+        Ok(format) => format.unwrap_or(ExportFormat::Csv),
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response();
+        },
+    };
+
+    let since = match query
+        .since
+        .as_deref()
+        .filter(|s| !s.is_empty())
+        .map(export::parse_since)
+        .transpose()
+    {
+        Ok(since) => since,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+                .into_response();
+        },
+    };
+
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return (
+                e.status(),
+                Json(serde_json::json!({"error": "Failed to connect to Kubernetes API"})),
+            )
+                .into_response();
+        },
+    };
+
+    let api: Api<UpdateRequest> = match query.namespace.as_deref().filter(|n| !n.is_empty()) {
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::all(client),
+    };
+    let mut update_requests = match api.list(&ListParams::default()).await {
+        Ok(list) => list.items,
+        Err(e) => {
+            error!("Failed to list UpdateRequests for export: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": format!("Failed to list UpdateRequests: {}", e)})),
+            )
+                .into_response();
+        },
+    };
+
+    if let Some(since) = since {
+        update_requests.retain(|ur| {
+            ur.metadata
+                .creation_timestamp
+                .as_ref()
+                .is_some_and(|ts| ts.0 >= since)
+        });
+    }
+    update_requests.sort_by_key(|ur| ur.metadata.creation_timestamp.clone());
+
+    let disposition = format!(
+        "attachment; filename={}",
+        format.filename(Utc::now().date_naive())
+    );
+    let headers = [
+        (header::CONTENT_TYPE, format.content_type().to_string()),
+        (header::CONTENT_DISPOSITION, disposition),
+    ];
+
+    if format == ExportFormat::Backup {
+        info!(
+            "Exporting {} UpdateRequests for backup",
+            update_requests.len()
+        );
+        let lines = update_requests
+            .into_iter()
+            .filter_map(|ur| export::backup_line(&ur))
+            .map(Ok::<_, std::convert::Infallible>);
+        let body = axum::body::Body::from_stream(futures::stream::iter(lines));
+        return (StatusCode::OK, headers, body).into_response();
+    }
+
+    let rows: Vec<ExportRow> = update_requests.iter().map(ExportRow::from).collect();
+    info!("Exporting {} UpdateRequests as {:?}", rows.len(), format);
+
+    (StatusCode::OK, headers, export::render(&rows, format)).into_response()
+}
+
+/// Recreate UpdateRequests from a `json-lines` export
+pub async fn import_update_requests(
+    Query(cluster): Query<ClusterQuery>,
+    body: String,
+) -> impl IntoResponse {
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return (
+                e.status(),
+                Json(serde_json::json!({"error": "Failed to connect to Kubernetes API"})),
+            )
+                .into_response();
+        },
+    };
+
+    match import::import_update_requests(&client, &body).await {
+        Ok(summary) => (StatusCode::OK, Json(summary)).into_response(),
+        Err(e) => {
+            error!("Failed to import UpdateRequests: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(
+                    serde_json::json!({"error": format!("Failed to import UpdateRequests: {}", e)}),
+                ),
+            )
+                .into_response()
+        },
+    }
+}
+
+/// Query parameters for `GET /api/v1/update-requests/{namespace}/{name}/preview`
+#[derive(Debug, Default, Deserialize)]
+pub struct PreviewQuery {
+    /// Validate the patch against the API server and include the resulting diff
+    #[serde(rename = "server-side-dry-run")]
+    pub server_side_dry_run: Option<bool>,
+}
+
+/// Show the patch approving an UpdateRequest would apply, without applying it
+pub async fn preview_update(
+    Path((namespace, name)): Path<(String, String)>,
+    Query(query): Query<PreviewQuery>,
+    Query(cluster): Query<ClusterQuery>,
+) -> impl IntoResponse {
+    PATCH_PREVIEWS_TOTAL.inc();
+
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return preview_response(e.status(), &serde_json::json!({"error": e.to_string()}));
+        },
+    };
+
+    let api: Api<UpdateRequest> = Api::namespaced(client.clone(), &namespace);
+    let update_request = match api.get_opt(&name).await {
+        Ok(Some(update_request)) => update_request,
+        Ok(None) => {
+            return preview_response(
+                StatusCode::NOT_FOUND,
+                &serde_json::json!({"error": format!("UpdateRequest {}/{} not found", namespace, name)}),
+            );
+        },
+        Err(e) => {
+            error!("Failed to get UpdateRequest {}/{}: {}", namespace, name, e);
+            return preview_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &serde_json::json!({"error": e.to_string()}),
+            );
+        },
+    };
+
+    let dry_run = query.server_side_dry_run.unwrap_or(false);
+    match preview_update_request(&client, &update_request, dry_run).await {
+        Ok(preview) => preview_response(StatusCode::OK, &preview),
+        Err(e) => {
+            error!("Patch preview failed for {}/{}: {}", namespace, name, e);
+            preview_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                &serde_json::json!({"error": format!("Patch preview failed: {}", e)}),
+            )
+        },
+    }
+}
+
+/// Pretty-printed JSON response, so previews are readable with plain curl
+fn preview_response(status: StatusCode, body: &impl serde::Serialize) -> axum::response::Response {
+    let body = serde_json::to_string_pretty(body).unwrap_or_default();
+    (status, [(header::CONTENT_TYPE, "application/json")], body).into_response()
+}
+
+/// Query parameters for `GET /api/v1/simulate`
+#[derive(Debug, Default, Deserialize)]
+pub struct SimulateQuery {
+    /// Only consider Deployments in this namespace
+    pub namespace: Option<String>,
+    /// Evaluate with this policy instead of each Deployment's own
+    pub policy: Option<String>,
+}
+
+/// Report which updates the policies would trigger, without modifying any resources
+pub async fn simulate(
+    Query(query): Query<SimulateQuery>,
+    Query(cluster): Query<ClusterQuery>,
+) -> impl IntoResponse {
+    let policy = match query.policy.as_deref().filter(|p| !p.is_empty()) {
+        Some(policy) => match policy.parse::<UpdatePolicy>() {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(serde_json::json!({"error": format!("Invalid policy: {}", e)})),
+                );
+            },
+        },
+        None => None,
+    };
+
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return (
+                e.status(),
+                Json(serde_json::json!({"error": e.to_string()})),
+            );
+        },
+    };
+
+    let tls = match RegistryTlsConfig::from_env() {
+        Ok(tls) => tls,
+        Err(e) => {
+            error!("Failed to load registry TLS settings: {}", e);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            );
+        },
+    };
+
+    // Events are never sent during a simulation, so the receivers are dropped
+    let (event_sender, _) = tokio::sync::mpsc::unbounded_channel();
+    let (chart_event_sender, _) = tokio::sync::mpsc::unbounded_channel();
+    let poller = RegistryPoller::with_client(
+        PollingConfig {
+            insecure_registries: insecure_registries_from_env(),
+            tls,
+            ..Default::default()
+        },
+        event_sender,
+        chart_event_sender,
+        client,
+    );
+
+    let namespace = query.namespace.as_deref().filter(|n| !n.is_empty());
+    match poller.simulate(namespace, policy).await {
+        Ok(results) => (StatusCode::OK, Json(serde_json::json!(results))),
+        Err(e) => {
+            error!("Simulation failed: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": format!("Simulation failed: {}", e)})),
+            )
+        },
+    }
+}
+
+/// Query parameters for `GET /api/v1/explain`
+#[derive(Debug, Deserialize)]
+pub struct ExplainQuery {
+    pub resource_kind: String,
+    pub namespace: String,
+    pub name: String,
+    /// Tag to evaluate against the resource's policy
+    pub candidate_tag: String,
+    /// Container to evaluate; defaults to the first one the policy targets
+    pub container: Option<String>,
+}
+
+/// Explain whether a workload would be updated to a candidate tag, and why
+pub async fn explain(
+    Query(query): Query<ExplainQuery>,
+    Query(cluster): Query<ClusterQuery>,
+) -> impl IntoResponse {
+    if !EXPLAINABLE_KINDS.contains(&query.resource_kind.as_str()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": ExplainError::UnsupportedKind(query.resource_kind).to_string()
+            })),
+        );
+    }
+
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return (
+                e.status(),
+                Json(serde_json::json!({"error": e.to_string()})),
+            );
+        },
+    };
+
+    let container = query.container.as_deref().filter(|c| !c.is_empty());
+    match explain_update(
+        &client,
+        &query.resource_kind,
+        &query.namespace,
+        &query.name,
+        container,
+        &query.candidate_tag,
+    )
+    .await
+    {
+        Ok(explanation) => (StatusCode::OK, Json(serde_json::json!(explanation))),
+        Err(e) => {
+            let status = match e {
+                ExplainError::UnsupportedKind(_) => StatusCode::BAD_REQUEST,
+                ExplainError::NotFound(_) => StatusCode::NOT_FOUND,
+                ExplainError::Kube(_) => {
+                    error!("Failed to explain update: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                },
+            };
+            (status, Json(serde_json::json!({"error": e.to_string()})))
+        },
+    }
+}
+
+/// How Headwind resolves a workload's policy from its annotations, and which
+/// of its containers it tracks. Read-only.
+pub async fn policy_evaluation(
+    Path((namespace, resource_kind, name)): Path<(String, String, String)>,
+    Query(cluster): Query<ClusterQuery>,
+) -> impl IntoResponse {
+    if !EXPLAINABLE_KINDS.contains(&resource_kind.as_str()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": ExplainError::UnsupportedKind(resource_kind).to_string()
+            })),
+        );
+    }
+
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return (
+                e.status(),
+                Json(serde_json::json!({"error": e.to_string()})),
+            );
+        },
+    };
+
+    match evaluate_workload_policy(&client, &resource_kind, &namespace, &name).await {
+        Ok(evaluation) => (StatusCode::OK, Json(serde_json::json!(evaluation))),
+        Err(e) => {
+            let status = match e {
+                ExplainError::UnsupportedKind(_) => StatusCode::BAD_REQUEST,
+                ExplainError::NotFound(_) => StatusCode::NOT_FOUND,
+                ExplainError::Kube(_) => {
+                    error!("Failed to evaluate policy: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                },
+            };
+            (status, Json(serde_json::json!({"error": e.to_string()})))
+        },
+    }
+}
+
+/// Header naming the user behind a manual rollback, as set by an
+/// authenticating proxy; the authenticated UI user is used when absent
+pub const REMOTE_USER_HEADER: &str = "X-Remote-User";
+
+/// Optional body of a manual rollback
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackBody {
+    /// Roll back to `to_image` even without a `headwind.sh/previous-image` annotation
+    #[serde(default)]
+    pub force: bool,
+    pub to_image: Option<String>,
+}
+
+/// Roll a workload back to the image saved in its `headwind.sh/previous-image`
+/// annotation, or with `force` to the image given in `toImage`
+pub async fn trigger_rollback(
+    user: UserIdentity,
+    headers: HeaderMap,
+    Path((namespace, resource_kind, name)): Path<(String, String, String)>,
+    Query(cluster): Query<ClusterQuery>,
+    body: Option<Json<RollbackBody>>,
+) -> impl IntoResponse {
+    let body = body.map(|Json(body)| body).unwrap_or_default();
+    let to_image = body
+        .to_image
+        .as_deref()
+        .map(str::trim)
+        .filter(|image| !image.is_empty());
+    let forced_image = match (body.force, to_image) {
+        (false, None) => None,
+        (true, Some(image)) => Some(image.to_string()),
+        (true, None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": "A forced rollback requires toImage"})),
+            );
+        },
+        (false, Some(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"error": "toImage requires force"})),
+            );
+        },
+    };
+
+    let rolled_back_by = headers
+        .get(REMOTE_USER_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .unwrap_or(user.username);
+
+    if !ROLLBACK_KINDS.contains(&resource_kind.as_str()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": ManualRollbackError::UnsupportedKind(resource_kind).to_string()
+            })),
+        );
+    }
+
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return (
+                e.status(),
+                Json(serde_json::json!({"error": e.to_string()})),
+            );
+        },
+    };
+
+    let result = match &forced_image {
+        Some(image) => {
+            rollback_to_image(
+                &client,
+                &resource_kind,
+                &namespace,
+                &name,
+                image,
+                &rolled_back_by,
+            )
+            .await
+        },
+        None => {
+            rollback_to_previous_image(&client, &resource_kind, &namespace, &name, &rolled_back_by)
+                .await
+        },
+    };
+
+    AuditLogEntry::new(
+        rolled_back_by,
+        "rollback".to_string(),
+        resource_kind,
+        namespace,
+        name,
+        if result.is_ok() { "success" } else { "failed" }.to_string(),
+        result.as_ref().err().map(|e| e.to_string()),
+    )
+    .log();
+
+    match result {
+        Ok(rollback) => (StatusCode::OK, Json(serde_json::json!(rollback))),
+        Err(e) => (
+            rollback_error_status(&e),
+            Json(serde_json::json!({"error": e.to_string()})),
+        ),
+    }
+}
+
+/// HTTP status for a failed manual rollback
+pub fn rollback_error_status(error: &ManualRollbackError) -> StatusCode {
+    match error {
+        ManualRollbackError::UnsupportedKind(_) => StatusCode::BAD_REQUEST,
+        ManualRollbackError::NotFound(_) => StatusCode::NOT_FOUND,
+        ManualRollbackError::NoPreviousImage(_)
+        | ManualRollbackError::NoMatchingContainer(..)
+        | ManualRollbackError::AlreadyRolledBack(..) => StatusCode::CONFLICT,
+        ManualRollbackError::Kube(_) | ManualRollbackError::Update(_) => {
+            error!("Manual rollback failed: {}", error);
+            StatusCode::INTERNAL_SERVER_ERROR
+        },
+    }
+}
+
+/// Discussion thread of an UpdateRequest, oldest comment first
+pub async fn list_comments(
+    Path((namespace, name)): Path<(String, String)>,
+    Query(cluster): Query<ClusterQuery>,
+) -> impl IntoResponse {
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return (
+                e.status(),
+                Json(serde_json::json!({"error": e.to_string()})),
+            );
+        },
+    };
+
+    let api: Api<UpdateRequest> = Api::namespaced(client, &namespace);
+    match api.get_opt(&name).await {
+        Ok(Some(update_request)) => (
+            StatusCode::OK,
+            Json(serde_json::json!(update_request.spec.discussion_thread)),
+        ),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(serde_json::json!({
+                "error": CommentError::NotFound(namespace, name).to_string()
+            })),
+        ),
+        Err(e) => {
+            error!("Failed to get UpdateRequest {}/{}: {}", namespace, name, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": e.to_string()})),
+            )
+        },
+    }
+}
+
+/// Add a comment to the discussion thread of an UpdateRequest
+pub async fn add_comment(
+    user: UserIdentity,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(cluster): Query<ClusterQuery>,
+    Json(body): Json<NewComment>,
+) -> impl IntoResponse {
+    if let Err(e) = validate_body(&body.body) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({"error": e.to_string()})),
+        );
+    }
+
+    let client = match client_for(cluster.cluster.as_deref()).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Kubernetes client: {}", e);
+            return (
+                e.status(),
+                Json(serde_json::json!({"error": e.to_string()})),
+            );
+        },
+    };
+
+    let comment = new_comment(comment_author(&user, body.author.as_deref()), body.body);
+    let result = append_comment(client, &namespace, &name, comment.clone()).await;
+
+    AuditLogEntry::new(
+        comment.author.clone(),
+        "comment".to_string(),
+        "UpdateRequest".to_string(),
+        namespace,
+        name,
+        if result.is_ok() { "success" } else { "failed" }.to_string(),
+        result.as_ref().err().map(|e| e.to_string()),
+    )
+    .log();
+
+    match result {
+        Ok(_) => (StatusCode::CREATED, Json(serde_json::json!(comment))),
+        Err(e) => {
+            let status = match &e {
+                CommentError::Empty | CommentError::TooLong(_) => StatusCode::BAD_REQUEST,
+                CommentError::NotFound(..) => StatusCode::NOT_FOUND,
+                CommentError::Conflict(..) => StatusCode::CONFLICT,
+                CommentError::Kube(_) => {
+                    error!("Failed to add comment: {}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                },
+            };
+            (status, Json(serde_json::json!({"error": e.to_string()})))
+        },
+    }
+}
+
 /// Get metrics data for dashboard
 pub async fn get_metrics_data() -> impl IntoResponse {
     use crate::metrics::client::create_metrics_client;
@@ -638,13 +1586,13 @@ fn fill_missing_intervals(
 }
 
 /// List all UpdateRequest CRDs (for update counts in observability dashboard)
-pub async fn list_update_requests() -> impl IntoResponse {
-    let client = match Client::try_default().await {
+pub async fn list_update_requests(Query(cluster): Query<ClusterQuery>) -> impl IntoResponse {
+    let client = match client_for(cluster.cluster.as_deref()).await {
         Ok(c) => c,
         Err(e) => {
             error!("Failed to create Kubernetes client: {}", e);
             return (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                e.status(),
                 Json(serde_json::json!({
                     "error": "Failed to connect to Kubernetes API"
                 })),
@@ -689,6 +1637,12 @@ pub async fn list_update_requests() -> impl IntoResponse {
     }
 }
 
+/// The authenticated UI user to pass to the approval API, which checks it
+/// against `headwind.sh/approved-by-role`
+fn forward_identity(user: &UserIdentity) -> Option<&str> {
+    (user.auth_mode != AuthMode::None).then_some(user.username.as_str())
+}
+
 /// Bulk approve updates
 #[derive(serde::Deserialize)]
 pub struct BulkApproveRequest {
@@ -711,6 +1665,7 @@ pub struct BulkOperationResponse {
 
 pub async fn bulk_approve(
     user: UserIdentity,
+    Query(cluster): Query<ClusterQuery>,
     Json(request): Json<BulkApproveRequest>,
 ) -> impl IntoResponse {
     info!(
@@ -726,7 +1681,7 @@ pub async fn bulk_approve(
     for update in &request.updates {
         // Call the approval API endpoint for each update
         let approval_url = format!(
-            "http://localhost:8081/api/v1/updates/{}/{}/approve",
+            "/api/v1/updates/{}/{}/approve",
             update.namespace, update.name
         );
 
@@ -734,14 +1689,16 @@ pub async fn bulk_approve(
             "approver": request.approver
         });
 
-        match reqwest::Client::new()
-            .post(&approval_url)
-            .json(&approve_payload)
-            .send()
-            .await
+        match post_approval_api(
+            cluster.cluster.as_deref(),
+            &approval_url,
+            forward_identity(&user),
+            &approve_payload,
+        )
+        .await
         {
-            Ok(response) => {
-                if response.status().is_success() {
+            Ok((status, _)) => {
+                if status.is_success() {
                     success_count += 1;
                     info!("Successfully approved {}/{}", update.namespace, update.name);
 
@@ -758,12 +1715,8 @@ pub async fn bulk_approve(
                     .log();
                 } else {
                     failed_count += 1;
-                    let error_msg = format!(
-                        "{}/{}: HTTP {}",
-                        update.namespace,
-                        update.name,
-                        response.status()
-                    );
+                    let error_msg =
+                        format!("{}/{}: HTTP {}", update.namespace, update.name, status);
                     errors.push(error_msg.clone());
                     error!(
                         "Failed to approve {}/{}: {}",
@@ -825,6 +1778,7 @@ pub struct BulkRejectRequest {
 
 pub async fn bulk_reject(
     user: UserIdentity,
+    Query(cluster): Query<ClusterQuery>,
     Json(request): Json<BulkRejectRequest>,
 ) -> impl IntoResponse {
     info!(
@@ -841,7 +1795,7 @@ pub async fn bulk_reject(
     for update in &request.updates {
         // Call the approval API endpoint for each update
         let reject_url = format!(
-            "http://localhost:8081/api/v1/updates/{}/{}/reject",
+            "/api/v1/updates/{}/{}/reject",
             update.namespace, update.name
         );
 
@@ -849,14 +1803,16 @@ pub async fn bulk_reject(
             "reason": request.reason
         });
 
-        match reqwest::Client::new()
-            .post(&reject_url)
-            .json(&reject_payload)
-            .send()
-            .await
+        match post_approval_api(
+            cluster.cluster.as_deref(),
+            &reject_url,
+            None,
+            &reject_payload,
+        )
+        .await
         {
-            Ok(response) => {
-                if response.status().is_success() {
+            Ok((status, _)) => {
+                if status.is_success() {
                     success_count += 1;
                     info!("Successfully rejected {}/{}", update.namespace, update.name);
 
@@ -873,12 +1829,8 @@ pub async fn bulk_reject(
                     .log();
                 } else {
                     failed_count += 1;
-                    let error_msg = format!(
-                        "{}/{}: HTTP {}",
-                        update.namespace,
-                        update.name,
-                        response.status()
-                    );
+                    let error_msg =
+                        format!("{}/{}: HTTP {}", update.namespace, update.name, status);
                     errors.push(error_msg.clone());
                     error!(
                         "Failed to reject {}/{}: {}",
@@ -932,14 +1884,16 @@ pub async fn bulk_reject(
 }
 
 /// Server-Sent Events endpoint for real-time UpdateRequest changes
-pub async fn updates_stream() -> Sse<impl Stream<Item = Result<Event, anyhow::Error>>> {
+pub async fn updates_stream(
+    Query(cluster): Query<ClusterQuery>,
+) -> Sse<impl Stream<Item = Result<Event, anyhow::Error>>> {
     use kube::runtime::watcher;
 
     info!("New SSE client connected for UpdateRequest stream");
 
     let stream = async_stream::stream! {
         // Create Kubernetes client
-        let client = match Client::try_default().await {
+        let client = match client_for(cluster.cluster.as_deref()).await {
             Ok(c) => c,
             Err(e) => {
                 error!("Failed to create Kubernetes client for SSE: {}", e);
@@ -1051,30 +2005,29 @@ pub struct ApprovalForm {
 pub async fn approve_update(
     user: UserIdentity,
     Path((namespace, name)): Path<(String, String)>,
+    Query(cluster): Query<ClusterQuery>,
     Form(form): Form<ApprovalForm>,
 ) -> impl IntoResponse {
     let approver = user.username.clone();
 
-    let approval_url = format!(
-        "http://localhost:8081/api/v1/updates/{}/{}/approve",
-        namespace, name
-    );
+    let approval_url = format!("/api/v1/updates/{}/{}/approve", namespace, name);
 
     // Convert form to JSON for the approval API
     let json_body = serde_json::json!({
         "approver": form.approver
     });
 
-    match reqwest::Client::new()
-        .post(&approval_url)
-        .json(&json_body)
-        .send()
-        .await
+    match post_approval_api(
+        cluster.cluster.as_deref(),
+        &approval_url,
+        forward_identity(&user),
+        &json_body,
+    )
+    .await
     {
-        Ok(response) => {
-            let status = response.status();
-            match response.json::<serde_json::Value>().await {
-                Ok(body) => {
+        Ok((status, body)) => {
+            match body {
+                Some(body) => {
                     // Log successful approval
                     AuditLogEntry::new(
                         approver,
@@ -1089,7 +2042,7 @@ pub async fn approve_update(
 
                     (status, Json(body))
                 },
-                Err(_) => {
+                None => {
                     // Log failed approval (parse error)
                     AuditLogEntry::new(
                         approver,
@@ -1140,15 +2093,13 @@ pub struct RejectionForm {
 pub async fn reject_update(
     user: UserIdentity,
     Path((namespace, name)): Path<(String, String)>,
+    Query(cluster): Query<ClusterQuery>,
     Form(form): Form<RejectionForm>,
 ) -> impl IntoResponse {
     let approver = user.username.clone();
     let rejection_reason = form.reason.clone();
 
-    let approval_url = format!(
-        "http://localhost:8081/api/v1/updates/{}/{}/reject",
-        namespace, name
-    );
+    let approval_url = format!("/api/v1/updates/{}/{}/reject", namespace, name);
 
     // Convert form to JSON for the approval API
     let json_body = serde_json::json!({
@@ -1156,16 +2107,10 @@ pub async fn reject_update(
         "reason": form.reason
     });
 
-    match reqwest::Client::new()
-        .post(&approval_url)
-        .json(&json_body)
-        .send()
-        .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            match response.json::<serde_json::Value>().await {
-                Ok(body) => {
+    match post_approval_api(cluster.cluster.as_deref(), &approval_url, None, &json_body).await {
+        Ok((status, body)) => {
+            match body {
+                Some(body) => {
                     // Log successful rejection
                     AuditLogEntry::new(
                         approver,
@@ -1180,7 +2125,7 @@ pub async fn reject_update(
 
                     (status, Json(body))
                 },
-                Err(_) => {
+                None => {
                     // Log failed rejection (parse error)
                     AuditLogEntry::new(
                         approver,
@@ -1220,3 +2165,87 @@ pub async fn reject_update(
         },
     }
 }
+
+/// Approve a BatchUpdateRequest (proxy to approval API)
+pub async fn approve_batch_update(
+    user: UserIdentity,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(cluster): Query<ClusterQuery>,
+    Form(form): Form<ApprovalForm>,
+) -> impl IntoResponse {
+    proxy_batch_action(
+        user,
+        cluster.cluster.as_deref(),
+        "approve",
+        namespace,
+        name,
+        serde_json::json!({ "approver": form.approver }),
+        None,
+    )
+    .await
+}
+
+/// Reject a BatchUpdateRequest (proxy to approval API)
+pub async fn reject_batch_update(
+    user: UserIdentity,
+    Path((namespace, name)): Path<(String, String)>,
+    Query(cluster): Query<ClusterQuery>,
+    Form(form): Form<RejectionForm>,
+) -> impl IntoResponse {
+    let reason = form.reason.clone();
+    proxy_batch_action(
+        user,
+        cluster.cluster.as_deref(),
+        "reject",
+        namespace,
+        name,
+        serde_json::json!({ "approver": form.approver, "reason": form.reason }),
+        reason,
+    )
+    .await
+}
+
+/// Forward a batch approve/reject to the approval API and audit the outcome
+async fn proxy_batch_action(
+    user: UserIdentity,
+    cluster: Option<&str>,
+    action: &str,
+    namespace: String,
+    name: String,
+    body: serde_json::Value,
+    reason: Option<String>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let approval_url = format!("/api/v1/batch-updates/{}/{}/{}", namespace, name, action);
+
+    let (status, body, result, reason) =
+        match post_approval_api(cluster, &approval_url, None, &body).await {
+            Ok((status, body)) => match body {
+                Some(body) => (status, body, "success", reason),
+                None => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    serde_json::json!({"error": "Failed to parse response"}),
+                    "failed",
+                    Some("Failed to parse approval API response".to_string()),
+                ),
+            },
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({"error": e.to_string()}),
+                "failed",
+                Some(format!("Failed to call approval API: {}", e)),
+            ),
+        };
+
+    AuditLogEntry::new(
+        user.username,
+        action.to_string(),
+        "BatchUpdateRequest".to_string(),
+        namespace,
+        name,
+        result.to_string(),
+        reason,
+    )
+    .log();
+
+    (status, Json(body))
+}
@@ -1,3 +1,4 @@
+use crate::models::crd::{Comment, ProgressStep, RollbackRecord, StepStatus, UpdateImpact};
 use maud::{DOCTYPE, Markup, html};
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +9,7 @@ pub struct UpdateRequestView {
     pub namespace: String,
     pub resource_kind: String,
     pub resource_name: String,
+    pub container_name: Option<String>,
     pub current_image: String,
     pub new_image: String,
     pub current_version: String,
@@ -18,6 +20,58 @@ pub struct UpdateRequestView {
     pub approved_by: Option<String>,
     pub rejected_by: Option<String>,
     pub rejection_reason: Option<String>,
+    /// When the update is approved automatically, if scheduled
+    pub scheduled_at: Option<String>,
+    /// Rollbacks of the applied update, oldest first
+    #[serde(default)]
+    pub rollback_history: Vec<RollbackRecord>,
+    /// Steps of the update, in the order they started
+    #[serde(default)]
+    pub progress_steps: Vec<ProgressStep>,
+    /// (container, current version, new version) of a multi-container update
+    #[serde(default)]
+    pub container_updates: Vec<(String, String, String)>,
+    /// Discussion thread, oldest comment first
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+    /// Estimated blast radius of the rollout, if assessed
+    #[serde(default)]
+    pub impact: Option<UpdateImpact>,
+}
+
+/// Represents a BatchUpdateRequest for display in the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchUpdateView {
+    pub name: String,
+    pub namespace: String,
+    pub resource_kind: String,
+    pub resource_name: String,
+    pub policy: String,
+    pub created_at: String,
+    /// (container, current version, new version) for every update in the batch
+    pub updates: Vec<(String, String, String)>,
+}
+
+/// Clusters the UI can switch between, see `HEADWIND_CLUSTERS`
+#[derive(Debug, Clone, Default)]
+pub struct ClusterView {
+    /// Configured clusters, empty outside multi-cluster mode
+    pub names: Vec<String>,
+    /// The cluster the page shows
+    pub selected: Option<String>,
+}
+
+impl ClusterView {
+    /// `path` with the selected cluster as `cluster` query parameter
+    pub fn url(&self, path: &str) -> String {
+        match &self.selected {
+            Some(cluster) if !self.names.is_empty() => {
+                let separator = if path.contains('?') { '&' } else { '?' };
+                format!("{}{}cluster={}", path, separator, cluster)
+            },
+            _ => path.to_string(),
+        }
+    }
 }
 
 /// Base layout template - shared layout for all pages
@@ -63,6 +117,7 @@ pub fn base_layout(title: &str, content: Markup) -> Markup {
                         ul class="menu menu-horizontal px-1" {
                             li { a href="/" { "Dashboard" } }
                             li { a href="/observability" { "Observability" } }
+                            li { a href="/status" { "Status" } }
                             li { a href="/settings" { "Settings" } }
                             li { a href="/health" { "Health" } }
                         }
@@ -111,6 +166,13 @@ pub fn base_layout(title: &str, content: Markup) -> Markup {
                         }, 3000);
                     }
 
+                    // Keep the cluster selected with ?cluster= on API calls
+                    function withCluster(url) {
+                        const cluster = new URLSearchParams(window.location.search).get('cluster');
+                        if (!cluster) return url;
+                        return url + (url.includes('?') ? '&' : '?') + 'cluster=' + encodeURIComponent(cluster);
+                    }
+
                     // Pagination state
                     let currentPage = 1;
                     const itemsPerPage = 20;
@@ -304,7 +366,7 @@ pub fn base_layout(title: &str, content: Markup) -> Markup {
                             name: cb.dataset.name
                         }));
 
-                        fetch('/api/v1/updates/bulk/approve', {
+                        fetch(withCluster('/api/v1/updates/bulk/approve'), {
                             method: 'POST',
                             headers: {
                                 'Content-Type': 'application/json'
@@ -344,7 +406,7 @@ pub fn base_layout(title: &str, content: Markup) -> Markup {
                             name: cb.dataset.name
                         }));
 
-                        fetch('/api/v1/updates/bulk/reject', {
+                        fetch(withCluster('/api/v1/updates/bulk/reject'), {
                             method: 'POST',
                             headers: {
                                 'Content-Type': 'application/json'
@@ -389,7 +451,7 @@ pub fn base_layout(title: &str, content: Markup) -> Markup {
 
                         isInitialLoad = true;
                         console.log('Connecting to SSE endpoint...');
-                        eventSource = new EventSource('/api/v1/events/updates');
+                        eventSource = new EventSource(withCluster('/api/v1/events/updates'));
 
                         eventSource.addEventListener('updated', function(e) {
                             console.log('SSE updated event:', e.data);
@@ -489,6 +551,10 @@ pub fn base_layout(title: &str, content: Markup) -> Markup {
 pub fn dashboard(
     pending_updates: &[UpdateRequestView],
     completed_updates: &[UpdateRequestView],
+    scheduled_approvals: &[UpdateRequestView],
+    pending_batches: &[BatchUpdateView],
+    label_filters: &[String],
+    clusters: &ClusterView,
 ) -> Markup {
     let total_pending = pending_updates.len();
     let total_completed = completed_updates.len();
@@ -519,6 +585,76 @@ pub fn dashboard(
             }
         }
 
+        div class="flex justify-end gap-2 mb-4" {
+            @if !clusters.names.is_empty() {
+                select id="cluster-select" class="select select-bordered select-sm"
+                    onchange="window.location.href = '/?cluster=' + encodeURIComponent(this.value)" {
+                    @for name in &clusters.names {
+                        option value=(name) selected[clusters.selected.as_deref() == Some(name.as_str())] { (name) }
+                    }
+                }
+            }
+            div class="dropdown dropdown-end" {
+                div tabindex="0" role="button" class="btn btn-outline btn-sm" { "Export" }
+                ul tabindex="0" class="dropdown-content menu bg-base-100 rounded-box z-10 w-40 p-2 shadow" {
+                    li { a href=(clusters.url("/api/v1/update-requests/export?format=csv")) download { "CSV" } }
+                    li { a href=(clusters.url("/api/v1/update-requests/export?format=json")) download { "JSON Lines" } }
+                }
+            }
+            a href="/simulate" class="btn btn-outline btn-sm" { "Simulate Updates" }
+        }
+
+        // Label selector filter chips
+        (label_filter_chips(label_filters, clusters))
+
+        // Scheduled Approvals Section, soonest first
+        @if !scheduled_approvals.is_empty() {
+            div class="card bg-base-100 shadow-xl mb-6" {
+                div class="card-body" {
+                    h2 class="card-title text-2xl mb-4" {
+                        span class="badge badge-info" { (scheduled_approvals.len()) }
+                        "Scheduled Approvals"
+                    }
+                    div class="overflow-x-auto" {
+                        table class="table table-sm" {
+                            thead {
+                                tr {
+                                    th { "Approves At" }
+                                    th { "Resource" }
+                                    th { "Namespace" }
+                                    th { "Version" }
+                                    th { "Actions" }
+                                }
+                            }
+                            tbody {
+                                @for update in scheduled_approvals {
+                                    tr {
+                                        td class="font-semibold" { (update.scheduled_at.as_deref().unwrap_or_default()) }
+                                        td {
+                                            span class="badge badge-outline badge-sm" { (update.resource_kind) }
+                                            " "
+                                            (update.resource_name)
+                                        }
+                                        td { span class="badge badge-ghost" { (update.namespace) } }
+                                        td {
+                                            code class="text-xs" { (update.current_version) }
+                                            " → "
+                                            code class="text-xs text-success" { (update.new_version) }
+                                        }
+                                        td {
+                                            a href=(clusters.url(&format!("/updates/{}/{}", update.namespace, update.name))) class="btn btn-ghost btn-sm" {
+                                                "Details"
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
         // Pending Updates Section
         div class="card bg-base-100 shadow-xl mb-6" {
             div class="card-body" {
@@ -679,6 +815,9 @@ pub fn dashboard(
                                             div class="flex flex-col" {
                                                 span class="badge badge-outline badge-sm mb-1" { (update.resource_kind) }
                                                 span class="font-semibold" { (update.resource_name) }
+                                                @if !update.container_updates.is_empty() {
+                                                    (container_updates_list(&update.container_updates))
+                                                }
                                             }
                                         }
                                         td { span class="badge badge-ghost" { (update.namespace) } }
@@ -689,7 +828,7 @@ pub fn dashboard(
                                         td {
                                             div class="flex gap-2" {
                                                 button class="btn btn-success btn-sm"
-                                                    hx-post=(format!("/api/v1/updates/{}/{}/approve", update.namespace, update.name))
+                                                    hx-post=(clusters.url(&format!("/api/v1/updates/{}/{}/approve", update.namespace, update.name)))
                                                     hx-vals=r#"{"approver": "web-ui"}"#
                                                     hx-confirm="Are you sure you want to approve this update?"
                                                     hx-swap="none"
@@ -700,7 +839,7 @@ pub fn dashboard(
                                                     onclick=(format!("document.getElementById('reject_modal_{}_{}').showModal()", update.namespace, update.name)) {
                                                     "✗ Reject"
                                                 }
-                                                a href=(format!("/updates/{}/{}", update.namespace, update.name)) class="btn btn-ghost btn-sm" {
+                                                a href=(clusters.url(&format!("/updates/{}/{}", update.namespace, update.name))) class="btn btn-ghost btn-sm" {
                                                     "Details"
                                                 }
                                             }
@@ -777,6 +916,11 @@ pub fn dashboard(
             }
         }
 
+        // Pending Batch Updates Section
+        @if !pending_batches.is_empty() {
+            (batch_updates_card(pending_batches, clusters))
+        }
+
         // Individual Reject Modals for Pending Updates
         @for update in pending_updates {
             dialog id=(format!("reject_modal_{}_{}", update.namespace, update.name)) class="modal" {
@@ -786,12 +930,13 @@ pub fn dashboard(
                     textarea id=(format!("reject_reason_{}_{}", update.namespace, update.name))
                         class="textarea textarea-bordered w-full"
                         placeholder="Reason for rejection..."
-                        rows="3" {}
+                        rows="3" required {}
                     div class="modal-action" {
                         button class="btn" onclick=(format!("document.getElementById('reject_modal_{}_{}').close()", update.namespace, update.name)) { "Cancel" }
                         button type="button" class="btn btn-error"
-                            hx-post=(format!("/api/v1/updates/{}/{}/reject", update.namespace, update.name))
+                            hx-post=(clusters.url(&format!("/api/v1/updates/{}/{}/reject", update.namespace, update.name)))
                             hx-vals=(format!(r#"js:{{approver: "web-ui", reason: document.getElementById("reject_reason_{}_{}").value}}"#, update.namespace, update.name))
+                            hx-on--before-request=(format!("if (!document.getElementById('reject_reason_{}_{}').reportValidity()) event.preventDefault()", update.namespace, update.name))
                             hx-swap="none"
                             hx-on--after-request="window.location.reload()" {
                             "Reject Update"
@@ -815,7 +960,7 @@ pub fn dashboard(
                         span class="label-text" { "Rejection Reason" }
                     }
                     textarea id="bulk-reject-reason" class="textarea textarea-bordered h-24"
-                        placeholder="e.g., Failed QA testing, security concerns, etc." {}
+                        placeholder="e.g., Failed QA testing, security concerns, etc." required {}
                 }
 
                 div class="modal-action" {
@@ -834,13 +979,340 @@ pub fn dashboard(
     base_layout("Headwind Dashboard", content)
 }
 
-/// Detail template - individual update request view
-pub fn detail(update: &UpdateRequestView) -> Markup {
+/// Card listing pending BatchUpdateRequests, approved or rejected as a whole
+/// Expandable list of the containers a multi-container update changes
+fn container_updates_list(updates: &[(String, String, String)]) -> Markup {
+    html! {
+        details class="mt-1 text-sm" {
+            summary class="cursor-pointer opacity-70" { (updates.len()) " containers" }
+            ul class="mt-1 space-y-1" {
+                @for (container, current_version, new_version) in updates {
+                    li {
+                        span class="font-semibold" { (container) } ": "
+                        code class="version-display" { (current_version) }
+                        " → "
+                        code class="version-display text-success" { (new_version) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn batch_updates_card(batches: &[BatchUpdateView], clusters: &ClusterView) -> Markup {
+    html! {
+        div class="card bg-base-100 shadow-xl mb-6" id="batch-updates" {
+            div class="card-body" {
+                h2 class="card-title text-2xl mb-4" {
+                    span class="badge badge-warning" { (batches.len()) }
+                    "Batch Updates"
+                }
+                p class="text-sm opacity-70 mb-4" {
+                    "Every container in a batch is updated together in a single rollout."
+                }
+
+                @for batch in batches {
+                    div class="border border-base-300 rounded-lg p-4 mb-4" {
+                        div class="flex justify-between items-center mb-2" {
+                            div {
+                                span class="badge badge-outline badge-sm" { (batch.resource_kind) }
+                                " "
+                                span class="font-semibold" { (batch.resource_name) }
+                                span class="text-sm opacity-70" { " in " (batch.namespace) }
+                                span class="badge badge-ghost badge-sm ml-2" { (batch.policy) }
+                            }
+                            div class="flex gap-2" {
+                                button class="btn btn-success btn-xs"
+                                    hx-post=(clusters.url(&format!("/api/v1/batch-updates/{}/{}/approve", batch.namespace, batch.name)))
+                                    hx-vals=r#"{"approver": "web-ui"}"#
+                                    hx-confirm=(format!("Approve all {} updates in this batch?", batch.updates.len()))
+                                    hx-swap="none"
+                                    hx-on--after-request="window.location.reload()" {
+                                    "✓ Approve All"
+                                }
+                                button class="btn btn-error btn-xs"
+                                    hx-post=(clusters.url(&format!("/api/v1/batch-updates/{}/{}/reject", batch.namespace, batch.name)))
+                                    hx-vals=r#"js:{approver: "web-ui", reason: prompt("Reason for rejecting this batch:") || ""}"#
+                                    hx-swap="none"
+                                    hx-on--after-request="window.location.reload()" {
+                                    "✗ Reject All"
+                                }
+                            }
+                        }
+                        table class="table table-sm" {
+                            thead {
+                                tr {
+                                    th { "Container" }
+                                    th { "Version" }
+                                }
+                            }
+                            tbody {
+                                @for (container, current_version, new_version) in &batch.updates {
+                                    tr {
+                                        td { (container) }
+                                        td {
+                                            code class="text-xs" { (current_version) }
+                                            " → "
+                                            code class="text-xs" { (new_version) }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        div class="text-xs opacity-60" { "Created " (batch.created_at) }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Simulation page - shows which updates the policies would trigger
+pub fn simulate() -> Markup {
+    let content = html! {
+        h1 class="text-3xl font-bold mb-2" { "Simulate Updates" }
+        p class="opacity-70 mb-6" {
+            "Check every tracked Deployment image against its registry and see which updates the policy would trigger. Nothing is modified."
+        }
+
+        div class="card bg-base-100 shadow-xl mb-6" {
+            div class="card-body" {
+                form id="simulate-form" class="flex flex-wrap gap-4 items-end" onsubmit="runSimulation(event)" {
+                    div class="form-control" {
+                        label class="label" for="simulate-namespace" {
+                            span class="label-text" { "Namespace" }
+                        }
+                        input type="text" id="simulate-namespace" placeholder="All namespaces"
+                            class="input input-bordered";
+                    }
+                    div class="form-control" {
+                        label class="label" for="simulate-policy" {
+                            span class="label-text" { "Policy" }
+                        }
+                        select id="simulate-policy" class="select select-bordered" {
+                            option value="" selected { "Each resource's own policy" }
+                            option value="patch" { "patch" }
+                            option value="minor" { "minor" }
+                            option value="major" { "major" }
+                            option value="all" { "all" }
+                        }
+                    }
+                    button type="submit" class="btn btn-primary" { "Run Simulation" }
+                }
+            }
+        }
+
+        div id="simulate-loading" class="hidden flex justify-center py-12" {
+            span class="loading loading-spinner loading-lg" {}
+        }
+
+        div class="card bg-base-100 shadow-xl" {
+            div class="card-body" {
+                div class="overflow-x-auto" {
+                    table class="table table-zebra" {
+                        thead {
+                            tr {
+                                th { "Resource" }
+                                th { "Namespace" }
+                                th { "Current Image" }
+                                th { "Latest Available" }
+                                th { "Result" }
+                            }
+                        }
+                        tbody id="simulate-results" {
+                            tr {
+                                td colspan="5" class="text-center opacity-70" { "Run a simulation to see results" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        script {
+            (maud::PreEscaped(r#"
+            function escapeHtml(value) {
+                const div = document.createElement('div');
+                div.textContent = value ?? '';
+                return div.innerHTML;
+            }
+
+            async function runSimulation(event) {
+                event.preventDefault();
+                const params = new URLSearchParams();
+                const namespace = document.getElementById('simulate-namespace').value.trim();
+                const policy = document.getElementById('simulate-policy').value;
+                if (namespace) params.set('namespace', namespace);
+                if (policy) params.set('policy', policy);
+
+                const loading = document.getElementById('simulate-loading');
+                const tbody = document.getElementById('simulate-results');
+                loading.classList.remove('hidden');
+
+                try {
+                    const response = await fetch('/api/v1/simulate?' + params.toString());
+                    const data = await response.json();
+                    if (!response.ok) {
+                        throw new Error(data.error || response.statusText);
+                    }
+
+                    if (data.length === 0) {
+                        tbody.innerHTML = '<tr><td colspan="5" class="text-center opacity-70">No tracked resources found</td></tr>';
+                        return;
+                    }
+
+                    tbody.innerHTML = data.map(r => `
+                        <tr>
+                            <td><span class="badge badge-outline badge-sm">${escapeHtml(r.resource_kind)}</span> ${escapeHtml(r.name)}</td>
+                            <td>${escapeHtml(r.namespace)}</td>
+                            <td><code class="text-xs">${escapeHtml(r.current_image)}</code></td>
+                            <td><code class="text-xs">${escapeHtml(r.latest_available || '-')}</code></td>
+                            <td>${r.would_update
+                                ? '<span class="badge badge-success">Would update</span>'
+                                : `<span class="badge badge-ghost">No update</span> <span class="text-xs opacity-70">${escapeHtml(r.policy_rejection_reason)}</span>`}</td>
+                        </tr>`).join('');
+                } catch (error) {
+                    showToast('Simulation failed: ' + error.message, 'error');
+                } finally {
+                    loading.classList.add('hidden');
+                }
+            }
+            "#))
+        }
+    };
+
+    base_layout("Simulate Updates - Headwind", content)
+}
+
+/// System status template - controllers, polling and notification health,
+/// refreshed from `/api/v1/status` every 5 seconds
+pub fn status() -> Markup {
+    let content = html! {
+        h1 class="text-3xl font-bold mb-6" { "System Status" }
+
+        div class="grid grid-cols-1 md:grid-cols-4 gap-4 mb-6" {
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-sm" { "Version" }
+                    p class="text-2xl font-bold" id="status-version" { "-" }
+                }
+            }
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-sm" { "Uptime" }
+                    p class="text-2xl font-bold" id="status-uptime" { "-" }
+                }
+            }
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-sm" { "Replica" }
+                    p class="text-2xl font-bold" id="status-leader" { "-" }
+                }
+            }
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-sm" { "Updates Pending" }
+                    p class="text-2xl font-bold" id="status-pending" { "-" }
+                }
+            }
+        }
+
+        div class="grid grid-cols-1 lg:grid-cols-3 gap-6" {
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-xl mb-4" { "Controllers" }
+                    table class="table table-sm" {
+                        tbody id="status-controllers" {}
+                    }
+                }
+            }
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-xl mb-4" { "Registry Polling" }
+                    p class="text-sm text-base-content/70" { "Last cycle" }
+                    p class="font-bold mb-2" id="status-last-poll" { "-" }
+                    p class="text-sm text-base-content/70" { "Duration" }
+                    p class="font-bold" id="status-poll-duration" { "-" }
+                }
+            }
+            div class="card bg-base-100 shadow-xl" {
+                div class="card-body" {
+                    h2 class="card-title text-xl mb-4" { "Notifications" }
+                    table class="table table-sm" {
+                        tbody id="status-notifications" {}
+                    }
+                }
+            }
+        }
+
+        script {
+            (maud::PreEscaped(r#"
+            function escapeHtml(value) {
+                const div = document.createElement('div');
+                div.textContent = value ?? '';
+                return div.innerHTML;
+            }
+
+            function formatUptime(seconds) {
+                const days = Math.floor(seconds / 86400);
+                const hours = Math.floor((seconds % 86400) / 3600);
+                const minutes = Math.floor((seconds % 3600) / 60);
+                return (days ? days + 'd ' : '') + hours + 'h ' + minutes + 'm';
+            }
+
+            function stateRows(entries, empty) {
+                if (entries.length === 0) {
+                    return `<tr><td class="opacity-70">${empty}</td></tr>`;
+                }
+                return entries.map(([name, state]) => {
+                    const badge = state === 'running' || state === 'ok' ? 'badge-success' : 'badge-error';
+                    return `<tr><td>${escapeHtml(name)}</td><td><span class="badge ${badge}">${escapeHtml(state)}</span></td></tr>`;
+                }).join('');
+            }
+
+            async function loadStatus() {
+                try {
+                    const response = await fetch('/api/v1/status');
+                    const status = await response.json();
+                    document.getElementById('status-version').textContent = status.version;
+                    document.getElementById('status-uptime').textContent = formatUptime(status.uptime_seconds);
+                    document.getElementById('status-leader').textContent = status.leader;
+                    document.getElementById('status-pending').textContent = status.pending_updates ?? 'unknown';
+                    document.getElementById('status-controllers').innerHTML =
+                        stateRows(Object.entries(status.controllers), 'Controllers disabled');
+                    document.getElementById('status-notifications').innerHTML =
+                        stateRows(Object.entries(status.notifications), 'No notifications sent yet');
+                    document.getElementById('status-last-poll').textContent =
+                        status.last_poll_cycle ? new Date(status.last_poll_cycle).toLocaleString() : 'Never';
+                    document.getElementById('status-poll-duration').textContent =
+                        status.poll_cycle_duration_ms != null ? status.poll_cycle_duration_ms + ' ms' : '-';
+                } catch (error) {
+                    showToast('Failed to load status: ' + error.message, 'error');
+                }
+            }
+
+            loadStatus();
+            setInterval(loadStatus, 5000);
+            "#))
+        }
+    };
+
+    base_layout("System Status - Headwind", content)
+}
+
+/// Detail template - individual update request view.
+/// `patch_preview` is the pretty-printed patch approving the request would apply.
+pub fn detail(
+    update: &UpdateRequestView,
+    patch_preview: Option<&str>,
+    clusters: &ClusterView,
+) -> Markup {
+    let dashboard_url = clusters.url("/");
     let content = html! {
         // Breadcrumbs
         div class="text-sm breadcrumbs mb-4" {
             ul {
-                li { a href="/" { "Dashboard" } }
+                li { a href=(dashboard_url) { "Dashboard" } }
                 li { (update.namespace) }
                 li { (update.name) }
             }
@@ -896,6 +1368,33 @@ pub fn detail(update: &UpdateRequestView) -> Markup {
                     }
                 }
 
+                @if !update.container_updates.is_empty() {
+                    div class="mt-4" {
+                        h3 class="text-lg font-semibold mb-2" { "Containers" }
+                        p class="text-sm opacity-70 mb-2" {
+                            "Every container is updated together in a single rollout."
+                        }
+                        table class="table table-sm" {
+                            thead {
+                                tr {
+                                    th { "Container" }
+                                    th { "Current Version" }
+                                    th { "New Version" }
+                                }
+                            }
+                            tbody {
+                                @for (container, current_version, new_version) in &update.container_updates {
+                                    tr {
+                                        td { (container) }
+                                        td { code class="version-display" { (current_version) } }
+                                        td { code class="version-display text-success" { (new_version) } }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
                 div class="divider" {}
 
                 // Metadata
@@ -932,20 +1431,276 @@ pub fn detail(update: &UpdateRequestView) -> Markup {
                     }
                 }
 
+                @if let Some(impact) = &update.impact {
+                    div class="divider" {}
+
+                    // Impact
+                    div {
+                        h3 class="text-lg font-semibold mb-2" { "Impact" }
+                        @if impact.violates_disruption_budget() {
+                            div class="alert alert-warning mb-2" {
+                                span { "The rollout takes down more pods at once than the PodDisruptionBudget allows." }
+                            }
+                        }
+                        div class="stats stats-vertical lg:stats-horizontal shadow" {
+                            div class="stat" {
+                                div class="stat-title" { "Replicas Restarted" }
+                                div class="stat-value text-2xl" { (impact.replica_count) }
+                            }
+                            div class="stat" {
+                                div class="stat-title" { "Unavailable at Once" }
+                                div class="stat-value text-2xl" {
+                                    (impact.max_unavailable.map_or("-".to_string(), |n| n.to_string()))
+                                }
+                            }
+                            div class="stat" {
+                                div class="stat-title" { "Estimated Downtime" }
+                                div class="stat-value text-2xl" {
+                                    (impact.estimated_downtime_seconds.map_or("unknown".to_string(), |s| format!("{}s", s)))
+                                }
+                                div class="stat-desc" { "with fewer ready replicas than desired" }
+                            }
+                            div class="stat" {
+                                div class="stat-title" { "PodDisruptionBudget" }
+                                @if let Some(pdb) = &impact.disruption_budget {
+                                    div class="stat-value text-2xl" { (pdb.name) }
+                                    div class="stat-desc" {
+                                        @if let Some(min_available) = &pdb.min_available {
+                                            "minAvailable " (min_available) ", "
+                                        }
+                                        @if let Some(max_unavailable) = &pdb.max_unavailable {
+                                            "maxUnavailable " (max_unavailable) ", "
+                                        }
+                                        (pdb.disruptions_allowed) " disruptions allowed"
+                                    }
+                                } @else {
+                                    div class="stat-value text-2xl" { "None" }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                @if !update.progress_steps.is_empty() {
+                    div class="divider" {}
+
+                    // Progress
+                    div {
+                        h3 class="text-lg font-semibold mb-2" { "Progress" }
+                        ul class="steps steps-vertical" {
+                            @for step in &update.progress_steps {
+                                li class=(format!("step {}", step_class(step.status))) {
+                                    div class="text-left" {
+                                        span class="font-semibold" { (step.step) }
+                                        " "
+                                        span class="badge badge-sm" { (format!("{:?}", step.status)) }
+                                        div class="text-xs opacity-70" {
+                                            (step.started_at.format("%Y-%m-%d %H:%M:%S UTC"))
+                                            @if let Some(completed_at) = step.completed_at {
+                                                (format!(" ({}s)", (completed_at - step.started_at).num_seconds()))
+                                            }
+                                        }
+                                        @if let Some(message) = &step.message {
+                                            div class="text-sm" { (message) }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                @if !update.rollback_history.is_empty() {
+                    div class="divider" {}
+
+                    // Rollback History
+                    div {
+                        h3 class="text-lg font-semibold mb-2" { "Rollback History" }
+                        div class="overflow-x-auto" {
+                            table class="table table-sm" {
+                                thead {
+                                    tr {
+                                        th { "Triggered At" }
+                                        th { "Reason" }
+                                        th { "Rolled Back To" }
+                                        th { "Triggered By" }
+                                    }
+                                }
+                                tbody {
+                                    @for rollback in &update.rollback_history {
+                                        tr {
+                                            td { (rollback.triggered_at.format("%Y-%m-%d %H:%M:%S UTC")) }
+                                            td { (rollback.reason) }
+                                            td { code class="text-xs" { (rollback.rolled_back_to) } }
+                                            td { (rollback.triggered_by) }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+                @if update.status == "Pending" {
+                    div class="divider" {}
+
+                    // Policy Decision
+                    div {
+                        h3 class="text-lg font-semibold mb-2" { "Policy Decision" }
+                        p class="text-sm opacity-70 mb-2" {
+                            "Whether the policy, minimum interval, update schedule and quota checks allow this update right now."
+                        }
+                        div id="policy-decision" class="alert" {
+                            span class="loading loading-spinner loading-sm" {}
+                            span { "Evaluating..." }
+                        }
+                    }
+
+                    script {
+                        (maud::PreEscaped(format!(r#"
+                        (async function() {{
+                            const box = document.getElementById('policy-decision');
+                            const params = new URLSearchParams({});
+                            try {{
+                                const response = await fetch('/api/v1/explain?' + params);
+                                const body = await response.json();
+                                if (!response.ok) {{
+                                    throw new Error(body.error || response.statusText);
+                                }}
+                                box.className = 'alert ' + (body.decision.allowed ? 'alert-success' : 'alert-warning');
+                                box.textContent = body.message + ' (evaluated ' + new Date(body.decision.evaluatedAt).toLocaleString() + ')';
+                            }} catch (e) {{
+                                box.className = 'alert alert-error';
+                                box.textContent = 'Could not evaluate the policy: ' + e.message;
+                            }}
+                        }})();
+                        "#, serde_json::json!({
+                            "resource_kind": update.resource_kind,
+                            "namespace": update.namespace,
+                            "name": update.resource_name,
+                            "candidate_tag": update.new_version,
+                            "container": update.container_name.as_deref().unwrap_or_default(),
+                            "cluster": clusters.selected.as_deref().unwrap_or_default(),
+                        }))))
+                    }
+                }
+
+                @if let Some(patch) = patch_preview {
+                    div class="divider" {}
+
+                    // Patch Preview
+                    div {
+                        div class="flex justify-between items-center mb-2" {
+                            h3 class="text-lg font-semibold" { "Patch Preview" }
+                            button id="dry-run-btn" class="btn btn-sm btn-outline" onclick="runDryRun()" {
+                                "Validate with Server-Side Dry Run"
+                            }
+                        }
+                        p class="text-sm opacity-70 mb-2" {
+                            "Strategic merge patch applied to the " (update.resource_kind) " when this update is approved."
+                        }
+                        div class="mockup-code" {
+                            pre id="patch-preview" class="px-4" { code { (patch) } }
+                        }
+                        div id="dry-run-error" class="alert alert-error mt-2 hidden" {}
+                    }
+
+                    // Plain fetch, so the global htmx handler does not reload the page
+                    script {
+                        (maud::PreEscaped(format!(r#"
+                        async function runDryRun() {{
+                            const btn = document.getElementById('dry-run-btn');
+                            const error = document.getElementById('dry-run-error');
+                            btn.classList.add('loading');
+                            error.classList.add('hidden');
+                            try {{
+                                const response = await fetch('{}');
+                                const body = await response.json();
+                                if (!response.ok) {{
+                                    throw new Error(body.error || response.statusText);
+                                }}
+                                document.querySelector('#patch-preview code').textContent = JSON.stringify(body, null, 2);
+                            }} catch (e) {{
+                                error.textContent = 'Dry run failed: ' + e.message;
+                                error.classList.remove('hidden');
+                            }} finally {{
+                                btn.classList.remove('loading');
+                            }}
+                        }}
+                        "#, clusters.url(&format!("/api/v1/update-requests/{}/{}/preview?server-side-dry-run=true", update.namespace, update.name)))))
+                    }
+                }
+
+                div class="divider" {}
+
+                // Discussion
+                div {
+                    h3 class="text-lg font-semibold mb-2" { "Discussion" }
+                    @if update.comments.is_empty() {
+                        p class="text-sm opacity-70 mb-2" { "No comments yet." }
+                    }
+                    @for comment in &update.comments {
+                        div class="chat chat-start" {
+                            div class="chat-header" {
+                                (comment.author)
+                                " "
+                                time class="text-xs opacity-50" { (comment.created_at.format("%Y-%m-%d %H:%M:%S UTC")) }
+                            }
+                            div class="chat-bubble whitespace-pre-wrap" { (comment.body) }
+                        }
+                    }
+                    div class="flex flex-col gap-2 mt-2" {
+                        textarea id="comment-body" class="textarea textarea-bordered w-full" placeholder="Add a comment..." rows="3" maxlength="4096" {}
+                        div id="comment-error" class="alert alert-error hidden" {}
+                        div class="flex justify-end" {
+                            button id="comment-btn" class="btn btn-sm btn-primary" onclick="addComment()" { "Comment" }
+                        }
+                    }
+                }
+
+                // Plain fetch, so the global htmx handler does not reload the page on errors
+                script {
+                    (maud::PreEscaped(format!(r#"
+                    async function addComment() {{
+                        const btn = document.getElementById('comment-btn');
+                        const error = document.getElementById('comment-error');
+                        btn.classList.add('loading');
+                        error.classList.add('hidden');
+                        try {{
+                            const response = await fetch('{}', {{
+                                method: 'POST',
+                                headers: {{ 'Content-Type': 'application/json' }},
+                                body: JSON.stringify({{ body: document.getElementById('comment-body').value }}),
+                            }});
+                            if (!response.ok) {{
+                                const body = await response.json().catch(() => ({{}}));
+                                throw new Error(body.error || response.statusText);
+                            }}
+                            window.location.reload();
+                        }} catch (e) {{
+                            error.textContent = 'Could not add the comment: ' + e.message;
+                            error.classList.remove('hidden');
+                        }} finally {{
+                            btn.classList.remove('loading');
+                        }}
+                    }}
+                    "#, clusters.url(&format!("/api/v1/update-requests/{}/{}/comments", update.namespace, update.name)))))
+                }
+
                 @if update.status == "Pending" {
                     div class="divider" {}
 
                     // Action Buttons
                     div class="card-actions justify-end" {
-                        a href="/" class="btn btn-ghost" { "Back to Dashboard" }
+                        a href=(dashboard_url) class="btn btn-ghost" { "Back to Dashboard" }
                         button class="btn btn-error" onclick="reject_modal.showModal()" {
                             "✗ Reject"
                         }
                         button class="btn btn-success"
-                            hx-post=(format!("/api/v1/updates/{}/{}/approve", update.namespace, update.name))
+                            hx-post=(clusters.url(&format!("/api/v1/updates/{}/{}/approve", update.namespace, update.name)))
                             hx-vals=r#"{"approver": "web-ui"}"#
                             hx-confirm="Are you sure you want to approve this update?"
-                            hx-on--after-request="window.location.href='/'" {
+                            hx-on--after-request=(format!("window.location.href='{}'", dashboard_url)) {
                             "✓ Approve Update"
                         }
                     }
@@ -956,14 +1711,14 @@ pub fn detail(update: &UpdateRequestView) -> Markup {
                             h3 class="font-bold text-lg" { "Reject Update" }
                             p class="py-4" { "Please provide a reason for rejecting this update:" }
                             form method="dialog" {
-                                textarea id="reject_reason" class="textarea textarea-bordered w-full" placeholder="Reason for rejection..." rows="4" {}
+                                textarea id="reject_reason" class="textarea textarea-bordered w-full" placeholder="Reason for rejection..." rows="4" required {}
                                 div class="modal-action" {
                                     button class="btn" { "Cancel" }
                                     button type="button" class="btn btn-error"
-                                        hx-post=(format!("/api/v1/updates/{}/{}/reject", update.namespace, update.name))
+                                        hx-post=(clusters.url(&format!("/api/v1/updates/{}/{}/reject", update.namespace, update.name)))
                                         hx-vals=r#"js:{approver: "web-ui", reason: document.getElementById("reject_reason").value}"#
-                                        hx-on--after-request="window.location.href='/'"
-                                        onclick="reject_modal.close()" {
+                                        hx-on--before-request="if (!document.getElementById('reject_reason').reportValidity()) event.preventDefault()"
+                                        hx-on--after-request=(format!("window.location.href='{}'", dashboard_url)) {
                                         "Reject Update"
                                     }
                                 }
@@ -976,7 +1731,7 @@ pub fn detail(update: &UpdateRequestView) -> Markup {
                 } @else {
                     div class="divider" {}
                     div class="card-actions justify-end" {
-                        a href="/" class="btn btn-primary" { "Back to Dashboard" }
+                        a href=(dashboard_url) class="btn btn-primary" { "Back to Dashboard" }
                     }
                 }
             }
@@ -987,6 +1742,64 @@ pub fn detail(update: &UpdateRequestView) -> Markup {
 }
 
 /// Helper function to get unique namespaces from updates
+/// Filter chips for the dashboard label selector. Each chip removes its
+/// requirement; the input adds one (e.g. `app=nginx` or `env!=dev`).
+/// daisyUI step colour for a progress step
+fn step_class(status: StepStatus) -> &'static str {
+    match status {
+        StepStatus::Running => "step-info",
+        StepStatus::Succeeded => "step-success",
+        StepStatus::Failed => "step-error",
+        StepStatus::Skipped => "step-neutral",
+    }
+}
+
+fn label_filter_chips(label_filters: &[String], clusters: &ClusterView) -> Markup {
+    let selector = label_filters.join(",");
+
+    html! {
+        div class="flex flex-wrap items-center gap-2 mb-6" {
+            span class="text-sm opacity-70" { "Labels:" }
+            @for (i, requirement) in label_filters.iter().enumerate() {
+                @let remaining = label_filters
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, r)| r.as_str())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                form method="get" action="/" class="inline" {
+                    input type="hidden" name="labelSelector" value=(remaining);
+                    (cluster_input(clusters))
+                    button type="submit" class="badge badge-primary gap-1" title="Remove filter" {
+                        (requirement)
+                        " ✕"
+                    }
+                }
+            }
+            form method="get" action="/" class="flex gap-2" {
+                input type="hidden" name="labelSelector" value=(selector);
+                (cluster_input(clusters))
+                input type="text" name="addLabel" placeholder="app=nginx"
+                    class="input input-bordered input-sm w-48";
+                button type="submit" class="btn btn-sm" { "Add label filter" }
+            }
+            @if !label_filters.is_empty() {
+                a href=(clusters.url("/")) class="btn btn-ghost btn-sm" { "Clear labels" }
+            }
+        }
+    }
+}
+
+/// Hidden `cluster` field keeping the selected cluster on GET forms
+fn cluster_input(clusters: &ClusterView) -> Markup {
+    html! {
+        @if let (Some(cluster), false) = (&clusters.selected, clusters.names.is_empty()) {
+            input type="hidden" name="cluster" value=(cluster);
+        }
+    }
+}
+
 fn get_unique_namespaces(updates: &[UpdateRequestView]) -> Vec<String> {
     let mut namespaces: Vec<String> = updates.iter().map(|u| u.namespace.clone()).collect();
     namespaces.sort();
@@ -1116,6 +1929,13 @@ pub fn settings() -> Markup {
                             }
                             input type="text" id="slack-icon-emoji" class="input input-bordered" value=":sailboat:";
                         }
+
+                        div class="form-control" {
+                            label class="label" {
+                                span class="label-text" { "Message Template ConfigMap" }
+                            }
+                            input type="text" id="slack-message-template-configmap" class="input input-bordered" placeholder="headwind-slack-template";
+                        }
                     }
                 }
             }
@@ -1146,6 +1966,86 @@ pub fn settings() -> Markup {
                 }
             }
 
+            // Telegram Notifications
+            div class="card bg-base-100 shadow-xl mb-6" {
+                div class="card-body" {
+                    h2 class="card-title text-2xl mb-4" {
+                        "Telegram Notifications"
+                        button class="btn btn-sm btn-outline ml-4" onclick="testNotification('telegram')" {
+                            "Test"
+                        }
+                    }
+
+                    div class="form-control mb-4" {
+                        label class="label cursor-pointer" {
+                            span class="label-text" { "Enable Telegram Notifications" }
+                            input type="checkbox" id="telegram-enabled" class="checkbox checkbox-primary";
+                        }
+                    }
+
+                    div class="form-control mb-4" {
+                        label class="label" {
+                            span class="label-text" { "Bot Token" }
+                        }
+                        input type="password" id="telegram-bot-token" class="input input-bordered" placeholder="123456789:AAE...";
+                    }
+
+                    div class="grid grid-cols-2 gap-4" {
+                        div class="form-control" {
+                            label class="label" {
+                                span class="label-text" { "Chat ID" }
+                            }
+                            input type="text" id="telegram-chat-id" class="input input-bordered" placeholder="-1001234567890";
+                        }
+
+                        div class="form-control" {
+                            label class="label" {
+                                span class="label-text" { "Parse Mode" }
+                            }
+                            select id="telegram-parse-mode" class="select select-bordered" {
+                                option value="Markdown" { "Markdown" }
+                                option value="HTML" { "HTML" }
+                            }
+                        }
+                    }
+                }
+            }
+
+            // VictorOps Notifications
+            div class="card bg-base-100 shadow-xl mb-6" {
+                div class="card-body" {
+                    h2 class="card-title text-2xl mb-4" {
+                        "VictorOps / Splunk On-Call"
+                        button class="btn btn-sm btn-outline ml-4" onclick="testNotification('victorops')" {
+                            "Test"
+                        }
+                    }
+
+                    div class="form-control mb-4" {
+                        label class="label cursor-pointer" {
+                            span class="label-text" { "Enable VictorOps Notifications" }
+                            input type="checkbox" id="victorops-enabled" class="checkbox checkbox-primary";
+                        }
+                    }
+
+                    div class="grid grid-cols-2 gap-4" {
+                        div class="form-control" {
+                            label class="label" {
+                                span class="label-text" { "REST Endpoint URL" }
+                            }
+                            input type="password" id="victorops-rest-endpoint-url" class="input input-bordered" placeholder="https://alert.victorops.com/integrations/generic/20131114/alert/...";
+                        }
+
+                        div class="form-control" {
+                            label class="label" {
+                                span class="label-text" { "Routing Key" }
+                            }
+                            input type="text" id="victorops-routing-key" class="input input-bordered" placeholder="platform";
+                        }
+                    }
+                }
+            }
+
             // Generic Webhook Notifications
             div class="card bg-base-100 shadow-xl mb-6" {
                 div class="card-body" {
@@ -1297,10 +2197,22 @@ pub fn settings() -> Markup {
                     document.getElementById('slack-channel').value = config.notifications.slack.channel || '';
                     document.getElementById('slack-username').value = config.notifications.slack.username || 'Headwind';
                     document.getElementById('slack-icon-emoji').value = config.notifications.slack.iconEmoji || ':sailboat:';
+                    document.getElementById('slack-message-template-configmap').value = config.notifications.slack.messageTemplateConfigMap || '';
 
                     document.getElementById('teams-enabled').checked = config.notifications.teams.enabled;
                     document.getElementById('teams-webhook-url').value = config.notifications.teams.webhookUrl || '';
 
+                    const telegram = config.notifications.telegram || {};
+                    document.getElementById('telegram-enabled').checked = !!telegram.enabled;
+                    document.getElementById('telegram-bot-token').value = telegram.botToken || '';
+                    document.getElementById('telegram-chat-id').value = telegram.chatId || '';
+                    document.getElementById('telegram-parse-mode').value = telegram.parseMode || 'Markdown';
+
+                    const victorops = config.notifications.victorops || {};
+                    document.getElementById('victorops-enabled').checked = !!victorops.enabled;
+                    document.getElementById('victorops-rest-endpoint-url').value = victorops.restEndpointUrl || '';
+                    document.getElementById('victorops-routing-key').value = victorops.routingKey || '';
+
                     document.getElementById('webhook-enabled').checked = config.notifications.webhook.enabled;
                     document.getElementById('webhook-url').value = config.notifications.webhook.url || '';
 
@@ -1344,12 +2256,24 @@ pub fn settings() -> Markup {
                             webhookUrl: document.getElementById('slack-webhook-url').value || null,
                             channel: document.getElementById('slack-channel').value || null,
                             username: document.getElementById('slack-username').value || null,
-                            iconEmoji: document.getElementById('slack-icon-emoji').value || null
+                            iconEmoji: document.getElementById('slack-icon-emoji').value || null,
+                            messageTemplateConfigMap: document.getElementById('slack-message-template-configmap').value || null
                         },
                         teams: {
                             enabled: document.getElementById('teams-enabled').checked,
                             webhookUrl: document.getElementById('teams-webhook-url').value || null
                         },
+                        telegram: {
+                            enabled: document.getElementById('telegram-enabled').checked,
+                            botToken: document.getElementById('telegram-bot-token').value || null,
+                            chatId: document.getElementById('telegram-chat-id').value || null,
+                            parseMode: document.getElementById('telegram-parse-mode').value
+                        },
+                        victorops: {
+                            enabled: document.getElementById('victorops-enabled').checked,
+                            restEndpointUrl: document.getElementById('victorops-rest-endpoint-url').value || null,
+                            routingKey: document.getElementById('victorops-routing-key').value || null
+                        },
                         webhook: {
                             enabled: document.getElementById('webhook-enabled').checked,
                             url: document.getElementById('webhook-url').value || null
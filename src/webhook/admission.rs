@@ -0,0 +1,1097 @@
+//! Validating admission webhook protecting the UpdateRequest audit trail.
+//!
+//! When `HEADWIND_ADMISSION_WEBHOOK_ENABLED=true`, headwind serves
+//! `/validate-updaterequests` over TLS on port 8443 and registers a
+//! ValidatingWebhookConfiguration at startup that rejects:
+//!
+//! 1. moving a finished UpdateRequest (`Completed`, `Rejected`, `Failed`,
+//!    `Expired`, `RolledBack`) back to `Pending`
+//! 2. changing `spec` after creation, other than scheduling its approval with
+//!    `spec.scheduledAt`, extending its expiry with `spec.expiresAt`,
+//!    escalating its `spec.priority` or appending to `spec.discussionThread`
+//! 3. rejecting without a reason in `status.message`, or with one longer than
+//!    `HEADWIND_MAX_REJECTION_REASON_LENGTH`
+//!
+//! With `HEADWIND_FEATURE_COSIGN_VERIFICATION=true` it also serves
+//! `/validate-image-signatures` for Pods, Deployments, StatefulSets and
+//! DaemonSets in namespaces annotated `headwind.sh/require-signature: "true"`.
+//! Verifying a signature takes too long for admission, so the webhook only
+//! consults the [`SignatureCache`]: images known to be unsigned are rejected,
+//! unknown images are admitted provisionally and verified in the background,
+//! and a `PodSignatureViolation` event is published if that verification fails.
+
+use super::tls::{GeneratedCert, generate_self_signed};
+use crate::approval::{max_rejection_reason_length, validate_rejection_reason};
+use crate::config::features::features;
+use crate::metrics::{SIGNATURE_ADMISSION_DENIALS_TOTAL, SIGNATURE_VIOLATIONS_TOTAL};
+use crate::models::crd::{UpdatePhase, UpdateRequest, UpdateRequestStatus};
+use crate::models::policy::annotations;
+use crate::polling::ShardedCache;
+use crate::security::cosign::SignatureVerifier;
+use crate::security::slsa::CosignVerifier;
+use anyhow::{Context, Result};
+use axum::{
+    Json, Router,
+    extract::State,
+    routing::{get, post},
+};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use k8s_openapi::ByteString;
+use k8s_openapi::api::admissionregistration::v1::{
+    RuleWithOperations, ServiceReference, ValidatingWebhook, ValidatingWebhookConfiguration,
+    WebhookClientConfig,
+};
+use k8s_openapi::api::core::v1::{Namespace, ObjectReference};
+use kube::api::{Api, Patch, PatchParams};
+use kube::core::admission::{AdmissionRequest, AdmissionResponse, AdmissionReview};
+use kube::core::{DynamicObject, GroupVersionKind};
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
+use kube::{Client, ResourceExt};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tower_http::trace::TraceLayer;
+use tracing::{debug, error, info, warn};
+
+const ADMISSION_PORT: u16 = 8443;
+const VALIDATE_PATH: &str = "/validate-updaterequests";
+const VALIDATE_SIGNATURES_PATH: &str = "/validate-image-signatures";
+const CONFIGURATION_NAME: &str = "headwind-updaterequest-validation";
+const WEBHOOK_NAME: &str = "updaterequests.headwind.sh";
+const SIGNATURE_WEBHOOK_NAME: &str = "image-signatures.headwind.sh";
+
+/// How long a signature verification result is trusted
+const SIGNATURE_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Images waiting for background verification before new ones are dropped
+const VERIFICATION_QUEUE_SIZE: usize = 256;
+
+/// Reason of the warning event published when a provisionally admitted image
+/// fails verification
+pub const SIGNATURE_VIOLATION_REASON: &str = "PodSignatureViolation";
+
+/// Whether the admission webhook is enabled (`HEADWIND_ADMISSION_WEBHOOK_ENABLED`, default false)
+pub fn admission_webhook_enabled() -> bool {
+    std::env::var("HEADWIND_ADMISSION_WEBHOOK_ENABLED")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(false)
+}
+
+/// Name of the Service routing to port 8443 (`HEADWIND_ADMISSION_SERVICE`,
+/// default `headwind-admission`)
+fn admission_service_name() -> String {
+    std::env::var("HEADWIND_ADMISSION_SERVICE").unwrap_or_else(|_| "headwind-admission".to_string())
+}
+
+fn is_terminal(phase: &UpdatePhase) -> bool {
+    matches!(
+        phase,
+        UpdatePhase::Completed
+            | UpdatePhase::Rejected
+            | UpdatePhase::Failed
+            | UpdatePhase::Expired
+            | UpdatePhase::RolledBack
+    )
+}
+
+fn is_rejected(status: &UpdateRequestStatus) -> bool {
+    status.phase == UpdatePhase::Rejected || status.rejected_by.is_some()
+}
+
+/// Check a create (`old` is None) or update of an UpdateRequest
+pub fn validate_update_request(
+    old: Option<&UpdateRequest>,
+    new: &UpdateRequest,
+) -> Result<(), String> {
+    if let Some(old) = old {
+        let old_phase = old.status.as_ref().map(|s| &s.phase);
+        let new_phase = new.status.as_ref().map(|s| &s.phase);
+        if let (Some(old_phase), Some(UpdatePhase::Pending)) = (old_phase, new_phase)
+            && is_terminal(old_phase)
+        {
+            return Err(format!(
+                "UpdateRequest is {:?} and cannot be moved back to Pending",
+                old_phase
+            ));
+        }
+
+        // Approvals can be (re)scheduled, expiry extended and priority
+        // escalated while the request waits
+        let fixed_spec = |ur: &UpdateRequest| {
+            let mut spec = ur.spec.clone();
+            spec.scheduled_at = None;
+            spec.expires_at = None;
+            spec.priority = None;
+            spec.discussion_thread = Vec::new();
+            serde_json::to_value(spec).ok()
+        };
+        if fixed_spec(old) != fixed_spec(new) {
+            return Err("UpdateRequest spec is immutable after creation".to_string());
+        }
+
+        // Comments can be added, but never edited or removed
+        if !new
+            .spec
+            .discussion_thread
+            .starts_with(&old.spec.discussion_thread)
+        {
+            return Err("Comments in spec.discussionThread can only be appended".to_string());
+        }
+    }
+
+    // Only the transition to Rejected is checked, so UpdateRequests rejected
+    // before a lower length limit can still be relabelled
+    let was_rejected = old
+        .and_then(|old| old.status.as_ref())
+        .is_some_and(is_rejected);
+    if let Some(status) = &new.status
+        && is_rejected(status)
+        && !was_rejected
+    {
+        validate_rejection_reason(status.message.as_deref(), max_rejection_reason_length())
+            .map_err(|e| format!("{} in status.message", e))?;
+    }
+
+    Ok(())
+}
+
+async fn validate(
+    Json(review): Json<AdmissionReview<UpdateRequest>>,
+) -> Json<AdmissionReview<DynamicObject>> {
+    let request: AdmissionRequest<UpdateRequest> = match review.try_into() {
+        Ok(request) => request,
+        Err(e) => {
+            warn!("Invalid admission review: {}", e);
+            return Json(AdmissionResponse::invalid(e.to_string()).into_review());
+        },
+    };
+
+    let mut response = AdmissionResponse::from(&request);
+    if let Some(object) = &request.object
+        && let Err(reason) = validate_update_request(request.old_object.as_ref(), object)
+    {
+        warn!(
+            "Denied {:?} of UpdateRequest {}/{}: {}",
+            request.operation,
+            request.namespace.as_deref().unwrap_or_default(),
+            object.name_any(),
+            reason
+        );
+        crate::metrics::ADMISSION_DENIALS_TOTAL.inc();
+        response = response.deny(reason);
+    }
+
+    Json(response.into_review())
+}
+
+/// Signature verification state of an image
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureStatus {
+    /// Queued for background verification
+    Pending,
+    Verified,
+    /// Verification failed, with the reason
+    Invalid(String),
+}
+
+#[derive(Debug, Clone)]
+struct CachedSignature {
+    status: SignatureStatus,
+    checked_at: Instant,
+}
+
+/// Signature verification results by image, filled by the background verifier
+pub struct SignatureCache {
+    entries: ShardedCache<CachedSignature>,
+    ttl: Duration,
+}
+
+impl SignatureCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: ShardedCache::default(),
+            ttl,
+        }
+    }
+
+    /// Status of `image`, unless unknown or older than the TTL
+    pub fn get(&self, image: &str) -> Option<SignatureStatus> {
+        self.get_at(image, Instant::now())
+    }
+
+    fn get_at(&self, image: &str, now: Instant) -> Option<SignatureStatus> {
+        self.entries
+            .get(image)
+            .filter(|entry| now.duration_since(entry.checked_at) < self.ttl)
+            .map(|entry| entry.status)
+    }
+
+    pub fn insert(&self, image: &str, status: SignatureStatus) {
+        self.insert_at(image, status, Instant::now());
+    }
+
+    fn insert_at(&self, image: &str, status: SignatureStatus, now: Instant) {
+        self.entries.insert(
+            image,
+            CachedSignature {
+                status,
+                checked_at: now,
+            },
+        );
+    }
+}
+
+impl Default for SignatureCache {
+    fn default() -> Self {
+        Self::new(SIGNATURE_CACHE_TTL)
+    }
+}
+
+/// Image to verify after its object was admitted provisionally
+#[derive(Debug)]
+struct VerificationJob {
+    image: String,
+    /// Object the `PodSignatureViolation` event is published on
+    object_ref: ObjectReference,
+}
+
+#[derive(Clone)]
+struct SignatureAdmission {
+    client: Client,
+    cache: Arc<SignatureCache>,
+    jobs: mpsc::Sender<VerificationJob>,
+}
+
+/// Check `images` against the cache. Returns the reason to deny if one is
+/// known to be unsigned, or else the images to verify in the background,
+/// which are marked pending so later admissions don't queue them again.
+fn check_signatures(
+    cache: &SignatureCache,
+    images: &[String],
+    now: Instant,
+) -> Result<Vec<String>, String> {
+    let mut unverified = Vec::new();
+    for image in images {
+        match cache.get_at(image, now) {
+            Some(SignatureStatus::Verified) | Some(SignatureStatus::Pending) => {},
+            Some(SignatureStatus::Invalid(reason)) => {
+                return Err(format!(
+                    "Image {} has no valid cosign signature: {}",
+                    image, reason
+                ));
+            },
+            None => {
+                cache.insert_at(image, SignatureStatus::Pending, now);
+                unverified.push(image.clone());
+            },
+        }
+    }
+    Ok(unverified)
+}
+
+/// Container and init container images of a Pod, or of the pod template of a
+/// Deployment, StatefulSet or DaemonSet
+fn pod_spec_images(kind: &str, object: &DynamicObject) -> Vec<String> {
+    let spec = match kind {
+        "Pod" => &object.data["spec"],
+        _ => &object.data["spec"]["template"]["spec"],
+    };
+
+    let mut images: Vec<String> = ["initContainers", "containers"]
+        .iter()
+        .filter_map(|field| spec[field].as_array())
+        .flatten()
+        .filter_map(|container| container["image"].as_str())
+        .map(String::from)
+        .collect();
+    images.sort();
+    images.dedup();
+    images
+}
+
+/// Object to report a violation on. Pods created by a controller have no name
+/// yet when admitted, so their owner is used instead.
+fn violation_ref(
+    kind: &GroupVersionKind,
+    namespace: &str,
+    object: &DynamicObject,
+) -> ObjectReference {
+    let owner = object.metadata.owner_references.iter().flatten().next();
+    match (&object.metadata.name, owner) {
+        (None, Some(owner)) => ObjectReference {
+            api_version: Some(owner.api_version.clone()),
+            kind: Some(owner.kind.clone()),
+            name: Some(owner.name.clone()),
+            namespace: Some(namespace.to_string()),
+            uid: Some(owner.uid.clone()),
+            ..Default::default()
+        },
+        (name, _) => ObjectReference {
+            api_version: Some(kind.api_version()),
+            kind: Some(kind.kind.clone()),
+            name: name
+                .clone()
+                .or_else(|| object.metadata.generate_name.clone()),
+            namespace: Some(namespace.to_string()),
+            uid: object.metadata.uid.clone(),
+            ..Default::default()
+        },
+    }
+}
+
+/// Whether `namespace` is annotated `headwind.sh/require-signature: "true"`.
+/// Namespaces that can't be read are not enforced.
+async fn namespace_requires_signature(client: &Client, namespace: &str) -> bool {
+    let namespaces: Api<Namespace> = Api::all(client.clone());
+    match namespaces.get_opt(namespace).await {
+        Ok(namespace) => namespace
+            .and_then(|ns| ns.metadata.annotations)
+            .and_then(|annotations| annotations.get(annotations::REQUIRE_SIGNATURE).cloned())
+            .is_some_and(|value| value == "true"),
+        Err(e) => {
+            warn!(
+                "Failed to read namespace {}, admitting without signature checks: {}",
+                namespace, e
+            );
+            false
+        },
+    }
+}
+
+async fn validate_signatures(
+    State(state): State<SignatureAdmission>,
+    Json(review): Json<AdmissionReview<DynamicObject>>,
+) -> Json<AdmissionReview<DynamicObject>> {
+    let request: AdmissionRequest<DynamicObject> = match review.try_into() {
+        Ok(request) => request,
+        Err(e) => {
+            warn!("Invalid admission review: {}", e);
+            return Json(AdmissionResponse::invalid(e.to_string()).into_review());
+        },
+    };
+
+    let mut response = AdmissionResponse::from(&request);
+    let (Some(object), Some(namespace)) = (&request.object, request.namespace.as_deref()) else {
+        return Json(response.into_review());
+    };
+    if !namespace_requires_signature(&state.client, namespace).await {
+        return Json(response.into_review());
+    }
+
+    let images = pod_spec_images(&request.kind.kind, object);
+    match check_signatures(&state.cache, &images, Instant::now()) {
+        Ok(unverified) => {
+            let object_ref = violation_ref(&request.kind, namespace, object);
+            for image in unverified {
+                debug!(
+                    "Admitted {} {}/{} before verifying the signature of {}",
+                    request.kind.kind,
+                    namespace,
+                    object_ref.name.as_deref().unwrap_or_default(),
+                    image
+                );
+                let job = VerificationJob {
+                    image,
+                    object_ref: object_ref.clone(),
+                };
+                if let Err(e) = state.jobs.try_send(job) {
+                    // Stays pending until the TTL passes, then is queued again
+                    warn!("Signature verification queue is full, dropping {:?}", e);
+                }
+            }
+        },
+        Err(reason) => {
+            warn!(
+                "Denied {:?} of {} {}/{}: {}",
+                request.operation,
+                request.kind.kind,
+                namespace,
+                object.name_any(),
+                reason
+            );
+            SIGNATURE_ADMISSION_DENIALS_TOTAL.inc();
+            response = response.deny(reason);
+        },
+    }
+
+    Json(response.into_review())
+}
+
+/// Verify the signature of `image` and record the result in `cache`
+async fn verify_and_record(
+    verifier: &dyn SignatureVerifier,
+    cache: &SignatureCache,
+    image: &str,
+) -> Result<()> {
+    let result = verifier.verify_signature(image).await;
+    let status = match &result {
+        Ok(()) => SignatureStatus::Verified,
+        Err(e) => SignatureStatus::Invalid(format!("{:#}", e)),
+    };
+    cache.insert(image, status);
+    result
+}
+
+/// Verify queued images one at a time, publishing a `PodSignatureViolation`
+/// event on the admitted object when verification fails
+fn spawn_signature_verifier(
+    client: Client,
+    cache: Arc<SignatureCache>,
+    verifier: Arc<dyn SignatureVerifier>,
+    mut jobs: mpsc::Receiver<VerificationJob>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        while let Some(job) = jobs.recv().await {
+            match verify_and_record(verifier.as_ref(), &cache, &job.image).await {
+                Ok(()) => debug!("Verified cosign signature of {}", job.image),
+                Err(e) => {
+                    warn!(
+                        "Admitted image {} has no valid cosign signature: {:#}",
+                        job.image, e
+                    );
+                    SIGNATURE_VIOLATIONS_TOTAL.inc();
+                    let note = format!(
+                        "Image {} was admitted before its cosign signature failed verification: {:#}",
+                        job.image, e
+                    );
+                    publish_signature_violation(&client, &job.object_ref, &note).await;
+                },
+            }
+        }
+    })
+}
+
+async fn publish_signature_violation(client: &Client, object_ref: &ObjectReference, note: &str) {
+    let recorder = Recorder::new(client.clone(), Reporter::from("headwind"));
+    let event = Event {
+        type_: EventType::Warning,
+        reason: SIGNATURE_VIOLATION_REASON.to_string(),
+        note: Some(note.to_string()),
+        action: "VerifySignature".to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(&event, object_ref).await {
+        warn!(
+            "Failed to publish {} event for {}/{}: {}",
+            SIGNATURE_VIOLATION_REASON,
+            object_ref.namespace.as_deref().unwrap_or_default(),
+            object_ref.name.as_deref().unwrap_or_default(),
+            e
+        );
+    }
+}
+
+/// Build the admission webhook router
+pub fn router() -> Router {
+    Router::new()
+        .route(VALIDATE_PATH, post(validate))
+        .route("/health", get(|| async { "OK" }))
+        .layer(TraceLayer::new_for_http())
+}
+
+fn signature_router(state: SignatureAdmission) -> Router {
+    Router::new()
+        .route(VALIDATE_SIGNATURES_PATH, post(validate_signatures))
+        .with_state(state)
+        .layer(TraceLayer::new_for_http())
+}
+
+/// Start the TLS admission server and register the webhook, if enabled
+pub async fn start_admission_server(client: Client) -> Result<Option<JoinHandle<()>>> {
+    if !admission_webhook_enabled() {
+        return Ok(None);
+    }
+
+    let namespace = crate::config::headwind_namespace();
+    let service = admission_service_name();
+    let dns_names = vec![
+        format!("{}.{}.svc", service, namespace),
+        format!("{}.{}.svc.cluster.local", service, namespace),
+        format!("{}.{}", service, namespace),
+        service.clone(),
+    ];
+    let cert = generate_self_signed(&dns_names)?;
+    let acceptor = tls_acceptor(&cert)?;
+
+    let addr = format!("0.0.0.0:{}", ADMISSION_PORT);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .with_context(|| format!("Failed to bind admission server on {}", addr))?;
+    info!("Starting admission webhook server on {}", addr);
+
+    let signatures = features().cosign_verification;
+    let mut app = router();
+    if signatures {
+        let cache = Arc::new(SignatureCache::default());
+        let (jobs, queue) = mpsc::channel(VERIFICATION_QUEUE_SIZE);
+        spawn_signature_verifier(
+            client.clone(),
+            cache.clone(),
+            Arc::new(CosignVerifier::from_env()),
+            queue,
+        );
+        app = app.merge(signature_router(SignatureAdmission {
+            client: client.clone(),
+            cache,
+            jobs,
+        }));
+    }
+
+    let handle = tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    error!("Admission server accept failed: {}", e);
+                    continue;
+                },
+            };
+
+            let acceptor = acceptor.clone();
+            let service = TowerToHyperService::new(app.clone());
+            tokio::spawn(async move {
+                let stream = match acceptor.accept(stream).await {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        debug!("TLS handshake with {} failed: {}", peer, e);
+                        return;
+                    },
+                };
+                if let Err(e) = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    debug!("Admission connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    });
+
+    register_webhook(&client, &service, &namespace, &cert, signatures).await?;
+
+    Ok(Some(handle))
+}
+
+fn tls_acceptor(cert: &GeneratedCert) -> Result<tokio_rustls::TlsAcceptor> {
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(
+        vec![CertificateDer::from(cert.cert_der.clone())],
+        PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.key_pkcs8.clone())),
+    )?;
+
+    Ok(tokio_rustls::TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Build the ValidatingWebhookConfiguration pointing at `service` with
+/// `ca_bundle`, including the image signature webhook if `signatures` is set
+fn webhook_configuration(
+    service: &str,
+    namespace: &str,
+    ca_bundle: &str,
+    signatures: bool,
+) -> ValidatingWebhookConfiguration {
+    let client_config = |path: &str| WebhookClientConfig {
+        service: Some(ServiceReference {
+            name: service.to_string(),
+            namespace: namespace.to_string(),
+            path: Some(path.to_string()),
+            port: Some(443),
+        }),
+        ca_bundle: Some(ByteString(ca_bundle.as_bytes().to_vec())),
+        url: None,
+    };
+
+    let mut webhooks = vec![ValidatingWebhook {
+        name: WEBHOOK_NAME.to_string(),
+        admission_review_versions: vec!["v1".to_string()],
+        side_effects: "None".to_string(),
+        failure_policy: Some("Fail".to_string()),
+        timeout_seconds: Some(5),
+        client_config: client_config(VALIDATE_PATH),
+        rules: Some(vec![RuleWithOperations {
+            api_groups: Some(vec!["headwind.sh".to_string()]),
+            api_versions: Some(vec!["v1alpha1".to_string()]),
+            operations: Some(vec!["CREATE".to_string(), "UPDATE".to_string()]),
+            resources: Some(vec![
+                "updaterequests".to_string(),
+                "updaterequests/status".to_string(),
+            ]),
+            scope: Some("Namespaced".to_string()),
+        }]),
+        ..Default::default()
+    }];
+
+    // Pods must keep scheduling while headwind is down
+    if signatures {
+        let rule = |group: &str, resources: &[&str]| RuleWithOperations {
+            api_groups: Some(vec![group.to_string()]),
+            api_versions: Some(vec!["v1".to_string()]),
+            operations: Some(vec!["CREATE".to_string(), "UPDATE".to_string()]),
+            resources: Some(resources.iter().map(|r| r.to_string()).collect()),
+            scope: Some("Namespaced".to_string()),
+        };
+        webhooks.push(ValidatingWebhook {
+            name: SIGNATURE_WEBHOOK_NAME.to_string(),
+            admission_review_versions: vec!["v1".to_string()],
+            side_effects: "None".to_string(),
+            failure_policy: Some("Ignore".to_string()),
+            timeout_seconds: Some(5),
+            client_config: client_config(VALIDATE_SIGNATURES_PATH),
+            rules: Some(vec![
+                rule("", &["pods"]),
+                rule("apps", &["deployments", "statefulsets", "daemonsets"]),
+            ]),
+            ..Default::default()
+        });
+    }
+
+    ValidatingWebhookConfiguration {
+        metadata: kube::api::ObjectMeta {
+            name: Some(CONFIGURATION_NAME.to_string()),
+            labels: Some(
+                [("app".to_string(), "headwind".to_string())]
+                    .into_iter()
+                    .collect(),
+            ),
+            ..Default::default()
+        },
+        webhooks: Some(webhooks),
+    }
+}
+
+/// Apply the ValidatingWebhookConfiguration with the current certificate
+async fn register_webhook(
+    client: &Client,
+    service: &str,
+    namespace: &str,
+    cert: &GeneratedCert,
+    signatures: bool,
+) -> Result<()> {
+    let configs: Api<ValidatingWebhookConfiguration> = Api::all(client.clone());
+    let config = webhook_configuration(service, namespace, &cert.cert_pem(), signatures);
+
+    configs
+        .patch(
+            CONFIGURATION_NAME,
+            &PatchParams::apply("headwind").force(),
+            &Patch::Apply(&config),
+        )
+        .await
+        .context("Failed to register ValidatingWebhookConfiguration")?;
+
+    info!(
+        "Registered ValidatingWebhookConfiguration {} for service {}/{}",
+        CONFIGURATION_NAME, namespace, service
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::crd::Comment;
+    use crate::testing;
+    use serde_json::json;
+
+    fn update_request(phase: UpdatePhase) -> UpdateRequest {
+        testing::update_request_in_phase("default", "web-nginx-1-26-0", phase)
+    }
+
+    #[test]
+    fn test_allows_normal_lifecycle() {
+        let pending = update_request(UpdatePhase::Pending);
+        assert!(validate_update_request(None, &pending).is_ok());
+
+        let completed = update_request(UpdatePhase::Completed);
+        assert!(validate_update_request(Some(&pending), &completed).is_ok());
+
+        let mut rejected = update_request(UpdatePhase::Rejected);
+        let status = rejected.status.as_mut().unwrap();
+        status.rejected_by = Some("alice".to_string());
+        status.message = Some("Waiting for the 1.26.1 fix".to_string());
+        assert!(validate_update_request(Some(&pending), &rejected).is_ok());
+
+        // Later changes to an already rejected request are not re-checked
+        let mut relabelled = rejected.clone();
+        relabelled.status.as_mut().unwrap().message = None;
+        assert!(validate_update_request(Some(&rejected), &relabelled).is_ok());
+    }
+
+    #[test]
+    fn test_denies_terminal_to_pending() {
+        let pending = update_request(UpdatePhase::Pending);
+        for phase in [
+            UpdatePhase::Completed,
+            UpdatePhase::Rejected,
+            UpdatePhase::Failed,
+            UpdatePhase::Expired,
+            UpdatePhase::RolledBack,
+        ] {
+            let mut terminal = update_request(phase);
+            terminal.status.as_mut().unwrap().message = Some("done".to_string());
+            let err = validate_update_request(Some(&terminal), &pending).unwrap_err();
+            assert!(err.contains("cannot be moved back to Pending"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_denies_spec_changes() {
+        let old = update_request(UpdatePhase::Pending);
+        let mut new = old.clone();
+        new.spec.new_image = "nginx:1.27.0".to_string();
+
+        let err = validate_update_request(Some(&old), &new).unwrap_err();
+        assert!(err.contains("immutable"), "{}", err);
+    }
+
+    #[test]
+    fn test_allows_scheduling_approval() {
+        let old = update_request(UpdatePhase::Pending);
+        let mut new = old.clone();
+        new.spec.scheduled_at = Some(chrono::Utc::now() + chrono::Duration::hours(1));
+
+        assert!(validate_update_request(Some(&old), &new).is_ok());
+        assert!(validate_update_request(Some(&new), &old).is_ok());
+    }
+
+    #[test]
+    fn test_allows_extending_expiry() {
+        let old = update_request(UpdatePhase::Pending);
+        let mut new = old.clone();
+        new.spec.expires_at = Some(chrono::Utc::now() + chrono::Duration::hours(48));
+
+        assert!(validate_update_request(Some(&old), &new).is_ok());
+    }
+
+    #[test]
+    fn test_allows_escalating_priority() {
+        let old = update_request(UpdatePhase::Pending);
+        let mut new = old.clone();
+        new.spec.priority = Some(100);
+        assert!(validate_update_request(Some(&old), &new).is_ok());
+
+        let mut raised = new.clone();
+        raised.spec.priority = Some(200);
+        assert!(validate_update_request(Some(&new), &raised).is_ok());
+    }
+
+    #[test]
+    fn test_allows_appending_comments_only() {
+        let old = update_request(UpdatePhase::Pending);
+        let comment = |body: &str| Comment {
+            author: "alice".to_string(),
+            body: body.to_string(),
+            created_at: chrono::Utc::now(),
+        };
+
+        let mut commented = old.clone();
+        commented
+            .spec
+            .discussion_thread
+            .push(comment("Checked the changelog"));
+        assert!(validate_update_request(Some(&old), &commented).is_ok());
+
+        let mut replied = commented.clone();
+        replied.spec.discussion_thread.push(comment("Fine by me"));
+        assert!(validate_update_request(Some(&commented), &replied).is_ok());
+
+        let mut edited = replied.clone();
+        edited.spec.discussion_thread[0].body = "Nothing to see".to_string();
+        let err = validate_update_request(Some(&replied), &edited).unwrap_err();
+        assert!(err.contains("can only be appended"), "{}", err);
+
+        let err = validate_update_request(Some(&replied), &old).unwrap_err();
+        assert!(err.contains("can only be appended"), "{}", err);
+    }
+
+    #[test]
+    fn test_denies_rejection_without_message() {
+        let pending = update_request(UpdatePhase::Pending);
+        for message in [None, Some(""), Some("   ")] {
+            let mut rejected = update_request(UpdatePhase::Rejected);
+            let status = rejected.status.as_mut().unwrap();
+            status.rejected_by = Some("alice".to_string());
+            status.message = message.map(String::from);
+
+            assert!(validate_update_request(Some(&pending), &rejected).is_err());
+            assert!(validate_update_request(None, &rejected).is_err());
+        }
+
+        // The phase alone counts as a rejection
+        let rejected = update_request(UpdatePhase::Rejected);
+        let err = validate_update_request(Some(&pending), &rejected).unwrap_err();
+        assert_eq!(err, "A rejection reason is required in status.message");
+
+        let mut rejected = update_request(UpdatePhase::Rejected);
+        rejected.status.as_mut().unwrap().message = Some("x".repeat(2049));
+        let err = validate_update_request(Some(&pending), &rejected).unwrap_err();
+        assert!(err.contains("maximum is 2048"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_validate_handler_denies_review() {
+        let old = update_request(UpdatePhase::Completed);
+        let new = update_request(UpdatePhase::Pending);
+        let review: AdmissionReview<UpdateRequest> = serde_json::from_value(json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "kind": { "group": "headwind.sh", "version": "v1alpha1", "kind": "UpdateRequest" },
+                "resource": { "group": "headwind.sh", "version": "v1alpha1", "resource": "updaterequests" },
+                "subResource": "status",
+                "name": "web-nginx-1-26-0",
+                "namespace": "default",
+                "operation": "UPDATE",
+                "userInfo": { "username": "mallory" },
+                "object": new,
+                "oldObject": old,
+                "dryRun": false
+            }
+        }))
+        .unwrap();
+
+        let Json(response) = validate(Json(review)).await;
+        let response = serde_json::to_value(response).unwrap();
+        assert_eq!(
+            response["response"]["uid"],
+            "705ab4f5-6393-11e8-b7cc-42010a800002"
+        );
+        assert_eq!(response["response"]["allowed"], false);
+    }
+
+    #[test]
+    fn test_webhook_configuration() {
+        let config = webhook_configuration("headwind-admission", "headwind-system", "PEM", false);
+        assert_eq!(config.webhooks.as_ref().unwrap().len(), 1);
+        let webhook = &config.webhooks.as_ref().unwrap()[0];
+        let service = webhook.client_config.service.as_ref().unwrap();
+
+        assert_eq!(service.namespace, "headwind-system");
+        assert_eq!(service.path.as_deref(), Some(VALIDATE_PATH));
+        assert_eq!(
+            webhook.rules.as_ref().unwrap()[0]
+                .resources
+                .as_ref()
+                .unwrap(),
+            &vec![
+                "updaterequests".to_string(),
+                "updaterequests/status".to_string()
+            ]
+        );
+    }
+
+    fn images(list: &[&str]) -> Vec<String> {
+        list.iter().map(|i| i.to_string()).collect()
+    }
+
+    #[test]
+    fn test_signature_cache_miss_admits_and_queues_once() {
+        let cache = SignatureCache::default();
+        let now = Instant::now();
+
+        let unverified = check_signatures(&cache, &images(&["nginx:1.26.0"]), now).unwrap();
+        assert_eq!(unverified, images(&["nginx:1.26.0"]));
+        assert_eq!(
+            cache.get_at("nginx:1.26.0", now),
+            Some(SignatureStatus::Pending)
+        );
+
+        // Already queued, so the next pod is admitted without queueing it again
+        let unverified = check_signatures(&cache, &images(&["nginx:1.26.0"]), now).unwrap();
+        assert!(unverified.is_empty());
+    }
+
+    #[test]
+    fn test_signature_cache_hit() {
+        let cache = SignatureCache::default();
+        let now = Instant::now();
+        cache.insert_at("nginx:1.26.0", SignatureStatus::Verified, now);
+        cache.insert_at(
+            "evil:latest",
+            SignatureStatus::Invalid("no signatures found".to_string()),
+            now,
+        );
+
+        assert_eq!(
+            check_signatures(&cache, &images(&["nginx:1.26.0"]), now),
+            Ok(Vec::new())
+        );
+        let reason =
+            check_signatures(&cache, &images(&["nginx:1.26.0", "evil:latest"]), now).unwrap_err();
+        assert_eq!(
+            reason,
+            "Image evil:latest has no valid cosign signature: no signatures found"
+        );
+    }
+
+    #[test]
+    fn test_signature_cache_entries_expire() {
+        let cache = SignatureCache::default();
+        let start = Instant::now();
+        cache.insert_at(
+            "evil:latest",
+            SignatureStatus::Invalid("no signatures found".to_string()),
+            start,
+        );
+
+        let later = start + SIGNATURE_CACHE_TTL - Duration::from_secs(1);
+        assert!(check_signatures(&cache, &images(&["evil:latest"]), later).is_err());
+
+        // Once the result is stale the image is admitted and verified again
+        let expired = start + SIGNATURE_CACHE_TTL;
+        assert_eq!(cache.get_at("evil:latest", expired), None);
+        assert_eq!(
+            check_signatures(&cache, &images(&["evil:latest"]), expired),
+            Ok(images(&["evil:latest"]))
+        );
+    }
+
+    struct MockSignatureVerifier;
+
+    #[async_trait::async_trait]
+    impl SignatureVerifier for MockSignatureVerifier {
+        async fn verify_signature(&self, image: &str) -> Result<()> {
+            if image.starts_with("signed/") {
+                Ok(())
+            } else {
+                anyhow::bail!("no signatures found")
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verification_fills_cache() {
+        let cache = SignatureCache::default();
+        assert!(
+            verify_and_record(&MockSignatureVerifier, &cache, "signed/web:1.0.0")
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            cache.get("signed/web:1.0.0"),
+            Some(SignatureStatus::Verified)
+        );
+
+        assert!(
+            verify_and_record(&MockSignatureVerifier, &cache, "web:1.0.0")
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            cache.get("web:1.0.0"),
+            Some(SignatureStatus::Invalid("no signatures found".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_pod_spec_images() {
+        let pod: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": { "generateName": "web-7d4b9c-", "namespace": "default" },
+            "spec": {
+                "initContainers": [{ "name": "migrate", "image": "web:1.2.0" }],
+                "containers": [
+                    { "name": "web", "image": "web:1.2.0" },
+                    { "name": "envoy", "image": "envoy:1.30.0" }
+                ]
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            pod_spec_images("Pod", &pod),
+            images(&["envoy:1.30.0", "web:1.2.0"])
+        );
+
+        let deployment: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "web", "namespace": "default" },
+            "spec": {
+                "template": {
+                    "spec": { "containers": [{ "name": "web", "image": "web:1.2.0" }] }
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            pod_spec_images("Deployment", &deployment),
+            images(&["web:1.2.0"])
+        );
+    }
+
+    #[test]
+    fn test_violation_ref_uses_owner_of_unnamed_pods() {
+        let pod_kind = GroupVersionKind::gvk("", "v1", "Pod");
+        let pod: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "generateName": "web-7d4b9c-",
+                "namespace": "default",
+                "ownerReferences": [{
+                    "apiVersion": "apps/v1",
+                    "kind": "ReplicaSet",
+                    "name": "web-7d4b9c",
+                    "uid": "d9607e19-f88f-11e6-a518-42010a800195"
+                }]
+            }
+        }))
+        .unwrap();
+        let object_ref = violation_ref(&pod_kind, "default", &pod);
+        assert_eq!(object_ref.kind.as_deref(), Some("ReplicaSet"));
+        assert_eq!(object_ref.name.as_deref(), Some("web-7d4b9c"));
+
+        let deployment_kind = GroupVersionKind::gvk("apps", "v1", "Deployment");
+        let deployment: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": "web", "namespace": "default" }
+        }))
+        .unwrap();
+        let object_ref = violation_ref(&deployment_kind, "default", &deployment);
+        assert_eq!(object_ref.api_version.as_deref(), Some("apps/v1"));
+        assert_eq!(object_ref.kind.as_deref(), Some("Deployment"));
+        assert_eq!(object_ref.name.as_deref(), Some("web"));
+    }
+
+    #[test]
+    fn test_signature_webhook_configuration() {
+        let config = webhook_configuration("headwind-admission", "headwind-system", "PEM", true);
+        let webhooks = config.webhooks.unwrap();
+        assert_eq!(webhooks.len(), 2);
+
+        let signatures = &webhooks[1];
+        assert_eq!(signatures.name, SIGNATURE_WEBHOOK_NAME);
+        assert_eq!(signatures.failure_policy.as_deref(), Some("Ignore"));
+        assert_eq!(
+            signatures
+                .client_config
+                .service
+                .as_ref()
+                .unwrap()
+                .path
+                .as_deref(),
+            Some(VALIDATE_SIGNATURES_PATH)
+        );
+        let rules = signatures.rules.as_ref().unwrap();
+        assert_eq!(
+            rules[0].resources.as_deref(),
+            Some(&["pods".to_string()][..])
+        );
+        assert_eq!(
+            rules[1].api_groups.as_deref(),
+            Some(&["apps".to_string()][..])
+        );
+    }
+}
@@ -1,11 +1,33 @@
+pub mod admission;
+pub mod mtls;
+pub mod processor;
+pub mod tls;
+
+use self::mtls::ClientIdentity;
+use self::processor::{EnqueueError, RETRY_AFTER_SECS, WebhookEventProcessor};
+use crate::cache::list_all;
+use crate::config::WebhookTlsConfig;
+use crate::controller::self_update::self_update_for;
 use crate::metrics::{WEBHOOK_EVENTS_PROCESSED, WEBHOOK_EVENTS_TOTAL};
 use crate::models::webhook::{ChartPushEvent, DockerHubWebhook, ImagePushEvent, RegistryWebhook};
-use crate::models::{EventSource, ResourcePolicy, annotations};
+use crate::models::{
+    Composition, EventSource, KnativeService, ResourcePolicy, annotations,
+    global_tag_normalization, parse_container_names, parse_slsa_level, parse_tag_normalization,
+};
 use crate::policy::PolicyEngine;
+use crate::policy::ignore::{ignore_patterns, is_image_ignored, is_resource_ignored};
 use anyhow::Result;
-use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::post};
-use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use axum::{
+    Extension, Json, Router,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet};
+use k8s_openapi::api::core::v1::PodSpec;
 use kube::{Api, Client, ResourceExt};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
@@ -19,7 +41,7 @@ pub type ChartEventReceiver = mpsc::UnboundedReceiver<ChartPushEvent>;
 
 #[derive(Clone)]
 struct WebhookState {
-    event_tx: EventSender,
+    processor: WebhookEventProcessor,
     chart_event_tx: ChartEventSender,
 }
 
@@ -28,52 +50,84 @@ pub async fn start_webhook_server() -> Result<(JoinHandle<()>, EventSender, Char
     let (chart_event_tx, chart_event_rx) = mpsc::unbounded_channel();
 
     // Clone senders to return them
-    let event_tx_clone = event_tx.clone();
     let chart_event_tx_clone = chart_event_tx.clone();
 
+    // Image push events from webhooks and polling share one worker pool
+    let client = Client::try_default().await?;
+    let policy_engine = Arc::new(PolicyEngine);
+    let (processor, _) = WebhookEventProcessor::start(
+        processor::webhook_workers(),
+        processor::QUEUE_CAPACITY,
+        move |event| {
+            let client = client.clone();
+            let policy_engine = policy_engine.clone();
+            async move {
+                info!("Processing image push event: {}", event.full_image());
+                process_image_push_event(&client, &policy_engine, &event).await
+            }
+        },
+    );
+
     // Spawn processors for both event types
-    tokio::spawn(process_webhook_events(event_rx));
+    tokio::spawn(forward_polled_events(event_rx, processor.clone()));
     tokio::spawn(process_chart_events(chart_event_rx));
 
     let state = WebhookState {
-        event_tx,
+        processor,
         chart_event_tx,
     };
 
     let app = Router::new()
         .route("/webhook/registry", post(handle_registry_webhook))
         .route("/webhook/dockerhub", post(handle_dockerhub_webhook))
+        .route("/webhook/github", post(handle_github_webhook))
+        .route("/webhooks/gitlab-ci", post(handle_gitlab_ci_webhook))
         .route("/health", axum::routing::get(health_check))
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
     let addr = "0.0.0.0:8080";
-    info!("Starting webhook server on {}", addr);
+    let acceptor = match WebhookTlsConfig::from_env()? {
+        Some(config) => {
+            info!("Starting webhook server on {} with mTLS", addr);
+            Some(mtls::acceptor_from_files(&config)?)
+        },
+        None => {
+            info!("Starting webhook server on {}", addr);
+            None
+        },
+    };
 
     let handle = tokio::spawn(async move {
         let listener = tokio::net::TcpListener::bind(addr)
             .await
             .expect("Failed to bind webhook server");
 
-        axum::serve(listener, app)
-            .await
-            .expect("Webhook server failed");
+        match acceptor {
+            Some(acceptor) => mtls::serve(listener, app, acceptor).await,
+            None => axum::serve(listener, app)
+                .await
+                .expect("Webhook server failed"),
+        }
     });
 
-    Ok((handle, event_tx_clone, chart_event_tx_clone))
+    Ok((handle, event_tx, chart_event_tx_clone))
 }
 
 async fn handle_registry_webhook(
     State(state): State<WebhookState>,
+    client: Option<Extension<ClientIdentity>>,
     Json(payload): Json<RegistryWebhook>,
-) -> impl IntoResponse {
+) -> Response {
     WEBHOOK_EVENTS_TOTAL.inc();
 
     info!(
-        "Received registry webhook with {} events",
-        payload.events.len()
+        "Received registry webhook with {} events{}",
+        payload.events.len(),
+        sent_by(client.as_ref())
     );
 
+    let mut push_events = Vec::new();
     for event in payload.events {
         if event.action == "push"
             && let Some(tag) = event.target.tag
@@ -103,35 +157,204 @@ async fn handle_registry_webhook(
 
                 if let Err(e) = state.chart_event_tx.send(chart_event) {
                     error!("Failed to send chart push event: {}", e);
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to process event");
+                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to process event")
+                        .into_response();
                 }
             } else {
                 // This is a container image push
-                let push_event = ImagePushEvent {
+                push_events.push(ImagePushEvent {
                     registry: extract_registry(&event.target.repository),
                     repository: event.target.repository.clone(),
                     tag,
                     digest: Some(event.target.digest),
-                };
-
-                if let Err(e) = state.event_tx.send(push_event) {
-                    error!("Failed to send push event: {}", e);
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to process event");
-                }
+                });
             }
         }
     }
 
+    enqueue_response(state.processor.try_enqueue(without_ignored(push_events)))
+}
+
+/// Check the `X-Hub-Signature-256` header of a GitHub webhook. Without a
+/// configured secret every request is rejected, as merged PRs patch the cluster.
+fn authenticate_github(
+    secret: Option<&str>,
+    headers: &axum::http::HeaderMap,
+    body: &[u8],
+) -> Result<(), &'static str> {
+    let Some(secret) = secret.filter(|s| !s.is_empty()) else {
+        return Err("Webhook secret not configured");
+    };
+    let signature = headers
+        .get("X-Hub-Signature-256")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !crate::integrations::github::verify_signature(secret, body, signature) {
+        return Err("Invalid signature");
+    }
+    Ok(())
+}
+
+/// Handle GitHub `pull_request` events; merged headwind PRs apply their update
+async fn handle_github_webhook(
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    use crate::integrations::github::{MergeSource, PullRequestEvent, apply_merged_update};
+
+    WEBHOOK_EVENTS_TOTAL.inc();
+
+    let secret = std::env::var("HEADWIND_GITHUB_WEBHOOK_SECRET").ok();
+    if let Err(reason) = authenticate_github(secret.as_deref(), &headers, &body) {
+        warn!("Rejected GitHub webhook: {}", reason);
+        return (StatusCode::UNAUTHORIZED, reason);
+    }
+
+    let event_type = headers
+        .get("X-GitHub-Event")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if event_type != "pull_request" {
+        debug!("Ignoring GitHub event: {}", event_type);
+        return (StatusCode::OK, "Event ignored");
+    }
+
+    let event: PullRequestEvent = match serde_json::from_slice(&body) {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("Failed to parse GitHub pull_request event: {}", e);
+            return (StatusCode::BAD_REQUEST, "Invalid payload");
+        },
+    };
+
+    let Some(target) = event.merged_target() else {
+        return (StatusCode::OK, "Event ignored");
+    };
+
+    info!(
+        "GitHub PR #{} merged, applying update for {} {}/{}",
+        event.pull_request.number, target.kind, target.namespace, target.name
+    );
+
+    let source = MergeSource::GitHub {
+        repository: event.repository.full_name,
+    };
+    let merged_by = event.pull_request.merged_by.map(|u| u.login);
+    tokio::spawn(async move {
+        let result = match Client::try_default().await {
+            Ok(client) => apply_merged_update(client, &target, &source, merged_by).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(()) => WEBHOOK_EVENTS_PROCESSED.inc(),
+            Err(e) => error!(
+                "Failed to apply merged PR update for {}/{}: {}",
+                target.namespace, target.name, e
+            ),
+        }
+    });
+
+    (StatusCode::OK, "Webhook processed")
+}
+
+/// Check the `X-Gitlab-Token` header of a GitLab webhook. Without a
+/// configured secret every request is rejected, as merged MRs patch the cluster.
+fn authenticate_gitlab(
+    secret: Option<&str>,
+    headers: &axum::http::HeaderMap,
+) -> Result<(), &'static str> {
+    let Some(secret) = secret.filter(|s| !s.is_empty()) else {
+        return Err("Webhook secret not configured");
+    };
+    let token = headers
+        .get("X-Gitlab-Token")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if !crate::integrations::gitlab::verify_token(secret, token) {
+        return Err("Invalid token");
+    }
+    Ok(())
+}
+
+/// Handle GitLab Pipeline Hook events; a successful pipeline for the merge of
+/// a headwind MR applies its update
+async fn handle_gitlab_ci_webhook(
+    headers: axum::http::HeaderMap,
+    body: axum::body::Bytes,
+) -> impl IntoResponse {
+    use crate::integrations::gitlab::{GITLAB_TOKEN_ENV, GitLabClient, apply_pipeline_updates};
+    use crate::models::webhook::GitLabPipelineEvent;
+
+    WEBHOOK_EVENTS_TOTAL.inc();
+
+    let secret = std::env::var("HEADWIND_GITLAB_WEBHOOK_SECRET").ok();
+    if let Err(reason) = authenticate_gitlab(secret.as_deref(), &headers) {
+        warn!("Rejected GitLab webhook: {}", reason);
+        return (StatusCode::UNAUTHORIZED, reason);
+    }
+
+    let event_type = headers
+        .get("X-Gitlab-Event")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    if event_type != "Pipeline Hook" {
+        debug!("Ignoring GitLab event: {}", event_type);
+        return (StatusCode::OK, "Event ignored");
+    }
+
+    let event: GitLabPipelineEvent = match serde_json::from_slice(&body) {
+        Ok(e) => e,
+        Err(e) => {
+            warn!("Failed to parse GitLab pipeline event: {}", e);
+            return (StatusCode::BAD_REQUEST, "Invalid payload");
+        },
+    };
+
+    if !event.succeeded() {
+        return (StatusCode::OK, "Event ignored");
+    }
+
+    let Ok(token) = std::env::var(GITLAB_TOKEN_ENV) else {
+        warn!(
+            "Ignoring GitLab pipeline {}: {} is not set",
+            event.object_attributes.id, GITLAB_TOKEN_ENV
+        );
+        return (StatusCode::OK, "Event ignored");
+    };
+
+    debug!(
+        "GitLab pipeline {} succeeded on {}, checking for merged merge requests",
+        event.object_attributes.id, event.object_attributes.git_ref
+    );
+
+    tokio::spawn(async move {
+        let gitlab = GitLabClient::from_env(token, event.project.id);
+        let result = match Client::try_default().await {
+            Ok(client) => apply_pipeline_updates(client, &gitlab, &event).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(0) => {},
+            Ok(_) => WEBHOOK_EVENTS_PROCESSED.inc(),
+            Err(e) => error!(
+                "Failed to apply merged MR updates for GitLab pipeline {}: {}",
+                event.object_attributes.id, e
+            ),
+        }
+    });
+
     (StatusCode::OK, "Webhook processed")
 }
 
 async fn handle_dockerhub_webhook(
     State(state): State<WebhookState>,
+    client: Option<Extension<ClientIdentity>>,
     Json(payload): Json<DockerHubWebhook>,
-) -> impl IntoResponse {
+) -> Response {
     info!(
-        "Received Docker Hub webhook for {}",
-        payload.repository.repo_name
+        "Received Docker Hub webhook for {}{}",
+        payload.repository.repo_name,
+        sent_by(client.as_ref())
     );
 
     let push_event = ImagePushEvent {
@@ -141,21 +364,96 @@ async fn handle_dockerhub_webhook(
         digest: None,
     };
 
-    if let Err(e) = state.event_tx.send(push_event) {
-        error!("Failed to send push event: {}", e);
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to process event");
-    }
+    enqueue_response(
+        state
+            .processor
+            .try_enqueue(without_ignored(vec![push_event])),
+    )
+}
 
-    (StatusCode::OK, "Webhook processed")
+/// Push events whose image doesn't match `HEADWIND_IGNORE_IMAGES`
+fn without_ignored(events: Vec<ImagePushEvent>) -> Vec<ImagePushEvent> {
+    events
+        .into_iter()
+        .filter(|event| {
+            let image = event.full_image();
+            let ignored = is_image_ignored(ignore_patterns(), &image);
+            if ignored {
+                debug!("Dropping push event for ignored image {}", image);
+            }
+            !ignored
+        })
+        .collect()
+}
+
+/// `202 Accepted` once events are queued, `429` with `Retry-After` while the queue is full
+fn enqueue_response(result: Result<(), EnqueueError>) -> Response {
+    match result {
+        Ok(()) => (StatusCode::ACCEPTED, "Webhook accepted").into_response(),
+        Err(EnqueueError::Full) => {
+            warn!("Webhook event queue is full, asking sender to retry");
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())],
+                "Webhook event queue is full",
+            )
+                .into_response()
+        },
+        Err(e) => {
+            error!("Failed to queue push event: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to process event").into_response()
+        },
+    }
 }
 
 async fn health_check() -> impl IntoResponse {
     (StatusCode::OK, "OK")
 }
 
+/// Workloads whose pod template is searched for images by `impl_process_resources!`
+trait PodTemplate {
+    fn pod_spec(&self) -> Option<&PodSpec>;
+
+    /// Whether headwind updates this workload itself rather than through an owner
+    fn managed_directly(&self) -> bool {
+        true
+    }
+
+    /// Whether init containers are searched as well as regular containers
+    fn tracks_init_containers(&self, _policy: &ResourcePolicy) -> bool {
+        false
+    }
+}
+
+impl PodTemplate for StatefulSet {
+    fn pod_spec(&self) -> Option<&PodSpec> {
+        self.spec.as_ref()?.template.spec.as_ref()
+    }
+
+    fn tracks_init_containers(&self, policy: &ResourcePolicy) -> bool {
+        policy.include_init_containers
+    }
+}
+
+impl PodTemplate for DaemonSet {
+    fn pod_spec(&self) -> Option<&PodSpec> {
+        self.spec.as_ref()?.template.spec.as_ref()
+    }
+}
+
+impl PodTemplate for ReplicaSet {
+    fn pod_spec(&self) -> Option<&PodSpec> {
+        self.spec.as_ref()?.template.as_ref()?.spec.as_ref()
+    }
+
+    fn managed_directly(&self) -> bool {
+        crate::controller::is_standalone_replicaset(self)
+    }
+}
+
 /// Macro to generate process_* functions for different Kubernetes resource types.
 /// This eliminates ~200 lines of duplicated code across process_deployments,
-/// process_statefulsets, and process_daemonsets.
+/// process_statefulsets, process_daemonsets and process_replicasets.
 ///
 /// Each generated function:
 /// 1. Queries all resources of the specified type
@@ -170,16 +468,19 @@ macro_rules! impl_process_resources {
             policy_engine: &Arc<PolicyEngine>,
             event: &ImagePushEvent,
         ) -> Result<()> {
-            let resources: Api<$resource_type> = Api::all(client.clone());
-            let resource_list = resources.list(&Default::default()).await?;
+            let resource_list = list_all::<$resource_type>(client).await?;
 
             debug!(
                 "Checking {} {}s for matching images",
-                resource_list.items.len(),
+                resource_list.len(),
                 $resource_name
             );
 
-            for resource in resource_list.items {
+            for resource in resource_list {
+                if !resource.managed_directly() {
+                    continue;
+                }
+
                 // Check if resource has headwind annotations
                 let annotations = match &resource.metadata.annotations {
                     Some(ann) => ann,
@@ -187,7 +488,9 @@ macro_rules! impl_process_resources {
                 };
 
                 // Skip if no policy annotation
-                if !annotations.contains_key(annotations::POLICY) {
+                if !annotations.contains_key(annotations::POLICY)
+                    || is_resource_ignored(annotations)
+                {
                     continue;
                 }
 
@@ -219,20 +522,21 @@ macro_rules! impl_process_resources {
                 }
 
                 // Check each container in the resource
-                let spec = match resource.spec.as_ref() {
+                let template_spec = match resource.pod_spec() {
                     Some(s) => s,
                     None => continue,
                 };
 
-                let template_spec = match spec.template.spec.as_ref() {
-                    Some(s) => s,
-                    None => continue,
-                };
+                let init_containers = template_spec
+                    .init_containers
+                    .as_deref()
+                    .filter(|_| resource.tracks_init_containers(&policy))
+                    .unwrap_or_default();
 
-                for container in &template_spec.containers {
+                for container in template_spec.containers.iter().chain(init_containers) {
                     let current_image = match container.image.as_ref() {
-                        Some(img) => img,
-                        None => continue,
+                        Some(img) if !is_image_ignored(ignore_patterns(), img) => img,
+                        _ => continue,
                     };
 
                     // Parse the current image
@@ -307,32 +611,198 @@ impl_process_resources!(
     crate::controller::handle_daemonset_image_update
 );
 
-async fn process_webhook_events(mut rx: EventReceiver) {
-    info!("Starting webhook event processor");
+// Only standalone ReplicaSets; Deployment-owned ones are updated via the Deployment
+impl_process_resources!(
+    process_replicasets,
+    ReplicaSet,
+    "replicaset",
+    crate::controller::handle_replicaset_image_update
+);
 
-    // Create Kubernetes client
-    let client = match Client::try_default().await {
-        Ok(c) => c,
-        Err(e) => {
-            error!("Failed to create Kubernetes client: {}", e);
-            return;
-        },
-    };
+/// Update Knative Services whose revision template references the pushed
+/// image. Knative Services are not in the informer cache.
+async fn process_knative_services(
+    client: &Client,
+    policy_engine: &Arc<PolicyEngine>,
+    event: &ImagePushEvent,
+) -> Result<()> {
+    let services: Api<KnativeService> = crate::controller::watched_api(client.clone());
+    let service_list = services.list(&Default::default()).await?;
 
-    let policy_engine = Arc::new(PolicyEngine);
+    debug!(
+        "Checking {} knative services for matching images",
+        service_list.items.len()
+    );
 
-    while let Some(event) = rx.recv().await {
-        info!("Processing image push event: {}", event.full_image());
+    for service in service_list.items {
+        let Some(annotations) = service.metadata.annotations.as_ref() else {
+            continue;
+        };
+        if !annotations.contains_key(annotations::POLICY) || is_resource_ignored(annotations) {
+            continue;
+        }
 
-        if let Err(e) = process_image_push_event(&client, &policy_engine, &event).await {
-            error!("Failed to process image push event: {}", e);
+        let policy = match parse_policy_from_annotations(annotations) {
+            Ok(p) => p,
+            Err(e) => {
+                warn!(
+                    "Failed to parse policy for knative service {}: {}",
+                    service.name_any(),
+                    e
+                );
+                continue;
+            },
+        };
+        if policy.event_source != EventSource::Webhook && policy.event_source != EventSource::Both {
+            debug!(
+                "Skipping knative service {} - event source is {:?}, not webhook",
+                service.name_any(),
+                policy.event_source
+            );
             continue;
         }
 
-        WEBHOOK_EVENTS_PROCESSED.inc();
+        for container in service.containers() {
+            if !policy.targets_container(container.name.as_deref().unwrap_or_default()) {
+                continue;
+            }
+            let current_image = match container.image.as_ref() {
+                Some(img) if !is_image_ignored(ignore_patterns(), img) => img,
+                _ => continue,
+            };
+            let (image_name, current_tag) = match parse_image_full(current_image) {
+                Ok(parts) => parts,
+                Err(e) => {
+                    warn!("Failed to parse image {}: {}", current_image, e);
+                    continue;
+                },
+            };
+            if !images_match(&event.registry, &event.repository, &image_name)
+                || current_tag == event.tag
+            {
+                continue;
+            }
+
+            info!(
+                "Found matching knative service {}/{} using {}",
+                service.namespace().unwrap_or_default(),
+                service.name_any(),
+                current_image
+            );
+            if let Err(e) = crate::controller::handle_knative_service_image_update(
+                client,
+                policy_engine,
+                &service,
+                &image_name,
+                &event.tag,
+            )
+            .await
+            {
+                error!(
+                    "Failed to handle image update for knative service {}/{}: {}",
+                    service.namespace().unwrap_or_default(),
+                    service.name_any(),
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Update Crossplane Compositions whose composed resources reference the
+/// pushed image. Compositions are cluster-scoped and not in the informer cache.
+async fn process_compositions(
+    client: &Client,
+    policy_engine: &Arc<PolicyEngine>,
+    event: &ImagePushEvent,
+) -> Result<()> {
+    let compositions: Api<Composition> = Api::all(client.clone());
+    let composition_list = compositions.list(&Default::default()).await?;
+
+    debug!(
+        "Checking {} compositions for matching images",
+        composition_list.items.len()
+    );
+
+    for composition in composition_list.items {
+        let Some(annotations) = composition.metadata.annotations.as_ref() else {
+            continue;
+        };
+        if !annotations.contains_key(annotations::POLICY) || is_resource_ignored(annotations) {
+            continue;
+        }
+
+        let policy = match parse_policy_from_annotations(annotations) {
+            Ok(p) => p,
+            Err(e) => {
+                warn!(
+                    "Failed to parse policy for composition {}: {}",
+                    composition.name_any(),
+                    e
+                );
+                continue;
+            },
+        };
+        if policy.event_source != EventSource::Webhook && policy.event_source != EventSource::Both {
+            debug!(
+                "Skipping composition {} - event source is {:?}, not webhook",
+                composition.name_any(),
+                policy.event_source
+            );
+            continue;
+        }
+
+        // The handler updates every reference to the image at once
+        let matching = composition.image_refs().into_iter().find_map(|image_ref| {
+            if is_image_ignored(ignore_patterns(), &image_ref.image) {
+                return None;
+            }
+            let (image_name, current_tag) = parse_image_full(&image_ref.image).ok()?;
+            (images_match(&event.registry, &event.repository, &image_name)
+                && current_tag != event.tag)
+                .then_some(image_name)
+        });
+        let Some(image_name) = matching else {
+            continue;
+        };
+
+        info!(
+            "Found matching composition {} using {}",
+            composition.name_any(),
+            image_name
+        );
+        if let Err(e) = crate::controller::handle_composition_image_update(
+            client,
+            policy_engine,
+            &composition,
+            &image_name,
+            &event.tag,
+        )
+        .await
+        {
+            error!(
+                "Failed to handle image update for composition {}: {}",
+                composition.name_any(),
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Queue image push events found by polling, waiting while the queue is full
+async fn forward_polled_events(mut rx: EventReceiver, processor: WebhookEventProcessor) {
+    while let Some(event) = rx.recv().await {
+        if let Err(e) = processor.send(event).await {
+            error!("Failed to queue polled image push event: {}", e);
+            break;
+        }
     }
 
-    warn!("Webhook event processor stopped");
+    warn!("Polled image push event forwarder stopped");
 }
 
 /// Process Helm chart push events
@@ -500,28 +970,35 @@ async fn process_image_push_event(
     event: &ImagePushEvent,
 ) -> Result<()> {
     // Query all deployments
-    let deployments: Api<Deployment> = Api::all(client.clone());
-    let deployment_list = deployments.list(&Default::default()).await?;
+    let deployment_list = list_all::<Deployment>(client).await?;
 
     debug!(
         "Checking {} deployments for matching images",
-        deployment_list.items.len()
+        deployment_list.len()
     );
 
-    for deployment in deployment_list.items {
-        // Check if deployment has headwind annotations
-        let annotations = match &deployment.metadata.annotations {
-            Some(ann) => ann,
-            None => continue,
-        };
+    let no_annotations = BTreeMap::new();
+
+    for deployment in deployment_list {
+        // Headwind's own Deployment is followed without annotations when self-update is on
+        let self_update = self_update_for(&deployment);
+        let annotations = deployment
+            .metadata
+            .annotations
+            .as_ref()
+            .unwrap_or(&no_annotations);
 
         // Skip if no policy annotation
-        if !annotations.contains_key(annotations::POLICY) {
+        if !annotations.contains_key(annotations::POLICY) && self_update.is_none() {
+            continue;
+        }
+
+        if is_resource_ignored(annotations) {
             continue;
         }
 
         // Parse policy
-        let policy = match parse_policy_from_annotations(annotations) {
+        let mut policy = match parse_policy_from_annotations(annotations) {
             Ok(p) => p,
             Err(e) => {
                 warn!(
@@ -532,6 +1009,9 @@ async fn process_image_push_event(
                 continue;
             },
         };
+        if let Some(self_update) = &self_update {
+            self_update.apply(&mut policy, annotations);
+        }
 
         // Check event source - only process webhook events if event_source is "webhook" or "both"
         if policy.event_source != EventSource::Webhook && policy.event_source != EventSource::Both {
@@ -560,9 +1040,13 @@ async fn process_image_push_event(
                 continue;
             }
 
+            if !policy.targets_container(&container.name) {
+                continue;
+            }
+
             let current_image = match container.image.as_ref() {
-                Some(img) => img,
-                None => continue,
+                Some(img) if !is_image_ignored(ignore_patterns(), img) => img,
+                _ => continue,
             };
 
             // Parse the current image to extract name and registry
@@ -574,6 +1058,14 @@ async fn process_image_push_event(
                 },
             };
 
+            // Only the configured image of Headwind's own Deployment is self-updated
+            if self_update
+                .as_ref()
+                .is_some_and(|c| !c.tracks_image(&image_name))
+            {
+                continue;
+            }
+
             // Check if this container uses the image from the webhook event
             let matches = images_match(&event.registry, &event.repository, &image_name);
             debug!(
@@ -632,6 +1124,23 @@ async fn process_image_push_event(
     // Process DaemonSets
     process_daemonsets(client, policy_engine, event).await?;
 
+    // Process standalone ReplicaSets
+    process_replicasets(client, policy_engine, event).await?;
+
+    // Process Knative Services
+    if crate::controller::knative_enabled()
+        && let Err(e) = process_knative_services(client, policy_engine, event).await
+    {
+        warn!("Failed to process Knative Services: {}", e);
+    }
+
+    // Process Crossplane Compositions
+    if crate::controller::crossplane_enabled()
+        && let Err(e) = process_compositions(client, policy_engine, event).await
+    {
+        warn!("Failed to process Crossplane Compositions: {}", e);
+    }
+
     Ok(())
 }
 
@@ -639,7 +1148,9 @@ async fn process_image_push_event(
 /// Examples:
 ///   "nginx:1.25.0" -> ("nginx", "1.25.0")
 ///   "gcr.io/project/image:v1.0" -> ("gcr.io/project/image", "v1.0")
+///   "nginx:1.25.0@sha256:abc" -> ("nginx", "1.25.0")
 fn parse_image_full(image: &str) -> Result<(String, String)> {
+    let image = crate::polling::digest::strip_digest(image);
     match image.rsplit_once(':') {
         Some((name, tag)) => {
             // Check if the part after ':' looks like a port
@@ -753,6 +1264,13 @@ fn images_match(event_registry: &str, event_repository: &str, deployment_image:
     }
 }
 
+/// " from <identity>" for webhooks received over mTLS, for log messages
+fn sent_by(client: Option<&Extension<ClientIdentity>>) -> String {
+    client
+        .map(|Extension(ClientIdentity(identity))| format!(" from {}", identity))
+        .unwrap_or_default()
+}
+
 /// Format image with registry and tag
 fn format_image(registry: &str, repository: &str, tag: &str) -> String {
     if registry == "docker.io" {
@@ -762,7 +1280,7 @@ fn format_image(registry: &str, repository: &str, tag: &str) -> String {
     }
 }
 
-fn parse_policy_from_annotations(
+pub(crate) fn parse_policy_from_annotations(
     annotations: &std::collections::BTreeMap<String, String>,
 ) -> Result<ResourcePolicy> {
     let mut policy = ResourcePolicy::default();
@@ -791,6 +1309,32 @@ fn parse_policy_from_annotations(
             .collect();
     }
 
+    if let Some(container_names) = annotations.get(annotations::CONTAINER_NAMES) {
+        policy.container_names = parse_container_names(container_names);
+    }
+
+    if let Some(batch_mode) = annotations.get(annotations::BATCH_UPDATES) {
+        policy.batch_mode = batch_mode.parse().unwrap_or(false);
+    }
+
+    if let Some(observers) = annotations.get(annotations::NOTIFY_OBSERVERS) {
+        policy.observers = crate::models::parse_observers(observers);
+    }
+
+    policy.tag_normalization = parse_tag_normalization(annotations, &global_tag_normalization())?;
+
+    if let Some(level) = annotations.get(annotations::REQUIRE_SLSA_LEVEL) {
+        policy.require_slsa_level = Some(parse_slsa_level(level)?);
+    }
+
+    if let Some(builder) = annotations.get(annotations::SLSA_BUILDER) {
+        policy.slsa_builder = Some(builder.clone());
+    }
+
+    if let Some(package) = annotations.get(annotations::OPA_POLICY) {
+        policy.opa_policy = Some(package.clone());
+    }
+
     Ok(policy)
 }
 
@@ -808,6 +1352,189 @@ fn extract_registry(repository: &str) -> String {
 mod tests {
     use super::*;
 
+    fn github_headers(signature: &str) -> axum::http::HeaderMap {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("X-Hub-Signature-256", signature.parse().unwrap());
+        headers
+    }
+
+    fn github_signature(secret: &str, body: &[u8]) -> String {
+        use hmac::{Hmac, Mac};
+        let mut mac = Hmac::<sha2::Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[tokio::test]
+    async fn test_image_event_creates_knative_service_update_request() {
+        use crate::testing::{FakeApi, api_error};
+        use axum::http::Method;
+        use serde_json::json;
+
+        let api = FakeApi::new(|request| match (&request.method, request.path.as_str()) {
+            (&Method::GET, path) if path.ends_with("/serving.knative.dev/v1/services") => (
+                StatusCode::OK,
+                json!({
+                    "apiVersion": "serving.knative.dev/v1",
+                    "kind": "ServiceList",
+                    "metadata": {},
+                    "items": [{
+                        "apiVersion": "serving.knative.dev/v1",
+                        "kind": "Service",
+                        "metadata": {
+                            "name": "hello",
+                            "namespace": "apps",
+                            "annotations": { "headwind.sh/policy": "minor" }
+                        },
+                        "spec": {
+                            "template": {
+                                "spec": {
+                                    "containers": [
+                                        { "name": "user-container", "image": "ghcr.io/org/hello:1.0.0" }
+                                    ]
+                                }
+                            }
+                        }
+                    }]
+                }),
+            ),
+            (&Method::GET, _) => api_error(StatusCode::NOT_FOUND, "NotFound", "not found"),
+            (&Method::POST, "/apis/headwind.sh/v1alpha1/namespaces/apps/updaterequests") => {
+                (StatusCode::CREATED, request.body.clone())
+            },
+            (method, path) => panic!("unexpected {} {}", method, path),
+        });
+
+        let event = ImagePushEvent {
+            registry: "ghcr.io".to_string(),
+            repository: "org/hello".to_string(),
+            tag: "1.1.0".to_string(),
+            digest: None,
+        };
+        process_knative_services(&api.client(), &Arc::new(PolicyEngine), &event)
+            .await
+            .unwrap();
+
+        let created = api.bodies(Method::POST, "/updaterequests");
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0]["metadata"]["name"], "hello-1-1-0");
+        assert_eq!(created[0]["spec"]["targetRef"]["kind"], "Service");
+        assert_eq!(created[0]["spec"]["targetRef"]["namespace"], "apps");
+        assert_eq!(created[0]["spec"]["containerName"], "user-container");
+        assert_eq!(created[0]["spec"]["newImage"], "ghcr.io/org/hello:1.1.0");
+    }
+
+    #[tokio::test]
+    async fn test_image_event_creates_composition_update_request() {
+        use crate::testing::{FakeApi, api_error};
+        use axum::http::Method;
+        use serde_json::json;
+
+        let api = FakeApi::new(|request| match (&request.method, request.path.as_str()) {
+            (&Method::GET, "/apis/apiextensions.crossplane.io/v1/compositions") => (
+                StatusCode::OK,
+                json!({
+                    "apiVersion": "apiextensions.crossplane.io/v1",
+                    "kind": "CompositionList",
+                    "metadata": {},
+                    "items": [{
+                        "apiVersion": "apiextensions.crossplane.io/v1",
+                        "kind": "Composition",
+                        "metadata": {
+                            "name": "platform-app",
+                            "annotations": { "headwind.sh/policy": "minor" }
+                        },
+                        "spec": {
+                            "resources": [{
+                                "name": "worker",
+                                "base": {
+                                    "spec": {
+                                        "containers": [
+                                            { "name": "app", "image": "ghcr.io/org/worker:1.2.0" }
+                                        ]
+                                    }
+                                }
+                            }]
+                        }
+                    }]
+                }),
+            ),
+            (&Method::GET, _) => api_error(StatusCode::NOT_FOUND, "NotFound", "not found"),
+            (&Method::POST, path) if path.ends_with("/updaterequests") => {
+                (StatusCode::CREATED, request.body.clone())
+            },
+            (method, path) => panic!("unexpected {} {}", method, path),
+        });
+
+        let event = ImagePushEvent {
+            registry: "ghcr.io".to_string(),
+            repository: "org/worker".to_string(),
+            tag: "1.3.0".to_string(),
+            digest: None,
+        };
+        process_compositions(&api.client(), &Arc::new(PolicyEngine), &event)
+            .await
+            .unwrap();
+
+        let created = api.bodies(Method::POST, "/updaterequests");
+        assert_eq!(created.len(), 1);
+        assert_eq!(
+            created[0]["metadata"]["name"],
+            "composition-platform-app-1-3-0"
+        );
+        assert_eq!(created[0]["spec"]["targetRef"]["kind"], "Composition");
+        assert_eq!(created[0]["spec"]["containerName"], "worker");
+        assert_eq!(
+            created[0]["spec"]["currentImage"],
+            "ghcr.io/org/worker:1.2.0"
+        );
+        assert_eq!(created[0]["spec"]["newImage"], "ghcr.io/org/worker:1.3.0");
+    }
+
+    #[test]
+    fn test_github_webhook_requires_secret() {
+        let body = br#"{"action":"closed"}"#;
+        let headers = github_headers(&github_signature("secret", body));
+
+        assert_eq!(authenticate_github(Some("secret"), &headers, body), Ok(()));
+        assert_eq!(
+            authenticate_github(None, &headers, body),
+            Err("Webhook secret not configured")
+        );
+        assert_eq!(
+            authenticate_github(Some(""), &headers, body),
+            Err("Webhook secret not configured")
+        );
+        assert_eq!(
+            authenticate_github(Some("other"), &headers, body),
+            Err("Invalid signature")
+        );
+        assert_eq!(
+            authenticate_github(Some("secret"), &axum::http::HeaderMap::new(), body),
+            Err("Invalid signature")
+        );
+    }
+
+    #[test]
+    fn test_gitlab_webhook_requires_secret() {
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("X-Gitlab-Token", "secret".parse().unwrap());
+
+        assert_eq!(authenticate_gitlab(Some("secret"), &headers), Ok(()));
+        assert_eq!(
+            authenticate_gitlab(None, &headers),
+            Err("Webhook secret not configured")
+        );
+        assert_eq!(
+            authenticate_gitlab(Some("other"), &headers),
+            Err("Invalid token")
+        );
+        assert_eq!(
+            authenticate_gitlab(Some("secret"), &axum::http::HeaderMap::new()),
+            Err("Invalid token")
+        );
+    }
+
     #[test]
     fn test_extract_registry() {
         assert_eq!(extract_registry("nginx"), "docker.io");
@@ -0,0 +1,128 @@
+//! Mutual TLS for the webhook server.
+//!
+//! With `HEADWIND_WEBHOOK_TLS_CERT_FILE`, `HEADWIND_WEBHOOK_TLS_KEY_FILE` and
+//! `HEADWIND_WEBHOOK_CLIENT_CA_FILE` set, the webhook server only accepts
+//! connections presenting a client certificate issued by one of the CAs in the
+//! client CA file. The certificate's common name (or first subjectAltName) is
+//! added to every request of the connection as a [`ClientIdentity`] extension.
+
+use super::tls::certificate_identity;
+use crate::config::WebhookTlsConfig;
+use crate::metrics::WEBHOOK_TLS_HANDSHAKE_FAILURES_TOTAL;
+use anyhow::{Context, Result, anyhow};
+use axum::{Extension, Router};
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, error, warn};
+
+/// Identity from the client certificate of an mTLS connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity(pub String);
+
+/// TLS acceptor from the certificate files in `config`
+pub fn acceptor_from_files(config: &WebhookTlsConfig) -> Result<TlsAcceptor> {
+    let read = |path: &std::path::Path| {
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+    };
+    let config = server_config(
+        &read(&config.cert_file)?,
+        &read(&config.key_file)?,
+        &read(&config.client_ca_file)?,
+    )?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Server configuration requiring a client certificate issued by a CA in
+/// `client_ca_pem`
+pub fn server_config(
+    cert_pem: &[u8],
+    key_pem: &[u8],
+    client_ca_pem: &[u8],
+) -> Result<rustls::ServerConfig> {
+    let certs = CertificateDer::pem_slice_iter(cert_pem)
+        .collect::<Result<Vec<_>, _>>()
+        .context("Invalid server certificate PEM")?;
+    if certs.is_empty() {
+        return Err(anyhow!(
+            "No certificate found in the server certificate file"
+        ));
+    }
+    let key = PrivateKeyDer::from_pem_slice(key_pem).context("Invalid server key PEM")?;
+
+    let mut roots = rustls::RootCertStore::empty();
+    for ca in CertificateDer::pem_slice_iter(client_ca_pem) {
+        roots
+            .add(ca.context("Invalid client CA PEM")?)
+            .context("Invalid client CA certificate")?;
+    }
+    if roots.is_empty() {
+        return Err(anyhow!("No certificate found in the client CA file"));
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .build()
+        .context("Invalid client CA certificates")?;
+
+    rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)
+        .context("Server certificate does not match its key")
+}
+
+/// Serve `app` over mTLS on `listener` until the listener fails
+pub async fn serve(listener: TcpListener, app: Router, acceptor: TlsAcceptor) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("Webhook server accept failed: {}", e);
+                continue;
+            },
+        };
+
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    WEBHOOK_TLS_HANDSHAKE_FAILURES_TOTAL.inc();
+                    warn!("TLS handshake with {} failed: {}", peer, e);
+                    return;
+                },
+            };
+
+            let identity = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(|cert| certificate_identity(cert));
+            let app = match identity {
+                Some(identity) => {
+                    debug!("mTLS connection from {} as {}", peer, identity);
+                    app.layer(Extension(ClientIdentity(identity)))
+                },
+                None => {
+                    debug!("mTLS connection from {} without a certificate name", peer);
+                    app
+                },
+            };
+
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), TowerToHyperService::new(app))
+                .await
+            {
+                debug!("Webhook connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
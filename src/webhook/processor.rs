@@ -0,0 +1,215 @@
+//! Worker pool for image push events.
+//!
+//! Webhook handlers only enqueue events and return `202 Accepted`; a fixed
+//! number of workers (`HEADWIND_WEBHOOK_WORKERS`, default 4) run update
+//! detection. The queue holds [`QUEUE_CAPACITY`] events and webhooks are
+//! answered with `429 Too Many Requests` while it is full.
+
+use crate::metrics::{WEBHOOK_EVENTS_PROCESSED, WEBHOOK_QUEUE_DEPTH};
+use crate::models::webhook::ImagePushEvent;
+use anyhow::Result;
+use std::future::Future;
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+use tracing::{error, info};
+
+/// Events that can wait for a worker
+pub const QUEUE_CAPACITY: usize = 1000;
+
+/// `Retry-After` seconds sent with 429 responses
+pub const RETRY_AFTER_SECS: u64 = 5;
+
+/// Number of workers (`HEADWIND_WEBHOOK_WORKERS`, default 4)
+pub fn webhook_workers() -> usize {
+    std::env::var("HEADWIND_WEBHOOK_WORKERS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(4usize)
+        .max(1)
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum EnqueueError {
+    #[error("webhook event queue is full")]
+    Full,
+    #[error("webhook event processor stopped")]
+    Closed,
+}
+
+/// Handle for queueing events to the worker pool
+#[derive(Clone)]
+pub struct WebhookEventProcessor {
+    tx: mpsc::Sender<ImagePushEvent>,
+}
+
+impl WebhookEventProcessor {
+    /// Start `workers` tasks calling `handler` for every queued event
+    pub fn start<F, Fut>(workers: usize, capacity: usize, handler: F) -> (Self, Vec<JoinHandle<()>>)
+    where
+        F: Fn(ImagePushEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(capacity);
+        let rx = Arc::new(Mutex::new(rx));
+        let handler = Arc::new(handler);
+
+        info!("Starting {} webhook event workers", workers);
+        let handles = (0..workers)
+            .map(|_| tokio::spawn(run_worker(rx.clone(), handler.clone())))
+            .collect();
+        (Self { tx }, handles)
+    }
+
+    /// Queue all `events` or none of them
+    pub fn try_enqueue(&self, events: Vec<ImagePushEvent>) -> Result<(), EnqueueError> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let permits = self
+            .tx
+            .try_reserve_many(events.len())
+            .map_err(|e| match e {
+                mpsc::error::TrySendError::Full(_) => EnqueueError::Full,
+                mpsc::error::TrySendError::Closed(_) => EnqueueError::Closed,
+            })?;
+        for (permit, event) in permits.zip(events) {
+            permit.send(event);
+        }
+        WEBHOOK_QUEUE_DEPTH.set(self.depth() as i64);
+        Ok(())
+    }
+
+    /// Queue an event, waiting for room instead of failing when the queue is full
+    pub async fn send(&self, event: ImagePushEvent) -> Result<(), EnqueueError> {
+        self.tx
+            .send(event)
+            .await
+            .map_err(|_| EnqueueError::Closed)?;
+        WEBHOOK_QUEUE_DEPTH.set(self.depth() as i64);
+        Ok(())
+    }
+
+    /// Events waiting for a worker
+    pub fn depth(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+}
+
+async fn run_worker<F, Fut>(rx: Arc<Mutex<mpsc::Receiver<ImagePushEvent>>>, handler: Arc<F>)
+where
+    F: Fn(ImagePushEvent) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    loop {
+        let event = {
+            let mut rx = rx.lock().await;
+            let event = rx.recv().await;
+            WEBHOOK_QUEUE_DEPTH.set(rx.len() as i64);
+            event
+        };
+        let Some(event) = event else {
+            break;
+        };
+
+        let image = event.full_image();
+        match handler(event).await {
+            Ok(()) => WEBHOOK_EVENTS_PROCESSED.inc(),
+            Err(e) => error!("Failed to process image push event {}: {}", image, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::Notify;
+
+    fn event(tag: &str) -> ImagePushEvent {
+        ImagePushEvent {
+            registry: "docker.io".to_string(),
+            repository: "library/nginx".to_string(),
+            tag: tag.to_string(),
+            digest: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_workers_process_every_event() {
+        let processed = Arc::new(AtomicUsize::new(0));
+        let counter = processed.clone();
+        let (processor, _) = WebhookEventProcessor::start(4, 100, move |_| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+
+        let before = WEBHOOK_EVENTS_PROCESSED.get();
+        for i in 0..50 {
+            processor.try_enqueue(vec![event(&i.to_string())]).unwrap();
+        }
+        while processed.load(Ordering::SeqCst) < 50 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(processor.depth(), 0);
+        assert!(WEBHOOK_EVENTS_PROCESSED.get() >= before + 50);
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_rejects_whole_batch() {
+        // The only worker blocks on its first event, so queued events stay queued
+        let release = Arc::new(Notify::new());
+        let started = Arc::new(Notify::new());
+        let (worker_release, worker_started) = (release.clone(), started.clone());
+        let (processor, _) = WebhookEventProcessor::start(1, 2, move |_| {
+            let (release, started) = (worker_release.clone(), worker_started.clone());
+            async move {
+                started.notify_one();
+                release.notified().await;
+                Ok(())
+            }
+        });
+
+        processor.try_enqueue(vec![event("1")]).unwrap();
+        started.notified().await;
+        processor.try_enqueue(vec![event("2")]).unwrap();
+        assert_eq!(
+            processor.try_enqueue(vec![event("3"), event("4")]),
+            Err(EnqueueError::Full)
+        );
+        assert_eq!(processor.depth(), 1);
+        processor.try_enqueue(vec![event("3")]).unwrap();
+        assert_eq!(
+            processor.try_enqueue(vec![event("4")]),
+            Err(EnqueueError::Full)
+        );
+        release.notify_one();
+    }
+
+    #[tokio::test]
+    async fn test_failed_events_do_not_stop_workers() {
+        let processed = Arc::new(AtomicUsize::new(0));
+        let counter = processed.clone();
+        let (processor, _) = WebhookEventProcessor::start(1, 10, move |event| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                if event.tag == "bad" {
+                    anyhow::bail!("no such image");
+                }
+                Ok(())
+            }
+        });
+
+        processor
+            .try_enqueue(vec![event("bad"), event("good")])
+            .unwrap();
+        while processed.load(Ordering::SeqCst) < 2 {
+            tokio::task::yield_now().await;
+        }
+    }
+}
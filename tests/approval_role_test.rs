@@ -0,0 +1,131 @@
+// Integration tests for headwind.sh/approved-by-role on the approval routes
+// that approve without `POST /approve`: batch approval and scheduling
+//
+// The fake Kubernetes API denies every SubjectAccessReview, so nothing may
+// be patched
+
+use axum::body::Body;
+use headwind::approval::{ApprovalState, router};
+use headwind::models::crd::UpdatePhase;
+use headwind::testing::{self, FakeApi};
+use http::{Method, Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+fn target_ref() -> Value {
+    json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "name": "web",
+        "namespace": "production"
+    })
+}
+
+fn fake_api() -> FakeApi {
+    FakeApi::ok(|request| match request.path.as_str() {
+        "/apis/authorization.k8s.io/v1/subjectaccessreviews" => {
+            let mut review = request.body.clone();
+            review["status"] = json!({"allowed": false});
+            review
+        },
+        "/apis/apps/v1/namespaces/production/deployments/web" => json!({
+            "apiVersion": "meta.k8s.io/v1",
+            "kind": "PartialObjectMetadata",
+            "metadata": {
+                "name": "web",
+                "namespace": "production",
+                "annotations": { "headwind.sh/approved-by-role": "senior-engineer" }
+            }
+        }),
+        "/apis/headwind.sh/v1alpha1/namespaces/production/batchupdaterequests/deployment-web-batch" =>
+        {
+            json!({
+                "apiVersion": "headwind.sh/v1alpha1",
+                "kind": "BatchUpdateRequest",
+                "metadata": { "name": "deployment-web-batch", "namespace": "production" },
+                "spec": {
+                    "targetRef": target_ref(),
+                    "updates": [{
+                        "containerName": "nginx",
+                        "currentImage": "nginx:1.25.0",
+                        "newImage": "nginx:1.26.0"
+                    }],
+                    "policy": "minor"
+                },
+                "status": { "phase": "Pending" }
+            })
+        },
+        "/apis/headwind.sh/v1alpha1/namespaces/production/updaterequests/web-nginx-1-26-0" => {
+            serde_json::to_value(testing::update_request_in_phase(
+                "production",
+                "web-nginx-1-26-0",
+                UpdatePhase::Pending,
+            ))
+            .unwrap()
+        },
+        path => panic!("unexpected request to {}", path),
+    })
+}
+
+async fn send(api: &FakeApi, method: Method, uri: &str, body: Value) -> (StatusCode, Value) {
+    let app = router(ApprovalState {
+        client: api.client(),
+    });
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("content-type", "application/json")
+        .header("X-Remote-User", "mallory")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn patched(api: &FakeApi) -> bool {
+    api.calls()
+        .iter()
+        .any(|(method, _)| *method == Method::PATCH)
+}
+
+#[tokio::test]
+async fn test_batch_approval_requires_role() {
+    let api = fake_api();
+    let (status, body) = send(
+        &api,
+        Method::POST,
+        "/api/v1/batch-updates/production/deployment-web-batch/approve",
+        json!({"approver": "mallory"}),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["error"],
+        "mallory may not approve: requires role senior-engineer"
+    );
+    assert!(!patched(&api));
+}
+
+#[tokio::test]
+async fn test_scheduling_requires_role() {
+    let api = fake_api();
+    let (status, body) = send(
+        &api,
+        Method::PUT,
+        "/api/v1/update-requests/production/web-nginx-1-26-0/schedule",
+        json!({"scheduled_at": "2099-01-01T02:00:00Z"}),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        body["error"],
+        "mallory may not approve: requires role senior-engineer"
+    );
+    assert!(!patched(&api));
+}
@@ -0,0 +1,402 @@
+// Integration tests for the headwindctl binary
+//
+// Each test runs the compiled binary against a fake Headwind API that records
+// the requests it receives
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::routing::{get, post};
+use headwind::config::HeadwindConfig;
+use headwind::testing;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use tokio::process::Command;
+
+type Requests = Arc<Mutex<Vec<(String, Value)>>>;
+
+fn update_request(name: &str, phase: &str) -> Value {
+    let mut object = serde_json::to_value(testing::update_request("default", name)).unwrap();
+    object["status"] = json!({ "phase": phase });
+    object
+}
+
+fn record(requests: &Requests, path: String, body: &Bytes) {
+    let body = serde_json::from_slice(body).unwrap_or(Value::Null);
+    requests.lock().unwrap().push((path, body));
+}
+
+/// Start the fake API and return its URL
+async fn start_api(requests: Requests) -> String {
+    let app = Router::new()
+        .route(
+            "/api/v1/updates",
+            get(|| async {
+                axum::Json(json!([
+                    update_request("web-nginx-1-26-0", "Pending"),
+                    update_request("web-nginx-1-24-0", "Completed")
+                ]))
+            }),
+        )
+        .route(
+            "/api/v1/updates/{namespace}/{name}/{action}",
+            post(
+                |State(requests): State<Requests>,
+                 Path((namespace, name, action)): Path<(String, String, String)>,
+                 body: Bytes| async move {
+                    record(
+                        &requests,
+                        format!("/api/v1/updates/{}/{}/{}", namespace, name, action),
+                        &body,
+                    );
+                    axum::Json(json!({ "message": format!("Update {}d", action) }))
+                },
+            ),
+        )
+        .route(
+            "/api/v1/rollback/{namespace}/{kind}/{name}",
+            post(
+                |State(requests): State<Requests>,
+                 Path((namespace, kind, name)): Path<(String, String, String)>,
+                 body: Bytes| async move {
+                    record(
+                        &requests,
+                        format!("/api/v1/rollback/{}/{}/{}", namespace, kind, name),
+                        &body,
+                    );
+                    let to = serde_json::from_slice::<Value>(&body)
+                        .ok()
+                        .and_then(|body| body["toImage"].as_str().map(String::from))
+                        .unwrap_or_else(|| "nginx:1.25.0".to_string());
+                    axum::Json(json!({
+                        "resourceKind": kind,
+                        "namespace": namespace,
+                        "name": name,
+                        "container": "app",
+                        "rolledBackFrom": "nginx:1.26.0",
+                        "rolledBackTo": to,
+                        "rolledBackBy": "headwindctl",
+                        "updateRequest": "nginx-app-rollback-1"
+                    }))
+                },
+            ),
+        )
+        .route(
+            "/api/v1/update-requests/export",
+            get(|uri: axum::http::Uri| async move {
+                assert_eq!(uri.query(), Some("format=json-lines"));
+                format!(
+                    "{}\n{}\n",
+                    update_request("web-nginx-1-24-0", "Completed"),
+                    update_request("web-nginx-1-23-0", "Rejected")
+                )
+            }),
+        )
+        .route(
+            "/api/v1/update-requests/import",
+            post(
+                |State(requests): State<Requests>, body: String| async move {
+                    let lines = body.lines().count();
+                    requests.lock().unwrap().push((
+                        "/api/v1/update-requests/import".to_string(),
+                        Value::String(body),
+                    ));
+                    axum::Json(json!({
+                        "imported": lines - 1,
+                        "skipped": 1,
+                        "errors": []
+                    }))
+                },
+            ),
+        )
+        .route(
+            "/api/v1/settings",
+            // Serves the last saved settings, so set and get round-trip
+            get(|State(requests): State<Requests>| async move {
+                let saved = requests
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .rev()
+                    .find(|(path, _)| path == "/api/v1/settings")
+                    .map(|(_, body)| body.clone());
+                axum::Json(saved.unwrap_or_else(|| json!(HeadwindConfig::default())))
+            })
+            .put(|State(requests): State<Requests>, body: Bytes| async move {
+                record(&requests, "/api/v1/settings".to_string(), &body);
+                axum::Json(json!({ "message": "Configuration updated successfully" }))
+            }),
+        )
+        .with_state(requests);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{}", addr)
+}
+
+async fn headwindctl(url: &str, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_headwindctl"))
+        .arg("--url")
+        .arg(url)
+        .args(args)
+        .env_remove("HEADWIND_API_URL")
+        .output()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_list_updates() {
+    let url = start_api(Requests::default()).await;
+
+    let output = headwindctl(&url, &["list-updates", "--status", "pending"]).await;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("NAMESPACE"));
+    assert!(lines[1].contains("web-nginx-1-26-0"));
+    assert!(lines[1].contains("Deployment/web"));
+
+    let output = headwindctl(&url, &["list-updates", "-o", "json"]).await;
+    assert!(output.status.success());
+    let updates: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(updates.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_approve_and_reject() {
+    let requests = Requests::default();
+    let url = start_api(requests.clone()).await;
+
+    let output = headwindctl(&url, &["approve", "default", "web-nginx-1-26-0"]).await;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Update approved\n"
+    );
+
+    // A rejection without a reason never reaches the API
+    let output = headwindctl(&url, &["reject", "default", "web-nginx-1-26-0"]).await;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("reject requires --reason")
+    );
+
+    let output = headwindctl(
+        &url,
+        &[
+            "reject",
+            "default",
+            "web-nginx-1-26-0",
+            "--reason",
+            "not yet",
+        ],
+    )
+    .await;
+    assert!(output.status.success());
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[0].0,
+        "/api/v1/updates/default/web-nginx-1-26-0/approve"
+    );
+    assert_eq!(
+        requests[1].0,
+        "/api/v1/updates/default/web-nginx-1-26-0/reject"
+    );
+    assert_eq!(requests[1].1["reason"], "not yet");
+}
+
+#[tokio::test]
+async fn test_rollback_resource() {
+    let requests = Requests::default();
+    let url = start_api(requests.clone()).await;
+
+    let output = headwindctl(
+        &url,
+        &["rollback", "deployment/nginx-app", "-n", "production"],
+    )
+    .await;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "Rolled back Deployment production/nginx-app container app from nginx:1.26.0 to \
+         nginx:1.25.0 (UpdateRequest nginx-app-rollback-1)\n"
+    );
+
+    // --force and --to-image only make sense together
+    let output = headwindctl(&url, &["rollback", "deployment/nginx-app", "--force"]).await;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("--force requires --to-image")
+    );
+
+    let output = headwindctl(
+        &url,
+        &[
+            "rollback",
+            "deployment/nginx-app",
+            "--force",
+            "--to-image",
+            "nginx:1.24.0",
+            "-o",
+            "json",
+        ],
+    )
+    .await;
+    assert!(output.status.success());
+    let rollback: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(rollback["rolledBackTo"], "nginx:1.24.0");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[0].0,
+        "/api/v1/rollback/production/Deployment/nginx-app"
+    );
+    assert_eq!(requests[0].1, json!({}));
+    assert_eq!(
+        requests[1].0,
+        "/api/v1/rollback/default/Deployment/nginx-app"
+    );
+    assert_eq!(
+        requests[1].1,
+        json!({ "force": true, "toImage": "nginx:1.24.0" })
+    );
+}
+
+#[tokio::test]
+async fn test_config_get_and_set() {
+    let requests = Requests::default();
+    let url = start_api(requests.clone()).await;
+
+    let output = headwindctl(&url, &["config", "get", "polling"]).await;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("polling.interval   300"));
+
+    let output = headwindctl(&url, &["config", "set", "polling.interval", "600"]).await;
+    assert!(output.status.success());
+
+    let output = headwindctl(&url, &["config", "set", "polling.interval", "soon"]).await;
+    assert!(!output.status.success());
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].1["polling"]["interval"], 600);
+    assert_eq!(requests[0].1["polling"]["enabled"], false);
+}
+
+#[tokio::test]
+async fn test_config_set_multiple_round_trip() {
+    let requests = Requests::default();
+    let url = start_api(requests.clone()).await;
+
+    let output = headwindctl(
+        &url,
+        &[
+            "config",
+            "set",
+            "notifications.slack.enabled=true",
+            "--set",
+            "notifications.slack.channel=#deploys",
+            "--set",
+            "polling.interval=900",
+        ],
+    )
+    .await;
+    assert!(output.status.success());
+
+    let output = headwindctl(&url, &["config", "get", "-o", "json"]).await;
+    assert!(output.status.success());
+    let config: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(config["notifications"]["slack"]["enabled"], true);
+    assert_eq!(config["notifications"]["slack"]["channel"], "#deploys");
+    assert_eq!(config["polling"]["interval"], 900);
+
+    let output = headwindctl(&url, &["config", "get", "notifications.slack.channel"]).await;
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("#deploys")
+    );
+
+    // Invalid settings are never sent
+    let output = headwindctl(&url, &["config", "set", "polling.interval=0"]).await;
+    assert!(!output.status.success());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_config_set_dry_run() {
+    let requests = Requests::default();
+    let url = start_api(requests.clone()).await;
+
+    let output = headwindctl(
+        &url,
+        &[
+            "config",
+            "set",
+            "--set",
+            "polling.enabled=true",
+            "--dry-run",
+            "-o",
+            "yaml",
+        ],
+    )
+    .await;
+    assert!(output.status.success());
+    let config: Value = serde_yaml::from_slice(&output.stdout).unwrap();
+    assert_eq!(config["polling"]["enabled"], true);
+    assert_eq!(config["polling"]["interval"], 300);
+
+    assert!(requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_export_and_import() {
+    let requests = Requests::default();
+    let url = start_api(requests.clone()).await;
+
+    let output = headwindctl(&url, &["export"]).await;
+    assert!(output.status.success());
+    let export = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<Value> = export
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["status"]["phase"], "Completed");
+
+    let file =
+        std::env::temp_dir().join(format!("headwindctl-export-{}.jsonl", std::process::id()));
+    std::fs::write(&file, &export).unwrap();
+    let output = headwindctl(&url, &["import", file.to_str().unwrap()]).await;
+    std::fs::remove_file(&file).unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("imported   1"), "{}", stdout);
+    assert!(stdout.contains("skipped    1"), "{}", stdout);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].1, Value::String(export));
+}
+
+#[tokio::test]
+async fn test_api_errors_exit_non_zero() {
+    let output = headwindctl("http://127.0.0.1:1", &["list-updates"]).await;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("Failed to reach Headwind API")
+    );
+}
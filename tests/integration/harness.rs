@@ -0,0 +1,163 @@
+// Shared setup for the end-to-end tests: a throwaway namespace per test and
+// helpers to drive headwind through its HTTP APIs
+
+use crate::common;
+use headwind::UpdateRequest;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Namespace;
+use kube::api::{DeleteParams, ListParams, PostParams};
+use kube::{Api, Client};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How often `wait_for` re-checks its condition
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct TestEnv {
+    pub client: Client,
+    pub namespace: String,
+    webhook_url: String,
+    approval_url: String,
+    http: reqwest::Client,
+}
+
+impl TestEnv {
+    /// Connect to the cluster from the current kubeconfig and create a fresh namespace
+    pub async fn new(prefix: &str) -> Self {
+        let client = Client::try_default()
+            .await
+            .expect("integration tests need a cluster; run them with `make integration-test`");
+
+        let suffix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .subsec_nanos();
+        let namespace = format!("e2e-{}-{:x}", prefix, suffix);
+        let namespaces: Api<Namespace> = Api::all(client.clone());
+        namespaces
+            .create(
+                &PostParams::default(),
+                &serde_json::from_value(json!({ "metadata": { "name": namespace } })).unwrap(),
+            )
+            .await
+            .expect("failed to create test namespace");
+
+        Self {
+            client,
+            namespace,
+            webhook_url: std::env::var("HEADWIND_E2E_WEBHOOK_URL")
+                .unwrap_or_else(|_| "http://localhost:18080".to_string()),
+            approval_url: std::env::var("HEADWIND_E2E_APPROVAL_URL")
+                .unwrap_or_else(|_| "http://localhost:18081".to_string()),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Delete the test namespace and everything in it
+    pub async fn cleanup(self) {
+        let namespaces: Api<Namespace> = Api::all(self.client);
+        let _ = namespaces
+            .delete(&self.namespace, &DeleteParams::default())
+            .await;
+    }
+
+    pub fn deployments(&self) -> Api<Deployment> {
+        Api::namespaced(self.client.clone(), &self.namespace)
+    }
+
+    pub fn update_requests(&self) -> Api<UpdateRequest> {
+        Api::namespaced(self.client.clone(), &self.namespace)
+    }
+
+    /// Create a Deployment with a single `app` container
+    pub async fn create_deployment(
+        &self,
+        name: &str,
+        image: &str,
+        annotations: BTreeMap<String, String>,
+    ) -> Deployment {
+        let deployment =
+            common::create_test_deployment(name, &self.namespace, image, Some(annotations));
+        self.deployments()
+            .create(&PostParams::default(), &deployment)
+            .await
+            .expect("failed to create deployment")
+    }
+
+    /// Image of the `app` container of a Deployment
+    pub async fn deployment_image(&self, name: &str) -> Option<String> {
+        let deployment = self.deployments().get(name).await.ok()?;
+        deployment
+            .spec?
+            .template
+            .spec?
+            .containers
+            .into_iter()
+            .find(|c| c.name == "app")?
+            .image
+    }
+
+    /// Send a Docker Hub push event for `repo:tag` to headwind's webhook server
+    pub async fn push_dockerhub_event(&self, repo: &str, tag: &str) {
+        let response = self
+            .http
+            .post(format!("{}/webhook/dockerhub", self.webhook_url))
+            .json(&common::create_dockerhub_webhook_payload(repo, tag))
+            .send()
+            .await
+            .expect("webhook server unreachable");
+        assert!(
+            response.status().is_success(),
+            "webhook returned {}",
+            response.status()
+        );
+    }
+
+    /// Approve an UpdateRequest through the approval API
+    pub async fn approve(&self, name: &str) {
+        let response = self
+            .http
+            .post(format!(
+                "{}/api/v1/updates/{}/{}/approve",
+                self.approval_url, self.namespace, name
+            ))
+            .json(&json!({ "approver": "e2e@headwind.sh" }))
+            .send()
+            .await
+            .expect("approval API unreachable");
+        assert!(
+            response.status().is_success(),
+            "approval returned {}",
+            response.status()
+        );
+    }
+
+    /// UpdateRequests in the test namespace
+    pub async fn list_update_requests(&self) -> Vec<UpdateRequest> {
+        self.update_requests()
+            .list(&ListParams::default())
+            .await
+            .map(|list| list.items)
+            .unwrap_or_default()
+    }
+}
+
+/// Re-evaluate `check` until it returns `Some` or `timeout` elapses
+pub async fn wait_for<T, F, Fut>(timeout: Duration, mut check: F) -> Option<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        if let Some(value) = check().await {
+            return Some(value);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return None;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,17 @@
+// End-to-end tests against a real Kubernetes API server
+//
+// These tests need a cluster running headwind, with its webhook and approval
+// API reachable from the test process. `make integration-test` creates a kind
+// cluster, deploys headwind, port-forwards both servers, runs the tests and
+// deletes the cluster. They are ignored by a plain `cargo test`.
+//
+// Environment:
+//   HEADWIND_E2E_WEBHOOK_URL   webhook server (default http://localhost:18080)
+//   HEADWIND_E2E_APPROVAL_URL  approval API (default http://localhost:18081)
+
+#[path = "../common/mod.rs"]
+mod common;
+mod harness;
+
+mod rollback;
+mod update_flow;
@@ -0,0 +1,56 @@
+// Automatic rollback of an approved update to an image that never becomes healthy
+
+use crate::common::headwind_annotations;
+use crate::harness::{TestEnv, wait_for};
+use std::time::Duration;
+
+#[tokio::test]
+#[ignore = "requires a cluster running headwind; run with `make integration-test`"]
+async fn test_unhealthy_update_is_rolled_back() {
+    let env = TestEnv::new("rollback").await;
+
+    let mut annotations = headwind_annotations("minor", true, None);
+    annotations.insert("headwind.sh/auto-rollback".to_string(), "true".to_string());
+    annotations.insert(
+        "headwind.sh/rollback-timeout".to_string(),
+        "120".to_string(),
+    );
+    annotations.insert(
+        "headwind.sh/health-check-retries".to_string(),
+        "1".to_string(),
+    );
+    env.create_deployment("web", "nginx:1.25.0", annotations)
+        .await;
+
+    // The tag doesn't exist, so the new pods end up in ErrImagePull
+    env.push_dockerhub_event("nginx", "1.99.99").await;
+    let name = wait_for(Duration::from_secs(60), || async {
+        env.list_update_requests()
+            .await
+            .into_iter()
+            .next()
+            .and_then(|ur| ur.metadata.name)
+    })
+    .await
+    .expect("no UpdateRequest was created");
+
+    env.approve(&name).await;
+
+    let updated = wait_for(Duration::from_secs(60), || async {
+        env.deployment_image("web")
+            .await
+            .filter(|image| image == "nginx:1.99.99")
+    })
+    .await;
+    assert!(updated.is_some(), "deployment was never updated");
+
+    let rolled_back = wait_for(Duration::from_secs(300), || async {
+        env.deployment_image("web")
+            .await
+            .filter(|image| image == "nginx:1.25.0")
+    })
+    .await;
+    assert!(rolled_back.is_some(), "deployment was not rolled back");
+
+    env.cleanup().await;
+}
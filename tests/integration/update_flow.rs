@@ -0,0 +1,99 @@
+// Webhook event -> UpdateRequest -> approval -> Deployment update
+
+use crate::common::headwind_annotations;
+use crate::harness::{TestEnv, wait_for};
+use std::time::Duration;
+
+#[tokio::test]
+#[ignore = "requires a cluster running headwind; run with `make integration-test`"]
+async fn test_webhook_event_creates_update_request() {
+    let env = TestEnv::new("create").await;
+    env.create_deployment(
+        "web",
+        "nginx:1.25.0",
+        headwind_annotations("minor", true, None),
+    )
+    .await;
+
+    env.push_dockerhub_event("nginx", "1.26.0").await;
+
+    let request = wait_for(Duration::from_secs(60), || async {
+        env.list_update_requests().await.into_iter().next()
+    })
+    .await
+    .expect("no UpdateRequest was created");
+
+    assert_eq!(request.spec.target_ref.name, "web");
+    assert_eq!(request.spec.current_image, "nginx:1.25.0");
+    assert_eq!(request.spec.new_image, "nginx:1.26.0");
+
+    // Nothing changes until the request is approved
+    assert_eq!(
+        env.deployment_image("web").await.as_deref(),
+        Some("nginx:1.25.0")
+    );
+
+    env.cleanup().await;
+}
+
+#[tokio::test]
+#[ignore = "requires a cluster running headwind; run with `make integration-test`"]
+async fn test_approval_updates_deployment_image() {
+    let env = TestEnv::new("approve").await;
+    env.create_deployment(
+        "web",
+        "nginx:1.25.0",
+        headwind_annotations("minor", true, None),
+    )
+    .await;
+
+    env.push_dockerhub_event("nginx", "1.26.0").await;
+    let name = wait_for(Duration::from_secs(60), || async {
+        env.list_update_requests()
+            .await
+            .into_iter()
+            .next()
+            .and_then(|ur| ur.metadata.name)
+    })
+    .await
+    .expect("no UpdateRequest was created");
+
+    env.approve(&name).await;
+
+    let image = wait_for(Duration::from_secs(60), || async {
+        env.deployment_image("web")
+            .await
+            .filter(|image| image == "nginx:1.26.0")
+    })
+    .await;
+    assert_eq!(image.as_deref(), Some("nginx:1.26.0"));
+
+    env.cleanup().await;
+}
+
+#[tokio::test]
+#[ignore = "requires a cluster running headwind; run with `make integration-test`"]
+async fn test_policy_rejects_major_bump() {
+    let env = TestEnv::new("policy").await;
+    env.create_deployment(
+        "web",
+        "nginx:1.25.0",
+        headwind_annotations("patch", true, None),
+    )
+    .await;
+
+    env.push_dockerhub_event("nginx", "2.0.0").await;
+
+    // Give headwind time to process the event before asserting nothing happened
+    let created = wait_for(Duration::from_secs(20), || async {
+        env.list_update_requests().await.into_iter().next()
+    })
+    .await;
+    assert!(created.is_none(), "major bump created {:?}", created);
+    assert_eq!(
+        env.deployment_image("web").await.as_deref(),
+        Some("nginx:1.25.0")
+    );
+
+    env.cleanup().await;
+}
@@ -0,0 +1,206 @@
+// Integration tests for manual rollback to `headwind.sh/previous-image`
+//
+// These tests roll back a Deployment against a fake Kubernetes API server
+// that serves the Deployment and records every request it receives
+
+use headwind::models::crd::{UpdatePhase, UpdateRequestStatus};
+use headwind::rollback::manual::{
+    ManualRollbackError, rollback_to_image, rollback_to_previous_image,
+};
+use headwind::testing::FakeApi;
+use headwind::ui::routes::rollback_error_status;
+use http::{Method, StatusCode};
+use serde_json::{Value, json};
+use std::sync::Mutex;
+
+fn deployment(annotations: Value) -> Value {
+    json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {
+            "name": "web",
+            "namespace": "default",
+            "uid": "0b5e5c3e-1111-2222-3333-444455556666",
+            "annotations": annotations
+        },
+        "spec": {
+            "selector": { "matchLabels": { "app": "web" } },
+            "template": {
+                "metadata": { "labels": { "app": "web" } },
+                "spec": {
+                    "containers": [
+                        { "name": "proxy", "image": "envoy:1.30.0" },
+                        { "name": "app", "image": "nginx:1.26.0" }
+                    ]
+                }
+            }
+        }
+    })
+}
+
+/// A fake API server serving `deployment` and echoing every other request
+fn fake_api(deployment: Value) -> FakeApi {
+    // Spec of the last UpdateRequest created, returned by its status patch
+    let created_spec = Mutex::new(Value::Null);
+    FakeApi::ok(move |request| {
+        if request.path.contains("/deployments/") {
+            deployment.clone()
+        } else if request.path.ends_with("/status") {
+            json!({
+                "apiVersion": "headwind.sh/v1alpha1",
+                "kind": "UpdateRequest",
+                "metadata": { "name": "web-rollback", "namespace": "default" },
+                "spec": created_spec.lock().unwrap().clone(),
+                "status": request.body["status"]
+            })
+        } else {
+            if request.method == Method::POST && request.path.ends_with("/updaterequests") {
+                *created_spec.lock().unwrap() = request.body["spec"].clone();
+            }
+            request.body.clone()
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_rollback_without_previous_image_fails_gracefully() {
+    let api = fake_api(deployment(json!({})));
+    let client = api.client();
+
+    let err = rollback_to_previous_image(&client, "Deployment", "default", "web", "alice")
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(err, ManualRollbackError::NoPreviousImage(_)),
+        "{:?}",
+        err
+    );
+    assert!(err.to_string().contains("headwind.sh/previous-image"));
+    assert_eq!(rollback_error_status(&err), StatusCode::CONFLICT);
+
+    // Only the Deployment was read; nothing was patched or created
+    let requests = api.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, Method::GET);
+}
+
+#[tokio::test]
+async fn test_rollback_with_empty_previous_image_fails_gracefully() {
+    let api = fake_api(deployment(json!({ "headwind.sh/previous-image": "" })));
+    let client = api.client();
+
+    let err = rollback_to_previous_image(&client, "Deployment", "default", "web", "alice")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ManualRollbackError::NoPreviousImage(_)));
+    assert!(api.bodies(Method::PATCH, "").is_empty());
+}
+
+#[tokio::test]
+async fn test_unsupported_kind_is_rejected_without_api_calls() {
+    let api = fake_api(deployment(json!({})));
+    let client = api.client();
+
+    let err = rollback_to_previous_image(&client, "CronJob", "default", "web", "alice")
+        .await
+        .unwrap_err();
+
+    assert_eq!(rollback_error_status(&err), StatusCode::BAD_REQUEST);
+    assert!(api.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_rollback_to_previous_image() {
+    let api = fake_api(deployment(
+        json!({ "headwind.sh/previous-image": "nginx:1.25.0" }),
+    ));
+    let client = api.client();
+
+    let rollback = rollback_to_previous_image(&client, "Deployment", "default", "web", "alice")
+        .await
+        .unwrap();
+
+    assert_eq!(rollback.container, "app");
+    assert_eq!(rollback.rolled_back_from, "nginx:1.26.0");
+    assert_eq!(rollback.rolled_back_to, "nginx:1.25.0");
+    assert_eq!(rollback.rolled_back_by, "alice");
+
+    // The image patch restores the previous image and saves the one it replaces
+    let patch = &api.bodies(Method::PATCH, "/deployments/web")[0];
+    assert_eq!(
+        patch["spec"]["template"]["spec"]["containers"],
+        json!([{ "name": "app", "image": "nginx:1.25.0" }])
+    );
+    assert_eq!(
+        patch["metadata"]["annotations"]["headwind.sh/previous-image"],
+        "nginx:1.26.0"
+    );
+
+    // An UpdateRequest in the RolledBack phase documents the rollback
+    let created = &api.bodies(Method::POST, "/updaterequests")[0];
+    assert_eq!(created["spec"]["currentImage"], "nginx:1.26.0");
+    assert_eq!(created["spec"]["newImage"], "nginx:1.25.0");
+    assert_eq!(
+        Some(created["metadata"]["name"].as_str().unwrap().to_string()),
+        rollback.update_request
+    );
+    let status_patch = &api.bodies(Method::PATCH, "/status")[0];
+    let status: UpdateRequestStatus =
+        serde_json::from_value(status_patch["status"].clone()).unwrap();
+    assert_eq!(status.phase, UpdatePhase::RolledBack);
+    assert_eq!(status.approved_by.as_deref(), Some("alice"));
+    assert_eq!(status.rollback_history.len(), 1);
+
+    // A ManualRollback event is emitted on the Deployment
+    let event = &api.bodies(Method::POST, "/events")[0];
+    assert_eq!(event["reason"], "ManualRollback");
+    assert_eq!(event["regarding"]["kind"], "Deployment");
+    assert_eq!(event["regarding"]["name"], "web");
+}
+
+#[tokio::test]
+async fn test_forced_rollback_without_previous_image() {
+    let api = fake_api(deployment(json!({})));
+    let client = api.client();
+
+    let rollback = rollback_to_image(
+        &client,
+        "Deployment",
+        "default",
+        "web",
+        "nginx:1.24.0",
+        "alice",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(rollback.rolled_back_from, "nginx:1.26.0");
+    assert_eq!(rollback.rolled_back_to, "nginx:1.24.0");
+    let patch = &api.bodies(Method::PATCH, "/deployments/web")[0];
+    assert_eq!(
+        patch["spec"]["template"]["spec"]["containers"],
+        json!([{ "name": "app", "image": "nginx:1.24.0" }])
+    );
+    assert_eq!(
+        patch["metadata"]["annotations"]["headwind.sh/previous-image"],
+        "nginx:1.26.0"
+    );
+}
+
+#[tokio::test]
+async fn test_rollback_when_already_at_previous_image() {
+    let api = fake_api(deployment(
+        json!({ "headwind.sh/previous-image": "nginx:1.26.0" }),
+    ));
+    let client = api.client();
+
+    let err = rollback_to_previous_image(&client, "Deployment", "default", "web", "alice")
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, ManualRollbackError::AlreadyRolledBack(..)));
+    assert_eq!(rollback_error_status(&err), StatusCode::CONFLICT);
+    assert!(api.bodies(Method::PATCH, "").is_empty());
+}
@@ -0,0 +1,389 @@
+// Integration tests for OIDC authentication of the Web UI
+//
+// These tests run the OIDC middleware in front of a small router, against
+// the in-process fake OIDC provider
+
+use axum::{Router, body::Body, routing::get};
+use base64::{
+    Engine as _, engine::general_purpose::STANDARD, engine::general_purpose::URL_SAFE_NO_PAD,
+};
+use headwind::testing::FakeOidcProvider;
+use headwind::ui::auth::{UserIdentity, with_oidc};
+use headwind::ui::oidc::{OidcClient, OidcConfig};
+use http::{Request, StatusCode, header};
+use ring::signature::{RSA_PKCS1_SHA256, RsaKeyPair, RsaPublicKeyComponents};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const CLIENT_ID: &str = "headwind";
+
+/// Throwaway 2048-bit RSA key (PKCS#8 DER), only used to sign RS256 test tokens
+const TEST_RSA_KEY: &str = concat!(
+    "MIIEvQIBADANBgkqhkiG9w0BAQEFAASCBKcwggSjAgEAAoIBAQDOAZ4nv9c+dHGsb0S6X6fWgx/I",
+    "8Pwb9zsjUmcydnaLeeL67meSDprxEG7qBzaLzkC0CxWoQY1HVflasTJ4pV7JV2sQVRCZZVpfkyTC",
+    "mwLvcrP+EIpZEmRJ+b9Zoo3lYlGhiVgF/0IsgCex2NOB85QI+/sDA7BDxeqwbfw+J+JjdSAeT83f",
+    "jvl1qkC43OsdCYsQvx1pBBAwxX6qz90wYMM0h8uD6KtU79hpb469vZtIjcOaUY34sN6LvGMlv2uD",
+    "k01dENPUH7JqSQ9PMss3BX/ncCRrFDCctBLHxL7S+tioyLEe1sdDfTEm7TJE8XXCOrrmfSzcfoHJ",
+    "inRZ50zjxE7/AgMBAAECggEABErszdrn4yXHSljc8szc4AlGUB2TjW9dQN/OAW8npBb+v5k+57fv",
+    "3CIqOtcx1mpKNTQZy0Nm9PMVpWWm3K/9v2ocBLkSp+izudQNNb7qB8cbRe5E53tiIQI6PLKN8v2Z",
+    "7NwXkYCwm7+DfueYuPVngsomM6QcqzTeA8iMzPDFFt4HpSAOzIB2dUirisT2K1gL9PVDGpfDzeKC",
+    "Fqg2l0JLf3i8pmwQCYtr2lOYdLY8BWJw6obRVlVfexlGbM+vOwH37hMKBfoGVsaqJyWD5WTFoDRh",
+    "XI9YFnMnDTinu8IZXhUQkorwx2PzA8bcufCRjHZeCd3ZZhLri/ttdj4PhAiGSQKBgQD2QhGVj86t",
+    "OeOgF9pKYuYiyC1LYWTmtczFZ8EwGMMzUn02Fm4PkW5nr5icYiCxISexrRw9zNvAVUTPUe1cTzN9",
+    "Hpl773AsNI5biKAgnqkC1v79lEHK5kvjIeL2ANr6oPQEUrrulxKK6AzF22uchPWokgPWT6FOZ8EB",
+    "anx7CN5OZwKBgQDWJ+g6ZMXCphQX05xn0d+0Da3SSCtuh+dmt8TTvsZxQJH+XlemFC1KYbMuz9uj",
+    "cnbdwBfTkGIr/XNMhuK6qYFGJ2CGFiDVfG6NEc/2s6ev5rTOQd9N5nBaUYlmXWtFWumnZA3gbBgd",
+    "1uXHKSAd6x9SzzfSTMsZzlFfk4u1NmbrqQKBgQCAe04TW1l4tbhWYbuJjfoGcax4JNNxL68r24tG",
+    "3dSSZVpgquk3hCg3Pr1/8g6ee+7q26fdcpHA0nw5MsSFoej6QcnQiMdcuPsBNNJe0ojZq3SUtxJw",
+    "myFC2v5AXYfitbE6xDM1omqRQKrRXD7UBebp0WzhFuDROH7Rj05tHt5lxwKBgBMZig+zLyQWFdKu",
+    "L9PelvoDAk6v2xfHjS7PEwHjkOVG4f5ywZRxVjy9C/MP3MHuEoXifP2Ecg0EVMUtfokxVvM/zc8H",
+    "qDQCHgX84bRZ/4mjpNqEa3sSI1Ea1otHp6qO2inwtYAfg+MdaG7jzxZ8AtYq3wqliLOrf/xrN1mZ",
+    "GYLJAoGANAvBqqCSIb4RJix1mcIj0bexWFhbAwPtgRYjmBCQvLcIP6mhMBC1Wp46G2PtH/vl3XUb",
+    "ZnryrRWdaZF69tXKMyXdspmsy/w828IVvZ4EWsLW4yuVKGE+C+gxTUtcGFoi3RXzOKyEuxTrQveR",
+    "I4mngnUQgZfZdj+5c5OgcBi5mi8=",
+);
+
+async fn whoami(user: UserIdentity) -> String {
+    user.username
+}
+
+async fn setup() -> (FakeOidcProvider, Router) {
+    let provider = FakeOidcProvider::start(CLIENT_ID).await.unwrap();
+    let client = OidcClient::new(OidcConfig {
+        issuer: provider.issuer(),
+        client_id: CLIENT_ID.to_string(),
+        client_secret: "client-secret".to_string(),
+        redirect_url: None,
+    });
+    let router = Router::new()
+        .route("/", get(whoami))
+        .route("/api/v1/whoami", get(whoami))
+        .route("/health", get(|| async { "OK" }));
+
+    (provider, with_oidc(router, Arc::new(client)))
+}
+
+async fn send(router: &Router, request: Request<Body>) -> http::Response<Body> {
+    router.clone().oneshot(request).await.unwrap()
+}
+
+fn get_request(uri: &str) -> http::request::Builder {
+    Request::builder()
+        .uri(uri)
+        .header(header::HOST, "headwind.test")
+        .header(header::ACCEPT, "text/html")
+}
+
+async fn body_text(response: http::Response<Body>) -> String {
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+fn location(response: &http::Response<Body>) -> String {
+    response.headers()[header::LOCATION]
+        .to_str()
+        .unwrap()
+        .to_string()
+}
+
+/// `name=value` of the Set-Cookie header for `name`
+fn set_cookie(response: &http::Response<Body>, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find(|v| v.starts_with(&format!("{}=", name)))
+        .and_then(|v| v.split(';').next())
+        .map(String::from)
+}
+
+fn query_params(url: &str) -> HashMap<String, String> {
+    url::Url::parse(url)
+        .unwrap()
+        .query_pairs()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
+
+async fn bearer(router: &Router, token: &str) -> http::Response<Body> {
+    send(
+        router,
+        get_request("/api/v1/whoami")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_browser_is_redirected_to_identity_provider() {
+    let (provider, router) = setup().await;
+
+    let response = send(
+        &router,
+        get_request("/updates/default/web")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+
+    assert_eq!(response.status(), StatusCode::FOUND);
+    let location = location(&response);
+    assert!(location.starts_with(&format!("{}/authorize?", provider.issuer())));
+    let params = query_params(&location);
+    assert_eq!(params["response_type"], "code");
+    assert_eq!(params["client_id"], CLIENT_ID);
+    assert_eq!(params["redirect_uri"], "http://headwind.test/auth/callback");
+    assert!(params["scope"].split(' ').any(|s| s == "openid"));
+    assert!(!params["state"].is_empty());
+    assert!(!params["nonce"].is_empty());
+    assert!(set_cookie(&response, "headwind_oidc_state").is_some());
+}
+
+#[tokio::test]
+async fn test_api_requests_without_credentials_are_unauthorized() {
+    let (_provider, router) = setup().await;
+
+    let response = send(
+        &router,
+        get_request("/api/v1/whoami").body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Health checks stay unauthenticated
+    let response = send(&router, get_request("/health").body(Body::empty()).unwrap()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_authorization_code_flow_creates_session() {
+    let (provider, router) = setup().await;
+
+    let response = send(
+        &router,
+        get_request("/updates/default/web")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    let params = query_params(&location(&response));
+    let state_cookie = set_cookie(&response, "headwind_oidc_state").unwrap();
+
+    let mut claims = provider.claims("user-1234");
+    claims["email"] = json!("alice@example.com");
+    claims["nonce"] = json!(params["nonce"]);
+    provider.add_code("code-1", &provider.sign(&claims));
+
+    let response = send(
+        &router,
+        get_request(&format!(
+            "/auth/callback?code=code-1&state={}",
+            params["state"]
+        ))
+        .header(header::COOKIE, &state_cookie)
+        .body(Body::empty())
+        .unwrap(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::SEE_OTHER);
+    assert_eq!(location(&response), "/updates/default/web");
+    let session = set_cookie(&response, "headwind_session").unwrap();
+
+    let token_request = &provider.token_requests()[0];
+    assert_eq!(token_request["grant_type"], "authorization_code");
+    assert_eq!(token_request["client_secret"], "client-secret");
+    assert_eq!(
+        token_request["redirect_uri"],
+        "http://headwind.test/auth/callback"
+    );
+
+    // The email claim is the identity used for approved_by
+    let response = send(
+        &router,
+        get_request("/")
+            .header(header::COOKIE, &session)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_text(response).await, "alice@example.com");
+}
+
+#[tokio::test]
+async fn test_callback_rejects_mismatched_state_and_nonce() {
+    let (provider, router) = setup().await;
+
+    let response = send(&router, get_request("/").body(Body::empty()).unwrap()).await;
+    let params = query_params(&location(&response));
+    let state_cookie = set_cookie(&response, "headwind_oidc_state").unwrap();
+
+    let response = send(
+        &router,
+        get_request("/auth/callback?code=code-1&state=forged")
+            .header(header::COOKIE, &state_cookie)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Token issued for a different login
+    let mut claims = provider.claims("user-1234");
+    claims["nonce"] = json!("another-login");
+    provider.add_code("code-2", &provider.sign(&claims));
+    let response = send(
+        &router,
+        get_request(&format!(
+            "/auth/callback?code=code-2&state={}",
+            params["state"]
+        ))
+        .header(header::COOKIE, &state_cookie)
+        .body(Body::empty())
+        .unwrap(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(set_cookie(&response, "headwind_session").is_none());
+}
+
+#[tokio::test]
+async fn test_bearer_token_uses_sub_without_email() {
+    let (provider, router) = setup().await;
+
+    let response = bearer(&router, &provider.sign(&provider.claims("user-1234"))).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_text(response).await, "user-1234");
+}
+
+#[tokio::test]
+async fn test_invalid_bearer_tokens_are_rejected() {
+    let (provider, router) = setup().await;
+    let valid = provider.sign(&provider.claims("user-1234"));
+
+    let mut expired = provider.claims("user-1234");
+    expired["exp"] = json!(chrono::Utc::now().timestamp() - 3600);
+    let mut wrong_audience = provider.claims("user-1234");
+    wrong_audience["aud"] = json!("another-client");
+    let mut wrong_issuer = provider.claims("user-1234");
+    wrong_issuer["iss"] = json!("https://evil.example.com");
+
+    // Claims swapped after signing
+    let (header, _) = valid.split_once('.').unwrap();
+    let (_, signature) = valid.rsplit_once('.').unwrap();
+    let mut elevated = provider.claims("admin");
+    elevated["email"] = json!("admin@example.com");
+    let tampered = format!(
+        "{}.{}.{}",
+        header,
+        URL_SAFE_NO_PAD.encode(elevated.to_string()),
+        signature
+    );
+    let unsigned = format!(
+        "{}.{}.",
+        URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#),
+        URL_SAFE_NO_PAD.encode(provider.claims("admin").to_string())
+    );
+
+    for token in [
+        provider.sign(&expired),
+        provider.sign(&wrong_audience),
+        provider.sign(&wrong_issuer),
+        tampered,
+        unsigned,
+        "not-a-jwt".to_string(),
+    ] {
+        let response = bearer(&router, &token).await;
+        assert_eq!(
+            response.status(),
+            StatusCode::UNAUTHORIZED,
+            "token {}",
+            token
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_rs256_bearer_token() {
+    let (provider, router) = setup().await;
+
+    let der = STANDARD.decode(TEST_RSA_KEY).unwrap();
+    let key_pair = RsaKeyPair::from_pkcs8(&der).unwrap();
+    let public = RsaPublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+    provider.add_jwk(json!({
+        "kty": "RSA",
+        "alg": "RS256",
+        "kid": "rsa-1",
+        "n": URL_SAFE_NO_PAD.encode(&public.n),
+        "e": URL_SAFE_NO_PAD.encode(&public.e),
+    }));
+
+    let mut claims = provider.claims("user-5678");
+    claims["email"] = json!("bob@example.com");
+    let message = format!(
+        "{}.{}",
+        URL_SAFE_NO_PAD.encode(json!({ "alg": "RS256", "kid": "rsa-1" }).to_string()),
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let mut signature = vec![0; key_pair.public().modulus_len()];
+    key_pair
+        .sign(
+            &RSA_PKCS1_SHA256,
+            &ring::rand::SystemRandom::new(),
+            message.as_bytes(),
+            &mut signature,
+        )
+        .unwrap();
+    let token = format!("{}.{}", message, URL_SAFE_NO_PAD.encode(&signature));
+
+    let response = bearer(&router, &token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body_text(response).await, "bob@example.com");
+}
+
+#[tokio::test]
+async fn test_jwks_is_cached_and_refreshed_on_key_rotation() {
+    let (provider, router) = setup().await;
+
+    for sub in ["user-1", "user-2", "user-3"] {
+        let response = bearer(&router, &provider.sign(&provider.claims(sub))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    assert_eq!(provider.jwks_fetches(), 1);
+
+    // A token signed with an unknown kid triggers one refetch
+    provider.rotate_key().unwrap();
+    let response = bearer(&router, &provider.sign(&provider.claims("user-4"))).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(provider.jwks_fetches(), 2);
+}
+
+#[tokio::test]
+async fn test_forged_session_cookie_is_ignored() {
+    let (_provider, router) = setup().await;
+
+    let payload: Value = json!({ "identity": "mallory@example.com", "exp": 9999999999i64 });
+    let forged = format!(
+        "headwind_session={}.{}",
+        URL_SAFE_NO_PAD.encode(payload.to_string()),
+        URL_SAFE_NO_PAD.encode([0u8; 32])
+    );
+
+    let response = send(
+        &router,
+        get_request("/api/v1/whoami")
+            .header(header::COOKIE, forged)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,555 @@
+// Integration tests for registry polling
+//
+// These tests run `RegistryPoller::poll_image` against the in-process fake
+// OCI registry, with a fake Kubernetes API server supplying imagePullSecrets
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use headwind::polling::{
+    ImageToTrack, PollingConfig, RegistryPoller, SimulationResult, TagSortStrategy,
+};
+use headwind::testing::{FakeApi, FakeRegistry, api_error};
+use headwind::{ImagePushEvent, UpdatePolicy};
+use http::StatusCode;
+use serde_json::{Value, json};
+use tokio::sync::mpsc;
+
+/// A Kubernetes API whose default service account references a pull secret
+/// holding `credentials` for `registry`, if given
+fn fake_kube_api(registry: Option<(String, &'static str)>) -> FakeApi {
+    fake_kube_api_with_deployments(registry, Vec::new())
+}
+
+/// Like [`fake_kube_api`], also serving `deployments` from the list endpoints
+fn fake_kube_api_with_deployments(
+    registry: Option<(String, &'static str)>,
+    deployments: Vec<Value>,
+) -> FakeApi {
+    FakeApi::new(move |request| {
+        let path = request.path.as_str();
+        let namespace = path
+            .strip_prefix("/apis/apps/v1/namespaces/")
+            .and_then(|rest| rest.strip_suffix("/deployments"));
+        if path == "/apis/apps/v1/deployments" || namespace.is_some() {
+            let items: Vec<&Value> = deployments
+                .iter()
+                .filter(|d| namespace.is_none_or(|ns| d["metadata"]["namespace"] == ns))
+                .collect();
+            return (
+                StatusCode::OK,
+                json!({
+                    "apiVersion": "apps/v1",
+                    "kind": "DeploymentList",
+                    "metadata": { "resourceVersion": "1" },
+                    "items": items
+                }),
+            );
+        }
+
+        match (&registry, path) {
+            (Some(_), "/api/v1/namespaces/default/serviceaccounts/default") => (
+                StatusCode::OK,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "ServiceAccount",
+                    "metadata": { "name": "default", "namespace": "default" },
+                    "imagePullSecrets": [{ "name": "regcred" }]
+                }),
+            ),
+            (Some((host, credentials)), "/api/v1/namespaces/default/secrets/regcred") => {
+                let config = json!({ "auths": { host: { "auth": STANDARD.encode(credentials) } } });
+                (
+                    StatusCode::OK,
+                    json!({
+                        "apiVersion": "v1",
+                        "kind": "Secret",
+                        "metadata": { "name": "regcred", "namespace": "default" },
+                        "type": "kubernetes.io/dockerconfigjson",
+                        "data": { ".dockerconfigjson": STANDARD.encode(config.to_string()) }
+                    }),
+                )
+            },
+            _ => api_error(StatusCode::NOT_FOUND, "NotFound", "not found"),
+        }
+    })
+}
+
+/// Paths requested from the fake Kubernetes API server
+fn paths(api: &FakeApi) -> Vec<String> {
+    api.calls().into_iter().map(|(_, path)| path).collect()
+}
+
+fn poller(
+    registry: &FakeRegistry,
+    kube_client: kube::Client,
+) -> (RegistryPoller, mpsc::UnboundedReceiver<ImagePushEvent>) {
+    let (event_sender, events) = mpsc::unbounded_channel();
+    let (chart_event_sender, _) = mpsc::unbounded_channel();
+    let config = PollingConfig {
+        insecure_registries: vec![registry.host()],
+        ..Default::default()
+    };
+    let poller = RegistryPoller::with_client(config, event_sender, chart_event_sender, kube_client);
+    (poller, events)
+}
+
+fn tag_list_requests(registry: &FakeRegistry) -> usize {
+    registry
+        .requests()
+        .iter()
+        .filter(|r| r.path.ends_with("/tags/list"))
+        .count()
+}
+
+#[tokio::test]
+async fn test_new_tag_triggers_event() {
+    let registry = FakeRegistry::start().await.unwrap();
+    registry.add_tags("team/app", &["1.0.0", "1.0.1", "1.1.0", "latest"]);
+
+    let api = fake_kube_api(None);
+    let (poller, mut events) = poller(&registry, api.client());
+    let image = ImageToTrack::new(
+        format!("{}/team/app:1.0.0", registry.host()),
+        UpdatePolicy::Patch,
+        "default",
+    );
+
+    let digest = poller.poll_image(&image).await.unwrap();
+    assert_eq!(digest, registry.digest("team/app", "1.0.1"));
+
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.repository, "team/app");
+    assert_eq!(event.tag, "1.0.1");
+    assert_eq!(event.digest, registry.digest("team/app", "1.0.1"));
+    assert!(events.try_recv().is_err());
+
+    // Anonymous access worked, so no credentials were looked up
+    assert!(api.requests().is_empty());
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_unchanged_tags_are_skipped() {
+    let registry = FakeRegistry::start().await.unwrap();
+    registry.add_tags("app", &["1.0.0", "0.9.0", "2.0.0"]);
+
+    let (poller, mut events) = poller(&registry, fake_kube_api(None).client());
+    let image = ImageToTrack::new(
+        format!("{}/app:1.0.0", registry.host()),
+        UpdatePolicy::Patch,
+        "default",
+    );
+
+    assert_eq!(poller.poll_image(&image).await.unwrap(), None);
+    assert_eq!(poller.poll_image(&image).await.unwrap(), None);
+    assert!(events.try_recv().is_err());
+
+    // The second poll is served from the tag list cache
+    assert_eq!(tag_list_requests(&registry), 1);
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_images_of_a_registry_share_a_client() {
+    let registry = FakeRegistry::start().await.unwrap();
+    registry.add_tags("team/api", &["1.0.0", "1.0.1"]);
+    registry.add_tags("team/web", &["2.0.0"]);
+
+    let (poller, _events) = poller(&registry, fake_kube_api(None).client());
+    for image in ["team/api:1.0.0", "team/web:2.0.0", "team/api:1.0.0"] {
+        let image = ImageToTrack::new(
+            format!("{}/{}", registry.host(), image),
+            UpdatePolicy::Patch,
+            "default",
+        );
+        poller.poll_image(&image).await.unwrap();
+    }
+    assert_eq!(poller.client_pool().len(), 1);
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_push_date_sort_picks_most_recently_pushed_tag() {
+    let registry = FakeRegistry::start().await.unwrap();
+    let tags = [
+        ("20240101-0a1b2c3", "2024-01-01T00:00:00Z"),
+        ("20240315-abc1234", "2024-03-15T10:00:00Z"),
+        // Tagged with an earlier date, but built last
+        ("20240201-fedcba9", "2024-04-02T08:30:00Z"),
+        ("20231201-1234567", "2023-12-01T00:00:00Z"),
+    ];
+    for (tag, created) in tags {
+        registry.add_tags("app", &[tag]);
+        registry.set_created("app", tag, created);
+    }
+    registry.add_tags("app", &["undated"]);
+
+    let (event_sender, mut events) = mpsc::unbounded_channel();
+    let (chart_event_sender, _) = mpsc::unbounded_channel();
+    let config = PollingConfig {
+        insecure_registries: vec![registry.host()],
+        tag_sort: TagSortStrategy::PushDate,
+        ..Default::default()
+    };
+    let poller = RegistryPoller::with_client(
+        config,
+        event_sender,
+        chart_event_sender,
+        fake_kube_api(None).client(),
+    );
+    let image = ImageToTrack::new(
+        format!("{}/app:20240101-0a1b2c3", registry.host()),
+        UpdatePolicy::All,
+        "default",
+    );
+
+    let digest = poller.poll_image(&image).await.unwrap();
+    assert_eq!(digest, registry.digest("app", "20240201-fedcba9"));
+    assert_eq!(events.try_recv().unwrap().tag, "20240201-fedcba9");
+
+    // Push dates are cached, so polling again only fetches the config of the
+    // tag without one
+    let config_requests = |registry: &FakeRegistry| {
+        registry
+            .requests()
+            .iter()
+            .filter(|r| r.path.contains("/blobs/"))
+            .count()
+    };
+    let fetched = config_requests(&registry);
+    assert_eq!(fetched, 5);
+    let image = ImageToTrack::new(
+        format!("{}/app:20240201-fedcba9", registry.host()),
+        UpdatePolicy::All,
+        "default",
+    );
+    assert_eq!(poller.poll_image(&image).await.unwrap(), None);
+    assert_eq!(config_requests(&registry), fetched + 1);
+    assert!(events.try_recv().is_err());
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_repushed_tag_triggers_event() {
+    let registry = FakeRegistry::start().await.unwrap();
+    registry.add_tags("app", &["1.0.0"]);
+
+    let (poller, mut events) = poller(&registry, fake_kube_api(None).client());
+    let image = ImageToTrack::new(
+        format!("{}/app:1.0.0", registry.host()),
+        UpdatePolicy::Patch,
+        "default",
+    );
+
+    assert_eq!(poller.poll_image(&image).await.unwrap(), None);
+
+    registry.add_tags("app", &["1.0.0"]);
+    let digest = poller.poll_image(&image).await.unwrap();
+    assert_eq!(digest, registry.digest("app", "1.0.0"));
+
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.tag, "1.0.0");
+    assert_eq!(event.digest, registry.digest("app", "1.0.0"));
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_paginated_tag_lists_are_followed() {
+    let registry = FakeRegistry::start().await.unwrap();
+    let tags: Vec<String> = (0..250).map(|i| format!("1.0.{}", i)).collect();
+    let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+    registry.add_tags("app", &tags);
+
+    let (poller, mut events) = poller(&registry, fake_kube_api(None).client());
+    let image = ImageToTrack::new(
+        format!("{}/app:1.0.0", registry.host()),
+        UpdatePolicy::Patch,
+        "default",
+    );
+
+    poller.poll_image(&image).await.unwrap();
+
+    // The newest tag is only on the last page
+    let event = events.try_recv().unwrap();
+    assert_eq!(event.tag, "1.0.249");
+
+    let pages: Vec<Option<String>> = registry
+        .requests()
+        .into_iter()
+        .filter(|r| r.path.ends_with("/tags/list"))
+        .map(|r| r.query.get("last").cloned())
+        .collect();
+    assert_eq!(
+        pages,
+        vec![
+            None,
+            Some("1.0.99".to_string()),
+            Some("1.0.199".to_string())
+        ]
+    );
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_unauthorized_triggers_credential_lookup() {
+    let registry = FakeRegistry::start_with_basic_auth("robot", "s3cret")
+        .await
+        .unwrap();
+    registry.add_tags("private/app", &["1.0.0", "1.0.1"]);
+
+    let api = fake_kube_api(Some((registry.host(), "robot:s3cret")));
+    let (poller, mut events) = poller(&registry, api.client());
+    let image = ImageToTrack::new(
+        format!("{}/private/app:1.0.0", registry.host()),
+        UpdatePolicy::Patch,
+        "default",
+    );
+
+    let digest = poller.poll_image(&image).await.unwrap();
+    assert_eq!(digest, registry.digest("private/app", "1.0.1"));
+    assert_eq!(events.try_recv().unwrap().tag, "1.0.1");
+
+    // The first anonymous request was rejected, everything after was authorized
+    let requests = registry.requests();
+    assert!(!requests[0].authorized);
+    let first_authorized = requests.iter().position(|r| r.authorized).unwrap();
+    assert!(requests[first_authorized..].iter().all(|r| r.authorized));
+
+    assert_eq!(
+        paths(&api),
+        vec![
+            "/api/v1/namespaces/default/serviceaccounts/default",
+            "/api/v1/namespaces/default/secrets/regcred",
+        ]
+    );
+
+    // Credentials are cached, so the next poll doesn't look them up again and
+    // only the client's anonymous `/v2/` challenge probe is unauthorized
+    let before = registry.requests().len();
+    poller.poll_image(&image).await.unwrap();
+    assert!(
+        registry.requests()[before..]
+            .iter()
+            .all(|r| r.authorized || r.path == "/v2/")
+    );
+    assert_eq!(paths(&api).len(), 2);
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_unauthorized_without_credentials_is_skipped() {
+    let registry = FakeRegistry::start_with_basic_auth("robot", "s3cret")
+        .await
+        .unwrap();
+    registry.add_tags("private/app", &["1.0.0", "1.0.1"]);
+
+    let api = fake_kube_api(None);
+    let (poller, mut events) = poller(&registry, api.client());
+    let image = ImageToTrack::new(
+        format!("{}/private/app:1.0.0", registry.host()),
+        UpdatePolicy::Patch,
+        "default",
+    );
+
+    assert_eq!(poller.poll_image(&image).await.unwrap(), None);
+    assert!(events.try_recv().is_err());
+    assert!(registry.requests().iter().all(|r| !r.authorized));
+    assert_eq!(
+        paths(&api),
+        vec!["/api/v1/namespaces/default/serviceaccounts/default"]
+    );
+
+    registry.shutdown().await;
+}
+
+/// A Deployment with one container per image and the given policy annotation
+fn deployment(namespace: &str, name: &str, policy: &str, images: &[String]) -> Value {
+    let containers: Vec<Value> = images
+        .iter()
+        .enumerate()
+        .map(|(i, image)| json!({ "name": format!("c{}", i), "image": image }))
+        .collect();
+    json!({
+        "apiVersion": "apps/v1",
+        "kind": "Deployment",
+        "metadata": {
+            "name": name,
+            "namespace": namespace,
+            "annotations": { "headwind.sh/policy": policy }
+        },
+        "spec": {
+            "selector": { "matchLabels": { "app": name } },
+            "template": {
+                "metadata": { "labels": { "app": name } },
+                "spec": { "containers": containers }
+            }
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_simulate_reports_policy_decisions() {
+    let registry = FakeRegistry::start().await.unwrap();
+    registry.add_tags("app", &["1.0.0", "1.0.1", "1.1.0", "2.0.0"]);
+    registry.add_tags("db", &["5.0.0", "6.0.0"]);
+    let app = format!("{}/app:1.0.0", registry.host());
+    let db = format!("{}/db:5.0.0", registry.host());
+
+    let api = fake_kube_api_with_deployments(
+        None,
+        vec![
+            deployment("default", "web", "minor", std::slice::from_ref(&app)),
+            deployment("data", "postgres", "patch", std::slice::from_ref(&db)),
+        ],
+    );
+    let (poller, mut events) = poller(&registry, api.client());
+
+    let results = poller.simulate(None, None).await.unwrap();
+    assert_eq!(
+        results,
+        vec![
+            SimulationResult {
+                resource_kind: "Deployment".to_string(),
+                namespace: "default".to_string(),
+                name: "web".to_string(),
+                current_image: app.clone(),
+                latest_available: Some("1.1.0".to_string()),
+                would_update: true,
+                policy_rejection_reason: None,
+            },
+            SimulationResult {
+                resource_kind: "Deployment".to_string(),
+                namespace: "data".to_string(),
+                name: "postgres".to_string(),
+                current_image: db.clone(),
+                latest_available: Some("6.0.0".to_string()),
+                would_update: false,
+                policy_rejection_reason: Some(
+                    "6.0.0 is not allowed by the Patch policy".to_string()
+                ),
+            },
+        ]
+    );
+
+    // A simulation never emits update events or writes to the cluster
+    assert!(events.try_recv().is_err());
+    assert!(
+        registry
+            .requests()
+            .iter()
+            .all(|r| r.method == "GET" || r.method == "HEAD")
+    );
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_simulate_with_namespace_and_policy_override() {
+    let registry = FakeRegistry::start().await.unwrap();
+    registry.add_tags("app", &["1.0.0", "1.0.1", "2.0.0"]);
+    let app = format!("{}/app:1.0.0", registry.host());
+
+    let api = fake_kube_api_with_deployments(
+        None,
+        vec![
+            deployment("default", "web", "patch", std::slice::from_ref(&app)),
+            deployment("other", "api", "patch", std::slice::from_ref(&app)),
+        ],
+    );
+    let (poller, _events) = poller(&registry, api.client());
+
+    let results = poller
+        .simulate(Some("default"), Some(UpdatePolicy::Major))
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "web");
+    assert_eq!(results[0].latest_available.as_deref(), Some("2.0.0"));
+    assert!(results[0].would_update);
+    assert_eq!(
+        paths(&api)[0],
+        "/apis/apps/v1/namespaces/default/deployments"
+    );
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_simulate_reports_registry_errors() {
+    let registry = FakeRegistry::start().await.unwrap();
+    let missing = format!("{}/missing:1.0.0", registry.host());
+
+    let api = fake_kube_api_with_deployments(
+        None,
+        vec![deployment("default", "web", "minor", &[missing])],
+    );
+    let (poller, _events) = poller(&registry, api.client());
+
+    let results = poller.simulate(None, None).await.unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(!results[0].would_update);
+    assert!(
+        results[0]
+            .policy_rejection_reason
+            .as_deref()
+            .unwrap()
+            .starts_with("Failed to list tags")
+    );
+
+    registry.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_fake_registry_serves_distribution_api() {
+    let registry = FakeRegistry::start().await.unwrap();
+    registry.add_tags("app", &["a", "b", "c"]);
+    let http = reqwest::Client::new();
+
+    let version = http
+        .get(format!("{}/v2/", registry.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(version.status(), StatusCode::OK);
+
+    let page = http
+        .get(format!("{}/v2/app/tags/list?n=2", registry.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        page.headers()["link"],
+        "</v2/app/tags/list?n=2&last=b>; rel=\"next\""
+    );
+    let body: Value = page.json().await.unwrap();
+    assert_eq!(body, json!({ "name": "app", "tags": ["a", "b"] }));
+
+    let manifest = http
+        .head(format!("{}/v2/app/manifests/b", registry.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        manifest.headers()["docker-content-digest"]
+            .to_str()
+            .unwrap(),
+        registry.digest("app", "b").unwrap()
+    );
+
+    let missing = http
+        .get(format!("{}/v2/other/tags/list", registry.url()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(missing.status(), StatusCode::NOT_FOUND);
+
+    let url = registry.url();
+    registry.shutdown().await;
+    assert!(http.get(format!("{}/v2/", url)).send().await.is_err());
+}
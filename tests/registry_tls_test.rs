@@ -0,0 +1,78 @@
+// Integration tests for TLS settings of registry connections
+//
+// The fake registry serves HTTPS with a self-signed certificate for
+// `localhost`, which clients only accept when told to trust it
+
+use headwind::polling::RegistryTlsConfig;
+use headwind::polling::tls::env_suffix;
+use headwind::testing::FakeRegistry;
+use headwind::webhook::tls::{GeneratedCert, generate_self_signed};
+use oci_distribution::secrets::RegistryAuth;
+use oci_distribution::{Client as OciClient, Reference};
+use std::str::FromStr;
+
+async fn start_registry() -> (FakeRegistry, GeneratedCert, String) {
+    let cert = generate_self_signed(&["localhost".to_string()]).unwrap();
+    let registry = FakeRegistry::start_with_tls(&cert.cert_pem(), &cert.key_pem())
+        .await
+        .unwrap();
+    registry.add_tags("team/app", &["1.0.0"]);
+    let host = format!("localhost:{}", registry.port());
+    (registry, cert, host)
+}
+
+async fn fetch_digest(tls: &RegistryTlsConfig, host: &str) -> anyhow::Result<String> {
+    let client = OciClient::new(tls.client_config(host, &[]));
+    let reference = Reference::from_str(&format!("{}/team/app:1.0.0", host))?;
+    Ok(client
+        .fetch_manifest_digest(&reference, &RegistryAuth::Anonymous)
+        .await?)
+}
+
+#[tokio::test]
+async fn test_self_signed_registry_is_rejected_by_default() {
+    let (_registry, _cert, host) = start_registry().await;
+
+    assert!(
+        fetch_digest(&RegistryTlsConfig::default(), &host)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_self_signed_registry_is_accepted_with_ca_cert() {
+    let (registry, cert, host) = start_registry().await;
+    let tls = RegistryTlsConfig {
+        ca_cert_pem: Some(cert.cert_pem()),
+        ..Default::default()
+    };
+
+    let digest = fetch_digest(&tls, &host).await.unwrap();
+    assert_eq!(Some(digest), registry.digest("team/app", "1.0.0"));
+}
+
+#[tokio::test]
+async fn test_per_registry_ca_cert_applies_to_its_registry_only() {
+    let (registry, cert, host) = start_registry().await;
+    let tls = RegistryTlsConfig {
+        registry_ca_certs: [(env_suffix(&host), cert.cert_pem())].into(),
+        ..Default::default()
+    };
+
+    let digest = fetch_digest(&tls, &host).await.unwrap();
+    assert_eq!(Some(digest), registry.digest("team/app", "1.0.0"));
+    assert!(tls.ca_certs_for("registry.example.com").is_empty());
+}
+
+#[tokio::test]
+async fn test_insecure_skip_verify_accepts_any_certificate() {
+    let (registry, _cert, host) = start_registry().await;
+    let tls = RegistryTlsConfig {
+        insecure_skip_verify: true,
+        ..Default::default()
+    };
+
+    let digest = fetch_digest(&tls, &host).await.unwrap();
+    assert_eq!(Some(digest), registry.digest("team/app", "1.0.0"));
+}
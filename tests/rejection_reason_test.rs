@@ -0,0 +1,104 @@
+// Integration tests for rejection reason validation in the approval API
+//
+// Invalid reasons must be refused with 400 before the UpdateRequest is even
+// read, so the fake Kubernetes API records every request it receives
+
+use axum::body::Body;
+use headwind::approval::{ApprovalState, router};
+use headwind::models::crd::UpdatePhase;
+use headwind::testing::{self, FakeApi};
+use http::{Method, Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+fn pending_update_request() -> Value {
+    serde_json::to_value(testing::update_request_in_phase(
+        "default",
+        "web-nginx-1-26-0",
+        UpdatePhase::Pending,
+    ))
+    .unwrap()
+}
+
+/// A fake API server serving a Pending UpdateRequest and applying status patches
+fn fake_api() -> FakeApi {
+    FakeApi::ok(|request| {
+        let mut response = pending_update_request();
+        if request.method == Method::PATCH {
+            response["status"] = request.body["status"].clone();
+        }
+        response
+    })
+}
+
+async fn reject(api: &FakeApi, body: Value) -> (StatusCode, Value) {
+    let app = router(ApprovalState {
+        client: api.client(),
+    });
+    let request = Request::post("/api/v1/updates/default/web-nginx-1-26-0/reject")
+        .header("content-type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_reject_without_reason_returns_400() {
+    let api = fake_api();
+
+    for body in [
+        json!({ "approver": "alice" }),
+        json!({ "approver": "alice", "reason": null }),
+        json!({ "approver": "alice", "reason": "" }),
+        json!({ "approver": "alice", "reason": "  \n\t" }),
+    ] {
+        let (status, response) = reject(&api, body.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+        assert_eq!(response["error"], "A rejection reason is required");
+    }
+
+    assert!(api.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_reject_with_too_long_reason_returns_400() {
+    let api = fake_api();
+
+    let (status, response) = reject(
+        &api,
+        json!({ "approver": "alice", "reason": "x".repeat(2049) }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response["error"],
+        "Rejection reason is 2049 characters long, the maximum is 2048"
+    );
+    assert!(api.requests().is_empty());
+}
+
+#[tokio::test]
+async fn test_reject_records_trimmed_reason() {
+    let api = fake_api();
+
+    let (status, response) = reject(
+        &api,
+        json!({ "approver": "alice", "reason": "  Waiting for the 1.26.1 fix\n" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(response["status"]["phase"], "Rejected");
+    assert_eq!(response["status"]["message"], "Waiting for the 1.26.1 fix");
+    assert_eq!(response["status"]["rejectedBy"], "alice");
+
+    let requests = api.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].method, Method::GET);
+    assert_eq!(requests[1].method, Method::PATCH);
+    assert!(requests[1].path.ends_with("/web-nginx-1-26-0/status"));
+}
@@ -0,0 +1,187 @@
+// Integration tests for the rollback history of UpdateRequests
+//
+// These tests record rollbacks against a fake Kubernetes API server that
+// stores a single UpdateRequest and enforces resourceVersion preconditions
+// on status patches, as the real API server does
+
+use axum::body::Body;
+use chrono::{TimeZone, Utc};
+use headwind::approval::{ApprovalState, router};
+use headwind::models::crd::{RollbackRecord, UpdatePhase};
+use headwind::rollback::record_rollback;
+use headwind::testing::{self, FakeApi, api_error};
+use http::{Method, Request, StatusCode};
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+
+fn update_request(history: Value) -> Value {
+    let mut update_request = serde_json::to_value(testing::update_request_in_phase(
+        "default",
+        "web-update",
+        UpdatePhase::Completed,
+    ))
+    .unwrap();
+    update_request["metadata"]["resourceVersion"] = json!("1");
+    update_request["status"]["approvedBy"] = json!("alice");
+    update_request["status"]["rollbackHistory"] = history;
+    update_request
+}
+
+fn record(minute: u32, reason: &str, triggered_by: &str) -> RollbackRecord {
+    RollbackRecord {
+        triggered_at: Utc.with_ymd_and_hms(2026, 1, 1, 12, minute, 0).unwrap(),
+        reason: reason.to_string(),
+        rolled_back_to: "nginx:1.25.0".to_string(),
+        triggered_by: triggered_by.to_string(),
+    }
+}
+
+/// State of the fake API server
+#[derive(Default)]
+struct Server {
+    /// The stored UpdateRequest
+    object: Value,
+    /// Record another writer appends right before the next status patch lands
+    concurrent_write: Option<RollbackRecord>,
+    /// Bodies of the status patches received
+    patches: Vec<Value>,
+}
+
+fn fake_client(server: Arc<Mutex<Server>>) -> kube::Client {
+    FakeApi::new(move |request| {
+        let mut server = server.lock().unwrap();
+        if request.method != Method::PATCH {
+            return (StatusCode::OK, server.object.clone());
+        }
+
+        assert!(request.path.ends_with("/web-update/status"));
+        server.patches.push(request.body.clone());
+
+        if let Some(concurrent) = server.concurrent_write.take() {
+            let stored = &mut server.object;
+            stored["status"]["rollbackHistory"]
+                .as_array_mut()
+                .unwrap()
+                .push(serde_json::to_value(concurrent).unwrap());
+            stored["metadata"]["resourceVersion"] = json!("2");
+        }
+
+        let stored = &mut server.object;
+        if request.body["metadata"]["resourceVersion"] != stored["metadata"]["resourceVersion"] {
+            return api_error(
+                StatusCode::CONFLICT,
+                "Conflict",
+                "the object has been modified",
+            );
+        }
+        stored["status"]["rollbackHistory"] = request.body["status"]["rollbackHistory"].clone();
+        (StatusCode::OK, stored.clone())
+    })
+    .client()
+}
+
+fn stored_history(server: &Arc<Mutex<Server>>) -> Vec<RollbackRecord> {
+    serde_json::from_value(server.lock().unwrap().object["status"]["rollbackHistory"].clone())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_rollback_is_appended_to_history() {
+    let first = record(
+        0,
+        "Container web is in CrashLoopBackOff",
+        "headwind-auto-rollback",
+    );
+    let server = Arc::new(Mutex::new(Server {
+        object: update_request(json!([first])),
+        ..Default::default()
+    }));
+
+    let second = record(5, "Health check timeout", "headwind-auto-rollback");
+    record_rollback(
+        fake_client(server.clone()),
+        "default",
+        "web-update",
+        second.clone(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(stored_history(&server), vec![first, second]);
+
+    // Only the history is patched; the rest of the status is left alone
+    let server = server.lock().unwrap();
+    assert_eq!(server.patches.len(), 1);
+    assert_eq!(server.patches[0]["metadata"]["resourceVersion"], "1");
+    assert_eq!(
+        server.patches[0]["status"].as_object().unwrap().len(),
+        1,
+        "{}",
+        server.patches[0]
+    );
+    assert_eq!(server.object["status"]["approvedBy"], "alice");
+}
+
+#[tokio::test]
+async fn test_concurrent_rollback_is_not_overwritten() {
+    let concurrent = record(1, "Manual rollback", "bob");
+    let server = Arc::new(Mutex::new(Server {
+        object: update_request(json!([])),
+        concurrent_write: Some(concurrent.clone()),
+        ..Default::default()
+    }));
+
+    let ours = record(
+        2,
+        "Deployment progress deadline exceeded",
+        "headwind-auto-rollback",
+    );
+    record_rollback(
+        fake_client(server.clone()),
+        "default",
+        "web-update",
+        ours.clone(),
+    )
+    .await
+    .unwrap();
+
+    // The first patch conflicted and the retry appended to the newer history
+    assert_eq!(server.lock().unwrap().patches.len(), 2);
+    assert_eq!(stored_history(&server), vec![concurrent, ours]);
+}
+
+#[tokio::test]
+async fn test_rollbacks_endpoint_returns_history() {
+    let history = vec![
+        record(
+            0,
+            "Container web is in CrashLoopBackOff",
+            "headwind-auto-rollback",
+        ),
+        record(30, "Manual rollback", "bob"),
+    ];
+    let server = Arc::new(Mutex::new(Server {
+        object: update_request(serde_json::to_value(&history).unwrap()),
+        ..Default::default()
+    }));
+
+    let app = router(ApprovalState {
+        client: fake_client(server),
+    });
+    let response = app
+        .oneshot(
+            Request::get("/api/v1/update-requests/default/web-update/rollbacks")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Vec<RollbackRecord> = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body, history);
+}
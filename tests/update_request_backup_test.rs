@@ -0,0 +1,199 @@
+// Round-trip tests for UpdateRequest export and import
+//
+// UpdateRequests are exported from one fake Kubernetes API server and imported
+// into another. Each server keeps UpdateRequests in memory, assigns UIDs on
+// create and applies status patches like the real status subresource.
+
+use headwind::cli::backend::Backend;
+use headwind::models::policy::annotations;
+use headwind::testing::{self, FakeApi, api_error};
+use headwind::ui::import::ImportSummary;
+use http::{Method, StatusCode};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// UpdateRequests by (namespace, name)
+type Store = Arc<Mutex<BTreeMap<(String, String), Value>>>;
+
+fn update_request(name: &str, uid: &str, phase: Option<&str>) -> Value {
+    let mut object = serde_json::to_value(testing::update_request("default", name)).unwrap();
+    object["metadata"]["uid"] = json!(uid);
+    object["metadata"]["creationTimestamp"] = json!("2026-01-01T00:00:00Z");
+    object["metadata"]["labels"] = json!({ "headwind.sh/resource-name": "web" });
+    object["spec"]["newImage"] = json!(format!(
+        "nginx:{}",
+        name.trim_start_matches("web-").replace('-', ".")
+    ));
+    if let Some(phase) = phase {
+        object["status"] = json!({ "phase": phase, "approvedBy": "alice" });
+    }
+    object
+}
+
+fn fake_cluster(objects: Vec<Value>) -> (kube::Client, Store) {
+    let store: Store = Arc::new(Mutex::new(
+        objects
+            .into_iter()
+            .map(|o| {
+                let key = (
+                    o["metadata"]["namespace"].as_str().unwrap().to_string(),
+                    o["metadata"]["name"].as_str().unwrap().to_string(),
+                );
+                (key, o)
+            })
+            .collect(),
+    ));
+
+    let service_store = store.clone();
+    let api = FakeApi::new(move |request| {
+        let segments: Vec<&str> = request
+            .path
+            .trim_start_matches("/apis/headwind.sh/v1alpha1/")
+            .split('/')
+            .collect();
+
+        let mut store = service_store.lock().unwrap();
+        match (&request.method, segments.as_slice()) {
+            (&Method::GET, ["updaterequests"]) => (
+                StatusCode::OK,
+                json!({
+                    "apiVersion": "headwind.sh/v1alpha1",
+                    "kind": "UpdateRequestList",
+                    "metadata": {},
+                    "items": store.values().cloned().collect::<Vec<_>>()
+                }),
+            ),
+            (&Method::POST, ["namespaces", namespace, "updaterequests"]) => {
+                let key = (
+                    namespace.to_string(),
+                    request.body["metadata"]["name"]
+                        .as_str()
+                        .unwrap()
+                        .to_string(),
+                );
+                if store.contains_key(&key) {
+                    return api_error(StatusCode::CONFLICT, "AlreadyExists", "already exists");
+                }
+                let mut object = request.body.clone();
+                // Status is a subresource, dropped on create
+                object.as_object_mut().unwrap().remove("status");
+                object["metadata"]["uid"] = json!(format!("uid-{}", store.len() + 100));
+                store.insert(key, object.clone());
+                (StatusCode::CREATED, object)
+            },
+            (&Method::PATCH, ["namespaces", namespace, "updaterequests", name, "status"]) => {
+                match store.get_mut(&(namespace.to_string(), name.to_string())) {
+                    Some(object) => {
+                        object["status"] = request.body["status"].clone();
+                        (StatusCode::OK, object.clone())
+                    },
+                    None => api_error(StatusCode::NOT_FOUND, "NotFound", "not found"),
+                }
+            },
+            _ => api_error(StatusCode::NOT_FOUND, "NotFound", "not found"),
+        }
+    });
+
+    (api.client(), store)
+}
+
+#[tokio::test]
+async fn test_export_import_round_trip() {
+    let (source, _) = fake_cluster(vec![
+        update_request("web-1-26-0", "uid-completed", Some("Completed")),
+        update_request("web-1-27-0", "uid-rejected", Some("Rejected")),
+        update_request("web-1-28-0", "uid-pending", None),
+    ]);
+    let export = Backend::Kubernetes(source).export(None).await.unwrap();
+    assert_eq!(export.lines().count(), 3);
+
+    let (target, store) = fake_cluster(Vec::new());
+    let target = Backend::Kubernetes(target);
+    let summary = target.import(export.clone()).await.unwrap();
+    assert_eq!(summary.imported, 2);
+    assert_eq!(summary.skipped, 0);
+    assert_eq!(summary.errors.len(), 1);
+    assert!(summary.errors[0].starts_with("line 3: "));
+    assert!(summary.errors[0].contains("Pending"));
+
+    {
+        let store = store.lock().unwrap();
+        assert_eq!(store.len(), 2);
+        let imported = &store[&("default".to_string(), "web-1-26-0".to_string())];
+        let imported_annotations = &imported["metadata"]["annotations"];
+        assert_eq!(
+            imported_annotations[annotations::ORIGINAL_UID],
+            "uid-completed"
+        );
+        assert!(imported_annotations[annotations::IMPORTED_AT].is_string());
+        assert_ne!(imported["metadata"]["uid"], "uid-completed");
+        assert_eq!(
+            imported["metadata"]["labels"]["headwind.sh/resource-name"],
+            "web"
+        );
+        assert_eq!(imported["spec"]["newImage"], "nginx:1.26.0");
+        assert_eq!(imported["status"]["phase"], "Completed");
+        assert_eq!(imported["status"]["approvedBy"], "alice");
+    }
+
+    // Importing the same export again creates nothing
+    let summary = target.import(export).await.unwrap();
+    assert_eq!(
+        summary,
+        ImportSummary {
+            imported: 0,
+            skipped: 2,
+            errors: summary.errors.clone()
+        }
+    );
+    assert_eq!(store.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_reimport_keeps_first_original_uid() {
+    let (first, _) = fake_cluster(vec![update_request(
+        "web-1-26-0",
+        "uid-first",
+        Some("Completed"),
+    )]);
+    let (second, _) = fake_cluster(Vec::new());
+    let (third, third_store) = fake_cluster(Vec::new());
+    let second = Backend::Kubernetes(second);
+
+    let export = Backend::Kubernetes(first).export(None).await.unwrap();
+    second.import(export).await.unwrap();
+    let export = second.export(None).await.unwrap();
+    let summary = Backend::Kubernetes(third).import(export).await.unwrap();
+    assert_eq!(summary.imported, 1);
+
+    let store = third_store.lock().unwrap();
+    let imported = store.values().next().unwrap();
+    assert_eq!(
+        imported["metadata"]["annotations"][annotations::ORIGINAL_UID],
+        "uid-first"
+    );
+}
+
+#[tokio::test]
+async fn test_import_reports_invalid_lines_and_name_conflicts() {
+    // Same name as the export but created in the target cluster independently
+    let (target, _) = fake_cluster(vec![update_request(
+        "web-1-26-0",
+        "uid-local",
+        Some("Completed"),
+    )]);
+    let line = update_request("web-1-26-0", "uid-remote", Some("Completed")).to_string();
+    let body = format!("{}\n\nnot json\n", line);
+
+    let summary = Backend::Kubernetes(target).import(body).await.unwrap();
+    assert_eq!(summary.imported, 0);
+    assert_eq!(summary.skipped, 0);
+    assert_eq!(summary.errors.len(), 2);
+    assert!(
+        summary.errors[0].contains("already exists"),
+        "{:?}",
+        summary
+    );
+    assert!(summary.errors[1].starts_with("line 3: invalid UpdateRequest"));
+}
@@ -0,0 +1,226 @@
+// Integration tests for the UpdateRequest label API
+//
+// These tests run the approval API router against a fake Kubernetes API
+// server that implements equality-based label selectors and pagination
+
+use axum::body::Body;
+use headwind::approval::{ApprovalState, UpdateRequestPage, router};
+use headwind::testing::{self, FakeApi};
+use headwind::ui::routes::split_label_selector;
+use http::{Method, Request, StatusCode};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+
+fn update_request(name: &str, labels: Value) -> Value {
+    let mut update_request =
+        serde_json::to_value(testing::update_request("default", name)).unwrap();
+    update_request["metadata"]["labels"] = labels;
+    update_request["spec"]["targetRef"]["name"] = json!(name);
+    update_request
+}
+
+fn fixtures() -> Vec<Value> {
+    vec![
+        update_request("web-prod", json!({ "app": "nginx", "env": "prod" })),
+        update_request("web-dev", json!({ "app": "nginx", "env": "dev" })),
+        update_request("api-prod", json!({ "app": "api", "env": "prod" })),
+    ]
+}
+
+/// Equality-based selector matching, as done by the API server
+fn matches(selector: &str, labels: &Value) -> bool {
+    selector
+        .split(',')
+        .filter(|r| !r.is_empty())
+        .all(|requirement| match requirement.split_once("!=") {
+            Some((key, value)) => labels[key].as_str() != Some(value),
+            None => {
+                let (key, value) = requirement
+                    .split_once("==")
+                    .or_else(|| requirement.split_once('='))
+                    .expect("unsupported selector");
+                labels[key].as_str() == Some(value)
+            },
+        })
+}
+
+/// A fake API server listing the fixtures with their labels and answering
+/// label patches
+fn fake_api() -> FakeApi {
+    FakeApi::ok(|request| {
+        if request.method == Method::PATCH {
+            let mut item = update_request("web-prod", json!({ "app": "nginx" }));
+            for (key, value) in request.body["metadata"]["labels"].as_object().unwrap() {
+                item["metadata"]["labels"][key] = value.clone();
+            }
+            return item;
+        }
+
+        let query = &request.query;
+        let selector = query.get("labelSelector").cloned().unwrap_or_default();
+        let items: Vec<Value> = fixtures()
+            .into_iter()
+            .filter(|item| matches(&selector, &item["metadata"]["labels"]))
+            .collect();
+
+        // The continue token is the offset of the next page
+        let offset: usize = query
+            .get("continue")
+            .map(|c| c.parse().unwrap())
+            .unwrap_or(0);
+        let limit: usize = query
+            .get("limit")
+            .map(|l| l.parse().unwrap())
+            .unwrap_or(items.len());
+        let next = offset + limit;
+        let continue_token = if next < items.len() {
+            next.to_string()
+        } else {
+            String::new()
+        };
+
+        json!({
+            "apiVersion": "headwind.sh/v1alpha1",
+            "kind": "UpdateRequestList",
+            "metadata": { "continue": continue_token },
+            "items": items.into_iter().skip(offset).take(limit).collect::<Vec<_>>()
+        })
+    })
+}
+
+async fn send(api: &FakeApi, request: Request<Body>) -> (StatusCode, Value) {
+    let app = router(ApprovalState {
+        client: api.client(),
+    });
+    let response = app.oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+async fn list(api: &FakeApi, query: &str) -> UpdateRequestPage {
+    let request = Request::get(format!("/api/v1/update-requests{}", query))
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(api, request).await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_value(body).unwrap()
+}
+
+fn names(page: &UpdateRequestPage) -> Vec<String> {
+    page.items
+        .iter()
+        .map(|ur| ur.metadata.name.clone().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_label_selector_returns_only_matching_requests() {
+    let api = fake_api();
+
+    let page = list(&api, "?labelSelector=app%3Dnginx%2Cenv%3Dprod").await;
+    assert_eq!(names(&page), vec!["web-prod"]);
+    assert!(page.continue_token.is_none());
+
+    let page = list(&api, "?labelSelector=env%3Dprod").await;
+    assert_eq!(names(&page), vec!["web-prod", "api-prod"]);
+
+    let page = list(&api, "?labelSelector=app%21%3Dnginx").await;
+    assert_eq!(names(&page), vec!["api-prod"]);
+
+    let request = &api.requests()[0];
+    assert_eq!(request.path, "/apis/headwind.sh/v1alpha1/updaterequests");
+    assert_eq!(request.query["labelSelector"], "app=nginx,env=prod");
+}
+
+#[tokio::test]
+async fn test_without_selector_returns_everything() {
+    let api = fake_api();
+
+    let page = list(&api, "").await;
+    assert_eq!(page.items.len(), 3);
+
+    assert!(!api.requests()[0].query.contains_key("labelSelector"));
+}
+
+#[tokio::test]
+async fn test_pagination_follows_continue_token() {
+    let api = fake_api();
+
+    let first = list(&api, "?labelSelector=app%3Dnginx&limit=1").await;
+    assert_eq!(names(&first), vec!["web-prod"]);
+    let token = first.continue_token.clone().expect("expected a next page");
+
+    let second = list(
+        &api,
+        &format!("?labelSelector=app%3Dnginx&limit=1&continue={}", token),
+    )
+    .await;
+    assert_eq!(names(&second), vec!["web-dev"]);
+    assert!(second.continue_token.is_none());
+}
+
+#[tokio::test]
+async fn test_label_endpoint_patches_labels() {
+    let api = fake_api();
+
+    let request = Request::post("/api/v1/update-requests/label")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "namespace": "default",
+                "name": "web-prod",
+                "labels": { "team": "platform" }
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let (status, body) = send(&api, request).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["metadata"]["labels"]["team"], "platform");
+    assert_eq!(body["metadata"]["labels"]["app"], "nginx");
+
+    let request = &api.requests()[0];
+    assert_eq!(request.method, Method::PATCH);
+    assert_eq!(
+        request.path,
+        "/apis/headwind.sh/v1alpha1/namespaces/default/updaterequests/web-prod"
+    );
+    assert_eq!(
+        request.body,
+        json!({ "metadata": { "labels": { "team": "platform" } } })
+    );
+}
+
+#[tokio::test]
+async fn test_label_endpoint_rejects_invalid_labels() {
+    let api = fake_api();
+
+    let request = Request::post("/api/v1/update-requests/label")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({
+                "namespace": "default",
+                "name": "web-prod",
+                "labels": { "team": "not valid" }
+            })
+            .to_string(),
+        ))
+        .unwrap();
+    let (status, _) = send(&api, request).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(api.requests().is_empty());
+}
+
+#[test]
+fn test_split_label_selector() {
+    assert_eq!(
+        split_label_selector("app=nginx, env in (prod,staging),!canary"),
+        vec!["app=nginx", "env in (prod,staging)", "!canary"]
+    );
+    assert!(split_label_selector("").is_empty());
+}
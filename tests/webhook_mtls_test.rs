@@ -0,0 +1,157 @@
+// Integration tests for mTLS on the webhook server
+//
+// A test CA issues the server certificate and a trusted client certificate; a
+// second CA issues a client certificate the server must reject
+
+use axum::routing::get;
+use axum::{Extension, Router};
+use headwind::metrics::WEBHOOK_TLS_HANDSHAKE_FAILURES_TOTAL;
+use headwind::webhook::mtls::{self, ClientIdentity};
+use headwind::webhook::tls::{CertificateUsage, GeneratedCert, generate_ca, generate_signed};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+struct Pki {
+    ca: GeneratedCert,
+    server: GeneratedCert,
+}
+
+fn pki() -> Pki {
+    let ca = generate_ca("Headwind Test CA").unwrap();
+    let server = generate_signed(
+        "localhost",
+        &["localhost".to_string()],
+        CertificateUsage::Server,
+        &ca,
+    )
+    .unwrap();
+    Pki { ca, server }
+}
+
+/// Start an mTLS server answering `/whoami` with the client identity
+async fn start_server(pki: &Pki) -> SocketAddr {
+    let config = mtls::server_config(
+        pki.server.cert_pem().as_bytes(),
+        pki.server.key_pem().as_bytes(),
+        pki.ca.cert_pem().as_bytes(),
+    )
+    .unwrap();
+    let app = Router::new().route(
+        "/whoami",
+        get(|Extension(ClientIdentity(identity)): Extension<ClientIdentity>| async move { identity }),
+    );
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(mtls::serve(
+        listener,
+        app,
+        TlsAcceptor::from(Arc::new(config)),
+    ));
+    addr
+}
+
+fn connector(ca: &GeneratedCert, client: Option<&GeneratedCert>) -> TlsConnector {
+    let mut roots = rustls::RootCertStore::empty();
+    roots
+        .add(CertificateDer::from(ca.cert_der.clone()))
+        .unwrap();
+    let builder = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_root_certificates(roots);
+
+    let config = match client {
+        Some(client) => builder
+            .with_client_auth_cert(
+                vec![CertificateDer::from(client.cert_der.clone())],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(client.key_pkcs8.clone())),
+            )
+            .unwrap(),
+        None => builder.with_no_client_auth(),
+    };
+    TlsConnector::from(Arc::new(config))
+}
+
+/// GET /whoami, returning the raw response or the connection error
+async fn whoami(addr: SocketAddr, connector: TlsConnector) -> Result<String, std::io::Error> {
+    let tcp = tokio::net::TcpStream::connect(addr).await?;
+    let mut stream = connector
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await?;
+    stream
+        .write_all(b"GET /whoami HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+#[tokio::test]
+async fn test_trusted_client_identity() {
+    let pki = pki();
+    let addr = start_server(&pki).await;
+    let client = generate_signed("ci-pipeline", &[], CertificateUsage::Client, &pki.ca).unwrap();
+
+    let response = whoami(addr, connector(&pki.ca, Some(&client)))
+        .await
+        .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("ci-pipeline"), "{}", response);
+}
+
+#[tokio::test]
+async fn test_untrusted_clients_rejected() {
+    let pki = pki();
+    let addr = start_server(&pki).await;
+    let failures = WEBHOOK_TLS_HANDSHAKE_FAILURES_TOTAL.get();
+
+    // Issued by a CA the server does not trust
+    let other_ca = generate_ca("Other CA").unwrap();
+    let untrusted = generate_signed("intruder", &[], CertificateUsage::Client, &other_ca).unwrap();
+    let result = whoami(addr, connector(&pki.ca, Some(&untrusted))).await;
+    assert!(
+        !matches!(&result, Ok(response) if response.contains("intruder")),
+        "{:?}",
+        result
+    );
+
+    // No client certificate at all
+    let result = whoami(addr, connector(&pki.ca, None)).await;
+    assert!(
+        !matches!(&result, Ok(response) if response.starts_with("HTTP/1.1 200")),
+        "{:?}",
+        result
+    );
+
+    // The server counts both failed handshakes
+    for _ in 0..50 {
+        if WEBHOOK_TLS_HANDSHAKE_FAILURES_TOTAL.get() >= failures + 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert!(WEBHOOK_TLS_HANDSHAKE_FAILURES_TOTAL.get() >= failures + 2);
+}
+
+#[test]
+fn test_server_config_errors() {
+    let pki = pki();
+    let cert = pki.server.cert_pem();
+    let key = pki.server.key_pem();
+
+    let err = mtls::server_config(cert.as_bytes(), key.as_bytes(), b"").unwrap_err();
+    assert!(err.to_string().contains("client CA"));
+
+    let err = mtls::server_config(b"", key.as_bytes(), cert.as_bytes()).unwrap_err();
+    assert!(err.to_string().contains("server certificate"));
+
+    // Key of another certificate
+    let other_key = pki.ca.key_pem();
+    assert!(mtls::server_config(cert.as_bytes(), other_key.as_bytes(), cert.as_bytes()).is_err());
+}